@@ -0,0 +1,205 @@
+//! Typed async client for the fuego server's HTTP API, for Rust services that would otherwise
+//! hand-roll reqwest calls. Requests are the server's own structs from fuego-types; every response
+//! keeps the server's `{"success", "data", "error", "code"}` envelope, with `data` returned as JSON.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub use fuego_types::{
+    ExtraAccount, ExtraInstruction, ExtraPosition, GetBalanceRequest, GetTokenBalanceRequest, GetTokensRequest,
+    MintBalanceRequest, RpcTarget, SubmitTransactionRequest, TokenBalancesRequest, TransferOptions, TransferPyusdRequest,
+    TransferQuoteRequest, TransferSolRequest, TransferTokenRequest, TransferUsdcRequest, TransferUsdtRequest,
+};
+
+const DEFAULT_BASE_URL: &str = "http://127.0.0.1:8080";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum FuegoError {
+    /// The request never produced a JSON response (connection, timeout, decode)
+    Http(String),
+    /// The server answered with `success: false`
+    Api { message: String, code: Option<String> },
+}
+
+impl std::fmt::Display for FuegoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FuegoError::Http(e) => write!(f, "fuego request failed: {}", e),
+            FuegoError::Api { message, code: Some(code) } => write!(f, "{} ({})", message, code),
+            FuegoError::Api { message, code: None } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for FuegoError {}
+
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(default)]
+    success: bool,
+    #[serde(default)]
+    data: Option<serde_json::Value>,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    code: Option<String>,
+}
+
+pub struct FuegoClientBuilder {
+    base_url: String,
+    api_key: Option<String>,
+    timeout: Duration,
+}
+
+impl FuegoClientBuilder {
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Bearer key configured on the server via FUEGO_AUTH_KEYS.
+    pub fn api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn build(self) -> Result<FuegoClient, FuegoError> {
+        let http = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| FuegoError::Http(e.to_string()))?;
+        Ok(FuegoClient {
+            http,
+            base_url: self.base_url,
+            api_key: self.api_key,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct FuegoClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl FuegoClient {
+    pub fn builder() -> FuegoClientBuilder {
+        FuegoClientBuilder {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            api_key: None,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<serde_json::Value, FuegoError> {
+        let request = match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        };
+        let response = request.send().await.map_err(|e| FuegoError::Http(e.to_string()))?;
+        let body: serde_json::Value = response.json().await.map_err(|e| FuegoError::Http(e.to_string()))?;
+
+        // A few read endpoints (/health, /network) answer without the envelope
+        if body.get("success").is_none() {
+            return Ok(body);
+        }
+        let envelope: Envelope = serde_json::from_value(body).map_err(|e| FuegoError::Http(e.to_string()))?;
+        if envelope.success {
+            Ok(envelope.data.unwrap_or(serde_json::Value::Null))
+        } else {
+            Err(FuegoError::Api {
+                message: envelope.error.unwrap_or_else(|| "Request failed".to_string()),
+                code: envelope.code,
+            })
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<serde_json::Value, FuegoError> {
+        self.send(self.http.get(format!("{}{}", self.base_url, path))).await
+    }
+
+    async fn post<T: Serialize>(&self, path: &str, body: &T) -> Result<serde_json::Value, FuegoError> {
+        self.send(self.http.post(format!("{}{}", self.base_url, path)).json(body)).await
+    }
+
+    pub async fn health(&self) -> Result<serde_json::Value, FuegoError> {
+        self.get("/health").await
+    }
+
+    pub async fn wallet_address(&self) -> Result<serde_json::Value, FuegoError> {
+        self.get("/wallet-address").await
+    }
+
+    pub async fn dashboard(&self) -> Result<serde_json::Value, FuegoError> {
+        self.get("/dashboard").await
+    }
+
+    /// SOL, USDC and USDT together; each asset carries its own `success` flag.
+    pub async fn balances(&self, request: &GetBalanceRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/balances", request).await
+    }
+
+    pub async fn sol_balance(&self, request: &GetBalanceRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/sol-balance", request).await
+    }
+
+    pub async fn usdc_balance(&self, request: &GetTokenBalanceRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/usdc-balance", request).await
+    }
+
+    pub async fn usdt_balance(&self, request: &GetTokenBalanceRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/usdt-balance", request).await
+    }
+
+    pub async fn pyusd_balance(&self, request: &GetTokenBalanceRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/pyusd-balance", request).await
+    }
+
+    pub async fn token_balance(&self, request: &MintBalanceRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/token-balance", request).await
+    }
+
+    /// Every SPL and Token-2022 holding; set `include_empty` to also list zero balances.
+    pub async fn token_balances(&self, request: &TokenBalancesRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/token-balances", request).await
+    }
+
+    pub async fn tokens(&self, request: &GetTokensRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/tokens", request).await
+    }
+
+    pub async fn build_transfer_sol(&self, request: &TransferSolRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/build-transfer-sol", request).await
+    }
+
+    pub async fn build_transfer_usdc(&self, request: &TransferUsdcRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/build-transfer-usdc", request).await
+    }
+
+    pub async fn build_transfer_usdt(&self, request: &TransferUsdtRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/build-transfer-usdt", request).await
+    }
+
+    pub async fn build_transfer_pyusd(&self, request: &TransferPyusdRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/build-transfer-pyusd", request).await
+    }
+
+    pub async fn build_transfer_token(&self, request: &TransferTokenRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/build-transfer-token", request).await
+    }
+
+    pub async fn transfer_quote(&self, request: &TransferQuoteRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/transfer-quote", request).await
+    }
+
+    pub async fn submit_transaction(&self, request: &SubmitTransactionRequest) -> Result<serde_json::Value, FuegoError> {
+        self.post("/submit-transaction", request).await
+    }
+}
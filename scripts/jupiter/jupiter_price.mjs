@@ -56,10 +56,15 @@ function parseArgs() {
         params.outputMint = TOKEN_MINTS[value.toUpperCase()] || value;
         break;
       case '--amount':
-        // If amount < 1, assume it's in SOL/token units, convert to lamports/smallest unit
-        const num = parseFloat(value);
-        if (num < 1 && num > 0) {
-          params.amount = Math.floor(num * 1000000000).toString(); // Convert SOL to lamports
+        // A decimal amount is in SOL/token units: convert to lamports with string math, since a
+        // float multiply drops the last unit on values like 0.07
+        if (value.includes('.')) {
+          const [whole, fraction = ''] = value.split('.');
+          if (!/^\d*$/.test(whole) || !/^\d*$/.test(fraction) || fraction.replace(/0+$/, '').length > 9) {
+            console.error(`❌ Invalid amount ${value}: use at most 9 decimal places`);
+            process.exit(1);
+          }
+          params.amount = (BigInt(whole || '0') * 1000000000n + BigInt(fraction.padEnd(9, '0').slice(0, 9))).toString();
         } else {
           params.amount = value;
         }
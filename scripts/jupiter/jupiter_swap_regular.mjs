@@ -29,7 +29,7 @@ const JUPITER_SWAP_URL = 'https://api.jup.ag/swap/v1/swap';
 const TOKEN_DECIMALS = {
   'So11111111111111111111111111111111111111112': 9,  // SOL
   'EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v': 6,  // USDC
-  'Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenEqw': 6,  // USDT
+  'Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB': 6,  // USDT
   'DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263': 5,  // BONK
   'HZ1JovNiVvGrGNiiYvEozEVgZ58xaU3RKwX8eACQBCt': 6,  // PYTH
   'JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN': 6,  // JUP
@@ -38,7 +38,7 @@ const TOKEN_DECIMALS = {
 const TOKEN_MINTS = {
   'SOL': 'So11111111111111111111111111111111111111112',
   'USDC': 'EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v',
-  'USDT': 'Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenEqw',
+  'USDT': 'Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB',
   'BONK': 'DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263',
 };
 
@@ -137,22 +137,28 @@ async function fetchTokenDecimals(rpcUrl, mintAddress) {
 
 /**
  * Convert human-readable amount to base units using BigInt for precision
- * @param {number} amount - Human readable amount (e.g., 356343 for BONK)
+ * @param {string} amount - Human readable amount as typed (e.g., "356343" or "0.07")
  * @param {number} decimals - Token decimals
  * @returns {string} - Base units as string (for JSON safety)
  */
 function toBaseUnits(amount, decimals) {
-  // Use string manipulation to avoid floating point errors
-  const amountStr = amount.toString();
+  // String and BigInt math only: a float turns "0.07" USDC into 69999 base units
+  const amountStr = String(amount).trim();
+  if (!/^(\d+(\.\d*)?|\.\d+)$/.test(amountStr)) {
+    throw new Error(`Invalid amount: ${amountStr}`);
+  }
   const [whole, fraction = ''] = amountStr.split('.');
-  
-  // Pad or trim fraction to match decimals
+
+  // Reject rather than truncate digits the token can't represent
+  if (fraction.replace(/0+$/, '').length > decimals) {
+    throw new Error(`Amount ${amountStr} has more than ${decimals} decimal places`);
+  }
   const paddedFraction = fraction.padEnd(decimals, '0').slice(0, decimals);
-  
+
   // Combine: whole * 10^decimals + paddedFraction
-  const wholeBig = BigInt(whole) * BigInt(10) ** BigInt(decimals);
-  const fractionBig = BigInt(paddedFraction.padStart(decimals, '0'));
-  
+  const wholeBig = BigInt(whole || '0') * BigInt(10) ** BigInt(decimals);
+  const fractionBig = BigInt(paddedFraction || '0');
+
   return (wholeBig + fractionBig).toString();
 }
 
@@ -251,9 +257,9 @@ Examples:
     process.exit(1);
   }
   
-  // Validate amount is positive
-  const amountValue = parseFloat(params.amount);
-  if (isNaN(amountValue) || amountValue <= 0) {
+  // Validate amount is a positive decimal; kept as a string so no float ever touches it
+  const amountValue = params.amount.trim();
+  if (!/^(\d+(\.\d*)?|\.\d+)$/.test(amountValue) || !/[1-9]/.test(amountValue)) {
     console.error('❌ Error: --amount must be a positive number');
     process.exit(1);
   }
@@ -434,7 +440,12 @@ async function main() {
   const inputDecimals = await fetchTokenDecimals(config.rpcUrl, params.inputMint);
   
   // Convert amount to base units using BigInt
-  params.amount = toBaseUnits(params.rawAmount, inputDecimals);
+  try {
+    params.amount = toBaseUnits(params.rawAmount, inputDecimals);
+  } catch (err) {
+    console.error(`❌ Error: ${err.message}`);
+    process.exit(1);
+  }
   
   console.log(`\n✓ Amount in base units: ${params.amount}`);
   console.log(`✓ Human readable: ${fromBaseUnits(params.amount, inputDecimals)}\n`);
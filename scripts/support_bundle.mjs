@@ -0,0 +1,53 @@
+#!/usr/bin/env node
+/**
+ * support_bundle.mjs - Download a redacted diagnostics zip from the running fuego server.
+ *
+ * Usage:
+ *   node support_bundle.mjs [--out <file.zip>]
+ *
+ * The bundle contains version info, redacted config, recent alerts, RPC health, queue and
+ * x402 state. Private keys, API keys and webhook secrets are never included.
+ */
+
+import fs from 'fs';
+
+const RUST_SERVER_URL = process.env.FUEGO_SERVER_URL || "http://127.0.0.1:8080";
+
+function parseArgs() {
+  const args = process.argv.slice(2);
+  const parsed = {};
+  for (let i = 0; i < args.length; i++) {
+    const arg = args[i];
+    if (arg.startsWith("--")) {
+      const key = arg.slice(2).replace(/-/g, "_");
+      const value =
+        args[i + 1] && !args[i + 1].startsWith("--") ? args[i + 1] : "";
+      parsed[key] = value;
+      if (value) i++;
+    }
+  }
+  return parsed;
+}
+
+async function main() {
+  const args = parseArgs();
+  const res = await fetch(`${RUST_SERVER_URL}/admin/support-bundle`);
+  if (!res.ok) {
+    console.error(`❌ Server returned ${res.status}`);
+    process.exit(1);
+  }
+
+  const disposition = res.headers.get("content-disposition") || "";
+  const match = disposition.match(/filename="([^"]+)"/);
+  const out = args.out || (match ? match[1] : "fuego-support.zip");
+
+  const bytes = Buffer.from(await res.arrayBuffer());
+  fs.writeFileSync(out, bytes);
+  console.log(`✅ Support bundle saved to ${out} (${bytes.length} bytes)`);
+  console.log("   Review it before sharing; secrets are redacted but addresses and RPC hosts remain.");
+}
+
+main().catch((err) => {
+  console.error(`❌ ${err.message}`);
+  process.exit(1);
+});
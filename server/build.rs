@@ -0,0 +1,46 @@
+//! Embeds build information (git commit, build time, rustc version, enabled features) so a
+//! distributed binary can report exactly what it is without the source tree around.
+
+use std::process::Command;
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+fn main() {
+    let git_hash = command_output("git", &["rev-parse", "--short=12", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    let git_commit = command_output("git", &["rev-parse", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    let dirty = command_output("git", &["status", "--porcelain", "--untracked-files=no"]).is_some();
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = command_output(&rustc, &["--version"]).unwrap_or_else(|| "unknown".to_string());
+    let built_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    // Cargo passes each enabled feature as CARGO_FEATURE_<NAME>, upper-cased with - turned into _
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(name, _)| name.strip_prefix("CARGO_FEATURE_").map(|f| f.to_lowercase().replace('_', "-")))
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=FUEGO_GIT_HASH={}{}", git_hash, if dirty { "-dirty" } else { "" });
+    println!("cargo:rustc-env=FUEGO_GIT_COMMIT={}", git_commit);
+    println!("cargo:rustc-env=FUEGO_GIT_DIRTY={}", dirty);
+    println!("cargo:rustc-env=FUEGO_FEATURES={}", features.join(","));
+    println!("cargo:rustc-env=FUEGO_RUSTC_VERSION={}", rustc_version);
+    println!("cargo:rustc-env=FUEGO_BUILT_AT={}", built_at);
+
+    // Rebuild when the checked-out commit moves, not on every source edit
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs");
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,150 @@
+//! Security alerts: a bounded in-memory log served by GET /alerts, optionally forwarded to a webhook.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::egress::EgressPolicy;
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Clone, Serialize)]
+pub struct Alert {
+    pub id: String,
+    /// Stable event name, e.g. "session_allowance_exceeded"
+    pub kind: String,
+    pub severity: Severity,
+    pub message: String,
+    /// Investigation context; must never contain key material or session tokens
+    pub details: serde_json::Value,
+    pub timestamp: i64,
+}
+
+#[derive(Clone)]
+pub struct AlertLog {
+    inner: Arc<Mutex<VecDeque<Alert>>>,
+    capacity: usize,
+    webhook_url: Option<String>,
+    /// Webhook deliveries go through the egress policy like any other outbound request
+    egress: EgressPolicy,
+}
+
+impl AlertLog {
+    /// Buffer size from FUEGO_ALERT_BUFFER (default 100), webhook from FUEGO_ALERT_WEBHOOK_URL.
+    pub fn from_env(egress: EgressPolicy) -> Self {
+        let capacity = std::env::var("FUEGO_ALERT_BUFFER")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(100);
+        let webhook_url = std::env::var("FUEGO_ALERT_WEBHOOK_URL").ok().filter(|u| !u.is_empty());
+        AlertLog {
+            inner: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+            webhook_url,
+            egress,
+        }
+    }
+
+    pub fn emit(&self, kind: &str, severity: Severity, message: String, details: serde_json::Value) {
+        let alert = Alert {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            kind: kind.to_string(),
+            severity,
+            message,
+            details,
+            timestamp: chrono::Utc::now().timestamp(),
+        };
+        eprintln!("ALERT [{}] {}: {}", kind, alert.id, alert.message);
+
+        {
+            let mut buffer = self.inner.lock().unwrap();
+            if buffer.len() == self.capacity {
+                buffer.pop_front();
+            }
+            buffer.push_back(alert.clone());
+        }
+
+        // Delivery is best-effort and must never hold up the request that raised the alert
+        if let Some(url) = self.webhook_url.clone() {
+            #[cfg(feature = "fault-injection")]
+            if crate::faults::drop_webhook() {
+                eprintln!("Dropped delivery of alert {} (injected fault)", alert.id);
+                return;
+            }
+            let egress = self.egress.clone();
+            crate::trace::spawn(async move {
+                let destination = match egress.check(&url, "alert_webhook").await {
+                    Ok(d) => d,
+                    Err(e) => {
+                        eprintln!("Not delivering alert {} to webhook: {}", alert.id, e.message());
+                        return;
+                    }
+                };
+                let http = match egress.client_builder(&destination).build() {
+                    Ok(c) => c,
+                    Err(e) => {
+                        eprintln!("Failed to deliver alert {} to webhook: {}", alert.id, e);
+                        return;
+                    }
+                };
+                let result = http
+                    .post(destination.url)
+                    .header("X-Fuego-Event", "alert")
+                    .json(&alert)
+                    .send()
+                    .await;
+                if let Err(e) = result {
+                    eprintln!("Failed to deliver alert {} to webhook: {}", alert.id, e);
+                }
+            });
+        }
+    }
+
+    /// Most recent alerts first.
+    pub fn recent(&self, limit: usize) -> Vec<Alert> {
+        self.inner.lock().unwrap().iter().rev().take(limit).cloned().collect()
+    }
+
+    pub fn webhook_configured(&self) -> bool {
+        self.webhook_url.is_some()
+    }
+}
+
+/// Poll wallet.json and raise a high-severity alert whenever its contents change underneath us.
+pub fn spawn_wallet_watch(alerts: AlertLog, wallet_path: std::path::PathBuf) {
+    use sha2::{Digest, Sha256};
+
+    let fingerprint = |path: &std::path::Path| -> Option<String> {
+        std::fs::read(path).ok().map(|bytes| format!("{:x}", Sha256::digest(&bytes)))
+    };
+
+    tokio::spawn(async move {
+        let mut last = fingerprint(&wallet_path);
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+        loop {
+            interval.tick().await;
+            let current = fingerprint(&wallet_path);
+            if current != last {
+                let describe = |state: &Option<String>| if state.is_some() { "present" } else { "missing" };
+                alerts.emit(
+                    "wallet_file_changed",
+                    Severity::High,
+                    format!("{} changed while the server was running", wallet_path.display()),
+                    serde_json::json!({
+                        "path": wallet_path.display().to_string(),
+                        "previous": describe(&last),
+                        "current": describe(&current)
+                    }),
+                );
+                last = current;
+            }
+        }
+    });
+}
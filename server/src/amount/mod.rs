@@ -0,0 +1,298 @@
+//! `TokenAmount`: one type for money, so lamports and SOL, or base units and UI strings, can't be
+//! mixed up. Amounts carry their asset and decimals, all conversions are integer math, and
+//! arithmetic is checked. Base units are held as u128 so sums and comparisons need no casts, but
+//! never leave the u64 range of on-chain balances: every constructor takes a u64 and `checked_add`
+//! refuses to go past it. Nothing here is a float.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+
+use crate::utils::{self, AmountError, RoundingPolicy};
+
+pub const SOL_DECIMALS: u8 = 9;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Asset {
+    Sol,
+    /// A token mint address
+    Mint(String),
+}
+
+impl Asset {
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "SOL" => Asset::Sol,
+            mint => Asset::Mint(mint.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Asset::Sol => "SOL",
+            Asset::Mint(mint) => mint,
+        }
+    }
+}
+
+/// Whether a rounding policy changed a UI amount on its way to base units. Rounding only ever
+/// drops digits, so a rounded amount is always smaller.
+#[derive(Clone, Copy, Default)]
+pub struct Rounding {
+    pub rounded: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenAmount {
+    asset: Asset,
+    units: u128,
+    decimals: u8,
+}
+
+impl TokenAmount {
+    pub fn from_base_units(asset: Asset, units: u64, decimals: u8) -> Self {
+        TokenAmount { asset, units: units.into(), decimals }
+    }
+
+    pub fn lamports(lamports: u64) -> Self {
+        Self::from_base_units(Asset::Sol, lamports, SOL_DECIMALS)
+    }
+
+    /// An integer base-unit string ("1500000"), as sent in `amount_raw`.
+    pub fn parse_raw(asset: Asset, raw: &str, decimals: u8) -> Result<Self, AmountError> {
+        utils::parse_base_units(raw).map(|units| Self::from_base_units(asset, units, decimals))
+    }
+
+    /// A UI amount string ("1.5"), with excess fractional digits resolved by `policy`.
+    pub fn parse_ui(asset: Asset, amount: &str, decimals: u8, policy: RoundingPolicy) -> Result<(Self, Rounding), AmountError> {
+        let parsed = utils::ui_amount_to_base_units_with(amount, decimals, policy)?;
+        Ok((
+            Self::from_base_units(asset, parsed.amount, decimals),
+            Rounding { rounded: parsed.rounded },
+        ))
+    }
+
+    /// Base units as the u64 instructions take; exact, since `units` never leaves the u64 range.
+    pub fn base_units(&self) -> u64 {
+        self.units as u64
+    }
+
+    /// Another amount of the same asset, e.g. the gross sent when a transfer fee is added.
+    pub fn with_base_units(&self, units: u64) -> TokenAmount {
+        TokenAmount { units: units.into(), ..self.clone() }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.units == 0
+    }
+
+    /// Base units as an integer string, e.g. "1500000".
+    pub fn raw(&self) -> String {
+        self.units.to_string()
+    }
+
+    /// UI amount without trailing zeros, e.g. "1.5".
+    pub fn ui(&self) -> String {
+        utils::base_units_to_ui(self.base_units(), self.decimals)
+    }
+
+    /// Sum of two amounts of the same asset, or None if they differ or the sum leaves u64.
+    pub fn checked_add(&self, other: &TokenAmount) -> Option<TokenAmount> {
+        self.same_kind(other)?;
+        let units = self.units.checked_add(other.units).filter(|u| *u <= u64::MAX.into())?;
+        Some(TokenAmount { units, ..self.clone() })
+    }
+
+    /// Difference of two amounts of the same asset, or None if they differ or it would go negative.
+    pub fn checked_sub(&self, other: &TokenAmount) -> Option<TokenAmount> {
+        self.same_kind(other)?;
+        let units = self.units.checked_sub(other.units)?;
+        Some(TokenAmount { units, ..self.clone() })
+    }
+
+    fn same_kind(&self, other: &TokenAmount) -> Option<()> {
+        (self.asset == other.asset && self.decimals == other.decimals).then_some(())
+    }
+
+    /// Compare by value across decimals, e.g. to sort a mixed list of token balances.
+    pub fn cmp_value(&self, other: &TokenAmount) -> Ordering {
+        match self.decimals.cmp(&other.decimals) {
+            Ordering::Equal => self.units.cmp(&other.units),
+            Ordering::Greater => cmp_scaled(self.units, other.units, self.decimals - other.decimals),
+            Ordering::Less => cmp_scaled(other.units, self.units, other.decimals - self.decimals).reverse(),
+        }
+    }
+}
+
+/// `fine` against `coarse` brought to `shift` more decimals. When that overflows u128, a non-zero
+/// `coarse` is the larger: it would exceed 10^38, and `fine` never exceeds u64.
+fn cmp_scaled(fine: u128, coarse: u128, shift: u8) -> Ordering {
+    if coarse == 0 {
+        return fine.cmp(&0);
+    }
+    match 10u128.checked_pow(shift as u32).and_then(|scale| coarse.checked_mul(scale)) {
+        Some(scaled) => fine.cmp(&scaled),
+        None => Ordering::Less,
+    }
+}
+
+/// Both representations, so a client can log either and never has to convert:
+/// `{"asset": "SOL", "raw": "1500000000", "ui": "1.5", "decimals": 9}`.
+#[derive(Serialize, Deserialize)]
+struct Wire {
+    asset: String,
+    raw: String,
+    #[serde(default)]
+    ui: Option<String>,
+    decimals: u8,
+}
+
+impl Serialize for TokenAmount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Wire {
+            asset: self.asset.as_str().to_string(),
+            raw: self.raw(),
+            ui: Some(self.ui()),
+            decimals: self.decimals,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// `raw` is authoritative; a `ui` that disagrees with it is rejected rather than guessed between.
+impl<'de> Deserialize<'de> for TokenAmount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wire = Wire::deserialize(deserializer)?;
+        let amount = TokenAmount::parse_raw(Asset::parse(&wire.asset), &wire.raw, wire.decimals)
+            .map_err(|e| serde::de::Error::custom(e.message()))?;
+        if let Some(ui) = wire.ui {
+            let (from_ui, _) = TokenAmount::parse_ui(amount.asset.clone(), &ui, wire.decimals, RoundingPolicy::Reject)
+                .map_err(|e| serde::de::Error::custom(e.message()))?;
+            if from_ui.units != amount.units {
+                return Err(serde::de::Error::custom(format!("ui {} does not match raw {}", ui, wire.raw)));
+            }
+        }
+        Ok(amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn usdc(units: u64) -> TokenAmount {
+        TokenAmount::from_base_units(Asset::parse(USDC), units, 6)
+    }
+
+    /// Deterministic xorshift, so the property tests need no extra crate and replay exactly.
+    fn values(seed: u64) -> impl Iterator<Item = u64> {
+        let mut x = seed;
+        std::iter::repeat_with(move || {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        })
+    }
+
+    #[test]
+    fn ui_and_raw_agree_for_each_asset() {
+        let (sol, _) = TokenAmount::parse_ui(Asset::Sol, "1.5", SOL_DECIMALS, RoundingPolicy::Reject).unwrap();
+        assert_eq!((sol.raw(), sol.ui(), sol.base_units()), ("1500000000".to_string(), "1.5".to_string(), 1_500_000_000));
+        let (usdc_amount, _) = TokenAmount::parse_ui(Asset::parse(USDC), "1.5", 6, RoundingPolicy::Reject).unwrap();
+        assert_eq!(usdc_amount, usdc(1_500_000));
+        assert_eq!(TokenAmount::lamports(1).ui(), "0.000000001");
+    }
+
+    #[test]
+    fn lamports_and_token_units_never_mix() {
+        // Same number of base units, different assets or decimals
+        let lamports = TokenAmount::lamports(1_000_000);
+        assert!(lamports.checked_add(&usdc(1_000_000)).is_none());
+        assert!(lamports.checked_sub(&usdc(1)).is_none());
+        let same_mint_other_decimals = TokenAmount::from_base_units(Asset::parse(USDC), 1, 9);
+        assert!(usdc(1).checked_add(&same_mint_other_decimals).is_none());
+        assert_ne!(usdc(1), same_mint_other_decimals);
+    }
+
+    #[test]
+    fn arithmetic_stays_in_the_u64_range() {
+        let max = TokenAmount::lamports(u64::MAX);
+        assert!(max.checked_add(&TokenAmount::lamports(1)).is_none());
+        assert_eq!(max.checked_add(&TokenAmount::lamports(0)).unwrap().base_units(), u64::MAX);
+        assert!(TokenAmount::lamports(1).checked_sub(&TokenAmount::lamports(2)).is_none());
+        assert!(TokenAmount::lamports(2).checked_sub(&TokenAmount::lamports(2)).unwrap().is_zero());
+    }
+
+    #[test]
+    fn cmp_value_compares_across_decimals() {
+        // 1 SOL's worth of units at 9 decimals equals 1.0 at 6
+        assert_eq!(TokenAmount::lamports(1_000_000_000).cmp_value(&usdc(1_000_000)), Ordering::Equal);
+        assert_eq!(TokenAmount::lamports(999_999_999).cmp_value(&usdc(1_000_000)), Ordering::Less);
+        assert_eq!(usdc(1).cmp_value(&TokenAmount::lamports(999)), Ordering::Greater);
+
+        // Shifts past u128 are settled without scaling, and zero is zero at any decimals
+        let tiny = TokenAmount::from_base_units(Asset::Sol, u64::MAX, 255);
+        let one = TokenAmount::from_base_units(Asset::Sol, 1, 0);
+        let zero = TokenAmount::from_base_units(Asset::Sol, 0, 0);
+        assert_eq!(tiny.cmp_value(&one), Ordering::Less);
+        assert_eq!(one.cmp_value(&tiny), Ordering::Greater);
+        assert_eq!(tiny.cmp_value(&zero), Ordering::Greater);
+        assert_eq!(zero.cmp_value(&TokenAmount::from_base_units(Asset::Sol, 0, 255)), Ordering::Equal);
+    }
+
+    #[test]
+    fn ui_strings_round_trip_to_the_same_units() {
+        for (units, decimals) in values(0x9E37_79B9_7F4A_7C15).zip(values(42).map(|d| (d % 20) as u8)).take(2000) {
+            let amount = TokenAmount::from_base_units(Asset::Sol, units, decimals);
+            let (parsed, rounding) = TokenAmount::parse_ui(Asset::Sol, &amount.ui(), decimals, RoundingPolicy::Reject).unwrap();
+            assert_eq!(parsed, amount, "{} at {} decimals", units, decimals);
+            assert!(!rounding.rounded);
+            assert_eq!(TokenAmount::parse_raw(Asset::Sol, &amount.raw(), decimals).unwrap(), amount);
+        }
+    }
+
+    #[test]
+    fn cmp_value_matches_comparing_the_exact_decimals() {
+        // The same ordering as comparing both UI strings digit by digit at a common precision
+        let exact = |a: &TokenAmount| {
+            let ui = a.ui();
+            let (whole, frac) = ui.split_once('.').unwrap_or((&ui, ""));
+            (format!("{:0>40}", whole), format!("{:0<40}", frac))
+        };
+        let mut units = values(7);
+        let mut decimals = values(11).map(|d| (d % 25) as u8);
+        for _ in 0..2000 {
+            let shift = |u: u64, d: u8| u >> (d as u64 % 64);
+            let (da, db) = (decimals.next().unwrap(), decimals.next().unwrap());
+            let a = TokenAmount::from_base_units(Asset::Sol, shift(units.next().unwrap(), da), da);
+            let b = TokenAmount::from_base_units(Asset::Sol, shift(units.next().unwrap(), db), db);
+            assert_eq!(a.cmp_value(&b), exact(&a).cmp(&exact(&b)), "{:?} vs {:?}", a, b);
+            assert_eq!(b.cmp_value(&a), a.cmp_value(&b).reverse());
+        }
+    }
+
+    #[test]
+    fn add_then_sub_is_the_identity() {
+        let mut units = values(3).map(|u| u >> 1);
+        for _ in 0..2000 {
+            let (a, b) = (usdc(units.next().unwrap()), usdc(units.next().unwrap()));
+            let sum = a.checked_add(&b).unwrap();
+            assert_eq!(sum.checked_sub(&b).unwrap(), a);
+            assert_eq!(sum.cmp_value(&a), if b.is_zero() { Ordering::Equal } else { Ordering::Greater });
+        }
+    }
+
+    #[test]
+    fn a_wire_amount_whose_ui_disagrees_with_raw_is_refused() {
+        let wire = serde_json::to_value(usdc(1_500_000)).unwrap();
+        assert_eq!(wire, serde_json::json!({ "asset": USDC, "raw": "1500000", "ui": "1.5", "decimals": 6 }));
+        assert_eq!(serde_json::from_value::<TokenAmount>(wire).unwrap(), usdc(1_500_000));
+
+        let lamports_as_sol = serde_json::json!({ "asset": "SOL", "raw": "1500000000", "ui": "1500000000", "decimals": 9 });
+        assert!(serde_json::from_value::<TokenAmount>(lamports_as_sol).is_err());
+        let raw_only = serde_json::json!({ "asset": "SOL", "raw": "5", "decimals": 9 });
+        assert_eq!(serde_json::from_value::<TokenAmount>(raw_only).unwrap(), TokenAmount::lamports(5));
+    }
+}
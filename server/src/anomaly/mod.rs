@@ -0,0 +1,258 @@
+//! Wallet activity heuristics: flag unusual transfers and x402 payments using fuego's own records
+//! (the memo index and x402 in-flight log). Flags are advisory: they are returned as `warnings` and
+//! raised as alerts, never used to refuse a request.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::alerts::{AlertLog, Severity};
+use crate::builds::BuildSummary;
+use crate::utils::ui_amount_to_base_units;
+
+pub const NEW_RECIPIENT: &str = "new_recipient";
+pub const BURST: &str = "burst";
+pub const NEW_X402_HOST: &str = "new_x402_host";
+pub const PRIORITY_FEE: &str = "priority_fee";
+
+const HEURISTICS: [&str; 4] = [NEW_RECIPIENT, BURST, NEW_X402_HOST, PRIORITY_FEE];
+
+/// Recent compute unit prices kept for the median.
+const FEE_SAMPLES: usize = 50;
+/// The priority-fee heuristic stays quiet until it has seen this many non-zero prices.
+const MIN_FEE_SAMPLES: usize = 5;
+
+#[derive(Clone, Serialize)]
+pub struct AnomalyConfig {
+    /// Heuristics switched off via FUEGO_ANOMALY_DISABLE
+    pub disabled: Vec<String>,
+    /// UI amount per token symbol above which a transfer to a never-seen address is flagged
+    pub new_recipient_thresholds: HashMap<String, String>,
+    pub burst_count: usize,
+    pub burst_minutes: i64,
+    /// Flag compute unit prices more than this many times the recent median
+    pub fee_multiplier: u64,
+}
+
+impl AnomalyConfig {
+    /// FUEGO_ANOMALY_DISABLE (comma list of heuristics), FUEGO_ANOMALY_NEW_RECIPIENT
+    /// ("SOL=1;USDC=100"), FUEGO_ANOMALY_BURST_COUNT (default 5) within FUEGO_ANOMALY_BURST_MINUTES
+    /// (default 10), FUEGO_ANOMALY_FEE_MULTIPLIER (default 10).
+    pub fn from_env() -> Self {
+        let env_number = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(default)
+        };
+
+        let mut disabled = Vec::new();
+        if let Ok(list) = std::env::var("FUEGO_ANOMALY_DISABLE") {
+            for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                if HEURISTICS.contains(&name) {
+                    disabled.push(name.to_string());
+                } else {
+                    eprintln!("Ignoring unknown heuristic in FUEGO_ANOMALY_DISABLE: {}", name);
+                }
+            }
+        }
+
+        let mut new_recipient_thresholds: HashMap<String, String> = [("SOL", "1"), ("USDC", "100"), ("USDT", "100"), ("PYUSD", "100")]
+            .iter()
+            .map(|(symbol, amount)| (symbol.to_string(), amount.to_string()))
+            .collect();
+        if let Ok(spec) = std::env::var("FUEGO_ANOMALY_NEW_RECIPIENT") {
+            for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+                match entry.split_once('=') {
+                    Some((symbol, amount)) => {
+                        new_recipient_thresholds.insert(symbol.trim().to_uppercase(), amount.trim().to_string());
+                    }
+                    None => eprintln!("Ignoring malformed FUEGO_ANOMALY_NEW_RECIPIENT entry: {}", entry),
+                }
+            }
+        }
+
+        AnomalyConfig {
+            disabled,
+            new_recipient_thresholds,
+            burst_count: env_number("FUEGO_ANOMALY_BURST_COUNT", 5) as usize,
+            burst_minutes: env_number("FUEGO_ANOMALY_BURST_MINUTES", 10) as i64,
+            fee_multiplier: env_number("FUEGO_ANOMALY_FEE_MULTIPLIER", 10),
+        }
+    }
+
+    fn enabled(&self, heuristic: &str) -> bool {
+        !self.disabled.iter().any(|d| d == heuristic)
+    }
+}
+
+/// One heuristic that fired.
+#[derive(Clone, Serialize)]
+pub struct Flag {
+    pub heuristic: &'static str,
+    pub message: String,
+    pub details: serde_json::Value,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// Build times (unix seconds) within the burst window
+    transfers: VecDeque<i64>,
+    /// Recent non-zero compute unit prices
+    fees: VecDeque<u64>,
+}
+
+#[derive(Clone)]
+pub struct AnomalyDetector {
+    config: Arc<AnomalyConfig>,
+    inner: Arc<Mutex<Inner>>,
+    alerts: AlertLog,
+}
+
+impl AnomalyDetector {
+    pub fn new(config: AnomalyConfig, alerts: AlertLog) -> Self {
+        AnomalyDetector {
+            config: Arc::new(config),
+            inner: Arc::new(Mutex::new(Inner::default())),
+            alerts,
+        }
+    }
+
+    pub fn config(&self) -> &AnomalyConfig {
+        &self.config
+    }
+
+    /// Check a built transfer. Rebuilds (`rebuild`) are evaluated but not counted again.
+    pub fn check_transfer(&self, summary: &BuildSummary, decimals: u8, rebuild: bool) -> Vec<Flag> {
+        let mut flags = Vec::new();
+        let now = chrono::Utc::now().timestamp();
+
+        if self.config.enabled(NEW_RECIPIENT) {
+            let threshold = self
+                .config
+                .new_recipient_thresholds
+                .get(&summary.token)
+                .and_then(|t| ui_amount_to_base_units(t, decimals).ok());
+            if let Some(threshold) = threshold {
+                if summary.amount > threshold && !known_recipients().contains(&summary.to) {
+                    flags.push(Flag {
+                        heuristic: NEW_RECIPIENT,
+                        message: format!("First transfer to {} and above the {} threshold", summary.to, summary.token),
+                        details: serde_json::json!({
+                            "to": summary.to,
+                            "token": summary.token,
+                            "amount": summary.amount,
+                            "threshold": threshold
+                        }),
+                    });
+                }
+            }
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        let window = self.config.burst_minutes * 60;
+        while inner.transfers.front().map(|t| now - t >= window).unwrap_or(false) {
+            inner.transfers.pop_front();
+        }
+        if !rebuild {
+            inner.transfers.push_back(now);
+        }
+        if self.config.enabled(BURST) && inner.transfers.len() > self.config.burst_count {
+            flags.push(Flag {
+                heuristic: BURST,
+                message: format!(
+                    "{} transfers built in the last {} minutes (limit {})",
+                    inner.transfers.len(),
+                    self.config.burst_minutes,
+                    self.config.burst_count
+                ),
+                details: serde_json::json!({
+                    "count": inner.transfers.len(),
+                    "window_minutes": self.config.burst_minutes,
+                    "limit": self.config.burst_count
+                }),
+            });
+        }
+
+        if summary.compute_unit_price > 0 {
+            if self.config.enabled(PRIORITY_FEE) && inner.fees.len() >= MIN_FEE_SAMPLES {
+                let mut sorted: Vec<u64> = inner.fees.iter().copied().collect();
+                sorted.sort_unstable();
+                let median = sorted[sorted.len() / 2];
+                if summary.compute_unit_price > median.saturating_mul(self.config.fee_multiplier) {
+                    flags.push(Flag {
+                        heuristic: PRIORITY_FEE,
+                        message: format!(
+                            "Priority fee of {} micro-lamports/CU is over {}x the recent median ({})",
+                            summary.compute_unit_price, self.config.fee_multiplier, median
+                        ),
+                        details: serde_json::json!({
+                            "compute_unit_price": summary.compute_unit_price,
+                            "median": median,
+                            "multiplier": self.config.fee_multiplier
+                        }),
+                    });
+                }
+            }
+            if !rebuild {
+                if inner.fees.len() == FEE_SAMPLES {
+                    inner.fees.pop_front();
+                }
+                inner.fees.push_back(summary.compute_unit_price);
+            }
+        }
+        drop(inner);
+
+        self.raise(&flags);
+        flags
+    }
+
+    /// Check an x402 payment about to be made to `url`.
+    pub fn check_x402(&self, url: &str) -> Vec<Flag> {
+        let mut flags = Vec::new();
+        if self.config.enabled(NEW_X402_HOST) {
+            if let Some(host) = host_of(url) {
+                if !known_x402_hosts().contains(&host) {
+                    flags.push(Flag {
+                        heuristic: NEW_X402_HOST,
+                        message: format!("First x402 payment to {}", host),
+                        details: serde_json::json!({ "host": host, "url": url }),
+                    });
+                }
+            }
+        }
+        self.raise(&flags);
+        flags
+    }
+
+    fn raise(&self, flags: &[Flag]) {
+        for flag in flags {
+            self.alerts.emit(
+                &format!("anomaly_{}", flag.heuristic),
+                Severity::Medium,
+                flag.message.clone(),
+                flag.details.clone(),
+            );
+        }
+    }
+}
+
+fn host_of(url: &str) -> Option<String> {
+    reqwest::Url::parse(url).ok()?.host_str().map(|h| h.to_lowercase())
+}
+
+/// Recipients of transfers fuego has submitted, from the memo index.
+fn known_recipients() -> HashSet<String> {
+    crate::memo_index::load().into_iter().filter_map(|m| m.to).collect()
+}
+
+/// Hosts that have answered a paid x402 request before.
+fn known_x402_hosts() -> HashSet<String> {
+    crate::receipts::list_inflight()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|r| r.status == "completed")
+        .filter_map(|r| host_of(&r.url))
+        .collect()
+}
@@ -0,0 +1,312 @@
+//! The error every handler returns: an HTTP status that says what kind of failure it was, with
+//! the body clients already parse (`success: false`, `error` and `code`).
+//! Validation problems are 400, missing things 404, state conflicts 409, and failures of the RPC
+//! or another upstream 502 (504 when it timed out, which the RPC timeout middleware sets).
+//!
+//! `code` is what clients branch on; the message is for people and may be reworded. Every code the
+//! server can emit is listed in `CODES` (served at GET /errors). A published code is never renamed
+//! or given a new meaning: retire one by leaving its entry in place.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+#[derive(Serialize)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    /// Whether the same request can succeed if sent again later, unchanged
+    pub retryable: bool,
+    pub description: &'static str,
+}
+
+const fn code(code: &'static str, retryable: bool, description: &'static str) -> ErrorCode {
+    ErrorCode { code, retryable, description }
+}
+
+pub const CODES: &[ErrorCode] = &[
+    // The request itself
+    code("invalid_request", false, "A parameter is missing, out of range or contradicts another"),
+    code("invalid_json", false, "The body is not valid JSON for this endpoint"),
+    code("unknown_fields", false, "The body has fields this endpoint doesn't define"),
+    code("invalid_fields", false, "The fields selector names paths that don't exist"),
+    code("body_too_large", false, "The request body is unreadable or over the size limit"),
+    code("invalid_address", false, "An address is not a valid base58 public key"),
+    code("invalid_mint", false, "The mint is not a valid address"),
+    code("unknown_mint", false, "No mint account exists at that address"),
+    code("unsupported_token", false, "The token isn't one this endpoint handles"),
+    code("invalid_signature", false, "A transaction signature is not valid base58"),
+    code("invalid_transaction", false, "A transaction is not valid base64 or doesn't deserialize"),
+    code("transaction_too_large", false, "The built transaction is over the 1232-byte packet limit"),
+    code("invalid_amount", false, "An amount can't be used: unparseable, too small or too large"),
+    code("amount_missing", false, "Neither amount nor amount_raw was passed"),
+    code("amount_conflict", false, "Amount parameters contradict each other"),
+    code("amount_zero", false, "The amount is zero"),
+    code("amount_not_numeric", false, "The amount is not a number"),
+    code("amount_negative", false, "The amount is negative"),
+    code("amount_too_precise", false, "The amount has more decimals than the token"),
+    code("amount_exceeds_max", false, "The amount doesn't fit in a u64 of base units"),
+    code("invalid_mint_decimals", false, "The token has more decimals than amounts can be converted at"),
+    code("invalid_rounding", false, "Unknown rounding policy"),
+    code("invalid_memo", false, "The memo fields (yid, notes) can't be encoded"),
+    code("invalid_memo_privacy", false, "Unknown memo_privacy value"),
+    code("invalid_create_ata", false, "Unknown create_ata value"),
+    code("invalid_extra_instructions", false, "An extra instruction is malformed or not allowed"),
+    code("exchange_memo_required", false, "The destination is an exchange deposit address that needs exchange_memo"),
+    code("exchange_memo_too_long", false, "exchange_memo is over the size limit"),
+    code("exchange_registry_invalid", false, "The exchange deposit registry file can't be read"),
+    code("missing_network", false, "No network was passed and none is configured"),
+    code("invalid_network", false, "The network is not a known cluster"),
+    code("invalid_rpc_url", false, "rpc_url is malformed or not allowed"),
+    code("unknown_provider", false, "No RPC provider profile by that name"),
+    code("provider_network_mismatch", false, "The provider serves a different network"),
+    code("invalid_url", false, "A URL to fetch is malformed"),
+    code("invalid_status", false, "Unknown status filter"),
+    code("invalid_timeout", false, "The timeout is out of range"),
+    code("empty_batch", false, "A batch has no items"),
+    code("batch_too_large", false, "A batch has more items than allowed"),
+    code("unlock_in_past", false, "The unlock time has already passed"),
+    code("dry_run_unsupported", false, "The request would act on chain, which dry-run mode never does"),
+    code("warnings_as_errors", false, "The build raised warnings and warnings_as_errors was set"),
+    // Credentials, sessions and share links
+    code("auth_required", false, "No API key or request signature"),
+    code("malformed_signature", false, "The request signature header can't be parsed"),
+    code("unknown_key", false, "The key is not configured"),
+    code("wrong_auth_scheme", false, "The key is configured for the other auth scheme"),
+    code("bad_signature", false, "The request signature does not verify"),
+    code("stale_timestamp", false, "The signed timestamp is outside the allowed window"),
+    code("replayed_nonce", false, "The signed nonce was already used"),
+    code("session_required", false, "This endpoint needs a spending session"),
+    code("session_unknown", false, "No session with that token"),
+    code("session_expired", false, "The session has expired"),
+    code("session_revoked", false, "The session was revoked"),
+    code("session_endpoint_not_allowed", false, "The session doesn't cover this endpoint"),
+    code("session_token_not_allowed", false, "The session doesn't cover this token"),
+    code("session_allowance_exceeded", false, "The request would go over the session's cap"),
+    code("session_not_found", false, "No session with that id"),
+    code("session_admin_required", false, "Only a session admin key can create or revoke sessions"),
+    code("share_link_invalid", false, "The share token is not valid"),
+    code("share_link_expired", false, "The share link has expired"),
+    code("share_link_revoked", false, "The share link was revoked"),
+    code("share_view_not_allowed", false, "The share link doesn't grant this view"),
+    code("share_param_not_allowed", false, "The share link fixes this parameter"),
+    code("share_out_of_scope", false, "The request reaches outside what the link shares"),
+    code("share_link_not_found", false, "No share link with that id"),
+    code("invalid_share_range", false, "The shared time range is invalid"),
+    code("invalid_share_ttl", false, "The share link lifetime is out of range"),
+    code("invalid_share_view", false, "Unknown share view"),
+    code("rate_limited", true, "Too many requests; retry after retry_after_seconds"),
+    code("cors_origin_denied", false, "A browser preflight came from an origin the CORS policy doesn't allow"),
+    // Wallets and balances
+    code("wallet_not_found", false, "No local wallet at ~/.fuego/wallet.json"),
+    code("no_local_wallet", false, "The request needs the local wallet to sign, and there is none"),
+    code("wallet_unreadable", false, "The local wallet file exists but can't be used"),
+    code("wallet_mismatch", false, "The local wallet files disagree with its key"),
+    code("local_wallet_not_sender", false, "The local wallet is not the transaction's fee payer"),
+    code("watch_only_wallet", false, "The address is watch-only and can't sign"),
+    code("key_export_disabled", false, "Key export is turned off on this server"),
+    code("key_export_not_set_up", false, "No export passphrase is set; run fuego-server key-export setup"),
+    code("invalid_passphrase", false, "The passphrase is wrong"),
+    code("totp_required", false, "This server requires totp_code for key export"),
+    code("invalid_totp", false, "totp_code is wrong or expired"),
+    code("insufficient_funds", false, "The balance doesn't cover the amount"),
+    code("insufficient_sol_for_fees", false, "Not enough SOL for fees and rent"),
+    code("fee_wallet_depleted", false, "The fee wallet is below its floor"),
+    code("onboard_budget_exceeded", false, "Sponsoring this wallet would go over the onboarding budget"),
+    code("sweep_balance_unavailable", true, "The balance to sweep couldn't be read"),
+    // Records
+    code("transaction_not_found", true, "The RPC has no transaction with that signature (yet)"),
+    code("scheduled_transfer_not_found", false, "No scheduled transfer with that id"),
+    code("queue_item_not_found", false, "No submit queue item with that id"),
+    code("receipt_not_found", false, "No x402 receipt with that id"),
+    code("receipt_body_not_stored", false, "The purchase didn't store its response body"),
+    code("receipt_body_mismatch", false, "The stored response body doesn't match the receipt hash"),
+    code("inflight_not_found", false, "No in-flight x402 record with that id"),
+    // State
+    code("not_awaiting_signature", false, "The scheduled transfer is not waiting for a signature"),
+    code("not_cancellable", false, "The item is past the point it can be cancelled"),
+    code("transfer_timelocked", true, "The transfer is still locked"),
+    code("reconcile_in_progress", true, "A reconcile run is already going"),
+    code("possible_duplicate_payment", false, "An earlier payment for this request may have gone through"),
+    code("transaction_mismatch", false, "The signed transaction differs from the one fuego built"),
+    code("confirmation_mismatch", false, "confirm_address is not the local wallet's address"),
+    code("missing_signatures", false, "Required signers haven't signed"),
+    code("invalid_signatures", false, "Signatures don't verify; the transaction changed after signing"),
+    // The chain and the RPC
+    code("rpc_error", true, "The RPC node failed or refused the call"),
+    code("rpc_timeout", true, "The RPC node didn't answer in time"),
+    code("rpc_circuit_open", true, "Every RPC endpoint for the call is failing; retry after retry_after_seconds"),
+    code("request_timeout", true, "The request ran past its route's time limit"),
+    code("blockhash_fetch_failed", true, "Couldn't get a recent blockhash to build with"),
+    code("blockhash_not_found", false, "Preflight: the blockhash expired or is unknown; rebuild with a fresh one"),
+    code("insufficient_funds_for_fee", false, "Preflight: the fee payer has too little SOL for the fee"),
+    code("insufficient_funds_for_rent", false, "Preflight: an account would drop below the rent-exempt minimum"),
+    code("already_processed", false, "Preflight: this exact transaction was already processed"),
+    code("account_not_found", false, "Preflight: the fee payer account doesn't exist"),
+    code("program_error", false, "Preflight: an instruction failed; see instruction_index and custom_error"),
+    code("transaction_rejected", false, "Preflight rejected the transaction for another reason; see details"),
+    code("transaction_failed", false, "The transaction landed but failed on chain"),
+    code("confirmation_timeout", true, "The transaction was sent but not confirmed in time; it may still land"),
+    code("transaction_reorged", true, "The transaction was confirmed, then dropped by a fork"),
+    code("nonce_account_failed", true, "Creating the durable nonce account failed"),
+    code("nonce_close_failed", true, "Closing the durable nonce account failed"),
+    // Paid requests
+    code("egress_blocked", false, "The egress policy doesn't allow that destination"),
+    code("egress_unresolved", true, "The destination host didn't resolve"),
+    code("upstream_request_failed", true, "The paid endpoint couldn't be reached or its answer read"),
+    code("upstream_response_too_large", false, "The paid endpoint's response is over the size limit"),
+    // Devnet faucet
+    code("faucet_not_configured", false, "The devnet faucet is not set up on this server"),
+    code("faucet_wrong_network", false, "The faucet only mints on devnet"),
+    code("faucet_cap_exceeded", false, "The amount is over the per-request faucet cap"),
+    // fuego itself
+    code("serialization_failed", false, "A transaction or request body couldn't be serialized"),
+    code("backup_failed", false, "Writing the backup archive failed"),
+    code("invalid_config", false, "server.json (or FUEGO_CONFIG) failed to parse or validate; nothing was reloaded"),
+    code("internal_error", false, "Something in fuego itself failed"),
+];
+
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+    code: Option<&'static str>,
+    /// Extra fields merged into the body after `error` and `code`
+    fields: Map<String, Value>,
+}
+
+/// What a handler returns: its JSON body (or a response it built itself), or the error.
+pub type ApiResult<T = Json<Value>> = Result<T, ApiError>;
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        ApiError { status, message: message.into(), code: None, fields: Map::new() }
+    }
+
+    /// The request itself is wrong: a malformed address, amount or parameter.
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    /// No credentials, or ones that don't check out.
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, message)
+    }
+
+    /// Refused by configuration or policy, however the request is phrased.
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, message)
+    }
+
+    /// The wallet, record or transaction named doesn't exist.
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    /// Valid, but not in the current state: already submitted, still locked, in progress.
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, message)
+    }
+
+    pub fn rate_limited(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::TOO_MANY_REQUESTS, message)
+    }
+
+    /// fuego's own fault: local files, serialization, a task that died.
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+
+    /// The RPC node or another upstream failed or refused.
+    pub fn upstream(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_GATEWAY, message)
+    }
+
+    /// Gave up waiting on the RPC or the chain.
+    pub fn timeout(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::GATEWAY_TIMEOUT, message)
+    }
+
+    /// A feature this server wasn't configured for.
+    pub fn unavailable(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, message)
+    }
+
+    pub fn code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Add a field to the body, e.g. the values a refusal was based on.
+    pub fn with(mut self, key: &str, value: impl Serialize) -> Self {
+        self.fields.insert(key.to_string(), serde_json::to_value(value).unwrap_or(Value::Null));
+        self
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut body = Map::new();
+        body.insert("success".to_string(), Value::Bool(false));
+        body.insert("error".to_string(), Value::String(self.message));
+        // Every site sets a code; these keep an unlabelled one from reaching a client without one
+        let code = self.code.unwrap_or(if self.status.is_client_error() { "invalid_request" } else { "internal_error" });
+        debug_assert!(CODES.iter().any(|c| c.code == code), "error code {} is not in CODES", code);
+        body.insert("code".to_string(), Value::String(code.to_string()));
+        if let Some(id) = crate::request_id::current() {
+            body.insert("request_id".to_string(), Value::String(id));
+        }
+        body.extend(self.fields);
+        (self.status, Json(Value::Object(body))).into_response()
+    }
+}
+
+impl From<crate::mints::MintLookupError> for ApiError {
+    fn from(e: crate::mints::MintLookupError) -> Self {
+        let error = match e {
+            crate::mints::MintLookupError::InvalidMint => ApiError::bad_request(e.message()),
+            crate::mints::MintLookupError::UnknownMint => ApiError::not_found(e.message()),
+            crate::mints::MintLookupError::Rpc(_) => ApiError::upstream(e.message()),
+        };
+        error.code(e.code())
+    }
+}
+
+impl From<crate::sessions::SessionError> for ApiError {
+    fn from(e: crate::sessions::SessionError) -> Self {
+        use crate::sessions::SessionError;
+        let error = match e {
+            SessionError::Missing | SessionError::Unknown | SessionError::Expired | SessionError::Revoked => {
+                ApiError::unauthorized(e.message())
+            }
+            SessionError::EndpointNotAllowed(_) | SessionError::TokenNotAllowed(_) | SessionError::Exceeded { .. } => {
+                ApiError::forbidden(e.message())
+            }
+        };
+        error.code(e.code())
+    }
+}
+
+impl From<crate::auth::AuthError> for ApiError {
+    fn from(e: crate::auth::AuthError) -> Self {
+        ApiError::unauthorized(e.message()).code(e.code())
+    }
+}
+
+impl From<crate::share_links::ShareError> for ApiError {
+    fn from(e: crate::share_links::ShareError) -> Self {
+        let error = if e.is_token_error() { ApiError::unauthorized(e.message()) } else { ApiError::forbidden(e.message()) };
+        error.code(e.code())
+    }
+}
+
+impl From<crate::egress::EgressError> for ApiError {
+    fn from(e: crate::egress::EgressError) -> Self {
+        let error = match e {
+            crate::egress::EgressError::Blocked(_) => ApiError::forbidden(e.message()),
+            crate::egress::EgressError::InvalidUrl(_) => ApiError::bad_request(e.message()),
+            crate::egress::EgressError::Resolve(_) => ApiError::upstream(e.message()),
+        };
+        error.code(e.code())
+    }
+}
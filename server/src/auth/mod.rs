@@ -0,0 +1,435 @@
+//! Request authentication. Keys come from FUEGO_AUTH_KEYS, FUEGO_API_KEY and the `auth_keys`
+//! section of the server config; each one is either a plain bearer token or an HMAC secret used
+//! to sign requests (x-fuego-signature). With no keys configured every request is allowed, as
+//! before. The file's keys are re-read by POST /admin/reload, so a key can be rotated by adding
+//! the new one, moving clients over, then removing the old one, without a restart.
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::config::ServerConfig;
+use crate::utils::constant_time_eq;
+
+pub const SIGNATURE_HEADER: &str = "x-fuego-signature";
+
+/// Routes reachable without credentials. Deploy tooling checks /health and /version before it
+/// has a key. The embedded dashboard's page and config are public so it can ask for a key; every
+/// call it makes after that is authenticated.
+pub const PUBLIC_PATHS: &[&str] = &["/health", "/version", "/auth/echo", "/ui", "/ui-config"];
+
+/// Path prefixes reachable without credentials: the dashboard's static assets, and share link
+/// views, where the signed token in the path is the credential.
+pub const PUBLIC_PREFIXES: &[&str] = &["/ui/", "/shared/"];
+
+pub fn is_public(path: &str) -> bool {
+    PUBLIC_PATHS.contains(&path) || PUBLIC_PREFIXES.iter().any(|prefix| path.starts_with(prefix))
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthScheme {
+    Bearer,
+    Hmac,
+}
+
+/// Label FUEGO_API_KEY is logged under.
+const SINGLE_KEY_LABEL: &str = "default";
+
+/// The key a request authenticated with, as a request extension. Absent when auth is off or the
+/// route is public.
+#[derive(Clone)]
+pub struct Authenticated {
+    pub label: String,
+}
+
+pub struct ApiKey {
+    label: String,
+    scheme: AuthScheme,
+    secret: Vec<u8>,
+}
+
+/// Why a request was not authenticated; `code()` is the stable error code.
+#[derive(Debug)]
+pub enum AuthError {
+    Missing,
+    Malformed(String),
+    UnknownKey(String),
+    WrongScheme(String),
+    BadSignature,
+    StaleTimestamp { skew_secs: u64, max_skew_secs: i64 },
+    ReplayedNonce,
+}
+
+impl AuthError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthError::Missing => "auth_required",
+            AuthError::Malformed(_) => "malformed_signature",
+            AuthError::UnknownKey(_) => "unknown_key",
+            AuthError::WrongScheme(_) => "wrong_auth_scheme",
+            AuthError::BadSignature => "bad_signature",
+            AuthError::StaleTimestamp { .. } => "stale_timestamp",
+            AuthError::ReplayedNonce => "replayed_nonce",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            AuthError::Missing => format!("Send Authorization: Bearer <key> or a signed {} header", SIGNATURE_HEADER),
+            AuthError::Malformed(e) => format!("Malformed {} header: {}", SIGNATURE_HEADER, e),
+            AuthError::UnknownKey(label) => format!("Unknown key: {}", label),
+            AuthError::WrongScheme(label) => format!("Key {} is not configured for this authentication scheme", label),
+            AuthError::BadSignature => "Signature does not match the request".to_string(),
+            AuthError::StaleTimestamp { skew_secs, max_skew_secs } => format!(
+                "Timestamp is {}s away from server time (allowed: {}s)",
+                skew_secs, max_skew_secs
+            ),
+            AuthError::ReplayedNonce => "Nonce was already used".to_string(),
+        }
+    }
+}
+
+/// Fields of `x-fuego-signature: keyId=...,ts=...,nonce=...,sig=...`.
+pub struct SignatureHeader {
+    pub key_id: String,
+    pub timestamp: i64,
+    pub nonce: String,
+    pub signature: String,
+}
+
+impl SignatureHeader {
+    pub fn parse(value: &str) -> Result<Self, AuthError> {
+        let mut fields: HashMap<&str, &str> = HashMap::new();
+        for part in value.split(',') {
+            match part.trim().split_once('=') {
+                Some((k, v)) => {
+                    fields.insert(k.trim(), v.trim());
+                }
+                None => return Err(AuthError::Malformed(format!("expected key=value, got {}", part.trim()))),
+            }
+        }
+        let field = |name: &str| {
+            fields
+                .get(name)
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+                .ok_or_else(|| AuthError::Malformed(format!("missing {}", name)))
+        };
+        let timestamp = field("ts")?
+            .parse::<i64>()
+            .map_err(|_| AuthError::Malformed("ts must be unix seconds".to_string()))?;
+        Ok(SignatureHeader {
+            key_id: field("keyId")?,
+            timestamp,
+            nonce: field("nonce")?,
+            signature: field("sig")?.to_lowercase(),
+        })
+    }
+}
+
+/// The string that gets signed: method, path with query, hex SHA-256 of the body, timestamp and
+/// nonce, one per line.
+pub fn canonical_request(method: &str, path_and_query: &str, body: &[u8], timestamp: i64, nonce: &str) -> String {
+    format!(
+        "{}\n{}\n{:x}\n{}\n{}",
+        method.to_uppercase(),
+        path_and_query,
+        Sha256::digest(body),
+        timestamp,
+        nonce
+    )
+}
+
+/// `scheme:secret`, as written after `label=` in FUEGO_AUTH_KEYS and as the values in `auth_keys`.
+pub fn parse_key(label: &str, spec: &str) -> Option<ApiKey> {
+    let (scheme, secret) = spec.split_once(':')?;
+    let scheme = match scheme.trim() {
+        "bearer" => AuthScheme::Bearer,
+        "hmac" => AuthScheme::Hmac,
+        _ => return None,
+    };
+    let key = ApiKey {
+        label: label.trim().to_string(),
+        scheme,
+        secret: secret.trim().as_bytes().to_vec(),
+    };
+    (!key.label.is_empty() && !key.secret.is_empty()).then_some(key)
+}
+
+/// Every configured key: FUEGO_AUTH_KEYS, then FUEGO_API_KEY, then the server config.
+fn load_keys(config: &ServerConfig) -> Vec<ApiKey> {
+    let mut keys = Vec::new();
+    if let Ok(spec) = std::env::var("FUEGO_AUTH_KEYS") {
+        for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.split_once('=').and_then(|(label, rest)| parse_key(label, rest)) {
+                Some(key) => keys.push(key),
+                // Never echo the entry: it contains the secret
+                None => eprintln!("Ignoring malformed FUEGO_AUTH_KEYS entry (expected label=bearer:token or label=hmac:secret)"),
+            }
+        }
+    }
+    if let Some(token) = std::env::var("FUEGO_API_KEY").ok().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()) {
+        keys.push(ApiKey {
+            label: SINGLE_KEY_LABEL.to_string(),
+            scheme: AuthScheme::Bearer,
+            secret: token.into_bytes(),
+        });
+    }
+    // Entries were checked when the config loaded
+    let mut labels: Vec<&String> = config.auth_keys.keys().collect();
+    labels.sort();
+    keys.extend(labels.into_iter().filter_map(|label| parse_key(label, &config.auth_keys[label])));
+    keys
+}
+
+/// Labels of the keys that may create and revoke sessions: FUEGO_SESSION_ADMIN_KEYS
+/// ("ops,deploy"), else `session_admin_keys` from the server config.
+fn load_session_admins(config: &ServerConfig, keys: &[ApiKey]) -> Vec<String> {
+    let labels: Vec<String> = match std::env::var("FUEGO_SESSION_ADMIN_KEYS") {
+        Ok(spec) => spec.split(',').map(str::trim).filter(|l| !l.is_empty()).map(str::to_string).collect(),
+        Err(_) => config.session_admin_keys.iter().map(|l| l.trim().to_string()).collect(),
+    };
+    for label in labels.iter().filter(|label| !keys.iter().any(|k| &k.label == *label)) {
+        eprintln!("Session admin key '{}' is not a configured auth key", label);
+    }
+    labels
+}
+
+#[derive(Clone)]
+pub struct Auth {
+    keys: Arc<RwLock<Vec<ApiKey>>>,
+    session_admins: Arc<RwLock<Vec<String>>>,
+    max_skew_secs: i64,
+    /// (key label, nonce) -> unix time after which the nonce can be forgotten
+    nonces: Arc<Mutex<HashMap<(String, String), i64>>>,
+}
+
+impl Auth {
+    /// Keys from FUEGO_AUTH_KEYS="label=hmac:secret;label2=bearer:token", FUEGO_API_KEY (a single
+    /// bearer token, labelled "default") and the server config; skew window from
+    /// FUEGO_AUTH_MAX_SKEW_SECS (default 300). Session admins from `load_session_admins`.
+    pub fn load(config: &ServerConfig) -> Self {
+        let max_skew_secs = std::env::var("FUEGO_AUTH_MAX_SKEW_SECS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(300);
+        let keys = load_keys(config);
+        Auth {
+            session_admins: Arc::new(RwLock::new(load_session_admins(config, &keys))),
+            keys: Arc::new(RwLock::new(keys)),
+            max_skew_secs,
+            nonces: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.keys.read().unwrap().is_empty()
+    }
+
+    /// Replace the keys and session admins from every source, with `config` freshly re-read;
+    /// returns how many keys are configured now.
+    pub fn reload(&self, config: &ServerConfig) -> usize {
+        let keys = load_keys(config);
+        let count = keys.len();
+        *self.session_admins.write().unwrap() = load_session_admins(config, &keys);
+        *self.keys.write().unwrap() = keys;
+        count
+    }
+
+    /// Whether the key labelled `label` may create and revoke sessions.
+    pub fn is_session_admin(&self, label: &str) -> bool {
+        self.session_admins.read().unwrap().iter().any(|l| l == label)
+    }
+
+    /// How many keys `reload` would leave configured, without changing anything.
+    pub fn count(config: &ServerConfig) -> usize {
+        load_keys(config).len()
+    }
+
+    pub fn max_skew_secs(&self) -> i64 {
+        self.max_skew_secs
+    }
+
+    /// Labels and schemes of the configured keys, never the secrets.
+    pub fn describe(&self) -> serde_json::Value {
+        serde_json::json!({
+            "enabled": self.enabled(),
+            "max_skew_secs": self.max_skew_secs,
+            "keys": self.keys.read().unwrap().iter().map(|k| serde_json::json!({ "label": k.label, "scheme": k.scheme })).collect::<Vec<_>>(),
+            "session_admin_keys": self.session_admins.read().unwrap().clone()
+        })
+    }
+
+    /// Label of the bearer key matching `token`.
+    pub fn verify_bearer(&self, token: &str) -> Result<String, AuthError> {
+        self.keys
+            .read()
+            .unwrap()
+            .iter()
+            .find(|k| constant_time_eq(&k.secret, token.as_bytes()))
+            .map(|k| {
+                if k.scheme == AuthScheme::Bearer {
+                    Ok(k.label.clone())
+                } else {
+                    Err(AuthError::WrongScheme(k.label.clone()))
+                }
+            })
+            .unwrap_or(Err(AuthError::UnknownKey("(bearer token)".to_string())))
+    }
+
+    /// Check a signed request. With `consume_nonce` false (the echo endpoint) a valid nonce is not
+    /// remembered, so the same request can still be sent for real afterwards.
+    pub fn verify_signed(
+        &self,
+        header: &SignatureHeader,
+        method: &str,
+        path_and_query: &str,
+        body: &[u8],
+        consume_nonce: bool,
+    ) -> Result<String, AuthError> {
+        let keys = self.keys.read().unwrap();
+        let key = keys.iter().find(|k| k.label == header.key_id).ok_or_else(|| AuthError::UnknownKey(header.key_id.clone()))?;
+        if key.scheme != AuthScheme::Hmac {
+            return Err(AuthError::WrongScheme(key.label.clone()));
+        }
+
+        let canonical = canonical_request(method, path_and_query, body, header.timestamp, &header.nonce);
+        let mut mac = match Hmac::<Sha256>::new_from_slice(&key.secret) {
+            Ok(m) => m,
+            Err(_) => return Err(AuthError::BadSignature),
+        };
+        mac.update(canonical.as_bytes());
+        let expected = format!("{:x}", mac.finalize().into_bytes());
+        if !constant_time_eq(expected.as_bytes(), header.signature.as_bytes()) {
+            return Err(AuthError::BadSignature);
+        }
+
+        // Only a correctly signed request can be stale or replayed
+        let now = chrono::Utc::now().timestamp();
+        // ts is whatever the client sent; one near i64::MIN or MAX must not overflow the subtraction
+        match now.checked_sub(header.timestamp).map(i64::unsigned_abs) {
+            Some(skew_secs) if skew_secs <= self.max_skew_secs.unsigned_abs() => {}
+            skew_secs => {
+                return Err(AuthError::StaleTimestamp {
+                    skew_secs: skew_secs.unwrap_or(u64::MAX),
+                    max_skew_secs: self.max_skew_secs,
+                })
+            }
+        }
+
+        let mut nonces = self.nonces.lock().unwrap();
+        nonces.retain(|_, expires| *expires > now);
+        let entry = (key.label.clone(), header.nonce.clone());
+        if nonces.contains_key(&entry) {
+            return Err(AuthError::ReplayedNonce);
+        }
+        if consume_nonce {
+            // Past this point the timestamp check alone rejects the request
+            nonces.insert(entry, header.timestamp + self.max_skew_secs + 1);
+        }
+        Ok(key.label.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: &str = "/build-transfer-sol?network=devnet";
+    const BODY: &[u8] = br#"{"amount":"0.1"}"#;
+
+    fn auth() -> Auth {
+        let mut config = ServerConfig::default();
+        config.auth_keys.insert("agent".to_string(), "hmac:agent-secret".to_string());
+        config.auth_keys.insert("ops".to_string(), "bearer:ops-token".to_string());
+        Auth::load(&config)
+    }
+
+    fn signed(key_id: &str, secret: &str, timestamp: i64, nonce: &str) -> SignatureHeader {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(canonical_request("POST", PATH, BODY, timestamp, nonce).as_bytes());
+        let signature = format!("{:x}", mac.finalize().into_bytes());
+        SignatureHeader::parse(&format!("keyId={},ts={},nonce={},sig={}", key_id, timestamp, nonce, signature)).unwrap()
+    }
+
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    #[test]
+    fn bearer_tokens_match_only_bearer_keys() {
+        let auth = auth();
+        assert_eq!(auth.verify_bearer("ops-token").unwrap(), "ops");
+        assert_eq!(auth.verify_bearer("ops-token2").unwrap_err().code(), "unknown_key");
+        assert_eq!(auth.verify_bearer("agent-secret").unwrap_err().code(), "wrong_auth_scheme");
+    }
+
+    #[test]
+    fn a_correctly_signed_request_verifies() {
+        let header = signed("agent", "agent-secret", now(), "n-1");
+        assert_eq!(auth().verify_signed(&header, "post", PATH, BODY, true).unwrap(), "agent");
+    }
+
+    #[test]
+    fn any_change_to_the_signed_request_breaks_the_signature() {
+        let auth = auth();
+        let header = signed("agent", "agent-secret", now(), "n-1");
+        for (method, path, body) in [
+            ("GET", PATH, BODY),
+            ("POST", "/build-transfer-sol?network=mainnet-beta", BODY),
+            ("POST", PATH, &br#"{"amount":"100"}"#[..]),
+        ] {
+            assert_eq!(auth.verify_signed(&header, method, path, body, true).unwrap_err().code(), "bad_signature");
+        }
+        let wrong_secret = signed("agent", "guessed", now(), "n-2");
+        assert_eq!(auth.verify_signed(&wrong_secret, "POST", PATH, BODY, true).unwrap_err().code(), "bad_signature");
+        let bearer_key = signed("ops", "ops-token", now(), "n-3");
+        assert_eq!(auth.verify_signed(&bearer_key, "POST", PATH, BODY, true).unwrap_err().code(), "wrong_auth_scheme");
+        let unknown = signed("nobody", "agent-secret", now(), "n-4");
+        assert_eq!(auth.verify_signed(&unknown, "POST", PATH, BODY, true).unwrap_err().code(), "unknown_key");
+    }
+
+    #[test]
+    fn a_nonce_is_accepted_once_and_the_echo_does_not_use_it_up() {
+        let auth = auth();
+        let header = signed("agent", "agent-secret", now(), "n-1");
+        assert!(auth.verify_signed(&header, "POST", PATH, BODY, false).is_ok());
+        assert!(auth.verify_signed(&header, "POST", PATH, BODY, true).is_ok());
+        assert_eq!(auth.verify_signed(&header, "POST", PATH, BODY, true).unwrap_err().code(), "replayed_nonce");
+        assert!(auth.verify_signed(&signed("agent", "agent-secret", now(), "n-2"), "POST", PATH, BODY, true).is_ok());
+    }
+
+    #[test]
+    fn timestamps_outside_the_skew_window_are_stale() {
+        let auth = auth();
+        let max = auth.max_skew_secs();
+        for ts in [now() - max + 5, now() + max - 5] {
+            assert!(auth.verify_signed(&signed("agent", "agent-secret", ts, &ts.to_string()), "POST", PATH, BODY, true).is_ok());
+        }
+        for ts in [now() - max - 5, now() + max + 5] {
+            let error = auth.verify_signed(&signed("agent", "agent-secret", ts, "n"), "POST", PATH, BODY, true).unwrap_err();
+            assert!(matches!(error, AuthError::StaleTimestamp { skew_secs, .. } if skew_secs > max as u64), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn extreme_timestamps_are_stale_rather_than_overflowing() {
+        let auth = auth();
+        for ts in [i64::MIN, i64::MIN + 1, i64::MAX] {
+            let error = auth.verify_signed(&signed("agent", "agent-secret", ts, "n"), "POST", PATH, BODY, true).unwrap_err();
+            assert_eq!(error.code(), "stale_timestamp", "{}", ts);
+        }
+    }
+
+    #[test]
+    fn malformed_signature_headers_are_refused() {
+        for value in ["", "keyId=agent", "keyId=agent,ts=soon,nonce=n,sig=ab", "keyId=agent,ts=1,nonce=,sig=ab", "keyId agent"] {
+            assert!(matches!(SignatureHeader::parse(value), Err(AuthError::Malformed(_))), "{:?}", value);
+        }
+        assert_eq!(SignatureHeader::parse("keyId=a, ts=1, nonce=n, sig=AB").unwrap().signature, "ab");
+    }
+}
@@ -0,0 +1,385 @@
+//! Encrypted backups of the fuego home directory (~/.fuego): wallet, config and local stores in a
+//! single passphrase-protected archive. The archive starts with a plaintext header line carrying
+//! the format version and KDF parameters, so backups stay restorable after the format changes.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose;
+use base64::Engine;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// First bytes of every archive.
+const MAGIC: &str = "FUEGO-BACKUP";
+/// Archive container format: header fields, KDF and cipher.
+pub const FORMAT_VERSION: u32 = 1;
+/// Layout of the files under ~/.fuego; bump when a store's on-disk format changes incompatibly.
+pub const STORE_SCHEMA_VERSION: u32 = 1;
+pub const MIN_PASSPHRASE_LEN: usize = 12;
+
+/// Argon2id cost for new archives; older archives carry their own in the header.
+const KDF_MEMORY_KIB: u32 = 64 * 1024;
+const KDF_ITERATIONS: u32 = 3;
+const KDF_PARALLELISM: u32 = 1;
+
+/// Most an archive header may ask for. The header is read before the passphrase is known to be
+/// right, so without a ceiling a crafted archive could make `backup verify` exhaust memory.
+const MAX_KDF_MEMORY_KIB: u32 = 256 * 1024;
+const MAX_KDF_ITERATIONS: u32 = 10;
+const MAX_KDF_PARALLELISM: u32 = 4;
+
+#[derive(Serialize, Deserialize)]
+pub struct Kdf {
+    pub algorithm: String,
+    /// Base64 salt
+    pub salt: String,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+/// Plaintext header. It is also bound to the ciphertext as associated data, so editing it
+/// (e.g. to weaken the KDF) makes decryption fail.
+#[derive(Serialize, Deserialize)]
+pub struct Header {
+    pub magic: String,
+    pub format_version: u32,
+    pub created_at: i64,
+    pub fuego_version: String,
+    pub kdf: Kdf,
+    pub cipher: String,
+    /// Base64 nonce
+    pub nonce: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BackupFile {
+    /// Path relative to the home directory, '/'-separated
+    path: String,
+    sha256: String,
+    /// Base64 contents
+    data: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Contents {
+    store_schema_version: u32,
+    files: Vec<BackupFile>,
+}
+
+#[derive(Serialize)]
+pub struct Summary {
+    pub format_version: u32,
+    pub store_schema_version: u32,
+    pub created_at: i64,
+    pub fuego_version: String,
+    pub files: Vec<String>,
+    pub bytes: u64,
+    pub wallet_included: bool,
+}
+
+/// Argon2id at the current cost with a fresh salt, for anything new that stores a passphrase.
+pub fn new_kdf() -> Kdf {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    Kdf {
+        algorithm: "argon2id".to_string(),
+        salt: general_purpose::STANDARD.encode(salt),
+        memory_kib: KDF_MEMORY_KIB,
+        iterations: KDF_ITERATIONS,
+        parallelism: KDF_PARALLELISM,
+    }
+}
+
+pub fn derive_key(passphrase: &str, kdf: &Kdf) -> Result<Key<Aes256Gcm>, String> {
+    if kdf.algorithm != "argon2id" {
+        return Err(format!("Unsupported key derivation '{}'", kdf.algorithm));
+    }
+    if kdf.memory_kib > MAX_KDF_MEMORY_KIB || kdf.iterations > MAX_KDF_ITERATIONS || kdf.parallelism > MAX_KDF_PARALLELISM {
+        return Err(format!(
+            "Archive header asks for more key derivation work than allowed ({} KiB, {} iterations, {} lanes; at most {} KiB, {} and {})",
+            kdf.memory_kib, kdf.iterations, kdf.parallelism, MAX_KDF_MEMORY_KIB, MAX_KDF_ITERATIONS, MAX_KDF_PARALLELISM
+        ));
+    }
+    let salt = general_purpose::STANDARD
+        .decode(&kdf.salt)
+        .map_err(|_| "Archive header has an invalid salt".to_string())?;
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| format!("Archive header has invalid KDF parameters: {}", e))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Ok(Key::<Aes256Gcm>::from(key))
+}
+
+/// Every regular file under `dir`, recursively.
+fn collect_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+        let file_type = entry.file_type().map_err(|e| e.to_string())?;
+        if file_type.is_dir() {
+            collect_files(&path, out)?;
+        } else if file_type.is_file() {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Encrypt every file under `home` into one archive. Callers flush in-memory stores first.
+pub fn create(home: &Path, passphrase: &str) -> Result<(Vec<u8>, Summary), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!("Passphrase must be at least {} characters", MIN_PASSPHRASE_LEN));
+    }
+    let mut paths = Vec::new();
+    if home.is_dir() {
+        collect_files(home, &mut paths)?;
+    }
+    paths.sort();
+
+    let mut files = Vec::new();
+    let mut bytes = 0u64;
+    for path in &paths {
+        let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let relative = path
+            .strip_prefix(home)
+            .map_err(|_| format!("{} is outside {}", path.display(), home.display()))?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        bytes += data.len() as u64;
+        files.push(BackupFile {
+            path: relative,
+            sha256: format!("{:x}", Sha256::digest(&data)),
+            data: general_purpose::STANDARD.encode(&data),
+        });
+    }
+    let contents = Contents { store_schema_version: STORE_SCHEMA_VERSION, files };
+
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let header = Header {
+        magic: MAGIC.to_string(),
+        format_version: FORMAT_VERSION,
+        created_at: chrono::Utc::now().timestamp(),
+        fuego_version: crate::build_info::VERSION.to_string(),
+        kdf: new_kdf(),
+        cipher: "aes-256-gcm".to_string(),
+        nonce: general_purpose::STANDARD.encode(nonce),
+    };
+    let header_line = serde_json::to_string(&header).map_err(|e| e.to_string())?;
+
+    let key = derive_key(passphrase, &header.kdf)?;
+    let plaintext = serde_json::to_vec(&contents).map_err(|e| e.to_string())?;
+    let ciphertext = Aes256Gcm::new(&key)
+        .encrypt(&nonce, Payload { msg: &plaintext, aad: header_line.as_bytes() })
+        .map_err(|_| "Encryption failed".to_string())?;
+
+    let mut archive = header_line.into_bytes();
+    archive.push(b'\n');
+    archive.extend(ciphertext);
+    let summary = summarize(&header, &contents, bytes);
+    Ok((archive, summary))
+}
+
+fn summarize(header: &Header, contents: &Contents, bytes: u64) -> Summary {
+    Summary {
+        format_version: header.format_version,
+        store_schema_version: contents.store_schema_version,
+        created_at: header.created_at,
+        fuego_version: header.fuego_version.clone(),
+        files: contents.files.iter().map(|f| f.path.clone()).collect(),
+        bytes,
+        wallet_included: contents.files.iter().any(|f| f.path == "wallet.json"),
+    }
+}
+
+/// Relative path with only plain components, so a crafted archive can't write outside the home.
+fn safe_relative_path(path: &str) -> Option<PathBuf> {
+    let relative = PathBuf::from(path);
+    let plain = relative.components().all(|c| matches!(c, Component::Normal(_)));
+    (plain && !path.is_empty()).then_some(relative)
+}
+
+/// A decrypted archive with each file's checked contents.
+type Opened = (Header, Contents, Vec<(PathBuf, Vec<u8>)>);
+
+/// Decrypt and fully check an archive: header, passphrase, schema version and every file hash.
+fn open(archive: &[u8], passphrase: &str) -> Result<Opened, String> {
+    let newline = archive
+        .iter()
+        .position(|b| *b == b'\n')
+        .ok_or_else(|| "Not a fuego backup (no header)".to_string())?;
+    let header_line = &archive[..newline];
+    let header: Header =
+        serde_json::from_slice(header_line).map_err(|_| "Not a fuego backup (unreadable header)".to_string())?;
+    if header.magic != MAGIC {
+        return Err("Not a fuego backup".to_string());
+    }
+    if header.format_version > FORMAT_VERSION {
+        return Err(format!(
+            "Archive format version {} is newer than this fuego supports ({}); upgrade fuego to restore it",
+            header.format_version, FORMAT_VERSION
+        ));
+    }
+    if header.cipher != "aes-256-gcm" {
+        return Err(format!("Unsupported cipher '{}'", header.cipher));
+    }
+
+    let key = derive_key(passphrase, &header.kdf)?;
+    let nonce: [u8; 12] = general_purpose::STANDARD
+        .decode(&header.nonce)
+        .ok()
+        .and_then(|n| n.try_into().ok())
+        .ok_or_else(|| "Archive header has an invalid nonce".to_string())?;
+    let plaintext = Aes256Gcm::new(&key)
+        .decrypt(
+            &Nonce::from(nonce),
+            Payload { msg: &archive[newline + 1..], aad: header_line },
+        )
+        .map_err(|_| "Wrong passphrase, or the archive is corrupted".to_string())?;
+    let contents: Contents =
+        serde_json::from_slice(&plaintext).map_err(|_| "Archive contents are unreadable".to_string())?;
+    if contents.store_schema_version > STORE_SCHEMA_VERSION {
+        return Err(format!(
+            "Archive store schema version {} is newer than this fuego supports ({}); upgrade fuego to restore it",
+            contents.store_schema_version, STORE_SCHEMA_VERSION
+        ));
+    }
+
+    let mut files = Vec::new();
+    for file in &contents.files {
+        let path = safe_relative_path(&file.path).ok_or_else(|| format!("Archive has an unsafe path '{}'", file.path))?;
+        let data = general_purpose::STANDARD
+            .decode(&file.data)
+            .map_err(|_| format!("{} is not valid base64", file.path))?;
+        if format!("{:x}", Sha256::digest(&data)) != file.sha256 {
+            return Err(format!("{} failed its integrity check", file.path));
+        }
+        files.push((path, data));
+    }
+    Ok((header, contents, files))
+}
+
+/// Check an archive end to end without touching the filesystem.
+pub fn verify(archive: &[u8], passphrase: &str) -> Result<Summary, String> {
+    let (header, contents, files) = open(archive, passphrase)?;
+    let bytes = files.iter().map(|(_, data)| data.len() as u64).sum();
+    Ok(summarize(&header, &contents, bytes))
+}
+
+/// Restore into `home`, which must be missing or empty. Files are written to a staging directory
+/// next to it and renamed into place, so a failed restore leaves nothing half-written.
+pub fn restore(archive: &[u8], passphrase: &str, home: &Path) -> Result<Summary, String> {
+    let (header, contents, files) = open(archive, passphrase)?;
+    if home.exists() {
+        let empty = fs::read_dir(home)
+            .map_err(|e| format!("Failed to read {}: {}", home.display(), e))?
+            .next()
+            .is_none();
+        if !empty {
+            return Err(format!("{} is not empty; move it aside before restoring", home.display()));
+        }
+    }
+
+    let parent = home.parent().ok_or_else(|| "Home directory has no parent".to_string())?;
+    let staging = parent.join(format!(".fuego-restore-{}", uuid::Uuid::new_v4().simple()));
+    let written = write_staging(&staging, &files).and_then(|_| {
+        if home.exists() {
+            fs::remove_dir(home).map_err(|e| format!("Failed to replace {}: {}", home.display(), e))?;
+        }
+        fs::rename(&staging, home).map_err(|e| format!("Failed to move restore into place: {}", e))
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    let bytes = files.iter().map(|(_, data)| data.len() as u64).sum();
+    Ok(summarize(&header, &contents, bytes))
+}
+
+fn write_staging(staging: &Path, files: &[(PathBuf, Vec<u8>)]) -> Result<(), String> {
+    fs::create_dir_all(staging).map_err(|e| format!("Failed to create {}: {}", staging.display(), e))?;
+    for (relative, data) in files {
+        let path = staging.join(relative);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        fs::write(&path, data).map_err(|e| format!("Failed to write {}: {}", relative.display(), e))?;
+        // The wallet holds key material; keep every restored file owner-only
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = fs::set_permissions(&path, fs::Permissions::from_mode(0o600));
+        }
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(staging, fs::Permissions::from_mode(0o700));
+    }
+    Ok(())
+}
+
+/// Passphrase from FUEGO_BACKUP_PASSPHRASE, otherwise one line from stdin.
+fn read_passphrase() -> Result<String, String> {
+    if let Ok(passphrase) = std::env::var("FUEGO_BACKUP_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    eprint!("Backup passphrase: ");
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).map_err(|e| e.to_string())?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// `fuego-server restore <archive>` and `fuego-server backup verify <archive>`; returns the exit code.
+pub fn run_cli(args: &[String]) -> i32 {
+    let (command, archive_path) = match args {
+        [cmd, path] if cmd == "restore" => ("restore", path),
+        [cmd, sub, path] if cmd == "backup" && sub == "verify" => ("verify", path),
+        _ => {
+            eprintln!("Usage: fuego-server restore <archive> | fuego-server backup verify <archive>");
+            return 2;
+        }
+    };
+    let archive = match fs::read(archive_path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", archive_path, e);
+            return 1;
+        }
+    };
+    let passphrase = match read_passphrase() {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Failed to read passphrase: {}", e);
+            return 1;
+        }
+    };
+
+    let home = crate::utils::fuego_home();
+    let result = match command {
+        "restore" => restore(&archive, &passphrase, &home),
+        _ => verify(&archive, &passphrase),
+    };
+    match result {
+        Ok(summary) => {
+            let verb = if command == "restore" { "Restored" } else { "Verified" };
+            eprintln!("{} {} files ({} bytes)", verb, summary.files.len(), summary.bytes);
+            println!("{}", serde_json::to_string_pretty(&summary).unwrap_or_default());
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
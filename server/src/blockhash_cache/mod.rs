@@ -0,0 +1,106 @@
+//! Recent blockhashes per (RPC endpoint, commitment), reused for a few seconds so builds made
+//! back to back share one getLatestBlockhash call. A blockhash stays usable for ~150 slots (about a
+//! minute), so a cached one still leaves most of that window to sign and submit in.
+
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_response::{Response, RpcBlockhash};
+use solana_sdk::hash::Hash;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Blocks a blockhash stays usable for after the one it was fetched at (MAX_PROCESSING_AGE).
+const VALID_BLOCKS: u64 = 150;
+/// Target slot time; real slots run a little slower, so expiry estimates err early.
+const SLOT_MS: u64 = 400;
+
+/// Keyed by the endpoint the request resolved to, not the network name: two custom RPC URLs for
+/// the same cluster can be at different slots, and a localnet hash is useless to devnet.
+type Key = (String, String);
+
+/// Blockhash, its last valid block height, the slot it was fetched at, and when.
+type Entries = HashMap<Key, (Hash, u64, u64, Instant)>;
+
+#[derive(Clone, Copy)]
+pub struct RecentBlockhash {
+    pub blockhash: Hash,
+    pub last_valid_block_height: u64,
+    /// Slot the RPC answered at when the blockhash was fetched
+    pub slot: u64,
+    /// Served from the cache rather than fetched for this request
+    pub cached: bool,
+    /// How long ago the RPC returned it
+    pub age_ms: u64,
+}
+
+impl RecentBlockhash {
+    /// Rough seconds left to land a transaction built on this blockhash: ~150 blocks of 400ms
+    /// from when it was fetched, less how long ago that was.
+    pub fn expires_in_seconds(&self) -> u64 {
+        (VALID_BLOCKS * SLOT_MS).saturating_sub(self.age_ms) / 1000
+    }
+}
+
+#[derive(Clone)]
+pub struct BlockhashCache {
+    inner: Arc<Mutex<Entries>>,
+    ttl: Duration,
+}
+
+impl BlockhashCache {
+    /// TTL from FUEGO_BLOCKHASH_TTL_SECS (default 20, 0 disables caching).
+    pub fn from_env() -> Self {
+        let ttl_secs = std::env::var("FUEGO_BLOCKHASH_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(20);
+        BlockhashCache {
+            inner: Arc::new(Mutex::new(HashMap::new())),
+            ttl: Duration::from_secs(ttl_secs),
+        }
+    }
+
+    /// A blockhash for `rpc_url` at the client's commitment, cached unless `fresh` is set. A fresh
+    /// fetch always replaces the cached entry, so later builds get the newer hash too.
+    pub async fn get(&self, rpc: &RpcClient, rpc_url: &str, fresh: bool) -> ClientResult<RecentBlockhash> {
+        let commitment = rpc.commitment();
+        let key = (rpc_url.to_string(), format!("{:?}", commitment.commitment));
+        if !fresh && !self.ttl.is_zero() {
+            if let Some((blockhash, last_valid_block_height, slot, fetched)) = self.inner.lock().unwrap().get(&key) {
+                if fetched.elapsed() < self.ttl {
+                    return Ok(RecentBlockhash {
+                        blockhash: *blockhash,
+                        last_valid_block_height: *last_valid_block_height,
+                        slot: *slot,
+                        cached: true,
+                        age_ms: fetched.elapsed().as_millis() as u64,
+                    });
+                }
+            }
+        }
+
+        // getLatestBlockhash directly rather than get_latest_blockhash_with_commitment, which drops
+        // the context slot
+        let response: Response<RpcBlockhash> =
+            rpc.send(RpcRequest::GetLatestBlockhash, serde_json::json!([commitment])).await?;
+        let blockhash = Hash::from_str(&response.value.blockhash)
+            .map_err(|e| ClientError::from(ClientErrorKind::Custom(format!("Invalid blockhash from RPC: {}", e))))?;
+        let last_valid_block_height = response.value.last_valid_block_height;
+        let slot = response.context.slot;
+        if !self.ttl.is_zero() {
+            self.inner.lock().unwrap().insert(key, (blockhash, last_valid_block_height, slot, Instant::now()));
+        }
+        Ok(RecentBlockhash { blockhash, last_valid_block_height, slot, cached: false, age_ms: 0 })
+    }
+
+    /// Drop every cached entry; returns how many were cleared.
+    pub fn invalidate_all(&self) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        let count = inner.len();
+        inner.clear();
+        count
+    }
+}
@@ -0,0 +1,182 @@
+//! What this binary is and where its configuration comes from: build metadata embedded by
+//! build.rs, the commented default config printed by `--print-default-config`, and the
+//! embedded-vs-overridden report printed by `--doctor`.
+
+use crate::registry::{KNOWN_PROGRAMS, KNOWN_TOKENS};
+use crate::utils::fuego_home;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Short hash, with "-dirty" when tracked files had uncommitted changes
+pub const GIT_HASH: &str = env!("FUEGO_GIT_HASH");
+pub const GIT_COMMIT: &str = env!("FUEGO_GIT_COMMIT");
+const GIT_DIRTY: &str = env!("FUEGO_GIT_DIRTY");
+/// Enabled Cargo features, comma separated
+const FEATURES: &str = env!("FUEGO_FEATURES");
+pub const RUSTC_VERSION: &str = env!("FUEGO_RUSTC_VERSION");
+const BUILT_AT: &str = env!("FUEGO_BUILT_AT");
+
+/// Files under ~/.fuego that override an embedded default when present.
+const OVERRIDE_FILES: &[(&str, &str)] = &[("exchange_deposits", "exchange-deposits.json")];
+
+/// Every setting read from the environment, with its default ("" when unset means off).
+const SETTINGS: &[(&str, &str, &str)] = &[
+    ("FUEGO_CONFIG", "~/.fuego/server.json", "server config file; must exist when set"),
+    ("FUEGO_MODE", "live", "live or dry-run, anything else refuses to start; also mode in server.json"),
+    ("FUEGO_LISTEN", "127.0.0.1:8080", "address to listen on; non-loopback needs tls and an API key; also listen in server.json"),
+    ("FUEGO_DEFAULT_NETWORK", "mainnet-beta", "network for requests that omit one"),
+    ("FUEGO_REQUIRE_EXPLICIT_NETWORK", "false", "true refuses requests that omit the network"),
+    ("FUEGO_REQUIRE_SESSIONS", "false", "money-moving endpoints require X-Fuego-Session"),
+    ("FUEGO_SESSION_ADMIN_KEYS", "", "labels of keys that may create and revoke sessions, comma separated; also session_admin_keys in server.json"),
+    ("FUEGO_MEMO_PRIVACY", "full", "full or minimal"),
+    ("FUEGO_STRICT_JSON", "", "true rejects unknown request fields everywhere"),
+    ("FUEGO_MAX_BODY_BYTES", "65536", "largest request body accepted; also max_body_bytes in server.json"),
+    ("FUEGO_REQUEST_TIMEOUT_SECS", "30", "requests still running after this get a 504; also request_timeout_secs in server.json"),
+    ("FUEGO_SLOW_REQUEST_TIMEOUT_SECS", "120", "the same for history routes; also slow_request_timeout_secs in server.json"),
+    ("FUEGO_RPC_BREAKER_COOLDOWN_SECS", "30", "how long an endpoint's open circuit is skipped before a probe call"),
+    ("FUEGO_RPC_BREAKER_FAILURES", "3", "transport failures in a row that open an endpoint's circuit"),
+    ("FUEGO_RPC_ENDPOINTS", "", "mainnet-beta=https://a,https://b;devnet=https://c"),
+    ("FUEGO_RPC_STRATEGY", "primary", "primary, fastest or round-robin"),
+    ("FUEGO_RPC_TIMEOUT_MS", "10000", "how long each RPC call waits; requests can set timeout_ms"),
+    ("FUEGO_RPC_URL", "", "endpoint for networks FUEGO_RPC_ENDPOINTS doesn't list; {network} is substituted"),
+    ("FUEGO_COMMITMENT", "", "per-network read commitment, e.g. mainnet-beta=finalized"),
+    ("FUEGO_CONFIRMATION", "", "per-network submit commitment, e.g. mainnet-beta=finalized"),
+    ("FUEGO_ALERT_WEBHOOK_URL", "", "alerts are also POSTed here"),
+    ("FUEGO_ALERT_BUFFER", "100", "recent alerts kept for GET /alerts"),
+    ("FUEGO_EGRESS_ALLOW", "", "hosts, *.domains or CIDRs x402 and webhooks may reach"),
+    ("FUEGO_EGRESS_DENY", "", "hosts, *.domains or CIDRs they may never reach"),
+    ("FUEGO_EGRESS_ALLOW_PRIVATE", "false", "true allows private, loopback and link-local addresses"),
+    ("FUEGO_OTLP_ENDPOINT", "", "OTLP/HTTP collector spans are exported to; OTEL_EXPORTER_OTLP_ENDPOINT also works"),
+    ("OTEL_SERVICE_NAME", "fuego-server", "service.name on exported spans"),
+    ("FUEGO_TRACE_AMOUNTS", "true", "false leaves amount buckets off spans and request logs"),
+    ("FUEGO_LOG", "text", "text or json log lines"),
+    ("RUST_LOG", "info", "log filter"),
+    ("FUEGO_FEE_WALLET_FLOOR_SOL", "0.01", "low_balance warning threshold"),
+    ("FUEGO_FEE_WALLET_HARD_FLOOR_SOL", "", "builders refuse below this"),
+    ("FUEGO_ALLOW_KEY_EXPORT", "", "true enables POST /wallet/export and POST /admin/backup"),
+    ("FUEGO_KEY_EXPORT_PASSPHRASE", "", "passphrase for key-export setup; prompted when unset"),
+    ("FUEGO_BACKUP_PASSPHRASE", "", "passphrase for restore and backup verify; prompted when unset"),
+    ("FUEGO_BALANCE_AT_MAX_SCAN", "500", "transactions /balance-at walks back through"),
+    ("FUEGO_BALANCES_BATCH_MAX", "1000", "addresses allowed per /balances-batch request"),
+    ("FUEGO_SUBMIT_CONCURRENCY", "4", "queue workers for /submit-transactions"),
+    ("FUEGO_SUBMIT_DELAY_MS", "250", "minimum gap between queued submissions"),
+    ("FUEGO_RECONCILE_ON_START", "true", "false skips the startup reconciliation"),
+    ("FUEGO_UI", "true", "false removes the embedded dashboard at /ui"),
+    ("FUEGO_TX_CACHE_CAPACITY", "2000", "transactions kept in memory; 0 disables"),
+    ("FUEGO_TX_CACHE_TTL_SECS", "86400", "how long finalized transactions stay cached"),
+    ("FUEGO_BLOCKHASH_TTL_SECS", "20", "how long a fetched blockhash is reused; 0 disables"),
+    ("FUEGO_DEVNET_MINT", "", "test mint for /devnet/mint-tokens"),
+    ("FUEGO_DEVNET_MINT_AUTHORITY", "", "keypair file of the test mint authority"),
+    ("FUEGO_DEVNET_MINT_MAX", "1000", "per-request faucet cap"),
+    ("FUEGO_ANOMALY_DISABLE", "", "comma list of heuristics to switch off"),
+    ("FUEGO_ANOMALY_NEW_RECIPIENT", "SOL=1;USDC=100;USDT=100;PYUSD=100", "new-recipient thresholds"),
+    ("FUEGO_ANOMALY_BURST_COUNT", "5", "transfers allowed per burst window"),
+    ("FUEGO_ANOMALY_BURST_MINUTES", "10", "burst window"),
+    ("FUEGO_ANOMALY_FEE_MULTIPLIER", "10", "priority fee multiple of the recent median"),
+    ("FUEGO_AUTH_KEYS", "", "label=bearer:token or label=hmac:secret, ; separated"),
+    ("FUEGO_API_KEY", "", "a single bearer key, labelled default"),
+    ("FUEGO_AUTH_MAX_SKEW_SECS", "300", "allowed clock skew for signed requests"),
+    ("FUEGO_RATE_LIMIT_READ_PER_MIN", "60", "read requests per minute per client IP; 0 disables"),
+    ("FUEGO_RATE_LIMIT_WRITE_PER_MIN", "10", "build, submit and payment requests per minute per client IP; 0 disables"),
+    ("FUEGO_TRUST_PROXY", "false", "true takes the client IP from X-Forwarded-For"),
+    ("FUEGO_ONBOARD_SOL", "0.002", "SOL sent to each onboarded wallet by default"),
+    ("FUEGO_ONBOARD_DAILY_SOL", "0.1", "daily cap on SOL spent sponsoring onboarding"),
+    ("FUEGO_ONBOARD_DAILY_USDC", "0", "daily cap on welcome USDC; 0 disables it"),
+];
+
+fn built_at() -> String {
+    BUILT_AT
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+pub fn features() -> Vec<&'static str> {
+    FEATURES.split(',').filter(|f| !f.is_empty()).collect()
+}
+
+pub fn git_dirty() -> bool {
+    GIT_DIRTY == "true"
+}
+
+pub fn json() -> serde_json::Value {
+    serde_json::json!({
+        "version": VERSION,
+        "git_hash": GIT_HASH,
+        "git_commit": GIT_COMMIT,
+        "git_dirty": git_dirty(),
+        "built_at": built_at(),
+        "rustc": RUSTC_VERSION,
+        "features": features()
+    })
+}
+
+/// `fuego-server --version`, and the startup banner
+pub fn version_line() -> String {
+    let features = features();
+    let features = if features.is_empty() { "no optional features".to_string() } else { format!("features: {}", features.join(", ")) };
+    format!("fuego-server {} ({}, built {}, {}, {})", VERSION, GIT_HASH, built_at(), RUSTC_VERSION, features)
+}
+
+/// Which configuration actually applies: environment overrides (names only, never values),
+/// override files found under ~/.fuego, and the tables that are always embedded.
+pub fn config_sources() -> serde_json::Value {
+    let env_overrides: Vec<&str> = SETTINGS
+        .iter()
+        .map(|(name, _, _)| *name)
+        .filter(|name| std::env::var(name).is_ok())
+        .collect();
+    let describe = |path: std::path::PathBuf| {
+        let source = if path.exists() { "file" } else { "embedded" };
+        serde_json::json!({ "source": source, "path": path.display().to_string() })
+    };
+    let mut files: serde_json::Map<String, serde_json::Value> =
+        OVERRIDE_FILES.iter().map(|(key, file)| (key.to_string(), describe(fuego_home().join(file)))).collect();
+    files.insert("server_config".to_string(), describe(crate::config::config_path()));
+    serde_json::json!({
+        "env_overrides": env_overrides,
+        "env_defaults": SETTINGS.len() - env_overrides.len(),
+        "files": files,
+        "token_registry": { "source": "embedded", "count": KNOWN_TOKENS.len() },
+        "program_registry": { "source": "embedded", "count": KNOWN_PROGRAMS.len() }
+    })
+}
+
+fn toml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A commented TOML listing every setting with its default and the embedded registries.
+pub fn default_config_toml() -> String {
+    let mut out = String::new();
+    out.push_str(&format!("# fuego-server {} default configuration ({})\n", VERSION, GIT_HASH));
+    out.push_str("# Every value below is already built in. Settings are read from the environment variable\n");
+    out.push_str("# named in each comment; uncomment only what you want to change.\n\n");
+    out.push_str("[server]\n");
+    for (name, default, help) in SETTINGS {
+        let key = name.trim_start_matches("FUEGO_").to_lowercase();
+        out.push_str(&format!("# {} ({})\n", help, name));
+        out.push_str(&format!("# {} = {}\n\n", key, toml_string(default)));
+    }
+    out.push_str("# Embedded token registry\n");
+    for token in KNOWN_TOKENS {
+        out.push_str(&format!(
+            "# [[tokens]]\n# symbol = {}\n# mint = {}\n# decimals = {}\n# token_program = {}\n",
+            toml_string(token.symbol),
+            toml_string(token.mint),
+            token.decimals,
+            toml_string(token.token_program)
+        ));
+    }
+    out.push_str("\n# Embedded program classification\n");
+    for program in KNOWN_PROGRAMS {
+        out.push_str(&format!(
+            "# [[programs]]\n# name = {}\n# program_id = {}\n# kind = {}\n",
+            toml_string(program.name),
+            toml_string(program.program_id),
+            toml_string(program.kind)
+        ));
+    }
+    out
+}
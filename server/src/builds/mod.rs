@@ -0,0 +1,77 @@
+//! Short-lived record of built transfers so a rebuild can report what changed economically.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a build summary stays available for diffing.
+const BUILD_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Economic parameters of a built transfer. Field order is fixed, so the JSON (and its hash) is canonical.
+#[derive(Clone, Serialize)]
+pub struct BuildSummary {
+    pub token: String,
+    pub from: String,
+    pub to: String,
+    /// Transfer amount in base units
+    pub amount: u64,
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+    /// Lamports spent on rent (e.g. creating the recipient's token account)
+    pub rent_lamports: u64,
+    /// Accounts that receive value: the recipient, or its token account
+    pub destination_accounts: Vec<String>,
+}
+
+impl BuildSummary {
+    pub fn hash(&self) -> String {
+        let canonical = serde_json::to_vec(self).unwrap_or_default();
+        format!("{:x}", Sha256::digest(&canonical))
+    }
+
+    /// Fields whose values differ from `previous`, as {field, previous, current}.
+    pub fn diff(&self, previous: &BuildSummary) -> Vec<serde_json::Value> {
+        let before = serde_json::to_value(previous).unwrap_or_default();
+        let after = serde_json::to_value(self).unwrap_or_default();
+        let mut changes = Vec::new();
+        if let (Some(before), Some(after)) = (before.as_object(), after.as_object()) {
+            for (field, current) in after {
+                let prior = before.get(field).cloned().unwrap_or(serde_json::Value::Null);
+                if &prior != current {
+                    changes.push(serde_json::json!({
+                        "field": field,
+                        "previous": prior,
+                        "current": current
+                    }));
+                }
+            }
+        }
+        changes
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct BuildStore {
+    inner: Arc<Mutex<HashMap<String, (BuildSummary, Instant)>>>,
+}
+
+impl BuildStore {
+    pub fn insert(&self, summary: BuildSummary) -> String {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let mut builds = self.inner.lock().unwrap();
+        builds.retain(|_, (_, created)| created.elapsed() < BUILD_TTL);
+        builds.insert(id.clone(), (summary, Instant::now()));
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<BuildSummary> {
+        self.inner
+            .lock()
+            .unwrap()
+            .get(id)
+            .filter(|(_, created)| created.elapsed() < BUILD_TTL)
+            .map(|(summary, _)| summary.clone())
+    }
+}
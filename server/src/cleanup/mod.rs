@@ -0,0 +1,90 @@
+//! Reclaimable value in a wallet's token accounts: empty accounts whose rent can be recovered by
+//! closing them and, only when asked for, accounts holding dust or wrapped SOL. The accounts come
+//! from the same getTokenAccountsByOwner listing as /tokens; nothing here calls the RPC itself.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+pub const WRAPPED_SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// Dust threshold when the request doesn't set one: $1.00.
+pub const DEFAULT_DUST_THRESHOLD_CENTS: u64 = 100;
+
+/// Tokens worth a dollar each. fuego has no price feed, so only these can be valued as dust.
+const USD_PEGGED: &[&str] = &["USDC", "USDT", "PYUSD"];
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// Zero balance; closing returns the rent
+    EmptyAccount,
+    /// A pegged token worth less than the threshold; send it away, then close
+    Dust,
+    /// wSOL still wrapped; closing returns the wrapped SOL along with the rent
+    WrappedSol,
+}
+
+pub struct Options {
+    /// Suggest accounts that still hold something (dust, wrapped SOL). Off, only empty accounts are
+    /// suggested, so following every suggestion can never move a balance.
+    pub include_dust: bool,
+    pub dust_threshold_cents: u64,
+}
+
+/// One token account as listed for the wallet.
+pub struct Account<'a> {
+    pub mint: &'a str,
+    pub symbol: Option<&'a str>,
+    /// Base units
+    pub amount: &'a str,
+    pub decimals: u8,
+    /// Frozen accounts and Token-2022 accounts with withheld fees can't be closed
+    pub closable: bool,
+}
+
+/// What a token account could give back, or None when it should be left alone.
+pub fn classify(account: &Account, options: &Options) -> Option<Kind> {
+    if !account.closable {
+        return None;
+    }
+    let units: u128 = account.amount.parse().ok()?;
+    if units == 0 {
+        return Some(Kind::EmptyAccount);
+    }
+    if !options.include_dust {
+        return None;
+    }
+    if account.mint == WRAPPED_SOL_MINT {
+        return Some(Kind::WrappedSol);
+    }
+    // units / 10^decimals dollars is under threshold / 100 dollars
+    let pegged = account.symbol.map(|s| USD_PEGGED.contains(&s)).unwrap_or(false);
+    let scale = 10u128.checked_pow(account.decimals as u32)?;
+    let is_dust = units.checked_mul(100)? < (options.dust_threshold_cents as u128).checked_mul(scale)?;
+    (pegged && is_dust).then_some(Kind::Dust)
+}
+
+/// Whether a token that isn't empty was left out only because it can't be priced.
+pub fn unpriced(account: &Account) -> bool {
+    account.mint != WRAPPED_SOL_MINT && !account.symbol.map(|s| USD_PEGGED.contains(&s)).unwrap_or(false)
+}
+
+/// The builder call that carries a suggestion out, when fuego has one. Dust is swept to an address
+/// the caller picks with the account closed in the same transaction; closing an empty account and
+/// unwrapping wSOL have no builder yet.
+pub fn builder(kind: Kind, mint: &str, network: &str, address: &str) -> Option<Value> {
+    match kind {
+        Kind::Dust => Some(json!({
+            "endpoint": "/build-transfer-token",
+            "params": {
+                "network": network,
+                "from_address": address,
+                "mint": mint,
+                "amount": "max",
+                "close_account": true
+            },
+            "needs": ["to_address", "yid"]
+        })),
+        Kind::EmptyAccount | Kind::WrappedSol => None,
+    }
+}
@@ -0,0 +1,125 @@
+//! Default commitment per network, for reads (FUEGO_COMMITMENT) and for the submit/confirm target
+//! (FUEGO_CONFIRMATION). Precedence: the request's `commitment`, then the network default, then "confirmed".
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Used when neither the request nor the network config names a commitment.
+pub const BUILT_IN: &str = "confirmed";
+
+/// Canonical name of a commitment level, or None if it isn't one.
+pub fn canonical(value: &str) -> Option<&'static str> {
+    match value.trim() {
+        "processed" => Some("processed"),
+        "confirmed" => Some("confirmed"),
+        "finalized" => Some("finalized"),
+        _ => None,
+    }
+}
+
+/// Request value first, then the configured default, then the built-in. Unrecognized request values
+/// are ignored, as they always have been.
+pub fn resolve(requested: Option<&str>, configured: Option<&'static str>) -> &'static str {
+    requested.and_then(canonical).or(configured).unwrap_or(BUILT_IN)
+}
+
+/// Parse "mainnet-beta=finalized;devnet=processed" from an env var.
+fn per_network_from_env(name: &str) -> HashMap<String, &'static str> {
+    std::env::var(name).map(|spec| per_network(name, &spec)).unwrap_or_default()
+}
+
+/// The levels in one variable's value; malformed entries are skipped with a warning naming `name`.
+fn per_network(name: &str, spec: &str) -> HashMap<String, &'static str> {
+    let mut levels = HashMap::new();
+    for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let parsed = entry.split_once('=').and_then(|(network, level)| Some((network.trim(), canonical(level)?)));
+        match parsed.filter(|(network, _)| !network.is_empty()) {
+            Some((network, level)) => {
+                levels.insert(network.to_string(), level);
+            }
+            None => eprintln!("Ignoring malformed {} entry: {}", name, entry),
+        }
+    }
+    levels
+}
+
+#[derive(Clone, Default)]
+pub struct CommitmentDefaults {
+    reads: Arc<HashMap<String, &'static str>>,
+    confirmations: Arc<HashMap<String, &'static str>>,
+}
+
+impl CommitmentDefaults {
+    pub fn from_env() -> Self {
+        CommitmentDefaults {
+            reads: Arc::new(per_network_from_env("FUEGO_COMMITMENT")),
+            confirmations: Arc::new(per_network_from_env("FUEGO_CONFIRMATION")),
+        }
+    }
+
+    /// Effective commitment for an RPC read on this network.
+    pub fn read(&self, network: &str, requested: &Option<String>) -> &'static str {
+        resolve(requested.as_deref(), self.reads.get(network).copied())
+    }
+
+    /// Effective commitment a submission on this network is preflighted and confirmed at.
+    pub fn confirmation(&self, network: &str, requested: &Option<String>) -> &'static str {
+        resolve(requested.as_deref(), self.confirmations.get(network).copied())
+    }
+
+    /// Configured defaults, for diagnostics.
+    pub fn snapshot(&self) -> serde_json::Value {
+        serde_json::json!({
+            "built_in": BUILT_IN,
+            "reads": *self.reads,
+            "confirmations": *self.confirmations
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults(reads: &str, confirmations: &str) -> CommitmentDefaults {
+        CommitmentDefaults {
+            reads: Arc::new(per_network("FUEGO_COMMITMENT", reads)),
+            confirmations: Arc::new(per_network("FUEGO_CONFIRMATION", confirmations)),
+        }
+    }
+
+    #[test]
+    fn each_network_gets_its_own_level() {
+        let levels = per_network("FUEGO_COMMITMENT", " mainnet-beta = finalized ; devnet=processed;;my-validator=confirmed ");
+        assert_eq!(levels.len(), 3);
+        assert_eq!(levels["mainnet-beta"], "finalized");
+        assert_eq!(levels["devnet"], "processed");
+        assert_eq!(levels["my-validator"], "confirmed");
+    }
+
+    #[test]
+    fn malformed_entries_are_skipped_and_the_rest_kept() {
+        let levels = per_network("FUEGO_COMMITMENT", "devnet;testnet=final;mainnet-beta=finalized;=processed");
+        assert_eq!(levels.len(), 1);
+        assert_eq!(levels["mainnet-beta"], "finalized");
+        assert!(per_network("FUEGO_COMMITMENT", "").is_empty());
+    }
+
+    #[test]
+    fn the_request_beats_the_network_default_which_beats_confirmed() {
+        let defaults = defaults("mainnet-beta=finalized", "devnet=processed");
+        assert_eq!(defaults.read("mainnet-beta", &None), "finalized");
+        assert_eq!(defaults.read("mainnet-beta", &Some("processed".to_string())), "processed");
+        assert_eq!(defaults.read("devnet", &None), BUILT_IN);
+        assert_eq!(defaults.confirmation("devnet", &None), "processed");
+        assert_eq!(defaults.confirmation("mainnet-beta", &None), BUILT_IN);
+    }
+
+    #[test]
+    fn unrecognized_request_levels_fall_back_to_the_default() {
+        let defaults = defaults("mainnet-beta=finalized", "");
+        assert_eq!(defaults.read("mainnet-beta", &Some("max".to_string())), "finalized");
+        assert_eq!(defaults.read("devnet", &Some("recent".to_string())), BUILT_IN);
+        assert_eq!(defaults.read("devnet", &Some(" finalized ".to_string())), "finalized");
+    }
+}
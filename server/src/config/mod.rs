@@ -0,0 +1,278 @@
+//! The server's own settings file: ~/.fuego/server.json, or the path in FUEGO_CONFIG. It is read
+//! once at startup and checked before anything binds; a missing file means every built-in default,
+//! so a fresh install behaves exactly as before. Environment variables still win over the file,
+//! setting by setting. Only POST /admin/reload re-reads it, and only for `auth_keys` and
+//! `session_admin_keys`.
+
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::utils::fuego_home;
+
+/// Where the server listens without `listen` or FUEGO_LISTEN: loopback only.
+const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+
+/// The values `mode` accepts.
+const MODES: &[&str] = &["live", "dry-run"];
+
+/// Priority fee presets in micro-lamports per compute unit, for `fee_amount: "medium"` etc.
+const DEFAULT_FEE_PRESETS: &[(&str, u64)] = &[("low", 1_000), ("medium", 10_000), ("high", 100_000)];
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Any origin and any request header, as for local development; excludes `origins`
+    pub allow_any: bool,
+    /// Origins allowed to call the API from a browser; with neither this nor allow_any, none are
+    pub origins: Vec<String>,
+    /// Empty means GET and POST
+    pub methods: Vec<String>,
+    /// Empty means the headers fuego reads (see cors::DEFAULT_HEADERS); ignored with allow_any
+    pub headers: Vec<String>,
+}
+
+/// HTTPS instead of HTTP; both files are PEM, checked at startup by `tls::load`
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// Certificate chain, leaf first
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Address and port to listen on, "ip:port"; anything but loopback needs `tls` and an API key
+    pub listen: Option<String>,
+    /// "live" or "dry-run"; FUEGO_MODE wins
+    pub mode: Option<String>,
+    pub default_network: Option<String>,
+    pub require_explicit_network: Option<bool>,
+    /// network -> endpoints, primary first; FUEGO_RPC_ENDPOINTS replaces the whole table
+    pub rpc_endpoints: HashMap<String, Vec<String>>,
+    /// Endpoint for networks rpc_endpoints doesn't list ("{network}" is replaced)
+    pub rpc_url: Option<String>,
+    pub providers: HashMap<String, crate::providers::Entry>,
+    /// Transport failures in a row that open an endpoint's circuit
+    pub rpc_breaker_failures: Option<u32>,
+    /// How long an open circuit is skipped before a probe call
+    pub rpc_breaker_cooldown_secs: Option<u64>,
+    pub max_body_bytes: Option<usize>,
+    pub request_timeout_secs: Option<u64>,
+    pub slow_request_timeout_secs: Option<u64>,
+    pub rate_limit_read_per_min: Option<u32>,
+    pub rate_limit_write_per_min: Option<u32>,
+    pub trust_proxy: Option<bool>,
+    pub cors: CorsConfig,
+    pub tls: Option<TlsConfig>,
+    /// name -> micro-lamports per compute unit; replaces the built-in presets
+    pub fee_presets: Option<BTreeMap<String, u64>>,
+    /// label -> "bearer:token" or "hmac:secret"; secret, never returned by GET /config
+    pub auth_keys: HashMap<String, String>,
+    /// Labels of auth keys that may create and revoke sessions; FUEGO_SESSION_ADMIN_KEYS wins
+    pub session_admin_keys: Vec<String>,
+}
+
+/// FUEGO_CONFIG when set, else ~/.fuego/server.json.
+pub fn config_path() -> PathBuf {
+    explicit_path().unwrap_or_else(|| fuego_home().join("server.json"))
+}
+
+fn explicit_path() -> Option<PathBuf> {
+    std::env::var("FUEGO_CONFIG").ok().map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).map(PathBuf::from)
+}
+
+/// An origin is scheme://host[:port] with nothing after it.
+fn is_origin(value: &str) -> bool {
+    // Origins match exactly, so a wildcard host would silently match nothing
+    if value.contains('*') {
+        return false;
+    }
+    match reqwest::Url::parse(value) {
+        Ok(url) => {
+            matches!(url.scheme(), "http" | "https")
+                && url.origin().ascii_serialization() == value.trim_end_matches('/')
+        }
+        Err(_) => false,
+    }
+}
+
+impl ServerConfig {
+    /// The file at `config_path()`, parsed and checked. A missing default file is every default;
+    /// a missing FUEGO_CONFIG file is an error, since that path was asked for.
+    pub fn load() -> Result<Self, String> {
+        let path = config_path();
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && explicit_path().is_none() => {
+                return Ok(ServerConfig::default())
+            }
+            Err(e) => return Err(format!("Can't read {}: {}", path.display(), e)),
+        };
+        let config: ServerConfig =
+            serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let problems = config.problems();
+        if !problems.is_empty() {
+            return Err(format!("{}: {}", path.display(), problems.join("; ")));
+        }
+        Ok(config)
+    }
+
+    /// Every field that parsed but can't be used, as "field: what's wrong". Secrets and URLs are
+    /// never echoed, since either may carry an API key.
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Some(listen) = &self.listen {
+            if listen.trim().parse::<SocketAddr>().is_err() {
+                problems.push(format!("listen: '{}' is not an address like 0.0.0.0:8443", listen));
+            }
+        }
+        if let Some(mode) = &self.mode {
+            if !MODES.contains(&mode.as_str()) {
+                problems.push(format!("mode: '{}' is not \"live\" or \"dry-run\"", mode));
+            }
+        }
+        if self.default_network.as_deref().map(|n| n.trim().is_empty()).unwrap_or(false) {
+            problems.push("default_network is empty; leave it out to use the built-in default".to_string());
+        }
+
+        let mut networks: Vec<&String> = self.rpc_endpoints.keys().collect();
+        networks.sort();
+        for network in networks {
+            let urls = &self.rpc_endpoints[network];
+            if urls.is_empty() {
+                problems.push(format!("rpc_endpoints.{} lists no URLs", network));
+            }
+            for (i, url) in urls.iter().enumerate() {
+                if let Err(e) = crate::rpc::validate_url(url) {
+                    problems.push(format!("rpc_endpoints.{}[{}]: {}", network, i, e));
+                }
+            }
+        }
+        if let Some(Err(e)) = self.rpc_url.as_deref().map(crate::rpc::validate_url) {
+            problems.push(format!("rpc_url: {}", e));
+        }
+        let mut names: Vec<&String> = self.providers.keys().collect();
+        names.sort();
+        for name in names {
+            if let Err(e) = crate::rpc::validate_url(self.providers[name].url()) {
+                problems.push(format!("providers.{}: {}", name, e));
+            }
+        }
+
+        if self.rpc_breaker_failures == Some(0) {
+            problems.push("rpc_breaker_failures must be at least 1".to_string());
+        }
+        if self.rpc_breaker_cooldown_secs == Some(0) {
+            problems.push("rpc_breaker_cooldown_secs must be at least 1".to_string());
+        }
+
+        if self.max_body_bytes == Some(0) {
+            problems.push("max_body_bytes must be greater than 0".to_string());
+        }
+        if self.request_timeout_secs == Some(0) {
+            problems.push("request_timeout_secs must be at least 1".to_string());
+        }
+        if let Some(slow) = self.slow_request_timeout_secs {
+            let normal = self.request_timeout_secs.unwrap_or(crate::limits::DEFAULT_TIMEOUT_SECS);
+            if slow < normal {
+                problems.push(format!(
+                    "slow_request_timeout_secs ({}) must be at least request_timeout_secs ({})",
+                    slow, normal
+                ));
+            }
+        }
+
+        if self.cors.allow_any && !self.cors.origins.is_empty() {
+            problems.push("cors.allow_any and cors.origins can't both be set; pick one".to_string());
+        }
+        for (i, origin) in self.cors.origins.iter().enumerate() {
+            if !is_origin(origin) {
+                problems.push(format!("cors.origins[{}]: '{}' is not an origin like https://app.example.com", i, origin));
+            }
+        }
+        for (i, method) in self.cors.methods.iter().enumerate() {
+            if axum::http::Method::from_bytes(method.trim().to_uppercase().as_bytes()).is_err() {
+                problems.push(format!("cors.methods[{}]: '{}' is not an HTTP method", i, method));
+            }
+        }
+        for (i, header) in self.cors.headers.iter().enumerate() {
+            if axum::http::HeaderName::from_bytes(header.trim().as_bytes()).is_err() {
+                problems.push(format!("cors.headers[{}]: '{}' is not a header name", i, header));
+            }
+        }
+        if let Some(tls) = &self.tls {
+            if tls.cert_path.as_os_str().is_empty() || tls.key_path.as_os_str().is_empty() {
+                problems.push("tls needs both cert_path and key_path; leave tls out to serve plain HTTP".to_string());
+            }
+        }
+        for name in self.fee_presets.iter().flat_map(|presets| presets.keys()) {
+            if name.trim().is_empty() || name.parse::<u64>().is_ok() {
+                problems.push(format!("fee_presets: '{}' can't be a preset name; use a word like \"fast\"", name));
+            }
+        }
+        let mut labels: Vec<&String> = self.auth_keys.keys().collect();
+        labels.sort();
+        for label in labels {
+            if crate::auth::parse_key(label, &self.auth_keys[label]).is_none() {
+                problems.push(format!("auth_keys.{}: expected \"bearer:token\" or \"hmac:secret\"", label));
+            }
+        }
+        if self.session_admin_keys.iter().any(|label| label.trim().is_empty()) {
+            problems.push("session_admin_keys lists an empty label".to_string());
+        }
+        problems
+    }
+
+    /// FUEGO_LISTEN, else `listen`, else 127.0.0.1:8080.
+    pub fn listen_addr(&self) -> Result<SocketAddr, String> {
+        match std::env::var("FUEGO_LISTEN").ok().filter(|v| !v.trim().is_empty()) {
+            Some(value) => value
+                .trim()
+                .parse()
+                .map_err(|_| format!("FUEGO_LISTEN '{}' is not an address like 0.0.0.0:8443", value)),
+            // Checked when the config loaded
+            None => Ok(self.listen.as_deref().unwrap_or(DEFAULT_LISTEN).trim().parse().unwrap_or_else(|_| DEFAULT_LISTEN.parse().unwrap())),
+        }
+    }
+
+    /// The presets `fee_amount` may name: the file's, else the built-in ones.
+    pub fn fee_presets(&self) -> BTreeMap<String, u64> {
+        match &self.fee_presets {
+            Some(presets) => presets.clone(),
+            None => DEFAULT_FEE_PRESETS.iter().map(|(name, fee)| (name.to_string(), *fee)).collect(),
+        }
+    }
+
+    pub fn fee_preset(&self, name: &str) -> Option<u64> {
+        self.fee_presets().get(name).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cors(config: CorsConfig) -> Vec<String> {
+        ServerConfig { cors: config, ..Default::default() }.problems()
+    }
+
+    #[test]
+    fn cors_origins_must_be_real_origins() {
+        let origins = |list: &[&str]| CorsConfig { origins: list.iter().map(|o| o.to_string()).collect(), ..Default::default() };
+        assert!(cors(origins(&["https://app.example.com", "http://localhost:3000/"])).is_empty());
+        for origin in ["*", "https://*.example.com", "app.example.com", "https://app.example.com/path", "null"] {
+            assert_eq!(cors(origins(&[origin])).len(), 1, "{}", origin);
+        }
+    }
+
+    #[test]
+    fn cors_wildcard_is_only_allow_any_and_never_with_origins() {
+        assert!(cors(CorsConfig { allow_any: true, ..Default::default() }).is_empty());
+        let both = CorsConfig { allow_any: true, origins: vec!["https://app.example.com".to_string()], ..Default::default() };
+        assert_eq!(cors(both), vec!["cors.allow_any and cors.origins can't both be set; pick one".to_string()]);
+    }
+}
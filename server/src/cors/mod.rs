@@ -0,0 +1,186 @@
+//! Which browser origins may call the API. The server can sign and pay from its hot wallet, so by
+//! default no other origin may: cross-origin calls need `cors.origins` in the server config, or
+//! `cors.allow_any` for local development. A preflight from an origin that isn't allowed is
+//! refused with a 403 instead of being answered without CORS headers.
+
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer};
+
+use crate::config::CorsConfig;
+
+const DEFAULT_METHODS: &[Method] = &[Method::GET, Method::POST];
+
+/// Request headers fuego reads, allowed when the config doesn't list its own.
+pub const DEFAULT_HEADERS: &[&str] = &[
+    "content-type",
+    "authorization",
+    crate::sessions::SESSION_HEADER,
+    crate::auth::SIGNATURE_HEADER,
+    crate::strict_json::STRICT_HEADER,
+    crate::request_id::HEADER,
+    crate::trace::TRACEPARENT,
+    crate::trace::TRACESTATE,
+];
+
+/// Response headers a page may read.
+const EXPOSED_HEADERS: &[&str] = &[crate::request_id::HEADER, crate::trace::TRACEPARENT];
+
+#[derive(Clone)]
+pub struct CorsPolicy {
+    allow_any: bool,
+    origins: Vec<HeaderValue>,
+    methods: Vec<Method>,
+    headers: Vec<HeaderName>,
+}
+
+impl CorsPolicy {
+    /// From the `cors` section, whose entries were checked when the config loaded.
+    pub fn load(config: &CorsConfig) -> Self {
+        let methods: Vec<Method> = config
+            .methods
+            .iter()
+            .filter_map(|m| Method::from_bytes(m.trim().to_uppercase().as_bytes()).ok())
+            .collect();
+        let headers: Vec<HeaderName> = config.headers.iter().filter_map(|h| HeaderName::from_bytes(h.trim().as_bytes()).ok()).collect();
+        CorsPolicy {
+            allow_any: config.allow_any,
+            origins: config.origins.iter().filter_map(|o| o.trim_end_matches('/').parse().ok()).collect(),
+            methods: if methods.is_empty() { DEFAULT_METHODS.to_vec() } else { methods },
+            headers: if headers.is_empty() {
+                DEFAULT_HEADERS.iter().map(|h| HeaderName::from_static(h)).collect()
+            } else {
+                headers
+            },
+        }
+    }
+
+    pub fn allows_origin(&self, origin: &HeaderValue) -> bool {
+        self.allow_any || self.origins.contains(origin)
+    }
+
+    /// The Origin of a preflight this policy refuses, if `method` and `headers` are one.
+    pub fn refused_preflight<'a>(&self, method: &Method, headers: &'a HeaderMap) -> Option<&'a HeaderValue> {
+        if method != Method::OPTIONS || !headers.contains_key(header::ACCESS_CONTROL_REQUEST_METHOD) {
+            return None;
+        }
+        headers.get(header::ORIGIN).filter(|origin| !self.allows_origin(origin))
+    }
+
+    pub fn layer(&self) -> CorsLayer {
+        let layer = CorsLayer::new()
+            .allow_methods(self.methods.clone())
+            .expose_headers(EXPOSED_HEADERS.iter().map(|h| HeaderName::from_static(h)).collect::<Vec<_>>());
+        if self.allow_any {
+            layer.allow_origin(AllowOrigin::any()).allow_headers(AllowHeaders::any())
+        } else {
+            layer.allow_origin(AllowOrigin::list(self.origins.clone())).allow_headers(self.headers.clone())
+        }
+    }
+
+    /// The effective policy, for GET /config.
+    pub fn describe(&self) -> serde_json::Value {
+        let text = |v: &HeaderValue| v.to_str().unwrap_or_default().to_string();
+        serde_json::json!({
+            "allow_any": self.allow_any,
+            "origins": self.origins.iter().map(text).collect::<Vec<_>>(),
+            "methods": self.methods.iter().map(Method::as_str).collect::<Vec<_>>(),
+            "headers": if self.allow_any { vec!["*"] } else { self.headers.iter().map(HeaderName::as_str).collect() }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    const APP: &str = "https://app.example.com";
+    const OTHER: &str = "https://evil.example.com";
+
+    fn policy(config: CorsConfig) -> CorsPolicy {
+        CorsPolicy::load(&config)
+    }
+
+    fn listing(origins: &[&str]) -> CorsPolicy {
+        policy(CorsConfig { origins: origins.iter().map(|o| o.to_string()).collect(), ..Default::default() })
+    }
+
+    fn preflight(origin: &str) -> Request<Body> {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/balances")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    async fn through_layer(policy: &CorsPolicy, request: Request<Body>) -> axum::response::Response {
+        let app = Router::new().route("/balances", get(|| async { "ok" }).post(|| async { "ok" })).layer(policy.layer());
+        app.oneshot(request).await.unwrap()
+    }
+
+    #[test]
+    fn only_listed_origins_are_allowed() {
+        let policy = listing(&["https://app.example.com/"]);
+        assert!(policy.allows_origin(&HeaderValue::from_static(APP)));
+        assert!(!policy.allows_origin(&HeaderValue::from_static(OTHER)));
+        assert!(!policy.allows_origin(&HeaderValue::from_static("http://app.example.com")));
+        assert!(!listing(&[]).allows_origin(&HeaderValue::from_static(APP)));
+    }
+
+    #[test]
+    fn only_preflights_from_other_origins_are_refused() {
+        let policy = listing(&[APP]);
+        assert!(policy.refused_preflight(&Method::OPTIONS, preflight(APP).headers()).is_none());
+        assert_eq!(policy.refused_preflight(&Method::OPTIONS, preflight(OTHER).headers()).unwrap(), OTHER);
+
+        // A plain OPTIONS, or a same-origin call without an Origin, is no preflight
+        let mut plain = HeaderMap::new();
+        plain.insert(header::ORIGIN, HeaderValue::from_static(OTHER));
+        assert!(policy.refused_preflight(&Method::OPTIONS, &plain).is_none());
+        assert!(policy.refused_preflight(&Method::POST, preflight(OTHER).headers()).is_none());
+    }
+
+    #[tokio::test]
+    async fn an_allowed_preflight_is_answered() {
+        let response = through_layer(&listing(&[APP]), preflight(APP)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], APP);
+        assert!(headers[header::ACCESS_CONTROL_ALLOW_METHODS].to_str().unwrap().contains("POST"));
+        assert!(headers[header::ACCESS_CONTROL_ALLOW_HEADERS].to_str().unwrap().contains("content-type"));
+    }
+
+    #[tokio::test]
+    async fn other_origins_get_no_cors_headers() {
+        let response = through_layer(&listing(&[APP]), preflight(OTHER)).await;
+        assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+        let request = Request::builder().uri("/balances").header(header::ORIGIN, OTHER).body(Body::empty()).unwrap();
+        let response = through_layer(&listing(&[APP]), request).await;
+        assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+
+    #[tokio::test]
+    async fn allow_any_never_allows_credentials() {
+        let policy = policy(CorsConfig { allow_any: true, ..Default::default() });
+        assert!(policy.refused_preflight(&Method::OPTIONS, preflight(OTHER).headers()).is_none());
+
+        let request = Request::builder()
+            .uri("/balances")
+            .header(header::ORIGIN, OTHER)
+            .header(header::AUTHORIZATION, "Bearer key")
+            .body(Body::empty())
+            .unwrap();
+        let response = through_layer(&policy, request).await;
+        // Browsers refuse to hand a credentialed response to the page under `*`
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+    }
+}
@@ -0,0 +1,436 @@
+//! Egress policy for outbound HTTP the server makes on a caller's or operator's behalf (x402
+//! targets, alert webhooks). Hosts and CIDR ranges can be allowed or denied; destinations that
+//! resolve to private, loopback or link-local addresses are refused unless explicitly allowed.
+//! A checked destination is pinned to the addresses that passed, so DNS can't be re-resolved to
+//! an internal IP afterwards, and redirects may not leave the checked host.
+
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use crate::utils::fuego_home;
+
+/// Most redirects followed on a pinned client (reqwest's default).
+const MAX_REDIRECTS: usize = 10;
+
+#[derive(Clone, Copy, Debug)]
+struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// "10.0.0.0/8", "fd00::/8", or a bare address (a /32 or /128).
+    fn parse(value: &str) -> Option<Self> {
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok()?)),
+            None => (value, None),
+        };
+        let network: IpAddr = addr.parse().ok()?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Cidr { network, prefix })
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.network, canonical(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// IPv4-mapped IPv6 addresses (::ffff:10.0.0.1) are checked as the IPv4 address they carry.
+fn canonical(ip: &IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(*ip),
+        IpAddr::V4(_) => *ip,
+    }
+}
+
+/// Addresses that never belong to a public service: private, loopback, link-local (including cloud
+/// metadata at 169.254.169.254), carrier-grade NAT, unique-local and unspecified.
+fn is_internal(ip: &IpAddr) -> bool {
+    match canonical(ip) {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+        }
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            v6.is_loopback() || v6.is_unspecified() || (first & 0xfe00) == 0xfc00 || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// Host pattern: "example.com" matches only that host, "*.example.com" also matches subdomains.
+fn host_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => host == suffix || host.ends_with(&format!(".{}", suffix)),
+        None => host == pattern,
+    }
+}
+
+#[derive(Default)]
+struct Rules {
+    hosts: Vec<String>,
+    cidrs: Vec<Cidr>,
+}
+
+impl Rules {
+    /// Comma-separated hosts, "*.domain" patterns and CIDR ranges.
+    fn parse(value: &str) -> Self {
+        let mut rules = Rules::default();
+        for entry in value.split(',').map(|e| e.trim().to_lowercase()).filter(|e| !e.is_empty()) {
+            match Cidr::parse(&entry) {
+                Some(cidr) => rules.cidrs.push(cidr),
+                None => rules.hosts.push(entry),
+            }
+        }
+        rules
+    }
+
+    fn is_empty(&self) -> bool {
+        self.hosts.is_empty() && self.cidrs.is_empty()
+    }
+
+    fn matches_host(&self, host: &str) -> bool {
+        self.hosts.iter().any(|p| host_matches(p, host))
+    }
+
+    fn matches_ip(&self, ip: &IpAddr) -> bool {
+        self.cidrs.iter().any(|c| c.contains(ip))
+    }
+}
+
+#[derive(Debug)]
+pub enum EgressError {
+    /// The policy refused the destination
+    Blocked(String),
+    InvalidUrl(String),
+    /// The host didn't resolve, so it couldn't be checked
+    Resolve(String),
+}
+
+impl EgressError {
+    pub fn message(&self) -> String {
+        match self {
+            EgressError::Blocked(reason) => format!("Outbound request blocked by egress policy: {}", reason),
+            EgressError::InvalidUrl(e) => format!("Invalid URL: {}", e),
+            EgressError::Resolve(e) => format!("Failed to resolve destination: {}", e),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            EgressError::Blocked(_) => "egress_blocked",
+            EgressError::InvalidUrl(_) => "invalid_url",
+            EgressError::Resolve(_) => "egress_unresolved",
+        }
+    }
+}
+
+/// Raised from the redirect policy; found again in a failed request's error chain by `is_violation`.
+#[derive(Debug)]
+struct RedirectBlocked(String);
+
+impl std::fmt::Display for RedirectBlocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "redirect blocked by egress policy: {}", self.0)
+    }
+}
+
+impl std::error::Error for RedirectBlocked {}
+
+/// Whether a failed request was stopped by the egress policy (a redirect off the checked host).
+pub fn is_violation(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(error);
+    while let Some(e) = current {
+        if e.downcast_ref::<RedirectBlocked>().is_some() {
+            return true;
+        }
+        current = e.source();
+    }
+    false
+}
+
+/// A destination that passed the policy, with the addresses it may be reached at.
+pub struct Destination {
+    pub url: reqwest::Url,
+    host: String,
+    addrs: Vec<SocketAddr>,
+}
+
+struct PolicyInner {
+    allow: Rules,
+    deny: Rules,
+    allow_private: bool,
+}
+
+#[derive(Clone)]
+pub struct EgressPolicy {
+    inner: Arc<PolicyInner>,
+}
+
+impl EgressPolicy {
+    /// Rules from FUEGO_EGRESS_ALLOW and FUEGO_EGRESS_DENY; FUEGO_EGRESS_ALLOW_PRIVATE=true permits
+    /// internal addresses. With no allowlist every public destination is allowed.
+    pub fn from_env() -> Self {
+        let rules = |name: &str| std::env::var(name).map(|v| Rules::parse(&v)).unwrap_or_default();
+        EgressPolicy {
+            inner: Arc::new(PolicyInner {
+                allow: rules("FUEGO_EGRESS_ALLOW"),
+                deny: rules("FUEGO_EGRESS_DENY"),
+                allow_private: std::env::var("FUEGO_EGRESS_ALLOW_PRIVATE").map(|v| v == "true").unwrap_or(false),
+            }),
+        }
+    }
+
+    /// Host-level decision, before any address is known.
+    fn check_host(&self, host: &str) -> Result<(), String> {
+        if self.inner.deny.matches_host(host) {
+            return Err(format!("{} is denied", host));
+        }
+        Ok(())
+    }
+
+    /// Address-level decision for a host that resolved (or is written as) `ip`.
+    fn check_ip(&self, host: &str, ip: &IpAddr) -> Result<(), String> {
+        let policy = &self.inner;
+        if policy.deny.matches_ip(ip) {
+            return Err(format!("{} resolves to denied address {}", host, ip));
+        }
+        let explicitly_allowed = policy.allow.matches_ip(ip);
+        if is_internal(ip) && !policy.allow_private && !explicitly_allowed {
+            return Err(format!("{} resolves to internal address {}", host, ip));
+        }
+        if !policy.allow.is_empty() && !policy.allow.matches_host(host) && !explicitly_allowed {
+            return Err(format!("{} is not on the egress allowlist", host));
+        }
+        Ok(())
+    }
+
+    /// Resolve `url` and check every address it resolves to. Violations are audit-logged.
+    pub async fn check(&self, url: &str, purpose: &str) -> Result<Destination, EgressError> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| EgressError::InvalidUrl(e.to_string()))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(EgressError::InvalidUrl(format!("unsupported scheme '{}'", parsed.scheme())));
+        }
+        let host = parsed
+            .host_str()
+            .map(|h| h.trim_start_matches('[').trim_end_matches(']').to_lowercase())
+            .ok_or_else(|| EgressError::InvalidUrl("URL has no host".to_string()))?;
+        let port = parsed.port_or_known_default().unwrap_or(443);
+
+        let addrs: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+            Ok(ip) => vec![SocketAddr::new(ip, port)],
+            Err(_) => {
+                if let Err(reason) = self.check_host(&host) {
+                    audit(purpose, url, &reason);
+                    return Err(EgressError::Blocked(reason));
+                }
+                tokio::net::lookup_host((host.as_str(), port))
+                    .await
+                    .map_err(|e| EgressError::Resolve(e.to_string()))?
+                    .collect()
+            }
+        };
+        if addrs.is_empty() {
+            return Err(EgressError::Resolve(format!("{} has no addresses", host)));
+        }
+        // Every address must pass: the client may connect to any of them
+        for addr in &addrs {
+            if let Err(reason) = self.check_ip(&host, &addr.ip()) {
+                audit(purpose, url, &reason);
+                return Err(EgressError::Blocked(reason));
+            }
+        }
+        Ok(Destination { url: parsed, host, addrs })
+    }
+
+    /// Client builder pinned to the checked addresses, whose redirects must stay on the same host.
+    pub fn client_builder(&self, destination: &Destination) -> reqwest::ClientBuilder {
+        let host = destination.host.clone();
+        let redirects = reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error(RedirectBlocked("too many redirects".to_string()));
+            }
+            let target = attempt.url().host_str().map(|h| h.trim_start_matches('[').trim_end_matches(']').to_lowercase());
+            if target.as_deref() != Some(host.as_str()) {
+                let reason = format!("redirect from {} to {}", host, attempt.url());
+                audit("redirect", attempt.url().as_str(), &reason);
+                return attempt.error(RedirectBlocked(reason));
+            }
+            attempt.follow()
+        });
+        // Outbound x402, webhook and per-request RPC calls join the trace of the request that made them
+        let builder = reqwest::Client::builder()
+            .redirect(redirects)
+            .default_headers(crate::trace::outbound_headers());
+        // IP-literal hosts need no pinning; a hostname only ever connects to the addresses checked
+        if destination.host.parse::<IpAddr>().is_ok() {
+            builder
+        } else {
+            builder.resolve_to_addrs(&destination.host, &destination.addrs)
+        }
+    }
+
+    pub fn describe(&self) -> serde_json::Value {
+        let policy = &self.inner;
+        serde_json::json!({
+            "allowlist": !policy.allow.is_empty(),
+            "allow_hosts": policy.allow.hosts,
+            "allow_cidrs": policy.allow.cidrs.iter().map(|c| format!("{}/{}", c.network, c.prefix)).collect::<Vec<_>>(),
+            "deny_hosts": policy.deny.hosts,
+            "deny_cidrs": policy.deny.cidrs.iter().map(|c| format!("{}/{}", c.network, c.prefix)).collect::<Vec<_>>(),
+            "allow_private": policy.allow_private
+        })
+    }
+}
+
+/// Append a refused destination to ~/.fuego/egress-audit.jsonl (and stderr).
+fn audit(purpose: &str, url: &str, reason: &str) {
+    let line = serde_json::json!({
+        "timestamp": chrono::Utc::now().timestamp(),
+        "purpose": purpose,
+        "url": crate::rpc::redact_url(url),
+        "reason": reason
+    })
+    .to_string();
+    eprintln!("AUDIT egress-blocked: {}", line);
+    // Tests refuse destinations on purpose; that doesn't belong in the developer's own audit log
+    if cfg!(test) {
+        return;
+    }
+    let result = std::fs::create_dir_all(fuego_home()).and_then(|_| {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(fuego_home().join("egress-audit.jsonl"))
+            .and_then(|mut f| writeln!(f, "{}", line))
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to write egress audit log: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allow: &str, deny: &str, allow_private: bool) -> EgressPolicy {
+        EgressPolicy { inner: Arc::new(PolicyInner { allow: Rules::parse(allow), deny: Rules::parse(deny), allow_private }) }
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn internal_addresses_are_recognized() {
+        let internal = [
+            "10.1.2.3", "172.16.0.1", "172.31.255.255", "192.168.1.1", "127.0.0.1", "169.254.169.254", "100.64.0.1",
+            "100.127.255.255", "0.0.0.0", "0.1.2.3", "255.255.255.255", "::1", "::", "fc00::1", "fd12:3456::1", "fe80::1",
+        ];
+        for addr in internal {
+            assert!(is_internal(&ip(addr)), "{} should be internal", addr);
+        }
+        for addr in ["8.8.8.8", "172.32.0.1", "100.128.0.1", "169.255.0.1", "2606:4700:4700::1111", "fec0::1"] {
+            assert!(!is_internal(&ip(addr)), "{} should be public", addr);
+        }
+    }
+
+    #[test]
+    fn cidrs_parse_and_match() {
+        let ten = Cidr::parse("10.0.0.0/8").unwrap();
+        assert!(ten.contains(&ip("10.255.1.1")));
+        assert!(!ten.contains(&ip("11.0.0.1")));
+        assert!(!ten.contains(&ip("fd00::1")));
+        let single = Cidr::parse("203.0.113.7").unwrap();
+        assert_eq!(single.prefix, 32);
+        assert!(single.contains(&ip("203.0.113.7")) && !single.contains(&ip("203.0.113.8")));
+        let everything = Cidr::parse("0.0.0.0/0").unwrap();
+        assert!(everything.contains(&ip("8.8.8.8")) && !everything.contains(&ip("2606:4700::1")));
+        let ula = Cidr::parse("fd00::/8").unwrap();
+        assert!(ula.contains(&ip("fd12::1")) && !ula.contains(&ip("fc00::1")));
+        for invalid in ["10.0.0.0/33", "fd00::/129", "10.0.0.0/x", "10.0.0/8", "api.example.com", ""] {
+            assert!(Cidr::parse(invalid).is_none(), "{} should not parse", invalid);
+        }
+        let rules = Rules::parse(" API.example.com , 10.0.0.0/8,, *.Example.org ");
+        assert_eq!(rules.hosts, ["api.example.com", "*.example.org"]);
+        assert_eq!(rules.cidrs.len(), 1);
+    }
+
+    #[test]
+    fn deny_beats_allow() {
+        let policy = policy("*.example.com,203.0.113.0/24,10.0.0.0/8", "bad.example.com,203.0.113.7,10.9.0.0/16", false);
+        assert!(policy.check_host("bad.example.com").is_err());
+        assert!(policy.check_ip("api.example.com", &ip("203.0.113.7")).is_err());
+        assert!(policy.check_ip("api.example.com", &ip("10.9.1.1")).is_err());
+        assert!(policy.check_ip("api.example.com", &ip("203.0.113.8")).is_ok());
+        // An allowed range permits an internal address; an unlisted host is refused
+        assert!(policy.check_ip("internal.example.net", &ip("10.1.1.1")).is_ok());
+        assert!(policy.check_ip("other.net", &ip("8.8.8.8")).is_err());
+        assert!(policy.check_ip("example.com", &ip("8.8.8.8")).is_ok());
+    }
+
+    #[test]
+    fn ipv4_mapped_ipv6_is_checked_as_ipv4() {
+        assert!(is_internal(&ip("::ffff:127.0.0.1")));
+        assert!(is_internal(&ip("::ffff:169.254.169.254")));
+        assert!(!is_internal(&ip("::ffff:8.8.8.8")));
+        assert!(Cidr::parse("10.0.0.0/8").unwrap().contains(&ip("::ffff:10.1.2.3")));
+        let policy = policy("", "8.8.8.8", false);
+        assert!(policy.check_ip("dns.example", &ip("::ffff:8.8.8.8")).is_err());
+        assert!(policy.check_ip("local.example", &ip("::ffff:192.168.0.1")).is_err());
+    }
+
+    #[tokio::test]
+    async fn internal_destinations_are_refused_unless_allowed() {
+        let strict = policy("", "", false);
+        for url in ["http://169.254.169.254/latest/meta-data/", "http://127.0.0.1:8899", "http://[::ffff:10.0.0.1]/", "http://[::1]:8080/"] {
+            assert!(matches!(strict.check(url, "test").await, Err(EgressError::Blocked(_))), "{}", url);
+        }
+        assert!(matches!(strict.check("ftp://example.com/", "test").await, Err(EgressError::InvalidUrl(_))));
+        assert!(policy("", "", true).check("http://127.0.0.1:8899", "test").await.is_ok());
+        assert!(policy("127.0.0.0/8", "", false).check("http://127.0.0.1:8899", "test").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn redirects_may_not_leave_the_checked_host() {
+        use axum::response::Redirect;
+        use axum::routing::get;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let app = axum::Router::new()
+            .route("/same", get(|| async { Redirect::temporary("/landed") }))
+            .route("/landed", get(|| async { "ok" }))
+            .route("/away", get(move || async move { Redirect::temporary(&format!("http://localhost:{}/landed", port)) }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let policy = policy("127.0.0.0/8", "", false);
+        let destination = policy.check(&format!("http://127.0.0.1:{}/same", port), "test").await.unwrap();
+        let client = policy.client_builder(&destination).build().unwrap();
+        let landed = client.get(destination.url.clone()).send().await.unwrap();
+        assert_eq!(landed.text().await.unwrap(), "ok");
+
+        let destination = policy.check(&format!("http://127.0.0.1:{}/away", port), "test").await.unwrap();
+        let client = policy.client_builder(&destination).build().unwrap();
+        let err = client.get(destination.url.clone()).send().await.unwrap_err();
+        assert!(is_violation(&err), "{}", err);
+    }
+}
@@ -0,0 +1,51 @@
+//! Known exchange deposit addresses, read from ~/.fuego/exchange-deposits.json on every build so the
+//! registry can be updated without a restart. Example entry:
+//! `{"name": "ExampleX", "addresses": ["9xQe...", "ExX*"], "memo_required": true, "uncredited_tokens": ["USDT"]}`
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::utils::fuego_home;
+
+/// Longest deposit memo we will attach; exchange tags are short ids.
+pub const MAX_EXCHANGE_MEMO_BYTES: usize = 200;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExchangeDeposit {
+    pub name: String,
+    /// Exact deposit addresses, or prefixes ending in '*'
+    pub addresses: Vec<String>,
+    #[serde(default)]
+    pub memo_required: bool,
+    /// Token symbols this exchange does not credit when sent to these addresses
+    #[serde(default)]
+    pub uncredited_tokens: Vec<String>,
+}
+
+impl ExchangeDeposit {
+    fn matches(&self, address: &str) -> bool {
+        self.addresses.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => !prefix.is_empty() && address.starts_with(prefix),
+            None => pattern == address,
+        })
+    }
+}
+
+fn registry_path() -> PathBuf {
+    fuego_home().join("exchange-deposits.json")
+}
+
+pub fn load_registry() -> Result<Vec<ExchangeDeposit>, String> {
+    let path = registry_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid exchange-deposits.json: {}", e))
+}
+
+/// The exchange a destination belongs to, if any.
+pub fn match_destination(address: &str) -> Result<Option<ExchangeDeposit>, String> {
+    Ok(load_registry()?.into_iter().find(|e| e.matches(address)))
+}
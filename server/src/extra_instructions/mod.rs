@@ -0,0 +1,102 @@
+//! Caller-supplied instructions appended to a fuego-built transfer (tips, program pings, exchange memos).
+
+use base64::engine::general_purpose;
+use base64::Engine;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::utils::string_to_pub_key;
+
+pub use fuego_types::{ExtraInstruction, ExtraPosition};
+
+/// Largest serialized transaction the cluster accepts (PACKET_DATA_SIZE).
+pub const MAX_TRANSACTION_BYTES: usize = 1232;
+
+/// Upper bound on extra instructions per build; the size limit usually bites first.
+const MAX_EXTRA_INSTRUCTIONS: usize = 8;
+
+/// fuego sets the compute budget itself; a second set of budget instructions fails the transaction.
+const COMPUTE_BUDGET_PROGRAM: &str = "ComputeBudget111111111111111111111111111111";
+
+/// Validated extra instructions, split by where they go around the memo.
+#[derive(Default)]
+pub struct ExtraInstructions {
+    pub before_memo: Vec<Instruction>,
+    pub after_memo: Vec<Instruction>,
+    /// Program ids in request order, for the response
+    pub programs: Vec<String>,
+}
+
+impl ExtraInstructions {
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+
+    /// Response block marking that the transaction carries caller-supplied instructions.
+    pub fn describe(&self) -> serde_json::Value {
+        serde_json::json!({
+            "present": !self.is_empty(),
+            "count": self.programs.len(),
+            "programs": self.programs,
+            "program_names": self.programs.iter().map(|p| crate::registry::program(p).map(|k| k.name)).collect::<Vec<_>>(),
+            "warning": if self.is_empty() {
+                serde_json::Value::Null
+            } else {
+                serde_json::Value::String("Transaction includes custom instructions that fuego did not build; review them before signing.".to_string())
+            }
+        })
+    }
+
+    /// Assemble the final instruction list: budget, transfer(s), extras before memo, memo, extras after memo.
+    pub fn assemble(&self, mut head: Vec<Instruction>, memo: Instruction) -> Vec<Instruction> {
+        head.extend(self.before_memo.iter().cloned());
+        head.push(memo);
+        head.extend(self.after_memo.iter().cloned());
+        head
+    }
+}
+
+/// Decode and validate extra instructions. Only `signers` (the accounts that will sign the
+/// transaction anyway) may be marked as signers.
+pub fn parse(extras: &[ExtraInstruction], signers: &[Pubkey]) -> Result<ExtraInstructions, String> {
+    if extras.len() > MAX_EXTRA_INSTRUCTIONS {
+        return Err(format!("At most {} extra_instructions are allowed", MAX_EXTRA_INSTRUCTIONS));
+    }
+
+    let mut parsed = ExtraInstructions::default();
+    for (i, extra) in extras.iter().enumerate() {
+        if extra.program_id == COMPUTE_BUDGET_PROGRAM {
+            return Err(format!("extra_instructions[{}]: compute budget is set by fuego (use fee_amount)", i));
+        }
+        let program_id = string_to_pub_key(&extra.program_id)
+            .map_err(|_| format!("extra_instructions[{}]: invalid program_id", i))?;
+        let data = general_purpose::STANDARD
+            .decode(&extra.data)
+            .map_err(|_| format!("extra_instructions[{}]: data is not valid base64", i))?;
+
+        let mut accounts = Vec::with_capacity(extra.accounts.len());
+        for account in &extra.accounts {
+            let pubkey = string_to_pub_key(&account.pubkey)
+                .map_err(|_| format!("extra_instructions[{}]: invalid account {}", i, account.pubkey))?;
+            if account.signer && !signers.contains(&pubkey) {
+                return Err(format!(
+                    "extra_instructions[{}]: {} would need to sign, but only the sender signs this transaction",
+                    i, account.pubkey
+                ));
+            }
+            accounts.push(AccountMeta {
+                pubkey,
+                is_signer: account.signer,
+                is_writable: account.writable,
+            });
+        }
+
+        let instruction = Instruction { program_id, accounts, data };
+        match extra.position {
+            ExtraPosition::BeforeMemo => parsed.before_memo.push(instruction),
+            ExtraPosition::AfterMemo => parsed.after_memo.push(instruction),
+        }
+        parsed.programs.push(extra.program_id.clone());
+    }
+    Ok(parsed)
+}
@@ -0,0 +1,259 @@
+//! Devnet test-token faucet: mints a team-controlled test mint to any address for QA.
+//! Configured with FUEGO_DEVNET_MINT, FUEGO_DEVNET_MINT_AUTHORITY and FUEGO_DEVNET_MINT_MAX.
+
+use serde::Serialize;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::utils::{from_spl_pubkey, fuego_home, instruction_from_spl, to_spl_pubkey};
+
+/// Networks the faucet will ever mint on. "localnet" needs a FUEGO_RPC_ENDPOINTS entry.
+const FAUCET_NETWORKS: &[&str] = &["devnet", "localnet"];
+
+#[derive(Clone)]
+pub struct DevnetFaucet {
+    pub mint: String,
+    /// Solana CLI keypair file (JSON array of 64 bytes) holding the mint authority
+    pub authority_path: PathBuf,
+    /// Per-request cap as a UI amount
+    pub max_amount: String,
+}
+
+impl DevnetFaucet {
+    /// None unless both the mint and its authority keypair are configured.
+    pub fn from_env() -> Option<Self> {
+        let mint = std::env::var("FUEGO_DEVNET_MINT").ok().filter(|v| !v.is_empty())?;
+        let authority_path = std::env::var("FUEGO_DEVNET_MINT_AUTHORITY").ok().filter(|v| !v.is_empty())?;
+        let max_amount = std::env::var("FUEGO_DEVNET_MINT_MAX")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "1000".to_string());
+        Some(DevnetFaucet {
+            mint,
+            authority_path: PathBuf::from(authority_path),
+            max_amount,
+        })
+    }
+
+    /// First 32 bytes (the secret) of the authority keypair file.
+    pub fn load_authority_secret(&self) -> Result<[u8; 32], String> {
+        let content = fs::read_to_string(&self.authority_path)
+            .map_err(|e| format!("Failed to read {}: {}", self.authority_path.display(), e))?;
+        let bytes: Vec<u8> = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid mint authority keypair file: {}", e))?;
+        if bytes.len() < 32 {
+            return Err("Mint authority keypair must be at least 32 bytes".to_string());
+        }
+        let mut secret = [0u8; 32];
+        secret.copy_from_slice(&bytes[..32]);
+        Ok(secret)
+    }
+}
+
+/// Genesis hashes of the public clusters. A cluster's genesis hash is its identity; an RPC URL
+/// says nothing reliable about where it points.
+pub const MAINNET_GENESIS: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+pub const TESTNET_GENESIS: &str = "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY";
+pub const DEVNET_GENESIS: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
+
+/// Refuse a network the faucet never mints on, before any RPC call.
+pub fn check_network_name(network: &str) -> Result<(), String> {
+    if !FAUCET_NETWORKS.contains(&network) {
+        return Err(format!("Test-token minting is only available on devnet (got '{}')", network));
+    }
+    Ok(())
+}
+
+/// Refuse an endpoint whose genesis hash isn't the cluster `network` names: devnet must be
+/// devnet, and localnet must be none of the public clusters.
+pub fn check_genesis(network: &str, endpoint: &str, genesis_hash: &str) -> Result<(), String> {
+    check_network_name(network)?;
+    let public = [MAINNET_GENESIS, TESTNET_GENESIS, DEVNET_GENESIS].contains(&genesis_hash);
+    let ok = match network {
+        "devnet" => genesis_hash == DEVNET_GENESIS,
+        _ => !public,
+    };
+    if ok {
+        return Ok(());
+    }
+    let cluster = match genesis_hash {
+        MAINNET_GENESIS => "mainnet-beta",
+        TESTNET_GENESIS => "testnet",
+        DEVNET_GENESIS => "devnet",
+        _ => "an unknown cluster",
+    };
+    Err(format!(
+        "Refusing to mint: the {} endpoint {} is on {} (genesis {})",
+        network,
+        crate::rpc::redact_url(endpoint),
+        cluster,
+        genesis_hash
+    ))
+}
+
+/// Create the recipient's token account if it's missing, then mint `amount` base units to it.
+/// Returns the instructions and the token account.
+pub fn mint_instructions(
+    authority: &Pubkey,
+    mint: &Pubkey,
+    to: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Result<(Vec<Instruction>, Pubkey), String> {
+    let authority = to_spl_pubkey(authority);
+    let mint = to_spl_pubkey(mint);
+    let to = to_spl_pubkey(to);
+    let token_account = get_associated_token_address(&to, &mint);
+    let create_ata = create_associated_token_account_idempotent(&authority, &to, &mint, &spl_token::ID);
+    let mint_to = spl_token::instruction::mint_to_checked(&spl_token::ID, &mint, &token_account, &authority, &[], amount, decimals)
+        .map_err(|e| format!("Failed to create mint instruction: {}", e))?;
+    Ok((vec![instruction_from_spl(&create_ata), instruction_from_spl(&mint_to)], from_spl_pubkey(&token_account)))
+}
+
+#[derive(Serialize)]
+pub struct MintAudit<'a> {
+    pub timestamp: i64,
+    pub network: &'a str,
+    pub mint: &'a str,
+    pub to: &'a str,
+    pub amount: &'a str,
+    pub outcome: &'a str,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Append one line to ~/.fuego/devnet-mint-audit.jsonl. Every attempt past validation is logged.
+pub fn audit(entry: &MintAudit) {
+    let line = match serde_json::to_string(entry) {
+        Ok(l) => l,
+        Err(_) => return,
+    };
+    eprintln!("AUDIT devnet-mint: {}", line);
+    let result = fs::create_dir_all(fuego_home()).and_then(|_| {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(fuego_home().join("devnet-mint-audit.jsonl"))
+            .and_then(|mut f| writeln!(f, "{}", line))
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to write devnet mint audit log: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    #[test]
+    fn only_test_networks_are_accepted() {
+        assert!(check_network_name("devnet").is_ok());
+        assert!(check_network_name("localnet").is_ok());
+        for network in ["mainnet-beta", "testnet", "mainnet", ""] {
+            assert!(check_network_name(network).is_err(), "{}", network);
+            assert!(check_genesis(network, "http://x", DEVNET_GENESIS).is_err(), "{}", network);
+        }
+    }
+
+    #[test]
+    fn devnet_endpoints_must_be_on_devnet() {
+        assert!(check_genesis("devnet", "https://api.devnet.solana.com", DEVNET_GENESIS).is_ok());
+        for genesis in [MAINNET_GENESIS, TESTNET_GENESIS, "11111111111111111111111111111111"] {
+            assert!(check_genesis("devnet", "https://rpc.example", genesis).is_err(), "{}", genesis);
+        }
+    }
+
+    #[test]
+    fn localnet_endpoints_must_not_be_a_public_cluster() {
+        assert!(check_genesis("localnet", "http://127.0.0.1:8899", "11111111111111111111111111111111").is_ok());
+        for genesis in [MAINNET_GENESIS, TESTNET_GENESIS, DEVNET_GENESIS] {
+            assert!(check_genesis("localnet", "http://127.0.0.1:8899", genesis).is_err(), "{}", genesis);
+        }
+    }
+
+    #[test]
+    fn the_refusal_names_the_cluster_and_hides_the_endpoint_key() {
+        let e = check_genesis("devnet", "https://rpc.example/?api-key=secret", MAINNET_GENESIS).unwrap_err();
+        assert!(e.contains("mainnet-beta"), "{}", e);
+        assert!(!e.contains("secret"), "{}", e);
+    }
+
+    #[test]
+    fn mints_into_the_recipients_token_account() {
+        let (authority, mint, to) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (instructions, token_account) = mint_instructions(&authority, &mint, &to, 250, 6).unwrap();
+        assert_eq!(token_account, from_spl_pubkey(&get_associated_token_address(&to_spl_pubkey(&to), &to_spl_pubkey(&mint))));
+        assert_eq!(instructions.len(), 2);
+        let mint_to = &instructions[1];
+        assert_eq!(mint_to.program_id, from_spl_pubkey(&spl_token::ID));
+        assert_eq!(mint_to.accounts[1].pubkey, token_account);
+        assert_eq!(mint_to.accounts[2].pubkey, authority);
+        assert!(mint_to.accounts[2].is_signer);
+    }
+
+    /// Needs a running `solana-test-validator`: `cargo test -- --ignored`, with
+    /// FUEGO_TEST_VALIDATOR_URL when it isn't at http://127.0.0.1:8899. Creates its own mint.
+    #[test]
+    #[ignore]
+    fn mints_on_a_local_validator() {
+        use solana_client::rpc_config::CommitmentConfig;
+        use spl_token::solana_program::program_pack::Pack;
+
+        let url = std::env::var("FUEGO_TEST_VALIDATOR_URL").unwrap_or_else(|_| "http://127.0.0.1:8899".to_string());
+        let rpc = crate::rpc::client(&url, CommitmentConfig::confirmed());
+        let genesis_hash = rpc.get_genesis_hash().expect("no validator at FUEGO_TEST_VALIDATOR_URL").to_string();
+        check_genesis("localnet", &url, &genesis_hash).unwrap();
+        assert!(check_genesis("devnet", &url, &genesis_hash).is_err());
+
+        let authority = Keypair::new();
+        let airdrop = rpc.request_airdrop(&authority.pubkey(), 1_000_000_000).unwrap();
+        let funded = (0..60).any(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(500));
+            rpc.confirm_transaction(&airdrop).unwrap_or(false)
+        });
+        assert!(funded, "airdrop never confirmed");
+
+        let send = |instructions: &[Instruction], signers: &[&Keypair]| {
+            let blockhash = rpc.get_latest_blockhash().unwrap();
+            let transaction = solana_sdk::transaction::Transaction::new_signed_with_payer(
+                instructions,
+                Some(&authority.pubkey()),
+                signers,
+                blockhash,
+            );
+            let transaction: solana_transaction::Transaction =
+                bincode::deserialize(&bincode::serialize(&transaction).unwrap()).unwrap();
+            rpc.send_and_confirm_transaction(&transaction).unwrap();
+        };
+
+        let mint = Keypair::new();
+        let rent = rpc.get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN).unwrap();
+        let create_mint = solana_system_interface::instruction::create_account(
+            &authority.pubkey(),
+            &mint.pubkey(),
+            rent,
+            spl_token::state::Mint::LEN as u64,
+            &from_spl_pubkey(&spl_token::ID),
+        );
+        let initialize_mint =
+            spl_token::instruction::initialize_mint2(&spl_token::ID, &to_spl_pubkey(&mint.pubkey()), &to_spl_pubkey(&authority.pubkey()), None, 6)
+                .unwrap();
+        send(&[create_mint, instruction_from_spl(&initialize_mint)], &[&authority, &mint]);
+
+        let recipient = Pubkey::new_unique();
+        let (instructions, token_account) = mint_instructions(&authority.pubkey(), &mint.pubkey(), &recipient, 250_000_000, 6).unwrap();
+        send(&instructions, &[&authority]);
+        // Idempotent: a second mint reuses the token account
+        send(&instructions, &[&authority]);
+
+        let balance = rpc.get_token_account_balance(&token_account).unwrap();
+        assert_eq!(balance.amount, "500000000");
+        assert_eq!(balance.decimals, 6);
+    }
+}
@@ -0,0 +1,326 @@
+//! Fault injection for resilience testing, compiled only with `--features fault-injection`.
+//! Delays or fails a share of RPC calls per method, corrupts the Nth RPC response and drops
+//! alert webhook deliveries. Configured through /admin/faults; DELETE resets everything, so each
+//! test can start from a clean state. Without the feature neither this module nor the endpoint
+//! exists, so production builds cannot enable it.
+
+#[cfg(not(debug_assertions))]
+compile_error!("the fault-injection feature is for test builds only; build without --release");
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_rpc_client::http_sender::HttpSender;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// One rule for RPC calls. Rules are checked in order and the first whose method matches applies.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RpcFault {
+    /// JSON-RPC method name (e.g. "getBalance"); "*" or absent matches every method
+    #[serde(default)]
+    pub method: Option<String>,
+    /// Share of matching calls affected, 0-100
+    pub percent: u8,
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Fail the affected calls after the delay instead of sending them
+    #[serde(default)]
+    pub fail: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct FaultConfig {
+    #[serde(default)]
+    pub rpc: Vec<RpcFault>,
+    /// Replace the result of the Nth RPC call (1-based, counted from when this config was set)
+    #[serde(default)]
+    pub corrupt_nth_response: Option<u64>,
+    #[serde(default)]
+    pub drop_webhooks: bool,
+}
+
+#[derive(Default, Serialize)]
+struct Counters {
+    rpc_calls: u64,
+    delayed: u64,
+    failed: u64,
+    corrupted: u64,
+    webhooks_dropped: u64,
+    /// Calls seen per rule, so a percentage fires on an exact, repeatable schedule
+    #[serde(skip)]
+    per_rule: Vec<u64>,
+}
+
+#[derive(Default)]
+struct Faults {
+    config: FaultConfig,
+    counters: Counters,
+}
+
+static FAULTS: Mutex<Option<Faults>> = Mutex::new(None);
+
+enum Action {
+    Pass,
+    Delay(Duration),
+    Fail(Duration),
+}
+
+/// Replace the active configuration; counters start again from zero.
+pub fn set(config: FaultConfig) {
+    let per_rule = vec![0; config.rpc.len()];
+    *FAULTS.lock().unwrap() = Some(Faults {
+        config,
+        counters: Counters { per_rule, ..Default::default() },
+    });
+}
+
+/// Back to no faults at all.
+pub fn reset() {
+    *FAULTS.lock().unwrap() = None;
+}
+
+pub fn snapshot() -> serde_json::Value {
+    match FAULTS.lock().unwrap().as_ref() {
+        Some(faults) => serde_json::json!({
+            "active": true,
+            "config": faults.config,
+            "counters": faults.counters
+        }),
+        None => serde_json::json!({ "active": false }),
+    }
+}
+
+/// Whether to drop this webhook delivery.
+pub fn drop_webhook() -> bool {
+    let mut guard = FAULTS.lock().unwrap();
+    match guard.as_mut() {
+        Some(faults) if faults.config.drop_webhooks => {
+            faults.counters.webhooks_dropped += 1;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Fires on exactly `percent` of every 100 calls: call n is hit when floor(n * p / 100) steps up.
+fn hits(call: u64, percent: u8) -> bool {
+    let p = percent.min(100) as u64;
+    call * p / 100 > (call - 1) * p / 100
+}
+
+/// Decide what happens to one RPC call; also reports whether its response is the one to corrupt.
+fn on_rpc_call(method: &str) -> (Action, bool) {
+    let mut guard = FAULTS.lock().unwrap();
+    let faults = match guard.as_mut() {
+        Some(faults) => faults,
+        None => return (Action::Pass, false),
+    };
+    faults.counters.rpc_calls += 1;
+    let corrupt = faults.config.corrupt_nth_response == Some(faults.counters.rpc_calls);
+
+    let rule = faults
+        .config
+        .rpc
+        .iter()
+        .position(|r| r.method.as_deref().is_none_or(|m| m == "*" || m == method));
+    let action = match rule {
+        Some(index) => {
+            faults.counters.per_rule[index] += 1;
+            let rule = &faults.config.rpc[index];
+            if !hits(faults.counters.per_rule[index], rule.percent) {
+                Action::Pass
+            } else if rule.fail {
+                faults.counters.failed += 1;
+                Action::Fail(Duration::from_millis(rule.delay_ms))
+            } else {
+                faults.counters.delayed += 1;
+                Action::Delay(Duration::from_millis(rule.delay_ms))
+            }
+        }
+        None => Action::Pass,
+    };
+    if corrupt {
+        faults.counters.corrupted += 1;
+    }
+    (action, corrupt)
+}
+
+/// A real transport with the configured faults applied in front of it.
+pub struct FaultySender {
+    inner: Box<dyn RpcSender + Send + Sync>,
+}
+
+#[async_trait]
+impl RpcSender for FaultySender {
+    async fn send(&self, request: RpcRequest, params: serde_json::Value) -> ClientResult<serde_json::Value> {
+        let method = request.to_string();
+        let (action, corrupt) = on_rpc_call(&method);
+        match action {
+            Action::Pass => {}
+            Action::Delay(delay) => tokio::time::sleep(delay).await,
+            // Shaped like a transport failure, so failover treats it as one
+            Action::Fail(delay) => {
+                tokio::time::sleep(delay).await;
+                return Err(ClientError::from(ClientErrorKind::Io(std::io::Error::other(format!(
+                    "injected fault: {} failed",
+                    method
+                )))));
+            }
+        }
+        let response = self.inner.send(request, params).await?;
+        if corrupt {
+            return Ok(serde_json::json!({ "injected_fault": "corrupted response" }));
+        }
+        Ok(response)
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+pub fn sender(url: String, timeout: Duration) -> FaultySender {
+    wrap(Box::new(HttpSender::new_with_timeout(url, timeout)))
+}
+
+/// Apply the faults to another transport, such as a request's pinned one.
+pub fn wrap(inner: Box<dyn RpcSender + Send + Sync>) -> FaultySender {
+    FaultySender { inner }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::{RpcGaveUp, RpcPool};
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use std::sync::Arc;
+
+    /// The fault config is process-wide, so tests that set it take turns.
+    static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    /// A JSON-RPC endpoint on an ephemeral port that answers every call with `result`, except the
+    /// client's own getVersion check.
+    async fn endpoint(result: serde_json::Value) -> String {
+        use axum::routing::post;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = axum::Router::new().route(
+            "/",
+            post(move |axum::Json(call): axum::Json<serde_json::Value>| async move {
+                let result = match call["method"].as_str() {
+                    Some("getVersion") => serde_json::json!({ "solana-core": "3.1.9", "feature-set": 0 }),
+                    _ => result,
+                };
+                axum::Json(serde_json::json!({ "jsonrpc": "2.0", "id": call["id"], "result": result }))
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    fn pool(endpoints: &[String]) -> RpcPool {
+        let mut config = crate::config::ServerConfig::default();
+        config.rpc_endpoints.insert("devnet".to_string(), endpoints.to_vec());
+        RpcPool::load(&config)
+    }
+
+    fn rule(method: &str, percent: u8, delay_ms: u64, fail: bool) -> FaultConfig {
+        FaultConfig {
+            rpc: vec![RpcFault { method: Some(method.to_string()), percent, delay_ms, fail }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn percentages_fire_on_an_exact_schedule() {
+        assert_eq!((1..=100).filter(|&n| hits(n, 25)).count(), 25);
+        assert_eq!((1..=4).map(|n| hits(n, 50)).collect::<Vec<_>>(), vec![false, true, false, true]);
+        assert!((1..=10).all(|n| hits(n, 100)));
+        assert!((1..=10).all(|n| !hits(n, 0)));
+    }
+
+    #[tokio::test]
+    async fn an_injected_failure_fails_over_to_the_next_endpoint() {
+        let _serial = SERIAL.lock().await;
+        let (primary, backup) = (endpoint(serde_json::json!(42)).await, endpoint(serde_json::json!(42)).await);
+        let pool = pool(&[primary.clone(), backup.clone()]);
+        let client = pool.client("devnet", &primary, CommitmentConfig::confirmed());
+
+        // Every second getBlockHeight fails: the first call passes, the second fails on the primary
+        set(rule("getBlockHeight", 50, 0, true));
+        assert_eq!(client.get_block_height().await.unwrap(), 42);
+        assert_eq!(client.url(), primary);
+        let answered = client.get_block_height().await;
+        let counters = snapshot()["counters"].clone();
+        reset();
+
+        assert_eq!(answered.unwrap(), 42);
+        assert_eq!(client.url(), backup);
+        assert_eq!(counters["failed"], 1);
+    }
+
+    #[tokio::test]
+    async fn a_delay_past_the_request_timeout_is_reported_as_a_timeout() {
+        let _serial = SERIAL.lock().await;
+        let url = endpoint(serde_json::json!(42)).await;
+        let pool = pool(std::slice::from_ref(&url));
+        let client = pool.pinned(&url, CommitmentConfig::confirmed());
+
+        set(rule("getBlockHeight", 100, 2_000, false));
+        let (answered, gave_up) = crate::rpc::scope(Duration::from_millis(100), client.get_block_height()).await;
+        reset();
+
+        assert!(answered.is_err());
+        match gave_up {
+            Some(RpcGaveUp::Timeout(timed_out)) => {
+                assert_eq!(timed_out.endpoint, url);
+                assert!(timed_out.elapsed_ms >= 100, "{}", timed_out.elapsed_ms);
+            }
+            _ => panic!("expected the call to be recorded as a timeout"),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_timed_out_request_answers_504_with_rpc_timeout() {
+        use axum::body::Body;
+        use axum::http::{Request, StatusCode};
+        use axum::response::IntoResponse;
+        use tower::ServiceExt;
+
+        let _serial = SERIAL.lock().await;
+        let url = endpoint(serde_json::json!(42)).await;
+        let client = Arc::new(pool(std::slice::from_ref(&url)).pinned(&url, CommitmentConfig::confirmed()));
+        let app = axum::Router::new()
+            .route(
+                "/height",
+                axum::routing::get(move || async move {
+                    match client.get_block_height().await {
+                        Ok(height) => axum::Json(serde_json::json!({ "success": true, "data": height })).into_response(),
+                        Err(e) => crate::api_error::ApiError::upstream(e.to_string()).code("rpc_error").into_response(),
+                    }
+                }),
+            )
+            .layer(axum::middleware::from_fn(crate::rpc_timeout_scope));
+
+        set(rule("getBlockHeight", 100, 2_000, false));
+        let response = app.oneshot(Request::get("/height?timeout_ms=100").body(Body::empty()).unwrap()).await.unwrap();
+        reset();
+
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["success"], false);
+        assert_eq!(body["code"], "rpc_timeout");
+        assert_eq!(body["endpoint"], url);
+        assert_eq!(body["timeout_ms"], 100);
+        assert!(body["elapsed_ms"].as_u64().unwrap() >= 100);
+    }
+}
@@ -0,0 +1,159 @@
+//! Balance guardrail for the local wallet that pays x402 payments and transaction fees.
+//! A background check compares its SOL balance with a warning floor and an optional hard floor.
+
+use serde::Serialize;
+use solana_client::rpc_config::CommitmentConfig;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::alerts::{AlertLog, Severity};
+use crate::rpc::RpcPool;
+use crate::utils::{fuego_home, string_to_pub_key, ui_amount_to_base_units};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Serialize, Default)]
+pub struct FeeWalletStatus {
+    pub address: Option<String>,
+    pub network: Option<String>,
+    pub lamports: Option<u64>,
+    pub floor_lamports: u64,
+    pub hard_floor_lamports: Option<u64>,
+    /// Balance is below the warning floor
+    pub low_balance: bool,
+    /// Balance is below the hard floor; money-moving endpoints refuse to sign
+    pub depleted: bool,
+    pub checked_at: Option<i64>,
+}
+
+#[derive(Clone)]
+pub struct FeeWalletGuard {
+    status: Arc<Mutex<FeeWalletStatus>>,
+}
+
+/// Address and network of the active wallet (wallet.json).
+fn active_wallet() -> Option<(String, String)> {
+    let content = std::fs::read_to_string(fuego_home().join("wallet.json")).ok()?;
+    let wallet: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let address = wallet.get("address")?.as_str()?.to_string();
+    let network = wallet
+        .get("network")
+        .and_then(|n| n.as_str())
+        .unwrap_or("mainnet-beta")
+        .to_string();
+    Some((address, network))
+}
+
+impl FeeWalletGuard {
+    /// Warning floor from FUEGO_FEE_WALLET_FLOOR_SOL (default 0.01), optional refusal floor from
+    /// FUEGO_FEE_WALLET_HARD_FLOOR_SOL.
+    pub fn from_env() -> Self {
+        let floor_lamports = std::env::var("FUEGO_FEE_WALLET_FLOOR_SOL")
+            .ok()
+            .and_then(|v| ui_amount_to_base_units(&v, 9).ok())
+            .unwrap_or(10_000_000);
+        let hard_floor_lamports = std::env::var("FUEGO_FEE_WALLET_HARD_FLOOR_SOL")
+            .ok()
+            .and_then(|v| ui_amount_to_base_units(&v, 9).ok());
+        FeeWalletGuard {
+            status: Arc::new(Mutex::new(FeeWalletStatus {
+                floor_lamports,
+                hard_floor_lamports,
+                ..Default::default()
+            })),
+        }
+    }
+
+    pub fn status(&self) -> FeeWalletStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Error message when `payer` is the fee wallet and it is below the hard floor.
+    pub fn check_payer(&self, payer: &str) -> Result<(), String> {
+        let status = self.status.lock().unwrap();
+        if status.depleted && status.address.as_deref() == Some(payer) {
+            return Err(format!(
+                "Fee wallet {} holds {} lamports, below the hard floor of {}; fund it before sending",
+                payer,
+                status.lamports.unwrap_or(0),
+                status.hard_floor_lamports.unwrap_or(0)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the given address is the fee wallet and currently below the warning floor.
+    pub fn is_low(&self, address: &str) -> bool {
+        let status = self.status.lock().unwrap();
+        status.low_balance && status.address.as_deref() == Some(address)
+    }
+
+    fn refresh(&self, rpc: &RpcPool, alerts: &AlertLog) {
+        let (address, network) = match active_wallet() {
+            Some(w) => w,
+            None => return,
+        };
+        let pubkey = match string_to_pub_key(&address) {
+            Ok(pk) => pk,
+            Err(_) => return,
+        };
+        let rpc_url = rpc.select_read(&network);
+        let client = crate::rpc::client(rpc_url.clone(), CommitmentConfig::confirmed());
+        let started = std::time::Instant::now();
+        let result = client.get_balance(&pubkey);
+        rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+        let lamports = match result {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("Fee wallet balance check failed: {}", e);
+                return;
+            }
+        };
+
+        let mut status = self.status.lock().unwrap();
+        let was_low = status.low_balance;
+        let was_depleted = status.depleted;
+        status.address = Some(address.clone());
+        status.network = Some(network.clone());
+        status.lamports = Some(lamports);
+        status.low_balance = lamports < status.floor_lamports;
+        status.depleted = status.hard_floor_lamports.map(|f| lamports < f).unwrap_or(false);
+        status.checked_at = Some(chrono::Utc::now().timestamp());
+
+        // Alert on the transition only, not on every check while the wallet stays low
+        let details = serde_json::json!({
+            "address": address,
+            "network": network,
+            "lamports": lamports,
+            "floor_lamports": status.floor_lamports,
+            "hard_floor_lamports": status.hard_floor_lamports
+        });
+        if status.depleted && !was_depleted {
+            alerts.emit(
+                "fee_wallet_depleted",
+                Severity::High,
+                format!("Fee wallet {} fell below the hard floor; signing endpoints now refuse", address),
+                details,
+            );
+        } else if status.low_balance && !was_low {
+            alerts.emit(
+                "fee_wallet_low",
+                Severity::Medium,
+                format!("Fee wallet {} fell below the SOL floor", address),
+                details,
+            );
+        }
+    }
+}
+
+/// Check the fee wallet balance now and then every minute.
+pub fn spawn_balance_check(guard: FeeWalletGuard, rpc: RpcPool, alerts: AlertLog) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let (guard, rpc, alerts) = (guard.clone(), rpc.clone(), alerts.clone());
+            let _ = tokio::task::spawn_blocking(move || guard.refresh(&rpc, &alerts)).await;
+        }
+    });
+}
@@ -0,0 +1,184 @@
+//! Response field selection: `fields=signature,blockTime` keeps only those paths of a response's
+//! `data`, so agents polling large histories or dashboards don't pay for fields they never read.
+//! Paths are dotted ("sections.balances.data.tokens") and apply to every element of an array.
+//! The envelope around `data` and any success/ok/error/code/warnings field are never pruned.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Kept at every level regardless of the selection, so a pruned response still says whether it
+/// (or a section of it) worked and why not.
+const ALWAYS_KEPT: [&str; 5] = ["success", "ok", "error", "code", "warnings"];
+
+/// Parsed selection: each node lists the children to keep; an empty node keeps the whole value.
+#[derive(Default)]
+pub struct Selection {
+    children: BTreeMap<String, Selection>,
+}
+
+/// Parse the `fields` parameter. Absent or blank means no selection.
+pub fn parse(spec: Option<&str>) -> Result<Option<Selection>, String> {
+    let spec = match spec.map(str::trim) {
+        Some(spec) if !spec.is_empty() => spec,
+        _ => return Ok(None),
+    };
+    let mut root = Selection::default();
+    for path in spec.split(',').map(str::trim) {
+        if path.is_empty() || path.split('.').any(|s| s.is_empty()) {
+            return Err(format!("Invalid field path \"{}\"", path));
+        }
+        let mut node = &mut root;
+        for segment in path.split('.') {
+            node = node.children.entry(segment.to_string()).or_default();
+        }
+    }
+    Ok(Some(root))
+}
+
+impl Selection {
+    /// Prune `body["data"]` down to the selected paths. Fails with the paths that exist nowhere in
+    /// the response; a path under an empty array, a null or a failed section can't be checked and is
+    /// let through.
+    pub fn apply(&self, mut body: Value) -> Result<Value, Vec<String>> {
+        let Some(data) = body.get_mut("data") else {
+            return Ok(body);
+        };
+        let unknown = self.unknown(data, "");
+        if !unknown.is_empty() {
+            return Err(unknown);
+        }
+        self.prune(data);
+        Ok(body)
+    }
+
+    fn unknown(&self, value: &Value, prefix: &str) -> Vec<String> {
+        if self.children.is_empty() {
+            return Vec::new();
+        }
+        match value {
+            Value::Object(map) => {
+                if failed(map) {
+                    return Vec::new();
+                }
+                let mut unknown = Vec::new();
+                for (name, child) in &self.children {
+                    let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+                    match map.get(name) {
+                        Some(v) => unknown.extend(child.unknown(v, &path)),
+                        None => unknown.push(path),
+                    }
+                }
+                unknown
+            }
+            // Unknown only if no element has it; optional fields are missing from some entries
+            Value::Array(items) => {
+                let mut per_item = items.iter().map(|item| self.unknown(item, prefix));
+                let first = per_item.next().unwrap_or_default();
+                per_item.fold(first, |mut acc, next| {
+                    acc.retain(|p| next.contains(p));
+                    acc
+                })
+            }
+            Value::Null => Vec::new(),
+            _ => self
+                .children
+                .keys()
+                .map(|name| if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) })
+                .collect(),
+        }
+    }
+
+    fn prune(&self, value: &mut Value) {
+        if self.children.is_empty() {
+            return;
+        }
+        match value {
+            Value::Object(map) => {
+                map.retain(|name, _| self.children.contains_key(name) || ALWAYS_KEPT.contains(&name.as_str()));
+                for (name, child) in &self.children {
+                    if let Some(v) = map.get_mut(name) {
+                        child.prune(v);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.prune(item)),
+            _ => {}
+        }
+    }
+}
+
+/// A section or asset that reported failure has only its error to select from.
+fn failed(map: &serde_json::Map<String, Value>) -> bool {
+    map.get("success") == Some(&Value::Bool(false)) || map.get("ok") == Some(&Value::Bool(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn select(spec: &str, body: Value) -> Result<Value, Vec<String>> {
+        parse(Some(spec)).unwrap().unwrap().apply(body)
+    }
+
+    fn dashboard() -> Value {
+        json!({
+            "success": true,
+            "request_id": "r-1",
+            "data": {
+                "address": "9WzD",
+                "sections": {
+                    "balances": { "ok": true, "data": { "sol_balance": "1.5", "tokens": [{ "mint": "EPj", "ui": "2" }] } },
+                    "fees": { "ok": false, "error": "rpc timed out", "code": "rpc_timeout" }
+                },
+                "transactions": [{ "signature": "a", "blockTime": 1, "memo": "x" }, { "signature": "b", "err": null }]
+            }
+        })
+    }
+
+    #[test]
+    fn nested_paths_keep_only_what_was_asked_for() {
+        let body = select("sections.balances.data.sol_balance,transactions.signature", dashboard()).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "success": true,
+                "request_id": "r-1",
+                "data": {
+                    "sections": { "balances": { "ok": true, "data": { "sol_balance": "1.5" } } },
+                    "transactions": [{ "signature": "a" }, { "signature": "b" }]
+                }
+            })
+        );
+        // A path to an object keeps all of it
+        let body = select("sections.balances", dashboard()).unwrap();
+        assert_eq!(body["data"]["sections"]["balances"], dashboard()["data"]["sections"]["balances"]);
+    }
+
+    #[test]
+    fn unknown_fields_are_reported_with_their_full_path() {
+        let unknown = select("address,sections.balances.data.sol_balanse,transactions.sig,nope", dashboard()).unwrap_err();
+        assert_eq!(unknown, ["nope", "sections.balances.data.sol_balanse", "transactions.sig"]);
+        // Below a scalar nothing exists
+        assert_eq!(select("address.length", dashboard()).unwrap_err(), ["address.length"]);
+    }
+
+    #[test]
+    fn fields_missing_from_some_elements_or_failed_sections_are_not_unknown() {
+        let body = select("transactions.blockTime,sections.fees.data.suggestions", dashboard()).unwrap();
+        assert_eq!(body["data"]["transactions"], json!([{ "blockTime": 1 }, {}]));
+        assert_eq!(body["data"]["sections"]["fees"], json!({ "ok": false, "error": "rpc timed out", "code": "rpc_timeout" }));
+        assert!(select("items.signature", json!({ "data": { "items": [] } })).is_ok());
+    }
+
+    #[test]
+    fn malformed_or_blank_specs() {
+        assert!(parse(None).unwrap().is_none());
+        assert!(parse(Some("  ")).unwrap().is_none());
+        for spec in ["a,,b", "a..b", ".a", "a.", ","] {
+            assert!(parse(Some(spec)).is_err(), "{}", spec);
+        }
+        // A body without data is left alone
+        assert_eq!(select("x", json!({ "success": false, "error": "nope" })).unwrap(), json!({ "success": false, "error": "nope" }));
+    }
+}
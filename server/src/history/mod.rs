@@ -0,0 +1,426 @@
+//! Transaction history helpers: historical balance reconstruction (start from the current balance
+//! and walk the account's history backwards, undoing each pre/post balance delta) and decoded
+//! transaction detail with the compute budget and fee breakdown.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::tx_cache::TxCache;
+
+/// Signatures requested per getSignaturesForAddress page (the RPC maximum).
+pub const SIGNATURE_PAGE: usize = 1000;
+
+const COMPUTE_BUDGET_PROGRAM: &str = "ComputeBudget111111111111111111111111111111";
+
+/// Fee charged per signature, independent of any priority fee.
+const LAMPORTS_PER_SIGNATURE: u64 = 5000;
+
+/// Runtime defaults when a transaction sets no compute unit limit.
+const DEFAULT_UNITS_PER_INSTRUCTION: u64 = 200_000;
+const MAX_COMPUTE_UNIT_LIMIT: u64 = 1_400_000;
+
+/// Point in time to reconstruct the balance at.
+#[derive(Clone, Copy)]
+pub enum Target {
+    Slot(u64),
+    Timestamp(i64),
+}
+
+/// What is being tracked: lamports of a wallet, or the raw amount held in one token account.
+pub enum TrackedBalance<'a> {
+    Sol { address: &'a str },
+    Token { token_account: &'a str },
+}
+
+#[derive(Serialize)]
+pub struct BalanceAt {
+    /// Balance in base units (lamports or token base units)
+    pub balance: u64,
+    /// Last transaction at or before the target (exhaustive), or the oldest one scanned (partial)
+    pub anchor_signature: Option<String>,
+    pub anchor_slot: Option<u64>,
+    /// True when the scan reached the target or the start of the account's history
+    pub exhaustive: bool,
+    /// "exact" when exhaustive, otherwise "partial": the balance as of the oldest scanned transaction
+    pub confidence: &'static str,
+    pub transactions_scanned: usize,
+    /// Slot the current balance was read at; newer history is ignored so both views line up
+    pub current_slot: u64,
+    pub current_balance: u64,
+}
+
+pub async fn rpc_call(http: &reqwest::Client, rpc_url: &str, method: &str, params: Value) -> Result<Value, String> {
+    let body = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params
+    });
+    let started = std::time::Instant::now();
+    let http_error = |e| crate::rpc::http_error(rpc_url, started, e);
+    let res = http
+        .post(rpc_url)
+        .headers(crate::trace::outbound_headers())
+        .timeout(crate::rpc::timeout())
+        .json(&body)
+        .send()
+        .await
+        .map_err(http_error)?;
+    let json: Value = res.json().await.map_err(http_error)?;
+    if let Some(err) = json.get("error") {
+        return Err(format!("RPC error: {}", err));
+    }
+    json.get("result")
+        .cloned()
+        .ok_or_else(|| "Missing result in RPC response".to_string())
+}
+
+/// Current balance plus the slot it was observed at.
+/// getTransaction through the shared cache. Not-found results are never cached.
+async fn fetch_transaction(
+    http: &reqwest::Client,
+    rpc_url: &str,
+    cache: &TxCache,
+    network: &str,
+    signature: &str,
+    encoding: &'static str,
+    commitment: &'static str,
+) -> Result<Value, String> {
+    if let Some(tx) = cache.get(network, signature, encoding, commitment) {
+        return Ok(tx);
+    }
+    let tx = rpc_call(
+        http,
+        rpc_url,
+        "getTransaction",
+        json!([signature, { "commitment": commitment, "encoding": encoding, "maxSupportedTransactionVersion": 0 }]),
+    )
+    .await?;
+    cache.insert(network, signature, encoding, commitment, tx.clone());
+    Ok(tx)
+}
+
+async fn current_balance(http: &reqwest::Client, rpc_url: &str, tracked: &TrackedBalance<'_>) -> Result<(u64, u64), String> {
+    match tracked {
+        TrackedBalance::Sol { address } => {
+            let result = rpc_call(http, rpc_url, "getBalance", json!([address, { "commitment": "confirmed" }])).await?;
+            let slot = result["context"]["slot"].as_u64().unwrap_or(0);
+            let lamports = result["value"].as_u64().ok_or("Missing balance in RPC response")?;
+            Ok((lamports, slot))
+        }
+        TrackedBalance::Token { token_account } => {
+            match rpc_call(http, rpc_url, "getTokenAccountBalance", json!([token_account, { "commitment": "confirmed" }])).await {
+                Ok(result) => {
+                    let slot = result["context"]["slot"].as_u64().unwrap_or(0);
+                    let amount = result["value"]["amount"]
+                        .as_str()
+                        .and_then(|a| a.parse::<u64>().ok())
+                        .ok_or("Missing token amount in RPC response")?;
+                    Ok((amount, slot))
+                }
+                // A closed or never-created token account holds nothing now; history still applies
+                Err(e) if e.contains("could not find account") || e.contains("Invalid param") => {
+                    let slot = rpc_call(http, rpc_url, "getSlot", json!([{ "commitment": "confirmed" }]))
+                        .await?
+                        .as_u64()
+                        .unwrap_or(0);
+                    Ok((0, slot))
+                }
+                Err(e) => Err(e),
+            }
+        }
+    }
+}
+
+/// Net change (post - pre) the transaction made to the tracked balance.
+fn balance_delta(tx: &Value, tracked: &TrackedBalance<'_>) -> i128 {
+    let account = match tracked {
+        TrackedBalance::Sol { address } => *address,
+        TrackedBalance::Token { token_account } => *token_account,
+    };
+    let keys = tx["transaction"]["message"]["accountKeys"].as_array().cloned().unwrap_or_default();
+    let index = match keys
+        .iter()
+        .position(|k| k["pubkey"].as_str().or_else(|| k.as_str()) == Some(account))
+    {
+        Some(i) => i,
+        None => return 0,
+    };
+    let meta = &tx["meta"];
+
+    match tracked {
+        TrackedBalance::Sol { .. } => {
+            let pre = meta["preBalances"][index].as_u64().unwrap_or(0) as i128;
+            let post = meta["postBalances"][index].as_u64().unwrap_or(0) as i128;
+            post - pre
+        }
+        TrackedBalance::Token { .. } => {
+            let amount_in = |list: &Value| -> i128 {
+                list.as_array()
+                    .and_then(|entries| entries.iter().find(|e| e["accountIndex"].as_u64() == Some(index as u64)))
+                    .and_then(|e| e["uiTokenAmount"]["amount"].as_str())
+                    .and_then(|a| a.parse::<i128>().ok())
+                    .unwrap_or(0)
+            };
+            amount_in(&meta["postTokenBalances"]) - amount_in(&meta["preTokenBalances"])
+        }
+    }
+}
+
+fn target_reached(target: Target, slot: u64, block_time: Option<i64>) -> bool {
+    match target {
+        Target::Slot(s) => slot <= s,
+        // Without a block time we can't place the transaction, so keep scanning past it
+        Target::Timestamp(t) => block_time.map(|bt| bt <= t).unwrap_or(false),
+    }
+}
+
+/// Walk `history_address`'s signatures newest-first, undoing deltas until `target` is passed or
+/// `max_scan` transactions have been applied.
+pub async fn balance_at(
+    rpc_url: &str,
+    network: &str,
+    cache: &TxCache,
+    history_address: &str,
+    tracked: TrackedBalance<'_>,
+    target: Target,
+    max_scan: usize,
+) -> Result<BalanceAt, String> {
+    let http = crate::rpc::http_client(rpc_url);
+    let (current, current_slot) = current_balance(&http, rpc_url, &tracked).await?;
+
+    let mut balance = current as i128;
+    let mut scanned = 0usize;
+    let mut before: Option<String> = None;
+    let mut oldest: Option<(String, u64)> = None;
+
+    loop {
+        let mut config = json!({ "commitment": "confirmed", "limit": SIGNATURE_PAGE });
+        if let Some(ref sig) = before {
+            config["before"] = json!(sig);
+        }
+        let page = rpc_call(&http, rpc_url, "getSignaturesForAddress", json!([history_address, config])).await?;
+        let entries = page.as_array().cloned().unwrap_or_default();
+        if entries.is_empty() {
+            // Reached the start of the account's history before the target: nothing existed earlier
+            return Ok(BalanceAt {
+                balance: balance.max(0) as u64,
+                anchor_signature: None,
+                anchor_slot: None,
+                exhaustive: true,
+                confidence: "exact",
+                transactions_scanned: scanned,
+                current_slot,
+                current_balance: current,
+            });
+        }
+
+        for entry in &entries {
+            let signature = entry["signature"].as_str().unwrap_or_default().to_string();
+            let slot = entry["slot"].as_u64().unwrap_or(0);
+            before = Some(signature.clone());
+            if slot > current_slot {
+                continue;
+            }
+
+            if target_reached(target, slot, entry["blockTime"].as_i64()) {
+                return Ok(BalanceAt {
+                    balance: balance.max(0) as u64,
+                    anchor_signature: Some(signature),
+                    anchor_slot: Some(slot),
+                    exhaustive: true,
+                    confidence: "exact",
+                    transactions_scanned: scanned,
+                    current_slot,
+                    current_balance: current,
+                });
+            }
+
+            if scanned == max_scan {
+                let (anchor_signature, anchor_slot) = oldest.map(|(s, sl)| (Some(s), Some(sl))).unwrap_or((None, None));
+                return Ok(BalanceAt {
+                    balance: balance.max(0) as u64,
+                    anchor_signature,
+                    anchor_slot,
+                    exhaustive: false,
+                    confidence: "partial",
+                    transactions_scanned: scanned,
+                    current_slot,
+                    current_balance: current,
+                });
+            }
+
+            let tx = fetch_transaction(&http, rpc_url, cache, network, &signature, "jsonParsed", "confirmed").await?;
+            balance -= balance_delta(&tx, &tracked);
+            scanned += 1;
+            oldest = Some((signature, slot));
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ComputeBudget {
+    pub unit_limit: u64,
+    /// Micro-lamports per compute unit
+    pub unit_price: u64,
+    /// False when the transaction had no SetComputeUnitLimit and the runtime default applies
+    pub unit_limit_set: bool,
+    /// False when the transaction had no SetComputeUnitPrice (price 0)
+    pub unit_price_set: bool,
+    pub units_consumed: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct FeeBreakdown {
+    /// Total fee from the transaction meta
+    pub total_lamports: u64,
+    /// Signature fee: 5000 lamports per signature
+    pub base_lamports: u64,
+    /// Whatever was paid on top of the signature fee
+    pub priority_lamports: u64,
+    /// Priority fee implied by unit price x unit limit; differs from the above only on odd fee rules
+    pub priority_lamports_from_budget: u64,
+    /// Share of the requested units actually used (unit_limit is what the priority fee is charged on)
+    pub unit_limit_utilization: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct TransactionDetail {
+    pub signature: String,
+    pub slot: Option<u64>,
+    pub block_time: Option<i64>,
+    pub succeeded: bool,
+    pub err: Value,
+    pub compute_budget: ComputeBudget,
+    pub fees: FeeBreakdown,
+    /// The RPC's getTransaction result, unmodified
+    pub raw: Value,
+}
+
+/// Fetch a transaction and decode its compute budget instructions and fee split. `commitment` is
+/// "confirmed" or "finalized" (getTransaction doesn't serve processed).
+pub async fn transaction_detail(
+    rpc_url: &str,
+    network: &str,
+    cache: &TxCache,
+    signature: &str,
+    commitment: &'static str,
+) -> Result<Option<TransactionDetail>, String> {
+    let http = crate::rpc::http_client(rpc_url);
+    let raw = fetch_transaction(&http, rpc_url, cache, network, signature, "json", commitment).await?;
+    if raw.is_null() {
+        return Ok(None);
+    }
+
+    let message = &raw["transaction"]["message"];
+    let keys: Vec<&str> = message["accountKeys"]
+        .as_array()
+        .map(|k| k.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+    let instructions = message["instructions"].as_array().cloned().unwrap_or_default();
+
+    let mut unit_limit: Option<u64> = None;
+    let mut unit_price: Option<u64> = None;
+    let mut other_instructions = 0u64;
+    for ix in &instructions {
+        let program = ix["programIdIndex"]
+            .as_u64()
+            .and_then(|i| keys.get(i as usize))
+            .copied()
+            .unwrap_or_default();
+        if program != COMPUTE_BUDGET_PROGRAM {
+            other_instructions += 1;
+            continue;
+        }
+        let data = ix["data"]
+            .as_str()
+            .and_then(|d| bs58::decode(d).into_vec().ok())
+            .unwrap_or_default();
+        match data.first() {
+            Some(2) if data.len() >= 5 => {
+                unit_limit = Some(u32::from_le_bytes([data[1], data[2], data[3], data[4]]) as u64);
+            }
+            Some(3) if data.len() >= 9 => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(&data[1..9]);
+                unit_price = Some(u64::from_le_bytes(bytes));
+            }
+            _ => {}
+        }
+    }
+
+    let meta = &raw["meta"];
+    let signatures = raw["transaction"]["signatures"].as_array().map(|s| s.len()).unwrap_or(1) as u64;
+    let total = meta["fee"].as_u64().unwrap_or(0);
+    let base = LAMPORTS_PER_SIGNATURE * signatures;
+    let units_consumed = meta["computeUnitsConsumed"].as_u64();
+
+    let effective_limit = unit_limit
+        .unwrap_or_else(|| (DEFAULT_UNITS_PER_INSTRUCTION * other_instructions).min(MAX_COMPUTE_UNIT_LIMIT))
+        .min(MAX_COMPUTE_UNIT_LIMIT);
+    let price = unit_price.unwrap_or(0);
+    let from_budget = ((price as u128 * effective_limit as u128).div_ceil(1_000_000)) as u64;
+
+    Ok(Some(TransactionDetail {
+        signature: signature.to_string(),
+        slot: raw["slot"].as_u64(),
+        block_time: raw["blockTime"].as_i64(),
+        succeeded: meta["err"].is_null(),
+        err: meta["err"].clone(),
+        compute_budget: ComputeBudget {
+            unit_limit: effective_limit,
+            unit_price: price,
+            unit_limit_set: unit_limit.is_some(),
+            unit_price_set: unit_price.is_some(),
+            units_consumed,
+        },
+        fees: FeeBreakdown {
+            total_lamports: total,
+            base_lamports: base.min(total),
+            priority_lamports: total.saturating_sub(base),
+            priority_lamports_from_budget: from_budget,
+            unit_limit_utilization: units_consumed
+                .filter(|_| effective_limit > 0)
+                .map(|used| used as f64 / effective_limit as f64),
+        },
+        raw,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Accepts connections and never answers.
+    async fn silent_endpoint() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn a_silent_endpoint_times_out_with_the_request_timeout() {
+        let url = silent_endpoint().await;
+        let http = reqwest::Client::new();
+        let started = std::time::Instant::now();
+        let (result, gave_up) =
+            crate::rpc::scope(Duration::from_millis(150), rpc_call(&http, &url, "getSlot", json!([]))).await;
+
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        match gave_up {
+            Some(crate::rpc::RpcGaveUp::Timeout(timed_out)) => {
+                assert_eq!(timed_out.endpoint, url);
+                assert!(timed_out.elapsed_ms >= 150, "{}", timed_out.elapsed_ms);
+            }
+            _ => panic!("expected the call to be recorded as a timeout"),
+        }
+    }
+}
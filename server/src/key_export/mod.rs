@@ -0,0 +1,358 @@
+//! What POST /wallet/export and POST /admin/backup check before handing out key material.
+//! wallet.json is not encrypted, so the API key alone must not be enough to read the key out:
+//! `fuego-server key-export setup` sets an export passphrase, stored only as an Argon2id hash in
+//! ~/.fuego/key-export.json, and with `--totp` a TOTP secret for an authenticator app. Until it
+//! has been run, export is refused. The file never leaves the machine except inside a backup.
+
+use base64::engine::general_purpose;
+use base64::Engine;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::backup::{self, Kdf};
+use crate::utils::constant_time_eq;
+
+const SECRET_FILE: &str = "key-export.json";
+
+/// Authenticator apps expect 30-second steps of 6 digits over HMAC-SHA1 (RFC 6238).
+const TOTP_STEP_SECS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+/// Steps either side of now still accepted, for clock drift
+const TOTP_SKEW_STEPS: i64 = 1;
+const TOTP_SECRET_BYTES: usize = 20;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportSecret {
+    kdf: Kdf,
+    /// Base64 Argon2id output for the passphrase
+    hash: String,
+    /// Base32, as entered in an authenticator app; absent when TOTP is off
+    #[serde(default)]
+    totp_secret: Option<String>,
+}
+
+/// Why an export was refused.
+#[derive(Debug, PartialEq)]
+pub enum Denied {
+    Disabled,
+    DryRun,
+    NotSetUp,
+    BadPassphrase,
+    TotpRequired,
+    BadTotp,
+}
+
+impl Denied {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Denied::Disabled | Denied::DryRun => "key_export_disabled",
+            Denied::NotSetUp => "key_export_not_set_up",
+            Denied::BadPassphrase => "invalid_passphrase",
+            Denied::TotpRequired => "totp_required",
+            Denied::BadTotp => "invalid_totp",
+        }
+    }
+
+    /// The audit log's outcome for the attempt.
+    pub fn outcome(&self) -> &'static str {
+        match self {
+            Denied::Disabled => "disabled",
+            Denied::DryRun => "dry_run",
+            Denied::NotSetUp => "not_set_up",
+            Denied::BadPassphrase => "bad_passphrase",
+            Denied::TotpRequired => "totp_required",
+            Denied::BadTotp => "bad_totp",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Denied::Disabled => "Key export is disabled; set FUEGO_ALLOW_KEY_EXPORT=true and restart the server to enable it",
+            Denied::DryRun => "Key export is not available in dry-run mode",
+            Denied::NotSetUp => "No export passphrase is set; run `fuego-server key-export setup` on the server first",
+            Denied::BadPassphrase => "passphrase is not the export passphrase",
+            Denied::TotpRequired => "This server requires totp_code from the authenticator app for key export",
+            Denied::BadTotp => "totp_code is wrong or expired",
+        }
+    }
+}
+
+/// Whether this server hands out key material at all: FUEGO_ALLOW_KEY_EXPORT, and never in dry-run.
+pub fn availability(allowed: bool, dry_run: bool) -> Result<(), Denied> {
+    if !allowed {
+        return Err(Denied::Disabled);
+    }
+    if dry_run {
+        return Err(Denied::DryRun);
+    }
+    Ok(())
+}
+
+fn secret_path() -> PathBuf {
+    crate::utils::fuego_home().join(SECRET_FILE)
+}
+
+/// The stored export secret; None when setup hasn't been run.
+pub fn load() -> Result<Option<ExportSecret>, String> {
+    match fs::read_to_string(secret_path()) {
+        Ok(content) => serde_json::from_str(&content).map(Some).map_err(|e| format!("{} is unreadable: {}", SECRET_FILE, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Can't read {}: {}", SECRET_FILE, e)),
+    }
+}
+
+impl ExportSecret {
+    fn create(passphrase: &str, totp: bool, kdf: Kdf) -> Result<Self, String> {
+        if passphrase.chars().count() < backup::MIN_PASSPHRASE_LEN {
+            return Err(format!("Passphrase must be at least {} characters", backup::MIN_PASSPHRASE_LEN));
+        }
+        let hash = general_purpose::STANDARD.encode(backup::derive_key(passphrase, &kdf)?);
+        let totp_secret = totp.then(|| {
+            let mut secret = [0u8; TOTP_SECRET_BYTES];
+            OsRng.fill_bytes(&mut secret);
+            base32_encode(&secret)
+        });
+        Ok(ExportSecret { kdf, hash, totp_secret })
+    }
+
+    /// Check the passphrase and, when TOTP is on, the code for unix time `now`. Runs Argon2id;
+    /// call it off the async workers.
+    pub fn check(&self, passphrase: &str, totp_code: Option<&str>, now: i64) -> Result<(), Denied> {
+        let expected = general_purpose::STANDARD.decode(&self.hash).map_err(|_| Denied::NotSetUp)?;
+        let derived = backup::derive_key(passphrase, &self.kdf).map_err(|_| Denied::NotSetUp)?;
+        if !constant_time_eq(&expected, &derived) {
+            return Err(Denied::BadPassphrase);
+        }
+        let Some(secret) = &self.totp_secret else {
+            return Ok(());
+        };
+        let code = totp_code.map(str::trim).filter(|c| !c.is_empty()).ok_or(Denied::TotpRequired)?;
+        let secret = base32_decode(secret).ok_or(Denied::NotSetUp)?;
+        if totp_matches(&secret, code, now) {
+            Ok(())
+        } else {
+            Err(Denied::BadTotp)
+        }
+    }
+}
+
+/// Check an attempt against the stored secret, refusing when there is none.
+pub fn check(secret: Option<&ExportSecret>, passphrase: &str, totp_code: Option<&str>, now: i64) -> Result<(), Denied> {
+    secret.ok_or(Denied::NotSetUp)?.check(passphrase, totp_code, now)
+}
+
+fn totp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([digest[offset] & 0x7f, digest[offset + 1], digest[offset + 2], digest[offset + 3]]);
+    value % 10u32.pow(TOTP_DIGITS)
+}
+
+fn totp_matches(secret: &[u8], code: &str, now: i64) -> bool {
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    let Ok(code) = code.parse::<u32>() else {
+        return false;
+    };
+    let step = now.div_euclid(TOTP_STEP_SECS);
+    (-TOTP_SKEW_STEPS..=TOTP_SKEW_STEPS)
+        .map(|skew| step + skew)
+        .filter(|counter| *counter >= 0)
+        .any(|counter| totp(secret, counter as u64) == code)
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::new();
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+fn base32_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for c in text.trim_end_matches('=').bytes().filter(|b| !b.is_ascii_whitespace()) {
+        let value = BASE32_ALPHABET.iter().position(|&a| a == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// Passphrase from FUEGO_KEY_EXPORT_PASSPHRASE, otherwise one line from stdin.
+fn read_passphrase() -> Result<String, String> {
+    if let Ok(passphrase) = std::env::var("FUEGO_KEY_EXPORT_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    eprint!("Export passphrase: ");
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).map_err(|e| e.to_string())?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// `fuego-server key-export setup [--totp]`; returns the exit code. Running it again replaces the
+/// passphrase and TOTP secret.
+pub fn run_cli(args: &[String]) -> i32 {
+    let totp = match args {
+        [cmd, sub] if cmd == "key-export" && sub == "setup" => false,
+        [cmd, sub, flag] if cmd == "key-export" && sub == "setup" && flag == "--totp" => true,
+        _ => {
+            eprintln!("Usage: fuego-server key-export setup [--totp]");
+            return 2;
+        }
+    };
+    let passphrase = match read_passphrase() {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Failed to read passphrase: {}", e);
+            return 1;
+        }
+    };
+    let secret = match ExportSecret::create(&passphrase, totp, backup::new_kdf()) {
+        Ok(secret) => secret,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let path = secret_path();
+    let written = fs::create_dir_all(crate::utils::fuego_home())
+        .and_then(|_| fs::write(&path, serde_json::to_string_pretty(&secret).unwrap_or_default()));
+    if let Err(e) = written {
+        eprintln!("Failed to write {}: {}", path.display(), e);
+        return 1;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(&path, fs::Permissions::from_mode(0o600));
+    }
+    eprintln!("Export passphrase set in {}", path.display());
+    if let Some(totp_secret) = &secret.totp_secret {
+        eprintln!("Add this TOTP secret to an authenticator app; it is not shown again:");
+        println!("{}", totp_secret);
+        println!("otpauth://totp/fuego:key-export?secret={}&issuer=fuego", totp_secret);
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Argon2id at the lowest cost, so tests stay fast
+    fn cheap_kdf() -> Kdf {
+        Kdf {
+            algorithm: "argon2id".to_string(),
+            salt: general_purpose::STANDARD.encode([7u8; 16]),
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        }
+    }
+
+    const PASSPHRASE: &str = "correct horse battery";
+
+    fn code_at(secret: &ExportSecret, now: i64) -> String {
+        let key = base32_decode(secret.totp_secret.as_deref().unwrap()).unwrap();
+        format!("{:06}", totp(&key, (now / TOTP_STEP_SECS) as u64))
+    }
+
+    #[test]
+    fn refused_when_disabled_or_dry_run() {
+        assert_eq!(availability(false, false), Err(Denied::Disabled));
+        assert_eq!(availability(false, true), Err(Denied::Disabled));
+        assert_eq!(availability(true, true), Err(Denied::DryRun));
+        assert_eq!(availability(true, false), Ok(()));
+    }
+
+    #[test]
+    fn refused_until_a_passphrase_is_set() {
+        assert_eq!(check(None, PASSPHRASE, None, 0), Err(Denied::NotSetUp));
+    }
+
+    #[test]
+    fn short_passphrases_are_not_stored() {
+        assert!(ExportSecret::create("short", false, cheap_kdf()).is_err());
+    }
+
+    #[test]
+    fn wrong_passphrase_is_refused() {
+        let secret = ExportSecret::create(PASSPHRASE, false, cheap_kdf()).unwrap();
+        assert_eq!(check(Some(&secret), "incorrect horse battery", None, 0), Err(Denied::BadPassphrase));
+        assert_eq!(check(Some(&secret), "", None, 0), Err(Denied::BadPassphrase));
+        assert_eq!(check(Some(&secret), PASSPHRASE, None, 0), Ok(()));
+    }
+
+    #[test]
+    fn totp_is_required_once_enabled() {
+        let secret = ExportSecret::create(PASSPHRASE, true, cheap_kdf()).unwrap();
+        assert_eq!(check(Some(&secret), PASSPHRASE, None, 1_000_000), Err(Denied::TotpRequired));
+        assert_eq!(check(Some(&secret), PASSPHRASE, Some("  "), 1_000_000), Err(Denied::TotpRequired));
+    }
+
+    #[test]
+    fn wrong_or_stale_totp_is_refused() {
+        let secret = ExportSecret::create(PASSPHRASE, true, cheap_kdf()).unwrap();
+        let now = 1_000_000;
+        let current = code_at(&secret, now);
+        let wrong = format!("{:06}", (current.parse::<u32>().unwrap() + 1) % 1_000_000);
+        assert_eq!(check(Some(&secret), PASSPHRASE, Some(&wrong), now), Err(Denied::BadTotp));
+        assert_eq!(check(Some(&secret), PASSPHRASE, Some("12345"), now), Err(Denied::BadTotp));
+        assert_eq!(check(Some(&secret), PASSPHRASE, Some("abcdef"), now), Err(Denied::BadTotp));
+        let stale = now - 3 * TOTP_STEP_SECS;
+        assert_eq!(check(Some(&secret), PASSPHRASE, Some(&code_at(&secret, stale)), now), Err(Denied::BadTotp));
+        // A right code never makes up for a wrong passphrase
+        assert_eq!(check(Some(&secret), "incorrect horse battery", Some(&current), now), Err(Denied::BadPassphrase));
+    }
+
+    #[test]
+    fn current_and_adjacent_totp_are_accepted() {
+        let secret = ExportSecret::create(PASSPHRASE, true, cheap_kdf()).unwrap();
+        let now = 1_000_000;
+        for at in [now - TOTP_STEP_SECS, now, now + TOTP_STEP_SECS] {
+            assert_eq!(check(Some(&secret), PASSPHRASE, Some(&code_at(&secret, at)), now), Ok(()));
+        }
+    }
+
+    #[test]
+    fn totp_matches_rfc_6238_vectors() {
+        let secret = b"12345678901234567890";
+        // RFC 6238 appendix B, SHA-1, last six of the eight digits
+        for (time, code) in [(59, 287_082), (1_111_111_109, 81_804), (1_234_567_890, 5_924), (2_000_000_000, 279_037)] {
+            assert_eq!(totp(secret, (time / TOTP_STEP_SECS) as u64), code);
+        }
+    }
+
+    #[test]
+    fn base32_round_trips() {
+        let data = [0u8, 1, 2, 250, 251, 252, 253, 254, 255, 42];
+        assert_eq!(base32_decode(&base32_encode(&data)).unwrap(), data);
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert!(base32_decode("not base32!").is_none());
+    }
+}
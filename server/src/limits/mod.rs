@@ -0,0 +1,74 @@
+//! How large a request body may be and how long a request may run. Each limit comes from its
+//! environment variable, then the same key in the server config, then the default: 64 KB
+//! bodies (a signed transaction is under 2 KB), 30 seconds per request, and 120 for the routes
+//! that walk transaction history.
+
+use std::time::Duration;
+
+use crate::config::ServerConfig;
+
+pub const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_SLOW_TIMEOUT_SECS: u64 = 120;
+
+/// Routes that page through signatures or re-scan the chain.
+const SLOW_ROUTES: &[&str] = &[
+    "/all-transactions",
+    "/balance-at",
+    "/search-memos",
+    "/stats/transfers",
+    "/admin/reconcile",
+    "/devnet/mint-tokens",
+];
+
+/// Routes that can hold the request open waiting for `min_confirmations`; they get that wait on
+/// top of the normal timeout.
+const CONFIRMING_ROUTES: &[&str] = &["/submit-transaction", "/submit-versioned-transaction"];
+
+#[derive(Clone, Copy)]
+pub struct RequestLimits {
+    pub max_body_bytes: usize,
+    pub timeout: Duration,
+    pub slow_timeout: Duration,
+}
+
+impl RequestLimits {
+    pub fn load(config: &ServerConfig) -> Self {
+        let env = |name: &str| std::env::var(name).ok().and_then(|v| v.trim().parse::<u64>().ok());
+
+        let max_body_bytes = env("FUEGO_MAX_BODY_BYTES")
+            .map(|v| v as usize)
+            .or(config.max_body_bytes)
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_MAX_BODY_BYTES);
+        let timeout_secs = env("FUEGO_REQUEST_TIMEOUT_SECS")
+            .or(config.request_timeout_secs)
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+        // Never shorter than the normal timeout
+        let slow_timeout_secs = env("FUEGO_SLOW_REQUEST_TIMEOUT_SECS")
+            .or(config.slow_request_timeout_secs)
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_SLOW_TIMEOUT_SECS)
+            .max(timeout_secs);
+
+        RequestLimits {
+            max_body_bytes,
+            timeout: Duration::from_secs(timeout_secs),
+            slow_timeout: Duration::from_secs(slow_timeout_secs),
+        }
+    }
+
+    /// How long a request to `path` may run before it is answered with a 504.
+    pub fn timeout_for(&self, path: &str) -> Duration {
+        if CONFIRMING_ROUTES.contains(&path) {
+            return self.timeout + Duration::from_secs(crate::pending::MAX_CONFIRM_TIMEOUT_SECS);
+        }
+        let shared_history = path.starts_with("/shared/") && path.ends_with("/history");
+        if SLOW_ROUTES.contains(&path) || shared_history {
+            self.slow_timeout
+        } else {
+            self.timeout
+        }
+    }
+}
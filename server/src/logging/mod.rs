@@ -0,0 +1,38 @@
+//! Log output for the running server, through `tracing`. Every request gets a span carrying its
+//! request id, method, path and, once the body is read, its network and addresses; the TraceLayer logs the
+//! status and latency when it finishes, and RPC calls made while handling it are child spans.
+//! FUEGO_LOG=json writes one JSON object per line instead of text; RUST_LOG filters as usual
+//! (default "info"). Amounts are logged only as an order-of-magnitude bucket, and not at all with
+//! FUEGO_TRACE_AMOUNTS=false.
+
+use axum::http::Request;
+use tracing::field::Empty;
+use tracing_subscriber::EnvFilter;
+
+const DEFAULT_FILTER: &str = "info";
+
+/// Install the global subscriber; call once, before anything logs.
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_target(false);
+    if std::env::var("FUEGO_LOG").map(|v| v == "json").unwrap_or(false) {
+        builder.json().with_current_span(true).with_span_list(true).init();
+    } else {
+        builder.init();
+    }
+}
+
+/// The span a request is logged under. The fields left empty are filled in by the trace_request
+/// middleware once it has read the body.
+pub fn request_span(request: &Request<axum::body::Body>) -> tracing::Span {
+    tracing::info_span!(
+        "request",
+        request_id = request.headers().get(crate::request_id::HEADER).and_then(|v| v.to_str().ok()).unwrap_or_default(),
+        method = %request.method(),
+        path = %request.uri().path(),
+        network = Empty,
+        address = Empty,
+        to_address = Empty,
+        amount_bucket = Empty,
+    )
+}
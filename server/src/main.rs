@@ -1,4 +1,58 @@
+mod alerts;
+mod amount;
+mod anomaly;
+mod api_error;
+mod auth;
+mod backup;
+mod blockhash_cache;
+mod build_info;
+mod builds;
+mod cleanup;
+mod commitment;
+mod config;
+mod cors;
+mod exchanges;
+mod egress;
+mod extra_instructions;
+#[cfg(feature = "fault-injection")]
+mod faults;
+mod faucet;
+mod fee_wallet;
+mod fields;
+mod history;
+mod key_export;
+mod limits;
+mod logging;
+mod memo_index;
+mod mints;
+mod network;
+mod nfts;
+mod onboard;
+mod pending;
+mod providers;
+mod rate_limit;
+mod receipts;
+mod reconcile;
+mod registry;
+mod request_id;
+mod rpc;
+mod rpc_errors;
+mod sessions;
+mod settlement;
+mod share_links;
+mod stats;
+mod strict_json;
+mod submit_queue;
+mod support;
+mod timelock;
+mod tls;
+mod trace;
+mod transfer_fee;
+mod tx_cache;
+mod ui;
 mod utils;
+mod wallets;
+mod warnings;
 
 /// Compute budget instructions (solana_sdk 4.x no longer exposes compute_budget module).
 mod compute_budget {
@@ -35,7 +89,7 @@ mod compute_budget {
     }
 }
 
-/// System program instructions (solana_sdk 4.x no longer exposes system_instruction module).
+// System program instructions (solana_sdk 4.x no longer exposes system_instruction module).
 // mod system_instruction {
 //     use solana_sdk::instruction::{AccountMeta, Instruction};
 //     use solana_sdk::pubkey::Pubkey;
@@ -63,99 +117,114 @@ mod compute_budget {
 
 use crate::compute_budget::ComputeBudgetInstruction;
 use axum::{
-    extract::State,
-    http::Method,
+    extract::{FromRequest, Path, Query, State},
+    http::{HeaderMap, Method},
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::{delete, get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use solana_client::rpc_client::RpcClient;
+use serde_json::value::RawValue;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_client::rpc_config::CommitmentConfig;
 use solana_sdk::message::Message;
+use solana_sdk::signer::Signer;
 use solana_sdk::transaction::Transaction;
 use solana_system_interface::instruction::transfer;
 use solana_transaction::versioned::VersionedTransaction as ClientVersionedTransaction;
 use solana_transaction::Transaction as ClientTransaction;
-use spl_associated_token_account::get_associated_token_address;
-use spl_token::instruction as token_instruction;
-use spl_memo;
+use std::future::Future;
 use std::net::SocketAddr;
-use tower_http::cors::{Any, CorsLayer};
+use std::time::Instant;
+use api_error::{ApiError, ApiResult};
+use fuego_types::{
+    GetBalanceRequest, GetTokenBalanceRequest, GetTokensRequest, MintBalanceRequest, RpcTarget, SubmitTransactionRequest,
+    TokenBalancesRequest, TransferOptions, TransferPyusdRequest, TransferQuoteRequest, TransferSolRequest, TransferTokenRequest,
+    TransferUsdcRequest, TransferUsdtRequest,
+};
 use utils::string_to_pub_key;
 use base64::engine::general_purpose;
 use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs;
+use std::sync::{Arc, Mutex};
+
+/// Minimum time between key export attempts.
+const KEY_EXPORT_COOLDOWN: std::time::Duration = std::time::Duration::from_secs(10 * 60);
 
 // Token mint addresses
 const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
-const USDT_MINT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenEqw";
+const USDT_MINT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB";
+/// Decimals the USDC and USDT builders pass to `transfer_checked`; checked against the mints at startup
+const USDC_DECIMALS: u8 = 6;
+const USDT_DECIMALS: u8 = 6;
+/// PayPal USD; a Token-2022 mint, so its accounts aren't at the classic ATA address
+const PYUSD_MINT: &str = "2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo";
+const PYUSD_DECIMALS: u8 = 6;
 
 #[derive(Serialize, Deserialize)]
 struct RpcNetwork {
+    #[serde(default)]
     network: String,
+    #[serde(flatten)]
+    target: RpcTarget,
+    /// Fetch a new blockhash instead of reusing one cached in the last few seconds
+    #[serde(default)]
+    fresh_blockhash: bool,
 }
 
 #[derive(Serialize, Deserialize)]
-struct GetBalanceRequest {
-    network: String,
-    address: String,
-}
-
-#[derive(Serialize, Deserialize)]
-struct GetTokenBalanceRequest {
+struct BalancesBatchRequest {
+    #[serde(default)]
     network: String,
-    address: String,
+    #[serde(flatten)]
+    target: RpcTarget,
+    addresses: Vec<String>,
     #[serde(default)]
     commitment: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
-struct TransferUsdcRequest {
+struct BuildSweepSolRequest {
+    #[serde(default)]
     network: String,
+    #[serde(flatten)]
+    target: RpcTarget,
     from_address: String,
     to_address: String,
-    amount: String, // String to preserve decimals
-    yid: String, // Yield ID for tracking
+    yid: String,
     #[serde(default)]
-    notes: Option<String>, // Optional memo notes (max 16 chars)
+    notes: Option<String>,
+    /// Compute unit price in micro-lamports; its fee is taken out of the sweep
     #[serde(default)]
     fee_amount: Option<String>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct TransferSolRequest {
-    network: String,
-    from_address: String,
-    to_address: String,
-    amount: String, // String to preserve decimals (in SOL)
-    yid: String, // Yield ID for tracking
+    /// Leave the rent-exempt minimum for a zero-data account behind (default true)
     #[serde(default)]
-    notes: Option<String>, // Optional memo notes (max 16 chars)
+    keep_rent_exempt: Option<bool>,
     #[serde(default)]
-    fee_amount: Option<String>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct TransferUsdtRequest {
-    network: String,
-    from_address: String,
-    to_address: String,
-    amount: String, // String to preserve decimals
-    yid: String, // Yield ID for tracking
+    memo_privacy: Option<String>,
     #[serde(default)]
-    notes: Option<String>, // Optional memo notes (max 16 chars)
+    previous_build_id: Option<String>,
     #[serde(default)]
-    fee_amount: Option<String>,
+    exchange_memo: Option<String>,
+    #[serde(default)]
+    treat_warnings_as_errors: bool,
+    /// Fetch a new blockhash instead of reusing one cached in the last few seconds
+    #[serde(default)]
+    fresh_blockhash: bool,
 }
 
 #[derive(Serialize, Deserialize)]
-struct SubmitTransactionRequest {
+struct SubmitTransactionsRequest {
+    #[serde(default)]
     network: String,
-    transaction: String, // Base64-encoded signed transaction
+    /// Base64-encoded signed transactions, submitted in order
+    transactions: Vec<String>,
+    /// True when the transactions are versioned (v0), e.g. from Jupiter
     #[serde(default)]
-    commitment: Option<String>,
+    versioned: bool,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -176,6 +245,29 @@ struct WalletStore {
     network: String,
 }
 
+#[derive(Serialize, Deserialize)]
+struct WatchWalletRequest {
+    address: String,
+    #[serde(default)]
+    label: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WalletExportRequest {
+    /// "solana_cli" (64-byte JSON array) or "base58"
+    format: String,
+    /// Must equal the local wallet's address, so an export is never triggered by accident
+    confirm_address: String,
+    /// The export passphrase set with `fuego-server key-export setup`
+    passphrase: String,
+    /// Current code from the authenticator app, when setup was run with --totp
+    #[serde(default)]
+    totp_code: Option<String>,
+    /// Export even though wallet.json disagrees with its own key (see GET /wallet/verify)
+    #[serde(default)]
+    force: bool,
+}
+
 #[derive(Serialize, Deserialize)]
 struct X402PurchRequest {
     /// Purch.xyz order endpoint (e.g. https://x402.purch.xyz/orders/solana) or product URL; server POSTs here with order body
@@ -203,1217 +295,7941 @@ struct X402PurchRequest {
     /// Maximum price in USD cents (e.g., 5000 = $50.00). Required for URL-based products.
     #[serde(default, rename = "maxPrice")]
     max_price: Option<u64>,
+    /// Optional Yield ID so the purchase can be attributed like a transfer.
+    #[serde(default)]
+    yid: Option<String>,
+    /// Persist the paid response body under ~/.fuego/x402-bodies (content-addressed). Off by default.
+    #[serde(default)]
+    store_response: bool,
+    /// Sign even though wallet.json disagrees with its own key (see GET /wallet/verify)
+    #[serde(default)]
+    force: bool,
+    /// Sign a new payment even though an identical request's payment may still settle
+    #[serde(default)]
+    force_new_payment: bool,
+    /// Refuse instead of returning warnings (nothing is charged or recorded)
+    #[serde(default)]
+    treat_warnings_as_errors: bool,
 }
 
 #[derive(Serialize, Deserialize)]
 struct GetAccountSignatures {
     address: String,
+    #[serde(default)]
     network: String,
+    #[serde(flatten)]
+    target: RpcTarget,
     #[serde(default)]
     limit: Option<usize>,
+    /// Forward the RPC's JSON result untouched instead of round-tripping through solana-client types
+    #[serde(default)]
+    raw: bool,
+    #[serde(default)]
+    commitment: Option<String>,
+    /// Comma-separated fields to keep per signature, e.g. "signature,blockTime"
+    #[serde(default)]
+    fields: Option<String>,
 }
 
-// State to hold RPC clients (could be expanded for caching)
-#[derive(Clone)]
-struct AppState {
-    default_network: String,
+#[derive(Serialize, Deserialize)]
+struct BalanceAtRequest {
+    #[serde(default)]
+    network: String,
+    #[serde(flatten)]
+    target: RpcTarget,
+    address: String,
+    /// "SOL" (default), "USDC", "USDT" or a mint address
+    #[serde(default)]
+    mint: Option<String>,
+    /// Reconstruct the balance as of this slot...
+    #[serde(default)]
+    slot: Option<u64>,
+    /// ...or as of this unix timestamp (exactly one of slot/timestamp)
+    #[serde(default)]
+    timestamp: Option<i64>,
+    /// Transactions to scan before giving up with a partial result (capped by FUEGO_BALANCE_AT_MAX_SCAN)
+    #[serde(default)]
+    max_scan: Option<usize>,
 }
 
-fn get_commitment_config(commitment: &Option<String>) -> CommitmentConfig {
-    match commitment.as_ref().map(|s| s.as_str()) {
-        Some("processed") => CommitmentConfig::processed(),
-        Some("finalized") => CommitmentConfig::finalized(),
-        _ => CommitmentConfig::confirmed(),
-    }
+#[derive(Serialize, Deserialize)]
+struct SearchMemosRequest {
+    #[serde(default)]
+    network: Option<String>,
+    #[serde(default)]
+    yid_prefix: Option<String>,
+    #[serde(default)]
+    notes_contains: Option<String>,
+    #[serde(default)]
+    token: Option<String>,
+    /// Matches either side of the transfer (f: or t:)
+    #[serde(default)]
+    counterparty: Option<String>,
+    /// Substring of the full memo text
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: Option<usize>,
 }
 
-fn build_memo(token_type: &str, from: &str, to: &str, amount: u64, yid: &str, notes: Option<&str>) -> Result<String, String> {
-    // Validate notes if provided
-    if let Some(n) = notes {
-        if n.len() > 16 {
-            return Err(format!("Notes must be 16 characters or less, got {}", n.len()));
-        }
-    }
-    
-    let notes_part = notes.unwrap_or("");
-    Ok(format!(
-        "fuego|{}|f:{}|t:{}|a:{}|yid:{}|n:{}",
-        token_type, from, to, amount, yid, notes_part
-    ))
+#[derive(Serialize, Deserialize)]
+struct BackupRequest {
+    /// At least 12 characters; needed again to restore
+    passphrase: String,
+    /// The export passphrase set with `fuego-server key-export setup`, as for /wallet/export
+    export_passphrase: String,
+    /// Current authenticator code, when setup was run with --totp
+    #[serde(default)]
+    totp_code: Option<String>,
 }
 
-async fn health_check() -> impl IntoResponse {
-    Json(json!({
-        "status": "healthy",
-        "service": "fuego-server",
-        "version": "0.1.0"
-    }))
+#[derive(Serialize, Deserialize, Default)]
+struct ReconcileRequest {
+    /// Report what would change without writing anything
+    #[serde(default)]
+    dry_run: bool,
+    /// History signatures scanned per wallet and network (default 100, max 5000)
+    #[serde(default)]
+    depth: Option<usize>,
+    /// Only this network
+    #[serde(default)]
+    network: Option<String>,
 }
 
-async fn get_latest_hash(
-    State(_state): State<AppState>,
-    Json(payload): Json<RpcNetwork>,
-) -> Response {
-    let rpc_url = format!("https://api.{}.solana.com", payload.network);
-    let rpc = RpcClient::new(rpc_url);
+#[derive(Serialize, Deserialize)]
+struct BuildOnboardRequest {
+    #[serde(default)]
+    network: String,
+    #[serde(flatten)]
+    target: RpcTarget,
+    /// The new user's wallet; the local wallet sponsors everything
+    wallet_address: String,
+    yid: String,
+    #[serde(default)]
+    notes: Option<String>,
+    /// SOL for the new wallet's future fees; defaults to FUEGO_ONBOARD_SOL
+    #[serde(default)]
+    sol_amount: Option<String>,
+    /// Optional USDC welcome transfer, UI amount
+    #[serde(default)]
+    welcome_usdc: Option<String>,
+    #[serde(default)]
+    fee_amount: Option<String>,
+    #[serde(default)]
+    memo_privacy: Option<String>,
+    #[serde(default)]
+    treat_warnings_as_errors: bool,
+    /// Fetch a new blockhash instead of reusing one cached in the last few seconds
+    #[serde(default)]
+    fresh_blockhash: bool,
+}
 
-    match rpc.get_latest_blockhash() {
-        Ok(blockhash) => Json(json!({
-            "success": true,
-            "data": {
-                "blockhash": blockhash.to_string(),
-                "network": payload.network
-            }
-        })).into_response(),
-        Err(e) => Json(json!({
-            "success": false,
-            "error": format!("Failed to get latest blockhash: {}", e)
-        })).into_response(),
-    }
+#[derive(Serialize, Deserialize)]
+struct BuildTimelockedTransferRequest {
+    #[serde(default)]
+    network: String,
+    #[serde(flatten)]
+    target: RpcTarget,
+    from_address: String,
+    to_address: String,
+    /// UI amount; may be omitted when amount_raw is given
+    #[serde(default)]
+    amount: String,
+    /// Base units as an integer string; wins over amount
+    #[serde(default)]
+    amount_raw: Option<String>,
+    /// SOL (default), USDC or USDT
+    #[serde(default)]
+    token: Option<String>,
+    /// Unix time; the transfer is not submitted before the cluster clock reaches it
+    unlock_at: i64,
+    yid: String,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    fee_amount: Option<String>,
+    #[serde(default)]
+    rounding: Option<String>,
+    #[serde(default)]
+    memo_privacy: Option<String>,
+    /// Sign with the local wallet now; only when it is the sender
+    #[serde(default)]
+    sign_with_local_wallet: bool,
+    /// Sign even though wallet.json disagrees with its own key (see GET /wallet/verify)
+    #[serde(default)]
+    force: bool,
+    #[serde(default)]
+    treat_warnings_as_errors: bool,
 }
 
-async fn get_sol_balance(
-    State(_state): State<AppState>,
-    Json(payload): Json<GetBalanceRequest>,
-) -> Response {
-    let rpc_url = format!("https://api.{}.solana.com", payload.network);
-    let rpc = RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed());
+#[derive(Serialize, Deserialize)]
+struct ScheduledTransferSignatureRequest {
+    /// The intent's transaction, base64, with the sender's signature added
+    #[serde(default)]
+    transaction: Option<String>,
+    /// Add the local wallet's signature instead; only when it is the sender
+    #[serde(default)]
+    sign_with_local_wallet: bool,
+    #[serde(default)]
+    force: bool,
+}
 
-    let pubkey = match string_to_pub_key(&payload.address) {
-        Ok(pk) => pk,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid wallet address"
-            }))
-            .into_response();
-        }
-    };
+#[derive(Deserialize)]
+struct ScheduledTransfersQuery {
+    /// Only return intents with this status
+    #[serde(default)]
+    status: Option<String>,
+}
 
-    match rpc.get_balance(&pubkey) {
-        Ok(lamports) => {
-            let sol = lamports as f64 / 1_000_000_000.0;
-            Json(json!({
-                "success": true,
-                "data": {
-                    "address": payload.address,
-                    "lamports": lamports,
-                    "sol": sol,
-                    "network": payload.network
-                }
-            }))
-            .into_response()
-        }
-        Err(e) => Json(json!({
-            "success": false,
-            "error": format!("Failed to get balance: {}", e)
-        }))
-        .into_response(),
-    }
+#[derive(Serialize, Deserialize)]
+struct TransferStatsRequest {
+    #[serde(default)]
+    network: String,
+    #[serde(flatten)]
+    target: RpcTarget,
+    /// Wallet whose outgoing transfers are counted; defaults to the local wallet
+    #[serde(default)]
+    address: Option<String>,
+    /// Any of "counterparty" and "token"
+    #[serde(default)]
+    group_by: Vec<String>,
+    /// "day", "week" or "month"
+    #[serde(default)]
+    bucket: Option<String>,
+    /// Unix seconds, inclusive
+    #[serde(default)]
+    from: Option<i64>,
+    /// Unix seconds, exclusive
+    #[serde(default)]
+    to: Option<i64>,
+    #[serde(default)]
+    token: Option<String>,
+    /// Destination address
+    #[serde(default)]
+    counterparty: Option<String>,
+    /// Backfill the index from the address's chain history before aggregating
+    #[serde(default)]
+    refresh: bool,
+    /// Signatures a refresh walks back through (default 1000, at most 5000)
+    #[serde(default)]
+    refresh_depth: Option<usize>,
 }
 
-async fn get_default_network(State(state): State<AppState>) -> impl IntoResponse {
-    Json(json!({
-        "network": state.default_network
-    }))
+#[derive(Serialize, Deserialize)]
+struct TransactionDetailRequest {
+    #[serde(default)]
+    network: String,
+    #[serde(flatten)]
+    target: RpcTarget,
+    signature: String,
+    /// "finalized" bypasses any copy cached at a lower commitment
+    #[serde(default)]
+    commitment: Option<String>,
+    /// Report whether the transaction has at least this many confirmations
+    #[serde(default)]
+    min_confirmations: Option<u64>,
+    /// Comma-separated dotted paths of the transaction to keep, e.g. "slot,meta.fee"
+    #[serde(default)]
+    fields: Option<String>,
 }
 
-async fn get_usdc_balance(
-    State(_state): State<AppState>,
-    Json(payload): Json<GetTokenBalanceRequest>,
-) -> Response {
-    let rpc_url = format!("https://api.{}.solana.com", payload.network);
-    let commitment = get_commitment_config(&payload.commitment);
-    let rpc = RpcClient::new_with_commitment(rpc_url, commitment);
+/// Server operating mode. In dry-run every write path is simulated and nothing is broadcast.
+#[derive(Clone, Copy, PartialEq)]
+enum ServerMode {
+    Live,
+    DryRun,
+}
 
-    let pubkey = match string_to_pub_key(&payload.address) {
-        Ok(pk) => pk,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid wallet address"
-            }))
-            .into_response();
+impl ServerMode {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "live" => Some(ServerMode::Live),
+            "dry-run" => Some(ServerMode::DryRun),
+            _ => None,
         }
-    };
+    }
 
-    let usdc_mint = match string_to_pub_key(USDC_MINT) {
-        Ok(mint) => mint,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Failed to parse USDC mint"
-            }))
-            .into_response();
+    /// FUEGO_MODE, else `mode` from the config file, else live. A value that is neither "live"
+    /// nor "dry-run" is an error rather than live, since a typo there would broadcast for real.
+    fn load(config: &config::ServerConfig) -> Result<Self, String> {
+        match std::env::var("FUEGO_MODE").ok().filter(|v| !v.trim().is_empty()) {
+            Some(value) => ServerMode::parse(value.trim())
+                .ok_or_else(|| format!("FUEGO_MODE '{}' is not \"live\" or \"dry-run\"", value)),
+            // Checked when the config loaded
+            None => Ok(config.mode.as_deref().and_then(ServerMode::parse).unwrap_or(ServerMode::Live)),
         }
-    };
+    }
 
-    let associated_token_account = get_associated_token_address(&utils::to_spl_pubkey(&pubkey), &utils::to_spl_pubkey(&usdc_mint));
+    fn as_str(&self) -> &'static str {
+        match self {
+            ServerMode::Live => "live",
+            ServerMode::DryRun => "dry-run",
+        }
+    }
 
-    match rpc.get_token_account_balance(&utils::from_spl_pubkey(&associated_token_account)) {
-        Ok(balance) => Json(json!({
-            "success": true,
-            "data": {
-                "address": payload.address,
-                "amount": balance.amount,
-                "decimals": balance.decimals,
-                "ui_amount": balance.ui_amount_string,
-                "network": payload.network,
-                "token": "USDC"
-            }
-        }))
-        .into_response(),
-        Err(e) => Json(json!({
-            "success": false,
-            "error": format!("Failed to get USDC balance: {}", e)
-        }))
-        .into_response(),
+    fn is_dry_run(&self) -> bool {
+        *self == ServerMode::DryRun
     }
 }
 
-async fn get_usdt_balance(
-    State(_state): State<AppState>,
-    Json(payload): Json<GetTokenBalanceRequest>,
-) -> Response {
-    let rpc_url = format!("https://api.{}.solana.com", payload.network);
-    let commitment = get_commitment_config(&payload.commitment);
-    let rpc = RpcClient::new_with_commitment(rpc_url, commitment);
+/// How much of the transfer the on-chain memo reveals. Minimal drops the from/to addresses,
+/// which the transaction already carries anyway.
+#[derive(Clone, Copy, PartialEq)]
+enum MemoPrivacy {
+    Full,
+    Minimal,
+}
 
-    let pubkey = match string_to_pub_key(&payload.address) {
-        Ok(pk) => pk,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid wallet address"
-            }))
-            .into_response();
+impl MemoPrivacy {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "full" => Ok(MemoPrivacy::Full),
+            "minimal" => Ok(MemoPrivacy::Minimal),
+            other => Err(format!("Invalid memo_privacy '{}': expected \"full\" or \"minimal\"", other)),
         }
-    };
+    }
 
-    let usdt_mint = match string_to_pub_key(USDT_MINT) {
-        Ok(mint) => mint,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Failed to parse USDT mint"
-            }))
-            .into_response();
+    /// Server default from FUEGO_MEMO_PRIVACY; full unless set to "minimal".
+    fn from_env() -> Self {
+        match std::env::var("FUEGO_MEMO_PRIVACY").as_deref() {
+            Ok("minimal") => MemoPrivacy::Minimal,
+            _ => MemoPrivacy::Full,
         }
-    };
-
-    let associated_token_account = get_associated_token_address(&utils::to_spl_pubkey(&pubkey), &utils::to_spl_pubkey(&usdt_mint));
+    }
 
-    match rpc.get_token_account_balance(&utils::from_spl_pubkey(&associated_token_account)) {
-        Ok(balance) => Json(json!({
-            "success": true,
-            "data": {
-                "address": payload.address,
-                "amount": balance.amount,
-                "decimals": balance.decimals,
-                "ui_amount": balance.ui_amount_string,
-                "network": payload.network,
-                "token": "USDT"
-            }
-        }))
-        .into_response(),
-        Err(e) => Json(json!({
-            "success": false,
-            "error": format!("Failed to get USDT balance: {}", e)
-        }))
-        .into_response(),
+    fn as_str(&self) -> &'static str {
+        match self {
+            MemoPrivacy::Full => "full",
+            MemoPrivacy::Minimal => "minimal",
+        }
     }
 }
 
-async fn build_transfer_usdc(
-    State(_state): State<AppState>,
-    Json(payload): Json<TransferUsdcRequest>,
-) -> Response {
-    // Fetch fresh blockhash
-    let rpc_url = format!("https://api.{}.solana.com", payload.network);
-    let rpc = RpcClient::new(rpc_url);
+// State to hold RPC clients (could be expanded for caching)
+#[derive(Clone)]
+struct AppState {
+    /// Network for requests that omit one (--network, FUEGO_DEFAULT_NETWORK, server.json, else mainnet-beta)
+    default_network: String,
+    /// Refuse requests that omit the network instead of using the default
+    require_explicit_network: bool,
+    /// Where default_network came from, for GET /network
+    default_network_source: &'static str,
+    mode: ServerMode,
+    sessions: sessions::SessionStore,
+    /// When set (FUEGO_REQUIRE_SESSIONS=true), money-moving endpoints reject requests without a session
+    require_sessions: bool,
+    /// Default memo privacy when a transfer request doesn't specify one
+    memo_privacy: MemoPrivacy,
+    alerts: alerts::AlertLog,
+    /// Allow/deny rules for x402 targets and webhooks
+    egress: egress::EgressPolicy,
+    /// Bearer/HMAC keys from FUEGO_AUTH_KEYS; no keys means no authentication
+    auth: auth::Auth,
+    /// Advisory heuristics over wallet activity; flags become `warnings` and alerts
+    anomaly: anomaly::AnomalyDetector,
+    /// Configured RPC endpoints per network plus the latency/error stats used to pick read endpoints
+    rpc: rpc::RpcPool,
+    /// Named endpoints (URLs with API keys) from ~/.fuego/server.json, picked with `provider`
+    providers: providers::Providers,
+    /// Per-network default commitment for reads and submissions (FUEGO_COMMITMENT, FUEGO_CONFIRMATION)
+    commitment: commitment::CommitmentDefaults,
+    builds: builds::BuildStore,
+    /// Daily sponsorship budget for POST /build-onboard
+    onboard: onboard::OnboardBudget,
+    /// Devnet test-token faucet; None unless a mint and its authority keypair are configured
+    faucet: Option<faucet::DevnetFaucet>,
+    /// Most transactions /balance-at will walk back through (FUEGO_BALANCE_AT_MAX_SCAN)
+    balance_at_max_scan: usize,
+    /// Most addresses one /balances-batch request may ask for (FUEGO_BALANCES_BATCH_MAX)
+    balances_batch_max: usize,
+    submit_queue: submit_queue::SubmitQueue,
+    /// Submitted signatures awaiting confirmation; feeds GET /metrics
+    pending: pending::PendingTracker,
+    /// Decimals and token program per (network, mint); cleared by POST /admin/reload
+    mints: mints::MintCache,
+    /// getTransaction results shared by /balance-at and /transaction
+    tx_cache: tx_cache::TxCache,
+    /// Recent blockhash per RPC endpoint, shared by the builders and /latest-hash
+    blockhashes: blockhash_cache::BlockhashCache,
+    /// SOL balance guardrail for the local wallet that pays fees
+    fee_wallet: fee_wallet::FeeWalletGuard,
+    /// Reject unknown request fields on every request (FUEGO_STRICT_JSON=true), not just ones sending X-Fuego-Strict
+    strict_json: bool,
+    /// POST /wallet/export is disabled unless FUEGO_ALLOW_KEY_EXPORT=true
+    allow_key_export: bool,
+    last_key_export: Arc<Mutex<Option<Instant>>>,
+    /// Durable-nonce transfers waiting for their unlock time
+    timelocks: timelock::TimelockStore,
+    /// Read-only share links and the key that signs them
+    share_links: share_links::ShareLinks,
+    /// OTLP span export; a no-op unless FUEGO_OTLP_ENDPOINT is set
+    tracer: trace::Tracer,
+    /// ~/.fuego/server.json (or FUEGO_CONFIG) as loaded at startup
+    config: Arc<config::ServerConfig>,
+    /// Body size and time limits, resolved from the environment and config
+    limits: limits::RequestLimits,
+    rate_limiter: rate_limit::RateLimiter,
+    /// Browser origins, methods and headers allowed cross-origin (`cors` in server.json)
+    cors: cors::CorsPolicy,
+    /// Where the server listens; off loopback, API keys can't be reloaded away
+    listen: SocketAddr,
+}
 
-    let blockhash = match rpc.get_latest_blockhash() {
-        Ok(bh) => bh,
-        Err(e) => {
-            return Json(json!({
-                "success": false,
-                "error": format!("Failed to fetch blockhash: {}", e)
-            }))
-            .into_response();
-        }
-    };
+#[derive(Deserialize)]
+struct AlertsQuery {
+    #[serde(default)]
+    limit: Option<usize>,
+}
 
-    // Parse addresses
-    let from_pubkey = match string_to_pub_key(&payload.from_address) {
-        Ok(pk) => pk,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid from_address"
-            }))
-            .into_response();
-        }
-    };
+#[derive(Serialize, Deserialize)]
+struct DevnetMintRequest {
+    #[serde(default)]
+    network: String,
+    /// Refused: the faucet only mints through the configured endpoints
+    #[serde(flatten)]
+    target: RpcTarget,
+    to_address: String,
+    amount: String, // UI amount of the configured test mint
+}
 
-    let to_pubkey = match string_to_pub_key(&payload.to_address) {
-        Ok(pk) => pk,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid to_address"
-            }))
-            .into_response();
-        }
-    };
+#[derive(Deserialize)]
+struct InflightQuery {
+    /// Only return records with this status (in_flight, completed, failed, interrupted)
+    #[serde(default)]
+    status: Option<String>,
+}
 
-    let usdc_mint = match string_to_pub_key(USDC_MINT) {
-        Ok(mint) => mint,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid USDC mint"
-            }))
-            .into_response();
+#[derive(Serialize, Deserialize)]
+struct CreateSessionRequest {
+    /// Spending caps per token symbol as UI amounts, e.g. {"USDC": "20"}
+    caps: HashMap<String, String>,
+    #[serde(default = "default_session_ttl")]
+    expires_in_seconds: i64,
+    /// Endpoints the session may be used on; defaults to every money-moving endpoint
+    #[serde(default)]
+    allowed_endpoints: Option<Vec<String>>,
+}
+
+fn default_session_ttl() -> i64 {
+    3600
+}
+
+#[derive(Serialize, Deserialize)]
+struct CreateShareLinkRequest {
+    address: String,
+    #[serde(default)]
+    network: String,
+    /// What the link shows: "history", "balances", "transaction"; defaults to history only
+    #[serde(default)]
+    views: Option<Vec<String>>,
+    /// Unix seconds bounding which transactions the link shows
+    #[serde(default)]
+    from: Option<i64>,
+    #[serde(default)]
+    until: Option<i64>,
+    /// Defaults to 7 days, at most 90
+    #[serde(default)]
+    expires_in_seconds: Option<i64>,
+    /// Note for the link list, e.g. who it was shared with
+    #[serde(default)]
+    label: Option<String>,
+}
+
+/// Client commitment for a level resolved by `state.commitment` (request > network default > confirmed).
+fn get_commitment_config(commitment: &str) -> CommitmentConfig {
+    match commitment {
+        "processed" => CommitmentConfig::processed(),
+        "finalized" => CommitmentConfig::finalized(),
+        _ => CommitmentConfig::confirmed(),
+    }
+}
+
+/// Check wallet.json's address against its own key and wallet-config.json. None when there is no wallet.
+fn verify_local_wallet() -> Option<Result<wallets::WalletVerification, String>> {
+    let home = utils::fuego_home();
+    let content = fs::read_to_string(home.join("wallet.json")).ok()?;
+    let wallet: WalletStore = match serde_json::from_str(&content) {
+        Ok(w) => w,
+        Err(e) => return Some(Err(format!("Invalid wallet.json: {}", e))),
+    };
+    let config_address = fs::read_to_string(home.join("wallet-config.json"))
+        .ok()
+        .and_then(|c| serde_json::from_str::<WalletConfig>(&c).ok())
+        .map(|c| c.wallet_address);
+    Some(wallets::verify_keys(&wallet.private_key, &wallet.address, config_address.as_deref()))
+}
+
+/// Signing endpoints refuse while the local wallet files disagree with the key, unless forced.
+fn wallet_mismatch_refusal(force: bool) -> Option<ApiError> {
+    if force {
+        return None;
+    }
+    match verify_local_wallet() {
+        Some(Ok(verification)) if !verification.ok => Some(ApiError::conflict(format!("Local wallet files disagree with the key: {} Pass \"force\": true to proceed anyway.", verification.explain()))
+            .code("wallet_mismatch")
+            .with("mismatches", &verification.mismatches)),
+        _ => None,
+    }
+}
+
+/// Axum's own `Json` rejections are plain text; this gives them the usual error body, with the
+/// codes strict mode uses.
+fn json_rejection_response(rejection: axum::extract::rejection::JsonRejection) -> Response {
+    use axum::extract::rejection::JsonRejection;
+    match rejection {
+        JsonRejection::JsonDataError(e) => ApiError::bad_request(format!("Invalid request: {}", e.body_text())).code("invalid_request"),
+        JsonRejection::JsonSyntaxError(e) => ApiError::bad_request(format!("Invalid JSON body: {}", e.body_text())).code("invalid_json"),
+        JsonRejection::MissingJsonContentType(e) => {
+            ApiError::new(e.status(), "Send the body with Content-Type: application/json").code("invalid_json")
+        }
+        JsonRejection::BytesRejection(e) => ApiError::new(e.status(), e.body_text()).code("body_too_large"),
+        other => ApiError::new(other.status(), other.body_text()).code("invalid_request"),
+    }
+    .into_response()
+}
+
+/// JSON body extractor. Lenient like `Json` by default; in strict mode, fields the request struct
+/// doesn't define are rejected with code "unknown_fields" instead of being ignored.
+struct StrictJson<T>(T);
+
+#[axum::async_trait]
+impl<T> FromRequest<AppState> for StrictJson<T>
+where
+    T: serde::de::DeserializeOwned + Serialize,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: axum::extract::Request, state: &AppState) -> Result<Self, Self::Rejection> {
+        if !state.strict_json && !strict_json::requested(req.headers()) {
+            return Json::<T>::from_request(req, state)
+                .await
+                .map(|Json(payload)| StrictJson(payload))
+                .map_err(json_rejection_response);
+        }
+
+        let body = axum::body::Bytes::from_request(req, state)
+            .await
+            .map_err(|e| ApiError::new(e.status(), e.body_text()).code("body_too_large").into_response())?;
+        let input: serde_json::Value = serde_json::from_slice(&body).map_err(|e| {
+            ApiError::bad_request(format!("Invalid JSON body: {}", e)).code("invalid_json").into_response()
+        })?;
+        let (payload, unknown) = strict_json::parse::<T>(&input).map_err(|e| {
+            ApiError::bad_request(format!("Invalid request: {}", e)).code("invalid_request").into_response()
+        })?;
+        if !unknown.is_empty() {
+            return Err(ApiError::bad_request(format!("Unknown field(s): {}", unknown.join(", ")))
+                .code("unknown_fields")
+                .with("unknown_fields", &unknown)
+                .into_response());
         }
+        Ok(StrictJson(payload))
+    }
+}
+
+fn session_error(err: sessions::SessionError) -> ApiError {
+    let remaining = match &err {
+        sessions::SessionError::Exceeded { remaining, .. } => Some(remaining.clone()),
+        _ => None,
     };
+    ApiError::from(err).with("remaining", remaining)
+}
 
-    // Derive token accounts
-    let source_token_account = get_associated_token_address(&utils::to_spl_pubkey(&from_pubkey), &utils::to_spl_pubkey(&usdc_mint));
-    let destination_token_account = get_associated_token_address(&utils::to_spl_pubkey(&to_pubkey), &utils::to_spl_pubkey(&usdc_mint));
+/// Charge the caller's spending session (X-Fuego-Session header) for a money-moving action.
+/// Returns the charged session id, or None when no session was presented and none is required.
+fn charge_session(
+    state: &AppState,
+    headers: &HeaderMap,
+    endpoint: &str,
+    symbol: &str,
+    amount: u64,
+) -> Result<Option<String>, sessions::SessionError> {
+    let token = match headers.get(sessions::SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+        Some(token) => token,
+        None if state.require_sessions => return Err(sessions::SessionError::Missing),
+        None => return Ok(None),
+    };
+    match state.sessions.charge(token, endpoint, symbol, amount) {
+        Ok(id) => Ok(Some(id)),
+        Err(err) => {
+            let attempted = utils::base_units_to_ui(amount, sessions::decimals_for_symbol(symbol).unwrap_or(0));
+            match &err {
+                sessions::SessionError::Exceeded { .. } => state.alerts.emit(
+                    "session_allowance_exceeded",
+                    alerts::Severity::Medium,
+                    format!("Spending session cap hit on /{}", endpoint),
+                    json!({ "endpoint": endpoint, "token": symbol, "attempted_amount": attempted }),
+                ),
+                sessions::SessionError::Revoked | sessions::SessionError::Expired => state.alerts.emit(
+                    "session_reuse",
+                    alerts::Severity::Low,
+                    format!("{} on /{}", err.message(), endpoint),
+                    json!({ "endpoint": endpoint, "token": symbol, "attempted_amount": attempted }),
+                ),
+                _ => {}
+            }
+            Err(err)
+        }
+    }
+}
 
-    // Parse amount (6 decimals for USDC)
-    let amount: u64 = match payload.amount.parse::<f64>() {
-        Ok(val) => (val * 1_000_000.0) as u64,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid amount"
-            }))
-            .into_response();
+/// Request-level memo privacy wins over the server default.
+fn resolve_memo_privacy(requested: &Option<String>, default: MemoPrivacy) -> Result<MemoPrivacy, String> {
+    match requested {
+        Some(value) => MemoPrivacy::parse(value),
+        None => Ok(default),
+    }
+}
+
+/// Remember a build's economic summary and, when the caller passed an earlier build id, diff against it.
+fn record_build(state: &AppState, summary: builds::BuildSummary, previous_build_id: &Option<String>) -> serde_json::Value {
+    let summary_hash = summary.hash();
+    let previous = previous_build_id.as_ref().map(|id| state.builds.get(id));
+    let (diff, materially_changed) = match &previous {
+        Some(Some(prev)) => {
+            let changes = summary.diff(prev);
+            let changed = !changes.is_empty();
+            (json!(changes), json!(changed))
         }
+        _ => (serde_json::Value::Null, serde_json::Value::Null),
     };
+    let build_id = state.builds.insert(summary);
 
-    // Build memo with new format: fuego|USDC|f:{from}|t:{to}|a:{amount}|yid:{yid}|n:{notes}
-    let memo_text = match build_memo("USDC", &payload.from_address, &payload.to_address, amount, &payload.yid, payload.notes.as_deref()) {
-        Ok(memo) => memo,
-        Err(e) => {
-            return Json(json!({
-                "success": false,
-                "error": e
-            }))
-            .into_response();
+    json!({
+        "build_id": build_id,
+        "summary_hash": summary_hash,
+        "previous_build_id": previous_build_id,
+        "previous_build_found": previous.as_ref().map(|p| p.is_some()),
+        "diff": diff,
+        "materially_changed": materially_changed
+    })
+}
+
+/// Warnings every transfer builder checks: rounding applied, exchange registry notes and a low fee wallet.
+fn build_warnings(state: &AppState, from_address: &str, rounding: &serde_json::Value, exchange: &serde_json::Value) -> warnings::Warnings {
+    let mut warnings = warnings::Warnings::default();
+    warnings.rounding(rounding);
+    warnings.exchange(exchange);
+    if state.fee_wallet.is_low(from_address) {
+        warnings.fee_wallet_low(from_address);
+    }
+    warnings
+}
+
+/// Refusal for callers that set treat_warnings_as_errors. Returned before any session is charged.
+fn warnings_error(warnings: &warnings::Warnings) -> ApiError {
+    ApiError::bad_request(format!("Refused because treat_warnings_as_errors is set: {}", warnings.codes().join(", ")))
+        .code("warnings_as_errors")
+        .with("warnings", warnings)
+}
+
+/// Convert a builder's UI amount to base units under the request's rounding policy. Returns the
+/// amount plus a disclosure of the exact base units sent and whether rounding changed them.
+/// `amount_raw`, when given, is taken as base units directly; an `amount` sent alongside it must
+/// come to the same value. Every transfer builder goes through here so zero, negative and oversized
+/// amounts are refused the same way everywhere; errors carry a stable code for `amount_error_response`.
+fn parse_transfer_amount(
+    asset: amount::Asset,
+    amount: &str,
+    amount_raw: &Option<String>,
+    decimals: u8,
+    rounding: &Option<String>,
+) -> Result<(amount::TokenAmount, serde_json::Value), (&'static str, String)> {
+    let policy = utils::RoundingPolicy::parse(rounding.as_deref()).map_err(|e| ("invalid_rounding", e))?;
+    let amount_given = !amount.trim().is_empty();
+    let (parsed, applied) = match amount_raw {
+        Some(raw) => {
+            let from_raw = amount::TokenAmount::parse_raw(asset.clone(), raw, decimals).map_err(|e| (e.code(), e.message()))?;
+            if amount_given {
+                let (from_ui, _) = amount::TokenAmount::parse_ui(asset, amount, decimals, policy).map_err(|e| (e.code(), e.message()))?;
+                if from_ui != from_raw {
+                    return Err((
+                        "amount_conflict",
+                        format!(
+                            "amount {} is {} base units but amount_raw is {}; send one or make them agree",
+                            amount.trim(),
+                            from_ui.raw(),
+                            raw.trim()
+                        ),
+                    ));
+                }
+            }
+            (from_raw, amount::Rounding::default())
         }
+        None if !amount_given => return Err(("amount_missing", "Pass amount or amount_raw".to_string())),
+        None => amount::TokenAmount::parse_ui(asset, amount, decimals, policy).map_err(|e| (e.code(), e.message()))?,
     };
+    if parsed.is_zero() {
+        let message = if applied.rounded {
+            format!("Amount {} is zero after {} rounding", amount.trim(), policy.as_str())
+        } else {
+            "Amount is zero; nothing would be transferred".to_string()
+        };
+        return Err(("amount_zero", message));
+    }
+    let direction = applied.rounded.then_some("down");
+    let disclosure = json!({
+        "policy": policy.as_str(),
+        "requested": amount_given.then(|| amount.trim()),
+        "requested_raw": amount_raw.as_deref().map(str::trim),
+        "amount_base_units": parsed.raw(),
+        "amount_ui": parsed.ui(),
+        "rounding_applied": applied.rounded,
+        "direction": direction
+    });
+    Ok((parsed, disclosure))
+}
 
-    // Build instructions
-    let from_spl = utils::to_spl_pubkey(&from_pubkey);
-    let transfer_instruction = match token_instruction::transfer(
-        &spl_token::ID,
-        &source_token_account,
-        &destination_token_account,
-        &from_spl,
-        &[&from_spl],
-        amount,
-    ) {
-        Ok(instr) => instr,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Failed to create transfer instruction"
-            }))
-            .into_response();
+/// `amount: "max"` on a transfer builder: send the source's whole balance, read at build time.
+fn is_sweep(amount: &str) -> bool {
+    amount.trim().eq_ignore_ascii_case("max")
+}
+
+/// The amount and amount_raw a builder hands to `parse_transfer_amount`: the request's own, or for
+/// a sweep the base units `balance` reads. Also returns whether this is a sweep. `close_account`
+/// only makes sense after a sweep, and a sweep can't be grossed up.
+async fn resolve_sweep(
+    amount: &str,
+    amount_raw: &Option<String>,
+    close_account: bool,
+    gross_up: bool,
+    balance: impl Future<Output = Result<u64, (&'static str, String)>>,
+) -> Result<(String, Option<String>, bool), (&'static str, String)> {
+    if !is_sweep(amount) {
+        if close_account {
+            return Err((
+                "close_requires_max",
+                "close_account needs amount \"max\"; an account can only be closed once it is empty".to_string(),
+            ));
+        }
+        return Ok((amount.to_string(), amount_raw.clone(), false));
+    }
+    if amount_raw.is_some() {
+        return Err(("amount_conflict", "amount \"max\" sends the whole balance; drop amount_raw".to_string()));
+    }
+    if gross_up {
+        return Err(("amount_conflict", "amount \"max\" can't be grossed up; the whole balance is already sent".to_string()));
+    }
+    match balance.await? {
+        0 => Err(("amount_zero", "Nothing to sweep: the balance is zero".to_string())),
+        units => Ok((String::new(), Some(units.to_string()), true)),
+    }
+}
+
+/// A token account's balance in base units, for a sweep.
+async fn token_account_balance(rpc: &RpcClient, account: &solana_sdk::pubkey::Pubkey) -> Result<u64, (&'static str, String)> {
+    let balance = rpc
+        .get_token_account_balance(account)
+        .await
+        .map_err(|e| ("sweep_balance_unavailable", format!("Failed to read the source token account: {}", e)))?;
+    balance
+        .amount
+        .parse::<u64>()
+        .map_err(|_| ("sweep_balance_unavailable", format!("Unreadable token balance {}", balance.amount)))
+}
+
+/// A token account's balance in base units for a pre-flight check; a missing account holds zero.
+async fn token_balance_or_zero(rpc: &RpcClient, account: &solana_sdk::pubkey::Pubkey) -> Result<u64, String> {
+    match rpc.get_token_account_balance(account).await {
+        Ok(balance) => balance.amount.parse::<u64>().map_err(|_| format!("Unreadable token balance {}", balance.amount)),
+        Err(e) if rpc::is_account_not_found(&e) => Ok(0),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// How a builder's pre-flight balance check went, reported as `balance_check`.
+#[derive(Clone, Copy)]
+enum BalanceCheck {
+    Passed,
+    /// skip_balance_check was set
+    Skipped,
+    /// The balance couldn't be read; the build went ahead unchecked
+    Unavailable,
+}
+
+impl BalanceCheck {
+    /// Combined outcome of two checks on one build; unavailable if either was.
+    fn and(self, other: BalanceCheck) -> BalanceCheck {
+        match (self, other) {
+            (BalanceCheck::Skipped, _) | (_, BalanceCheck::Skipped) => BalanceCheck::Skipped,
+            (BalanceCheck::Unavailable, _) | (_, BalanceCheck::Unavailable) => BalanceCheck::Unavailable,
+            _ => BalanceCheck::Passed,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            BalanceCheck::Passed => "passed",
+            BalanceCheck::Skipped => "skipped",
+            BalanceCheck::Unavailable => "unavailable",
+        }
+    }
+}
+
+/// What a transfer needs against what the source holds, when the source comes up short.
+struct Shortfall {
+    required: amount::TokenAmount,
+    available: amount::TokenAmount,
+}
+
+/// Refuse a build whose source can't cover it, rather than hand back a transaction that fails
+/// on-chain with an opaque program error. Balances can change before submit, so this is a soft
+/// check: `skip` turns it off, and a sweep (whose amount came from the balance) always passes.
+async fn preflight_balance(
+    skip: bool,
+    swept: bool,
+    required: &amount::TokenAmount,
+    available: impl Future<Output = Result<u64, String>>,
+) -> Result<BalanceCheck, Shortfall> {
+    if skip {
+        return Ok(BalanceCheck::Skipped);
+    }
+    if swept {
+        return Ok(BalanceCheck::Passed);
+    }
+    let available = match available.await {
+        Ok(units) => required.with_base_units(units),
+        Err(e) => {
+            eprintln!("Pre-flight balance check skipped: {}", providers::scrub(&e));
+            return Ok(BalanceCheck::Unavailable);
         }
     };
+    match available.checked_sub(required) {
+        Some(_) => Ok(BalanceCheck::Passed),
+        None => Err(Shortfall { required: required.clone(), available }),
+    }
+}
 
-    let memo_instruction = spl_memo::build_memo(memo_text.as_bytes(), &[]);
+fn insufficient_funds_error(token: &str, shortfall: &Shortfall) -> ApiError {
+    let missing = shortfall.required.checked_sub(&shortfall.available).map(|m| m.ui()).unwrap_or_default();
+    ApiError::bad_request(format!(
+        "Insufficient {}: this transfer needs {} but the source holds {} (short {}); pass skip_balance_check to build anyway",
+        token,
+        shortfall.required.ui(),
+        shortfall.available.ui(),
+        missing
+    ))
+    .code("insufficient_funds")
+    .with("token", token)
+    .with("required", shortfall.required.ui())
+    .with("available", shortfall.available.ui())
+    .with("required_raw", shortfall.required.raw())
+    .with("available_raw", shortfall.available.raw())
+}
 
-    // Compute budget instructions
-    let compute_limit = ComputeBudgetInstruction::set_compute_unit_limit(100_000);
-    let unit_price = ComputeBudgetInstruction::set_compute_unit_price(
-        payload.fee_amount
-            .as_ref()
-            .and_then(|f| f.parse::<u64>().ok())
-            .unwrap_or(0)
-    );
+/// The endpoint a request talks to: its own `rpc_url` when it sends one, otherwise the configured
+/// primary for `network` (or the pool's read choice when `read`). Every handler resolves its URL here.
+/// A URL the operator didn't configure must pass the egress policy, like an x402 destination, and
+/// the request's calls to it only connect to the addresses that check approved.
+async fn request_rpc_url(state: &AppState, network: &str, target: &RpcTarget, read: bool) -> Result<String, ApiError> {
+    let url = match target.rpc_url.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+        Some(url) => rpc::validate_url(url).map_err(|e| ApiError::bad_request(e).code("invalid_rpc_url"))?,
+        None if read => return Ok(state.rpc.select_read(network)),
+        None => return Ok(state.rpc.primary(network)),
+    };
+    let from_provider = target.provider.as_deref().and_then(|name| state.providers.get(name)).is_some_and(|p| p.url() == url);
+    if from_provider || state.rpc.endpoints(network).contains(&url) {
+        return Ok(url);
+    }
+    let destination = state.egress.check(&url, "rpc").await?;
+    let http = state
+        .egress
+        .client_builder(&destination)
+        .build()
+        .map_err(|e| ApiError::internal(format!("Failed to build RPC client: {}", e)).code("internal_error"))?;
+    rpc::pin(&url, http);
+    Ok(url)
+}
 
-    // Create transaction message with fresh blockhash
-    let transfer_ix = utils::instruction_from_spl(&transfer_instruction);
-    let memo_ix = utils::instruction_from_spl(&memo_instruction);
-    let message = Message::new_with_blockhash(
-        &[compute_limit, unit_price, transfer_ix, memo_ix],
-        Some(&from_pubkey),
-        &blockhash,
-    );
+struct TargetError {
+    code: &'static str,
+    message: String,
+}
 
-    let transaction = Transaction::new_unsigned(message);
+/// Fill in an omitted `network` with the server default, unless explicit networks are required,
+/// and refuse names that are neither a public cluster nor configured in FUEGO_RPC_ENDPOINTS.
+fn resolve_network(state: &AppState, network: &mut String) -> Result<(), TargetError> {
+    if network.is_empty() {
+        if state.require_explicit_network {
+            return Err(TargetError {
+                code: "missing_network",
+                message: "network is required: this server doesn't fall back to a default network".to_string(),
+            });
+        }
+        *network = state.default_network.clone();
+    }
+    match state.rpc.network(network) {
+        Some(_) => Ok(()),
+        None => Err(TargetError {
+            code: "invalid_network",
+            message: format!("Unknown network '{}': use one of {}", network, state.rpc.network_names().join(", ")),
+        }),
+    }
+}
 
-    // Serialize transaction
-    let serialized_tx = match bincode::serialize(&transaction) {
-        Ok(bytes) => bytes,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Failed to serialize transaction"
-            }))
-            .into_response();
+/// Resolve a request's `provider` into its endpoint (as `rpc_url`) and, when the request left
+/// `network` empty, the profile's network. Anything still without a network gets the default.
+fn resolve_rpc_target(state: &AppState, network: &mut String, target: &mut RpcTarget) -> Result<(), TargetError> {
+    let Some(name) = target.provider.as_deref().filter(|p| !p.is_empty()) else {
+        return resolve_network(state, network);
+    };
+    let Some(profile) = state.providers.get(name) else {
+        return Err(TargetError {
+            code: "unknown_provider",
+            message: format!("No provider named '{}' in {}", name, config::config_path().display()),
+        });
+    };
+    if target.rpc_url.is_some() {
+        return Err(TargetError { code: "invalid_rpc_url", message: "Pass either rpc_url or provider, not both".to_string() });
+    }
+    match (&profile.network, network.is_empty()) {
+        (Some(profile_network), true) => *network = profile_network.clone(),
+        (Some(profile_network), false) if profile_network != network => {
+            return Err(TargetError {
+                code: "provider_network_mismatch",
+                message: format!("Provider '{}' serves {}, not {}", name, profile_network, network),
+            });
         }
+        // A network the profile names is the operator's choice; anything else must be a known one
+        (None, _) => resolve_network(state, network)?,
+        _ => {}
+    }
+    target.rpc_url = Some(profile.url().to_string());
+    Ok(())
+}
+
+impl From<TargetError> for ApiError {
+    fn from(error: TargetError) -> Self {
+        ApiError::bad_request(error.message).code(error.code)
+    }
+}
+
+/// Explorer link for a signature on a network that already passed `resolve_network`.
+fn explorer_link(state: &AppState, network: &str, signature: &str) -> String {
+    state.rpc.network(network).unwrap_or(network::Network::Custom(String::new())).explorer_tx_url(signature)
+}
+
+/// Micro-lamports per compute unit for a builder's `fee_amount`: a number, or the name of one of
+/// the configured fee presets. Anything else, or no fee_amount, is `default`.
+fn compute_unit_price(state: &AppState, fee_amount: Option<&str>, default: u64) -> u64 {
+    let Some(fee) = fee_amount.map(str::trim) else {
+        return default;
     };
+    fee.parse::<u64>().ok().or_else(|| state.config.fee_preset(fee)).unwrap_or(default)
+}
 
-    Json(json!({
-        "success": true,
-        "data": {
-            "transaction": serde_json::Value::String(
-                general_purpose::STANDARD.encode(&serialized_tx)
+/// Priority fee in lamports for `compute_unit_limit` units at `compute_unit_price` micro-lamports.
+fn priority_fee_lamports(compute_unit_limit: u32, compute_unit_price: u64) -> u64 {
+    let fee = (compute_unit_limit as u128 * compute_unit_price as u128).div_ceil(1_000_000);
+    u64::try_from(fee).unwrap_or(u64::MAX)
+}
+
+/// What the sender pays in SOL for a build to land, apart from any SOL it transfers.
+#[derive(Serialize)]
+struct NetworkCost {
+    fee_lamports: u64,
+    priority_fee_lamports: u64,
+    /// Rent for a recipient token account the transaction creates
+    rent_lamports: u64,
+    total_lamports: u64,
+    total_sol: String,
+}
+
+impl NetworkCost {
+    fn total(&self) -> amount::TokenAmount {
+        amount::TokenAmount::lamports(self.total_lamports)
+    }
+}
+
+fn estimate_network_cost(compute_unit_limit: u32, compute_unit_price: u64, rent_lamports: u64) -> NetworkCost {
+    let priority_fee_lamports = priority_fee_lamports(compute_unit_limit, compute_unit_price);
+    let total_lamports = LAMPORTS_PER_SIGNATURE.saturating_add(priority_fee_lamports).saturating_add(rent_lamports);
+    NetworkCost {
+        fee_lamports: LAMPORTS_PER_SIGNATURE,
+        priority_fee_lamports,
+        rent_lamports,
+        total_lamports,
+        total_sol: amount::TokenAmount::lamports(total_lamports).ui(),
+    }
+}
+
+/// A token transfer whose sender holds too little SOL for the fees and rent.
+fn insufficient_sol_error(shortfall: &Shortfall, cost: &NetworkCost) -> ApiError {
+    let missing = shortfall.required.checked_sub(&shortfall.available).unwrap_or_else(|| shortfall.required.clone());
+    ApiError::bad_request(format!(
+        "Insufficient SOL for fees: this transfer costs {} SOL in fees and rent but the sender holds {} SOL (short {}); pass skip_balance_check to build anyway",
+        cost.total_sol,
+        shortfall.available.ui(),
+        missing.ui()
+    ))
+    .code("insufficient_sol_for_fees")
+    .with("required", shortfall.required.ui())
+    .with("available", shortfall.available.ui())
+    .with("shortfall", missing.ui())
+    .with("shortfall_lamports", missing.base_units())
+    .with("network_cost", cost)
+}
+
+/// Compute units requested by a plain SOL transfer build.
+const SOL_TRANSFER_COMPUTE_UNITS: u32 = 100_000;
+
+/// How a SOL sweep's amount was arrived at, returned with the build so the signer can check it.
+#[derive(Clone, Copy, Serialize)]
+struct SolSweep {
+    balance_lamports: u64,
+    fee_lamports: u64,
+    priority_fee_lamports: u64,
+    reserve_lamports: u64,
+    sweep_lamports: u64,
+}
+
+/// What a SOL sweep can send: the balance less the signature fee and priority fee, and less the
+/// rent-exempt minimum when `keep_rent_exempt`. Otherwise the source ends at exactly zero, never
+/// in between, where the runtime would reject it.
+async fn sol_sweep(
+    rpc: &RpcClient,
+    from: &solana_sdk::pubkey::Pubkey,
+    compute_unit_price: u64,
+    keep_rent_exempt: bool,
+) -> Result<SolSweep, (&'static str, String)> {
+    let balance = rpc
+        .get_balance(from)
+        .await
+        .map_err(|e| ("sweep_balance_unavailable", format!("Failed to read the SOL balance: {}", e)))?;
+    let reserve = if keep_rent_exempt {
+        rpc.get_minimum_balance_for_rent_exemption(0)
+            .await
+            .map_err(|e| ("sweep_balance_unavailable", format!("Failed to fetch the rent-exempt minimum: {}", e)))?
+    } else {
+        0
+    };
+    let priority_fee = priority_fee_lamports(SOL_TRANSFER_COMPUTE_UNITS, compute_unit_price);
+    let insufficient = || {
+        (
+            "sweep_insufficient_balance",
+            format!(
+                "Balance of {} lamports doesn't cover the {} lamport fee, {} lamport priority fee and {} lamport reserve",
+                balance, LAMPORTS_PER_SIGNATURE, priority_fee, reserve
             ),
-            "blockhash": blockhash.to_string(),
-            "from": payload.from_address,
-            "to": payload.to_address,
-            "amount": payload.amount,
-            "yid": payload.yid,
-            "memo": memo_text,
-            "network": payload.network
-        }
-    }))
-    .into_response()
+        )
+    };
+    let kept = [LAMPORTS_PER_SIGNATURE, priority_fee, reserve]
+        .into_iter()
+        .try_fold(amount::TokenAmount::lamports(0), |total, part| total.checked_add(&amount::TokenAmount::lamports(part)))
+        .ok_or_else(insufficient)?;
+    let sweep = amount::TokenAmount::lamports(balance)
+        .checked_sub(&kept)
+        .filter(|s| !s.is_zero())
+        .ok_or_else(insufficient)?;
+    Ok(SolSweep {
+        balance_lamports: balance,
+        fee_lamports: LAMPORTS_PER_SIGNATURE,
+        priority_fee_lamports: priority_fee,
+        reserve_lamports: reserve,
+        sweep_lamports: sweep.base_units(),
+    })
 }
 
-async fn build_transfer_sol(
-    State(_state): State<AppState>,
-    Json(payload): Json<TransferSolRequest>,
-) -> Response {
-    // Fetch fresh blockhash
-    let rpc_url = format!("https://api.{}.solana.com", payload.network);
-    let rpc = RpcClient::new(rpc_url);
+/// Refusal for an amount `parse_transfer_amount` rejected, echoing the values as the caller sent them.
+fn amount_error(amount: &str, amount_raw: &Option<String>, (code, error): (&'static str, String)) -> ApiError {
+    ApiError::bad_request(error).code(code).with("amount", amount).with("amount_raw", amount_raw)
+}
 
-    let blockhash = match rpc.get_latest_blockhash() {
-        Ok(bh) => bh,
-        Err(e) => {
-            return Json(json!({
-                "success": false,
-                "error": format!("Failed to fetch blockhash: {}", e)
-            }))
-            .into_response();
+/// Check a destination against the exchange deposit registry. Returns the plain deposit memo
+/// instruction to include (if any) and the `exchange` block for the build response.
+fn exchange_deposit_check(
+    to_address: &str,
+    token: &str,
+    exchange_memo: &Option<String>,
+) -> Result<(Option<solana_sdk::instruction::Instruction>, serde_json::Value), (&'static str, String)> {
+    let exchange = exchanges::match_destination(to_address).map_err(|e| ("exchange_registry_invalid", e))?;
+    let memo = exchange_memo.as_deref().map(str::trim).filter(|m| !m.is_empty());
+
+    if let Some(memo) = memo {
+        if memo.len() > exchanges::MAX_EXCHANGE_MEMO_BYTES {
+            return Err((
+                "exchange_memo_too_long",
+                format!("exchange_memo must be at most {} bytes", exchanges::MAX_EXCHANGE_MEMO_BYTES),
+            ));
+        }
+    }
+
+    let mut warnings: Vec<String> = Vec::new();
+    if let Some(ref exchange) = exchange {
+        if exchange.memo_required && memo.is_none() {
+            return Err((
+                "exchange_memo_required",
+                format!(
+                    "{} is a {} deposit address that requires a memo; pass exchange_memo with your deposit tag",
+                    to_address, exchange.name
+                ),
+            ));
+        }
+        if exchange.uncredited_tokens.iter().any(|t| t.eq_ignore_ascii_case(token)) {
+            warnings.push(format!("{} is known not to credit {} deposits to this address", exchange.name, token));
+        }
+    }
+
+    let instruction = memo.map(|m| utils::instruction_from_spl(&spl_memo::build_memo(m.as_bytes(), &[])));
+    let block = json!({
+        "matched": exchange.as_ref().map(|e| e.name.clone()),
+        "memo_required": exchange.as_ref().map(|e| e.memo_required).unwrap_or(false),
+        "exchange_memo": memo,
+        "warnings": warnings
+    });
+    Ok((instruction, block))
+}
+
+fn build_memo(
+    token_type: &str,
+    from: &str,
+    to: &str,
+    amount: &amount::TokenAmount,
+    yid: &str,
+    notes: Option<&str>,
+    privacy: MemoPrivacy,
+) -> Result<String, String> {
+    // Validate notes if provided
+    if let Some(n) = notes {
+        if n.len() > 16 {
+            return Err(format!("Notes must be 16 characters or less, got {}", n.len()));
         }
+    }
+    
+    let notes_part = notes.unwrap_or("");
+    // Registered symbols also carry their mint (m:), so the memo stays unambiguous if the registry
+    // changes; an unregistered mint is already its own token field
+    let mint_part = registry::token_mint(token_type).map(|mint| format!("m:{}|", mint)).unwrap_or_default();
+    match privacy {
+        MemoPrivacy::Full => Ok(format!(
+            "fuego|{}|{}f:{}|t:{}|a:{}|yid:{}|n:{}",
+            token_type, mint_part, from, to, amount.raw(), yid, notes_part
+        )),
+        MemoPrivacy::Minimal => Ok(format!(
+            "fuego|{}|{}a:{}|yid:{}|n:{}",
+            token_type, mint_part, amount.raw(), yid, notes_part
+        )),
+    }
+}
+
+async fn get_version() -> ApiResult {
+    Ok(Json(json!({
+        "success": true,
+        "data": build_info::json()
+    })))
+}
+
+/// How long the deep health check waits on the RPC; orchestrators want an answer quickly.
+const DEEP_HEALTH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+#[derive(Deserialize)]
+struct HealthQuery {
+    /// Also check the default network's RPC and the wallet file
+    #[serde(default)]
+    deep: bool,
+}
+
+// Instant unless ?deep=true, so it stays usable as a liveness probe
+async fn health_check(State(state): State<AppState>, Query(query): Query<HealthQuery>) -> ApiResult<Response> {
+    let fee_wallet = state.fee_wallet.status();
+    let mut body = json!({
+        "status": "healthy",
+        "service": "fuego-server",
+        "version": build_info::VERSION,
+        "build": build_info::json(),
+        "mode": state.mode.as_str(),
+        "auth_required": state.auth.enabled(),
+        "low_balance": fee_wallet.low_balance,
+        "fee_wallet": fee_wallet
+    });
+    if !query.deep {
+        return Ok(Json(body).into_response());
+    }
+
+    let (rpc_check, wallet_check) = tokio::join!(rpc_health(&state), async { wallet_health() });
+    let rpc_ok = rpc_check["ok"] == json!(true);
+    let wallet_ok = wallet_check["ok"] == json!(true);
+    body["status"] = json!(if rpc_ok && wallet_ok { "healthy" } else { "degraded" });
+    body["checks"] = json!({ "rpc": rpc_check, "wallet": wallet_check });
+    // Without the RPC nothing can be built or sent, so the instance shouldn't get traffic
+    let status = if rpc_ok { axum::http::StatusCode::OK } else { axum::http::StatusCode::SERVICE_UNAVAILABLE };
+    Ok((status, Json(body)).into_response())
+}
+
+/// getLatestBlockhash against the default network's primary endpoint, without the blockhash cache.
+async fn rpc_health(state: &AppState) -> serde_json::Value {
+    let network = state.default_network.clone();
+    let url = state.rpc.primary(&network);
+    let endpoint = rpc::redact_url(&providers::scrub(&url));
+    let rpc = state.rpc.pinned(&url, CommitmentConfig::confirmed());
+    let started = Instant::now();
+    let result = tokio::time::timeout(DEEP_HEALTH_TIMEOUT, rpc.get_latest_blockhash()).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let error = match result {
+        Ok(Ok(_)) => return json!({ "ok": true, "network": network, "endpoint": endpoint, "latency_ms": latency_ms }),
+        Ok(Err(e)) => providers::scrub(&e.to_string()),
+        Err(_) => format!("No answer within {} ms", DEEP_HEALTH_TIMEOUT.as_millis()),
+    };
+    json!({ "ok": false, "network": network, "endpoint": endpoint, "latency_ms": latency_ms, "error": error })
+}
+
+/// Whether ~/.fuego/wallet.json exists and parses. Only the public address is reported; a parse
+/// error isn't, since serde can quote the file's contents.
+fn wallet_health() -> serde_json::Value {
+    let path = utils::fuego_home().join("wallet.json");
+    let Ok(content) = fs::read_to_string(&path) else {
+        return json!({ "ok": false, "present": path.exists(), "parseable": false });
+    };
+    match serde_json::from_str::<WalletStore>(&content) {
+        Ok(wallet) => json!({ "ok": true, "present": true, "parseable": true, "address": wallet.address, "network": wallet.network }),
+        Err(_) => json!({ "ok": false, "present": true, "parseable": false }),
+    }
+}
+
+async fn get_latest_hash(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<RpcNetwork>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, false).await?;
+    let rpc = state.rpc.client(&payload.network, &rpc_url, CommitmentConfig::default());
+
+    let result = state.blockhashes.get(&rpc, &rpc_url, payload.fresh_blockhash).await;
+    let rpc_endpoint = rpc::redact_url(&rpc.url());
+    match result {
+        Ok(recent) => Ok(Json(json!({
+            "success": true,
+            "data": {
+                "blockhash": recent.blockhash.to_string(),
+                "last_valid_block_height": recent.last_valid_block_height,
+                "slot": recent.slot,
+                "expires_in_seconds": recent.expires_in_seconds(),
+                "cached": recent.cached,
+                "age_ms": recent.age_ms,
+                "network": payload.network,
+                "rpc_endpoint": rpc_endpoint
+            }
+        }))),
+        Err(e) => Err(ApiError::upstream(format!("Failed to get latest blockhash: {}", e))
+            .code("blockhash_fetch_failed")
+            .with("rpc_endpoint", &rpc_endpoint)),
+    }
+}
+
+/// Rejects a `fields` parameter that is malformed or names paths the response doesn't have.
+fn fields_error(error: String, unknown_paths: Vec<String>) -> ApiError {
+    ApiError::bad_request(error).code("invalid_fields").with("unknown_paths", &unknown_paths)
+}
+
+/// A successful read response, pruned to the requested fields when there are any.
+fn select_fields(selection: Option<&fields::Selection>, body: serde_json::Value) -> ApiResult {
+    let Some(selection) = selection else {
+        return Ok(Json(body));
+    };
+    match selection.apply(body) {
+        Ok(pruned) => Ok(Json(pruned)),
+        Err(unknown) => Err(fields_error(format!("Unknown fields: {}", unknown.join(", ")), unknown)),
+    }
+}
+
+async fn get_sol_balance(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<GetBalanceRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let selection = fields::parse(payload.fields.as_deref()).map_err(|e| fields_error(e, Vec::new()))?;
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let commitment = state.commitment.read(&payload.network, &payload.commitment);
+    let rpc = state.rpc.client(&payload.network, &rpc_url, get_commitment_config(commitment));
+
+    let pubkey = string_to_pub_key(&payload.address).map_err(|_| ApiError::bad_request("Invalid wallet address").code("invalid_address"))?;
+
+    let started = Instant::now();
+    let result = rpc.get_balance(&pubkey).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+
+    match result {
+        Ok(lamports) => {
+            let sol = utils::base_units_to_ui_number(lamports, amount::SOL_DECIMALS);
+            select_fields(
+                selection.as_ref(),
+                json!({
+                    "success": true,
+                    "data": {
+                        "address": payload.address,
+                        "lamports": lamports,
+                        "sol": sol,
+                        "network": payload.network,
+                        "commitment": commitment
+                    }
+                }),
+            )
+        }
+        Err(e) => Err(ApiError::upstream(format!("Failed to get balance: {}", e)).code("rpc_error")),
+    }
+}
+
+/// One token's balance for /balances.
+async fn token_balance(
+    state: &AppState,
+    rpc: &RpcClient,
+    rpc_url: &str,
+    network: &str,
+    owner: solana_sdk::pubkey::Pubkey,
+    mint: &'static str,
+) -> Result<serde_json::Value, String> {
+    let mint_pubkey = string_to_pub_key(mint).map_err(|_| "Failed to parse mint".to_string())?;
+    let token_program = state.mints.token_program(rpc_url, network, mint).await.map_err(|e| e.message())?;
+    let token_account = token_account_address(&owner, &mint_pubkey, &token_program);
+    let started = Instant::now();
+    let result = rpc.get_token_account_balance(&token_account).await;
+    state.rpc.record(rpc_url, started.elapsed(), result.is_ok());
+    match result {
+        Ok(balance) => Ok(json!({
+            "amount": balance.amount,
+            "decimals": balance.decimals,
+            "ui_amount": balance.ui_amount_string,
+            "token_account": token_account.to_string(),
+            "ata_exists": true
+        })),
+        Err(e) if rpc::is_account_not_found(&e) => {
+            let decimals = state.mints.get(rpc_url, network, mint).await.map(|info| info.decimals).unwrap_or(6);
+            Ok(json!({
+                "amount": "0",
+                "decimals": decimals,
+                "ui_amount": "0",
+                "token_account": token_account.to_string(),
+                "ata_exists": false
+            }))
+        }
+        Err(e) => Err(format!("Failed to get balance: {}", e)),
+    }
+}
+
+// SOL, USDC and USDT in one call; each asset succeeds or fails on its own
+async fn get_balances(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<GetBalanceRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let selection = fields::parse(payload.fields.as_deref()).map_err(|e| fields_error(e, Vec::new()))?;
+    let started = Instant::now();
+    let pubkey = string_to_pub_key(&payload.address).map_err(|_| ApiError::bad_request("Invalid wallet address").code("invalid_address"))?;
+
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let commitment = state.commitment.read(&payload.network, &payload.commitment);
+    let rpc = state.rpc.client(&payload.network, &rpc_url, get_commitment_config(commitment));
+
+    let sol_task = async {
+        let started = Instant::now();
+        let result = rpc.get_balance(&pubkey).await;
+        state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+        result
+            .map(|lamports| json!({ "lamports": lamports, "sol": utils::base_units_to_ui_number(lamports, amount::SOL_DECIMALS) }))
+            .map_err(|e| format!("Failed to get balance: {}", e))
+    };
+    let usdc_task = token_balance(&state, &rpc, &rpc_url, &payload.network, pubkey, USDC_MINT);
+    let usdt_task = token_balance(&state, &rpc, &rpc_url, &payload.network, pubkey, USDT_MINT);
+    let (sol, usdc, usdt) = tokio::join!(sol_task, usdc_task, usdt_task);
+
+    let asset = |result: Result<serde_json::Value, String>| match result {
+        Ok(mut data) => {
+            data["success"] = json!(true);
+            data
+        }
+        Err(e) => json!({ "success": false, "error": e, "code": "rpc_error" }),
+    };
+
+    select_fields(
+        selection.as_ref(),
+        json!({
+            "success": true,
+            "data": {
+                "address": payload.address,
+                "network": payload.network,
+                "commitment": commitment,
+                "sol": asset(sol),
+                "usdc": asset(usdc),
+                "usdt": asset(usdt),
+                "elapsed_ms": started.elapsed().as_millis() as u64
+            }
+        }),
+    )
+}
+
+/// getMultipleAccounts takes at most this many keys per call.
+const MULTIPLE_ACCOUNTS_CHUNK: usize = 100;
+
+// SOL balances for many addresses with one getMultipleAccounts call per 100 of them
+async fn get_balances_batch(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<BalancesBatchRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    if payload.addresses.is_empty() {
+        return Err(ApiError::bad_request("addresses must not be empty").code("empty_batch"));
+    }
+    if payload.addresses.len() > state.balances_batch_max {
+        return Err(ApiError::bad_request(format!(
+            "{} addresses requested; at most {} are allowed per batch",
+            payload.addresses.len(),
+            state.balances_batch_max
+        ))
+        .code("batch_too_large")
+        .with("max_batch_size", state.balances_batch_max));
+    }
+
+    let started = Instant::now();
+    let mut errors = Vec::new();
+    let mut pubkeys: Vec<(String, solana_sdk::pubkey::Pubkey)> = Vec::new();
+    for address in &payload.addresses {
+        if pubkeys.iter().any(|(a, _)| a == address) {
+            continue;
+        }
+        match string_to_pub_key(address) {
+            Ok(pk) => pubkeys.push((address.clone(), pk)),
+            Err(_) => errors.push(json!({ "address": address, "error": "Invalid wallet address", "code": "invalid_address" })),
+        }
+    }
+
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let commitment = state.commitment.read(&payload.network, &payload.commitment);
+    let commitment_config = get_commitment_config(commitment);
+    let rpc = state.rpc.client(&payload.network, &rpc_url, commitment_config);
+
+    let mut balances = serde_json::Map::new();
+    let mut rpc_calls = 0;
+    for chunk in pubkeys.chunks(MULTIPLE_ACCOUNTS_CHUNK) {
+        let keys: Vec<_> = chunk.iter().map(|(_, pk)| *pk).collect();
+        let call_started = Instant::now();
+        let result = rpc.get_multiple_accounts_with_commitment(&keys, commitment_config).await;
+        state.rpc.record(&rpc_url, call_started.elapsed(), result.is_ok());
+        rpc_calls += 1;
+        match result {
+            Ok(response) => {
+                // A missing account simply holds no SOL
+                for ((address, _), account) in chunk.iter().zip(response.value) {
+                    let lamports = account.map(|a| a.lamports).unwrap_or(0);
+                    balances.insert(
+                        address.clone(),
+                        json!({ "lamports": lamports, "sol": utils::base_units_to_ui_number(lamports, amount::SOL_DECIMALS) }),
+                    );
+                }
+            }
+            Err(e) => {
+                let message = format!("Failed to get balances: {}", e);
+                errors.extend(chunk.iter().map(|(address, _)| json!({ "address": address, "error": message, "code": "rpc_error" })));
+            }
+        }
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "network": payload.network,
+            "commitment": commitment,
+            "balances": balances,
+            "errors": errors,
+            "rpc_calls": rpc_calls,
+            "elapsed_ms": started.elapsed().as_millis() as u64
+        }
+    })))
+}
+
+// Reconstruct a past balance by undoing transaction deltas from the current balance backwards
+async fn get_balance_at(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<BalanceAtRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let target = match (payload.slot, payload.timestamp) {
+        (Some(slot), None) => history::Target::Slot(slot),
+        (None, Some(ts)) => history::Target::Timestamp(ts),
+        _ => {
+            return Err(ApiError::bad_request("Provide exactly one of slot or timestamp").code("invalid_request"));
+        }
+    };
+
+    let owner = string_to_pub_key(&payload.address).map_err(|_| ApiError::bad_request("Invalid wallet address").code("invalid_address"))?;
+
+    let mint_arg = payload.mint.clone().unwrap_or_else(|| "SOL".to_string());
+    let mint_address = match mint_arg.to_uppercase().as_str() {
+        "SOL" => None,
+        "USDC" => Some(USDC_MINT.to_string()),
+        "USDT" => Some(USDT_MINT.to_string()),
+        _ => Some(mint_arg.clone()),
+    };
+
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let max_scan = payload
+        .max_scan
+        .unwrap_or(state.balance_at_max_scan)
+        .min(state.balance_at_max_scan);
+
+    // Tokens are tracked through the owner's associated token account, which is where transfers land
+    let (token_account, decimals) = match &mint_address {
+        None => (None, 9u8),
+        Some(mint) => {
+            let mint_pubkey = string_to_pub_key(mint).map_err(|_| ApiError::bad_request("Invalid mint").code("invalid_mint"))?;
+            let info = state.mints.get(&rpc_url, &payload.network, mint).await?;
+            let program_id = string_to_pub_key(&info.program_id).unwrap_or(mint_pubkey);
+            let ata = spl_associated_token_account::get_associated_token_address_with_program_id(
+                &utils::to_spl_pubkey(&owner),
+                &utils::to_spl_pubkey(&mint_pubkey),
+                &utils::to_spl_pubkey(&program_id),
+            );
+            let decimals = info.decimals;
+            (Some(ata.to_string()), decimals)
+        }
+    };
+
+    let (history_address, tracked) = match &token_account {
+        None => (payload.address.as_str(), history::TrackedBalance::Sol { address: &payload.address }),
+        Some(ata) => (ata.as_str(), history::TrackedBalance::Token { token_account: ata }),
+    };
+
+    let started = Instant::now();
+    let result = history::balance_at(&rpc_url, &payload.network, &state.tx_cache, history_address, tracked, target, max_scan).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+
+    match result {
+        Ok(reconstructed) => Ok(Json(json!({
+            "success": true,
+            "data": {
+                "address": payload.address,
+                "mint": mint_address.unwrap_or_else(|| "SOL".to_string()),
+                "token_account": token_account,
+                "slot": payload.slot,
+                "timestamp": payload.timestamp,
+                "balance_ui": utils::base_units_to_ui(reconstructed.balance, decimals),
+                "decimals": decimals,
+                "reconstruction": reconstructed,
+                "network": payload.network
+            }
+        }))),
+        Err(e) => Err(ApiError::upstream(format!("Failed to reconstruct balance: {}", e)).code("rpc_error")),
+    }
+}
+
+// Transaction detail with decoded compute budget and the base vs priority fee split
+// Search memos of transactions fuego has submitted; a local-file query that never touches the RPC
+async fn search_memos(StrictJson(payload): StrictJson<SearchMemosRequest>) -> ApiResult {
+    let indexed = memo_index::load();
+    let contains = |field: &Option<String>, needle: &Option<String>| match needle {
+        Some(n) => field.as_deref().map(|f| f.contains(n.as_str())).unwrap_or(false),
+        None => true,
+    };
+
+    let mut matches: Vec<&memo_index::IndexedMemo> = indexed
+        .iter()
+        .filter(|m| payload.network.as_ref().map(|n| &m.network == n).unwrap_or(true))
+        .filter(|m| match &payload.yid_prefix {
+            Some(prefix) => m.yid.as_deref().map(|y| y.starts_with(prefix.as_str())).unwrap_or(false),
+            None => true,
+        })
+        .filter(|m| contains(&m.notes, &payload.notes_contains))
+        .filter(|m| payload.token.as_deref().map(|token| m.matches_token(token)).unwrap_or(true))
+        .filter(|m| match &payload.counterparty {
+            Some(c) => m.from.as_deref() == Some(c.as_str()) || m.to.as_deref() == Some(c.as_str()),
+            None => true,
+        })
+        .filter(|m| payload.text.as_ref().map(|t| m.memo.contains(t.as_str())).unwrap_or(true))
+        .collect();
+    matches.reverse();
+
+    let total = matches.len();
+    let limit = payload.limit.unwrap_or(50).min(200);
+    let offset = payload.offset.unwrap_or(0);
+    let page: Vec<&memo_index::IndexedMemo> = matches.into_iter().skip(offset).take(limit).collect();
+
+    // x402 purchases never carry a memo, so a yid query also reads the receipt store
+    let x402_purchases = match &payload.yid_prefix {
+        Some(prefix) => {
+            let filter = receipts::YidFilter { yid_prefix: Some(prefix.clone()), ..Default::default() };
+            let receipts = receipts::list_receipts()
+                .map_err(|e| ApiError::internal(format!("Failed to read x402 receipts: {}", e)).code("internal_error"))?;
+            let purchases: Vec<receipts::X402Receipt> = receipts
+                .into_iter()
+                .filter(|r| payload.network.as_ref().map(|n| &r.network == n).unwrap_or(true))
+                .filter(|r| filter.matches(r))
+                .collect();
+            Some(purchases)
+        }
+        None => None,
+    };
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "matches": page,
+            "x402_purchases": x402_purchases,
+            "total": total,
+            "limit": limit,
+            "offset": offset,
+            "coverage": {
+                "indexed_memos": indexed.len(),
+                "from": indexed.first().map(|m| m.indexed_at),
+                "to": indexed.last().map(|m| m.indexed_at),
+                "sources": ["submit-transaction", "submit-versioned-transaction", "submit-queue"],
+                "note": "Only transactions submitted through this fuego server since indexing began are searchable."
+            }
+        }
+    })))
+}
+
+// Counts and summed amounts of outgoing transfers from the memo index, grouped for reporting
+async fn transfer_stats(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<TransferStatsRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let address = match payload.address.clone().or_else(|| load_active_wallet().map(|(address, _, _)| address)) {
+        Some(address) => address,
+        None => {
+            return Err(ApiError::not_found("No address given and no local wallet found")
+                .code("no_local_wallet"));
+        }
+    };
+    if string_to_pub_key(&address).is_err() {
+        return Err(ApiError::bad_request("Invalid address").code("invalid_address"));
+    }
+    let group_by = payload.group_by.iter().map(|g| stats::GroupBy::parse(g)).collect::<Result<Vec<_>, _>>()
+        .map_err(|e| ApiError::bad_request(e).code("invalid_request"))?;
+    let bucket = payload.bucket.as_deref().map(stats::Bucket::parse).transpose()
+        .map_err(|e| ApiError::bad_request(e).code("invalid_request"))?;
+
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let backfill = if payload.refresh {
+        let depth = payload.refresh_depth.unwrap_or(stats::DEFAULT_BACKFILL_DEPTH);
+        let started = Instant::now();
+        let result = stats::backfill(&rpc_url, &payload.network, &address, depth).await;
+        state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+        match result {
+            Ok(report) => Some(report),
+            Err(e) => {
+                return Err(ApiError::upstream(format!("Failed to backfill the index: {}", e)).code("rpc_error"));
+            }
+        }
+    } else {
+        None
+    };
+
+    let query = stats::Query {
+        network: payload.network.clone(),
+        address: address.clone(),
+        group_by,
+        bucket,
+        from: payload.from,
+        to: payload.to,
+        token: payload.token.clone(),
+        counterparty: payload.counterparty.clone(),
+    };
+    let started = Instant::now();
+    let result = stats::aggregate(&rpc_url, &query).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+
+    match result {
+        Ok(mut data) => {
+            data["address"] = json!(address);
+            data["network"] = json!(payload.network);
+            data["backfill"] = json!(backfill);
+            Ok(Json(json!({
+                "success": true,
+                "data": data
+            })))
+        }
+        Err(e) => Err(ApiError::upstream(format!("Failed to check transaction statuses: {}", e)).code("rpc_error")),
+    }
+}
+
+async fn get_transaction_detail(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<TransactionDetailRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let selection = fields::parse(payload.fields.as_deref()).map_err(|e| fields_error(e, Vec::new()))?;
+    if utils::string_to_signature(&payload.signature).is_err() {
+        return Err(ApiError::bad_request("Invalid signature").code("invalid_signature"));
+    }
+
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let commitment = match state.commitment.read(&payload.network, &payload.commitment) {
+        "finalized" => "finalized",
+        _ => "confirmed",
+    };
+    let started = Instant::now();
+    let result = history::transaction_detail(&rpc_url, &payload.network, &state.tx_cache, &payload.signature, commitment).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+
+    // Raw confirmations count alongside the commitment-based status
+    let confirmation = match payload.min_confirmations {
+        Some(min) => match pending::signature_status(&rpc_url, &payload.signature, min).await {
+            Ok(progress) => Some(progress),
+            Err(e) => {
+                return Err(ApiError::upstream(format!("Failed to fetch confirmations: {}", e)).code("rpc_error"));
+            }
+        },
+        None => None,
+    };
+
+    // What the tracker knows about signatures fuego submitted, including reorgs
+    let tracking = state.pending.status(&payload.signature);
+    match result {
+        Ok(Some(detail)) => select_fields(
+            selection.as_ref(),
+            json!({
+                "success": true,
+                "data": detail,
+                "network": payload.network,
+                "commitment": commitment,
+                "confirmation": confirmation,
+                "tracking": tracking
+            }),
+        ),
+        Ok(None) if tracking.as_ref().map(|t| t.state) == Some(pending::TrackState::Reorged) => Err(ApiError::not_found("Transaction was confirmed, then dropped by a fork")
+            .code("transaction_reorged")
+            .with("tracking", &tracking)),
+        Ok(None) => Err(ApiError::not_found("Transaction not found").code("transaction_not_found").with("tracking", &tracking)),
+        Err(e) => Err(ApiError::upstream(format!("Failed to fetch transaction: {}", e)).code("rpc_error")),
+    }
+}
+
+// Token-2022 transfer fee disclosure: fee withheld, amount received, and the gross-up for an exact net
+async fn transfer_quote(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<TransferQuoteRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let started = Instant::now();
+    let result = transfer_fee::fetch_mint_fees(&state.rpc, &payload.network, &rpc_url, &payload.mint).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+    let fees = result.map_err(|e| ApiError::upstream(e).code("rpc_error"))?;
+
+    let (requested, by_net) = match (&payload.amount, &payload.net_amount) {
+        (Some(amount), None) => (amount, false),
+        (None, Some(net)) => (net, true),
+        _ => {
+            return Err(ApiError::bad_request("Pass exactly one of amount or net_amount").code("amount_conflict"));
+        }
+    };
+    let requested = utils::ui_amount_to_base_units(requested, fees.decimals).map_err(|e| ApiError::bad_request(e).code("invalid_amount"))?;
+
+    let gross_up = match fees.gross_for_net(requested) {
+        Some(gross) => gross,
+        None => {
+            return Err(ApiError::bad_request("No amount of this token can deliver that net amount").code("invalid_amount"));
+        }
+    };
+    let amount = if by_net { gross_up } else { requested };
+    let fee = fees.fee(amount);
+
+    let units = |raw: u64| json!({ "raw": raw.to_string(), "ui": utils::base_units_to_ui(raw, fees.decimals) });
+    let current = fees.current();
+    let scheduled = fees
+        .config
+        .filter(|c| c.newer.epoch > fees.epoch)
+        .map(|c| c.newer);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "mint": payload.mint,
+            "network": payload.network,
+            "program_id": fees.program_id,
+            "decimals": fees.decimals,
+            "epoch": fees.epoch,
+            "has_transfer_fee": current.map(|f| f.basis_points > 0).unwrap_or(false),
+            "transfer_fee": current,
+            "scheduled_transfer_fee": scheduled,
+            "amount": units(amount),
+            "fee": units(fee),
+            "received": units(amount - fee),
+            "gross_up": {
+                "net": units(requested),
+                "send": units(gross_up),
+                "fee": units(gross_up - requested)
+            }
+        }
+    })))
+}
+
+async fn get_default_network(State(state): State<AppState>) -> impl IntoResponse {
+    Json(json!({
+        "network": state.default_network,
+        "source": state.default_network_source,
+        "require_explicit_network": state.require_explicit_network
+    }))
+}
+
+// Everything the server resolved from the environment and server.json; secrets (auth keys, RPC
+// API keys) are left out or redacted
+async fn get_config(State(state): State<AppState>) -> ApiResult {
+    let path = config::config_path();
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "path": path.display().to_string(),
+            "file_found": path.exists(),
+            "listen": state.listen.to_string(),
+            "mode": state.mode.as_str(),
+            "env_overrides": build_info::config_sources()["env_overrides"],
+            "network": {
+                "default": state.default_network,
+                "source": state.default_network_source,
+                "require_explicit": state.require_explicit_network
+            },
+            "rpc": state.rpc.describe(),
+            "providers": state.providers.describe(),
+            "limits": {
+                "max_body_bytes": state.limits.max_body_bytes,
+                "request_timeout_secs": state.limits.timeout.as_secs(),
+                "slow_request_timeout_secs": state.limits.slow_timeout.as_secs()
+            },
+            "rate_limit": state.rate_limiter.describe(),
+            "cors": state.cors.describe(),
+            "tls": state.config.tls.as_ref().map(|tls| json!({
+                "cert_path": tls.cert_path.display().to_string(),
+                "key_path": tls.key_path.display().to_string()
+            })),
+            "fee_presets": state.config.fee_presets(),
+            "auth": state.auth.describe()
+        }
+    })))
+}
+
+async fn get_usdc_balance(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<GetTokenBalanceRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let commitment = state.commitment.read(&payload.network, &payload.commitment);
+    let rpc = state.rpc.client(&payload.network, &rpc_url, get_commitment_config(commitment));
+
+    let pubkey = string_to_pub_key(&payload.address).map_err(|_| ApiError::bad_request("Invalid wallet address").code("invalid_address"))?;
+
+    let usdc_mint = string_to_pub_key(USDC_MINT).map_err(|_| ApiError::internal("Failed to parse USDC mint").code("internal_error"))?;
+
+    let token_program = state.mints.token_program(&rpc_url, &payload.network, USDC_MINT).await?;
+    let associated_token_account = token_account_address(&pubkey, &usdc_mint, &token_program);
+
+    let started = Instant::now();
+    let result = rpc.get_token_account_balance(&associated_token_account).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+
+    match result {
+        Ok(balance) => Ok(Json(json!({
+            "success": true,
+            "data": {
+                "address": payload.address,
+                "amount": balance.amount,
+                "decimals": balance.decimals,
+                "ui_amount": balance.ui_amount_string,
+                "network": payload.network,
+                "token": "USDC",
+                "ata": associated_token_account.to_string(),
+                "ata_exists": true,
+                "owner_program": mints::program_label(&token_program),
+                "commitment": commitment
+            }
+        }))),
+        // No token account yet means the wallet has never held USDC: a zero balance, not an error
+        Err(e) if rpc::is_account_not_found(&e) => Ok(Json(json!({
+            "success": true,
+            "data": {
+                "address": payload.address,
+                "amount": "0",
+                "decimals": 6,
+                "ui_amount": "0",
+                "network": payload.network,
+                "token": "USDC",
+                "ata": associated_token_account.to_string(),
+                "ata_exists": false,
+                "owner_program": mints::program_label(&token_program),
+                "commitment": commitment
+            }
+        }))),
+        Err(e) => Err(ApiError::upstream(format!("Failed to get USDC balance: {}", e)).code("rpc_error")),
+    }
+}
+
+// PYUSD lives under Token-2022, so enumerate the owner's accounts for the mint instead of deriving one ATA
+async fn get_pyusd_balance(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<GetTokenBalanceRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    if string_to_pub_key(&payload.address).is_err() {
+        return Err(ApiError::bad_request("Invalid wallet address").code("invalid_address"));
+    }
+
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let commitment = state.commitment.read(&payload.network, &payload.commitment);
+    let started = Instant::now();
+    let result = fetch_token_accounts_json(&rpc_url, &payload.address, json!({ "mint": PYUSD_MINT }), commitment).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+
+    let accounts = result.map_err(|e| ApiError::upstream(format!("Failed to get PYUSD balance: {}", e)).code("rpc_error"))?;
+
+    // Sum every Token-2022 account holding the mint; no accounts means a zero balance
+    let mut total: u64 = 0;
+    let mut token_accounts = Vec::new();
+    for account in &accounts {
+        if account["account"]["owner"].as_str() != Some(mints::TOKEN_2022_PROGRAM) {
+            continue;
+        }
+        let amount = account["account"]["data"]["parsed"]["info"]["tokenAmount"]["amount"]
+            .as_str()
+            .and_then(|a| a.parse::<u64>().ok())
+            .unwrap_or(0);
+        total = total.saturating_add(amount);
+        if let Some(pubkey) = account["pubkey"].as_str() {
+            token_accounts.push(pubkey.to_string());
+        }
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "address": payload.address,
+            "amount": total.to_string(),
+            "decimals": PYUSD_DECIMALS,
+            "ui_amount": utils::base_units_to_ui(total, PYUSD_DECIMALS),
+            "network": payload.network,
+            "token": "PYUSD",
+            "token_accounts": token_accounts,
+            "commitment": commitment
+        }
+    })))
+}
+
+/// Balance of any mint's associated token account, derived under whichever token program owns the mint.
+async fn get_token_balance(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<MintBalanceRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let pubkey = string_to_pub_key(&payload.address).map_err(|_| ApiError::bad_request("Invalid wallet address").code("invalid_address"))?;
+    let mint_pubkey = string_to_pub_key(&payload.mint).map_err(|_| ApiError::bad_request("Invalid mint address").code("invalid_mint"))?;
+
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let commitment = state.commitment.read(&payload.network, &payload.commitment);
+
+    let info = state.mints.get(&rpc_url, &payload.network, &payload.mint).await?;
+    let program_id = state.mints.token_program(&rpc_url, &payload.network, &payload.mint).await?;
+    let ata = token_account_address(&pubkey, &mint_pubkey, &program_id);
+
+    let rpc = state.rpc.client(&payload.network, &rpc_url, get_commitment_config(commitment));
+    let started = Instant::now();
+    let result = rpc.get_account_with_commitment(&ata, get_commitment_config(commitment)).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+
+    let account = match result {
+        Ok(response) => response.value,
+        Err(e) => {
+            return Err(ApiError::upstream(format!("Failed to get token balance: {}", e)).code("rpc_error"));
+        }
+    };
+    // The amount sits at the same offset (after mint and owner) for both token programs
+    let amount = account
+        .as_ref()
+        .and_then(|a| a.data.get(64..72))
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .unwrap_or(0);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "address": payload.address,
+            "amount": amount.to_string(),
+            "decimals": info.decimals,
+            "ui_amount": utils::base_units_to_ui(amount, info.decimals),
+            "network": payload.network,
+            "token": get_token_symbol(&payload.mint),
+            "mint": payload.mint,
+            "token_program": info.program_id,
+            "token_account": ata.to_string(),
+            "exists": account.is_some(),
+            "ata_exists": account.is_some(),
+            "commitment": commitment
+        }
+    })))
+}
+
+async fn get_usdt_balance(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<GetTokenBalanceRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let commitment = state.commitment.read(&payload.network, &payload.commitment);
+    let rpc = state.rpc.client(&payload.network, &rpc_url, get_commitment_config(commitment));
+
+    let pubkey = string_to_pub_key(&payload.address).map_err(|_| ApiError::bad_request("Invalid wallet address").code("invalid_address"))?;
+
+    let usdt_mint = string_to_pub_key(USDT_MINT).map_err(|_| ApiError::internal("Failed to parse USDT mint").code("internal_error"))?;
+
+    let token_program = state.mints.token_program(&rpc_url, &payload.network, USDT_MINT).await?;
+    let associated_token_account = token_account_address(&pubkey, &usdt_mint, &token_program);
+
+    let started = Instant::now();
+    let result = rpc.get_token_account_balance(&associated_token_account).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+
+    match result {
+        Ok(balance) => Ok(Json(json!({
+            "success": true,
+            "data": {
+                "address": payload.address,
+                "amount": balance.amount,
+                "decimals": balance.decimals,
+                "ui_amount": balance.ui_amount_string,
+                "network": payload.network,
+                "token": "USDT",
+                "ata": associated_token_account.to_string(),
+                "ata_exists": true,
+                "owner_program": mints::program_label(&token_program),
+                "commitment": commitment
+            }
+        }))),
+        // No token account yet means the wallet has never held USDT: a zero balance, not an error
+        Err(e) if rpc::is_account_not_found(&e) => Ok(Json(json!({
+            "success": true,
+            "data": {
+                "address": payload.address,
+                "amount": "0",
+                "decimals": 6,
+                "ui_amount": "0",
+                "network": payload.network,
+                "token": "USDT",
+                "ata": associated_token_account.to_string(),
+                "ata_exists": false,
+                "owner_program": mints::program_label(&token_program),
+                "commitment": commitment
+            }
+        }))),
+        Err(e) => Err(ApiError::upstream(format!("Failed to get USDT balance: {}", e)).code("rpc_error")),
+    }
+}
+
+async fn build_transfer_usdc(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    StrictJson(mut payload): StrictJson<TransferUsdcRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    // Fetch fresh blockhash
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, false).await?;
+    let rpc = state.rpc.client(&payload.network, &rpc_url, CommitmentConfig::default());
+
+    let recent = state.blockhashes.get(&rpc, &rpc_url, payload.options.fresh_blockhash).await
+        .map_err(|e| ApiError::upstream(format!("Failed to fetch blockhash: {}", e)).code("blockhash_fetch_failed"))?;
+    let blockhash = recent.blockhash;
+
+    // Parse addresses
+    let from_pubkey = string_to_pub_key(&payload.from_address)
+        .map_err(|_| ApiError::bad_request("Invalid from_address").code("invalid_address"))?;
+
+    if let Err(e) = state.fee_wallet.check_payer(&payload.from_address) {
+        return Err(ApiError::unavailable(e).code("fee_wallet_depleted"));
+    }
+
+    let to_pubkey = string_to_pub_key(&payload.to_address)
+        .map_err(|_| ApiError::bad_request("Invalid to_address").code("invalid_address"))?;
+
+    let usdc_mint = string_to_pub_key(USDC_MINT).map_err(|_| ApiError::internal("Invalid USDC mint").code("internal_error"))?;
+
+
+    // Resolve the owning token program instead of assuming spl-token, so a Token-2022 mint works too
+    let token_program = state.mints.token_program(&rpc_url, &payload.network, USDC_MINT).await?;
+
+    // Derive token accounts
+    let source_token_account = token_account_address(&from_pubkey, &usdc_mint, &token_program);
+    let destination_token_account = token_account_address(&to_pubkey, &usdc_mint, &token_program);
+
+    // amount "max" sweeps whatever the source token account holds right now
+    let (amount_text, amount_raw, swept) = match resolve_sweep(&payload.amount, &payload.amount_raw, payload.close_account, false, token_account_balance(&rpc, &source_token_account)).await {
+        Ok(resolved) => resolved,
+        Err(e) => return Err(amount_error(&payload.amount, &payload.amount_raw, e)),
+    };
+
+    // Parse amount (6 decimals for USDC)
+    let (transfer_amount, rounding) = match parse_transfer_amount(amount::Asset::Mint(USDC_MINT.to_string()), &amount_text, &amount_raw, USDC_DECIMALS, &payload.options.rounding) {
+        Ok(parsed) => parsed,
+        Err(e) => return Err(amount_error(&payload.amount, &payload.amount_raw, e)),
+    };
+    let amount = transfer_amount.base_units();
+
+    // Refuse a transfer the source token account can't cover
+    let balance_check = match preflight_balance(payload.options.skip_balance_check, swept, &transfer_amount, token_balance_or_zero(&rpc, &source_token_account)).await {
+        Ok(check) => check,
+        Err(shortfall) => return Err(insufficient_funds_error("USDC", &shortfall)),
+    };
+
+    let memo_privacy = resolve_memo_privacy(&payload.options.memo_privacy, state.memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo_privacy"))?;
+
+    // Build memo: fuego|USDC|m:{mint}|f:{from}|t:{to}|a:{amount}|yid:{yid}|n:{notes} (minimal privacy drops f/t)
+    let memo_text = build_memo("USDC", &payload.from_address, &payload.to_address, &transfer_amount, &payload.yid, payload.options.notes.as_deref(), memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo"))?;
+
+    let create_ata = CreateAta::parse(&payload.create_ata).map_err(|e| ApiError::bad_request(e).code("invalid_create_ata"))?;
+
+    // A recipient that has never held USDC has no account to receive it; create it (paid by the sender)
+    let recipient = recipient_ata(&rpc, create_ata, &from_pubkey, &to_pubkey, &usdc_mint, &token_program, &destination_token_account).await
+        .map_err(|e| ApiError::upstream(e).code("rpc_error"))?;
+
+    // Build instructions
+    let transfer_ix = token_transfer_checked(&token_program, &source_token_account, &usdc_mint, &destination_token_account, &from_pubkey, amount, USDC_DECIMALS);
+
+    let memo_instruction = spl_memo::build_memo(memo_text.as_bytes(), &[]);
+
+    // Compute budget instructions, plus headroom for the ATA create
+    let compute_unit_limit: u32 = 100_000 + recipient.compute_units();
+    let compute_unit_price = compute_unit_price(&state, payload.options.fee_amount.as_deref(), 0);
+    let compute_limit = ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit);
+    let unit_price = ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price);
+
+    // The sender also pays the transaction fee and any new account's rent, in SOL
+    let network_cost = estimate_network_cost(compute_unit_limit, compute_unit_price, recipient.rent_lamports);
+    let balance_check = match preflight_balance(payload.options.skip_balance_check, false, &network_cost.total(), async {
+        rpc.get_balance(&from_pubkey).await.map_err(|e| e.to_string())
+    }).await {
+        Ok(check) => balance_check.and(check),
+        Err(shortfall) => return Err(insufficient_sol_error(&shortfall, &network_cost)),
+    };
+
+    // Create transaction message with fresh blockhash
+    let extras = extra_instructions::parse(&payload.options.extra_instructions, &[from_pubkey])
+        .map_err(|e| ApiError::bad_request(e).code("invalid_extra_instructions"))?;
+
+    let (exchange_memo_ix, exchange) = match exchange_deposit_check(&payload.to_address, "USDC", &payload.options.exchange_memo) {
+        Ok(r) => r,
+        Err((code, e)) => {
+            return Err(ApiError::bad_request(e).code(code));
+        }
+    };
+
+    let memo_ix = utils::instruction_from_spl(&memo_instruction);
+    let mut head = vec![compute_limit, unit_price];
+    head.extend(recipient.instruction.clone());
+    head.push(transfer_ix);
+    if payload.close_account {
+        head.push(token_close_account(&token_program, &source_token_account, &from_pubkey, &from_pubkey));
+    }
+    head.extend(exchange_memo_ix);
+    let message = Message::new_with_blockhash(
+        &extras.assemble(head, memo_ix),
+        Some(&from_pubkey),
+        &blockhash,
+    );
+
+    let transaction = Transaction::new_unsigned(message);
+
+    // Serialize transaction
+    let serialized_tx = bincode::serialize(&transaction)
+        .map_err(|_| ApiError::internal("Failed to serialize transaction").code("serialization_failed"))?;
+
+    // Unsigned transactions already carry zeroed signature slots, so this is the on-wire size
+    if serialized_tx.len() > extra_instructions::MAX_TRANSACTION_BYTES {
+        return Err(ApiError::bad_request(format!(
+            "Transaction is {} bytes, over the {} byte limit; remove some extra_instructions",
+            serialized_tx.len(),
+            extra_instructions::MAX_TRANSACTION_BYTES
+        ))
+        .code("transaction_too_large"));
+    }
+
+    let summary = builds::BuildSummary {
+        token: "USDC".to_string(),
+        from: payload.from_address.clone(),
+        to: payload.to_address.clone(),
+        amount,
+        compute_unit_limit,
+        compute_unit_price,
+        rent_lamports: recipient.rent_lamports,
+        destination_accounts: vec![destination_token_account.to_string()],
+    };
+    let mut warnings = build_warnings(&state, &payload.from_address, &rounding, &exchange);
+    warnings.extend_flags(state.anomaly.check_transfer(&summary, USDC_DECIMALS, payload.options.previous_build_id.is_some()));
+    if payload.options.treat_warnings_as_errors && !warnings.is_empty() {
+        return Err(warnings_error(&warnings));
+    }
+
+    // Consume spending session allowance only once the build has succeeded
+    let session_id = match charge_session(&state, &headers, "build-transfer-usdc", "USDC", amount) {
+        Ok(id) => id,
+        Err(e) => return Err(session_error(e)),
+    };
+
+    let build = record_build(&state, summary, &payload.options.previous_build_id);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "transaction": serde_json::Value::String(
+                general_purpose::STANDARD.encode(&serialized_tx)
+            ),
+            "blockhash": blockhash.to_string(),
+            "last_valid_block_height": recent.last_valid_block_height,
+            "slot": recent.slot,
+            "expires_in_seconds": recent.expires_in_seconds(),
+            "from": payload.from_address,
+            "to": payload.to_address,
+            "amount": payload.amount,
+            "amount_raw": transfer_amount.raw(),
+            "amount_ui": transfer_amount.ui(),
+            "rounding": rounding,
+            "sweep": swept,
+            "close_account": payload.close_account,
+            "balance_check": balance_check.as_str(),
+            "yid": payload.yid,
+            "memo": memo_text,
+            "memo_privacy": memo_privacy.as_str(),
+            "network": payload.network,
+            "create_ata": create_ata.as_str(),
+            "ata_exists": recipient.exists,
+            "ata_created": recipient.created,
+            "rent_lamports": recipient.rent_lamports,
+            "rent_sol": utils::base_units_to_ui(recipient.rent_lamports, 9),
+            "network_cost": network_cost,
+            "session_id": session_id,
+            "build": build,
+            "warnings": warnings,
+            "extra_instructions": extras.describe(),
+            "exchange": exchange,
+            "low_balance": state.fee_wallet.is_low(&payload.from_address)
+        }
+    })))
+}
+
+async fn build_transfer_sol(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    StrictJson(payload): StrictJson<TransferSolRequest>,
+) -> ApiResult {
+    sol_transfer(state, headers, payload, "build-transfer-sol").await
+}
+
+// Everything the wallet holds, less fees and (by default) the rent-exempt minimum
+async fn build_sweep_sol(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    StrictJson(payload): StrictJson<BuildSweepSolRequest>,
+) -> ApiResult {
+    let transfer = TransferSolRequest {
+        network: payload.network,
+        target: payload.target,
+        from_address: payload.from_address,
+        to_address: payload.to_address,
+        amount: "max".to_string(),
+        amount_raw: None,
+        yid: payload.yid,
+        // No extra_instructions: ones that move SOL would make the computed sweep amount wrong
+        options: TransferOptions {
+            notes: payload.notes,
+            fee_amount: payload.fee_amount,
+            memo_privacy: payload.memo_privacy,
+            previous_build_id: payload.previous_build_id,
+            exchange_memo: payload.exchange_memo,
+            treat_warnings_as_errors: payload.treat_warnings_as_errors,
+            fresh_blockhash: payload.fresh_blockhash,
+            ..TransferOptions::default()
+        },
+        keep_rent_exempt: payload.keep_rent_exempt.unwrap_or(true),
+    };
+    sol_transfer(state, headers, transfer, "build-sweep-sol").await
+}
+
+/// Shared by /build-transfer-sol and /build-sweep-sol; `endpoint` is what a session is charged as.
+async fn sol_transfer(state: AppState, headers: HeaderMap, mut payload: TransferSolRequest, endpoint: &str) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    // Fetch fresh blockhash
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, false).await?;
+    let rpc = state.rpc.client(&payload.network, &rpc_url, CommitmentConfig::default());
+
+    let recent = state.blockhashes.get(&rpc, &rpc_url, payload.options.fresh_blockhash).await
+        .map_err(|e| ApiError::upstream(format!("Failed to fetch blockhash: {}", e)).code("blockhash_fetch_failed"))?;
+    let blockhash = recent.blockhash;
+
+    // Parse addresses
+    let from_pubkey = string_to_pub_key(&payload.from_address)
+        .map_err(|_| ApiError::bad_request("Invalid from_address").code("invalid_address"))?;
+
+    if let Err(e) = state.fee_wallet.check_payer(&payload.from_address) {
+        return Err(ApiError::unavailable(e).code("fee_wallet_depleted"));
+    }
+
+    let to_pubkey = string_to_pub_key(&payload.to_address)
+        .map_err(|_| ApiError::bad_request("Invalid to_address").code("invalid_address"))?;
+
+    // Compute budget instructions; a sweep needs the fee before it knows the amount
+    let compute_unit_limit = SOL_TRANSFER_COMPUTE_UNITS;
+    let compute_unit_price = compute_unit_price(&state, payload.options.fee_amount.as_deref(), 0);
+
+    // amount "max" sends everything the fees (and the reserve, if kept) leave behind
+    let mut sweep = None;
+    let (amount_text, amount_raw, swept) = match resolve_sweep(&payload.amount, &payload.amount_raw, false, false, async {
+        let computed = sol_sweep(&rpc, &from_pubkey, compute_unit_price, payload.keep_rent_exempt).await?;
+        sweep = Some(computed);
+        Ok(computed.sweep_lamports)
+    }).await {
+        Ok(resolved) => resolved,
+        Err(e) => return Err(amount_error(&payload.amount, &payload.amount_raw, e)),
+    };
+
+    // Parse amount (in SOL, convert to lamports)
+    let (transfer_amount, rounding) = parse_transfer_amount(amount::Asset::Sol, &amount_text, &amount_raw, 9, &payload.options.rounding)
+        .map_err(|e| amount_error(&payload.amount, &payload.amount_raw, e))?;
+    let amount_lamports = transfer_amount.base_units();
+
+    // The sender pays the signature fee and priority fee on top of the amount
+    let network_cost = estimate_network_cost(compute_unit_limit, compute_unit_price, 0);
+    let required = transfer_amount.checked_add(&network_cost.total()).unwrap_or_else(|| transfer_amount.with_base_units(u64::MAX));
+    let balance_check = match preflight_balance(payload.options.skip_balance_check, swept, &required, async {
+        rpc.get_balance(&from_pubkey).await.map_err(|e| e.to_string())
+    }).await {
+        Ok(check) => check,
+        Err(shortfall) => return Err(insufficient_funds_error("SOL", &shortfall)),
+    };
+
+    let memo_privacy = resolve_memo_privacy(&payload.options.memo_privacy, state.memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo_privacy"))?;
+
+    // Build memo: fuego|SOL|f:{from}|t:{to}|a:{amount}|yid:{yid}|n:{notes} (minimal privacy drops f/t)
+    let memo_text = build_memo("SOL", &payload.from_address, &payload.to_address, &transfer_amount, &payload.yid, payload.options.notes.as_deref(), memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo"))?;
+    
+    // Try converting to the format solana_system_interface expects
+    let from_bytes = from_pubkey.to_bytes();
+    let to_bytes = to_pubkey.to_bytes();
+    
+    // Build instructions using solana_system_interface with explicit bytes
+    let transfer_instruction = transfer(
+        &solana_sdk::pubkey::Pubkey::new_from_array(from_bytes),
+        &solana_sdk::pubkey::Pubkey::new_from_array(to_bytes),
+        amount_lamports
+    );
+    
+    let memo_instruction = spl_memo::build_memo(memo_text.as_bytes(), &[]);
+
+    let compute_limit = ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit);
+    let unit_price = ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price);
+
+    // Create transaction message with fresh blockhash
+    let extras = extra_instructions::parse(&payload.options.extra_instructions, &[from_pubkey])
+        .map_err(|e| ApiError::bad_request(e).code("invalid_extra_instructions"))?;
+
+    let (exchange_memo_ix, exchange) = match exchange_deposit_check(&payload.to_address, "SOL", &payload.options.exchange_memo) {
+        Ok(r) => r,
+        Err((code, e)) => {
+            return Err(ApiError::bad_request(e).code(code));
+        }
+    };
+
+    let memo_ix = utils::instruction_from_spl(&memo_instruction);
+    let mut head = vec![compute_limit, unit_price, transfer_instruction];
+    head.extend(exchange_memo_ix);
+    let message = Message::new_with_blockhash(
+        &extras.assemble(head, memo_ix),
+        Some(&from_pubkey),
+        &blockhash,
+    );
+
+    let transaction = Transaction::new_unsigned(message);
+
+    // Serialize transaction
+    let serialized_tx = bincode::serialize(&transaction)
+        .map_err(|_| ApiError::internal("Failed to serialize transaction").code("serialization_failed"))?;
+
+    // Unsigned transactions already carry zeroed signature slots, so this is the on-wire size
+    if serialized_tx.len() > extra_instructions::MAX_TRANSACTION_BYTES {
+        return Err(ApiError::bad_request(format!(
+            "Transaction is {} bytes, over the {} byte limit; remove some extra_instructions",
+            serialized_tx.len(),
+            extra_instructions::MAX_TRANSACTION_BYTES
+        ))
+        .code("transaction_too_large"));
+    }
+
+    let summary = builds::BuildSummary {
+        token: "SOL".to_string(),
+        from: payload.from_address.clone(),
+        to: payload.to_address.clone(),
+        amount: amount_lamports,
+        compute_unit_limit,
+        compute_unit_price,
+        rent_lamports: 0,
+        destination_accounts: vec![payload.to_address.clone()],
+    };
+    let mut warnings = build_warnings(&state, &payload.from_address, &rounding, &exchange);
+    warnings.extend_flags(state.anomaly.check_transfer(&summary, 9, payload.options.previous_build_id.is_some()));
+    if payload.options.treat_warnings_as_errors && !warnings.is_empty() {
+        return Err(warnings_error(&warnings));
+    }
+
+    // Consume spending session allowance only once the build has succeeded
+    let session_id = charge_session(&state, &headers, endpoint, "SOL", amount_lamports).map_err(session_error)?;
+
+    let build = record_build(&state, summary, &payload.options.previous_build_id);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "transaction": serde_json::Value::String(
+                general_purpose::STANDARD.encode(&serialized_tx)
+            ),
+            "blockhash": blockhash.to_string(),
+            "last_valid_block_height": recent.last_valid_block_height,
+            "slot": recent.slot,
+            "expires_in_seconds": recent.expires_in_seconds(),
+            "from": payload.from_address,
+            "to": payload.to_address,
+            "amount": payload.amount,
+            "amount_raw": transfer_amount.raw(),
+            "amount_ui": transfer_amount.ui(),
+            "rounding": rounding,
+            "sweep": swept,
+            "sweep_breakdown": sweep,
+            "balance_check": balance_check.as_str(),
+            "network_cost": network_cost,
+            "yid": payload.yid,
+            "memo": memo_text,
+            "memo_privacy": memo_privacy.as_str(),
+            "network": payload.network,
+            "session_id": session_id,
+            "build": build,
+            "warnings": warnings,
+            "extra_instructions": extras.describe(),
+            "exchange": exchange,
+            "low_balance": state.fee_wallet.is_low(&payload.from_address)
+        }
+    })))
+}
+
+async fn build_transfer_usdt(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    StrictJson(mut payload): StrictJson<TransferUsdtRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    // Fetch fresh blockhash
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, false).await?;
+    let rpc = state.rpc.client(&payload.network, &rpc_url, CommitmentConfig::default());
+
+    let recent = state.blockhashes.get(&rpc, &rpc_url, payload.options.fresh_blockhash).await
+        .map_err(|e| ApiError::upstream(format!("Failed to fetch blockhash: {}", e)).code("blockhash_fetch_failed"))?;
+    let blockhash = recent.blockhash;
+
+    // Parse addresses
+    let from_pubkey = string_to_pub_key(&payload.from_address)
+        .map_err(|_| ApiError::bad_request("Invalid from_address").code("invalid_address"))?;
+
+    if let Err(e) = state.fee_wallet.check_payer(&payload.from_address) {
+        return Err(ApiError::unavailable(e).code("fee_wallet_depleted"));
+    }
+
+    let to_pubkey = string_to_pub_key(&payload.to_address)
+        .map_err(|_| ApiError::bad_request("Invalid to_address").code("invalid_address"))?;
+
+    let usdt_mint = string_to_pub_key(USDT_MINT).map_err(|_| ApiError::internal("Invalid USDT mint").code("internal_error"))?;
+
+
+    // Resolve the owning token program instead of assuming spl-token, so a Token-2022 mint works too
+    let token_program = state.mints.token_program(&rpc_url, &payload.network, USDT_MINT).await?;
+
+    // Get associated token accounts
+    let from_ata = token_account_address(&from_pubkey, &usdt_mint, &token_program);
+    let to_ata = token_account_address(&to_pubkey, &usdt_mint, &token_program);
+
+    // amount "max" sweeps whatever the source token account holds right now
+    let (amount_text, amount_raw, swept) = match resolve_sweep(&payload.amount, &payload.amount_raw, payload.close_account, false, token_account_balance(&rpc, &from_ata)).await {
+        Ok(resolved) => resolved,
+        Err(e) => return Err(amount_error(&payload.amount, &payload.amount_raw, e)),
+    };
+
+    // Parse amount (USDT has 6 decimals)
+    let (transfer_amount, rounding) = match parse_transfer_amount(amount::Asset::Mint(USDT_MINT.to_string()), &amount_text, &amount_raw, USDT_DECIMALS, &payload.options.rounding) {
+        Ok(parsed) => parsed,
+        Err(e) => return Err(amount_error(&payload.amount, &payload.amount_raw, e)),
+    };
+    let amount = transfer_amount.base_units();
+
+    // Refuse a transfer the source token account can't cover
+    let balance_check = match preflight_balance(payload.options.skip_balance_check, swept, &transfer_amount, token_balance_or_zero(&rpc, &from_ata)).await {
+        Ok(check) => check,
+        Err(shortfall) => return Err(insufficient_funds_error("USDT", &shortfall)),
+    };
+
+    let create_ata = CreateAta::parse(&payload.create_ata).map_err(|e| ApiError::bad_request(e).code("invalid_create_ata"))?;
+
+    // A recipient that has never held USDT has no account to receive it; create it (paid by the sender)
+    let recipient = recipient_ata(&rpc, create_ata, &from_pubkey, &to_pubkey, &usdt_mint, &token_program, &to_ata).await
+        .map_err(|e| ApiError::upstream(e).code("rpc_error"))?;
+
+    // Build instructions, with headroom for the ATA create
+    let compute_unit_limit: u32 = 300_000 + recipient.compute_units();
+    let compute_unit_price = compute_unit_price(&state, payload.options.fee_amount.as_deref(), 100);
+    let compute_limit = ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit);
+    let unit_price = ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price);
+
+    // The sender also pays the transaction fee and any new account's rent, in SOL
+    let network_cost = estimate_network_cost(compute_unit_limit, compute_unit_price, recipient.rent_lamports);
+    let balance_check = match preflight_balance(payload.options.skip_balance_check, false, &network_cost.total(), async {
+        rpc.get_balance(&from_pubkey).await.map_err(|e| e.to_string())
+    }).await {
+        Ok(check) => balance_check.and(check),
+        Err(shortfall) => return Err(insufficient_sol_error(&shortfall, &network_cost)),
+    };
+    let from_spl = utils::to_spl_pubkey(&from_pubkey);
+    let transfer_ix = token_transfer_checked(&token_program, &from_ata, &usdt_mint, &to_ata, &from_pubkey, amount, USDT_DECIMALS);
+
+    let memo_privacy = resolve_memo_privacy(&payload.options.memo_privacy, state.memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo_privacy"))?;
+    let memo_text = build_memo("USDT", &payload.from_address, &payload.to_address, &transfer_amount, &payload.yid, payload.options.notes.as_deref(), memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo"))?;
+    let memo_instruction = spl_memo::build_memo(memo_text.as_bytes(), &[&from_spl]);
+
+    let extras = extra_instructions::parse(&payload.options.extra_instructions, &[from_pubkey])
+        .map_err(|e| ApiError::bad_request(e).code("invalid_extra_instructions"))?;
+
+    let (exchange_memo_ix, exchange) = match exchange_deposit_check(&payload.to_address, "USDT", &payload.options.exchange_memo) {
+        Ok(r) => r,
+        Err((code, e)) => {
+            return Err(ApiError::bad_request(e).code(code));
+        }
+    };
+
+    let memo_ix = utils::instruction_from_spl(&memo_instruction);
+    let mut head = vec![compute_limit, unit_price];
+    head.extend(recipient.instruction.clone());
+    head.push(transfer_ix);
+    if payload.close_account {
+        head.push(token_close_account(&token_program, &from_ata, &from_pubkey, &from_pubkey));
+    }
+    head.extend(exchange_memo_ix);
+    let message = Message::new_with_blockhash(
+        &extras.assemble(head, memo_ix),
+        Some(&from_pubkey),
+        &blockhash,
+    );
+
+    let transaction = Transaction::new_unsigned(message);
+
+    // Serialize transaction
+    let serialized_tx = bincode::serialize(&transaction)
+        .map_err(|_| ApiError::internal("Failed to serialize transaction").code("serialization_failed"))?;
+
+    // Unsigned transactions already carry zeroed signature slots, so this is the on-wire size
+    if serialized_tx.len() > extra_instructions::MAX_TRANSACTION_BYTES {
+        return Err(ApiError::bad_request(format!(
+            "Transaction is {} bytes, over the {} byte limit; remove some extra_instructions",
+            serialized_tx.len(),
+            extra_instructions::MAX_TRANSACTION_BYTES
+        ))
+        .code("transaction_too_large"));
+    }
+
+    let summary = builds::BuildSummary {
+        token: "USDT".to_string(),
+        from: payload.from_address.clone(),
+        to: payload.to_address.clone(),
+        amount,
+        compute_unit_limit,
+        compute_unit_price,
+        rent_lamports: recipient.rent_lamports,
+        destination_accounts: vec![to_ata.to_string()],
+    };
+    let mut warnings = build_warnings(&state, &payload.from_address, &rounding, &exchange);
+    warnings.extend_flags(state.anomaly.check_transfer(&summary, USDT_DECIMALS, payload.options.previous_build_id.is_some()));
+    if payload.options.treat_warnings_as_errors && !warnings.is_empty() {
+        return Err(warnings_error(&warnings));
+    }
+
+    // Consume spending session allowance only once the build has succeeded
+    let session_id = match charge_session(&state, &headers, "build-transfer-usdt", "USDT", amount) {
+        Ok(id) => id,
+        Err(e) => return Err(session_error(e)),
+    };
+
+    let build = record_build(&state, summary, &payload.options.previous_build_id);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "transaction": serde_json::Value::String(
+                general_purpose::STANDARD.encode(&serialized_tx)
+            ),
+            "blockhash": blockhash.to_string(),
+            "last_valid_block_height": recent.last_valid_block_height,
+            "slot": recent.slot,
+            "expires_in_seconds": recent.expires_in_seconds(),
+            "from": payload.from_address,
+            "to": payload.to_address,
+            "amount": payload.amount,
+            "amount_raw": transfer_amount.raw(),
+            "amount_ui": transfer_amount.ui(),
+            "rounding": rounding,
+            "sweep": swept,
+            "close_account": payload.close_account,
+            "balance_check": balance_check.as_str(),
+            "yid": payload.yid,
+            "memo": memo_text,
+            "memo_privacy": memo_privacy.as_str(),
+            "network": payload.network,
+            "create_ata": create_ata.as_str(),
+            "ata_exists": recipient.exists,
+            "ata_created": recipient.created,
+            "rent_lamports": recipient.rent_lamports,
+            "rent_sol": utils::base_units_to_ui(recipient.rent_lamports, 9),
+            "network_cost": network_cost,
+            "session_id": session_id,
+            "build": build,
+            "warnings": warnings,
+            "extra_instructions": extras.describe(),
+            "exchange": exchange,
+            "low_balance": state.fee_wallet.is_low(&payload.from_address)
+        }
+    })))
+}
+
+/// Size of a Token-2022 associated token account for a mint with transfer fees: the base account
+/// plus the ImmutableOwner and TransferFeeAmount extensions. Used to estimate rent for a new ATA.
+const TOKEN_2022_FEE_ATA_LEN: usize = 182;
+
+/// Associated token account of `owner` for `mint`, derived under the mint's token program.
+fn token_account_address(
+    owner: &solana_sdk::pubkey::Pubkey,
+    mint: &solana_sdk::pubkey::Pubkey,
+    program_id: &solana_sdk::pubkey::Pubkey,
+) -> solana_sdk::pubkey::Pubkey {
+    utils::from_spl_pubkey(&spl_associated_token_account::get_associated_token_address_with_program_id(
+        &utils::to_spl_pubkey(owner),
+        &utils::to_spl_pubkey(mint),
+        &utils::to_spl_pubkey(program_id),
+    ))
+}
+
+/// `create_ata` on the token transfer builders: whether to prepend an idempotent create of the
+/// recipient's associated token account.
+#[derive(Clone, Copy, PartialEq)]
+enum CreateAta {
+    /// Only when the account doesn't exist yet (default)
+    Auto,
+    Never,
+    /// Even when it exists, in case it is closed before the transaction lands
+    Always,
+}
+
+impl CreateAta {
+    fn parse(value: &Option<String>) -> Result<Self, String> {
+        match value.as_deref() {
+            None | Some("auto") => Ok(CreateAta::Auto),
+            Some("never") => Ok(CreateAta::Never),
+            Some("always") => Ok(CreateAta::Always),
+            Some(other) => Err(format!("Unknown create_ata '{}'; use auto, never or always", other)),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            CreateAta::Auto => "auto",
+            CreateAta::Never => "never",
+            CreateAta::Always => "always",
+        }
+    }
+}
+
+/// Extra compute for an idempotent ATA create; an actual create costs roughly 25k units.
+const CREATE_ATA_COMPUTE_UNITS: u32 = 30_000;
+
+/// What a transfer build does about the recipient's associated token account.
+struct RecipientAta {
+    /// Create instruction to place before the transfer
+    instruction: Option<solana_sdk::instruction::Instruction>,
+    /// None when create_ata is "never" and the account wasn't looked up
+    exists: Option<bool>,
+    /// True when the transaction will create the account (and the sender pays its rent)
+    created: bool,
+    rent_lamports: u64,
+}
+
+impl RecipientAta {
+    fn compute_units(&self) -> u32 {
+        if self.instruction.is_some() {
+            CREATE_ATA_COMPUTE_UNITS
+        } else {
+            0
+        }
+    }
+}
+
+/// Look up the recipient's ATA and, per `mode`, build an idempotent create paid by `payer`.
+/// Rent is estimated for a plain account, or one with transfer-fee extensions under Token-2022.
+async fn recipient_ata(
+    rpc: &RpcClient,
+    mode: CreateAta,
+    payer: &solana_sdk::pubkey::Pubkey,
+    owner: &solana_sdk::pubkey::Pubkey,
+    mint: &solana_sdk::pubkey::Pubkey,
+    program_id: &solana_sdk::pubkey::Pubkey,
+    ata: &solana_sdk::pubkey::Pubkey,
+) -> Result<RecipientAta, String> {
+    if mode == CreateAta::Never {
+        return Ok(RecipientAta { instruction: None, exists: None, created: false, rent_lamports: 0 });
+    }
+    let exists = rpc
+        .get_account_with_commitment(ata, CommitmentConfig::confirmed())
+        .await
+        .map_err(|e| format!("Failed to check recipient token account: {}", e))?
+        .value
+        .is_some();
+    if exists && mode == CreateAta::Auto {
+        return Ok(RecipientAta { instruction: None, exists: Some(true), created: false, rent_lamports: 0 });
+    }
+
+    let create = spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+        &utils::to_spl_pubkey(payer),
+        &utils::to_spl_pubkey(owner),
+        &utils::to_spl_pubkey(mint),
+        &utils::to_spl_pubkey(program_id),
+    );
+    let rent_lamports = if exists {
+        0
+    } else {
+        let account_len = if program_id.to_string() == mints::TOKEN_2022_PROGRAM {
+            TOKEN_2022_FEE_ATA_LEN
+        } else {
+            SPL_TOKEN_ACCOUNT_LEN
+        };
+        rpc.get_minimum_balance_for_rent_exemption(account_len)
+            .await
+            .map_err(|e| format!("Failed to fetch rent: {}", e))?
+    };
+    Ok(RecipientAta {
+        instruction: Some(utils::instruction_from_spl(&create)),
+        exists: Some(exists),
+        created: !exists,
+        rent_lamports,
+    })
+}
+
+/// `TransferChecked` (instruction 12) for either token program. spl_token's builder only accepts the
+/// legacy program id, and the instruction layout is identical under Token-2022, so it is assembled here.
+fn token_transfer_checked(
+    program_id: &solana_sdk::pubkey::Pubkey,
+    source: &solana_sdk::pubkey::Pubkey,
+    mint: &solana_sdk::pubkey::Pubkey,
+    destination: &solana_sdk::pubkey::Pubkey,
+    authority: &solana_sdk::pubkey::Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> solana_sdk::instruction::Instruction {
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+
+    let mut data = vec![12u8];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta { pubkey: *source, is_signer: false, is_writable: true },
+            AccountMeta { pubkey: *mint, is_signer: false, is_writable: false },
+            AccountMeta { pubkey: *destination, is_signer: false, is_writable: true },
+            AccountMeta { pubkey: *authority, is_signer: true, is_writable: false },
+        ],
+        data,
+    }
+}
+
+/// SPL Token / Token-2022 `CloseAccount` (instruction 9): the emptied account's rent goes to
+/// `destination`.
+fn token_close_account(
+    program_id: &solana_sdk::pubkey::Pubkey,
+    account: &solana_sdk::pubkey::Pubkey,
+    destination: &solana_sdk::pubkey::Pubkey,
+    owner: &solana_sdk::pubkey::Pubkey,
+) -> solana_sdk::instruction::Instruction {
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta { pubkey: *account, is_signer: false, is_writable: true },
+            AccountMeta { pubkey: *destination, is_signer: false, is_writable: true },
+            AccountMeta { pubkey: *owner, is_signer: true, is_writable: false },
+        ],
+        data: vec![9u8],
+    }
+}
+
+/// Token-2022 `TransferCheckedWithFee` (TransferFeeExtension, instruction 26/1). The program rejects
+/// it unless `fee` matches what the mint's current schedule withholds, so a transfer never silently
+/// delivers less than the build showed.
+fn token_transfer_checked_with_fee(
+    source: &solana_sdk::pubkey::Pubkey,
+    mint: &solana_sdk::pubkey::Pubkey,
+    destination: &solana_sdk::pubkey::Pubkey,
+    authority: &solana_sdk::pubkey::Pubkey,
+    amount: u64,
+    decimals: u8,
+    fee: u64,
+) -> Result<solana_sdk::instruction::Instruction, String> {
+    let program_id = string_to_pub_key(mints::TOKEN_2022_PROGRAM).map_err(|_| "Invalid Token-2022 program id".to_string())?;
+    // Same accounts as TransferChecked; only the data differs
+    let mut instruction = token_transfer_checked(&program_id, source, mint, destination, authority, amount, decimals);
+    let mut data = vec![26u8, 1];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+    data.extend_from_slice(&fee.to_le_bytes());
+    instruction.data = data;
+    Ok(instruction)
+}
+
+/// Transfer instruction for a Token-2022 mint, honouring its transfer fee when it has one.
+fn token_2022_transfer(
+    source: &solana_sdk::pubkey::Pubkey,
+    mint: &solana_sdk::pubkey::Pubkey,
+    destination: &solana_sdk::pubkey::Pubkey,
+    authority: &solana_sdk::pubkey::Pubkey,
+    amount: u64,
+    decimals: u8,
+    fee_plan: &Option<transfer_fee::FeePlan>,
+) -> Result<solana_sdk::instruction::Instruction, String> {
+    match fee_plan {
+        Some(plan) => token_transfer_checked_with_fee(source, mint, destination, authority, plan.send, decimals, plan.expected_fee),
+        None => {
+            let program_id = string_to_pub_key(mints::TOKEN_2022_PROGRAM).map_err(|_| "Invalid Token-2022 program id".to_string())?;
+            Ok(token_transfer_checked(&program_id, source, mint, destination, authority, amount, decimals))
+        }
+    }
+}
+
+async fn build_transfer_pyusd(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    StrictJson(mut payload): StrictJson<TransferPyusdRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    // Fetch fresh blockhash
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, false).await?;
+    let rpc = state.rpc.client(&payload.network, &rpc_url, CommitmentConfig::default());
+
+    let recent = state.blockhashes.get(&rpc, &rpc_url, payload.options.fresh_blockhash).await
+        .map_err(|e| ApiError::upstream(format!("Failed to fetch blockhash: {}", e)).code("blockhash_fetch_failed"))?;
+    let blockhash = recent.blockhash;
+
+    // Parse addresses
+    let from_pubkey = string_to_pub_key(&payload.from_address)
+        .map_err(|_| ApiError::bad_request("Invalid from_address").code("invalid_address"))?;
+
+    if let Err(e) = state.fee_wallet.check_payer(&payload.from_address) {
+        return Err(ApiError::unavailable(e).code("fee_wallet_depleted"));
+    }
+
+    let to_pubkey = string_to_pub_key(&payload.to_address)
+        .map_err(|_| ApiError::bad_request("Invalid to_address").code("invalid_address"))?;
+
+    let (pyusd_mint, token_program) = match (string_to_pub_key(PYUSD_MINT), string_to_pub_key(mints::TOKEN_2022_PROGRAM)) {
+        (Ok(mint), Ok(program)) => (mint, program),
+        _ => {
+            return Err(ApiError::internal("Invalid PYUSD mint").code("internal_error"));
+        }
+    };
+
+    // PYUSD is a Token-2022 mint, so its ATAs are derived with the Token-2022 program id
+    let source_token_account = token_account_address(&from_pubkey, &pyusd_mint, &token_program);
+    let destination_token_account = token_account_address(&to_pubkey, &pyusd_mint, &token_program);
+
+    // amount "max" sweeps whatever the source token account holds right now
+    let (amount_text, amount_raw, swept) = match resolve_sweep(&payload.amount, &payload.amount_raw, payload.close_account, payload.gross_up, token_account_balance(&rpc, &source_token_account)).await {
+        Ok(resolved) => resolved,
+        Err(e) => return Err(amount_error(&payload.amount, &payload.amount_raw, e)),
+    };
+
+    let (transfer_amount, rounding) = match parse_transfer_amount(amount::Asset::Mint(PYUSD_MINT.to_string()), &amount_text, &amount_raw, PYUSD_DECIMALS, &payload.options.rounding) {
+        Ok(parsed) => parsed,
+        Err(e) => return Err(amount_error(&payload.amount, &payload.amount_raw, e)),
+    };
+    let amount = transfer_amount.base_units();
+
+    // Read the transfer fee fresh: the build must state the exact fee the program will withhold
+    let fee_plan = transfer_fee::fetch_mint_fees(&state.rpc, &payload.network, &rpc_url, PYUSD_MINT).await.and_then(|fees| fees.plan(amount, payload.gross_up))
+        .map_err(|e| ApiError::upstream(e).code("rpc_error"))?;
+    let send_amount = fee_plan.map(|p| p.send).unwrap_or(amount);
+
+    // Refuse a transfer the source token account can't cover
+    let balance_check = match preflight_balance(payload.options.skip_balance_check, swept, &transfer_amount.with_base_units(send_amount), token_balance_or_zero(&rpc, &source_token_account)).await {
+        Ok(check) => check,
+        Err(shortfall) => return Err(insufficient_funds_error("PYUSD", &shortfall)),
+    };
+
+    let memo_privacy = resolve_memo_privacy(&payload.options.memo_privacy, state.memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo_privacy"))?;
+
+    // Build memo: fuego|PYUSD|m:{mint}|f:{from}|t:{to}|a:{amount}|yid:{yid}|n:{notes} (minimal privacy drops f/t)
+    let memo_text = build_memo("PYUSD", &payload.from_address, &payload.to_address, &transfer_amount.with_base_units(send_amount), &payload.yid, payload.options.notes.as_deref(), memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo"))?;
+
+    let create_ata = CreateAta::parse(&payload.create_ata).map_err(|e| ApiError::bad_request(e).code("invalid_create_ata"))?;
+
+    // A recipient that has never held PYUSD has no account to receive it; create it (paid by the sender)
+    let recipient = recipient_ata(&rpc, create_ata, &from_pubkey, &to_pubkey, &pyusd_mint, &token_program, &destination_token_account).await
+        .map_err(|e| ApiError::upstream(e).code("rpc_error"))?;
+
+    let transfer_ix = match token_2022_transfer(
+        &source_token_account,
+        &pyusd_mint,
+        &destination_token_account,
+        &from_pubkey,
+        amount,
+        PYUSD_DECIMALS,
+        &fee_plan,
+    ) {
+        Ok(ix) => ix,
+        Err(e) => {
+            return Err(ApiError::internal(e).code("internal_error"));
+        }
+    };
+
+    let memo_instruction = spl_memo::build_memo(memo_text.as_bytes(), &[]);
+
+    // Compute budget instructions, plus headroom for the ATA create
+    let compute_unit_limit: u32 = 100_000 + recipient.compute_units();
+    let compute_unit_price = compute_unit_price(&state, payload.options.fee_amount.as_deref(), 0);
+    let compute_limit = ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit);
+    let unit_price = ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price);
+
+    // The sender also pays the transaction fee and any new account's rent, in SOL
+    let network_cost = estimate_network_cost(compute_unit_limit, compute_unit_price, recipient.rent_lamports);
+    let balance_check = match preflight_balance(payload.options.skip_balance_check, false, &network_cost.total(), async {
+        rpc.get_balance(&from_pubkey).await.map_err(|e| e.to_string())
+    }).await {
+        Ok(check) => balance_check.and(check),
+        Err(shortfall) => return Err(insufficient_sol_error(&shortfall, &network_cost)),
+    };
+
+    let extras = extra_instructions::parse(&payload.options.extra_instructions, &[from_pubkey])
+        .map_err(|e| ApiError::bad_request(e).code("invalid_extra_instructions"))?;
+
+    let (exchange_memo_ix, exchange) = match exchange_deposit_check(&payload.to_address, "PYUSD", &payload.options.exchange_memo) {
+        Ok(r) => r,
+        Err((code, e)) => {
+            return Err(ApiError::bad_request(e).code(code));
+        }
+    };
+
+    let memo_ix = utils::instruction_from_spl(&memo_instruction);
+    let mut head = vec![compute_limit, unit_price];
+    head.extend(recipient.instruction.clone());
+    head.push(transfer_ix);
+    if payload.close_account {
+        head.push(token_close_account(&token_program, &source_token_account, &from_pubkey, &from_pubkey));
+    }
+    head.extend(exchange_memo_ix);
+    let message = Message::new_with_blockhash(
+        &extras.assemble(head, memo_ix),
+        Some(&from_pubkey),
+        &blockhash,
+    );
+
+    let transaction = Transaction::new_unsigned(message);
+
+    // Serialize transaction
+    let serialized_tx = bincode::serialize(&transaction)
+        .map_err(|_| ApiError::internal("Failed to serialize transaction").code("serialization_failed"))?;
+
+    // Unsigned transactions already carry zeroed signature slots, so this is the on-wire size
+    if serialized_tx.len() > extra_instructions::MAX_TRANSACTION_BYTES {
+        return Err(ApiError::bad_request(format!(
+            "Transaction is {} bytes, over the {} byte limit; remove some extra_instructions",
+            serialized_tx.len(),
+            extra_instructions::MAX_TRANSACTION_BYTES
+        ))
+        .code("transaction_too_large"));
+    }
+
+    let summary = builds::BuildSummary {
+        token: "PYUSD".to_string(),
+        from: payload.from_address.clone(),
+        to: payload.to_address.clone(),
+        amount: send_amount,
+        compute_unit_limit,
+        compute_unit_price,
+        rent_lamports: recipient.rent_lamports,
+        destination_accounts: vec![destination_token_account.to_string()],
+    };
+    let mut warnings = build_warnings(&state, &payload.from_address, &rounding, &exchange);
+    warnings.extend_flags(state.anomaly.check_transfer(&summary, PYUSD_DECIMALS, payload.options.previous_build_id.is_some()));
+    warnings.transfer_fee(&fee_plan);
+    if payload.options.treat_warnings_as_errors && !warnings.is_empty() {
+        return Err(warnings_error(&warnings));
+    }
+
+    // Consume spending session allowance only once the build has succeeded
+    let session_id = match charge_session(&state, &headers, "build-transfer-pyusd", "PYUSD", send_amount) {
+        Ok(id) => id,
+        Err(e) => return Err(session_error(e)),
+    };
+
+    let build = record_build(&state, summary, &payload.options.previous_build_id);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "transaction": serde_json::Value::String(
+                general_purpose::STANDARD.encode(&serialized_tx)
+            ),
+            "blockhash": blockhash.to_string(),
+            "last_valid_block_height": recent.last_valid_block_height,
+            "slot": recent.slot,
+            "expires_in_seconds": recent.expires_in_seconds(),
+            "from": payload.from_address,
+            "to": payload.to_address,
+            "amount": payload.amount,
+            "amount_raw": transfer_amount.raw(),
+            "amount_ui": transfer_amount.ui(),
+            "rounding": rounding,
+            "sweep": swept,
+            "close_account": payload.close_account,
+            "balance_check": balance_check.as_str(),
+            "yid": payload.yid,
+            "memo": memo_text,
+            "memo_privacy": memo_privacy.as_str(),
+            "network": payload.network,
+            "token_program": mints::TOKEN_2022_PROGRAM,
+            "destination_token_account": destination_token_account.to_string(),
+            "create_ata": create_ata.as_str(),
+            "ata_exists": recipient.exists,
+            "ata_created": recipient.created,
+            "rent_lamports": recipient.rent_lamports,
+            "rent_sol": utils::base_units_to_ui(recipient.rent_lamports, 9),
+            "network_cost": network_cost,
+            "expected_fee": utils::base_units_to_ui(fee_plan.map(|p| p.expected_fee).unwrap_or(0), PYUSD_DECIMALS),
+            "net_amount": utils::base_units_to_ui(fee_plan.map(|p| p.net_amount).unwrap_or(amount), PYUSD_DECIMALS),
+            "transfer_fee": fee_plan,
+            "session_id": session_id,
+            "build": build,
+            "warnings": warnings,
+            "extra_instructions": extras.describe(),
+            "exchange": exchange,
+            "low_balance": state.fee_wallet.is_low(&payload.from_address)
+        }
+    })))
+}
+
+// Any mint: decimals and owning token program come from the mint account, never a hardcoded value
+async fn build_transfer_token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    StrictJson(mut payload): StrictJson<TransferTokenRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, false).await?;
+
+    let from_pubkey = string_to_pub_key(&payload.from_address)
+        .map_err(|_| ApiError::bad_request("Invalid from_address").code("invalid_address"))?;
+
+    if let Err(e) = state.fee_wallet.check_payer(&payload.from_address) {
+        return Err(ApiError::unavailable(e).code("fee_wallet_depleted"));
+    }
+
+    let to_pubkey = string_to_pub_key(&payload.to_address)
+        .map_err(|_| ApiError::bad_request("Invalid to_address").code("invalid_address"))?;
+
+    let mint_pubkey = string_to_pub_key(&payload.mint).map_err(|_| ApiError::bad_request("Invalid mint address").code("invalid_mint"))?;
+    let info = state.mints.get(&rpc_url, &payload.network, &payload.mint).await?;
+    let token_program = string_to_pub_key(&info.program_id)
+        .map_err(|_| ApiError::upstream("Mint has an unparseable owner program").code("rpc_error"))?;
+
+    let source_token_account = token_account_address(&from_pubkey, &mint_pubkey, &token_program);
+
+    // amount "max" sweeps whatever the source token account holds right now
+    let (amount_text, amount_raw, swept) = match resolve_sweep(&payload.amount, &payload.amount_raw, payload.close_account, payload.gross_up, async {
+        token_account_balance(&state.rpc.client(&payload.network, &rpc_url, CommitmentConfig::default()), &source_token_account).await
+    }).await {
+        Ok(resolved) => resolved,
+        Err(e) => return Err(amount_error(&payload.amount, &payload.amount_raw, e)),
+    };
+
+    // Amounts with more fractional digits than the mint supports are rejected unless the caller opts into rounding
+    let (transfer_amount, rounding) = match parse_transfer_amount(amount::Asset::Mint(payload.mint.clone()), &amount_text, &amount_raw, info.decimals, &payload.options.rounding) {
+        Ok(parsed) => parsed,
+        Err(e) => return Err(amount_error(&payload.amount, &payload.amount_raw, e)),
+    };
+    let amount = transfer_amount.base_units();
+
+    // Token-2022 mints may withhold a transfer fee; the schedule is read fresh, never from the mint cache
+    let fee_plan = if info.program_id == mints::TOKEN_2022_PROGRAM {
+        match transfer_fee::fetch_mint_fees(&state.rpc, &payload.network, &rpc_url, &payload.mint).await.and_then(|fees| fees.plan(amount, payload.gross_up)) {
+            Ok(plan) => plan,
+            Err(e) => {
+                return Err(ApiError::upstream(e).code("rpc_error"));
+            }
+        }
+    } else {
+        None
+    };
+    let send_amount = fee_plan.map(|p| p.send).unwrap_or(amount);
+
+    let destination_token_account = token_account_address(&to_pubkey, &mint_pubkey, &token_program);
+
+    let memo_privacy = resolve_memo_privacy(&payload.options.memo_privacy, state.memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo_privacy"))?;
+
+    // The memo's token field is the symbol when fuego knows the mint, otherwise the mint address
+    let token_label = get_token_symbol(&payload.mint).unwrap_or(&payload.mint).to_string();
+    let memo_text = build_memo(&token_label, &payload.from_address, &payload.to_address, &transfer_amount.with_base_units(send_amount), &payload.yid, payload.options.notes.as_deref(), memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo"))?;
+
+    let transfer_ix = if info.program_id == mints::TOKEN_2022_PROGRAM {
+        match token_2022_transfer(
+            &source_token_account,
+            &mint_pubkey,
+            &destination_token_account,
+            &from_pubkey,
+            amount,
+            info.decimals,
+            &fee_plan,
+        ) {
+            Ok(ix) => ix,
+            Err(e) => {
+                return Err(ApiError::internal(e).code("internal_error"));
+            }
+        }
+    } else {
+        token_transfer_checked(
+            &token_program,
+            &source_token_account,
+            &mint_pubkey,
+            &destination_token_account,
+            &from_pubkey,
+            amount,
+            info.decimals,
+        )
+    };
+    let memo_instruction = spl_memo::build_memo(memo_text.as_bytes(), &[]);
+
+    let create_ata = CreateAta::parse(&payload.create_ata).map_err(|e| ApiError::bad_request(e).code("invalid_create_ata"))?;
+
+    let extras = extra_instructions::parse(&payload.options.extra_instructions, &[from_pubkey])
+        .map_err(|e| ApiError::bad_request(e).code("invalid_extra_instructions"))?;
+
+    let (exchange_memo_ix, exchange) = match exchange_deposit_check(&payload.to_address, &token_label, &payload.options.exchange_memo) {
+        Ok(r) => r,
+        Err((code, e)) => {
+            return Err(ApiError::bad_request(e).code(code));
+        }
+    };
+
+    // RPC lookups come last, so validation failures never cost a round trip
+    let rpc = state.rpc.client(&payload.network, &rpc_url, CommitmentConfig::default());
+
+    // Refuse a transfer the source token account can't cover
+    let balance_check = match preflight_balance(payload.options.skip_balance_check, swept, &transfer_amount.with_base_units(send_amount), token_balance_or_zero(&rpc, &source_token_account)).await {
+        Ok(check) => check,
+        Err(shortfall) => return Err(insufficient_funds_error(&token_label, &shortfall)),
+    };
+
+    let recipient = recipient_ata(&rpc, create_ata, &from_pubkey, &to_pubkey, &mint_pubkey, &token_program, &destination_token_account).await
+        .map_err(|e| ApiError::upstream(e).code("rpc_error"))?;
+
+    // Compute budget instructions, plus headroom for the ATA create
+    let compute_unit_limit: u32 = 100_000 + recipient.compute_units();
+    let compute_unit_price = compute_unit_price(&state, payload.options.fee_amount.as_deref(), 0);
+    let compute_limit = ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit);
+    let unit_price = ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price);
+
+    // The sender also pays the transaction fee and any new account's rent, in SOL
+    let network_cost = estimate_network_cost(compute_unit_limit, compute_unit_price, recipient.rent_lamports);
+    let balance_check = match preflight_balance(payload.options.skip_balance_check, false, &network_cost.total(), async {
+        rpc.get_balance(&from_pubkey).await.map_err(|e| e.to_string())
+    }).await {
+        Ok(check) => balance_check.and(check),
+        Err(shortfall) => return Err(insufficient_sol_error(&shortfall, &network_cost)),
+    };
+
+    // Fetch the blockhash last
+    let recent = state.blockhashes.get(&rpc, &rpc_url, payload.options.fresh_blockhash).await
+        .map_err(|e| ApiError::upstream(format!("Failed to fetch blockhash: {}", e)).code("blockhash_fetch_failed"))?;
+    let blockhash = recent.blockhash;
+
+    let memo_ix = utils::instruction_from_spl(&memo_instruction);
+    let mut head = vec![compute_limit, unit_price];
+    head.extend(recipient.instruction.clone());
+    head.push(transfer_ix);
+    if payload.close_account {
+        head.push(token_close_account(&token_program, &source_token_account, &from_pubkey, &from_pubkey));
+    }
+    head.extend(exchange_memo_ix);
+    let message = Message::new_with_blockhash(
+        &extras.assemble(head, memo_ix),
+        Some(&from_pubkey),
+        &blockhash,
+    );
+
+    let transaction = Transaction::new_unsigned(message);
+
+    // Serialize transaction
+    let serialized_tx = bincode::serialize(&transaction)
+        .map_err(|_| ApiError::internal("Failed to serialize transaction").code("serialization_failed"))?;
+
+    // Unsigned transactions already carry zeroed signature slots, so this is the on-wire size
+    if serialized_tx.len() > extra_instructions::MAX_TRANSACTION_BYTES {
+        return Err(ApiError::bad_request(format!(
+            "Transaction is {} bytes, over the {} byte limit; remove some extra_instructions",
+            serialized_tx.len(),
+            extra_instructions::MAX_TRANSACTION_BYTES
+        ))
+        .code("transaction_too_large"));
+    }
+
+    let summary = builds::BuildSummary {
+        token: token_label.clone(),
+        from: payload.from_address.clone(),
+        to: payload.to_address.clone(),
+        amount: send_amount,
+        compute_unit_limit,
+        compute_unit_price,
+        rent_lamports: recipient.rent_lamports,
+        destination_accounts: vec![destination_token_account.to_string()],
+    };
+    let mut warnings = build_warnings(&state, &payload.from_address, &rounding, &exchange);
+    warnings.extend_flags(state.anomaly.check_transfer(&summary, info.decimals, payload.options.previous_build_id.is_some()));
+    warnings.transfer_fee(&fee_plan);
+    if payload.options.treat_warnings_as_errors && !warnings.is_empty() {
+        return Err(warnings_error(&warnings));
+    }
+
+    // Consume spending session allowance only once the build has succeeded
+    let session_id = match charge_session(&state, &headers, "build-transfer-token", &token_label, send_amount) {
+        Ok(id) => id,
+        Err(e) => return Err(session_error(e)),
+    };
+
+    let build = record_build(&state, summary, &payload.options.previous_build_id);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "transaction": serde_json::Value::String(
+                general_purpose::STANDARD.encode(&serialized_tx)
+            ),
+            "blockhash": blockhash.to_string(),
+            "last_valid_block_height": recent.last_valid_block_height,
+            "slot": recent.slot,
+            "expires_in_seconds": recent.expires_in_seconds(),
+            "from": payload.from_address,
+            "to": payload.to_address,
+            "amount": payload.amount,
+            "amount_raw": transfer_amount.raw(),
+            "amount_ui": transfer_amount.ui(),
+            "rounding": rounding,
+            "sweep": swept,
+            "close_account": payload.close_account,
+            "balance_check": balance_check.as_str(),
+            "mint": payload.mint,
+            "token": token_label,
+            "decimals": info.decimals,
+            "token_program": info.program_id,
+            "destination_token_account": destination_token_account.to_string(),
+            "create_ata": create_ata.as_str(),
+            "ata_exists": recipient.exists,
+            "ata_created": recipient.created,
+            "rent_lamports": recipient.rent_lamports,
+            "rent_sol": utils::base_units_to_ui(recipient.rent_lamports, 9),
+            "network_cost": network_cost,
+            "expected_fee": utils::base_units_to_ui(fee_plan.map(|p| p.expected_fee).unwrap_or(0), info.decimals),
+            "net_amount": utils::base_units_to_ui(fee_plan.map(|p| p.net_amount).unwrap_or(amount), info.decimals),
+            "transfer_fee": fee_plan,
+            "yid": payload.yid,
+            "memo": memo_text,
+            "memo_privacy": memo_privacy.as_str(),
+            "network": payload.network,
+            "session_id": session_id,
+            "build": build,
+            "warnings": warnings,
+            "extra_instructions": extras.describe(),
+            "exchange": exchange,
+            "low_balance": state.fee_wallet.is_low(&payload.from_address)
+        }
+    })))
+}
+
+/// Size of a plain SPL token account, for the rent a sponsored USDC account costs.
+const SPL_TOKEN_ACCOUNT_LEN: usize = 165;
+
+/// Signature fee per signer, in lamports.
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+// One sponsored transaction from the local wallet: the new wallet's USDC account, SOL for fees and
+// an optional USDC welcome transfer, capped by the daily onboarding budget
+async fn build_onboard(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<BuildOnboardRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let sponsor_address = match load_active_wallet() {
+        Some((address, _, _)) => address,
+        None => {
+            return Err(ApiError::not_found("No local wallet found; onboarding is sponsored by the local wallet")
+                .code("no_local_wallet"));
+        }
+    };
+    let (sponsor, wallet) = match (string_to_pub_key(&sponsor_address), string_to_pub_key(&payload.wallet_address)) {
+        (Ok(sponsor), Ok(wallet)) => (sponsor, wallet),
+        (Err(_), _) => {
+            return Err(ApiError::internal("Local wallet address is invalid").code("wallet_unreadable"));
+        }
+        (_, Err(_)) => {
+            return Err(ApiError::bad_request("Invalid wallet_address").code("invalid_address"));
+        }
+    };
+    if sponsor == wallet {
+        return Err(ApiError::bad_request("wallet_address is the sponsoring wallet itself").code("invalid_request"));
+    }
+    if let Err(e) = state.fee_wallet.check_payer(&sponsor_address) {
+        return Err(ApiError::unavailable(e).code("fee_wallet_depleted"));
+    }
+
+    let sol_lamports = match &payload.sol_amount {
+        Some(amount) => match utils::ui_amount_to_base_units(amount, 9) {
+            Ok(lamports) => lamports,
+            Err(e) => {
+                return Err(ApiError::bad_request(format!("Invalid sol_amount: {}", e)).code("invalid_amount"));
+            }
+        },
+        None => state.onboard.config().default_sol_lamports,
+    };
+    let welcome_usdc = match &payload.welcome_usdc {
+        Some(amount) => match utils::ui_amount_to_base_units(amount, USDC_DECIMALS) {
+            Ok(units) => units,
+            Err(e) => {
+                return Err(ApiError::bad_request(format!("Invalid welcome_usdc: {}", e)).code("invalid_amount"));
+            }
+        },
+        None => 0,
+    };
+
+    let memo_privacy = resolve_memo_privacy(&payload.memo_privacy, state.memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo_privacy"))?;
+    // fuego|ONBOARD|f:{sponsor}|t:{wallet}|a:{sol lamports}|yid:{yid}|n:{notes}; indexed like any fuego memo
+    let memo_text = build_memo("ONBOARD", &sponsor_address, &payload.wallet_address, &amount::TokenAmount::lamports(sol_lamports), &payload.yid, payload.notes.as_deref(), memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo"))?;
+
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, false).await?;
+    let usdc_mint = string_to_pub_key(USDC_MINT).map_err(|_| ApiError::internal("Failed to parse USDC mint").code("internal_error"))?;
+    let token_program = state.mints.token_program(&rpc_url, &payload.network, USDC_MINT).await?;
+    let wallet_token_account = token_account_address(&wallet, &usdc_mint, &token_program);
+
+    let rpc = state.rpc.client(&payload.network, &rpc_url, CommitmentConfig::default());
+    let ata_exists = match rpc.get_account_with_commitment(&wallet_token_account, CommitmentConfig::confirmed()).await {
+        Ok(response) => response.value.is_some(),
+        Err(e) => {
+            return Err(ApiError::upstream(format!("Failed to check the wallet's USDC account: {}", e)).code("rpc_error"));
+        }
+    };
+
+    let compute_unit_limit: u32 = 150_000;
+    let compute_unit_price = compute_unit_price(&state, payload.fee_amount.as_deref(), 0);
+    let mut instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit),
+        ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price),
+    ];
+
+    let rent_lamports = if ata_exists {
+        0
+    } else {
+        let account_len = if token_program.to_string() == mints::TOKEN_2022_PROGRAM {
+            TOKEN_2022_FEE_ATA_LEN
+        } else {
+            SPL_TOKEN_ACCOUNT_LEN
+        };
+        let rent = rpc.get_minimum_balance_for_rent_exemption(account_len).await
+            .map_err(|e| ApiError::upstream(format!("Failed to fetch rent: {}", e)).code("rpc_error"))?;
+        let create_ata = spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+            &utils::to_spl_pubkey(&sponsor),
+            &utils::to_spl_pubkey(&wallet),
+            &utils::to_spl_pubkey(&usdc_mint),
+            &utils::to_spl_pubkey(&token_program),
+        );
+        instructions.push(utils::instruction_from_spl(&create_ata));
+        rent
+    };
+    if sol_lamports > 0 {
+        instructions.push(transfer(&sponsor, &wallet, sol_lamports));
+    }
+    if welcome_usdc > 0 {
+        instructions.push(token_transfer_checked(
+            &token_program,
+            &token_account_address(&sponsor, &usdc_mint, &token_program),
+            &usdc_mint,
+            &wallet_token_account,
+            &sponsor,
+            welcome_usdc,
+            USDC_DECIMALS,
+        ));
+    }
+    instructions.push(utils::instruction_from_spl(&spl_memo::build_memo(memo_text.as_bytes(), &[])));
+
+    // What the sponsor pays in SOL: rent, the SOL sent and the transaction fee
+    let priority_lamports = priority_fee_lamports(compute_unit_limit, compute_unit_price);
+    let fee_lamports = LAMPORTS_PER_SIGNATURE + priority_lamports;
+    let total_lamports = rent_lamports + sol_lamports + fee_lamports;
+
+    let mut warnings = warnings::Warnings::default();
+    if state.fee_wallet.is_low(&sponsor_address) {
+        warnings.fee_wallet_low(&sponsor_address);
+    }
+    if payload.treat_warnings_as_errors && !warnings.is_empty() {
+        return Err(warnings_error(&warnings));
+    }
+
+    let recent = state.blockhashes.get(&rpc, &rpc_url, payload.fresh_blockhash).await
+        .map_err(|e| ApiError::upstream(format!("Failed to fetch blockhash: {}", e)).code("blockhash_fetch_failed"))?;
+    let blockhash = recent.blockhash;
+    let transaction = Transaction::new_unsigned(Message::new_with_blockhash(&instructions, Some(&sponsor), &blockhash));
+    let serialized_tx = bincode::serialize(&transaction)
+        .map_err(|_| ApiError::internal("Failed to serialize transaction").code("serialization_failed"))?;
+
+    // Reserve the budget only once the build has succeeded
+    let ledger = match state.onboard.reserve(total_lamports, welcome_usdc) {
+        Ok(ledger) => ledger,
+        Err(e) => {
+            return Err(ApiError::rate_limited(e.message())
+                .code("onboard_budget_exceeded")
+                .with("total_sponsorship_lamports", total_lamports));
+        }
+    };
+    let budget = state.onboard.config();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "transaction": general_purpose::STANDARD.encode(&serialized_tx),
+            "blockhash": blockhash.to_string(),
+            "last_valid_block_height": recent.last_valid_block_height,
+            "slot": recent.slot,
+            "expires_in_seconds": recent.expires_in_seconds(),
+            "sponsor": sponsor_address,
+            "wallet": payload.wallet_address,
+            "usdc_token_account": wallet_token_account.to_string(),
+            "ata_created": !ata_exists,
+            "rent_lamports": rent_lamports,
+            "sol_lamports": sol_lamports,
+            "fee_lamports": fee_lamports,
+            "welcome_usdc": utils::base_units_to_ui(welcome_usdc, USDC_DECIMALS),
+            "total_sponsorship_lamports": total_lamports,
+            "total_sponsorship_sol": utils::base_units_to_ui(total_lamports, 9),
+            "budget": {
+                "day": ledger.day,
+                "wallets_onboarded": ledger.wallets,
+                "lamports_used": ledger.lamports,
+                "lamports_remaining": budget.daily_lamports.saturating_sub(ledger.lamports),
+                "usdc_used": utils::base_units_to_ui(ledger.usdc, USDC_DECIMALS),
+                "usdc_remaining": utils::base_units_to_ui(budget.daily_usdc.saturating_sub(ledger.usdc), USDC_DECIMALS)
+            },
+            "yid": payload.yid,
+            "memo": memo_text,
+            "memo_privacy": memo_privacy.as_str(),
+            "network": payload.network,
+            "warnings": warnings
+        }
+    })))
+}
+
+/// Build a transfer against a fresh durable nonce account that fuego controls. The transaction
+/// doesn't expire, so it can be signed now and is submitted by the scheduler after `unlock_at`.
+async fn build_timelocked_transfer(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    StrictJson(mut payload): StrictJson<BuildTimelockedTransferRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    use solana_system_interface::instruction::advance_nonce_account;
+
+    if state.mode.is_dry_run() {
+        return Err(ApiError::forbidden("Time-locked transfers create a nonce account on chain, which dry-run mode never does")
+            .code("dry_run_unsupported"));
+    }
+    if let Some(refusal) = wallet_mismatch_refusal(payload.force) {
+        return Err(refusal);
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    if payload.unlock_at <= now {
+        return Err(ApiError::bad_request("unlock_at must be in the future")
+            .code("unlock_in_past")
+            .with("server_time", now));
+    }
+
+    let token = payload.token.as_deref().unwrap_or("SOL").to_uppercase();
+    let (decimals, mint) = match token.as_str() {
+        "SOL" => (9, None),
+        "USDC" => (USDC_DECIMALS, Some(USDC_MINT)),
+        "USDT" => (USDT_DECIMALS, Some(USDT_MINT)),
+        _ => {
+            return Err(ApiError::bad_request(format!("Unsupported token '{}' (use SOL, USDC or USDT)", token))
+                .code("unsupported_token"));
+        }
+    };
+
+    let authority = match load_local_keypair() {
+        Ok(k) => k,
+        Err(e) => {
+            return Err(ApiError::not_found(format!("The local wallet is the nonce authority for time-locked transfers: {}", e))
+                .code("no_local_wallet"));
+        }
+    };
+    let (from_pubkey, to_pubkey) = match (string_to_pub_key(&payload.from_address), string_to_pub_key(&payload.to_address)) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(_), _) => {
+            return Err(ApiError::bad_request("Invalid from_address").code("invalid_address"));
+        }
+        (_, Err(_)) => {
+            return Err(ApiError::bad_request("Invalid to_address").code("invalid_address"));
+        }
+    };
+    if payload.sign_with_local_wallet && from_pubkey != authority.pubkey() {
+        return Err(ApiError::bad_request("sign_with_local_wallet only works when the local wallet is the sender; sign externally and POST /scheduled-transfers/:id/signature")
+            .code("local_wallet_not_sender"));
+    }
+
+    let asset = mint.map(|m| amount::Asset::Mint(m.to_string())).unwrap_or(amount::Asset::Sol);
+    let (transfer_amount, rounding) = match parse_transfer_amount(asset, &payload.amount, &payload.amount_raw, decimals, &payload.rounding) {
+        Ok(parsed) => parsed,
+        Err(e) => return Err(amount_error(&payload.amount, &payload.amount_raw, e)),
+    };
+    let amount = transfer_amount.base_units();
+    let memo_privacy = resolve_memo_privacy(&payload.memo_privacy, state.memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo_privacy"))?;
+    let memo_text = build_memo(&token, &payload.from_address, &payload.to_address, &transfer_amount, &payload.yid, payload.notes.as_deref(), memo_privacy)
+        .map_err(|e| ApiError::bad_request(e).code("invalid_memo"))?;
+
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, false).await?;
+    let rpc = state.rpc.client(&payload.network, &rpc_url, CommitmentConfig::confirmed());
+
+    // SPL transfers create the recipient's account idempotently: it may not exist yet at unlock time
+    let mut compute_unit_limit: u32 = 100_000;
+    let mut transfer_instructions = Vec::new();
+    match mint {
+        None => transfer_instructions.push(transfer(&from_pubkey, &to_pubkey, amount)),
+        Some(mint) => {
+            let token_program = state.mints.token_program(&rpc_url, &payload.network, mint).await?;
+            let mint_pubkey = string_to_pub_key(mint).map_err(|_| ApiError::bad_request("Invalid mint address").code("invalid_mint"))?;
+            let source = token_account_address(&from_pubkey, &mint_pubkey, &token_program);
+            let destination = token_account_address(&to_pubkey, &mint_pubkey, &token_program);
+            transfer_instructions.push(utils::instruction_from_spl(
+                &spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                    &utils::to_spl_pubkey(&from_pubkey),
+                    &utils::to_spl_pubkey(&to_pubkey),
+                    &utils::to_spl_pubkey(&mint_pubkey),
+                    &utils::to_spl_pubkey(&token_program),
+                ),
+            ));
+            transfer_instructions.push(token_transfer_checked(&token_program, &source, &mint_pubkey, &destination, &from_pubkey, amount, decimals));
+            compute_unit_limit += CREATE_ATA_COMPUTE_UNITS;
+        }
+    }
+    let compute_unit_price = compute_unit_price(&state, payload.fee_amount.as_deref(), 0);
+
+    let summary = builds::BuildSummary {
+        token: token.clone(),
+        from: payload.from_address.clone(),
+        to: payload.to_address.clone(),
+        amount,
+        compute_unit_limit,
+        compute_unit_price,
+        rent_lamports: 0,
+        destination_accounts: vec![payload.to_address.clone()],
+    };
+    let mut warnings = build_warnings(&state, &payload.from_address, &rounding, &serde_json::Value::Null);
+    warnings.extend_flags(state.anomaly.check_transfer(&summary, decimals, false));
+    if payload.treat_warnings_as_errors && !warnings.is_empty() {
+        return Err(warnings_error(&warnings));
+    }
+
+    // Everything else is validated; only now spend rent on the nonce account
+    let (nonce_account, nonce, nonce_rent) = timelock::create_managed_nonce(&rpc, &authority).await
+        .map_err(|e| ApiError::upstream(e).code("nonce_account_failed"))?;
+
+    // AdvanceNonceAccount must be the first instruction of a durable-nonce transaction
+    let mut instructions = vec![
+        advance_nonce_account(&nonce_account, &authority.pubkey()),
+        ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit),
+        ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price),
+    ];
+    instructions.extend(transfer_instructions);
+    instructions.push(utils::instruction_from_spl(&spl_memo::build_memo(memo_text.as_bytes(), &[])));
+    let message = Message::new_with_blockhash(&instructions, Some(&from_pubkey), &nonce);
+    let mut transaction = Transaction::new_unsigned(message);
+    // The nonce authority signs now; when it is also the sender this completes the transaction
+    transaction.partial_sign(&[&authority], nonce);
+    let fully_signed = transaction.is_signed();
+
+    let serialized_tx = match bincode::serialize(&transaction) {
+        Ok(bytes) => general_purpose::STANDARD.encode(bytes),
+        Err(_) => {
+            let _ = timelock::close_nonce(&rpc, &authority, &nonce_account).await;
+            return Err(ApiError::internal("Failed to serialize transaction").code("serialization_failed"));
+        }
+    };
+
+    let session_id = match charge_session(&state, &headers, "build-timelocked-transfer", &token, amount) {
+        Ok(id) => id,
+        Err(e) => {
+            // Nothing will use the nonce account; return its rent
+            let _ = timelock::close_nonce(&rpc, &authority, &nonce_account).await;
+            return Err(session_error(e));
+        }
+    };
+
+    let intent = timelock::TimelockedTransfer {
+        id: uuid::Uuid::new_v4().simple().to_string(),
+        network: payload.network.clone(),
+        token: token.clone(),
+        from: payload.from_address.clone(),
+        to: payload.to_address.clone(),
+        amount: utils::base_units_to_ui(amount, decimals),
+        amount_base_units: amount.to_string(),
+        unlock_at: payload.unlock_at,
+        nonce_account: nonce_account.to_string(),
+        nonce_authority: authority.pubkey().to_string(),
+        nonce: nonce.to_string(),
+        nonce_rent_lamports: nonce_rent,
+        transaction: serialized_tx,
+        signature: if fully_signed { transaction.signatures.first().map(|s| s.to_string()) } else { None },
+        status: if fully_signed {
+            timelock::IntentStatus::AwaitingUnlock
+        } else {
+            timelock::IntentStatus::AwaitingSignature
+        },
+        error: None,
+        send_attempts: 0,
+        created_at: now,
+        submitted_at: None,
+        confirmed_at: None,
+        cancelled_at: None,
+        nonce_closed: false,
+    };
+    state.timelocks.insert(intent.clone());
+
+    let mut data = scheduled_transfer_json(&intent);
+    data["missing_signers"] = json!(if fully_signed { vec![] } else { vec![payload.from_address.clone()] });
+    data["rounding"] = rounding;
+    data["memo"] = json!(memo_text);
+    data["memo_privacy"] = json!(memo_privacy.as_str());
+    data["yid"] = json!(payload.yid);
+    data["session_id"] = json!(session_id);
+    data["warnings"] = json!(warnings);
+    Ok(Json(json!({
+        "success": true,
+        "data": data
+    })))
+}
+
+/// An intent as returned by the scheduled-transfer endpoints, with its unlock time spelled out.
+fn scheduled_transfer_json(intent: &timelock::TimelockedTransfer) -> serde_json::Value {
+    let mut data = json!(intent);
+    data["unlock_at_iso"] = json!(chrono::DateTime::from_timestamp(intent.unlock_at, 0).map(|t| t.to_rfc3339()));
+    data["nonce_rent_sol"] = json!(utils::base_units_to_ui(intent.nonce_rent_lamports, 9));
+    data
+}
+
+fn scheduled_transfer_not_found() -> ApiError {
+    ApiError::not_found("Scheduled transfer not found").code("scheduled_transfer_not_found")
+}
+
+/// Refusal for a transaction built on a time-locked transfer's nonce, sent before its unlock time.
+fn timelock_refusal(state: &AppState, account_keys: &[String]) -> Option<ApiError> {
+    let intent = state.timelocks.locked_by(account_keys, chrono::Utc::now().timestamp())?;
+    Some(
+        ApiError::conflict(format!(
+            "Transaction belongs to time-locked transfer {}, which unlocks at {}; fuego submits it then",
+            intent.id,
+            chrono::DateTime::from_timestamp(intent.unlock_at, 0).map(|t| t.to_rfc3339()).unwrap_or_default()
+        ))
+        .code("transfer_timelocked")
+        .with("scheduled_transfer_id", &intent.id)
+        .with("unlock_at", intent.unlock_at),
+    )
+}
+
+async fn list_scheduled_transfers(State(state): State<AppState>, Query(query): Query<ScheduledTransfersQuery>) -> ApiResult {
+    let status = query.status.as_deref().map(timelock::IntentStatus::parse).transpose()
+        .map_err(|e| ApiError::bad_request(e).code("invalid_status"))?;
+    let transfers: Vec<serde_json::Value> = state.timelocks.list(status).iter().map(scheduled_transfer_json).collect();
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "transfers": transfers,
+            "count": transfers.len()
+        }
+    })))
+}
+
+async fn get_scheduled_transfer(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult {
+    match state.timelocks.get(&id) {
+        Some(intent) => Ok(Json(json!({
+            "success": true,
+            "data": scheduled_transfer_json(&intent)
+        }))),
+        None => Err(scheduled_transfer_not_found()),
+    }
+}
+
+/// Attach the sender's signature to an intent built for an external signer.
+async fn sign_scheduled_transfer(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    StrictJson(payload): StrictJson<ScheduledTransferSignatureRequest>,
+) -> ApiResult {
+    let intent = match state.timelocks.get(&id) {
+        Some(intent) => intent,
+        None => return Err(scheduled_transfer_not_found()),
+    };
+    if intent.status != timelock::IntentStatus::AwaitingSignature {
+        return Err(ApiError::conflict("This transfer is not waiting for a signature")
+            .code("not_awaiting_signature")
+            .with("status", intent.status));
+    }
+    let stored: Option<ClientTransaction> = general_purpose::STANDARD
+        .decode(&intent.transaction)
+        .ok()
+        .and_then(|bytes| bincode::deserialize(&bytes).ok());
+    let stored = match stored {
+        Some(tx) => tx,
+        None => {
+            return Err(ApiError::internal("Stored transaction is unreadable").code("internal_error"));
+        }
+    };
+
+    let transaction = match (&payload.transaction, payload.sign_with_local_wallet) {
+        (Some(encoded), _) => {
+            let signed: Option<ClientTransaction> = general_purpose::STANDARD
+                .decode(encoded)
+                .ok()
+                .and_then(|bytes| bincode::deserialize(&bytes).ok());
+            match signed {
+                // Only signatures may change; the message is what the nonce authority signed
+                Some(tx) if tx.message_data() == stored.message_data() => tx,
+                Some(_) => {
+                    return Err(ApiError::bad_request("Transaction differs from the one built for this transfer; only add the sender's signature")
+                        .code("transaction_mismatch"));
+                }
+                None => {
+                    return Err(ApiError::bad_request("Failed to decode transaction").code("invalid_transaction"));
+                }
+            }
+        }
+        (None, true) => {
+            if let Some(refusal) = wallet_mismatch_refusal(payload.force) {
+                return Err(refusal);
+            }
+            let mut tx = stored;
+            let (missing, _) = legacy_signature_problems(&tx);
+            let signers = required_signers(&tx.message.account_keys, tx.message.header.num_required_signatures);
+            match local_signature(&signers, &missing, &tx.message_data()) {
+                Ok((index, signature)) if index < tx.signatures.len() => tx.signatures[index] = signature.into(),
+                Ok(_) => {}
+                Err(e) => {
+                    return Err(ApiError::bad_request(e)
+                        .code("missing_signatures")
+                        .with("missing_signers", &missing));
+                }
+            }
+            tx
+        }
+        (None, false) => {
+            return Err(ApiError::bad_request("Pass the signed transaction, or sign_with_local_wallet: true")
+                .code("invalid_request"));
+        }
+    };
+
+    let (missing, invalid) = legacy_signature_problems(&transaction);
+    if !missing.is_empty() || !invalid.is_empty() {
+        return Err(signature_error(&missing, &invalid));
+    }
+    let encoded = match bincode::serialize(&transaction) {
+        Ok(bytes) => general_purpose::STANDARD.encode(bytes),
+        Err(_) => {
+            return Err(ApiError::internal("Failed to serialize transaction").code("serialization_failed"));
+        }
+    };
+    let signature = transaction.signatures.first().map(|s| s.to_string());
+    let updated = state.timelocks.update(&id, |t| {
+        // A cancel may have raced this request
+        if t.status == timelock::IntentStatus::AwaitingSignature {
+            t.transaction = encoded;
+            t.signature = signature;
+            t.status = timelock::IntentStatus::AwaitingUnlock;
+        }
+    });
+    match updated {
+        Some(intent) if intent.status == timelock::IntentStatus::AwaitingUnlock => Ok(Json(json!({
+            "success": true,
+            "data": scheduled_transfer_json(&intent)
+        }))),
+        Some(intent) => Err(ApiError::conflict("This transfer is not waiting for a signature")
+            .code("not_awaiting_signature")
+            .with("status", intent.status)),
+        None => Err(scheduled_transfer_not_found()),
+    }
+}
+
+/// Cancel an intent that hasn't been submitted by closing its nonce account: the transaction,
+/// signed or not, can then never land. The nonce account's rent returns to the local wallet.
+async fn cancel_scheduled_transfer(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult {
+    let intent = match state.timelocks.get(&id) {
+        Some(intent) => intent,
+        None => return Err(scheduled_transfer_not_found()),
+    };
+    let retrying = intent.status == timelock::IntentStatus::Cancelled && !intent.nonce_closed;
+    if !intent.status.is_locked() && !retrying {
+        return Err(ApiError::conflict("Only transfers that haven't been submitted can be cancelled")
+            .code("not_cancellable")
+            .with("status", intent.status));
+    }
+
+    // Flip the status first so the scheduler stops considering it
+    let now = chrono::Utc::now().timestamp();
+    state.timelocks.update(&id, |t| {
+        if t.status.is_locked() {
+            t.status = timelock::IntentStatus::Cancelled;
+            t.cancelled_at = Some(now);
+        }
+    });
+
+    let closed = async {
+        let authority = load_local_keypair()?;
+        let nonce_account = string_to_pub_key(&intent.nonce_account).map_err(|_| "Invalid nonce account".to_string())?;
+        let rpc = state.rpc.client(&intent.network, &state.rpc.primary(&intent.network), CommitmentConfig::confirmed());
+        timelock::close_nonce(&rpc, &authority, &nonce_account).await
+    }
+    .await;
+    match closed {
+        Ok(signature) => {
+            let updated = state.timelocks.update(&id, |t| t.nonce_closed = true);
+            match updated {
+                Some(intent) if intent.status == timelock::IntentStatus::Cancelled => {
+                    let mut data = scheduled_transfer_json(&intent);
+                    data["close_signature"] = json!(signature);
+                    Ok(Json(json!({
+                        "success": true,
+                        "data": data
+                    })))
+                }
+                // The scheduler submitted it first; closing the nonce may still have stopped it
+                Some(intent) => Err(ApiError::conflict("The transfer was submitted before it could be cancelled")
+                    .code("not_cancellable")
+                    .with("status", intent.status)),
+                None => Err(scheduled_transfer_not_found()),
+            }
+        }
+        Err(e) => Err(ApiError::upstream(format!("Cancelled, but the nonce account could not be closed: {}. Retry the cancel; until it is closed the transaction could still be submitted elsewhere.", e))
+            .code("nonce_close_failed")),
+    }
+}
+
+// x402 Purch endpoint: call Purch x402 URL with order payload; x402-rs handles 402 → pay → retry; return final response.
+async fn x402_purch(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    StrictJson(payload): StrictJson<X402PurchRequest>,
+) -> ApiResult<Response> {
+    use x402_chain_solana::v1_solana_exact::client::V1SolanaExactClient;
+    use x402_chain_solana::v2_solana_exact::client::V2SolanaExactClient;
+    use x402_reqwest::{ReqwestWithPayments, ReqwestWithPaymentsBuild, X402Client};
+
+    let mut network = payload.network.clone();
+    resolve_network(&state, &mut network)?;
+
+    // Watch-only wallets have no key here, so they can never be the x402 payer
+    if let Some(ref payer) = payload.payer_address {
+        if wallets::is_watch_only(payer) {
+            return Err(ApiError::forbidden(format!("{} is a watch-only wallet; fuego holds no key that can sign for it", payer))
+                .code("watch_only_wallet"));
+        }
+    }
+
+    if let Some(refusal) = wallet_mismatch_refusal(payload.force) {
+        return Err(refusal);
+    }
+
+    // The URL is caller-supplied: refuse internal or unapproved destinations before anything is signed
+    let destination = match state.egress.check(&payload.url, "x402").await {
+        Ok(d) => d,
+        Err(e) => {
+            if let egress::EgressError::Blocked(ref reason) = e {
+                state.alerts.emit(
+                    "egress_blocked",
+                    alerts::Severity::Medium,
+                    format!("x402 request to {} blocked by egress policy", rpc::redact_url(&payload.url)),
+                    json!({ "url": rpc::redact_url(&payload.url), "reason": reason }),
+                );
+            }
+            return Err(ApiError::from(e));
+        }
+    };
+
+    // Load keypair from ~/.fuego/wallet.json (required for signing x402 payment)
+    let home_dir = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("/"));
+    let wallet_path = home_dir.join(".fuego").join("wallet.json");
+    let wallet_content = match fs::read_to_string(&wallet_path) {
+        Ok(c) => c,
+        Err(_) => {
+            return Err(ApiError::not_found("No wallet found at ~/.fuego/wallet.json. Run 'fuego create' first.")
+                .code("wallet_not_found"));
+        }
+    };
+    let wallet: WalletStore = serde_json::from_str(&wallet_content)
+        .map_err(|e| ApiError::internal(format!("Invalid wallet.json: {}", e)).code("wallet_unreadable"))?;
+
+    if wallet.private_key.len() < 32 {
+        return Err(ApiError::internal("Wallet private key must be at least 32 bytes").code("wallet_unreadable"));
+    }
+    let mut secret_arr = [0u8; 32];
+    secret_arr.copy_from_slice(&wallet.private_key[..32]);
+
+    let keypair = solana_sdk::signer::keypair::Keypair::new_from_array(secret_arr);
+
+    let rpc_url = state.rpc.primary(&network);
+    let verify_rpc_url = rpc_url.clone();
+    // Through the pool, so the payment's RPC calls fail over and count against the breaker
+    let rpc = state.rpc.client(&network, &rpc_url, CommitmentConfig::default());
+    let rpc_arc = Arc::new(rpc);
+    let keypair_arc = Arc::new(keypair);
+
+    // Register both V1 and V2 Solana exact clients so we match whatever Purch.xyz returns (V1 or V2 402 format)
+    let x402_client = X402Client::new()
+        .register(V1SolanaExactClient::new(keypair_arc.clone(), rpc_arc.clone()))
+        .register(V2SolanaExactClient::new(keypair_arc, rpc_arc));
+
+    // Pinned to the addresses the egress check approved, so a DNS change can't redirect the payment.
+    // The request's RPC timeout bounds the paid call too.
+    let http_client = state.egress.client_builder(&destination).timeout(rpc::timeout()).with_payments(x402_client).build()
+        .map_err(|e| ApiError::internal(format!("Failed to build HTTP client: {}", e)).code("internal_error"))?;
+
+    let payer_address = payload.payer_address.as_deref().unwrap_or(wallet.address.as_str());
+
+    // The local wallet signs the payment, so it is the one that needs SOL for fees
+    if let Err(e) = state.fee_wallet.check_payer(&wallet.address) {
+        return Err(ApiError::unavailable(e).code("fee_wallet_depleted"));
+    }
+    let low_balance = state.fee_wallet.is_low(&wallet.address);
+    let mut physical_address = serde_json::Map::new();
+    physical_address.insert("name".to_string(), serde_json::Value::String(payload.name.clone()));
+    physical_address.insert("line1".to_string(), serde_json::Value::String(payload.address_line1.clone()));
+    physical_address.insert("city".to_string(), serde_json::Value::String(payload.city.clone()));
+    physical_address.insert("state".to_string(), serde_json::Value::String(payload.state.clone()));
+    physical_address.insert("postalCode".to_string(), serde_json::Value::String(payload.postal_code.clone()));
+    physical_address.insert("country".to_string(), serde_json::Value::String(if payload.country.is_empty() { "US".to_string() } else { payload.country.clone() }));
+    if let Some(ref line2) = payload.address_line2 {
+        if !line2.is_empty() {
+            physical_address.insert("line2".to_string(), serde_json::Value::String(line2.clone()));
+        }
+    }
+
+    // Build order body - Purch requires lineItems with maxPrice AND top-level productUrl
+    let order_body = json!({
+        "email": payload.email,
+        "payerAddress": payer_address,
+        "productUrl": payload.product_url,
+        "physicalAddress": physical_address,
+        "lineItems": [
+            {
+                "productUrl": payload.product_url,
+                "maxPrice": payload.max_price.unwrap_or(10000)
+            }
+        ]
+    });
+    
+    eprintln!("DEBUG: Order body being sent to Purch: {}", serde_json::to_string_pretty(&order_body).unwrap_or_default());
+
+    // Reserve the worst-case spend (maxPrice, USD cents -> USDC base units) against the session
+    let session_charge = payload.max_price.unwrap_or(10000).saturating_mul(10_000);
+    let session_id = match charge_session(&state, &headers, "x402-purch", "USDC", session_charge) {
+        Ok(id) => id,
+        Err(e) => return Err(session_error(e)),
+    };
+
+    // Dry-run: never hit the paid endpoint, so no payment is ever signed or settled
+    if state.mode.is_dry_run() {
+        return Ok(Json(json!({
+            "success": true,
+            "simulated": true,
+            "mode": state.mode.as_str(),
+            "data": {
+                "url": payload.url,
+                "order": order_body,
+                "network": network
+            },
+            "yid": payload.yid,
+            "x402_note": "Dry-run mode: order was not sent and no payment was signed."
+        })).into_response());
+    }
+
+    let body_bytes = serde_json::to_vec(&order_body)
+        .map_err(|e| ApiError::internal(format!("Failed to serialize order body: {}", e)).code("serialization_failed"))?;
+
+    // Revocation wins over a reservation made earlier in this request: nothing has been signed yet
+    if let Some(ref id) = session_id {
+        if !state.sessions.is_active(id) {
+            state.sessions.refund(id, "USDC", session_charge);
+            return Err(session_error(sessions::SessionError::Revoked));
+        }
+    }
+
+    // A retry of a request whose outcome is unknown could pay twice: its payment may still settle
+    let previous_attempt = receipts::find_unresolved_attempt(&payload.url, &body_bytes);
+    if let Some(ref previous) = previous_attempt {
+        if !payload.force_new_payment {
+            if let Some(ref id) = session_id {
+                state.sessions.refund(id, "USDC", session_charge);
+            }
+            let retry_after = receipts::PAYMENT_REUSE_WINDOW_SECS - (chrono::Utc::now().timestamp() - previous.created_at);
+            return Err(ApiError::conflict(format!(
+                "An identical x402 request ({}) is {} and its payment may still settle; not signing a second payment",
+                previous.id, previous.status
+            ))
+            .code("possible_duplicate_payment")
+            .with("payment_reuse", json!({
+                "decision": "blocked",
+                "previous_inflight_id": previous.id,
+                "previous_status": previous.status,
+                "retry_after_secs": retry_after.max(0)
+            }))
+            .with("hint", "Check GET /x402-inflight/<id> for the earlier outcome, wait retry_after_secs, or pass force_new_payment: true"));
+        }
+    }
+    let mut warnings = warnings::Warnings::default();
+    warnings.extend_flags(state.anomaly.check_x402(&payload.url));
+    if low_balance {
+        warnings.fee_wallet_low(&wallet.address);
+    }
+    if payload.treat_warnings_as_errors && !warnings.is_empty() {
+        if let Some(ref id) = session_id {
+            state.sessions.refund(id, "USDC", session_charge);
+        }
+        return Err(warnings_error(&warnings));
+    }
+    let payment_reuse = json!({
+        "decision": if previous_attempt.is_some() { "forced_new_payment" } else { "new_payment" },
+        "previous_inflight_id": previous_attempt.as_ref().map(|r| r.id.clone())
+    });
+
+    // Persist the purchase before anything is paid, so a dropped connection never loses track of it
+    let mut inflight = receipts::InflightRecord::new(
+        &payload.url,
+        &network,
+        payload.max_price.unwrap_or(10000),
+        &body_bytes,
+        payload.yid.clone(),
+    );
+    if let Err(e) = receipts::save_inflight(&inflight) {
+        if let Some(ref id) = session_id {
+            state.sessions.refund(id, "USDC", session_charge);
+        }
+        return Err(ApiError::internal(format!("Failed to record in-flight x402 request: {}", e)).code("internal_error"));
+    }
+
+    // The paid request runs detached: if the caller disconnects, the handler future is dropped but
+    // the purchase still completes and its outcome lands on the in-flight record.
+    let url = payload.url.clone();
+    let yid = payload.yid.clone();
+    let store_response = payload.store_response;
+    let inflight_id = inflight.id.clone();
+    let alerts = state.alerts.clone();
+    let payer = wallet.address.clone();
+    let task = tokio::spawn(async move {
+        let started_at = chrono::Utc::now().timestamp();
+        let mut outcome = async {
+            let response = match http_client
+                .post(&url)
+                .header("Content-Type", "application/json")
+                .body(body_bytes)
+                .send()
+                .await
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    if egress::is_violation(&e) {
+                        return json!({
+                            "success": false,
+                            "error": format!("Request blocked by egress policy: {}", e),
+                            "code": "egress_blocked"
+                        });
+                    }
+                    return json!({
+                        "success": false,
+                        "error": format!("Request failed: {}", e),
+                        "code": "upstream_request_failed"
+                    });
+                }
+            };
+
+            let status = response.status();
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string());
+            let claim = settlement::claim(response.headers());
+
+            // Hash the body chunk by chunk as it streams in, enforcing the size cap before buffering more
+            let mut response = response;
+            let mut hasher = Sha256::new();
+            let mut response_bytes: Vec<u8> = Vec::new();
+            loop {
+                match response.chunk().await {
+                    Ok(Some(chunk)) => {
+                        if response_bytes.len() + chunk.len() > receipts::MAX_X402_RESPONSE_BYTES {
+                            return json!({
+                                "success": false,
+                                "status": status.as_u16(),
+                                "error": format!("Response body exceeds {} bytes", receipts::MAX_X402_RESPONSE_BYTES),
+                                "code": "upstream_response_too_large"
+                            });
+                        }
+                        hasher.update(&chunk);
+                        response_bytes.extend_from_slice(&chunk);
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        return json!({
+                            "success": false,
+                            "error": format!("Failed to read response: {}", e),
+                            "code": "upstream_request_failed"
+                        });
+                    }
+                }
+            }
+            let sha256 = format!("{:x}", hasher.finalize());
+
+            // The status and settlement header are only the endpoint's word; ask the chain what happened
+            let receipt_id = receipts::new_receipt_id();
+            let verification = settlement::verify(&verify_rpc_url, &payer, claim, status.as_u16(), started_at).await;
+            if let Some(mismatch) = verification.mismatch {
+                alerts.emit(
+                    "x402_settlement_mismatch",
+                    if mismatch == settlement::Mismatch::PaidButDenied { alerts::Severity::High } else { alerts::Severity::Medium },
+                    format!(
+                        "x402 request to {} returned {} but its payment {}",
+                        rpc::redact_url(&url),
+                        status.as_u16(),
+                        if mismatch == settlement::Mismatch::PaidButDenied { "settled on-chain" } else { "never settled on-chain" }
+                    ),
+                    json!({
+                        "receipt_id": receipt_id,
+                        "url": rpc::redact_url(&url),
+                        "status": status.as_u16(),
+                        "mismatch": mismatch.as_str(),
+                        "signature": verification.signature,
+                        "network": network
+                    }),
+                );
+            }
+
+            // Receipt bookkeeping must never fail the purchase itself; problems are reported as warnings
+            let mut receipt_warnings: Vec<String> = Vec::new();
+            let body_stored = if store_response {
+                match receipts::store_body(&sha256, &response_bytes) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        receipt_warnings.push(format!("Failed to store response body: {}", e));
+                        false
+                    }
+                }
+            } else {
+                false
+            };
+            let receipt = receipts::X402Receipt {
+                id: receipt_id,
+                url: url.clone(),
+                network: network.clone(),
+                status: status.as_u16(),
+                sha256,
+                content_type,
+                byte_length: response_bytes.len(),
+                body_stored,
+                yid: yid.clone(),
+                created_at: chrono::Utc::now().timestamp(),
+                on_chain_verified: verification.on_chain_verified(),
+                settlement: Some(verification),
+            };
+            if let Err(e) = receipts::save_receipt(&receipt) {
+                receipt_warnings.push(format!("Failed to save receipt: {}", e));
+            }
+
+            let body = String::from_utf8_lossy(&response_bytes).into_owned();
+            let body_json: serde_json::Value = match serde_json::from_str(&body) {
+                Ok(j) => j,
+                Err(_) => serde_json::Value::String(body),
+            };
+
+            let success = status.is_success();
+            json!({
+                "success": success,
+                "status": status.as_u16(),
+                "data": body_json,
+                "yid": yid,
+                "session_id": session_id,
+                "on_chain_verified": receipt.on_chain_verified,
+                "settlement_mismatch": receipt.settlement.as_ref().and_then(|v| v.mismatch),
+                "receipt": receipt,
+                "receipt_warnings": receipt_warnings,
+                "x402_note": if success { "Payment accepted; order response above." } else { "Request completed; check status and data." }
+            })
+        }
+        .await;
+        if let Some(fields) = outcome.as_object_mut() {
+            fields.insert("inflight_id".to_string(), json!(inflight.id));
+            fields.insert("low_balance".to_string(), json!(low_balance));
+            fields.insert("payment_reuse".to_string(), payment_reuse);
+            fields.insert("warnings".to_string(), json!(warnings));
+        }
+
+        inflight.finish(outcome.clone());
+        if let Err(e) = receipts::save_inflight(&inflight) {
+            eprintln!("Failed to update in-flight x402 record {}: {}", inflight.id, e);
+        }
+        outcome
+    });
+
+    match task.await {
+        // The seller's own answer is relayed whatever its status; a request that never got one is a 502
+        Ok(outcome) if outcome["success"] == json!(false) && outcome.get("data").is_none() => {
+            let status = match outcome["code"].as_str() {
+                Some("egress_blocked") => axum::http::StatusCode::FORBIDDEN,
+                _ => axum::http::StatusCode::BAD_GATEWAY,
+            };
+            Ok((status, Json(outcome)).into_response())
+        }
+        Ok(outcome) => Ok(Json(outcome).into_response()),
+        Err(e) => Err(ApiError::internal(format!("x402 request task failed: {}", e))
+            .code("internal_error")
+            .with("inflight_id", &inflight_id)),
+    }
+}
+
+/// String form of the first `count` account keys: the signers a message requires.
+fn required_signers<K: ToString>(keys: &[K], count: u8) -> Vec<String> {
+    keys.iter().take(count as usize).map(|k| k.to_string()).collect()
+}
+
+/// Required signers without a signature, and those whose signature doesn't verify against the message.
+fn legacy_signature_problems(tx: &ClientTransaction) -> (Vec<String>, Vec<String>) {
+    let signers = required_signers(&tx.message.account_keys, tx.message.header.num_required_signatures);
+    let verified = tx.verify_with_results();
+    let present: Vec<bool> = tx.signatures.iter().map(|s| *s != Default::default()).collect();
+    split_signature_problems(&signers, &present, &verified)
+}
+
+fn versioned_signature_problems(tx: &ClientVersionedTransaction) -> (Vec<String>, Vec<String>) {
+    let signers = required_signers(tx.message.static_account_keys(), tx.message.header().num_required_signatures);
+    let verified = tx.verify_with_results();
+    let present: Vec<bool> = tx.signatures.iter().map(|s| *s != Default::default()).collect();
+    split_signature_problems(&signers, &present, &verified)
+}
+
+fn split_signature_problems(signers: &[String], present: &[bool], verified: &[bool]) -> (Vec<String>, Vec<String>) {
+    let mut missing = Vec::new();
+    let mut invalid = Vec::new();
+    for (i, signer) in signers.iter().enumerate() {
+        if !present.get(i).copied().unwrap_or(false) {
+            missing.push(signer.clone());
+        } else if !verified.get(i).copied().unwrap_or(false) {
+            invalid.push(signer.clone());
+        }
+    }
+    (missing, invalid)
+}
+
+/// Keypair held in ~/.fuego/wallet.json.
+fn load_local_keypair() -> Result<solana_sdk::signer::keypair::Keypair, String> {
+    let content = fs::read_to_string(utils::fuego_home().join("wallet.json"))
+        .map_err(|_| "No wallet found at ~/.fuego/wallet.json. Run 'fuego create' first.".to_string())?;
+    let wallet: WalletStore = serde_json::from_str(&content).map_err(|e| format!("Invalid wallet.json: {}", e))?;
+    if wallet.private_key.len() < 32 {
+        return Err("Wallet private key must be at least 32 bytes".to_string());
+    }
+    let mut secret = [0u8; 32];
+    secret.copy_from_slice(&wallet.private_key[..32]);
+    Ok(solana_sdk::signer::keypair::Keypair::new_from_array(secret))
+}
+
+/// The local wallet's signature over `message`, with its position among the signers. Only offered
+/// when the local wallet is the sole missing signer.
+fn local_signature(signers: &[String], missing: &[String], message: &[u8]) -> Result<(usize, [u8; 64]), String> {
+
+    if let Some(Ok(verification)) = verify_local_wallet() {
+        if !verification.ok {
+            return Err(format!("Local wallet files disagree with the key: {}", verification.explain()));
+        }
+    }
+    let keypair = load_local_keypair()?;
+    let address = keypair.pubkey().to_string();
+    if missing.len() != 1 || missing[0] != address {
+        return Err(format!(
+            "The local wallet ({}) can't complete this transaction; missing signatures from: {}",
+            address,
+            missing.join(", ")
+        ));
+    }
+    let index = signers.iter().position(|s| *s == address).unwrap_or(0);
+    let mut signature = [0u8; 64];
+    signature.copy_from_slice(keypair.sign_message(message).as_ref());
+    Ok((index, signature))
+}
+
+fn signature_error(missing: &[String], invalid: &[String]) -> ApiError {
+
+    let local_wallet = load_local_keypair().ok().map(|k| k.pubkey().to_string());
+    let local_can_sign = local_wallet.as_ref().map(|a| missing.contains(a)).unwrap_or(false);
+    let (code, error) = if !missing.is_empty() {
+        ("missing_signatures", format!("Transaction is missing signatures from: {}", missing.join(", ")))
+    } else {
+        (
+            "invalid_signatures",
+            format!(
+                "Signatures from {} do not verify; the transaction was modified after it was signed",
+                invalid.join(", ")
+            ),
+        )
+    };
+    ApiError::bad_request(error)
+        .code(code)
+        .with("missing_signers", missing)
+        .with("invalid_signers", invalid)
+        .with("local_wallet_can_sign", local_can_sign)
+        .with(
+            "hint",
+            if local_can_sign && missing.len() == 1 && invalid.is_empty() {
+                Some("Resubmit with \"sign_and_submit\": true to add the local wallet's signature")
+            } else {
+                None
+            },
+        )
+}
+
+/// A send or simulate call that failed. When the RPC says why the transaction itself was rejected
+/// (an expired blockhash, too little SOL, a program error) its code is used, with HTTP 422 (409 for
+/// already_processed); anything else is rpc_error. The RPC's own message is kept in `details`.
+fn rpc_failure(context: &str, e: &solana_client::client_error::ClientError) -> ApiError {
+    let details = e.to_string();
+    let Some(failure) = rpc_errors::classify(e) else {
+        return ApiError::upstream(format!("{}: {}", context, e)).code("rpc_error").with("details", details);
+    };
+    let status = match failure.code {
+        "already_processed" => axum::http::StatusCode::CONFLICT,
+        _ => axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+    };
+    let mut error = ApiError::new(status, format!("{}: {}", context, failure.message)).code(failure.code);
+    if let Some(index) = failure.instruction_index {
+        error = error.with("instruction_index", index);
+    }
+    if let Some(custom_error) = failure.custom_error {
+        error = error.with("custom_error", custom_error);
+    }
+    error.with("logs", rpc_errors::preflight_logs(e)).with("details", details)
+}
+
+/// A submitted transaction that failed on chain or didn't reach min_confirmations in time. The
+/// signature is included either way: the transaction was sent and may still land.
+fn confirmation_failure(signature: &str, network: &str, outcome: pending::ConfirmationOutcome) -> ApiError {
+    let (error, code, progress, waited_secs) = match outcome {
+        pending::ConfirmationOutcome::Failed(progress) => ("Transaction landed but failed on chain".to_string(), "transaction_failed", progress, None),
+        pending::ConfirmationOutcome::TimedOut { progress, waited_secs } => (
+            format!(
+                "Timed out after {}s waiting for {} confirmations (last seen: {})",
+                waited_secs,
+                progress.min_confirmations,
+                progress
+                    .confirmations
+                    .map(|c| format!("{} confirmations", c))
+                    .or_else(|| progress.confirmation_status.clone())
+                    .unwrap_or_else(|| "not yet seen by the RPC".to_string())
+            ),
+            "confirmation_timeout",
+            progress,
+            Some(waited_secs),
+        ),
+        pending::ConfirmationOutcome::Reached(progress) => ("Confirmation threshold reached".to_string(), "confirmed", progress, None),
+    };
+    let error = match code {
+        "confirmation_timeout" => ApiError::timeout(error),
+        // Executed and rejected by the chain: the request was understood, the transaction was not valid
+        _ => ApiError::new(axum::http::StatusCode::UNPROCESSABLE_ENTITY, error),
+    };
+    error
+        .code(code)
+        .with("signature", signature)
+        .with("network", network)
+        .with("waited_secs", waited_secs)
+        .with("confirmation", progress)
+}
+
+async fn submit_transaction(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<SubmitTransactionRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, false).await?;
+    let commitment = state.commitment.confirmation(&payload.network, &payload.commitment);
+    let rpc = state.rpc.client(&payload.network, &rpc_url, get_commitment_config(commitment));
+
+    // Decode base64 transaction
+    let tx_bytes = general_purpose::STANDARD.decode(&payload.transaction)
+        .map_err(|_| ApiError::bad_request("Failed to decode transaction - invalid base64").code("invalid_transaction"))?;
+
+    // Deserialize transaction (already signed by agent with correct blockhash)
+    let mut transaction: ClientTransaction = bincode::deserialize(&tx_bytes)
+        .map_err(|_| ApiError::bad_request("Failed to deserialize transaction").code("invalid_transaction"))?;
+
+    let account_keys: Vec<String> = transaction.message.account_keys.iter().map(|k| k.to_string()).collect();
+    if let Some(refusal) = timelock_refusal(&state, &account_keys) {
+        return Err(refusal);
+    }
+
+    // Every required signer must have signed, and every signature must verify against the message
+    let (mut missing, mut invalid) = legacy_signature_problems(&transaction);
+    if payload.sign_and_submit && !missing.is_empty() && invalid.is_empty() {
+        let signers = required_signers(&transaction.message.account_keys, transaction.message.header.num_required_signatures);
+        match local_signature(&signers, &missing, &transaction.message_data()) {
+            Ok((index, signature)) if index < transaction.signatures.len() => {
+                transaction.signatures[index] = signature.into();
+                (missing, invalid) = legacy_signature_problems(&transaction);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                return Err(ApiError::bad_request(e)
+                    .code("missing_signatures")
+                    .with("missing_signers", &missing));
+            }
+        }
+    }
+    if !missing.is_empty() || !invalid.is_empty() {
+        return Err(signature_error(&missing, &invalid));
+    }
+
+    // The fee payer is always the first account key
+    let mut warnings = warnings::Warnings::default();
+    let fee_payer = transaction.message.account_keys.first().map(|k| k.to_string()).unwrap_or_default();
+    if state.fee_wallet.is_low(&fee_payer) {
+        warnings.fee_wallet_low(&fee_payer);
+    }
+    if payload.treat_warnings_as_errors && !warnings.is_empty() {
+        return Err(warnings_error(&warnings));
+    }
+
+    // Dry-run: simulate only and report the signature the transaction would have landed with
+    if state.mode.is_dry_run() {
+        return match rpc.simulate_transaction(&transaction).await {
+            Ok(result) => Ok(Json(json!({
+                "success": true,
+                "data": {
+                    "signature": transaction.signatures.first().map(|s| s.to_string()),
+                    "network": payload.network,
+                    "status": "simulated",
+                    "simulated": true,
+                    "mode": state.mode.as_str(),
+                    "simulation": {
+                        "err": result.value.err.map(|e| e.to_string()),
+                        "logs": result.value.logs,
+                        "units_consumed": result.value.units_consumed
+                    }
+                }
+            }))),
+            Err(e) => Err(rpc_failure("Failed to simulate transaction", &e)
+                .with("simulated", true)
+                .with("mode", state.mode.as_str())),
+        };
+    }
+
+    // Submit to RPC (transaction is already signed with correct blockhash by agent)
+    let result = rpc.send_transaction(&transaction).await;
+    let rpc_endpoint = rpc::redact_url(&rpc.url());
+    match result {
+        Ok(signature) => {
+            let sig_string = signature.to_string();
+            memo_index::record(
+                &sig_string,
+                &payload.network,
+                memo_index::memos_from_legacy(&transaction),
+                "submit-transaction",
+            );
+            // Held so the tracker can resend it if a fork drops it after confirmation
+            let signed = bincode::serialize(&transaction).ok().map(|bytes| pending::SignedTransaction {
+                transaction: general_purpose::STANDARD.encode(bytes),
+                versioned: false,
+            });
+            state.pending.track(&sig_string, &payload.network, signed);
+            let confirmation = match payload.min_confirmations {
+                Some(min) => {
+                    let timeout = std::time::Duration::from_secs(payload.confirm_timeout_secs.unwrap_or(60).min(pending::MAX_CONFIRM_TIMEOUT_SECS));
+                    match pending::wait_for_confirmations(&rpc_url, &sig_string, min, timeout).await {
+                        pending::ConfirmationOutcome::Reached(progress) => Some(progress),
+                        outcome => return Err(confirmation_failure(&sig_string, &payload.network, outcome)),
+                    }
+                }
+                None => None,
+            };
+            let explorer_link = explorer_link(&state, &payload.network, &sig_string);
+            Ok(Json(json!({
+                "success": true,
+                "data": {
+                    "signature": sig_string,
+                    "explorer_link": explorer_link,
+                    "network": payload.network,
+                    "status": confirmation.as_ref().and_then(|c| c.confirmation_status.clone()).unwrap_or_else(|| "submitted".to_string()),
+                    "commitment": commitment,
+                    "confirmation": confirmation,
+                    "warnings": warnings,
+                    "rpc_endpoint": rpc_endpoint
+                }
+            })))
+        },
+        Err(e) => Err(rpc_failure("Failed to submit transaction", &e)
+            .with("rpc_endpoint", &rpc_endpoint)),
+    }
+}
+
+// VersionedTransaction endpoint specifically for Jupiter swaps and other v0 transactions
+async fn submit_versioned_transaction(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<SubmitTransactionRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, false).await?;
+    let commitment = state.commitment.confirmation(&payload.network, &payload.commitment);
+    let rpc = state.rpc.client(&payload.network, &rpc_url, get_commitment_config(commitment));
+
+    // Decode base64 transaction
+    let tx_bytes = general_purpose::STANDARD.decode(&payload.transaction)
+        .map_err(|_| ApiError::bad_request("Failed to decode transaction - invalid base64").code("invalid_transaction"))?;
+
+    // Deserialize as VersionedTransaction (Jupiter format)
+    let mut versioned_transaction: ClientVersionedTransaction = match bincode::deserialize(&tx_bytes) {
+        Ok(tx) => tx,
+        Err(_) => {
+            return Err(ApiError::bad_request("Failed to deserialize VersionedTransaction - ensure this is a v0 transaction format")
+                .code("invalid_transaction"));
+        }
+    };
+
+    let account_keys: Vec<String> = versioned_transaction.message.static_account_keys().iter().map(|k| k.to_string()).collect();
+    if let Some(refusal) = timelock_refusal(&state, &account_keys) {
+        return Err(refusal);
+    }
+
+    // Every required signer must have signed, and every signature must verify against the message
+    let (mut missing, mut invalid) = versioned_signature_problems(&versioned_transaction);
+    if payload.sign_and_submit && !missing.is_empty() && invalid.is_empty() {
+        let signers = required_signers(
+            versioned_transaction.message.static_account_keys(),
+            versioned_transaction.message.header().num_required_signatures,
+        );
+        match local_signature(&signers, &missing, &versioned_transaction.message.serialize()) {
+            Ok((index, signature)) if index < versioned_transaction.signatures.len() => {
+                versioned_transaction.signatures[index] = signature.into();
+                (missing, invalid) = versioned_signature_problems(&versioned_transaction);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                return Err(ApiError::bad_request(e)
+                    .code("missing_signatures")
+                    .with("missing_signers", &missing));
+            }
+        }
+    }
+    if !missing.is_empty() || !invalid.is_empty() {
+        return Err(signature_error(&missing, &invalid));
+    }
+
+    // The fee payer is always the first account key
+    let mut warnings = warnings::Warnings::default();
+    let fee_payer = versioned_transaction.message.static_account_keys().first().map(|k| k.to_string()).unwrap_or_default();
+    if state.fee_wallet.is_low(&fee_payer) {
+        warnings.fee_wallet_low(&fee_payer);
+    }
+    if payload.treat_warnings_as_errors && !warnings.is_empty() {
+        return Err(warnings_error(&warnings));
+    }
+
+    // Dry-run: simulate only and report the signature the transaction would have landed with
+    if state.mode.is_dry_run() {
+        return match rpc.simulate_transaction(&versioned_transaction).await {
+            Ok(result) => Ok(Json(json!({
+                "success": true,
+                "data": {
+                    "signature": versioned_transaction.signatures.first().map(|s| s.to_string()),
+                    "network": payload.network,
+                    "status": "simulated",
+                    "simulated": true,
+                    "mode": state.mode.as_str(),
+                    "transaction_type": "VersionedTransaction",
+                    "simulation": {
+                        "err": result.value.err.map(|e| e.to_string()),
+                        "logs": result.value.logs,
+                        "units_consumed": result.value.units_consumed
+                    }
+                }
+            }))),
+            Err(e) => Err(rpc_failure("Failed to simulate VersionedTransaction", &e)
+                .with("simulated", true)
+                .with("mode", state.mode.as_str())),
+        };
+    }
+
+    // Submit VersionedTransaction to RPC (already signed by agent)
+    let result = rpc.send_transaction(&versioned_transaction).await;
+    let rpc_endpoint = rpc::redact_url(&rpc.url());
+    match result {
+        Ok(signature) => {
+            let sig_string = signature.to_string();
+            memo_index::record(
+                &sig_string,
+                &payload.network,
+                memo_index::memos_from_versioned(&versioned_transaction),
+                "submit-versioned-transaction",
+            );
+            let signed = bincode::serialize(&versioned_transaction).ok().map(|bytes| pending::SignedTransaction {
+                transaction: general_purpose::STANDARD.encode(bytes),
+                versioned: true,
+            });
+            state.pending.track(&sig_string, &payload.network, signed);
+            let confirmation = match payload.min_confirmations {
+                Some(min) => {
+                    let timeout = std::time::Duration::from_secs(payload.confirm_timeout_secs.unwrap_or(60).min(pending::MAX_CONFIRM_TIMEOUT_SECS));
+                    match pending::wait_for_confirmations(&rpc_url, &sig_string, min, timeout).await {
+                        pending::ConfirmationOutcome::Reached(progress) => Some(progress),
+                        outcome => return Err(confirmation_failure(&sig_string, &payload.network, outcome)),
+                    }
+                }
+                None => None,
+            };
+            let explorer_link = explorer_link(&state, &payload.network, &sig_string);
+            Ok(Json(json!({
+                "success": true,
+                "data": {
+                    "signature": sig_string,
+                    "explorer_link": explorer_link,
+                    "network": payload.network,
+                    "status": confirmation.as_ref().and_then(|c| c.confirmation_status.clone()).unwrap_or_else(|| "submitted".to_string()),
+                    "commitment": commitment,
+                    "confirmation": confirmation,
+                    "warnings": warnings,
+                    "transaction_type": "VersionedTransaction",
+                    "rpc_endpoint": rpc_endpoint
+                }
+            })))
+        },
+        Err(e) => Err(rpc_failure("Failed to submit VersionedTransaction", &e)
+            .with("rpc_endpoint", &rpc_endpoint)),
+    }
+}
+
+/// Envelope for raw history responses; `data` is written out byte-for-byte as the RPC returned it.
+#[derive(Serialize)]
+struct RawHistoryResponse<'a> {
+    success: bool,
+    data: &'a RawValue,
+    network: &'a str,
+    raw: bool,
+    status: &'a str,
+    commitment: &'a str,
+}
+
+#[derive(Deserialize)]
+struct RawRpcResponse {
+    #[serde(default)]
+    result: Option<Box<RawValue>>,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
+/// Call getSignaturesForAddress via raw RPC and keep `result` as unparsed JSON.
+async fn fetch_signatures_raw(rpc_url: &str, address: &str, limit: Option<usize>, commitment: &str) -> Result<Box<RawValue>, String> {
+    let mut config = serde_json::Map::new();
+    config.insert("commitment".to_string(), json!(commitment));
+    if let Some(limit) = limit {
+        config.insert("limit".to_string(), json!(limit));
+    }
+    let body = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "getSignaturesForAddress",
+        "params": [address, config]
+    });
+    let client = rpc::http_client(rpc_url);
+    let started = Instant::now();
+    let http_error = |e| rpc::http_error(rpc_url, started, e);
+    let res = client
+        .post(rpc_url)
+        .headers(trace::outbound_headers())
+        .timeout(rpc::timeout())
+        .json(&body)
+        .send()
+        .await
+        .map_err(http_error)?;
+    let bytes = res.bytes().await.map_err(http_error)?;
+    let parsed: RawRpcResponse = serde_json::from_slice(&bytes).map_err(|e| e.to_string())?;
+    if let Some(err) = parsed.error {
+        return Err(format!("RPC error: {}", err));
+    }
+    parsed.result.ok_or_else(|| "Missing result in RPC response".to_string())
+}
+
+// Bulk submission: validate every transaction up front, then hand them to the queue workers
+async fn submit_transactions(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<SubmitTransactionsRequest>,
+) -> ApiResult {
+    resolve_network(&state, &mut payload.network)?;
+    if payload.transactions.is_empty() {
+        return Err(ApiError::bad_request("transactions must not be empty").code("empty_batch"));
+    }
+
+    // Reject the whole batch if any entry is malformed, so nothing is half-enqueued
+    let mut signatures: Vec<Option<String>> = Vec::with_capacity(payload.transactions.len());
+    for (i, encoded) in payload.transactions.iter().enumerate() {
+        let bytes = general_purpose::STANDARD.decode(encoded)
+            .map_err(|_| ApiError::bad_request(format!("transactions[{}]: invalid base64", i)).code("invalid_transaction"))?;
+        let parsed = if payload.versioned {
+            bincode::deserialize::<ClientVersionedTransaction>(&bytes).map(|tx| {
+                let keys: Vec<String> = tx.message.static_account_keys().iter().map(|k| k.to_string()).collect();
+                (tx.signatures.first().map(|s| s.to_string()), keys)
+            })
+        } else {
+            bincode::deserialize::<ClientTransaction>(&bytes).map(|tx| {
+                let keys: Vec<String> = tx.message.account_keys.iter().map(|k| k.to_string()).collect();
+                (tx.signatures.first().map(|s| s.to_string()), keys)
+            })
+        };
+        match parsed {
+            Ok((sig, keys)) => {
+                if let Some(refusal) = timelock_refusal(&state, &keys) {
+                    return Err(refusal);
+                }
+                signatures.push(sig);
+            }
+            Err(_) => {
+                return Err(ApiError::bad_request(format!("transactions[{}]: failed to deserialize transaction", i))
+                    .code("invalid_transaction"));
+            }
+        }
+    }
+
+    let items: Vec<serde_json::Value> = payload
+        .transactions
+        .into_iter()
+        .zip(signatures)
+        .map(|(tx, signature)| {
+            let item = state.submit_queue.enqueue(&payload.network, tx, payload.versioned, signature);
+            json!({
+                "id": item.id,
+                "signature": item.signature,
+                "status": item.status
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "items": items,
+            "queue": state.submit_queue.stats(),
+            "network": payload.network
+        }
+    })))
+}
+
+#[derive(Serialize, Deserialize)]
+struct PendingTransactionsQuery {
+    /// Most statuses to return (default 50, max 1000)
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+// Signatures the tracker remembers, newest first, with how each one is doing
+async fn get_pending_transactions(State(state): State<AppState>, Query(query): Query<PendingTransactionsQuery>) -> ApiResult {
+    let transactions = state.pending.recent(query.limit.unwrap_or(50).min(1000));
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "transactions": transactions,
+            "gauges": state.pending.gauges()
+        }
+    })))
+}
+
+// What the embedded dashboard needs before calling anything else; public, so it can ask for a key
+async fn get_ui_config(State(state): State<AppState>) -> ApiResult {
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "auth_required": state.auth.enabled(),
+            "require_sessions": state.require_sessions,
+            "default_network": state.default_network,
+            "dry_run": state.mode.is_dry_run(),
+            "version": env!("CARGO_PKG_VERSION")
+        }
+    })))
+}
+
+async fn get_submit_queue(State(state): State<AppState>) -> ApiResult {
+    Ok(Json(json!({
+        "success": true,
+        "data": state.submit_queue.stats()
+    })))
+}
+
+async fn get_submit_queue_item(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult {
+    match state.submit_queue.get(&id) {
+        Some(item) => {
+            let tracking = item.signature.as_deref().and_then(|sig| state.pending.status(sig));
+            let mut data = json!(item);
+            data["tracking"] = json!(tracking);
+            Ok(Json(json!({
+                "success": true,
+                "data": data
+            })))
+        }
+        None => Err(ApiError::not_found("Queue item not found").code("queue_item_not_found")),
+    }
+}
+
+async fn get_all_transactions(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<GetAccountSignatures>,
+) -> ApiResult<Response> {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let selection = fields::parse(payload.fields.as_deref()).map_err(|e| fields_error(e, Vec::new()))?;
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    // getSignaturesForAddress doesn't serve "processed"; confirmed is the closest it offers
+    let commitment = match state.commitment.read(&payload.network, &payload.commitment) {
+        "processed" => "confirmed",
+        level => level,
+    };
+
+    let user_pubkey = string_to_pub_key(&payload.address).map_err(|_| ApiError::bad_request("Invalid wallet address").code("invalid_address"))?;
+
+    if payload.raw {
+        let started = Instant::now();
+        let result = fetch_signatures_raw(&rpc_url, &payload.address, payload.limit, commitment).await;
+        state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+
+        return match result {
+            // Pruning needs a parsed tree, so a selection gives up the byte-for-byte pass-through
+            Ok(result) if selection.is_some() => match serde_json::from_str::<serde_json::Value>(result.get()) {
+                Ok(data) => select_fields(
+                    selection.as_ref(),
+                    json!({
+                        "success": true,
+                        "data": data,
+                        "network": payload.network,
+                        "raw": true,
+                        "status": "Successful all transactions request",
+                        "commitment": commitment
+                    }),
+                ).map(IntoResponse::into_response),
+                Err(_) => Err(ApiError::upstream("Could not retrieve signatures for account").code("rpc_error")),
+            },
+            Ok(result) => Ok(Json(RawHistoryResponse {
+                success: true,
+                data: &result,
+                network: &payload.network,
+                raw: true,
+                status: "Successful all transactions request",
+                commitment,
+            }).into_response()),
+            Err(_) => Err(ApiError::upstream("Could not retrieve signatures for account").code("rpc_error")),
+        };
+    }
+
+    let rpc = state.rpc.client(&payload.network, &rpc_url, CommitmentConfig::default());
+
+    let config = solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config {
+        before: None,
+        until: None,
+        limit: payload.limit,
+        commitment: Some(get_commitment_config(commitment)),
+    };
+
+    let started = Instant::now();
+    let result = rpc.get_signatures_for_address_with_config(&user_pubkey, config).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+
+    let signatures = result.map_err(|_| ApiError::upstream("Could not retrieve signatures for account").code("rpc_error"))?;
+
+    select_fields(
+        selection.as_ref(),
+        json!({
+            "success": true,
+            "data": signatures,
+            "network": payload.network,
+            "status": "Successful all transactions request",
+            "commitment": commitment
+        }),
+    ).map(IntoResponse::into_response)
+}
+
+// getTokenAccountsByOwner is implemented via raw RPC (jsonParsed) in get_tokens and get_pyusd_balance — no account decoder.
+// Token metadata for known tokens (embedded registry)
+fn get_token_symbol(mint: &str) -> Option<&'static str> {
+    registry::token_symbol(mint)
+}
+
+const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+/// Call getTokenAccountsByOwner via raw RPC (jsonParsed) and parse response as JSON.
+/// `filter` is `{"programId": ...}` or `{"mint": ...}`.
+/// Avoids solana_account_decoder; uses only reqwest + serde_json.
+async fn fetch_token_accounts_json(
+    rpc_url: &str,
+    wallet_address: &str,
+    filter: serde_json::Value,
+    commitment: &str,
+) -> Result<Vec<serde_json::Value>, String> {
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "getTokenAccountsByOwner",
+        "params": [
+            wallet_address,
+            filter,
+            { "encoding": "jsonParsed", "commitment": commitment }
+        ]
+    });
+    let client = rpc::http_client(rpc_url);
+    let started = Instant::now();
+    let http_error = |e| rpc::http_error(rpc_url, started, e);
+    let res = client
+        .post(rpc_url)
+        .headers(trace::outbound_headers())
+        .timeout(rpc::timeout())
+        .json(&body)
+        .send()
+        .await
+        .map_err(http_error)?;
+    let json: serde_json::Value = res.json().await.map_err(http_error)?;
+    let value = json
+        .get("result")
+        .and_then(|r| r.get("value"))
+        .ok_or_else(|| "Missing result.value in RPC response".to_string())?;
+    let arr = value.as_array().ok_or_else(|| "result.value is not an array".to_string())?;
+    Ok(arr.clone())
+}
+
+#[derive(Deserialize)]
+struct DashboardQuery {
+    /// Defaults to the active wallet's network, then the server default
+    #[serde(default)]
+    network: Option<String>,
+    // RpcTarget's fields, spelled out: a flattened struct makes query strings fail to fill the
+    // numeric and boolean fields below
+    /// RPC endpoint for this request only (http or https); `network` still names the cluster
+    #[serde(default)]
+    rpc_url: Option<String>,
+    /// Named provider profile from ~/.fuego/server.json, in place of rpc_url (and of network, when the profile names one)
+    #[serde(default)]
+    provider: Option<String>,
+    /// Defaults to the active wallet
+    #[serde(default)]
+    address: Option<String>,
+    /// Recent transactions to return (default 10)
+    #[serde(default)]
+    limit: Option<usize>,
+    // Sections, all on by default; pass false to skip one
+    #[serde(default)]
+    balances: Option<bool>,
+    #[serde(default)]
+    transactions: Option<bool>,
+    #[serde(default)]
+    pending: Option<bool>,
+    #[serde(default)]
+    fees: Option<bool>,
+    /// Comma-separated dotted paths of `data` to keep, e.g. "sections.balances.data.sol_balance"
+    #[serde(default)]
+    fields: Option<String>,
+}
+
+/// Compute unit price suggestions (micro-lamports) from getRecentPrioritizationFees percentiles.
+async fn fetch_priority_fee_suggestions(rpc_url: &str) -> Result<serde_json::Value, String> {
+    let body = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "getRecentPrioritizationFees",
+        "params": []
+    });
+    let started = Instant::now();
+    let http_error = |e| rpc::http_error(rpc_url, started, e);
+    let res = rpc::http_client(rpc_url)
+        .post(rpc_url)
+        .headers(trace::outbound_headers())
+        .timeout(rpc::timeout())
+        .json(&body)
+        .send()
+        .await
+        .map_err(http_error)?;
+    let json: serde_json::Value = res.json().await.map_err(http_error)?;
+    if let Some(err) = json.get("error") {
+        return Err(format!("RPC error: {}", err));
+    }
+    let mut fees: Vec<u64> = json["result"]
+        .as_array()
+        .ok_or_else(|| "Missing result in RPC response".to_string())?
+        .iter()
+        .filter_map(|f| f["prioritizationFee"].as_u64())
+        .collect();
+    fees.sort_unstable();
+    let percentile = |p: usize| fees.get((fees.len().saturating_sub(1)) * p / 100).copied().unwrap_or(0);
+    Ok(json!({
+        "samples": fees.len(),
+        "low": percentile(25),
+        "medium": percentile(50),
+        "high": percentile(75),
+        "max": fees.last().copied().unwrap_or(0),
+        "unit": "micro_lamports_per_compute_unit"
+    }))
+}
+
+/// One dashboard section: its data, or the error that kept it from loading.
+fn dashboard_section(result: Result<serde_json::Value, String>) -> serde_json::Value {
+    match result {
+        Ok(data) => json!({ "ok": true, "data": data }),
+        Err(e) => json!({ "ok": false, "error": e }),
+    }
+}
+
+/// Everything the dashboard home screen shows, in one call. A failing section is reported in place
+/// instead of failing the whole response.
+async fn get_dashboard(State(state): State<AppState>, Query(query): Query<DashboardQuery>) -> ApiResult {
+    let selection = fields::parse(query.fields.as_deref()).map_err(|e| fields_error(e, Vec::new()))?;
+    let active = load_active_wallet();
+    let address = query.address.clone().or_else(|| active.as_ref().map(|(a, _, _)| a.clone()));
+    // A provider profile supplies the endpoint, and the network when the query doesn't name one
+    let mut network = query.network.clone().unwrap_or_default();
+    let mut target = RpcTarget { rpc_url: query.rpc_url.clone(), provider: query.provider.clone() };
+    if target.provider.is_some() {
+        resolve_rpc_target(&state, &mut network, &mut target)?;
+    }
+    let mut network = Some(network)
+        .filter(|n| !n.is_empty())
+        .or_else(|| active.as_ref().map(|(_, n, _)| n.clone()))
+        .filter(|n| !n.is_empty())
+        .unwrap_or_default();
+    resolve_network(&state, &mut network)?;
+    let rpc_url = request_rpc_url(&state, &network, &target, true).await?;
+    let commitment = state.commitment.read(&network, &None);
+    let limit = query.limit.unwrap_or(10).min(100);
+
+    let mut sections = serde_json::Map::new();
+    let mut failed: Vec<&str> = Vec::new();
+
+    let wallet = match (&address, &active) {
+        (Some(address), Some((active_address, _, source))) if active_address == address => {
+            Ok(json!({ "address": address, "source": source, "low_balance": state.fee_wallet.is_low(address) }))
+        }
+        (Some(address), _) => Ok(json!({ "address": address, "source": "query" })),
+        (None, _) => Err("No wallet found. Initialize with: fuego create".to_string()),
+    };
+
+    if query.balances.unwrap_or(true) {
+        let result = match &address {
+            None => Err("No wallet address".to_string()),
+            Some(address) => match string_to_pub_key(address) {
+                Err(_) => Err("Invalid wallet address".to_string()),
+                Ok(pubkey) => {
+                    let rpc = state.rpc.client(&network, &rpc_url, get_commitment_config(commitment));
+                    let started = Instant::now();
+                    let lamports = rpc.get_balance(&pubkey).await;
+                    state.rpc.record(&rpc_url, started.elapsed(), lamports.is_ok());
+                    match lamports {
+                        Err(e) => Err(format!("Failed to get SOL balance: {}", e)),
+                        Ok(lamports) => {
+                            let mut tokens = Vec::new();
+                            let mut token_errors = Vec::new();
+                            for program in [TOKEN_PROGRAM_ID, mints::TOKEN_2022_PROGRAM] {
+                                let started = Instant::now();
+                                let result = fetch_token_accounts_json(&rpc_url, address, json!({ "programId": program }), commitment).await;
+                                state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+                                match result {
+                                    Ok(accounts) => tokens.extend(parse_token_accounts(accounts)),
+                                    Err(e) => token_errors.push(format!("{}: {}", program, e)),
+                                }
+                            }
+                            tokens.sort_by(|a, b| token_value(&b.mint, &b.amount, b.decimals).cmp_value(&token_value(&a.mint, &a.amount, a.decimals)));
+                            Ok(json!({
+                                "sol_lamports": lamports,
+                                "sol_balance": utils::base_units_to_ui(lamports, 9),
+                                "tokens": tokens,
+                                "token_errors": token_errors,
+                                "commitment": commitment
+                            }))
+                        }
+                    }
+                }
+            },
+        };
+        if result.is_err() {
+            failed.push("balances");
+        }
+        sections.insert("balances".to_string(), dashboard_section(result));
+    }
+
+    if query.transactions.unwrap_or(true) {
+        // fuego's own submissions come from the local index; a wallet with none falls back to the RPC
+        let mut indexed = memo_index::load();
+        indexed.retain(|m| m.network == network);
+        let result = if !indexed.is_empty() {
+            let recent: Vec<memo_index::IndexedMemo> = indexed.into_iter().rev().take(limit).collect();
+            Ok(json!({ "source": "local_index", "transactions": recent }))
+        } else {
+            match &address {
+                None => Err("No wallet address".to_string()),
+                Some(address) => {
+                    let started = Instant::now();
+                    let result = fetch_signatures_raw(&rpc_url, address, Some(limit), commitment).await;
+                    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+                    result
+                        .and_then(|raw| serde_json::from_str::<serde_json::Value>(raw.get()).map_err(|e| e.to_string()))
+                        .map(|signatures| json!({ "source": "rpc", "transactions": signatures }))
+                }
+            }
+        };
+        if result.is_err() {
+            failed.push("transactions");
+        }
+        sections.insert("transactions".to_string(), dashboard_section(result));
+    }
+
+    if query.pending.unwrap_or(true) {
+        sections.insert(
+            "pending".to_string(),
+            dashboard_section(Ok(json!({ "gauges": state.pending.gauges(), "submit_queue_depth": state.submit_queue.depth() }))),
+        );
+    }
+
+    if query.fees.unwrap_or(true) {
+        let started = Instant::now();
+        let result = fetch_priority_fee_suggestions(&rpc_url).await;
+        state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+        if result.is_err() {
+            failed.push("fees");
+        }
+        sections.insert("fees".to_string(), dashboard_section(result));
+    }
+
+    if wallet.is_err() {
+        failed.push("wallet");
+    }
+    sections.insert("wallet".to_string(), dashboard_section(wallet));
+
+    select_fields(
+        selection.as_ref(),
+        json!({
+            "success": true,
+            "data": {
+                "network": network,
+                "sections": sections,
+                "failed_sections": failed,
+                "generated_at": chrono::Utc::now().timestamp()
+            }
+        }),
+    )
+}
+
+#[derive(Serialize, Deserialize)]
+struct NftHoldingsRequest {
+    #[serde(default)]
+    network: String,
+    #[serde(flatten)]
+    target: RpcTarget,
+    address: String,
+    #[serde(default)]
+    commitment: Option<String>,
+    /// 1-based page, passed through to getAssetsByOwner (default 1)
+    #[serde(default)]
+    page: Option<u32>,
+    /// Assets per page (default 100, at most 1000)
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CleanupSuggestionsRequest {
+    #[serde(default)]
+    network: String,
+    #[serde(flatten)]
+    target: RpcTarget,
+    address: String,
+    #[serde(default)]
+    commitment: Option<String>,
+    /// Also suggest accounts that aren't empty: USD stablecoin dust and wrapped SOL
+    #[serde(default)]
+    include_dust: bool,
+    /// Dust is a stablecoin balance worth less than this many US cents (default 100)
+    #[serde(default)]
+    dust_threshold_cents: Option<u64>,
+}
+
+/// One entry of POST /token-balances.
+#[derive(Serialize)]
+struct TokenBalanceEntry {
+    mint: String,
+    symbol: Option<String>,
+    amount: String,
+    decimals: u8,
+    ui_amount: serde_json::Number,
+    token_program: &'static str,
+    /// The token account holding the balance
+    ata: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TokenAccountInfo {
+    mint: String,
+    symbol: Option<String>,
+    amount: String,
+    decimals: u8,
+    ui_amount: serde_json::Number,
+    token_account: String,
+    /// Rent plus, for wSOL, the wrapped SOL
+    #[serde(skip)]
+    lamports: u64,
+    /// Not frozen and holding no withheld Token-2022 fees
+    #[serde(skip)]
+    closable: bool,
+}
+
+/// A token account's balance as a TokenAmount; an unparseable amount counts as zero.
+fn token_value(mint: &str, amount: &str, decimals: u8) -> amount::TokenAmount {
+    let asset = amount::Asset::Mint(mint.to_string());
+    amount::TokenAmount::parse_raw(asset.clone(), amount, decimals)
+        .unwrap_or_else(|_| amount::TokenAmount::from_base_units(asset, 0, decimals))
+}
+
+/// jsonParsed token accounts (getTokenAccountsByOwner) as TokenAccountInfo; malformed entries are skipped.
+fn parse_token_accounts(token_accounts: Vec<serde_json::Value>) -> Vec<TokenAccountInfo> {
+    let mut tokens: Vec<TokenAccountInfo> = Vec::new();
+    for item in token_accounts {
+        let pubkey = item.get("pubkey").and_then(|p| p.as_str()).unwrap_or("").to_string();
+        let account = match item.get("account") {
+            Some(a) => a,
+            None => continue,
+        };
+        let data = match account.get("data") {
+            Some(d) => d,
+            None => continue,
+        };
+        let parsed = match data.get("parsed") {
+            Some(p) => p,
+            None => continue,
+        };
+        let info = match parsed.get("info") {
+            Some(i) => i,
+            None => continue,
+        };
+        let mint = info.get("mint").and_then(|m| m.as_str()).unwrap_or("").to_string();
+        let token_amount = info.get("tokenAmount");
+        let amount = token_amount
+            .and_then(|t| t.get("amount"))
+            .and_then(|a| a.as_str())
+            .unwrap_or("0")
+            .to_string();
+        let decimals = token_amount
+            .and_then(|t| t.get("decimals"))
+            .and_then(|d| d.as_u64())
+            .unwrap_or(0) as u8;
+        // From the exact integer amount rather than the RPC's float uiAmount
+        let ui_amount = utils::base_units_to_ui_number(token_value(&mint, &amount, decimals).base_units(), decimals);
+        let withheld_fees = info["extensions"].as_array().into_iter().flatten().any(|ext| {
+            ext["extension"] == "transferFeeAmount" && ext["state"]["withheldAmount"].as_u64().unwrap_or(0) > 0
+        });
+
+        tokens.push(TokenAccountInfo {
+            mint: mint.clone(),
+            symbol: get_token_symbol(&mint).map(|s| s.to_string()),
+            amount,
+            decimals,
+            ui_amount,
+            token_account: pubkey,
+            lamports: account.get("lamports").and_then(|l| l.as_u64()).unwrap_or(0),
+            closable: info["state"] != "frozen" && !withheld_fees,
+        });
+    }
+    tokens
+}
+
+async fn get_tokens(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<GetTokensRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let commitment = state.commitment.read(&payload.network, &payload.commitment);
+    let rpc = state.rpc.client(&payload.network, &rpc_url, get_commitment_config(commitment));
+
+    let wallet_pubkey = string_to_pub_key(&payload.address)
+        .map_err(|_| ApiError::bad_request("Invalid wallet address").code("invalid_address"))?;
+
+    // Get SOL balance (no account decoder involved)
+    let started = Instant::now();
+    let result = rpc.get_balance(&wallet_pubkey).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+
+    let sol_balance = result.map_err(|e| ApiError::upstream(format!("Failed to get SOL balance: {}", e)).code("rpc_error"))?;
+
+    // Get token accounts via raw RPC (jsonParsed) and parse as JSON — no solana_account_decoder
+    let started = Instant::now();
+    let result = fetch_token_accounts_json(&rpc_url, &payload.address, json!({ "programId": TOKEN_PROGRAM_ID }), commitment).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+
+    let token_accounts = result.map_err(|e| ApiError::upstream(format!("Failed to get token accounts: {}", e)).code("rpc_error"))?;
+
+    let mut tokens = parse_token_accounts(token_accounts);
+
+    // Sort by UI amount (descending)
+    tokens.sort_by(|a, b| token_value(&b.mint, &b.amount, b.decimals).cmp_value(&token_value(&a.mint, &a.amount, a.decimals)));
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "wallet": payload.address,
+            "network": payload.network,
+            "sol_balance": utils::base_units_to_ui_number(sol_balance, amount::SOL_DECIMALS),
+            "sol_lamports": sol_balance,
+            "tokens": tokens,
+            "token_count": tokens.len(),
+            "commitment": commitment
+        }
+    })))
+}
+
+// Every token the wallet holds, across both token programs, in one call
+async fn get_token_balances(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<TokenBalancesRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    let selection = fields::parse(payload.fields.as_deref()).map_err(|e| fields_error(e, Vec::new()))?;
+    if string_to_pub_key(&payload.address).is_err() {
+        return Err(ApiError::bad_request("Invalid wallet address").code("invalid_address"));
+    }
+
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let commitment = state.commitment.read(&payload.network, &payload.commitment);
+
+    let mut balances: Vec<TokenBalanceEntry> = Vec::new();
+    for program in [TOKEN_PROGRAM_ID, mints::TOKEN_2022_PROGRAM] {
+        let started = Instant::now();
+        let result = fetch_token_accounts_json(&rpc_url, &payload.address, json!({ "programId": program }), commitment).await;
+        state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+        let accounts = result.map_err(|e| ApiError::upstream(format!("Failed to get token accounts: {}", e)).code("rpc_error"))?;
+        balances.extend(
+            parse_token_accounts(accounts)
+                .into_iter()
+                .filter(|t| payload.include_empty || t.amount != "0")
+                .map(|t| TokenBalanceEntry {
+                    mint: t.mint,
+                    symbol: t.symbol,
+                    amount: t.amount,
+                    decimals: t.decimals,
+                    ui_amount: t.ui_amount,
+                    token_program: program,
+                    ata: t.token_account,
+                }),
+        );
+    }
+
+    // Sort by UI amount (descending), as /tokens does
+    balances.sort_by(|a, b| token_value(&b.mint, &b.amount, b.decimals).cmp_value(&token_value(&a.mint, &a.amount, a.decimals)));
+
+    select_fields(
+        selection.as_ref(),
+        json!({
+            "success": true,
+            "data": {
+                "wallet": payload.address,
+                "network": payload.network,
+                "balances": balances,
+                "count": balances.len(),
+                "include_empty": payload.include_empty,
+                "commitment": commitment
+            }
+        }),
+    )
+}
+
+// NFTs and compressed NFTs through DAS when the endpoint has it, else from token accounts
+async fn get_nft_holdings(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<NftHoldingsRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    if string_to_pub_key(&payload.address).is_err() {
+        return Err(ApiError::bad_request("Invalid wallet address").code("invalid_address"));
+    }
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let commitment = state.commitment.read(&payload.network, &payload.commitment);
+    let page = payload.page.unwrap_or(1).max(1);
+    let limit = payload.limit.unwrap_or(nfts::DEFAULT_LIMIT).clamp(1, nfts::MAX_LIMIT);
+    let http = rpc::http_client(&rpc_url);
+
+    // DAS is optional: any failure falls back to token accounts, and says why
+    let mut das_error = None;
+    if !nfts::known_without_das(&rpc_url) {
+        let started = Instant::now();
+        match nfts::from_das(&http, &rpc_url, &payload.address, page, limit).await {
+            Ok(holdings) => {
+                state.rpc.record(&rpc_url, started.elapsed(), true);
+                return nft_holdings_response(&payload, "das", holdings, page, limit, None);
+            }
+            Err(e) => das_error = Some(e),
+        }
+    }
+
+    let started = Instant::now();
+    let result = nfts::from_token_accounts(&http, &rpc_url, &payload.address, page, limit, commitment).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+    match result {
+        Ok(holdings) => nft_holdings_response(&payload, "token_accounts", holdings, page, limit, das_error),
+        Err(e) => Err(ApiError::upstream(format!("Failed to get NFT holdings: {}", e))
+            .code("rpc_error")
+            .with("das_error", &das_error)),
+    }
+}
+
+fn nft_holdings_response(
+    payload: &NftHoldingsRequest,
+    backend: &str,
+    holdings: nfts::Holdings,
+    page: u32,
+    limit: usize,
+    das_error: Option<String>,
+) -> ApiResult {
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "wallet": payload.address,
+            "network": payload.network,
+            "backend": backend,
+            "nfts": holdings.nfts,
+            "count": holdings.nfts.len(),
+            "page": page,
+            "limit": limit,
+            "total": holdings.total,
+            "das_error": das_error
+        }
+    })))
+}
+
+// Empty token accounts (and, on request, dust and wrapped SOL) whose rent can be reclaimed
+async fn get_cleanup_suggestions(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<CleanupSuggestionsRequest>,
+) -> ApiResult {
+    resolve_rpc_target(&state, &mut payload.network, &mut payload.target)?;
+    if string_to_pub_key(&payload.address).is_err() {
+        return Err(ApiError::bad_request("Invalid wallet address").code("invalid_address"));
+    }
+    let rpc_url = request_rpc_url(&state, &payload.network, &payload.target, true).await?;
+    let commitment = state.commitment.read(&payload.network, &payload.commitment);
+    let options = cleanup::Options {
+        include_dust: payload.include_dust,
+        dust_threshold_cents: payload.dust_threshold_cents.unwrap_or(cleanup::DEFAULT_DUST_THRESHOLD_CENTS),
+    };
+
+    let mut suggestions = Vec::new();
+    let mut recoverable_lamports: u64 = 0;
+    let mut unpriced = 0;
+    for program in [TOKEN_PROGRAM_ID, mints::TOKEN_2022_PROGRAM] {
+        let started = Instant::now();
+        let result = fetch_token_accounts_json(&rpc_url, &payload.address, json!({ "programId": program }), commitment).await;
+        state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+        let accounts = result.map_err(|e| ApiError::upstream(format!("Failed to get token accounts: {}", e)).code("rpc_error"))?;
+        for t in parse_token_accounts(accounts) {
+            let account = cleanup::Account {
+                mint: &t.mint,
+                symbol: t.symbol.as_deref(),
+                amount: &t.amount,
+                decimals: t.decimals,
+                closable: t.closable,
+            };
+            let Some(kind) = cleanup::classify(&account, &options) else {
+                if options.include_dust && t.closable && t.amount != "0" && cleanup::unpriced(&account) {
+                    unpriced += 1;
+                }
+                continue;
+            };
+            recoverable_lamports = recoverable_lamports.saturating_add(t.lamports);
+            suggestions.push(json!({
+                "kind": kind,
+                "token_account": t.token_account,
+                "mint": t.mint,
+                "symbol": t.symbol,
+                "amount": t.amount,
+                "ui_amount": t.ui_amount,
+                "token_program": program,
+                "recoverable_lamports": t.lamports,
+                "builder": cleanup::builder(kind, &t.mint, &payload.network, &payload.address)
+            }));
+        }
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "wallet": payload.address,
+            "network": payload.network,
+            "suggestions": suggestions,
+            "count": suggestions.len(),
+            "recoverable_lamports": recoverable_lamports,
+            "recoverable_sol": utils::base_units_to_ui_number(recoverable_lamports, amount::SOL_DECIMALS),
+            "include_dust": options.include_dust,
+            "dust_threshold_cents": options.dust_threshold_cents,
+            "unpriced_skipped": unpriced,
+            "commitment": commitment
+        }
+    })))
+}
+
+/// Address, network and source file of the active wallet: wallet-config.json first (has
+/// walletAddress), then the legacy wallet.json (has address).
+fn load_active_wallet() -> Option<(String, String, &'static str)> {
+    let home_dir = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("/"));
+
+    let config_path = home_dir.join(".fuego").join("wallet-config.json");
+    if let Ok(config_content) = fs::read_to_string(&config_path) {
+        if let Ok(config) = serde_json::from_str::<WalletConfig>(&config_content) {
+            return Some((config.wallet_address, config.network, "wallet-config"));
+        }
+    }
+
+    let wallet_path = home_dir.join(".fuego").join("wallet.json");
+    if let Ok(wallet_content) = fs::read_to_string(&wallet_path) {
+        if let Ok(wallet) = serde_json::from_str::<WalletStore>(&wallet_content) {
+            return Some((wallet.address, wallet.network, "wallet"));
+        }
+    }
+    None
+}
+
+async fn get_wallet_address(State(state): State<AppState>) -> ApiResult {
+    let fee_wallet = state.fee_wallet.status();
+
+    if let Some((address, network, source)) = load_active_wallet() {
+        return Ok(Json(json!({
+            "success": true,
+            "data": {
+                "address": address,
+                "network": network,
+                "source": source,
+                "low_balance": fee_wallet.low_balance,
+                "lamports": fee_wallet.lamports
+            }
+        })));
+    }
+
+    // No wallet found
+    Err(ApiError::not_found("No wallet found. Initialize with: fuego create").code("wallet_not_found"))
+}
+
+async fn get_rpc_status(State(state): State<AppState>) -> ApiResult {
+    let mut data = state.rpc.snapshot();
+    data["providers"] = json!(state.providers.describe());
+    Ok(Json(json!({
+        "success": true,
+        "data": data
+    })))
+}
+
+// Prometheus scrape target; pending values come from the tracker's last tick and RPC values from
+// the same per-endpoint stats GET /rpc-status reports, so nothing is computed or counted twice
+async fn get_metrics(State(state): State<AppState>) -> Response {
+    (
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        format!("{}{}", state.pending.render_prometheus(), state.rpc.render_prometheus()),
+    )
+        .into_response()
+}
+
+async fn get_tx_cache(State(state): State<AppState>) -> ApiResult {
+    Ok(Json(json!({
+        "success": true,
+        "data": state.tx_cache.snapshot()
+    })))
+}
+
+/// Every `code` an error response can carry, so clients can check they handle them all.
+async fn list_error_codes() -> ApiResult {
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "count": api_error::CODES.len(),
+            "codes": api_error::CODES
+        }
+    })))
+}
+
+fn auth_error_response(e: auth::AuthError) -> Response {
+    ApiError::from(e).into_response()
+}
+
+/// Largest body a signed request may carry; it is buffered in full to be hashed.
+const MAX_SIGNED_BODY_BYTES: usize = 1024 * 1024;
+
+/// With FUEGO_AUTH_KEYS set, every route except auth::is_public ones needs a bearer key or a valid
+/// x-fuego-signature. Signed requests have their body buffered, hashed, then handed on unchanged.
+/// One line per authenticated request, naming the key by its label; the secret is never logged.
+fn log_authenticated(label: &str, method: &Method, path: &str) {
+    tracing::info!(key = label, %method, path, "authenticated");
+}
+
+async fn require_auth(State(state): State<AppState>, mut request: axum::extract::Request, next: axum::middleware::Next) -> Response {
+    if !state.auth.enabled() || auth::is_public(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    if let Some(value) = request.headers().get(auth::SIGNATURE_HEADER) {
+        let header = match value.to_str().map_err(|_| auth::AuthError::Malformed("not valid ASCII".to_string())).and_then(auth::SignatureHeader::parse) {
+            Ok(h) => h,
+            Err(e) => return auth_error_response(e),
+        };
+        let (mut parts, body) = request.into_parts();
+        let bytes = match axum::body::to_bytes(body, MAX_SIGNED_BODY_BYTES).await {
+            Ok(b) => b,
+            Err(_) => {
+                return auth_error_response(auth::AuthError::Malformed(format!(
+                    "body unreadable or over {} bytes",
+                    MAX_SIGNED_BODY_BYTES
+                )))
+            }
+        };
+        let path = parts.uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+        match state.auth.verify_signed(&header, parts.method.as_str(), path, &bytes, true) {
+            Ok(label) => {
+                log_authenticated(&label, &parts.method, parts.uri.path());
+                parts.extensions.insert(auth::Authenticated { label });
+            }
+            Err(e) => return auth_error_response(e),
+        }
+        return next.run(axum::extract::Request::from_parts(parts, axum::body::Body::from(bytes))).await;
+    }
+
+    let bearer = request
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.trim().to_string());
+    match bearer {
+        Some(token) => match state.auth.verify_bearer(&token) {
+            Ok(label) => {
+                log_authenticated(&label, request.method(), request.uri().path());
+                request.extensions_mut().insert(auth::Authenticated { label });
+                next.run(request).await
+            }
+            Err(e) => auth_error_response(e),
+        },
+        None => auth_error_response(auth::AuthError::Missing),
+    }
+}
+
+/// Largest request body a traced request has read for its span attributes; axum's own default limit.
+const MAX_TRACED_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Largest response body the middleware reads into memory to rewrite or inspect. Every body fuego
+/// builds has a known length; a bigger one, or one of unknown length, passes through unread.
+const MAX_BUFFERED_RESPONSE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// The response's body in memory, or the response back untouched when it is too big to buffer.
+async fn buffer_response(response: Response) -> Result<(axum::http::response::Parts, axum::body::Bytes), Response> {
+    let (parts, body) = response.into_parts();
+    match axum::body::HttpBody::size_hint(&body).upper() {
+        Some(len) if len <= MAX_BUFFERED_RESPONSE_BYTES => {}
+        _ => return Err(Response::from_parts(parts, body)),
+    }
+    match axum::body::to_bytes(body, MAX_BUFFERED_RESPONSE_BYTES as usize).await {
+        Ok(bytes) => Ok((parts, bytes)),
+        Err(e) => Err(ApiError::internal(format!("Failed to read response body: {}", e)).code("internal_error").into_response()),
+    }
+}
+
+/// Provider URLs carry API keys, and RPC errors quote the URL they called: JSON responses name
+/// the provider instead.
+async fn scrub_provider_urls(State(state): State<AppState>, request: axum::extract::Request, next: axum::middleware::Next) -> Response {
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("application/json"))
+        .unwrap_or(false);
+    if state.providers.is_empty() || !is_json {
+        return response;
+    }
+    let (mut parts, bytes) = match buffer_response(response).await {
+        Ok(buffered) => buffered,
+        Err(response) => return response,
+    };
+    let text = String::from_utf8_lossy(&bytes);
+    let scrubbed = providers::scrub(&text);
+    if scrubbed == text {
+        return Response::from_parts(parts, axum::body::Body::from(bytes));
+    }
+    parts.headers.remove(axum::http::header::CONTENT_LENGTH);
+    Response::from_parts(parts, axum::body::Body::from(scrubbed))
+}
+
+/// Gives the request an id, the caller's X-Request-Id or a new UUID, for everything inside it: the
+/// log span, error bodies and outbound calls. The response echoes it back.
+async fn assign_request_id(mut request: axum::extract::Request, next: axum::middleware::Next) -> Response {
+    let id = request_id::from_headers(request.headers());
+    let value = axum::http::HeaderValue::from_str(&id).ok();
+    if let Some(value) = &value {
+        request.headers_mut().insert(request_id::HEADER, value.clone());
+    }
+    let mut response = request_id::scope(id, next.run(request)).await;
+    if let Some(value) = value {
+        response.headers_mut().insert(request_id::HEADER, value);
+    }
+    response
+}
+
+/// Refuses a CORS preflight from an origin the policy doesn't allow, before the CORS layer would
+/// answer it without Access-Control headers.
+async fn cors_preflight(
+    State(policy): State<cors::CorsPolicy>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> Response {
+    if let Some(origin) = policy.refused_preflight(request.method(), request.headers()) {
+        let origin = origin.to_str().unwrap_or("(not text)").to_string();
+        return ApiError::forbidden(format!("Origin {} may not call this server; add it to cors.origins in server.json", origin))
+            .code("cors_origin_denied")
+            .with("origin", origin)
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// Draws the request from its client's read or write bucket. An empty bucket is a 429 with
+/// `retry_after_seconds` in the body and a Retry-After header.
+async fn rate_limit(
+    State(limiter): State<rate_limit::RateLimiter>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> Response {
+    let Some(class) = rate_limit::Class::of(request.method(), request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let peer = request.extensions().get::<axum::extract::ConnectInfo<SocketAddr>>().map(|info| info.0);
+    let Some(ip) = limiter.client_ip(request.headers(), peer) else {
+        return next.run(request).await;
+    };
+    if let Err(wait) = limiter.check(ip, class, Instant::now()) {
+        let retry_after = (wait.as_secs_f64().ceil() as u64).max(1);
+        let mut response = ApiError::rate_limited(format!(
+            "Too many {} requests from {}; try again in {} seconds",
+            class.as_str(),
+            ip,
+            retry_after
+        ))
+        .code("rate_limited")
+        .with("retry_after_seconds", retry_after)
+        .with("limit_class", class.as_str())
+        .with("limit_per_minute", limiter.per_minute(class))
+        .into_response();
+        response.headers_mut().insert(axum::http::header::RETRY_AFTER, retry_after.into());
+        return response;
+    }
+    next.run(request).await
+}
+
+/// Refuses a body declared over the size limit before anything buffers it (the body limit layer
+/// cuts off one that grows past it unannounced), and answers a request that outlives its route's
+/// timeout with a 504 rather than a dropped connection.
+async fn request_limits(
+    State(limits): State<limits::RequestLimits>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> Response {
+    let declared = request
+        .headers()
+        .get(axum::http::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared.map(|len| len > limits.max_body_bytes as u64).unwrap_or(false) {
+        return ApiError::new(
+            axum::http::StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Request body is over the {} byte limit", limits.max_body_bytes),
+        )
+        .code("body_too_large")
+        .with("max_body_bytes", limits.max_body_bytes)
+        .into_response();
+    }
+    let timeout = limits.timeout_for(request.uri().path());
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => ApiError::timeout(format!("Request did not finish within {}s", timeout.as_secs()))
+            .code("request_timeout")
+            .with("timeout_secs", timeout.as_secs())
+            .into_response(),
+    }
+}
+
+/// Runs the request under its RPC timeout: `timeout_ms` from the JSON body or query string, else
+/// FUEGO_RPC_TIMEOUT_MS. When an RPC call ran out of time and the request failed, the failure
+/// carries `code: "rpc_timeout"` with the endpoint and how long it waited; when it wasn't sent
+/// because every endpoint's circuit was open, `code: "rpc_circuit_open"` and when to retry.
+async fn rpc_timeout_scope(request: axum::extract::Request, next: axum::middleware::Next) -> Response {
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_TRACED_BODY_BYTES).await {
+        Ok(b) => b,
+        Err(_) => {
+            return ApiError::bad_request(format!("Request body unreadable or over {} bytes", MAX_TRACED_BODY_BYTES))
+                .code("body_too_large")
+                .into_response()
+        }
+    };
+    let from_query = parts
+        .uri
+        .query()
+        .and_then(|q| q.split('&').find_map(|pair| pair.strip_prefix("timeout_ms=")))
+        .map(|v| json!(v.parse::<u64>().ok()));
+    // Only bodies that mention the field are parsed
+    let from_body = if bytes.windows(b"\"timeout_ms\"".len()).any(|w| w == b"\"timeout_ms\"") {
+        serde_json::from_slice::<serde_json::Value>(&bytes).ok().map(|v| v["timeout_ms"].clone()).filter(|v| !v.is_null())
+    } else {
+        None
+    };
+    let timeout = match from_body.or(from_query) {
+        None => rpc::default_timeout(),
+        Some(value) => match value.as_u64().filter(|ms| (1..=rpc::MAX_TIMEOUT_MS).contains(ms)) {
+            Some(ms) => std::time::Duration::from_millis(ms),
+            None => {
+                return ApiError::bad_request(format!("timeout_ms must be a whole number of milliseconds from 1 to {}", rpc::MAX_TIMEOUT_MS))
+                    .code("invalid_timeout")
+                    .into_response()
+            }
+        },
     };
 
-    // Parse addresses
-    let from_pubkey = match string_to_pub_key(&payload.from_address) {
-        Ok(pk) => pk,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid from_address"
-            }))
-            .into_response();
-        }
+    let request = axum::extract::Request::from_parts(parts, axum::body::Body::from(bytes));
+    let (response, gave_up) = rpc::scope(timeout, next.run(request)).await;
+    let Some(gave_up) = gave_up else {
+        return response;
+    };
+    let (mut parts, bytes) = match buffer_response(response).await {
+        Ok(buffered) => buffered,
+        Err(response) => return response,
     };
+    let mut outcome: serde_json::Value = match serde_json::from_slice(&bytes) {
+        Ok(outcome) => outcome,
+        Err(_) => return Response::from_parts(parts, axum::body::Body::from(bytes)),
+    };
+    if outcome["success"] != json!(false) {
+        return Response::from_parts(parts, axum::body::Body::from(bytes));
+    }
+    match gave_up {
+        rpc::RpcGaveUp::Timeout(timed_out) => {
+            outcome["code"] = json!("rpc_timeout");
+            outcome["endpoint"] = json!(timed_out.endpoint);
+            outcome["elapsed_ms"] = json!(timed_out.elapsed_ms);
+            outcome["timeout_ms"] = json!(timeout.as_millis() as u64);
+            parts.status = axum::http::StatusCode::GATEWAY_TIMEOUT;
+        }
+        rpc::RpcGaveUp::CircuitOpen(open) => {
+            outcome["code"] = json!("rpc_circuit_open");
+            outcome["endpoint"] = json!(open.endpoint);
+            outcome["retry_after_seconds"] = json!(open.retry_after_secs);
+            parts.status = axum::http::StatusCode::SERVICE_UNAVAILABLE;
+            parts.headers.insert(axum::http::header::RETRY_AFTER, open.retry_after_secs.into());
+        }
+    }
+    parts.headers.remove(axum::http::header::CONTENT_LENGTH);
+    Response::from_parts(parts, axum::body::Body::from(outcome.to_string()))
+}
 
-    let to_pubkey = match string_to_pub_key(&payload.to_address) {
-        Ok(pk) => pk,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid to_address"
-            }))
-            .into_response();
+/// Every request runs as a span: a child of the caller's `traceparent` when it sent one. The span
+/// context is echoed back and carried onto outbound calls. With an OTLP endpoint configured, the
+/// finished span is exported with the network, amount bucket and outcome code read from the JSON
+/// bodies on the way through.
+async fn trace_request(State(state): State<AppState>, request: axum::extract::Request, next: axum::middleware::Next) -> Response {
+    let context = trace::TraceContext::from_headers(request.headers());
+    let traceparent = context.traceparent();
+    let with_traceparent = |mut response: Response| {
+        if let Ok(value) = traceparent.parse() {
+            response.headers_mut().insert(trace::TRACEPARENT, value);
         }
+        response
     };
+    let start = std::time::SystemTime::now();
+    let route = request
+        .extensions()
+        .get::<axum::extract::MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let method = request.method().to_string();
+    let mut attributes: Vec<(&'static str, serde_json::Value)> =
+        vec![("http.request.method", json!(method)), ("http.route", json!(route))];
 
-    // Parse amount (in SOL, convert to lamports)
-    let amount_lamports: u64 = match payload.amount.parse::<f64>() {
-        Ok(val) => (val * 1_000_000_000.0) as u64,
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_TRACED_BODY_BYTES).await {
+        Ok(b) => b,
         Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid amount"
-            }))
-            .into_response();
+            return with_traceparent(
+                ApiError::bad_request(format!("Request body unreadable or over {} bytes", MAX_TRACED_BODY_BYTES))
+                    .code("body_too_large")
+                    .into_response(),
+            )
         }
     };
-
-    // Build memo with new format: fuego|SOL|f:{from}|t:{to}|a:{amount}|yid:{yid}|n:{notes}
-    let memo_text = match build_memo("SOL", &payload.from_address, &payload.to_address, amount_lamports, &payload.yid, payload.notes.as_deref()) {
-        Ok(memo) => memo,
-        Err(e) => {
-            return Json(json!({
-                "success": false,
-                "error": e
-            }))
-            .into_response();
-        }
+    let fields: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+    let network = fields["network"].as_str().map(str::to_string).or_else(|| {
+        parts
+            .uri
+            .query()
+            .and_then(|q| q.split('&').find_map(|pair| pair.strip_prefix("network=")))
+            .map(str::to_string)
+    });
+    let amount = match &fields["amount"] {
+        serde_json::Value::String(s) => s.trim().parse::<f64>().ok(),
+        other => other.as_f64(),
     };
-    
-    // Try converting to the format solana_system_interface expects
-    let from_bytes = from_pubkey.to_bytes();
-    let to_bytes = to_pubkey.to_bytes();
-    
-    // Build instructions using solana_system_interface with explicit bytes
-    let transfer_instruction = transfer(
-        &solana_sdk::pubkey::Pubkey::new_from_array(from_bytes),
-        &solana_sdk::pubkey::Pubkey::new_from_array(to_bytes),
-        amount_lamports
-    );
-    
-    let memo_instruction = spl_memo::build_memo(memo_text.as_bytes(), &[]);
+    let amount_bucket = amount.filter(|_| state.tracer.record_amounts).map(trace::amount_bucket);
 
-    // Compute budget instructions
-    let compute_limit = ComputeBudgetInstruction::set_compute_unit_limit(100_000);
-    let unit_price = ComputeBudgetInstruction::set_compute_unit_price(
-        payload.fee_amount
-            .as_ref()
-            .and_then(|f| f.parse::<u64>().ok())
-            .unwrap_or(0)
-    );
+    // The log span TraceLayer opened for this request gets the same fields
+    let span = tracing::Span::current();
+    if let Some(network) = &network {
+        span.record("network", network.as_str());
+    }
+    if let Some(address) = ["address", "from_address", "wallet_address"].iter().find_map(|key| fields[*key].as_str()) {
+        span.record("address", address);
+    }
+    if let Some(to_address) = fields["to_address"].as_str() {
+        span.record("to_address", to_address);
+    }
+    if let Some(bucket) = amount_bucket {
+        span.record("amount_bucket", bucket);
+    }
 
-    // Create transaction message with fresh blockhash
-    let memo_ix = utils::instruction_from_spl(&memo_instruction);
-    let message = Message::new_with_blockhash(
-        &[compute_limit, unit_price, transfer_instruction, memo_ix],
-        Some(&from_pubkey),
-        &blockhash,
-    );
+    let request = axum::extract::Request::from_parts(parts, axum::body::Body::from(bytes));
+    if !state.tracer.exports(&context) {
+        return with_traceparent(trace::scope(context, next.run(request)).await);
+    }
+    if let Some(network) = network {
+        attributes.push(("fuego.network", json!(network)));
+    }
+    if let Some(bucket) = amount_bucket {
+        attributes.push(("fuego.amount_bucket", json!(bucket)));
+    }
+    let response = trace::scope(context.clone(), next.run(request)).await;
 
-    let transaction = Transaction::new_unsigned(message);
+    // A relayed x402 answer can be success: false under HTTP 200, so the body is checked as well
+    let (status, outcome, response) = match buffer_response(response).await {
+        Ok((parts, bytes)) => {
+            let outcome: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+            (parts.status, outcome, Response::from_parts(parts, axum::body::Body::from(bytes)))
+        }
+        // Too big to read: judged by its status alone
+        Err(response) => (response.status(), serde_json::Value::Null, response),
+    };
+    let failed = !status.is_success() || outcome["success"] == json!(false);
+    attributes.push(("http.response.status_code", json!(status.as_u16())));
+    attributes.push(("fuego.outcome", json!(if failed { "error" } else { "success" })));
+    if let Some(code) = outcome["code"].as_str() {
+        attributes.push(("fuego.error_code", json!(code)));
+    }
+    state.tracer.record(trace::Span {
+        context,
+        name: format!("{} {}", method, route),
+        start,
+        end: std::time::SystemTime::now(),
+        attributes,
+        failed,
+    });
+    with_traceparent(response)
+}
 
-    // Serialize transaction
-    let serialized_tx = match bincode::serialize(&transaction) {
-        Ok(bytes) => bytes,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Failed to serialize transaction"
-            }))
-            .into_response();
+/// Debug a signing implementation: shows what the server hashed and signed for this exact request
+/// and whether the signature verifies. The nonce is not consumed and no expected signature is revealed.
+async fn auth_echo(
+    State(state): State<AppState>,
+    method: Method,
+    uri: axum::http::Uri,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> ApiResult {
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+    let server_time = chrono::Utc::now().timestamp();
+    let header = match headers.get(auth::SIGNATURE_HEADER) {
+        None => Err(auth::AuthError::Missing),
+        Some(v) => v
+            .to_str()
+            .map_err(|_| auth::AuthError::Malformed("not valid ASCII".to_string()))
+            .and_then(auth::SignatureHeader::parse),
+    };
+
+    let (received, canonical, verification) = match header {
+        Ok(h) => {
+            let canonical = auth::canonical_request(method.as_str(), path, &body, h.timestamp, &h.nonce);
+            let verification = match state.auth.verify_signed(&h, method.as_str(), path, &body, false) {
+                Ok(label) => json!({ "valid": true, "key_label": label }),
+                Err(e) => json!({ "valid": false, "code": e.code(), "error": e.message() }),
+            };
+            (
+                json!({ "key_id": h.key_id, "ts": h.timestamp, "nonce": h.nonce, "skew_secs": server_time.checked_sub(h.timestamp) }),
+                Some(canonical),
+                verification,
+            )
         }
+        Err(e) => (serde_json::Value::Null, None, json!({ "valid": false, "code": e.code(), "error": e.message() })),
     };
 
-    Json(json!({
+    Ok(Json(json!({
         "success": true,
         "data": {
-            "transaction": serde_json::Value::String(
-                general_purpose::STANDARD.encode(&serialized_tx)
-            ),
-            "blockhash": blockhash.to_string(),
-            "from": payload.from_address,
-            "to": payload.to_address,
-            "amount": payload.amount,
-            "yid": payload.yid,
-            "memo": memo_text,
-            "network": payload.network
+            "method": method.as_str(),
+            "path": path,
+            "body_length": body.len(),
+            "body_sha256": format!("{:x}", Sha256::digest(&body)),
+            "received": received,
+            "canonical_request": canonical,
+            "server_time": server_time,
+            "max_skew_secs": state.auth.max_skew_secs(),
+            "verification": verification
         }
-    }))
-    .into_response()
+    })))
 }
 
-async fn build_transfer_usdt(
-    State(_state): State<AppState>,
-    Json(payload): Json<TransferUsdtRequest>,
-) -> Response {
-    // Fetch fresh blockhash
-    let rpc_url = format!("https://api.{}.solana.com", payload.network);
-    let rpc = RpcClient::new(rpc_url);
-
-    let blockhash = match rpc.get_latest_blockhash() {
-        Ok(bh) => bh,
-        Err(e) => {
-            return Json(json!({
-                "success": false,
-                "error": format!("Failed to fetch blockhash: {}", e)
-            }))
-            .into_response();
+async fn get_alerts(State(state): State<AppState>, Query(query): Query<AlertsQuery>) -> ApiResult {
+    let alerts = state.alerts.recent(query.limit.unwrap_or(50));
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "alerts": alerts,
+            "count": alerts.len(),
+            "webhook_configured": state.alerts.webhook_configured(),
+            "anomaly_config": state.anomaly.config()
         }
-    };
+    })))
+}
 
-    // Parse addresses
-    let from_pubkey = match string_to_pub_key(&payload.from_address) {
-        Ok(pk) => pk,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid from_address"
-            }))
-            .into_response();
+async fn get_x402_receipt_body(Path(id): Path<String>) -> ApiResult<Response> {
+    let receipt = match receipts::load_receipt(&id) {
+        Ok(Some(r)) => r,
+        Ok(None) => {
+            return Err(ApiError::not_found("Receipt not found").code("receipt_not_found"));
         }
-    };
-
-    let to_pubkey = match string_to_pub_key(&payload.to_address) {
-        Ok(pk) => pk,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid to_address"
-            }))
-            .into_response();
+        Err(e) => {
+            return Err(ApiError::bad_request(e).code("invalid_request"));
         }
     };
 
-    let usdt_mint = match string_to_pub_key(USDT_MINT) {
-        Ok(mint) => mint,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid USDT mint"
-            }))
-            .into_response();
-        }
-    };
+    if !receipt.body_stored {
+        return Err(ApiError::not_found("Response body was not stored for this receipt (pass store_response: true on the purchase)")
+            .code("receipt_body_not_stored"));
+    }
 
-    // Get associated token accounts
-    let from_ata = get_associated_token_address(&utils::to_spl_pubkey(&from_pubkey), &utils::to_spl_pubkey(&usdt_mint));
-    let to_ata = get_associated_token_address(&utils::to_spl_pubkey(&to_pubkey), &utils::to_spl_pubkey(&usdt_mint));
+    let body = receipts::load_body(&receipt.sha256)
+        .map_err(|e| ApiError::internal(format!("Failed to read stored body: {}", e)).code("internal_error"))?;
 
-    // Parse amount (USDT has 6 decimals)
-    let amount_ui = match payload.amount.parse::<f64>() {
-        Ok(a) => a,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid amount format"
-            }))
-            .into_response();
-        }
-    };
-    let amount = (amount_ui * 1_000_000.0) as u64;
+    // Refuse to serve a payload that no longer matches what was hashed at purchase time
+    if format!("{:x}", Sha256::digest(&body)) != receipt.sha256 {
+        return Err(ApiError::internal("Stored body does not match the receipt hash").code("receipt_body_mismatch"));
+    }
 
-    // Build instructions
-    let compute_limit = ComputeBudgetInstruction::set_compute_unit_limit(300_000);
-    let unit_price = ComputeBudgetInstruction::set_compute_unit_price(100);
-    let from_spl = utils::to_spl_pubkey(&from_pubkey);
-    let transfer_instruction = token_instruction::transfer(
-        &spl_token::id(),
-        &from_ata,
-        &to_ata,
-        &from_spl,
-        &[&from_spl],
-        amount,
-    ).unwrap();
+    // The body is whatever the seller sent; served with its own type, HTML or SVG would run as
+    // script on this origin. It always downloads as bytes; the receipt records the original type.
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, "application/octet-stream"),
+            (axum::http::header::CONTENT_DISPOSITION, "attachment"),
+            (axum::http::header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+        ],
+        body,
+    )
+        .into_response())
+}
 
-    let memo_text = build_memo("USDT", &payload.from_address, &payload.to_address, amount, &payload.yid, payload.notes.as_deref()).unwrap_or_default();
-    let memo_instruction = spl_memo::build_memo(memo_text.as_bytes(), &[&from_spl]);
+async fn list_x402_receipts(Query(filter): Query<receipts::YidFilter>) -> ApiResult {
+    if let Err(e) = filter.validate() {
+        return Err(ApiError::bad_request(e).code("invalid_request"));
+    }
+    let receipts = receipts::list_receipts()
+        .map_err(|e| ApiError::internal(format!("Failed to read x402 receipts: {}", e)).code("internal_error"))?;
+    let receipts: Vec<receipts::X402Receipt> = receipts.into_iter().filter(|r| filter.matches(r)).collect();
 
-    let transfer_ix = utils::instruction_from_spl(&transfer_instruction);
-    let memo_ix = utils::instruction_from_spl(&memo_instruction);
-    let message = Message::new_with_blockhash(
-        &[compute_limit, unit_price, transfer_ix, memo_ix],
-        Some(&from_pubkey),
-        &blockhash,
-    );
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "receipts": receipts,
+            "count": receipts.len()
+        }
+    })))
+}
 
-    let transaction = Transaction::new_unsigned(message);
+async fn list_x402_inflight(Query(query): Query<InflightQuery>) -> ApiResult {
+    let records = receipts::list_inflight()
+        .map_err(|e| ApiError::internal(format!("Failed to read in-flight records: {}", e)).code("internal_error"))?;
+    let records: Vec<receipts::InflightRecord> = records
+        .into_iter()
+        .filter(|r| query.status.as_deref().map(|s| r.status == s).unwrap_or(true))
+        .collect();
 
-    // Serialize transaction
-    let serialized_tx = match bincode::serialize(&transaction) {
-        Ok(bytes) => bytes,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Failed to serialize transaction"
-            }))
-            .into_response();
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "records": records,
+            "count": records.len()
         }
-    };
+    })))
+}
 
-    Json(json!({
+async fn get_x402_inflight(Path(id): Path<String>) -> ApiResult {
+    match receipts::load_inflight(&id) {
+        Ok(Some(record)) => Ok(Json(json!({
+            "success": true,
+            "data": record
+        }))),
+        Ok(None) => Err(ApiError::not_found("In-flight record not found").code("inflight_not_found")),
+        Err(e) => Err(ApiError::bad_request(e).code("invalid_request")),
+    }
+}
+
+/// Receipts whose response and on-chain settlement disagree, newest first: evidence for disputes.
+async fn list_x402_disputes() -> ApiResult {
+    let receipts = receipts::list_receipts()
+        .map_err(|e| ApiError::internal(format!("Failed to read receipts: {}", e)).code("internal_error"))?;
+    let disputes: Vec<receipts::X402Receipt> = receipts
+        .into_iter()
+        .filter(|r| r.settlement.as_ref().map(|v| v.mismatch.is_some()).unwrap_or(false))
+        .collect();
+
+    Ok(Json(json!({
         "success": true,
         "data": {
-            "transaction": serde_json::Value::String(
-                general_purpose::STANDARD.encode(&serialized_tx)
-            ),
-            "blockhash": blockhash.to_string(),
-            "from": payload.from_address,
-            "to": payload.to_address,
-            "amount": payload.amount,
-            "yid": payload.yid,
-            "memo": memo_text,
-            "network": payload.network
+            "receipts": disputes,
+            "count": disputes.len()
         }
-    }))
-    .into_response()
+    })))
 }
 
-// x402 Purch endpoint: call Purch x402 URL with order payload; x402-rs handles 402 → pay → retry; return final response.
-async fn x402_purch(
-    State(_state): State<AppState>,
-    Json(payload): Json<X402PurchRequest>,
-) -> Response {
-    use reqwest::Client;
-    use std::sync::Arc;
-    use x402_chain_solana::v1_solana_exact::client::V1SolanaExactClient;
-    use x402_chain_solana::v2_solana_exact::client::V2SolanaExactClient;
-    use x402_reqwest::{ReqwestWithPayments, ReqwestWithPaymentsBuild, X402Client};
+// Devnet faucet: mint the team's test token to any address (creating its ATA if needed)
+async fn devnet_mint_tokens(
+    State(state): State<AppState>,
+    StrictJson(mut payload): StrictJson<DevnetMintRequest>,
+) -> ApiResult {
+    // Only the configured endpoints, whose cluster is checked below, ever see the mint authority
+    if payload.target.is_set() {
+        return Err(ApiError::bad_request("The faucet only mints through the server's configured endpoints; drop rpc_url and provider")
+            .code("faucet_wrong_network"));
+    }
+    resolve_network(&state, &mut payload.network)?;
 
-    let network = if payload.network.is_empty() {
-        "mainnet-beta".to_string()
-    } else {
-        payload.network.clone()
+    let faucet = match state.faucet.as_ref() {
+        Some(f) => f,
+        None => {
+            return Err(ApiError::unavailable("Devnet faucet is not configured (set FUEGO_DEVNET_MINT and FUEGO_DEVNET_MINT_AUTHORITY)")
+                .code("faucet_not_configured"));
+        }
     };
 
-    // Load keypair from ~/.fuego/wallet.json (required for signing x402 payment)
-    let home_dir = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("/"));
-    let wallet_path = home_dir.join(".fuego").join("wallet.json");
-    let wallet_content = match fs::read_to_string(&wallet_path) {
-        Ok(c) => c,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "No wallet found at ~/.fuego/wallet.json. Run 'fuego create' first."
-            }))
-            .into_response();
+    if let Err(e) = faucet::check_network_name(&payload.network) {
+        return Err(ApiError::bad_request(e).code("faucet_wrong_network"));
+    }
+    // The pooled client fails over, so every endpoint it might reach must be on a test cluster
+    for endpoint in state.rpc.endpoints(&payload.network) {
+        let genesis_hash = match state.rpc.pinned(&endpoint, CommitmentConfig::default()).get_genesis_hash().await {
+            Ok(hash) => hash.to_string(),
+            Err(e) => return Err(rpc_failure("Failed to check the faucet endpoint's cluster", &e)),
+        };
+        if let Err(e) = faucet::check_genesis(&payload.network, &endpoint, &genesis_hash) {
+            return Err(ApiError::bad_request(e).code("faucet_wrong_network"));
         }
-    };
-    let wallet: WalletStore = match serde_json::from_str(&wallet_content) {
-        Ok(w) => w,
+    }
+    let rpc_url = state.rpc.primary(&payload.network);
+    let rpc = state.rpc.client(&payload.network, &rpc_url, CommitmentConfig::default());
+
+    let to_pubkey = string_to_pub_key(&payload.to_address)
+        .map_err(|_| ApiError::bad_request("Invalid to_address").code("invalid_address"))?;
+    let mint = string_to_pub_key(&faucet.mint)
+        .map_err(|_| ApiError::internal("FUEGO_DEVNET_MINT is not a valid address").code("internal_error"))?;
+
+    let decimals = state.mints.get(&rpc_url, &payload.network, &faucet.mint).await?.decimals;
+
+    let amount = match utils::ui_amount_to_base_units(&payload.amount, decimals) {
+        Ok(0) => {
+            return Err(ApiError::bad_request("Amount must be greater than zero").code("amount_zero"));
+        }
+        Ok(a) => a,
         Err(e) => {
-            return Json(json!({
-                "success": false,
-                "error": format!("Invalid wallet.json: {}", e)
-            }))
-            .into_response();
+            return Err(ApiError::bad_request(e).code("invalid_amount"));
         }
     };
-
-    if wallet.private_key.len() < 32 {
-        return Json(json!({
-            "success": false,
-            "error": "Wallet private key must be at least 32 bytes"
-        }))
-        .into_response();
+    let max_amount = utils::ui_amount_to_base_units(&faucet.max_amount, decimals).unwrap_or(0);
+    if amount > max_amount {
+        return Err(ApiError::bad_request(format!("Amount exceeds the per-request faucet cap of {}", faucet.max_amount))
+            .code("faucet_cap_exceeded"));
     }
-    let mut secret_arr = [0u8; 32];
-    secret_arr.copy_from_slice(&wallet.private_key[..32]);
 
-    let keypair = solana_sdk::signer::keypair::Keypair::new_from_array(secret_arr);
+    let secret = faucet.load_authority_secret().map_err(|e| ApiError::internal(e).code("internal_error"))?;
+    let authority = solana_sdk::signer::keypair::Keypair::new_from_array(secret);
+    let authority_pubkey = authority.pubkey();
 
-    let rpc_url = format!("https://api.{}.solana.com", network);
-    let rpc = solana_client::nonblocking::rpc_client::RpcClient::new(rpc_url);
-    let rpc_arc = Arc::new(rpc);
-    let keypair_arc = Arc::new(keypair);
+    let audit = |outcome: &str, signature: Option<String>, error: Option<String>| {
+        faucet::audit(&faucet::MintAudit {
+            timestamp: chrono::Utc::now().timestamp(),
+            network: &payload.network,
+            mint: &faucet.mint,
+            to: &payload.to_address,
+            amount: &payload.amount,
+            outcome,
+            signature,
+            error,
+        });
+    };
 
-    // Register both V1 and V2 Solana exact clients so we match whatever Purch.xyz returns (V1 or V2 402 format)
-    let x402_client = X402Client::new()
-        .register(V1SolanaExactClient::new(keypair_arc.clone(), rpc_arc.clone()))
-        .register(V2SolanaExactClient::new(keypair_arc, rpc_arc));
+    let (instructions, destination_token_account) =
+        faucet::mint_instructions(&authority_pubkey, &mint, &to_pubkey, amount, decimals)
+            .map_err(|e| ApiError::internal(e).code("internal_error"))?;
 
-    let http_client = match Client::builder().with_payments(x402_client).build() {
-        Ok(c) => c,
-        Err(e) => {
-            return Json(json!({
-                "success": false,
-                "error": format!("Failed to build HTTP client: {}", e)
-            }))
-            .into_response();
+    let blockhash = rpc.get_latest_blockhash().await
+        .map_err(|e| ApiError::upstream(format!("Failed to fetch blockhash: {}", e)).code("blockhash_fetch_failed"))?;
+
+    let message = Message::new_with_blockhash(&instructions, Some(&authority_pubkey), &blockhash);
+    let transaction = Transaction::new(&[&authority], message, blockhash);
+    let transaction: ClientTransaction = match bincode::serialize(&transaction)
+        .ok()
+        .and_then(|bytes| bincode::deserialize(&bytes).ok())
+    {
+        Some(tx) => tx,
+        None => {
+            return Err(ApiError::internal("Failed to serialize transaction").code("serialization_failed"));
         }
     };
 
-    let payer_address = payload.payer_address.as_deref().unwrap_or(wallet.address.as_str());
-    let mut physical_address = serde_json::Map::new();
-    physical_address.insert("name".to_string(), serde_json::Value::String(payload.name.clone()));
-    physical_address.insert("line1".to_string(), serde_json::Value::String(payload.address_line1.clone()));
-    physical_address.insert("city".to_string(), serde_json::Value::String(payload.city.clone()));
-    physical_address.insert("state".to_string(), serde_json::Value::String(payload.state.clone()));
-    physical_address.insert("postalCode".to_string(), serde_json::Value::String(payload.postal_code.clone()));
-    physical_address.insert("country".to_string(), serde_json::Value::String(if payload.country.is_empty() { "US".to_string() } else { payload.country.clone() }));
-    if let Some(ref line2) = payload.address_line2 {
-        if !line2.is_empty() {
-            physical_address.insert("line2".to_string(), serde_json::Value::String(line2.clone()));
-        }
+    if state.mode.is_dry_run() {
+        audit("simulated", None, None);
+        return match rpc.simulate_transaction(&transaction).await {
+            Ok(result) => Ok(Json(json!({
+                "success": true,
+                "data": {
+                    "signature": transaction.signatures.first().map(|s| s.to_string()),
+                    "mint": faucet.mint,
+                    "to": payload.to_address,
+                    "token_account": destination_token_account.to_string(),
+                    "amount": payload.amount,
+                    "network": payload.network,
+                    "simulated": true,
+                    "mode": state.mode.as_str(),
+                    "simulation": {
+                        "err": result.value.err.map(|e| e.to_string()),
+                        "logs": result.value.logs,
+                        "units_consumed": result.value.units_consumed
+                    }
+                }
+            }))),
+            Err(e) => Err(rpc_failure("Failed to simulate transaction", &e)
+                .with("simulated", true)
+                .with("mode", state.mode.as_str())),
+        };
     }
 
-    // Build order body - Purch requires lineItems with maxPrice AND top-level productUrl
-    let order_body = json!({
-        "email": payload.email,
-        "payerAddress": payer_address,
-        "productUrl": payload.product_url,
-        "physicalAddress": physical_address,
-        "lineItems": [
-            {
-                "productUrl": payload.product_url,
-                "maxPrice": payload.max_price.unwrap_or(10000)
-            }
-        ]
-    });
-    
-    eprintln!("DEBUG: Order body being sent to Purch: {}", serde_json::to_string_pretty(&order_body).unwrap_or_default());
-    let body_bytes = match serde_json::to_vec(&order_body) {
-        Ok(b) => b,
-        Err(e) => {
-            return Json(json!({
-                "success": false,
-                "error": format!("Failed to serialize order body: {}", e)
-            }))
-            .into_response();
-        }
-    };
-
-    let response = match http_client
-        .post(&payload.url)
-        .header("Content-Type", "application/json")
-        .body(body_bytes)
-        .send()
-        .await
-    {
-        Ok(r) => r,
+    match rpc.send_and_confirm_transaction(&transaction).await {
+        Ok(signature) => {
+            audit("minted", Some(signature.to_string()), None);
+            Ok(Json(json!({
+                "success": true,
+                "data": {
+                    "signature": signature.to_string(),
+                    "explorer_link": explorer_link(&state, &payload.network, &signature.to_string()),
+                    "mint": faucet.mint,
+                    "to": payload.to_address,
+                    "token_account": destination_token_account.to_string(),
+                    "amount": payload.amount,
+                    "network": payload.network
+                }
+            })))
+        }
         Err(e) => {
-            return Json(json!({
-                "success": false,
-                "error": format!("Request failed: {}", e)
-            }))
-            .into_response();
+            audit("failed", None, Some(e.to_string()));
+            Err(rpc_failure("Failed to mint test tokens", &e))
         }
-    };
+    }
+}
 
-    let status = response.status();
-    let body: String = match response.text().await {
-        Ok(b) => b,
-        Err(e) => {
-            return Json(json!({
-                "success": false,
-                "error": format!("Failed to read response: {}", e)
-            }))
-            .into_response();
+/// Wallets reconciliation scans: the local signing wallet and every watch-only wallet, plus the
+/// local wallet's network.
+fn reconcile_scope() -> (Vec<String>, Option<String>) {
+    let mut wallets = Vec::new();
+    let mut network = None;
+    if let Ok(content) = fs::read_to_string(utils::fuego_home().join("wallet.json")) {
+        if let Ok(wallet) = serde_json::from_str::<WalletStore>(&content) {
+            wallets.push(wallet.address);
+            network = Some(wallet.network);
         }
-    };
+    }
+    match wallets::load_watch_wallets() {
+        Ok(watch_wallets) => wallets.extend(watch_wallets.into_iter().map(|w| w.address)),
+        Err(e) => eprintln!("Reconciliation skips watch wallets: {}", e),
+    }
+    (wallets, network)
+}
 
-    let body_json: serde_json::Value = match serde_json::from_str(&body) {
-        Ok(j) => j,
-        Err(_) => serde_json::Value::String(body),
+async fn run_reconcile(state: &AppState, payload: ReconcileRequest) -> Result<reconcile::Report, String> {
+    let (wallets, network) = reconcile_scope();
+    let options = reconcile::Options {
+        dry_run: payload.dry_run,
+        depth: payload.depth.unwrap_or(reconcile::DEFAULT_DEPTH).min(stats::MAX_BACKFILL_DEPTH),
+        network: payload.network,
     };
-
-    let success = status.is_success();
-    Json(json!({
-        "success": success,
-        "status": status.as_u16(),
-        "data": body_json,
-        "x402_note": if success { "Payment accepted; order response above." } else { "Request completed; check status and data." }
-    }))
-    .into_response()
+    let report = reconcile::run(&state.rpc, &state.pending, &state.submit_queue, wallets, network, options).await?;
+    println!("{}", report.summary());
+    Ok(report)
 }
 
-async fn submit_transaction(
-    State(_state): State<AppState>,
-    Json(payload): Json<SubmitTransactionRequest>,
-) -> Response {
-    let rpc_url = format!("https://api.{}.solana.com", payload.network);
-    let rpc = RpcClient::new(rpc_url);
+// Re-sync the memo index and pending tracker with the chain, e.g. after downtime
+async fn admin_reconcile(State(state): State<AppState>, StrictJson(payload): StrictJson<ReconcileRequest>) -> ApiResult {
+    match run_reconcile(&state, payload).await {
+        Ok(report) => Ok(Json(json!({
+            "success": true,
+            "data": report
+        }))),
+        Err(e) => Err(ApiError::conflict(e).code("reconcile_in_progress")),
+    }
+}
 
-    // Decode base64 transaction
-    let tx_bytes = match general_purpose::STANDARD.decode(&payload.transaction) {
-        Ok(bytes) => bytes,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Failed to decode transaction - invalid base64"
-            }))
-            .into_response();
+// Clear runtime caches so they are rebuilt from the chain on next use
+async fn admin_reload(State(state): State<AppState>) -> ApiResult {
+    let server_config = config::ServerConfig::load().map_err(|e| ApiError::bad_request(e).code("invalid_config"))?;
+    let mints_cleared = state.mints.invalidate_all();
+    let transactions_cleared = state.tx_cache.invalidate_all();
+    let blockhashes_cleared = state.blockhashes.invalidate_all();
+    if !state.listen.ip().is_loopback() && auth::Auth::count(&server_config) == 0 {
+        return Err(ApiError::bad_request(format!("The server listens on {}, off loopback, and this config leaves it without API keys", state.listen))
+            .code("invalid_config"));
+    }
+    let auth_keys = state.auth.reload(&server_config);
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "mint_cache_entries_cleared": mints_cleared,
+            "tx_cache_entries_cleared": transactions_cleared,
+            "blockhash_cache_entries_cleared": blockhashes_cleared,
+            "auth_keys": auth_keys
         }
-    };
+    })))
+}
 
-    // Deserialize transaction (already signed by agent with correct blockhash)
-    let transaction: ClientTransaction = match bincode::deserialize(&tx_bytes) {
-        Ok(tx) => tx,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Failed to deserialize transaction"
-            }))
-            .into_response();
-        }
-    };
+#[cfg(feature = "fault-injection")]
+async fn admin_faults_get() -> ApiResult {
+    Ok(Json(json!({
+        "success": true,
+        "data": faults::snapshot()
+    })))
+}
 
-    // Submit to RPC (transaction is already signed with correct blockhash by agent)
-    match rpc.send_transaction(&transaction) {
-        Ok(signature) => {
-            let sig_string = signature.to_string();
-            let explorer_link = format!(
-                "https://explorer.solana.com/tx/{}?cluster={}",
-                sig_string, payload.network
-            );
-            Json(json!({
-                "success": true,
-                "data": {
-                    "signature": sig_string,
-                    "explorer_link": explorer_link,
-                    "network": payload.network,
-                    "status": "submitted"
-                }
-            }))
-            .into_response()
-        },
-        Err(e) => Json(json!({
-            "success": false,
-            "error": format!("Failed to submit transaction: {}", e)
-        }))
-        .into_response(),
+// Replace the injected faults; counters restart so each test sees a clean schedule
+#[cfg(feature = "fault-injection")]
+async fn admin_faults_set(StrictJson(config): StrictJson<faults::FaultConfig>) -> ApiResult {
+    if let Some(rule) = config.rpc.iter().find(|r| r.percent > 100) {
+        return Err(ApiError::bad_request(format!("percent must be 0-100, got {}", rule.percent)).code("invalid_request"));
     }
+    faults::set(config);
+    Ok(Json(json!({
+        "success": true,
+        "data": faults::snapshot()
+    })))
 }
 
-// VersionedTransaction endpoint specifically for Jupiter swaps and other v0 transactions
-async fn submit_versioned_transaction(
-    State(_state): State<AppState>,
-    Json(payload): Json<SubmitTransactionRequest>,
-) -> Response {
-    let rpc_url = format!("https://api.{}.solana.com", payload.network);
-    let rpc = RpcClient::new(rpc_url);
+#[cfg(feature = "fault-injection")]
+async fn admin_faults_reset() -> ApiResult {
+    faults::reset();
+    Ok(Json(json!({
+        "success": true,
+        "data": faults::snapshot()
+    })))
+}
 
-    // Decode base64 transaction
-    let tx_bytes = match general_purpose::STANDARD.decode(&payload.transaction) {
-        Ok(bytes) => bytes,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Failed to decode transaction - invalid base64"
-            }))
-            .into_response();
-        }
-    };
+// Zip of redacted diagnostics for bug reports; never includes key material
+async fn admin_support_bundle(State(state): State<AppState>) -> ApiResult<Response> {
+    let collected_at = chrono::Utc::now();
 
-    // Deserialize as VersionedTransaction (Jupiter format)
-    let versioned_transaction: ClientVersionedTransaction = match bincode::deserialize(&tx_bytes) {
-        Ok(tx) => tx,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Failed to deserialize VersionedTransaction - ensure this is a v0 transaction format"
-            }))
-            .into_response();
+    // Every value we know to be secret, scrubbed from all files as a second line of defence
+    let mut secrets: Vec<String> = Vec::new();
+    let env = support::env_settings(std::env::vars(), &mut secrets);
+    secrets.extend(support::config_secrets(&state.config));
+    // auth_keys may have been reloaded since startup
+    if let Ok(on_disk) = config::ServerConfig::load() {
+        secrets.extend(support::config_secrets(&on_disk));
+    }
+
+    let wallet_summary = match fs::read_to_string(utils::fuego_home().join("wallet.json"))
+        .ok()
+        .and_then(|c| serde_json::from_str::<WalletStore>(&c).ok())
+    {
+        Some(wallet) => {
+            secrets.extend(support::key_secrets(&wallet.private_key));
+            json!({ "address": wallet.address, "network": wallet.network, "has_private_key": !wallet.private_key.is_empty() })
         }
+        None => json!({ "address": null }),
     };
+    if let Some(faucet) = &state.faucet {
+        if let Ok(authority) = fs::read_to_string(&faucet.authority_path) {
+            secrets.push(authority.trim().to_string());
+        }
+    }
 
-    // Submit VersionedTransaction to RPC (already signed by agent)
-    match rpc.send_transaction(&versioned_transaction) {
-        Ok(signature) => {
-            let sig_string = signature.to_string();
-            let explorer_link = format!(
-                "https://explorer.solana.com/tx/{}?cluster={}",
-                sig_string, payload.network
-            );
-            Json(json!({
-                "success": true,
-                "data": {
-                    "signature": sig_string,
-                    "explorer_link": explorer_link,
-                    "network": payload.network,
-                    "status": "submitted",
-                    "transaction_type": "VersionedTransaction"
-                }
-            }))
-            .into_response()
-        },
-        Err(e) => Json(json!({
-            "success": false,
-            "error": format!("Failed to submit VersionedTransaction: {}", e)
+    let version = json!({
+        "service": "fuego-server",
+        "version": build_info::VERSION,
+        "build": build_info::json(),
+        "mode": state.mode.as_str(),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH
+    });
+    let settings = json!({
+        "env": env,
+        "default_network": state.default_network,
+        "require_explicit_network": state.require_explicit_network,
+        "require_sessions": state.require_sessions,
+        "memo_privacy": state.memo_privacy.as_str(),
+        "faucet_configured": state.faucet.is_some(),
+        "balance_at_max_scan": state.balance_at_max_scan,
+        "commitment": state.commitment.snapshot(),
+        "auth": state.auth.describe(),
+        "egress": state.egress.describe(),
+        "config_sources": build_info::config_sources()
+    });
+
+    let queue_items: Vec<serde_json::Value> = state
+        .submit_queue
+        .snapshot()
+        .into_iter()
+        .map(|item| json!({
+            "id": item.id,
+            "network": item.network,
+            "signature": item.signature,
+            "status": item.status,
+            "error": item.error,
+            "enqueued_at": item.enqueued_at,
+            "finished_at": item.finished_at
         }))
-        .into_response(),
-    }
+        .collect();
+    let inflight: Vec<serde_json::Value> = receipts::list_inflight()
+        .unwrap_or_default()
+        .into_iter()
+        .take(100)
+        .map(|r| json!({
+            "id": r.id,
+            "url": rpc::redact_url(&r.url),
+            "network": r.network,
+            "status": r.status,
+            "created_at": r.created_at,
+            "completed_at": r.completed_at
+        }))
+        .collect();
+
+    let mint_audit: String = fs::read_to_string(utils::fuego_home().join("devnet-mint-audit.jsonl"))
+        .map(|content| {
+            let lines: Vec<&str> = content.lines().collect();
+            lines[lines.len().saturating_sub(200)..].join("\n")
+        })
+        .unwrap_or_default();
+
+    let pretty = |value: serde_json::Value| serde_json::to_string_pretty(&value).unwrap_or_default();
+    let files: Vec<(String, String)> = vec![
+        ("version.json".to_string(), pretty(version)),
+        ("config.json".to_string(), pretty(settings)),
+        ("wallet-summary.json".to_string(), pretty(wallet_summary)),
+        ("alerts.json".to_string(), pretty(json!(state.alerts.recent(200)))),
+        ("rpc-status.json".to_string(), pretty(state.rpc.snapshot())),
+        ("fee-wallet.json".to_string(), pretty(json!(state.fee_wallet.status()))),
+        (
+            "submit-queue.json".to_string(),
+            pretty(json!({ "stats": state.submit_queue.stats(), "items": queue_items })),
+        ),
+        ("x402-inflight.json".to_string(), pretty(json!(inflight))),
+        ("devnet-mint-audit.jsonl".to_string(), mint_audit),
+    ];
+
+    let zip = support::archive(files, &secrets, &collected_at.to_rfc3339());
+
+    let filename = format!("fuego-support-{}.zip", collected_at.format("%Y%m%d-%H%M%S"));
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                axum::http::header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        zip,
+    )
+        .into_response())
 }
 
-async fn get_all_transactions(
-    Json(payload): Json<GetAccountSignatures>,
-) -> Response {
-    let rpc_url = format!("https://api.{}.solana.com", payload.network);
-    let rpc = RpcClient::new(rpc_url);
+// Passphrase-encrypted archive of the whole fuego home, wallet included, so it shares the key
+// export switch and audit log. Restore with `fuego-server restore <archive>`.
+async fn admin_backup(
+    State(state): State<AppState>,
+    StrictJson(payload): StrictJson<BackupRequest>,
+) -> ApiResult<Response> {
+    if let Err(denied) = key_export::availability(state.allow_key_export, state.mode.is_dry_run()) {
+        return Err(ApiError::forbidden(format!("Backups include the wallet key. {}", denied.message()))
+            .code(denied.code()));
+    }
+    if payload.passphrase.chars().count() < backup::MIN_PASSPHRASE_LEN {
+        return Err(ApiError::bad_request(format!("passphrase must be at least {} characters", backup::MIN_PASSPHRASE_LEN))
+            .code("invalid_request"));
+    }
+    if let Some(refusal) = key_export_refusal(&state, "backup", &payload.export_passphrase, payload.totp_code.as_deref()).await {
+        return Err(refusal);
+    }
 
-    let user_pubkey = match string_to_pub_key(&payload.address) {
-        Ok(pubkey) => pubkey,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid wallet address"
-            }))
-            .into_response()
+    // Flush in-memory state so the snapshot matches what a restart would load
+    state.submit_queue.persist();
+
+    // Argon2id and reading all of ~/.fuego: both blocking
+    let created = tokio::task::spawn_blocking(move || backup::create(&utils::fuego_home(), &payload.passphrase))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|created| created);
+    let (archive, summary) = match created {
+        Ok(result) => result,
+        Err(e) => {
+            wallets::audit_export("backup", "failed");
+            return Err(ApiError::internal(e).code("backup_failed"));
         }
     };
+    wallets::audit_export("backup", "ok");
+    state.alerts.emit(
+        "backup_created",
+        alerts::Severity::Medium,
+        format!("Encrypted backup of {} files created", summary.files.len()),
+        json!({ "files": summary.files.len(), "bytes": summary.bytes, "wallet_included": summary.wallet_included }),
+    );
 
-    let config = solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config {
-        before: None,
-        until: None,
-        limit: None, // Default to 20 transactions for "all"
-        commitment: Some(CommitmentConfig::confirmed()),
-    };
+    let filename = format!("fuego-backup-{}.fuegobak", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (
+                axum::http::header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        archive,
+    )
+        .into_response())
+}
 
-    let signatures = match rpc.get_signatures_for_address_with_config(&user_pubkey, config) {
-        Ok(signatures) => signatures,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Could not retrieve signatures for account"
-            }))
-            .into_response()
+/// Sessions bound what an agent's key may spend, so that key can't be the one that mints or
+/// revokes them: with auth on, only keys in session_admin_keys can. Without auth the server only
+/// listens on loopback, and there is no credential to tell apart.
+fn session_admin_refusal(state: &AppState, key: Option<&auth::Authenticated>) -> Option<ApiError> {
+    if !state.auth.enabled() || key.map(|k| state.auth.is_session_admin(&k.label)).unwrap_or(false) {
+        return None;
+    }
+    Some(
+        ApiError::forbidden("Creating and revoking sessions needs a key listed in session_admin_keys")
+            .code("session_admin_required"),
+    )
+}
+
+async fn create_session(
+    State(state): State<AppState>,
+    key: Option<axum::Extension<auth::Authenticated>>,
+    StrictJson(payload): StrictJson<CreateSessionRequest>,
+) -> ApiResult {
+    if let Some(refusal) = session_admin_refusal(&state, key.as_deref()) {
+        return Err(refusal);
+    }
+    if payload.caps.is_empty() {
+        return Err(ApiError::bad_request("At least one spending cap is required, e.g. {\"USDC\": \"20\"}").code("invalid_request"));
+    }
+    if payload.expires_in_seconds <= 0 {
+        return Err(ApiError::bad_request("expires_in_seconds must be positive").code("invalid_request"));
+    }
+
+    let mut caps = HashMap::new();
+    for (symbol, amount) in &payload.caps {
+        let decimals = match sessions::decimals_for_symbol(symbol) {
+            Some(d) => d,
+            None => {
+                return Err(ApiError::bad_request(format!("Unsupported session token: {} (expected SOL, USDC, USDT or PYUSD)", symbol))
+                    .code("unsupported_token"));
+            }
+        };
+        match utils::ui_amount_to_base_units(amount, decimals) {
+            Ok(base) => caps.insert(symbol.clone(), base),
+            Err(e) => {
+                return Err(ApiError::bad_request(format!("Invalid cap for {}: {}", symbol, e)).code("invalid_amount"));
+            }
+        };
+    }
+
+    let allowed_endpoints = match payload.allowed_endpoints {
+        Some(endpoints) => {
+            if let Some(unknown) = endpoints.iter().find(|e| !sessions::SESSION_ENDPOINTS.contains(&e.as_str())) {
+                return Err(ApiError::bad_request(format!("Unknown session endpoint: {}", unknown))
+                    .code("invalid_request")
+                    .with("allowed", sessions::SESSION_ENDPOINTS));
+            }
+            endpoints
         }
+        None => sessions::SESSION_ENDPOINTS.iter().map(|e| e.to_string()).collect(),
     };
 
-    Json(json!({
+    let session = state.sessions.create(caps, allowed_endpoints, payload.expires_in_seconds);
+    let mut data = session.summary();
+    data["token"] = json!(session.token);
+
+    Ok(Json(json!({
         "success": true,
-        "data": signatures,
-        "network": payload.network,
-        "status": "Successful all transactions request"
-    }))
-    .into_response()
+        "data": data
+    })))
 }
 
-// TODO: PYUSD balance endpoint using Token-2022
-// getTokenAccountsByOwner is implemented via raw RPC (jsonParsed) in get_tokens — no account decoder.
-// Token metadata for known tokens
-fn get_token_symbol(mint: &str) -> Option<&str> {
-    match mint {
-        "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v" => Some("USDC"),
-        "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB" => Some("USDT"),
-        "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263" => Some("BONK"),
-        "JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN" => Some("JUP"),
-        _ => None,
+async fn get_session(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult {
+    match state.sessions.get(&id) {
+        Some(session) => Ok(Json(json!({
+            "success": true,
+            "data": session.summary()
+        }))),
+        None => Err(ApiError::not_found("Session not found").code("session_not_found")),
     }
 }
 
-const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+async fn revoke_session(
+    State(state): State<AppState>,
+    key: Option<axum::Extension<auth::Authenticated>>,
+    Path(id): Path<String>,
+) -> ApiResult {
+    if let Some(refusal) = session_admin_refusal(&state, key.as_deref()) {
+        return Err(refusal);
+    }
+    if state.sessions.revoke(&id) {
+        Ok(Json(json!({
+            "success": true,
+            "data": { "id": id, "revoked": true }
+        })))
+    } else {
+        Err(ApiError::not_found("Session not found").code("session_not_found"))
+    }
+}
 
-/// Call getTokenAccountsByOwner via raw RPC (jsonParsed) and parse response as JSON.
-/// Avoids solana_account_decoder; uses only reqwest + serde_json.
-async fn fetch_token_accounts_json(rpc_url: &str, wallet_address: &str) -> Result<Vec<serde_json::Value>, String> {
-    let body = serde_json::json!({
-        "jsonrpc": "2.0",
-        "id": 1,
-        "method": "getTokenAccountsByOwner",
-        "params": [
-            wallet_address,
-            { "programId": TOKEN_PROGRAM_ID },
-            { "encoding": "jsonParsed" }
-        ]
-    });
-    let client = reqwest::Client::new();
-    let res = client
-        .post(rpc_url)
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-    let json: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
-    let value = json
-        .get("result")
-        .and_then(|r| r.get("value"))
-        .ok_or_else(|| "Missing result.value in RPC response".to_string())?;
-    let arr = value.as_array().ok_or_else(|| "result.value is not an array".to_string())?;
-    Ok(arr.clone())
+async fn create_share_link(State(state): State<AppState>, StrictJson(mut payload): StrictJson<CreateShareLinkRequest>) -> ApiResult {
+    resolve_network(&state, &mut payload.network)?;
+    if string_to_pub_key(&payload.address).is_err() {
+        return Err(ApiError::bad_request("Invalid wallet address").code("invalid_address"));
+    }
+    let mut views = payload.views.unwrap_or_else(|| vec!["history".to_string()]);
+    if views.is_empty() {
+        views.push("history".to_string());
+    }
+    if let Some(unknown) = views.iter().find(|v| !share_links::SHARE_VIEWS.contains(&v.as_str())) {
+        return Err(ApiError::bad_request(format!("Unknown share view: {}", unknown))
+            .code("invalid_share_view")
+            .with("allowed", share_links::SHARE_VIEWS));
+    }
+    views.sort();
+    views.dedup();
+    if let (Some(from), Some(until)) = (payload.from, payload.until) {
+        if from > until {
+            return Err(ApiError::bad_request("from must not be after until").code("invalid_share_range"));
+        }
+    }
+    let ttl = payload.expires_in_seconds.unwrap_or(share_links::DEFAULT_TTL_SECS);
+    if ttl <= 0 || ttl > share_links::MAX_TTL_SECS {
+        return Err(ApiError::bad_request(format!("expires_in_seconds must be between 1 and {}", share_links::MAX_TTL_SECS))
+            .code("invalid_share_ttl"));
+    }
+
+    let scope = share_links::Scope {
+        address: payload.address,
+        network: payload.network,
+        views,
+        from: payload.from,
+        until: payload.until,
+    };
+    let link = state.share_links.create(scope, ttl, payload.label);
+    let mut data = link.summary();
+    data["token"] = json!(link.token());
+    data["path"] = json!(format!("/shared/{}", link.token()));
+    Ok(Json(json!({
+        "success": true,
+        "data": data
+    })))
 }
 
-#[derive(Serialize, Deserialize)]
-struct GetTokensRequest {
-    address: String,
-    network: String,
+async fn list_share_links(State(state): State<AppState>) -> ApiResult {
+    let links: Vec<serde_json::Value> = state.share_links.list().iter().map(|l| l.summary()).collect();
+    Ok(Json(json!({
+        "success": true,
+        "data": { "links": links, "count": links.len() }
+    })))
 }
 
-#[derive(Serialize, Deserialize)]
-struct TokenAccountInfo {
-    mint: String,
-    symbol: Option<String>,
-    amount: String,
-    decimals: u8,
-    ui_amount: f64,
-    token_account: String,
+async fn revoke_share_link(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult {
+    if state.share_links.revoke(&id) {
+        Ok(Json(json!({
+            "success": true,
+            "data": { "id": id, "revoked": true }
+        })))
+    } else {
+        Err(ApiError::not_found("Share link not found").code("share_link_not_found"))
+    }
 }
 
-async fn get_tokens(
-    Json(payload): Json<GetTokensRequest>,
-) -> Response {
-    let rpc_url = format!("https://api.{}.solana.com", payload.network);
-    let rpc = RpcClient::new(rpc_url.clone());
+/// The link behind `token`, if it shares `view` and the request sets only `allowed` parameters.
+fn shared_link(
+    state: &AppState,
+    token: &str,
+    view: &str,
+    params: &HashMap<String, String>,
+    allowed: &[&str],
+) -> Result<share_links::ShareLink, share_links::ShareError> {
+    let link = state.share_links.verify(token)?;
+    if !link.scope.allows_view(view) {
+        return Err(share_links::ShareError::ViewNotShared(view.to_string()));
+    }
+    share_links::check_params(params, allowed)?;
+    Ok(link)
+}
 
-    let wallet_pubkey = match string_to_pub_key(&payload.address) {
-        Ok(pubkey) => pubkey,
-        Err(_) => {
-            return Json(json!({
-                "success": false,
-                "error": "Invalid wallet address"
-            })).into_response();
+// What a share link shows; public, the token is the credential
+async fn shared_scope(State(state): State<AppState>, Path(token): Path<String>, Query(params): Query<HashMap<String, String>>) -> ApiResult {
+    let link = state.share_links.verify(&token).and_then(|link| share_links::check_params(&params, &[]).map(|_| link))?;
+    let mut data = link.summary();
+    if let Some(object) = data.as_object_mut() {
+        // Only the owner needs these
+        object.remove("id");
+        object.remove("label");
+        object.remove("revoked");
+        object.remove("revoked_at");
+    }
+    Ok(Json(json!({
+        "success": true,
+        "data": data
+    })))
+}
+
+async fn shared_history(State(state): State<AppState>, Path(token): Path<String>, Query(params): Query<HashMap<String, String>>) -> ApiResult {
+    let link = shared_link(&state, &token, "history", &params, &["limit", "before"])?;
+    let limit = match params.get("limit").map(|l| l.parse::<usize>()) {
+        None => 20,
+        Some(Ok(limit)) if (1..=100).contains(&limit) => limit,
+        Some(_) => {
+            return Err(ApiError::bad_request("limit must be between 1 and 100").code("invalid_request"))
         }
     };
+    let mut config = json!({ "limit": limit, "commitment": "confirmed" });
+    if let Some(before) = params.get("before") {
+        if utils::string_to_signature(before).is_err() {
+            return Err(ApiError::bad_request("Invalid before signature").code("invalid_signature"));
+        }
+        config["before"] = json!(before);
+    }
 
-    // Get SOL balance (no account decoder involved)
-    let sol_balance = match rpc.get_balance(&wallet_pubkey) {
-        Ok(lamports) => lamports,
-        Err(e) => {
-            return Json(json!({
-                "success": false,
-                "error": format!("Failed to get SOL balance: {}", e)
-            })).into_response();
+    let scope = &link.scope;
+    let rpc_url = state.rpc.select_read(&scope.network);
+    let started = Instant::now();
+    let result = history::rpc_call(&rpc::http_client(&rpc_url), &rpc_url, "getSignaturesForAddress", json!([scope.address, config])).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+    let page = match result {
+        Ok(page) => page.as_array().cloned().unwrap_or_default(),
+        Err(_) => {
+            return Err(ApiError::upstream("Could not retrieve signatures for account").code("rpc_error"))
         }
     };
+    // The cursor covers the whole page, so paging continues past entries the range hides
+    let next_before = if page.len() == limit { page.last().and_then(|e| e["signature"].as_str()).map(str::to_string) } else { None };
+    let signatures: Vec<serde_json::Value> = page.into_iter().filter(|e| scope.in_range(e["blockTime"].as_i64())).collect();
 
-    // Get token accounts via raw RPC (jsonParsed) and parse as JSON — no solana_account_decoder
-    let token_accounts = match fetch_token_accounts_json(&rpc_url, &payload.address).await {
-        Ok(accounts) => accounts,
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "address": scope.address,
+            "network": scope.network,
+            "signatures": signatures,
+            "count": signatures.len(),
+            "next_before": next_before
+        }
+    })))
+}
+
+async fn shared_balances(State(state): State<AppState>, Path(token): Path<String>, Query(params): Query<HashMap<String, String>>) -> ApiResult {
+    let link = shared_link(&state, &token, "balances", &params, &[])?;
+    let scope = &link.scope;
+    let rpc_url = state.rpc.select_read(&scope.network);
+    let started = Instant::now();
+    let lamports = history::rpc_call(&rpc::http_client(&rpc_url), &rpc_url, "getBalance", json!([scope.address, { "commitment": "confirmed" }]))
+        .await
+        .and_then(|r| r["value"].as_u64().ok_or_else(|| "Missing balance in RPC response".to_string()));
+    state.rpc.record(&rpc_url, started.elapsed(), lamports.is_ok());
+    let lamports = match lamports {
+        Ok(lamports) => lamports,
         Err(e) => {
-            return Json(json!({
-                "success": false,
-                "error": format!("Failed to get token accounts: {}", e)
-            })).into_response();
+            return Err(ApiError::upstream(format!("Failed to get SOL balance: {}", e)).code("rpc_error"))
         }
     };
 
-    let mut tokens: Vec<TokenAccountInfo> = Vec::new();
-    for item in token_accounts {
-        let pubkey = item.get("pubkey").and_then(|p| p.as_str()).unwrap_or("").to_string();
-        let account = match item.get("account") {
-            Some(a) => a,
-            None => continue,
-        };
-        let data = match account.get("data") {
-            Some(d) => d,
-            None => continue,
-        };
-        let parsed = match data.get("parsed") {
-            Some(p) => p,
-            None => continue,
-        };
-        let info = match parsed.get("info") {
-            Some(i) => i,
-            None => continue,
-        };
-        let mint = info.get("mint").and_then(|m| m.as_str()).unwrap_or("").to_string();
-        let token_amount = info.get("tokenAmount");
-        let amount = token_amount
-            .and_then(|t| t.get("amount"))
-            .and_then(|a| a.as_str())
-            .unwrap_or("0")
-            .to_string();
-        let decimals = token_amount
-            .and_then(|t| t.get("decimals"))
-            .and_then(|d| d.as_u64())
-            .unwrap_or(0) as u8;
-        let ui_amount = token_amount
-            .and_then(|t| t.get("uiAmount"))
-            .and_then(|u| u.as_f64())
-            .unwrap_or(0.0);
+    let mut tokens = Vec::new();
+    for program in [TOKEN_PROGRAM_ID, mints::TOKEN_2022_PROGRAM] {
+        let started = Instant::now();
+        let result = fetch_token_accounts_json(&rpc_url, &scope.address, json!({ "programId": program }), "confirmed").await;
+        state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+        match result {
+            Ok(accounts) => tokens.extend(parse_token_accounts(accounts).into_iter().filter(|t| t.amount != "0")),
+            Err(e) => {
+                return Err(ApiError::upstream(format!("Failed to get token accounts: {}", e)).code("rpc_error"))
+            }
+        }
+    }
+    tokens.sort_by(|a, b| token_value(&b.mint, &b.amount, b.decimals).cmp_value(&token_value(&a.mint, &a.amount, a.decimals)));
 
-        tokens.push(TokenAccountInfo {
-            mint: mint.clone(),
-            symbol: get_token_symbol(&mint).map(|s| s.to_string()),
-            amount,
-            decimals,
-            ui_amount,
-            token_account: pubkey,
-        });
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "address": scope.address,
+            "network": scope.network,
+            "sol_lamports": lamports,
+            "sol_balance": utils::base_units_to_ui_number(lamports, amount::SOL_DECIMALS),
+            "tokens": tokens
+        }
+    })))
+}
+
+async fn shared_transaction(
+    State(state): State<AppState>,
+    Path((token, signature)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+) -> ApiResult {
+    let link = shared_link(&state, &token, "transaction", &params, &[])?;
+    if utils::string_to_signature(&signature).is_err() {
+        return Err(ApiError::bad_request("Invalid signature").code("invalid_signature"));
     }
+    let scope = &link.scope;
+    let rpc_url = state.rpc.select_read(&scope.network);
+    let config = json!({ "encoding": "json", "maxSupportedTransactionVersion": 0, "commitment": "confirmed" });
+    let started = Instant::now();
+    let result = history::rpc_call(&rpc::http_client(&rpc_url), &rpc_url, "getTransaction", json!([signature, config])).await;
+    state.rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+    let transaction = match result {
+        Ok(tx) if !tx.is_null() => tx,
+        Ok(_) => return Err(ApiError::from(share_links::ShareError::OutOfScope("This transaction".to_string()))),
+        Err(e) => {
+            return Err(ApiError::upstream(format!("Failed to get transaction: {}", e)).code("rpc_error"))
+        }
+    };
 
-    // Sort by UI amount (descending)
-    tokens.sort_by(|a, b| b.ui_amount.partial_cmp(&a.ui_amount).unwrap_or(std::cmp::Ordering::Equal));
+    // Only transactions the shared wallet took part in, inside the range. A missing transaction
+    // answers the same way, so the link can't be used to probe other activity.
+    let loaded = &transaction["meta"]["loadedAddresses"];
+    let involves_wallet = [&transaction["transaction"]["message"]["accountKeys"], &loaded["writable"], &loaded["readonly"]]
+        .iter()
+        .filter_map(|keys| keys.as_array())
+        .flatten()
+        .any(|key| key.as_str() == Some(scope.address.as_str()));
+    if !involves_wallet || !scope.in_range(transaction["blockTime"].as_i64()) {
+        return Err(ApiError::from(share_links::ShareError::OutOfScope("This transaction".to_string())));
+    }
 
-    Json(json!({
+    Ok(Json(json!({
         "success": true,
         "data": {
-            "wallet": payload.address,
-            "network": payload.network,
-            "sol_balance": sol_balance as f64 / 1_000_000_000.0,
-            "sol_lamports": sol_balance,
-            "tokens": tokens,
-            "token_count": tokens.len()
+            "signature": signature,
+            "network": scope.network,
+            "transaction": transaction
         }
-    })).into_response()
+    })))
 }
 
-async fn get_wallet_address() -> Response {
-    // Try to load wallet address from ~/.fuego/wallet-config.json
-    let home_dir = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("/"));
-    
-    // Try wallet-config.json first (has walletAddress field)
-    let config_path = home_dir.join(".fuego").join("wallet-config.json");
-    if config_path.exists() {
-        if let Ok(config_content) = fs::read_to_string(&config_path) {
-            if let Ok(config) = serde_json::from_str::<WalletConfig>(&config_content) {
-                return Json(json!({
-                    "success": true,
-                    "data": {
-                        "address": config.wallet_address,
-                        "network": config.network,
-                        "source": "wallet-config"
-                    }
-                })).into_response();
+async fn verify_wallet() -> ApiResult {
+    match verify_local_wallet() {
+        Some(Ok(verification)) => Ok(Json(json!({
+            "success": true,
+            "data": verification
+        }))),
+        Some(Err(e)) => Err(ApiError::internal(e).code("wallet_unreadable")),
+        None => Err(ApiError::not_found("No wallet found at ~/.fuego/wallet.json. Run 'fuego create' first.")
+            .code("wallet_not_found")),
+    }
+}
+
+async fn add_watch_wallet(StrictJson(payload): StrictJson<WatchWalletRequest>) -> ApiResult {
+    if string_to_pub_key(&payload.address).is_err() {
+        return Err(ApiError::bad_request("Invalid wallet address").code("invalid_address"));
+    }
+
+    let wallet_path = utils::fuego_home().join("wallet.json");
+    if let Ok(content) = fs::read_to_string(&wallet_path) {
+        if let Ok(wallet) = serde_json::from_str::<WalletStore>(&content) {
+            if wallet.address == payload.address {
+                return Err(ApiError::bad_request("This address is the local signing wallet and can't also be watch-only")
+                    .code("invalid_request"));
             }
         }
     }
-    
-    // Fallback to legacy wallet.json (has address field)
-    let wallet_path = home_dir.join(".fuego").join("wallet.json");
-    if wallet_path.exists() {
-        if let Ok(wallet_content) = fs::read_to_string(&wallet_path) {
-            if let Ok(wallet) = serde_json::from_str::<WalletStore>(&wallet_content) {
-                return Json(json!({
-                    "success": true,
-                    "data": {
-                        "address": wallet.address,
-                        "network": wallet.network,
-                        "source": "wallet"
-                    }
-                })).into_response();
+
+    let mut watch_wallets = wallets::load_watch_wallets().map_err(|e| ApiError::internal(e).code("internal_error"))?;
+
+    let label = payload.label.unwrap_or_else(|| "watch".to_string());
+    match watch_wallets.iter_mut().find(|w| w.address == payload.address) {
+        Some(existing) => existing.label = label,
+        None => watch_wallets.push(wallets::WatchWallet {
+            address: payload.address.clone(),
+            label,
+            added_at: chrono::Utc::now().timestamp(),
+        }),
+    }
+
+    if let Err(e) = wallets::save_watch_wallets(&watch_wallets) {
+        return Err(ApiError::internal(format!("Failed to save watch wallets: {}", e)).code("internal_error"));
+    }
+
+    let saved = watch_wallets.iter().find(|w| w.address == payload.address);
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "wallet": saved,
+            "kind": "watch"
+        }
+    })))
+}
+
+/// The cooldown and export secret checks shared by everything that hands out the wallet key. Every
+/// call consumes the cooldown, successful or not, so each passphrase or TOTP guess costs the wait.
+async fn key_export_refusal(state: &AppState, audit_format: &str, passphrase: &str, totp_code: Option<&str>) -> Option<ApiError> {
+    {
+        let mut last = state.last_key_export.lock().unwrap();
+        if let Some(at) = *last {
+            if at.elapsed() < KEY_EXPORT_COOLDOWN {
+                let wait = (KEY_EXPORT_COOLDOWN - at.elapsed()).as_secs();
+                wallets::audit_export(audit_format, "rate_limited");
+                return Some(
+                    ApiError::rate_limited(format!("Key export is rate limited; try again in {} seconds", wait))
+                        .code("rate_limited")
+                        .with("retry_after_seconds", wait),
+                );
             }
         }
+        *last = Some(Instant::now());
+    }
+
+    // Argon2id takes a moment and 64 MiB; keep it off the async workers
+    let (passphrase, totp_code) = (passphrase.to_string(), totp_code.map(str::to_string));
+    let checked = tokio::task::spawn_blocking(move || {
+        let secret = key_export::load()?;
+        Ok::<_, String>(key_export::check(secret.as_ref(), &passphrase, totp_code.as_deref(), chrono::Utc::now().timestamp()))
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|checked| checked);
+    match checked {
+        Ok(Ok(())) => None,
+        Ok(Err(denied)) => {
+            wallets::audit_export(audit_format, denied.outcome());
+            Some(ApiError::forbidden(denied.message()).code(denied.code()))
+        }
+        Err(e) => {
+            wallets::audit_export(audit_format, "failed");
+            Some(ApiError::internal(e).code("internal_error"))
+        }
     }
-    
-    // No wallet found
-    Json(json!({
-        "success": false,
-        "error": "No wallet found. Initialize with: fuego create"
-    })).into_response()
 }
 
-#[tokio::main]
-async fn main() {
-    let state = AppState {
-        default_network: "mainnet-beta".to_string(),
+// Export the local wallet key for use in other tools. Off by default; every attempt is audited.
+async fn export_wallet(
+    State(state): State<AppState>,
+    StrictJson(payload): StrictJson<WalletExportRequest>,
+) -> ApiResult {
+    if let Err(denied) = key_export::availability(state.allow_key_export, state.mode.is_dry_run()) {
+        return Err(ApiError::forbidden(denied.message()).code(denied.code()));
+    }
+
+    let format = wallets::ExportFormat::parse(&payload.format).map_err(|e| ApiError::bad_request(e).code("invalid_request"))?;
+
+    if let Some(refusal) = wallet_mismatch_refusal(payload.force) {
+        wallets::audit_export(format.as_str(), "wallet_mismatch");
+        return Err(refusal);
+    }
+
+    if let Some(refusal) = key_export_refusal(&state, format.as_str(), &payload.passphrase, payload.totp_code.as_deref()).await {
+        return Err(refusal);
+    }
+
+    let wallet = match fs::read_to_string(utils::fuego_home().join("wallet.json"))
+        .ok()
+        .and_then(|c| serde_json::from_str::<WalletStore>(&c).ok())
+    {
+        Some(w) => w,
+        None => {
+            wallets::audit_export(format.as_str(), "no_wallet");
+            return Err(ApiError::not_found("No wallet found at ~/.fuego/wallet.json").code("wallet_not_found"));
+        }
     };
 
-    let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST])
-        .allow_headers(Any)
-        .allow_origin(Any);
+    if payload.confirm_address != wallet.address {
+        wallets::audit_export(format.as_str(), "confirmation_mismatch");
+        return Err(ApiError::bad_request("confirm_address does not match the local wallet address")
+            .code("confirmation_mismatch"));
+    }
+
+    match wallets::export_keypair(&wallet.private_key, &wallet.address, &format) {
+        Ok(key) => {
+            wallets::audit_export(format.as_str(), "exported");
+            Ok(Json(json!({
+                "success": true,
+                "data": {
+                    "address": wallet.address,
+                    "format": format.as_str(),
+                    "key": key,
+                    "warning": "Anyone with this key controls the wallet. Store it offline and never paste it into chats or websites."
+                }
+            })))
+        }
+        Err(e) => {
+            wallets::audit_export(format.as_str(), "failed");
+            Err(ApiError::internal(e).code("internal_error"))
+        }
+    }
+}
+
+async fn list_wallets() -> ApiResult {
+    let mut profiles: Vec<serde_json::Value> = Vec::new();
+
+    // The signing profile is whatever key lives in ~/.fuego/wallet.json
+    let wallet_path = utils::fuego_home().join("wallet.json");
+    if let Ok(content) = fs::read_to_string(&wallet_path) {
+        if let Ok(wallet) = serde_json::from_str::<WalletStore>(&content) {
+            profiles.push(json!({
+                "address": wallet.address,
+                "label": "default",
+                "network": wallet.network,
+                "kind": "signing"
+            }));
+        }
+    }
+
+    match wallets::load_watch_wallets() {
+        Ok(watch_wallets) => {
+            for w in watch_wallets {
+                profiles.push(json!({
+                    "address": w.address,
+                    "label": w.label,
+                    "added_at": w.added_at,
+                    "kind": "watch"
+                }));
+            }
+        }
+        Err(e) => {
+            return Err(ApiError::internal(e).code("internal_error"));
+        }
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "wallets": profiles
+        }
+    })))
+}
+
+/// Every route with its middleware, ready to serve.
+fn app(state: AppState) -> Router {
+    let cors_policy = state.cors.clone();
+    let limits = state.limits;
+    let rate_limiter = state.rate_limiter.clone();
 
     let app = Router::new()
         .route("/", get(|| async { "Fuego Server 🔥" }))
         .route("/health", get(health_check))
+        .route("/version", get(get_version))
         .route("/network", get(get_default_network))
+        .route("/config", get(get_config))
         .route("/wallet-address", get(get_wallet_address))
+        .route("/wallets", get(list_wallets))
+        .route("/wallet/watch", post(add_watch_wallet))
+        .route("/wallet/export", post(export_wallet))
+        .route("/wallet/verify", get(verify_wallet))
+        .route("/alerts", get(get_alerts))
+        .route("/dashboard", get(get_dashboard))
+        .route("/auth/echo", post(auth_echo))
+        .route("/rpc-status", get(get_rpc_status))
+        .route("/tx-cache", get(get_tx_cache))
+        .route("/errors", get(list_error_codes))
+        .route("/transfer-quote", post(transfer_quote))
+        .route("/metrics", get(get_metrics))
+        .route("/admin/reload", post(admin_reload))
+        .route("/admin/reconcile", post(admin_reconcile))
+        .route("/admin/support-bundle", get(admin_support_bundle))
+        .route("/admin/backup", post(admin_backup))
         // READ endpoints
         .route("/latest-hash", post(get_latest_hash))
         .route("/sol-balance", post(get_sol_balance))
+        .route("/balances", post(get_balances))
+        .route("/balances-batch", post(get_balances_batch))
+        .route("/balance-at", post(get_balance_at))
         .route("/usdc-balance", post(get_usdc_balance))
         .route("/usdt-balance", post(get_usdt_balance))
+        .route("/pyusd-balance", post(get_pyusd_balance))
         .route("/all-transactions", post(get_all_transactions))
+        .route("/transaction", post(get_transaction_detail))
+        .route("/search-memos", post(search_memos))
+        .route("/stats/transfers", post(transfer_stats))
         .route("/tokens", post(get_tokens))
+        .route("/token-balance", post(get_token_balance))
+        .route("/token-balances", post(get_token_balances))
+        .route("/nft-holdings", post(get_nft_holdings))
+        .route("/cleanup-suggestions", post(get_cleanup_suggestions))
         // TRANSFER endpoints
         .route("/build-transfer-usdc", post(build_transfer_usdc))
         .route("/build-transfer-sol", post(build_transfer_sol))
+        .route("/build-sweep-sol", post(build_sweep_sol))
         .route("/build-transfer-usdt", post(build_transfer_usdt))
+        .route("/build-transfer-pyusd", post(build_transfer_pyusd))
+        .route("/build-transfer-token", post(build_transfer_token))
+        .route("/build-onboard", post(build_onboard))
+        .route("/build-timelocked-transfer", post(build_timelocked_transfer))
+        .route("/scheduled-transfers", get(list_scheduled_transfers))
+        .route("/scheduled-transfers/:id", get(get_scheduled_transfer))
+        .route("/scheduled-transfers/:id/signature", post(sign_scheduled_transfer))
+        .route("/scheduled-transfers/:id/cancel", post(cancel_scheduled_transfer))
         .route("/x402-purch", post(x402_purch))
+        .route("/x402-receipts", get(list_x402_receipts))
+        .route("/x402-receipts/:id/body", get(get_x402_receipt_body))
+        .route("/x402-inflight", get(list_x402_inflight))
+        .route("/x402-inflight/:id", get(get_x402_inflight))
+        .route("/x402-disputes", get(list_x402_disputes))
+        .route("/devnet/mint-tokens", post(devnet_mint_tokens))
         .route("/submit-transaction", post(submit_transaction))
         .route("/submit-versioned-transaction", post(submit_versioned_transaction))
-        .layer(cors)
-        .with_state(state);
+        .route("/submit-transactions", post(submit_transactions))
+        .route("/submit-queue", get(get_submit_queue))
+        .route("/submit-queue/:id", get(get_submit_queue_item))
+        .route("/pending-transactions", get(get_pending_transactions))
+        // SESSION endpoints
+        .route("/sessions", post(create_session))
+        .route("/sessions/:id", get(get_session).delete(revoke_session))
+        .route("/share-links", post(create_share_link).get(list_share_links))
+        .route("/share-links/:id", delete(revoke_share_link))
+        .route("/shared/:token", get(shared_scope))
+        .route("/shared/:token/history", get(shared_history))
+        .route("/shared/:token/balances", get(shared_balances))
+        .route("/shared/:token/transaction/:signature", get(shared_transaction));
+    // Embedded dashboard; headless deployments leave it out with FUEGO_UI=false
+    let app = if ui::enabled() {
+        app.route("/ui", get(ui::index))
+            .route("/ui/*path", get(ui::asset))
+            .route("/ui-config", get(get_ui_config))
+    } else {
+        app
+    };
+    // Test builds only; the route doesn't exist without the fault-injection feature
+    #[cfg(feature = "fault-injection")]
+    let app = app.route(
+        "/admin/faults",
+        get(admin_faults_get).post(admin_faults_set).delete(admin_faults_reset),
+    );
+    app.layer(axum::middleware::from_fn(rpc_timeout_scope))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), scrub_provider_urls))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), require_auth))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), trace_request))
+        .layer(tower_http::limit::RequestBodyLimitLayer::new(limits.max_body_bytes))
+        .layer(axum::middleware::from_fn_with_state(limits, request_limits))
+        .layer(axum::middleware::from_fn_with_state(rate_limiter, rate_limit))
+        .layer(cors_policy.layer())
+        .layer(axum::middleware::from_fn_with_state(cors_policy, cors_preflight))
+        .layer(
+            tower_http::trace::TraceLayer::new_for_http()
+                .make_span_with(logging::request_span)
+                .on_response(
+                    tower_http::trace::DefaultOnResponse::new()
+                        .level(tracing::Level::INFO)
+                        .latency_unit(tower_http::LatencyUnit::Millis),
+                ),
+        )
+        .layer(axum::middleware::from_fn(assign_request_id))
+        .with_state(state)
+}
+
+#[tokio::main]
+async fn main() {
+    // Informational flags print and exit before any state is set up
+    match std::env::args().nth(1).as_deref() {
+        Some("--version") | Some("-V") => {
+            println!("{}", build_info::version_line());
+            return;
+        }
+        Some("--print-default-config") => {
+            print!("{}", build_info::default_config_toml());
+            return;
+        }
+        Some("--doctor") => {
+            let report = json!({ "build": build_info::json(), "config_sources": build_info::config_sources() });
+            println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+            return;
+        }
+        Some("restore") | Some("backup") => {
+            let args: Vec<String> = std::env::args().skip(1).collect();
+            std::process::exit(backup::run_cli(&args));
+        }
+        Some("key-export") => {
+            let args: Vec<String> = std::env::args().skip(1).collect();
+            std::process::exit(key_export::run_cli(&args));
+        }
+        // The one flag that starts the server: `--network <name>` sets the default network
+        Some("--network") => {}
+        Some(other) => {
+            eprintln!("Unknown argument: {} (expected --version, --print-default-config, --doctor, --network <name>, restore, backup verify or key-export setup)", other);
+            std::process::exit(2);
+        }
+        None => {}
+    }
+    let cli_network = match std::env::args().nth(1).as_deref() {
+        Some("--network") => match std::env::args().nth(2) {
+            Some(network) => Some(network),
+            None => {
+                eprintln!("--network needs a network name, e.g. --network devnet");
+                std::process::exit(2);
+            }
+        },
+        _ => None,
+    };
+    logging::init();
+    let server_config = match config::ServerConfig::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid server config: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let tls = match &server_config.tls {
+        Some(config) => match tls::load(config).await {
+            Ok(rustls) => Some((rustls, config.clone())),
+            Err(e) => {
+                eprintln!("Invalid TLS setup: {}", e);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+    let listen = match server_config.listen_addr() {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!("Invalid listen address: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let mode = match ServerMode::load(&server_config) {
+        Ok(mode) => mode,
+        Err(e) => {
+            eprintln!("Invalid mode: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let networks = network::NetworkDefaults::load(cli_network, &server_config);
+
+    let rpc_pool = rpc::RpcPool::load(&server_config);
+    let egress = egress::EgressPolicy::from_env();
+    let alert_log = alerts::AlertLog::from_env(egress.clone());
+    let state = AppState {
+        default_network: networks.default_network,
+        require_explicit_network: networks.require_explicit,
+        default_network_source: networks.source,
+        mode,
+        sessions: sessions::SessionStore::default(),
+        require_sessions: std::env::var("FUEGO_REQUIRE_SESSIONS").map(|v| v == "true").unwrap_or(false),
+        memo_privacy: MemoPrivacy::from_env(),
+        alerts: alert_log.clone(),
+        egress,
+        auth: auth::Auth::load(&server_config),
+        anomaly: anomaly::AnomalyDetector::new(anomaly::AnomalyConfig::from_env(), alert_log),
+        rpc: rpc_pool.clone(),
+        providers: providers::Providers::load(&server_config),
+        commitment: commitment::CommitmentDefaults::from_env(),
+        builds: builds::BuildStore::default(),
+        onboard: onboard::OnboardBudget::new(onboard::OnboardConfig::from_env()),
+        faucet: faucet::DevnetFaucet::from_env(),
+        balance_at_max_scan: std::env::var("FUEGO_BALANCE_AT_MAX_SCAN")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(500),
+        balances_batch_max: std::env::var("FUEGO_BALANCES_BATCH_MAX")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(1000),
+        submit_queue: submit_queue::SubmitQueue::from_env(),
+        pending: pending::PendingTracker::default(),
+        mints: mints::MintCache::new(rpc_pool),
+        tx_cache: tx_cache::TxCache::from_env(),
+        blockhashes: blockhash_cache::BlockhashCache::from_env(),
+        fee_wallet: fee_wallet::FeeWalletGuard::from_env(),
+        strict_json: std::env::var("FUEGO_STRICT_JSON").map(|v| v == "true").unwrap_or(false),
+        allow_key_export: std::env::var("FUEGO_ALLOW_KEY_EXPORT").map(|v| v == "true").unwrap_or(false),
+        last_key_export: Arc::new(Mutex::new(None)),
+        timelocks: timelock::TimelockStore::load(),
+        share_links: share_links::ShareLinks::load(),
+        tracer: trace::Tracer::from_env(),
+        limits: limits::RequestLimits::load(&server_config),
+        rate_limiter: rate_limit::RateLimiter::load(&server_config),
+        cors: cors::CorsPolicy::load(&server_config.cors),
+        config: Arc::new(server_config),
+        listen,
+    };
+
+    // Off loopback the wallet is one request away from anyone on the network, so the traffic must
+    // be encrypted and authenticated
+    if !listen.ip().is_loopback() && (tls.is_none() || !state.auth.enabled()) {
+        eprintln!(
+            "Refusing to listen on {}: a non-loopback address needs both `tls` in the server config and an API key (FUEGO_API_KEY, FUEGO_AUTH_KEYS or auth_keys)",
+            listen
+        );
+        std::process::exit(2);
+    }
+
+    if state.rpc.network(&state.default_network).is_none() {
+        eprintln!(
+            "Unknown default network '{}' (from {}): use one of {}",
+            state.default_network,
+            state.default_network_source,
+            state.rpc.network_names().join(", ")
+        );
+        std::process::exit(2);
+    }
+
+    fee_wallet::spawn_balance_check(state.fee_wallet.clone(), state.rpc.clone(), state.alerts.clone());
+    if state.require_explicit_network {
+        println!("Default network: none, every request must name its network (require_explicit_network)");
+    } else {
+        println!("Default network: {} (from {})", state.default_network, state.default_network_source);
+    }
+
+    // Pre-warm mint metadata for the built-in tokens on the default network
+    {
+        let mints = state.mints.clone();
+        let network = state.default_network.clone();
+        let rpc_url = state.rpc.select_read(&network);
+        tokio::spawn(async move {
+            for (mint, decimals) in [(USDC_MINT, USDC_DECIMALS), (USDT_MINT, USDT_DECIMALS)] {
+                match mints.get(&rpc_url, &network, mint).await {
+                    // transfer_checked makes a mismatch fail on-chain rather than move the wrong amount
+                    Ok(info) if info.decimals != decimals => eprintln!(
+                        "WARNING: mint {} has {} decimals on {}, but fuego assumes {}; its transfers will fail until this is fixed",
+                        mint, info.decimals, network, decimals
+                    ),
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Could not pre-warm mint {}: {}", mint, e.message()),
+                }
+            }
+        });
+    }
+
+    state.submit_queue.spawn_workers(state.rpc.clone(), state.pending.clone(), state.mode.is_dry_run());
+    pending::spawn_tracker(state.pending.clone(), state.rpc.clone(), state.submit_queue.clone(), state.alerts.clone());
+    // Catch up on whatever settled while the server was down
+    if std::env::var("FUEGO_RECONCILE_ON_START").map(|v| v != "false").unwrap_or(true) {
+        let reconcile_state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = run_reconcile(&reconcile_state, ReconcileRequest::default()).await {
+                eprintln!("Startup reconciliation skipped: {}", e);
+            }
+        });
+    }
+    // Dry-run never broadcasts, so unlocked intents simply wait for a live run
+    if !state.mode.is_dry_run() {
+        timelock::spawn_scheduler(state.timelocks.clone(), state.rpc.clone(), state.alerts.clone(), load_local_keypair);
+    }
+
+    alerts::spawn_wallet_watch(state.alerts.clone(), utils::fuego_home().join("wallet.json"));
+
+    match verify_local_wallet() {
+        Some(Ok(verification)) if !verification.ok => {
+            println!("⚠️  Wallet mismatch: {}", verification.explain());
+            println!("    Signing endpoints refuse until this is fixed (or the request sets \"force\": true); see GET /wallet/verify");
+            state.alerts.emit(
+                "wallet_mismatch",
+                alerts::Severity::High,
+                verification.explain(),
+                json!({ "derived_address": verification.derived_address, "wallet_address": verification.wallet_address }),
+            );
+        }
+        Some(Err(e)) => println!("⚠️  {}", e),
+        _ => {}
+    }
+
+    let interrupted = receipts::mark_interrupted_inflight();
+    if interrupted > 0 {
+        println!("⚠️  {} x402 purchase(s) were interrupted by a restart; see GET /x402-inflight", interrupted);
+    }
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
-    println!("🔥 Fuego server running on http://{}", addr);
+    let mode = state.mode;
+    let submit_queue = state.submit_queue.clone();
+    let app = app(state);
+
+    let addr = listen;
+    println!("🔥 Fuego server running on {}://{}", if tls.is_some() { "https" } else { "http" }, addr);
+    println!("   {}", build_info::version_line());
+    tracing::info!(
+        version = build_info::VERSION,
+        git_commit = build_info::GIT_COMMIT,
+        git_dirty = build_info::git_dirty(),
+        features = ?build_info::features(),
+        "starting"
+    );
+    if ui::enabled() {
+        println!("   Dashboard: http://{}/ui", addr);
+    }
+    if mode.is_dry_run() {
+        println!("⚠️  DRY-RUN MODE: submissions and x402 payments are simulated, nothing is broadcast");
+    }
     println!("Endpoints:");
     println!("  READ:");
-    println!("    GET  /health - Health check");
+    println!("    GET  /health - Health check (?deep=true also checks the RPC and wallet file)");
+    println!("    GET  /version - Version, git commit, build time and enabled features");
     println!("    GET  /network - Get default network");
+    println!("    GET  /config - Effective server configuration, without secrets");
     println!("    GET  /wallet-address - Get local wallet address");
+    println!("    GET  /dashboard - Wallet, balances, recent transactions, pending and fees in one call");
+    println!("    GET  /ui - Embedded web dashboard (FUEGO_UI=false disables); GET /ui-config for its settings");
+    println!("    GET  /wallets - List signing and watch-only wallets");
+    println!("    POST /wallet/watch - Register a watch-only wallet (address + label)");
+    println!("    POST /wallet/export - Export the wallet key (requires FUEGO_ALLOW_KEY_EXPORT=true)");
+    println!("    GET  /wallet/verify - Check wallet.json against its own key and wallet-config.json");
+    println!("    GET  /alerts - Recent security alerts (session caps, wallet file changes)");
+    println!("    POST /auth/echo - Show how the server canonicalizes and verifies a signed request");
+    println!("    GET  /rpc-status - Per-endpoint RPC latency/error stats and read strategy");
+    println!("    GET  /tx-cache - Transaction cache size and hit rate");
+    println!("    GET  /errors - Every error code the server can return");
+    println!("    GET  /metrics - Prometheus gauges for pending/expired transactions and queue depth");
+    println!("    POST /admin/reload - Clear runtime caches (mint decimals/program) and re-read API keys");
+    println!("    POST /admin/reconcile - Re-sync the memo index and pending tracker with the chain");
+    println!("    GET  /admin/support-bundle - Zip of redacted diagnostics for bug reports");
+    println!("    POST /admin/backup - Passphrase-encrypted archive of ~/.fuego");
+    #[cfg(feature = "fault-injection")]
+    println!("    GET|POST|DELETE /admin/faults - Fault injection (test build)");
     println!("    POST /latest-hash - Get latest blockhash");
     println!("    POST /sol-balance - Get SOL balance");
+    println!("    POST /balances - SOL, USDC and USDT in one concurrent call");
+    println!("    POST /balances-batch - SOL balances for many addresses via getMultipleAccounts");
+    println!("    POST /balance-at - Reconstruct a SOL/token balance at a past slot or timestamp");
     println!("    POST /usdc-balance - Get USDC balance");
     println!("    POST /usdt-balance - Get USDT balance");
+    println!("    POST /pyusd-balance - Get PYUSD balance (Token-2022, summed across accounts)");
     println!("    POST /tokens - Get all SPL token accounts with balances");
+    println!("    POST /token-balance - Balance of any SPL/Token-2022 mint's associated token account");
+    println!("    POST /token-balances - Every token the wallet holds, across both token programs");
+    println!("    POST /nft-holdings - NFTs and compressed NFTs (DAS when available, else token accounts)");
+    println!("    POST /cleanup-suggestions - Empty token accounts (and dust) whose rent can be reclaimed");
     println!("  BUILD TRANSFERS:");
     println!("    POST /build-transfer-sol - Build unsigned SOL transfer (agent signs in script)");
+    println!("    POST /build-sweep-sol - Build a transfer of the whole SOL balance less fees and the rent-exempt minimum");
     println!("    POST /build-transfer-usdc - Build unsigned USDC transfer (agent signs in script)");
     println!("    POST /build-transfer-usdt - Build unsigned USDT transfer (agent signs in script)");
+    println!("    POST /build-transfer-pyusd - Build unsigned PYUSD transfer (Token-2022, creates recipient ATA if missing)");
+    println!("    POST /build-transfer-token - Build unsigned transfer of any SPL/Token-2022 mint (decimals read on-chain)");
+    println!("    POST /build-onboard - Build a sponsored transaction creating a new wallet's USDC account and funding it with SOL");
+    println!("    POST /build-timelocked-transfer - Durable-nonce SOL/USDC/USDT transfer submitted after unlock_at");
+    println!("    GET  /scheduled-transfers - Time-locked transfers and their state (?status=)");
+    println!("    GET  /scheduled-transfers/:id - One time-locked transfer");
+    println!("    POST /scheduled-transfers/:id/signature - Attach the sender's signature");
+    println!("    POST /scheduled-transfers/:id/cancel - Close the nonce account so the transfer can never land");
     println!("  X402:");
     println!("    POST /x402-purch - x402 Purch: WIP -- call Purch URL with order payload (Solana); returns final response");
+    println!("    GET  /x402-receipts - x402 purchase receipts (?yid=, ?yid_prefix=, ?unattributed=true)");
+    println!("    GET  /x402-receipts/:id/body - Fetch a stored x402 response body (store_response: true)");
+    println!("    GET  /x402-inflight - x402 purchases and their eventual outcomes (?status=)");
+    println!("    POST /devnet/mint-tokens - Mint the configured devnet test token (faucet)");
+    println!("    GET  /x402-inflight/:id - One x402 purchase by inflight_id");
+    println!("    GET  /x402-disputes - x402 receipts whose response and on-chain settlement disagree");
     println!("  SUBMIT:");
     println!("    POST /submit-transaction - Broadcast signed transaction (legacy format - fuego transfers)");
     println!("    POST /submit-versioned-transaction - Broadcast VersionedTransaction (Jupiter/v0 format)");
+    println!("    POST /submit-transactions - Queue signed transactions for paced bulk submission");
+    println!("    GET  /submit-queue - Queue depth and drain rate; /submit-queue/:id for one item");
+    println!("    GET  /pending-transactions - Tracked signatures, newest first, with their state");
+    println!("  SESSIONS:");
+    println!("    POST /sessions - Create a spending session (caps, expiry, allowed endpoints)");
+    println!("    GET  /sessions/:id - Show session consumption");
+    println!("    DELETE /sessions/:id - Revoke a session immediately");
+    println!("    POST /share-links - Create a signed, expiring read-only link to one wallet's history");
+    println!("    GET  /share-links - List share links");
+    println!("    DELETE /share-links/:id - Revoke a share link");
+    println!("    GET  /shared/:token[/history|/balances|/transaction/:signature] - Public views a share link grants");
     println!("  HISTORY:");
     println!("    POST /all-transactions - Get all transactions (unfiltered)");
-    println!("  TODO:");
-    println!("    POST /pyusd-balance - Get PYUSD (Token-2022) balance");
+    println!("    POST /transaction - Transaction detail with compute budget and fee breakdown");
+    println!("    POST /transfer-quote - Token-2022 transfer fee, amount received and gross-up for a net amount");
+    println!("    POST /search-memos - Search memos of transactions fuego submitted (local index)");
+    println!("    POST /stats/transfers - Confirmed outgoing transfer totals by counterparty, token and period");
+
+    // Connection addresses are what rate limiting keys on when proxies aren't trusted
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+    match tls {
+        Some((rustls, config)) => {
+            tls::reload_on_sighup(rustls.clone(), config);
+            let handle = axum_server::Handle::new();
+            let shutdown = handle.clone();
+            tokio::spawn(async move {
+                let _ = tokio::signal::ctrl_c().await;
+                shutdown.graceful_shutdown(None);
+            });
+            axum_server::bind_rustls(addr, rustls).handle(handle).serve(service).await.unwrap();
+        }
+        None => {
+            let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+            axum::serve(listener, service)
+                .with_graceful_shutdown(async {
+                    let _ = tokio::signal::ctrl_c().await;
+                })
+                .await
+                .unwrap();
+        }
+    }
+
+    // The queue is written through on every change; this final flush covers anything in between
+    submit_queue.persist();
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const FROM: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+    const TO: &str = "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T";
+
+    fn memo(token: &str, privacy: MemoPrivacy) -> String {
+        let amount = amount::TokenAmount::from_base_units(amount::Asset::parse(token), 1_500_000, 6);
+        build_memo(token, FROM, TO, &amount, "yid-1", Some("rent"), privacy).unwrap()
+    }
+
+    #[test]
+    fn minimal_memos_carry_no_address() {
+        for token in ["SOL", "USDC", "PYUSD", "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"] {
+            let memo = memo(token, MemoPrivacy::Minimal);
+            assert!(!memo.contains(FROM) && !memo.contains(TO), "{}", memo);
+            assert!(!memo.contains("|f:") && !memo.contains("|t:"), "{}", memo);
+            assert!(memo.contains("|a:1500000|yid:yid-1|n:rent"), "{}", memo);
+        }
+    }
+
+    #[test]
+    fn full_memos_name_both_addresses() {
+        let memo = memo("USDC", MemoPrivacy::Full);
+        assert_eq!(memo, format!("fuego|USDC|m:{}|f:{}|t:{}|a:1500000|yid:yid-1|n:rent", USDC_MINT, FROM, TO));
+    }
+
+    /// An RPC client whose every call gets the same answer, apart from the version check some
+    /// client versions make first.
+    fn answering(answer: Result<serde_json::Value, fn() -> solana_client::client_error::ClientError>) -> RpcClient {
+        struct Fixed(Result<serde_json::Value, fn() -> solana_client::client_error::ClientError>);
+        #[async_trait::async_trait]
+        impl solana_client::rpc_sender::RpcSender for Fixed {
+            async fn send(
+                &self,
+                request: solana_client::rpc_request::RpcRequest,
+                _: serde_json::Value,
+            ) -> solana_client::client_error::Result<serde_json::Value> {
+                match request {
+                    solana_client::rpc_request::RpcRequest::GetVersion => Ok(json!({ "solana-core": "3.1.9", "feature-set": 0 })),
+                    _ => self.0.clone().map_err(|error| error()),
+                }
+            }
+            fn get_transport_stats(&self) -> solana_client::rpc_sender::RpcTransportStats {
+                Default::default()
+            }
+            fn url(&self) -> String {
+                "http://fixed.invalid".to_string()
+            }
+        }
+        RpcClient::new_sender(Fixed(answer), solana_client::rpc_client::RpcClientConfig::with_commitment(CommitmentConfig::confirmed()))
+    }
+
+    fn rpc_error(code: i64, message: &str) -> solana_client::client_error::ClientError {
+        solana_client::rpc_request::RpcError::RpcResponseError {
+            code,
+            message: message.to_string(),
+            data: solana_client::rpc_request::RpcResponseErrorData::Empty,
+        }
+        .into()
+    }
+
+    #[tokio::test]
+    async fn a_missing_token_account_holds_zero() {
+        let ata = solana_sdk::pubkey::Pubkey::new_unique();
+        let missing = answering(Err(|| rpc_error(-32602, "Invalid param: could not find account")));
+        assert_eq!(token_balance_or_zero(&missing, &ata).await, Ok(0));
+        let funded = answering(Ok(json!({
+            "context": { "slot": 1 },
+            "value": { "amount": "2500", "decimals": 6, "uiAmount": 0.0025, "uiAmountString": "0.0025" }
+        })));
+        assert_eq!(token_balance_or_zero(&funded, &ata).await, Ok(2500));
+    }
+
+    #[tokio::test]
+    async fn only_a_missing_account_reads_as_zero() {
+        let ata = solana_sdk::pubkey::Pubkey::new_unique();
+        let failures: [fn() -> solana_client::client_error::ClientError; 3] = [
+            || rpc_error(-32602, "Invalid param: WrongSize"),
+            || rpc_error(-32005, "Node is behind by 42 slots"),
+            || solana_client::client_error::ClientErrorKind::Io(std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out")).into(),
+        ];
+        for failure in failures {
+            assert!(token_balance_or_zero(&answering(Err(failure)), &ata).await.is_err());
+        }
+    }
+
+    #[test]
+    fn notes_over_16_characters_are_refused_in_either_mode() {
+        let amount = amount::TokenAmount::lamports(1);
+        for privacy in [MemoPrivacy::Full, MemoPrivacy::Minimal] {
+            assert!(build_memo("SOL", FROM, TO, &amount, "yid", Some("seventeen chars!!"), privacy).is_err());
+        }
+    }
+
+    /// Dry-run state over the built-in endpoints with nothing read from or written to ~/.fuego.
+    fn test_state() -> AppState {
+        let config = config::ServerConfig::default();
+        let rpc = rpc::RpcPool::load(&config);
+        let egress = egress::EgressPolicy::from_env();
+        let alerts = alerts::AlertLog::from_env(egress.clone());
+        AppState {
+            default_network: "mainnet-beta".to_string(),
+            require_explicit_network: false,
+            default_network_source: "built-in",
+            mode: ServerMode::DryRun,
+            sessions: sessions::SessionStore::default(),
+            require_sessions: false,
+            memo_privacy: MemoPrivacy::Minimal,
+            alerts: alerts.clone(),
+            egress,
+            auth: auth::Auth::load(&config),
+            anomaly: anomaly::AnomalyDetector::new(anomaly::AnomalyConfig::from_env(), alerts),
+            rpc: rpc.clone(),
+            providers: providers::Providers::default(),
+            commitment: commitment::CommitmentDefaults::default(),
+            builds: builds::BuildStore::default(),
+            onboard: onboard::OnboardBudget::new(onboard::OnboardConfig::from_env()),
+            faucet: None,
+            balance_at_max_scan: 500,
+            balances_batch_max: 1000,
+            submit_queue: submit_queue::SubmitQueue::from_env(),
+            pending: pending::PendingTracker::default(),
+            mints: mints::MintCache::new(rpc),
+            tx_cache: tx_cache::TxCache::from_env(),
+            blockhashes: blockhash_cache::BlockhashCache::from_env(),
+            fee_wallet: fee_wallet::FeeWalletGuard::from_env(),
+            strict_json: false,
+            allow_key_export: false,
+            last_key_export: Arc::new(Mutex::new(None)),
+            timelocks: timelock::TimelockStore::default(),
+            share_links: share_links::ShareLinks::in_memory(&[7; 32]),
+            tracer: trace::Tracer::from_env(),
+            limits: limits::RequestLimits::load(&config),
+            rate_limiter: rate_limit::RateLimiter::load(&config),
+            cors: cors::CorsPolicy::load(&config.cors),
+            config: Arc::new(config),
+            listen: SocketAddr::from(([127, 0, 0, 1], 0)),
+        }
+    }
+
+    #[test]
+    fn an_omitted_network_gets_the_default() {
+        let mut state = test_state();
+        state.default_network = "devnet".to_string();
+        let mut network = String::new();
+        assert!(resolve_network(&state, &mut network).is_ok());
+        assert_eq!(network, "devnet");
+
+        // A named network is kept as is
+        let mut network = "testnet".to_string();
+        assert!(resolve_network(&state, &mut network).is_ok());
+        assert_eq!(network, "testnet");
+    }
+
+    #[test]
+    fn require_explicit_network_refuses_an_omitted_network() {
+        let mut state = test_state();
+        state.require_explicit_network = true;
+        let mut network = String::new();
+        let err = resolve_network(&state, &mut network).unwrap_err();
+        assert_eq!(err.code, "missing_network");
+        assert!(network.is_empty());
+
+        let mut network = "devnet".to_string();
+        assert!(resolve_network(&state, &mut network).is_ok());
+    }
+
+    #[test]
+    fn unknown_networks_are_refused() {
+        let state = test_state();
+        for name in ["mainnet", "mainnet-beta.evil.com", "DEVNET"] {
+            let mut network = name.to_string();
+            assert_eq!(resolve_network(&state, &mut network).unwrap_err().code, "invalid_network", "{}", name);
+        }
+    }
+
+    /// A router with only the request-id middleware, whose handler reports the id it saw.
+    async fn request_id_response(caller_id: Option<&str>, fail: bool) -> (Response, serde_json::Value) {
+        use tower::ServiceExt;
+        let handler = move || async move {
+            if fail {
+                ApiError::not_found("No session with that id").code("session_not_found").into_response()
+            } else {
+                Json(json!({ "seen": request_id::current() })).into_response()
+            }
+        };
+        let app: Router = Router::new().route("/", get(handler)).layer(axum::middleware::from_fn(assign_request_id));
+        let mut request = axum::http::Request::builder().uri("/");
+        if let Some(id) = caller_id {
+            request = request.header(request_id::HEADER, id);
+        }
+        let response = app.oneshot(request.body(axum::body::Body::empty()).unwrap()).await.unwrap();
+        let (parts, body) = response.into_parts();
+        let bytes = axum::body::to_bytes(body, 64 * 1024).await.unwrap();
+        (Response::from_parts(parts, axum::body::Body::empty()), serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn a_caller_request_id_reaches_the_handler_and_is_echoed() {
+        let (response, body) = request_id_response(Some("agent-run-42"), false).await;
+        assert_eq!(response.headers()[request_id::HEADER], "agent-run-42");
+        assert_eq!(body["seen"], "agent-run-42");
+    }
+
+    #[tokio::test]
+    async fn a_generated_request_id_is_echoed_and_put_in_error_bodies() {
+        let (response, body) = request_id_response(None, true).await;
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+        let id = response.headers()[request_id::HEADER].to_str().unwrap().to_string();
+        assert!(uuid::Uuid::parse_str(&id).is_ok(), "{}", id);
+        assert_eq!(body["request_id"], id);
+
+        let (response, _) = request_id_response(Some("has spaces"), true).await;
+        assert_ne!(response.headers()[request_id::HEADER], "has spaces");
+    }
+
+    #[tokio::test]
+    async fn responses_over_the_buffer_limit_pass_through_unread() {
+        let (parts, bytes) = buffer_response(Json(json!({ "success": true })).into_response()).await.unwrap();
+        assert_eq!(parts.status, axum::http::StatusCode::OK);
+        assert_eq!(&bytes[..], br#"{"success":true}"#);
+
+        let big = vec![b'x'; MAX_BUFFERED_RESPONSE_BYTES as usize + 1];
+        let response = buffer_response(Response::new(axum::body::Body::from(big.clone()))).await.unwrap_err();
+        let body = axum::body::to_bytes(response.into_body(), big.len()).await.unwrap();
+        assert_eq!(body.len(), big.len());
+    }
+
+    #[test]
+    fn strict_parsing_names_typos_in_a_real_transfer_request() {
+        let input = json!({
+            "network": "devnet",
+            "rpc_url": null,
+            "from_address": FROM,
+            "to_address": TO,
+            "ammount": "0.5",
+            "yid": "yid-1",
+            "notes": null,
+            "extra_instructions": [{ "program_id": FROM, "acounts": [] }]
+        });
+        let (_, unknown) = strict_json::parse::<TransferSolRequest>(&input).unwrap();
+        assert_eq!(unknown, vec!["ammount", "extra_instructions[0].acounts"]);
+    }
+
+    /// A JSON-RPC node on an ephemeral port. Each call waits `delay`, then gets `answer(method)`:
+    /// `{ "result": ... }` or `{ "error": ... }`. getVersion always answers.
+    async fn mock_rpc(delay: Duration, answer: fn(&str) -> serde_json::Value) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let node = Router::new().route(
+            "/",
+            post(move |Json(call): Json<serde_json::Value>| async move {
+                tokio::time::sleep(delay).await;
+                let method = call["method"].as_str().unwrap_or_default();
+                let mut body = match method {
+                    "getVersion" => json!({ "result": { "solana-core": "3.1.9", "feature-set": 0 } }),
+                    _ => answer(method),
+                };
+                body["jsonrpc"] = json!("2.0");
+                body["id"] = call["id"].clone();
+                Json(body)
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, node).await.unwrap() });
+        url
+    }
+
+    /// `test_state` with devnet served by `url`.
+    fn state_on(url: &str) -> AppState {
+        let mut config = config::ServerConfig::default();
+        config.rpc_endpoints.insert("devnet".to_string(), vec![url.to_string()]);
+        let mut state = test_state();
+        state.rpc = rpc::RpcPool::load(&config);
+        state.mints = mints::MintCache::new(state.rpc.clone());
+        state
+    }
+
+    fn post_json(uri: &str, body: serde_json::Value) -> axum::http::Request<axum::body::Body> {
+        axum::http::Request::post(uri)
+            .header(axum::http::header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    // A blocking RPC client called on this single-threaded runtime panics in block_in_place, and
+    // one that blocked the thread would hold /health up behind the slow node
+    #[tokio::test]
+    async fn health_answers_while_balance_reads_wait_on_a_slow_rpc() {
+        use tower::ServiceExt;
+
+        let url = mock_rpc(Duration::from_millis(1_000), |_| json!({ "result": { "context": { "slot": 1 }, "value": 5 } })).await;
+        let app = app(state_on(&url));
+        let reads: Vec<_> = (0..50)
+            .map(|_| tokio::spawn(app.clone().oneshot(post_json("/sol-balance", json!({ "network": "devnet", "address": FROM })))))
+            .collect();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let started = Instant::now();
+        let health = app.clone().oneshot(axum::http::Request::get("/health").body(axum::body::Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(health.status(), axum::http::StatusCode::OK);
+        assert!(started.elapsed() < Duration::from_millis(250), "{:?}", started.elapsed());
+
+        for read in reads {
+            assert_eq!(read.await.unwrap().unwrap().status(), axum::http::StatusCode::OK);
+        }
+    }
 
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
-}
\ No newline at end of file
+    /// Status and JSON body of one request through the full router.
+    async fn call(state: AppState, request: axum::http::Request<axum::body::Body>) -> (axum::http::StatusCode, serde_json::Value) {
+        use tower::ServiceExt;
+
+        let response = app(state).oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), 64 * 1024).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn blockhash(_: &str) -> serde_json::Value {
+        json!({ "result": { "context": { "slot": 1 }, "value": { "blockhash": "11111111111111111111111111111111", "lastValidBlockHeight": 100 } } })
+    }
+
+    fn node_error(_: &str) -> serde_json::Value {
+        json!({ "error": { "code": -32005, "message": "Node is behind" } })
+    }
+
+    #[tokio::test]
+    async fn bad_input_answers_400_with_its_code() {
+        let url = mock_rpc(Duration::ZERO, blockhash).await;
+
+        let (status, body) = call(state_on(&url), post_json("/sol-balance", json!({ "network": "devnet", "address": "not-a-key" }))).await;
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_address");
+
+        let transfer = json!({ "network": "devnet", "from_address": "not-a-key", "to_address": TO, "amount": "1", "yid": "yid-1" });
+        let (status, body) = call(state_on(&url), post_json("/build-transfer-usdc", transfer)).await;
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_address");
+
+        let (status, body) = call(state_on(&url), post_json("/sol-balance", json!({ "network": "nowhere", "address": FROM }))).await;
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST, "{}", body);
+        assert_eq!(body["success"], false);
+    }
+
+    #[tokio::test]
+    async fn an_unknown_scheduled_transfer_answers_404() {
+        let request = axum::http::Request::get("/scheduled-transfers/missing").body(axum::body::Body::empty()).unwrap();
+        let (status, body) = call(test_state(), request).await;
+        assert_eq!(status, axum::http::StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "scheduled_transfer_not_found");
+    }
+
+    #[tokio::test]
+    async fn an_rpc_error_answers_502() {
+        let url = mock_rpc(Duration::ZERO, node_error).await;
+
+        let (status, body) = call(state_on(&url), post_json("/sol-balance", json!({ "network": "devnet", "address": FROM }))).await;
+        assert_eq!(status, axum::http::StatusCode::BAD_GATEWAY, "{}", body);
+        assert_eq!(body["code"], "rpc_error");
+
+        let transfer = json!({ "network": "devnet", "from_address": FROM, "to_address": TO, "amount": "1", "yid": "yid-1" });
+        let (status, body) = call(state_on(&url), post_json("/build-transfer-usdc", transfer)).await;
+        assert_eq!(status, axum::http::StatusCode::BAD_GATEWAY, "{}", body);
+        assert_eq!(body["code"], "blockhash_fetch_failed");
+    }
+
+    #[tokio::test]
+    async fn a_slow_rpc_answers_504_past_timeout_ms() {
+        let url = mock_rpc(Duration::from_millis(1_000), blockhash).await;
+
+        let read = json!({ "network": "devnet", "address": FROM, "timeout_ms": 100 });
+        let (status, body) = call(state_on(&url), post_json("/sol-balance", read)).await;
+        assert_eq!(status, axum::http::StatusCode::GATEWAY_TIMEOUT, "{}", body);
+        assert_eq!(body["code"], "rpc_timeout");
+
+        let transfer = json!({ "network": "devnet", "from_address": FROM, "to_address": TO, "amount": "1", "yid": "yid-1", "timeout_ms": 100 });
+        let (status, body) = call(state_on(&url), post_json("/build-transfer-usdc", transfer)).await;
+        assert_eq!(status, axum::http::StatusCode::GATEWAY_TIMEOUT, "{}", body);
+        assert_eq!(body["code"], "rpc_timeout");
+    }
+
+    #[tokio::test]
+    async fn fuego_client_round_trips_through_the_router() {
+        let url = mock_rpc(Duration::ZERO, |method| match method {
+            "getBalance" => json!({ "result": { "context": { "slot": 1 }, "value": 5_000_000_000u64 } }),
+            _ => blockhash(method),
+        })
+        .await;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let service = app(state_on(&url)).into_make_service_with_connect_info::<SocketAddr>();
+        tokio::spawn(async move { axum::serve(listener, service).await.unwrap() });
+        let fuego = fuego_client::FuegoClient::builder().base_url(base_url).build().unwrap();
+
+        assert_eq!(fuego.health().await.unwrap()["status"], "healthy");
+
+        let read = GetBalanceRequest { network: "devnet".into(), address: FROM.into(), ..Default::default() };
+        let balance = fuego.sol_balance(&read).await.unwrap();
+        assert_eq!(balance["lamports"], 5_000_000_000u64);
+        assert_eq!(balance["network"], "devnet");
+
+        let transfer = TransferUsdcRequest {
+            network: "devnet".into(),
+            from_address: "not-a-key".into(),
+            to_address: TO.into(),
+            amount: "1".into(),
+            yid: "yid-1".into(),
+            ..Default::default()
+        };
+        match fuego.build_transfer_usdc(&transfer).await.unwrap_err() {
+            fuego_client::FuegoError::Api { code, .. } => assert_eq!(code.as_deref(), Some("invalid_address")),
+            other => panic!("{}", other),
+        }
+    }
+
+    // What fuego-client sends is exactly what the handlers read: a default body of each shared
+    // type, serialized, parses strictly with no unknown fields
+    #[test]
+    fn shared_request_types_parse_strictly() {
+        fn round_trip<T: Default + Serialize + serde::de::DeserializeOwned>() -> Vec<String> {
+            let sent = serde_json::to_value(T::default()).unwrap();
+            strict_json::parse::<T>(&sent).unwrap().1
+        }
+        assert!(round_trip::<GetBalanceRequest>().is_empty());
+        assert!(round_trip::<MintBalanceRequest>().is_empty());
+        assert!(round_trip::<TokenBalancesRequest>().is_empty());
+        assert!(round_trip::<TransferSolRequest>().is_empty());
+        assert!(round_trip::<TransferPyusdRequest>().is_empty());
+        assert!(round_trip::<TransferTokenRequest>().is_empty());
+        assert!(round_trip::<SubmitTransactionRequest>().is_empty());
+        assert!(round_trip::<TransferQuoteRequest>().is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+//! Local index of memos from transactions fuego has submitted, appended to
+//! ~/.fuego/memo-index.jsonl so memo searches never touch the RPC.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::utils::fuego_home;
+
+const MEMO_PROGRAMS: &[&str] = &[
+    "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr",
+    "Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo",
+];
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IndexedMemo {
+    pub signature: String,
+    pub network: String,
+    /// Full memo text as sent
+    pub memo: String,
+    /// Fields parsed from a fuego-formatted memo (fuego|TOKEN|m:|f:|t:|a:|yid:|n:). TOKEN is the
+    /// symbol as written, or the mint address for tokens the registry doesn't know
+    pub token: Option<String>,
+    /// Mint from the memo's m: field; for memos written before it existed, the registry's mint for
+    /// the symbol (or the token field itself when that is a mint address). None for SOL
+    #[serde(default)]
+    pub mint: Option<String>,
+    /// Token for display, resolved on load: the registry's current symbol for the mint, else the
+    /// token field as written
+    #[serde(default, skip_deserializing)]
+    pub symbol: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// Amount in base units
+    pub amount: Option<String>,
+    pub yid: Option<String>,
+    pub notes: Option<String>,
+    /// Which path submitted the transaction, or "backfill" for memos read back from chain history
+    pub source: String,
+    pub indexed_at: i64,
+    /// On-chain block time; only known for backfilled memos
+    #[serde(default)]
+    pub block_time: Option<i64>,
+    /// Reconciliation found no trace of the signature on-chain. Kept in memo-orphans.json, not
+    /// in the index, so it can be cleared if the signature turns up later.
+    #[serde(default, skip_deserializing)]
+    pub orphaned: bool,
+}
+
+impl IndexedMemo {
+    /// Whether `token` (a symbol or a mint address) names this memo's token.
+    pub fn matches_token(&self, token: &str) -> bool {
+        self.mint.as_deref() == Some(token)
+            || [&self.symbol, &self.token].iter().any(|t| t.as_deref().map(|t| t.eq_ignore_ascii_case(token)).unwrap_or(false))
+    }
+}
+
+/// Mint for a token field written without m:: a registered symbol's mint, or the field itself
+/// when it is already a mint address.
+fn legacy_mint(token: &str) -> Option<String> {
+    if let Some(mint) = crate::registry::token_mint(token) {
+        return Some(mint.to_string());
+    }
+    let is_address = (32..=44).contains(&token.len()) && bs58::decode(token).into_vec().map(|b| b.len() == 32).unwrap_or(false);
+    is_address.then(|| token.to_string())
+}
+
+fn index_path() -> PathBuf {
+    fuego_home().join("memo-index.jsonl")
+}
+
+fn orphans_path() -> PathBuf {
+    fuego_home().join("memo-orphans.json")
+}
+
+/// Memo texts from a transaction's instructions, given its account keys and
+/// (program id index, data) per instruction.
+pub fn extract_memos(account_keys: &[String], instructions: &[(usize, Vec<u8>)]) -> Vec<String> {
+    instructions
+        .iter()
+        .filter(|(program, _)| {
+            account_keys
+                .get(*program)
+                .map(|key| MEMO_PROGRAMS.contains(&key.as_str()))
+                .unwrap_or(false)
+        })
+        .filter_map(|(_, data)| String::from_utf8(data.clone()).ok())
+        .collect()
+}
+
+pub fn memos_from_legacy(tx: &solana_transaction::Transaction) -> Vec<String> {
+    let keys: Vec<String> = tx.message.account_keys.iter().map(|k| k.to_string()).collect();
+    let instructions: Vec<(usize, Vec<u8>)> = tx
+        .message
+        .instructions
+        .iter()
+        .map(|ix| (ix.program_id_index as usize, ix.data.clone()))
+        .collect();
+    extract_memos(&keys, &instructions)
+}
+
+pub fn memos_from_versioned(tx: &solana_transaction::versioned::VersionedTransaction) -> Vec<String> {
+    // Program ids are always static keys, so lookup-table addresses never matter here
+    let keys: Vec<String> = tx.message.static_account_keys().iter().map(|k| k.to_string()).collect();
+    let instructions: Vec<(usize, Vec<u8>)> = tx
+        .message
+        .instructions()
+        .iter()
+        .map(|ix| (ix.program_id_index as usize, ix.data.clone()))
+        .collect();
+    extract_memos(&keys, &instructions)
+}
+
+/// Record every memo in a submitted transaction. Index failures are logged, never surfaced.
+pub fn record(signature: &str, network: &str, memos: Vec<String>, source: &str) {
+    append(signature, network, memos, source, None);
+}
+
+/// Record memos of a confirmed transaction found in chain history rather than submitted here.
+pub fn record_backfill(signature: &str, network: &str, memos: Vec<String>, block_time: Option<i64>) {
+    append(signature, network, memos, BACKFILL_SOURCE, block_time);
+}
+
+pub const BACKFILL_SOURCE: &str = "backfill";
+
+fn append(signature: &str, network: &str, memos: Vec<String>, source: &str, block_time: Option<i64>) {
+    if memos.is_empty() {
+        return;
+    }
+    let mut lines = String::new();
+    for memo in memos {
+        let mut entry = IndexedMemo {
+            signature: signature.to_string(),
+            network: network.to_string(),
+            memo: memo.clone(),
+            token: None,
+            mint: None,
+            symbol: None,
+            from: None,
+            to: None,
+            amount: None,
+            yid: None,
+            notes: None,
+            source: source.to_string(),
+            indexed_at: chrono::Utc::now().timestamp(),
+            block_time,
+            orphaned: false,
+        };
+        let mut parts = memo.split('|');
+        if parts.next() == Some("fuego") {
+            entry.token = parts.next().map(str::to_string);
+            for part in parts {
+                match part.split_once(':') {
+                    Some(("m", v)) => entry.mint = Some(v.to_string()),
+                    Some(("f", v)) => entry.from = Some(v.to_string()),
+                    Some(("t", v)) => entry.to = Some(v.to_string()),
+                    Some(("a", v)) => entry.amount = Some(v.to_string()),
+                    Some(("yid", v)) => entry.yid = Some(v.to_string()),
+                    Some(("n", v)) => entry.notes = Some(v.to_string()),
+                    _ => {}
+                }
+            }
+            if entry.mint.is_none() {
+                entry.mint = entry.token.as_deref().and_then(legacy_mint);
+            }
+        }
+        if let Ok(line) = serde_json::to_string(&entry) {
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+    }
+
+    let result = fs::create_dir_all(fuego_home()).and_then(|_| {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(index_path())
+            .and_then(|mut f| f.write_all(lines.as_bytes()))
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to index memo for {}: {}", signature, e);
+    }
+}
+
+/// Every indexed memo, oldest first, with orphan flags applied. Malformed lines are skipped.
+pub fn load() -> Vec<IndexedMemo> {
+    let orphans = load_orphans();
+    fs::read_to_string(index_path())
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|l| serde_json::from_str::<IndexedMemo>(l).ok())
+                .map(|mut m| {
+                    m.orphaned = orphans.contains(&m.signature);
+                    if m.mint.is_none() {
+                        m.mint = m.token.as_deref().and_then(legacy_mint);
+                    }
+                    m.symbol = m
+                        .mint
+                        .as_deref()
+                        .and_then(crate::registry::token_symbol)
+                        .map(str::to_string)
+                        .or_else(|| m.token.clone());
+                    m
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Signatures flagged as missing from the chain by the last reconciliations.
+pub fn load_orphans() -> HashSet<String> {
+    fs::read_to_string(orphans_path())
+        .ok()
+        .and_then(|content| serde_json::from_str::<Vec<String>>(&content).ok())
+        .map(|list| list.into_iter().collect())
+        .unwrap_or_default()
+}
+
+pub fn save_orphans(orphans: &HashSet<String>) -> Result<(), String> {
+    let mut list: Vec<&String> = orphans.iter().collect();
+    list.sort();
+    fs::create_dir_all(fuego_home()).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(&list).map_err(|e| e.to_string())?;
+    fs::write(orphans_path(), content).map_err(|e| e.to_string())
+}
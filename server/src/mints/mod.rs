@@ -0,0 +1,189 @@
+//! Process-wide mint metadata cache: decimals and owning token program per (network, mint).
+//! Known mints are cached for a long time; mints that don't exist are cached briefly; RPC
+//! failures are never cached so a transient error can't poison later lookups.
+
+use serde::Serialize;
+use solana_client::rpc_config::CommitmentConfig;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::rpc::RpcPool;
+use crate::utils::string_to_pub_key;
+
+const KNOWN_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const UNKNOWN_TTL: Duration = Duration::from_secs(60);
+
+pub const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const TOKEN_2022_PROGRAM: &str = "TokenzQdBNbLqP5VX4wAn2ZfKNPq2E7UC9yW5HHDH4n";
+
+/// Short name of a token program for responses: "spl-token" or "token-2022".
+pub fn program_label(program_id: &Pubkey) -> &'static str {
+    if program_id.to_string() == TOKEN_2022_PROGRAM {
+        "token-2022"
+    } else {
+        "spl-token"
+    }
+}
+
+/// Size of the base SPL mint layout; Token-2022 mints with extensions are longer.
+const MINT_LEN: usize = 82;
+/// Offset of the decimals byte in the mint layout.
+const DECIMALS_OFFSET: usize = 44;
+
+#[derive(Clone, Serialize)]
+pub struct MintInfo {
+    pub decimals: u8,
+    /// Token program that owns the mint (spl-token or Token-2022)
+    pub program_id: String,
+}
+
+#[derive(Clone)]
+enum Entry {
+    Known(MintInfo),
+    /// The account does not exist or is not a token mint
+    Unknown,
+}
+
+#[derive(Debug)]
+pub enum MintLookupError {
+    InvalidMint,
+    UnknownMint,
+    Rpc(String),
+}
+
+impl MintLookupError {
+    pub fn message(&self) -> String {
+        match self {
+            MintLookupError::InvalidMint => "Invalid mint address".to_string(),
+            MintLookupError::UnknownMint => "Mint not found (or not a token mint) on this network".to_string(),
+            MintLookupError::Rpc(e) => format!("Failed to fetch mint: {}", e),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            MintLookupError::InvalidMint => "invalid_mint",
+            MintLookupError::UnknownMint => "unknown_mint",
+            MintLookupError::Rpc(_) => "rpc_error",
+        }
+    }
+}
+
+/// What a fetched mint account is: a mint of the token program that owns it, or not a mint at all.
+fn classify(account: Option<&Account>) -> Entry {
+    let Some(account) = account else {
+        return Entry::Unknown;
+    };
+    let owner = account.owner.to_string();
+    let is_token_program = owner == TOKEN_PROGRAM || owner == TOKEN_2022_PROGRAM;
+    if is_token_program && account.data.len() >= MINT_LEN {
+        Entry::Known(MintInfo {
+            decimals: account.data[DECIMALS_OFFSET],
+            program_id: owner,
+        })
+    } else {
+        Entry::Unknown
+    }
+}
+
+/// Cached lookups keyed by (network, mint), with the time each was fetched.
+type MintEntries = HashMap<(String, String), (Entry, Instant)>;
+
+#[derive(Clone)]
+pub struct MintCache {
+    inner: Arc<Mutex<MintEntries>>,
+    rpc: RpcPool,
+}
+
+impl MintCache {
+    /// Lookups go through `rpc`, so they fail over and respect its circuit breakers.
+    pub fn new(rpc: RpcPool) -> Self {
+        MintCache { inner: Arc::default(), rpc }
+    }
+
+    /// Decimals and token program for a mint, from cache or the given RPC endpoint.
+    pub async fn get(&self, rpc_url: &str, network: &str, mint: &str) -> Result<MintInfo, MintLookupError> {
+        let key = (network.to_string(), mint.to_string());
+        if let Some((entry, fetched)) = self.inner.lock().unwrap().get(&key) {
+            match entry {
+                Entry::Known(info) if fetched.elapsed() < KNOWN_TTL => return Ok(info.clone()),
+                Entry::Unknown if fetched.elapsed() < UNKNOWN_TTL => return Err(MintLookupError::UnknownMint),
+                _ => {}
+            }
+        }
+
+        let pubkey = string_to_pub_key(mint).map_err(|_| MintLookupError::InvalidMint)?;
+        let rpc = self.rpc.client(network, rpc_url, CommitmentConfig::confirmed());
+        let account = rpc
+            .get_account_with_commitment(&pubkey, CommitmentConfig::confirmed())
+            .await
+            .map_err(|e| MintLookupError::Rpc(e.to_string()))?
+            .value;
+
+        let entry = classify(account.as_ref());
+        self.inner.lock().unwrap().insert(key, (entry.clone(), Instant::now()));
+        match entry {
+            Entry::Known(info) => Ok(info),
+            Entry::Unknown => Err(MintLookupError::UnknownMint),
+        }
+    }
+
+    /// Program that owns the mint (spl-token or Token-2022), which token accounts and instructions
+    /// must be derived under. Shares the decimals cache entry.
+    pub async fn token_program(&self, rpc_url: &str, network: &str, mint: &str) -> Result<Pubkey, MintLookupError> {
+        let info = self.get(rpc_url, network, mint).await?;
+        string_to_pub_key(&info.program_id).map_err(|_| MintLookupError::UnknownMint)
+    }
+
+    /// Drop every cached entry; returns how many were cleared.
+    pub fn invalidate_all(&self) -> usize {
+        let mut cache = self.inner.lock().unwrap();
+        let count = cache.len();
+        cache.clear();
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mint(owner: &str, len: usize, decimals: u8) -> Account {
+        let mut data = vec![0; len];
+        if len > DECIMALS_OFFSET {
+            data[DECIMALS_OFFSET] = decimals;
+        }
+        Account { lamports: 1_461_600, data, owner: string_to_pub_key(owner).unwrap(), executable: false, rent_epoch: 0 }
+    }
+
+    fn program_of(account: &Account) -> Option<(String, u8)> {
+        match classify(Some(account)) {
+            Entry::Known(info) => Some((info.program_id, info.decimals)),
+            Entry::Unknown => None,
+        }
+    }
+
+    #[test]
+    fn the_owner_decides_spl_or_token_2022() {
+        assert_eq!(program_of(&mint(TOKEN_PROGRAM, MINT_LEN, 6)), Some((TOKEN_PROGRAM.to_string(), 6)));
+        assert_eq!(program_of(&mint(TOKEN_2022_PROGRAM, MINT_LEN, 9)), Some((TOKEN_2022_PROGRAM.to_string(), 9)));
+        // Extensions make Token-2022 mints longer; the base layout still holds the decimals
+        assert_eq!(program_of(&mint(TOKEN_2022_PROGRAM, 234, 2)), Some((TOKEN_2022_PROGRAM.to_string(), 2)));
+        let label = |program: &str| program_label(&string_to_pub_key(program).unwrap());
+        assert_eq!(label(TOKEN_PROGRAM), "spl-token");
+        assert_eq!(label(TOKEN_2022_PROGRAM), "token-2022");
+    }
+
+    #[test]
+    fn accounts_of_other_programs_or_too_short_are_not_mints() {
+        // A mint-shaped account owned by the system program
+        assert_eq!(program_of(&mint("11111111111111111111111111111111", MINT_LEN, 6)), None);
+        assert_eq!(program_of(&mint("BPFLoaderUpgradeab1e11111111111111111111111", MINT_LEN, 6)), None);
+        assert_eq!(program_of(&mint(TOKEN_PROGRAM, MINT_LEN - 1, 6)), None);
+        assert!(matches!(classify(None), Entry::Unknown));
+    }
+}
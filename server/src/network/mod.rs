@@ -0,0 +1,141 @@
+//! Which network a request means when it doesn't name one. The default comes from
+//! `--network <name>`, then FUEGO_DEFAULT_NETWORK, then `default_network` in the server config,
+//! then mainnet-beta. With `require_explicit_network` on, requests that omit the network are
+//! refused instead, so a missing field can never turn into a mainnet call.
+
+use std::str::FromStr;
+
+pub const FALLBACK_NETWORK: &str = "mainnet-beta";
+
+/// A cluster a request can name. Only these reach the RPC layer: a name is never pasted into a
+/// URL unless it is one of the public clusters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Network {
+    MainnetBeta,
+    Devnet,
+    Testnet,
+    /// A name the operator gave endpoints in FUEGO_RPC_ENDPOINTS (localnet, say), with its first URL
+    Custom(String),
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    /// The public clusters only; custom names need the RPC configuration, see `RpcPool::network`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "mainnet-beta" => Ok(Network::MainnetBeta),
+            "devnet" => Ok(Network::Devnet),
+            "testnet" => Ok(Network::Testnet),
+            _ => Err(format!("Unknown network '{}': use mainnet-beta, devnet or testnet", name)),
+        }
+    }
+}
+
+impl Network {
+    /// Solana's public RPC endpoint; custom networks have only what was configured.
+    pub fn public_endpoint(&self) -> Option<&'static str> {
+        match self {
+            Network::MainnetBeta => Some("https://api.mainnet-beta.solana.com"),
+            Network::Devnet => Some("https://api.devnet.solana.com"),
+            Network::Testnet => Some("https://api.testnet.solana.com"),
+            Network::Custom(_) => None,
+        }
+    }
+
+    /// Solana Explorer link for a transaction. The explorer reaches a custom network itself, so it
+    /// only gets the URL of a local validator; any other URL may carry an API key.
+    pub fn explorer_tx_url(&self, signature: &str) -> String {
+        let base = format!("https://explorer.solana.com/tx/{}", signature);
+        let cluster = match self {
+            Network::MainnetBeta => "mainnet-beta",
+            Network::Devnet => "devnet",
+            Network::Testnet => "testnet",
+            Network::Custom(url) => {
+                let local = reqwest::Url::parse(url)
+                    .map(|u| matches!(u.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")))
+                    .unwrap_or(false);
+                if local {
+                    if let Ok(link) = reqwest::Url::parse_with_params(&base, &[("cluster", "custom"), ("customUrl", url)]) {
+                        return link.to_string();
+                    }
+                }
+                "custom"
+            }
+        };
+        format!("{}?cluster={}", base, cluster)
+    }
+}
+
+pub struct NetworkDefaults {
+    pub default_network: String,
+    pub require_explicit: bool,
+    /// Where default_network came from: "cli", "env", "config" or "built-in"
+    pub source: &'static str,
+}
+
+impl NetworkDefaults {
+    pub fn load(cli_network: Option<String>, config: &crate::config::ServerConfig) -> Self {
+        let nonempty = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+        let (default_network, source) = if let Some(network) = nonempty(cli_network) {
+            (network, "cli")
+        } else if let Some(network) = nonempty(std::env::var("FUEGO_DEFAULT_NETWORK").ok()) {
+            (network, "env")
+        } else if let Some(network) = nonempty(config.default_network.clone()) {
+            (network, "config")
+        } else {
+            (FALLBACK_NETWORK.to_string(), "built-in")
+        };
+        let require_explicit = match std::env::var("FUEGO_REQUIRE_EXPLICIT_NETWORK") {
+            Ok(value) => value == "true",
+            Err(_) => config.require_explicit_network.unwrap_or(false),
+        };
+
+        NetworkDefaults { default_network, require_explicit, source }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_exact_cluster_names_parse() {
+        assert_eq!("mainnet-beta".parse::<Network>(), Ok(Network::MainnetBeta));
+        assert_eq!("devnet".parse::<Network>(), Ok(Network::Devnet));
+        assert_eq!("testnet".parse::<Network>(), Ok(Network::Testnet));
+        for name in [
+            "mainnet-beta.evil.com",
+            "evil.com/mainnet-beta",
+            "mainnet-beta/",
+            "mainnet-beta@evil.com",
+            " devnet",
+            "Devnet",
+            "mainnet",
+            "",
+        ] {
+            assert!(name.parse::<Network>().is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn unknown_names_never_reach_an_endpoint() {
+        let pool = crate::rpc::RpcPool::load(&crate::config::ServerConfig::default());
+        assert!(pool.network("mainnet-beta.evil.com").is_none());
+        assert_eq!(
+            pool.network("mainnet-beta").and_then(|n| n.public_endpoint()),
+            Some("https://api.mainnet-beta.solana.com")
+        );
+    }
+
+    #[test]
+    fn explorer_links_only_carry_local_custom_urls() {
+        let local = Network::Custom("http://127.0.0.1:8899".to_string()).explorer_tx_url("sig");
+        assert!(local.contains("cluster=custom") && local.contains("customUrl=http"), "{}", local);
+
+        let keyed = Network::Custom("https://rpc.example.com/?api-key=secret".to_string()).explorer_tx_url("sig");
+        assert_eq!(keyed, "https://explorer.solana.com/tx/sig?cluster=custom");
+        assert_eq!(Network::Devnet.explorer_tx_url("sig"), "https://explorer.solana.com/tx/sig?cluster=devnet");
+    }
+}
@@ -0,0 +1,244 @@
+//! NFT holdings of a wallet. RPCs with the Digital Asset Standard API (Helius, Triton) answer
+//! `getAssetsByOwner`, which also sees compressed NFTs and knows image URIs. Anywhere else the
+//! holdings are the wallet's token accounts with exactly one unit of a zero-decimal mint, named
+//! from their Metaplex metadata accounts; compressed NFTs can't be seen that way.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+
+use crate::history::rpc_call;
+use crate::utils::SplPubkey;
+
+pub const METADATA_PROGRAM: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+
+pub const DEFAULT_LIMIT: usize = 100;
+/// getAssetsByOwner's own page size ceiling.
+pub const MAX_LIMIT: usize = 1000;
+
+/// JSON-RPC "method not found": the endpoint has no DAS API.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// getMultipleAccounts takes at most this many accounts per call.
+const ACCOUNTS_PER_CALL: usize = 100;
+
+/// Endpoints that answered getAssetsByOwner with "method not found", so later requests go
+/// straight to the fallback instead of paying for the round trip again.
+static WITHOUT_DAS: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn without_das() -> &'static Mutex<HashSet<String>> {
+    WITHOUT_DAS.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+#[derive(Serialize)]
+pub struct Nft {
+    /// Mint address, or the asset id of a compressed NFT
+    pub id: String,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    /// Verified collection address
+    pub collection: Option<String>,
+    pub compressed: bool,
+    /// Only known through DAS
+    pub image: Option<String>,
+    /// Off-chain metadata JSON
+    pub uri: Option<String>,
+}
+
+pub struct Holdings {
+    pub nfts: Vec<Nft>,
+    /// Assets across all pages; as reported by DAS, or counted from the token accounts
+    pub total: Option<u64>,
+}
+
+/// Whether this endpoint is already known to lack the DAS API.
+pub fn known_without_das(rpc_url: &str) -> bool {
+    without_das().lock().unwrap().contains(rpc_url)
+}
+
+/// One page of the wallet's assets from getAssetsByOwner. `page` starts at 1.
+pub async fn from_das(http: &reqwest::Client, rpc_url: &str, owner: &str, page: u32, limit: usize) -> Result<Holdings, String> {
+    let body = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "getAssetsByOwner",
+        "params": { "ownerAddress": owner, "page": page, "limit": limit }
+    });
+    let started = std::time::Instant::now();
+    let http_error = |e| crate::rpc::http_error(rpc_url, started, e);
+    let res = http
+        .post(rpc_url)
+        .headers(crate::trace::outbound_headers())
+        .timeout(crate::rpc::timeout())
+        .json(&body)
+        .send()
+        .await
+        .map_err(http_error)?;
+    let response: Value = res.json().await.map_err(http_error)?;
+    if let Some(err) = response.get("error") {
+        if err["code"].as_i64() == Some(METHOD_NOT_FOUND) {
+            without_das().lock().unwrap().insert(rpc_url.to_string());
+        }
+        return Err(format!("RPC error: {}", err));
+    }
+    let result = response.get("result").ok_or_else(|| "Missing result in RPC response".to_string())?;
+    let items = result["items"].as_array().ok_or_else(|| "Missing items in getAssetsByOwner result".to_string())?;
+
+    let nfts = items
+        .iter()
+        // Fungible tokens only appear when asked for, but some providers list them anyway
+        .filter(|item| !matches!(item["interface"].as_str(), Some("FungibleToken" | "FungibleAsset")))
+        .filter_map(|item| {
+            let metadata = &item["content"]["metadata"];
+            let text = |value: &Value| value.as_str().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+            let collection = item["grouping"]
+                .as_array()
+                .and_then(|groups| groups.iter().find(|g| g["group_key"] == "collection"))
+                .and_then(|g| text(&g["group_value"]));
+            let image = text(&item["content"]["links"]["image"]).or_else(|| {
+                item["content"]["files"].as_array().and_then(|files| files.first()).and_then(|f| text(&f["uri"]))
+            });
+            Some(Nft {
+                id: text(&item["id"])?,
+                name: text(&metadata["name"]),
+                symbol: text(&metadata["symbol"]),
+                collection,
+                compressed: item["compression"]["compressed"].as_bool().unwrap_or(false),
+                image,
+                uri: text(&item["content"]["json_uri"]),
+            })
+        })
+        .collect();
+    Ok(Holdings { nfts, total: result["total"].as_u64() })
+}
+
+/// One page of the wallet's NFTs from its token accounts (both token programs) and their Metaplex
+/// metadata. Mints are sorted so pages are stable between calls.
+pub async fn from_token_accounts(
+    http: &reqwest::Client,
+    rpc_url: &str,
+    owner: &str,
+    page: u32,
+    limit: usize,
+    commitment: &str,
+) -> Result<Holdings, String> {
+    let mut mints: Vec<String> = Vec::new();
+    for program in [crate::mints::TOKEN_PROGRAM, crate::mints::TOKEN_2022_PROGRAM] {
+        let config = json!({ "encoding": "jsonParsed", "commitment": commitment });
+        let result = rpc_call(http, rpc_url, "getTokenAccountsByOwner", json!([owner, { "programId": program }, config])).await?;
+        for account in result["value"].as_array().into_iter().flatten() {
+            let info = &account["account"]["data"]["parsed"]["info"];
+            let token_amount = &info["tokenAmount"];
+            if token_amount["amount"] == "1" && token_amount["decimals"] == 0 {
+                if let Some(mint) = info["mint"].as_str() {
+                    mints.push(mint.to_string());
+                }
+            }
+        }
+    }
+    mints.sort();
+    mints.dedup();
+    let total = mints.len() as u64;
+    let start = (page.saturating_sub(1) as usize).saturating_mul(limit);
+    let mints: Vec<String> = mints.into_iter().skip(start).take(limit).collect();
+
+    let metadata_program: SplPubkey = METADATA_PROGRAM.parse().map_err(|_| "Invalid metadata program id".to_string())?;
+    let mut nfts = Vec::with_capacity(mints.len());
+    for chunk in mints.chunks(ACCOUNTS_PER_CALL) {
+        let pdas: Vec<String> = chunk
+            .iter()
+            .map(|mint| {
+                let mint: SplPubkey = mint.parse().unwrap_or_default();
+                let seeds: &[&[u8]] = &[b"metadata", metadata_program.as_ref(), mint.as_ref()];
+                SplPubkey::find_program_address(seeds, &metadata_program).0.to_string()
+            })
+            .collect();
+        let config = json!({ "encoding": "base64", "commitment": commitment });
+        let result = rpc_call(http, rpc_url, "getMultipleAccounts", json!([pdas, config])).await?;
+        let accounts = result["value"].as_array().cloned().unwrap_or_default();
+        for (i, mint) in chunk.iter().enumerate() {
+            let metadata = accounts
+                .get(i)
+                .and_then(|account| account["data"][0].as_str())
+                .and_then(|data| {
+                    use base64::Engine;
+                    base64::engine::general_purpose::STANDARD.decode(data).ok()
+                })
+                .and_then(|bytes| parse_metadata(&bytes));
+            nfts.push(match metadata {
+                Some(m) => Nft { id: mint.clone(), name: m.name, symbol: m.symbol, collection: m.collection, compressed: false, image: None, uri: m.uri },
+                None => Nft { id: mint.clone(), name: None, symbol: None, collection: None, compressed: false, image: None, uri: None },
+            });
+        }
+    }
+    Ok(Holdings { nfts, total: Some(total) })
+}
+
+struct Metadata {
+    name: Option<String>,
+    symbol: Option<String>,
+    uri: Option<String>,
+    collection: Option<String>,
+}
+
+/// Borsh reader over a Metaplex metadata account; every read fails cleanly on short data.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Fixed-width fields are padded with NULs; empty means unset.
+    fn string(&mut self) -> Option<Option<String>> {
+        let len = self.u32()? as usize;
+        let text = String::from_utf8_lossy(self.take(len)?).trim_matches(char::from(0)).trim().to_string();
+        Some(Some(text).filter(|t| !t.is_empty()))
+    }
+}
+
+/// Name, symbol, URI and verified collection of a Metaplex metadata account (key 4). The
+/// collection is the last field read, so older accounts that end earlier still get a name.
+fn parse_metadata(data: &[u8]) -> Option<Metadata> {
+    let mut r = Reader { data, pos: 0 };
+    if r.u8()? != 4 {
+        return None;
+    }
+    r.take(64)?; // update authority, mint
+    let mut metadata = Metadata { name: r.string()?, symbol: r.string()?, uri: r.string()?, collection: None };
+    metadata.collection = (|| {
+        r.take(2)?; // seller fee basis points
+        if r.u8()? == 1 {
+            let creators = r.u32()? as usize;
+            r.take(creators.checked_mul(34)?)?;
+        }
+        r.take(2)?; // primary sale happened, is mutable
+        for _ in 0..2 {
+            // edition nonce, token standard
+            if r.u8()? == 1 {
+                r.take(1)?;
+            }
+        }
+        if r.u8()? != 1 {
+            return None;
+        }
+        let verified = r.u8()? == 1;
+        let key = SplPubkey::try_from(r.take(32)?).ok()?;
+        verified.then(|| key.to_string())
+    })();
+    Some(metadata)
+}
@@ -0,0 +1,128 @@
+//! Onboarding sponsorship: the local wallet pays for a new user's USDC account, a little SOL for
+//! fees and an optional welcome transfer. Spending is capped per UTC day; the day's ledger is kept
+//! in ~/.fuego/onboard-budget.json so a restart doesn't reset it.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::utils::{base_units_to_ui, fuego_home, ui_amount_to_base_units};
+
+#[derive(Clone, Serialize)]
+pub struct OnboardConfig {
+    /// SOL sent to each new wallet when the request doesn't say, in lamports
+    pub default_sol_lamports: u64,
+    /// Most lamports (rent + SOL dust + fees) sponsored per UTC day
+    pub daily_lamports: u64,
+    /// Most USDC base units sent as welcome transfers per UTC day
+    pub daily_usdc: u64,
+}
+
+impl OnboardConfig {
+    /// FUEGO_ONBOARD_SOL (default 0.002), FUEGO_ONBOARD_DAILY_SOL (default 0.1) and
+    /// FUEGO_ONBOARD_DAILY_USDC (default 0: welcome transfers off).
+    pub fn from_env() -> Self {
+        let env_amount = |name: &str, default: &str, decimals: u8| {
+            let value = std::env::var(name).unwrap_or_else(|_| default.to_string());
+            ui_amount_to_base_units(value.trim(), decimals).unwrap_or_else(|e| {
+                eprintln!("Ignoring {}: {}", name, e);
+                ui_amount_to_base_units(default, decimals).unwrap_or(0)
+            })
+        };
+        OnboardConfig {
+            default_sol_lamports: env_amount("FUEGO_ONBOARD_SOL", "0.002", 9),
+            daily_lamports: env_amount("FUEGO_ONBOARD_DAILY_SOL", "0.1", 9),
+            daily_usdc: env_amount("FUEGO_ONBOARD_DAILY_USDC", "0", 6),
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct DailyLedger {
+    /// UTC date, YYYY-MM-DD
+    pub day: String,
+    pub lamports: u64,
+    pub usdc: u64,
+    pub wallets: u64,
+}
+
+pub enum BudgetError {
+    Lamports { requested: u64, remaining: u64 },
+    Usdc { requested: u64, remaining: u64 },
+}
+
+impl BudgetError {
+    pub fn message(&self) -> String {
+        match self {
+            BudgetError::Lamports { requested, remaining } => format!(
+                "Onboarding needs {} SOL but only {} SOL of today's sponsorship budget is left",
+                base_units_to_ui(*requested, 9),
+                base_units_to_ui(*remaining, 9)
+            ),
+            BudgetError::Usdc { requested, remaining } => format!(
+                "Welcome transfer of {} USDC exceeds the {} USDC left in today's budget",
+                base_units_to_ui(*requested, 6),
+                base_units_to_ui(*remaining, 6)
+            ),
+        }
+    }
+}
+
+fn ledger_path() -> PathBuf {
+    fuego_home().join("onboard-budget.json")
+}
+
+fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+#[derive(Clone)]
+pub struct OnboardBudget {
+    config: Arc<OnboardConfig>,
+    ledger: Arc<Mutex<DailyLedger>>,
+}
+
+impl OnboardBudget {
+    pub fn new(config: OnboardConfig) -> Self {
+        let ledger = fs::read_to_string(ledger_path())
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default();
+        OnboardBudget {
+            config: Arc::new(config),
+            ledger: Arc::new(Mutex::new(ledger)),
+        }
+    }
+
+    pub fn config(&self) -> &OnboardConfig {
+        &self.config
+    }
+
+    /// Reserve one onboarding's cost against today's budget. Nothing is reserved on refusal.
+    pub fn reserve(&self, lamports: u64, usdc: u64) -> Result<DailyLedger, BudgetError> {
+        let mut ledger = self.ledger.lock().unwrap();
+        let day = today();
+        if ledger.day != day {
+            *ledger = DailyLedger { day, ..Default::default() };
+        }
+        let remaining = self.config.daily_lamports.saturating_sub(ledger.lamports);
+        if lamports > remaining {
+            return Err(BudgetError::Lamports { requested: lamports, remaining });
+        }
+        let remaining = self.config.daily_usdc.saturating_sub(ledger.usdc);
+        if usdc > remaining {
+            return Err(BudgetError::Usdc { requested: usdc, remaining });
+        }
+        ledger.lamports += lamports;
+        ledger.usdc += usdc;
+        ledger.wallets += 1;
+
+        let persisted = fs::create_dir_all(fuego_home())
+            .and_then(|_| fs::write(ledger_path(), serde_json::to_vec_pretty(&*ledger).unwrap_or_default()));
+        if let Err(e) = persisted {
+            eprintln!("Failed to save onboarding budget: {}", e);
+        }
+        Ok(ledger.clone())
+    }
+}
@@ -0,0 +1,669 @@
+//! Pending-transaction tracker: signatures fuego submitted are polled with getSignatureStatuses until
+//! they finalize, fail or expire. Confirmed is not terminal: a confirmed signature that vanishes
+//! because its slot was skipped is marked reorged, alerted on, and resent when the signed
+//! transaction is still held and its blockhash is valid. Each tick also refreshes the gauges served
+//! by GET /metrics, so alerts keep firing while no API traffic is flowing.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::alerts::{AlertLog, Severity};
+use crate::history::rpc_call;
+use crate::rpc::RpcPool;
+use crate::submit_queue::SubmitQueue;
+
+const TICK: Duration = Duration::from_secs(15);
+
+/// A signature still unconfirmed this long after submission is counted as expired: its blockhash
+/// (valid for ~150 slots) has lapsed, so it can no longer land.
+pub const EXPIRY_SECS: i64 = 180;
+
+/// A confirmed signature that hasn't finalized (or vanished) after this long stops being watched.
+const FINALIZE_WATCH_SECS: i64 = 900;
+
+/// Settled statuses kept for GET lookups.
+const MAX_SETTLED: usize = 1000;
+
+/// getSignatureStatuses accepts at most this many signatures per call.
+pub const STATUS_BATCH: usize = 256;
+
+/// How often a caller waiting on `min_confirmations` re-checks the signature.
+const CONFIRM_POLL: Duration = Duration::from_secs(2);
+
+/// Upper bound on confirm_timeout_secs, so a request can't hold a connection open indefinitely.
+pub const MAX_CONFIRM_TIMEOUT_SECS: u64 = 300;
+
+/// Age buckets for the pending gauge: (label, exclusive upper bound in seconds).
+const AGE_BUCKETS: [(&str, i64); 3] = [("lt_30s", 30), ("30s_to_2m", 120), ("gt_2m", i64::MAX)];
+
+/// A signed transaction kept so it can be resent after a reorg.
+#[derive(Clone)]
+pub struct SignedTransaction {
+    /// Base64-encoded, as submitted
+    pub transaction: String,
+    pub versioned: bool,
+}
+
+struct Pending {
+    network: String,
+    submitted_at: i64,
+    signed: Option<SignedTransaction>,
+    /// Slot the signature was seen confirmed in; it is watched from then until finalized
+    confirmed_slot: Option<u64>,
+    reorgs: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackState {
+    Pending,
+    /// Confirmed, not yet finalized; can still be dropped by a fork
+    Confirmed,
+    Finalized,
+    /// Landed with an on-chain error
+    Failed,
+    /// Never confirmed before its blockhash lapsed
+    Expired,
+    /// Was confirmed, then its slot was skipped and the signature vanished
+    Reorged,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TrackedStatus {
+    pub signature: String,
+    pub network: String,
+    pub state: TrackState,
+    pub slot: Option<u64>,
+    /// Times the transaction was dropped by a reorg
+    pub reorgs: u32,
+    /// Times it was resent after a reorg
+    pub resubmits: u32,
+    pub detail: Option<String>,
+    pub updated_at: i64,
+}
+
+/// Values as of the last tick.
+#[derive(Clone, Default, Serialize)]
+pub struct Gauges {
+    pub pending_total: usize,
+    /// (bucket label, count), in AGE_BUCKETS order
+    pub pending_by_age: Vec<(String, usize)>,
+    pub oldest_pending_age_seconds: i64,
+    pub expired_last_hour: usize,
+    pub submit_queue_depth: usize,
+    pub expirations_total: u64,
+    pub confirmed_total: u64,
+    pub failed_total: u64,
+    /// Confirmed transactions still waiting to finalize
+    pub awaiting_finalization: usize,
+    pub reorged_total: u64,
+    pub resubmitted_total: u64,
+    /// Unix time of the tick that produced these values (0 before the first tick)
+    pub updated_at: i64,
+}
+
+#[derive(Default)]
+struct Inner {
+    pending: HashMap<String, Pending>,
+    /// Expiry times within the last hour
+    expired: VecDeque<i64>,
+    expirations_total: u64,
+    confirmed_total: u64,
+    failed_total: u64,
+    reorged_total: u64,
+    resubmitted_total: u64,
+    /// Latest status per signature, including settled ones (oldest evicted first)
+    statuses: HashMap<String, TrackedStatus>,
+    status_order: VecDeque<String>,
+    gauges: Gauges,
+}
+
+impl Inner {
+    fn set_status(&mut self, signature: &str, network: &str, state: TrackState, slot: Option<u64>, detail: Option<String>) {
+        let now = chrono::Utc::now().timestamp();
+        if let Some(status) = self.statuses.get_mut(signature) {
+            status.state = state;
+            status.slot = slot.or(status.slot);
+            status.detail = detail;
+            status.updated_at = now;
+            return;
+        }
+        if self.status_order.len() == MAX_SETTLED {
+            if let Some(oldest) = self.status_order.pop_front() {
+                self.statuses.remove(&oldest);
+            }
+        }
+        self.status_order.push_back(signature.to_string());
+        self.statuses.insert(
+            signature.to_string(),
+            TrackedStatus {
+                signature: signature.to_string(),
+                network: network.to_string(),
+                state,
+                slot,
+                reorgs: 0,
+                resubmits: 0,
+                detail,
+                updated_at: now,
+            },
+        );
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct PendingTracker {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl PendingTracker {
+    /// Start watching a signature that was just sent. Pass the signed transaction to allow a resend
+    /// if a reorg drops it.
+    pub fn track(&self, signature: &str, network: &str, signed: Option<SignedTransaction>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.pending.insert(
+            signature.to_string(),
+            Pending {
+                network: network.to_string(),
+                submitted_at: chrono::Utc::now().timestamp(),
+                signed,
+                confirmed_slot: None,
+                reorgs: 0,
+            },
+        );
+        inner.set_status(signature, network, TrackState::Pending, None, None);
+    }
+
+    /// Latest known state of a signature fuego submitted, if it is still remembered.
+    pub fn status(&self, signature: &str) -> Option<TrackedStatus> {
+        self.inner.lock().unwrap().statuses.get(signature).cloned()
+    }
+
+    /// Record a state learned by reconciliation for a signature this run isn't watching. Returns
+    /// false (and changes nothing) when it is being watched or already has that state.
+    pub fn record_reconciled(&self, signature: &str, network: &str, state: TrackState, slot: Option<u64>, detail: Option<String>) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.pending.contains_key(signature) {
+            return false;
+        }
+        if inner.statuses.get(signature).map(|s| s.state) == Some(state) {
+            return false;
+        }
+        inner.set_status(signature, network, state, slot, detail);
+        true
+    }
+
+    /// Remembered statuses, most recently added first.
+    pub fn recent(&self, limit: usize) -> Vec<TrackedStatus> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .status_order
+            .iter()
+            .rev()
+            .filter_map(|signature| inner.statuses.get(signature).cloned())
+            .take(limit)
+            .collect()
+    }
+
+    pub fn gauges(&self) -> Gauges {
+        self.inner.lock().unwrap().gauges.clone()
+    }
+
+    /// Apply a batch of statuses: failed and finalized signatures settle, confirmed ones keep being
+    /// watched. Returns confirmed signatures the RPC no longer knows, with the slot they were seen in.
+    fn apply(&self, signatures: &[String], statuses: &[serde_json::Value]) -> Vec<(String, String, u64)> {
+        let mut inner = self.inner.lock().unwrap();
+        let mut vanished = Vec::new();
+        for (signature, status) in signatures.iter().zip(statuses) {
+            let (network, confirmed_slot) = match inner.pending.get(signature) {
+                Some(p) => (p.network.clone(), p.confirmed_slot),
+                None => continue,
+            };
+            if status.is_null() {
+                if let Some(slot) = confirmed_slot {
+                    vanished.push((signature.clone(), network, slot));
+                }
+                continue;
+            }
+            let slot = status["slot"].as_u64();
+            if !status["err"].is_null() {
+                inner.pending.remove(signature);
+                inner.failed_total += 1;
+                inner.set_status(signature, &network, TrackState::Failed, slot, Some(status["err"].to_string()));
+                continue;
+            }
+            match status["confirmationStatus"].as_str() {
+                Some("finalized") => {
+                    inner.pending.remove(signature);
+                    if confirmed_slot.is_none() {
+                        inner.confirmed_total += 1;
+                    }
+                    inner.set_status(signature, &network, TrackState::Finalized, slot, None);
+                }
+                Some("confirmed") if confirmed_slot.is_none() => {
+                    if let Some(p) = inner.pending.get_mut(signature) {
+                        p.confirmed_slot = Some(slot.unwrap_or(0));
+                    }
+                    inner.confirmed_total += 1;
+                    inner.set_status(signature, &network, TrackState::Confirmed, slot, None);
+                }
+                _ => {}
+            }
+        }
+        vanished
+    }
+
+    /// Mark a confirmed signature as dropped by a fork. Returns the signed transaction to resend,
+    /// if one is held.
+    fn mark_reorged(&self, signature: &str, slot: u64) -> Option<SignedTransaction> {
+        let mut inner = self.inner.lock().unwrap();
+        let pending = inner.pending.remove(signature)?;
+        inner.reorged_total += 1;
+        inner.set_status(
+            signature,
+            &pending.network,
+            TrackState::Reorged,
+            Some(slot),
+            Some(format!("slot {} was skipped and the signature is no longer known", slot)),
+        );
+        if let Some(status) = inner.statuses.get_mut(signature) {
+            status.reorgs = pending.reorgs + 1;
+        }
+        pending.signed
+    }
+
+    /// Watch a signature again after resending it; it keeps its reorg history.
+    fn resubmitted(&self, signature: &str, network: &str, signed: SignedTransaction) {
+        let mut inner = self.inner.lock().unwrap();
+        let reorgs = inner.statuses.get(signature).map(|s| s.reorgs).unwrap_or(1);
+        inner.pending.insert(
+            signature.to_string(),
+            Pending {
+                network: network.to_string(),
+                submitted_at: chrono::Utc::now().timestamp(),
+                signed: Some(signed),
+                confirmed_slot: None,
+                reorgs,
+            },
+        );
+        inner.resubmitted_total += 1;
+        inner.set_status(signature, network, TrackState::Pending, None, Some("resubmitted after a reorg".to_string()));
+        if let Some(status) = inner.statuses.get_mut(signature) {
+            status.resubmits += 1;
+        }
+    }
+
+    fn set_detail(&self, signature: &str, detail: String) {
+        if let Some(status) = self.inner.lock().unwrap().statuses.get_mut(signature) {
+            status.detail = Some(detail);
+        }
+    }
+
+    fn refresh(&self, queue_depth: usize) {
+        let now = chrono::Utc::now().timestamp();
+        let mut inner = self.inner.lock().unwrap();
+
+        let expired: Vec<String> = inner
+            .pending
+            .iter()
+            .filter(|(_, p)| p.confirmed_slot.is_none() && now - p.submitted_at >= EXPIRY_SECS)
+            .map(|(s, _)| s.clone())
+            .collect();
+        for signature in expired {
+            if let Some(p) = inner.pending.remove(&signature) {
+                inner.set_status(&signature, &p.network, TrackState::Expired, None, None);
+            }
+            inner.expired.push_back(now);
+            inner.expirations_total += 1;
+        }
+        // Confirmed long ago and still not finalized or gone: the RPC is lagging, stop watching
+        inner
+            .pending
+            .retain(|_, p| p.confirmed_slot.is_none() || now - p.submitted_at < FINALIZE_WATCH_SECS);
+        while inner.expired.front().map(|t| now - t > 3600).unwrap_or(false) {
+            inner.expired.pop_front();
+        }
+
+        let ages: Vec<i64> = inner
+            .pending
+            .values()
+            .filter(|p| p.confirmed_slot.is_none())
+            .map(|p| now - p.submitted_at)
+            .collect();
+        let awaiting_finalization = inner.pending.len() - ages.len();
+        let mut lower = 0;
+        let pending_by_age = AGE_BUCKETS
+            .iter()
+            .map(|(label, upper)| {
+                let count = ages.iter().filter(|a| **a >= lower && **a < *upper).count();
+                lower = *upper;
+                (label.to_string(), count)
+            })
+            .collect();
+
+        inner.gauges = Gauges {
+            pending_total: ages.len(),
+            pending_by_age,
+            oldest_pending_age_seconds: ages.iter().copied().max().unwrap_or(0),
+            expired_last_hour: inner.expired.len(),
+            submit_queue_depth: queue_depth,
+            expirations_total: inner.expirations_total,
+            confirmed_total: inner.confirmed_total,
+            failed_total: inner.failed_total,
+            awaiting_finalization,
+            reorged_total: inner.reorged_total,
+            resubmitted_total: inner.resubmitted_total,
+            updated_at: now,
+        };
+    }
+
+    /// Prometheus text exposition of the last tick's gauges.
+    pub fn render_prometheus(&self) -> String {
+        let g = self.gauges();
+        let mut out = String::new();
+        out.push_str("# HELP fuego_pending_transactions Submitted transactions not yet confirmed, by age.\n");
+        out.push_str("# TYPE fuego_pending_transactions gauge\n");
+        for (bucket, count) in &g.pending_by_age {
+            out.push_str(&format!("fuego_pending_transactions{{age=\"{}\"}} {}\n", bucket, count));
+        }
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n{} {}\n", name, help, name, kind, name, value));
+        };
+        metric(
+            "fuego_pending_oldest_age_seconds",
+            "gauge",
+            "Age of the oldest unconfirmed transaction (0 when none).",
+            g.oldest_pending_age_seconds.to_string(),
+        );
+        metric(
+            "fuego_transactions_expired_last_hour",
+            "gauge",
+            "Transactions that expired without confirming in the last hour.",
+            g.expired_last_hour.to_string(),
+        );
+        metric(
+            "fuego_submit_queue_depth",
+            "gauge",
+            "Transactions waiting in the submission queue.",
+            g.submit_queue_depth.to_string(),
+        );
+        metric(
+            "fuego_transaction_expirations_total",
+            "counter",
+            "Transactions that expired without confirming since startup.",
+            g.expirations_total.to_string(),
+        );
+        metric(
+            "fuego_transactions_confirmed_total",
+            "counter",
+            "Tracked transactions that confirmed since startup.",
+            g.confirmed_total.to_string(),
+        );
+        metric(
+            "fuego_transactions_failed_total",
+            "counter",
+            "Tracked transactions that landed with an error since startup.",
+            g.failed_total.to_string(),
+        );
+        metric(
+            "fuego_transactions_awaiting_finalization",
+            "gauge",
+            "Confirmed transactions not yet finalized.",
+            g.awaiting_finalization.to_string(),
+        );
+        metric(
+            "fuego_transactions_reorged_total",
+            "counter",
+            "Confirmed transactions dropped by a fork since startup.",
+            g.reorged_total.to_string(),
+        );
+        metric(
+            "fuego_transactions_resubmitted_total",
+            "counter",
+            "Reorged transactions resent since startup.",
+            g.resubmitted_total.to_string(),
+        );
+        metric(
+            "fuego_pending_tracker_last_tick_timestamp_seconds",
+            "gauge",
+            "Unix time the gauges were last refreshed.",
+            g.updated_at.to_string(),
+        );
+        out
+    }
+}
+
+/// Poll every TICK: resolve statuses, check vanished confirmed signatures for reorgs, expire stale
+/// signatures, then recompute the gauges.
+pub fn spawn_tracker(tracker: PendingTracker, rpc: RpcPool, queue: SubmitQueue, alerts: AlertLog) {
+    tokio::spawn(async move {
+        let http = crate::trace::http_client();
+        let mut interval = tokio::time::interval(TICK);
+        loop {
+            interval.tick().await;
+
+            let mut by_network: HashMap<String, Vec<String>> = HashMap::new();
+            for (signature, pending) in tracker.inner.lock().unwrap().pending.iter() {
+                by_network.entry(pending.network.clone()).or_default().push(signature.clone());
+            }
+
+            for (network, signatures) in by_network {
+                let rpc_url = rpc.primary(&network);
+                for batch in signatures.chunks(STATUS_BATCH) {
+                    let started = Instant::now();
+                    let result = fetch_statuses(&http, &rpc_url, batch, false).await;
+                    rpc.record(&rpc_url, started.elapsed(), result.is_ok());
+                    match result {
+                        Ok(statuses) => {
+                            for (signature, network, slot) in tracker.apply(batch, &statuses) {
+                                check_reorg(&http, &tracker, &rpc, &alerts, &signature, &network, slot).await;
+                            }
+                        }
+                        Err(e) => eprintln!("Pending tracker: status check on {} failed: {}", network, e),
+                    }
+                }
+            }
+
+            tracker.refresh(queue.depth());
+        }
+    });
+}
+
+/// Whether `slot` was skipped: Some(true) once the finalized root has passed it without including
+/// it, None while it is still ahead of the root.
+async fn slot_skipped(http: &reqwest::Client, rpc_url: &str, slot: u64) -> Result<Option<bool>, String> {
+    let finalized = rpc_call(http, rpc_url, "getSlot", serde_json::json!([{ "commitment": "finalized" }]))
+        .await?
+        .as_u64()
+        .unwrap_or(0);
+    if finalized < slot {
+        return Ok(None);
+    }
+    let blocks = rpc_call(http, rpc_url, "getBlocks", serde_json::json!([slot, slot, { "commitment": "finalized" }])).await?;
+    Ok(Some(blocks.as_array().map(|b| b.is_empty()).unwrap_or(false)))
+}
+
+fn recent_blockhash(signed: &SignedTransaction) -> Option<String> {
+    use base64::Engine;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(&signed.transaction).ok()?;
+    if signed.versioned {
+        let tx: solana_transaction::versioned::VersionedTransaction = bincode::deserialize(&bytes).ok()?;
+        Some(tx.message.recent_blockhash().to_string())
+    } else {
+        let tx: solana_transaction::Transaction = bincode::deserialize(&bytes).ok()?;
+        Some(tx.message.recent_blockhash.to_string())
+    }
+}
+
+/// Resend an already signed transaction when its blockhash is still valid. Same bytes, same signature.
+async fn resend(http: &reqwest::Client, rpc_url: &str, signed: &SignedTransaction) -> Result<(), String> {
+    let blockhash = recent_blockhash(signed).ok_or("stored transaction could not be decoded")?;
+    let valid = rpc_call(http, rpc_url, "isBlockhashValid", serde_json::json!([blockhash, { "commitment": "processed" }]))
+        .await?["value"]
+        .as_bool()
+        .unwrap_or(false);
+    if !valid {
+        return Err("blockhash expired; rebuild and sign the transaction again".to_string());
+    }
+    rpc_call(
+        http,
+        rpc_url,
+        "sendTransaction",
+        serde_json::json!([signed.transaction, { "encoding": "base64", "skipPreflight": true }]),
+    )
+    .await
+    .map(|_| ())
+}
+
+/// A confirmed signature the RPC no longer knows: if its slot was skipped it was lost to a fork.
+async fn check_reorg(
+    http: &reqwest::Client,
+    tracker: &PendingTracker,
+    rpc: &RpcPool,
+    alerts: &AlertLog,
+    signature: &str,
+    network: &str,
+    slot: u64,
+) {
+    let rpc_url = rpc.primary(network);
+    let started = Instant::now();
+    let skipped = slot_skipped(http, &rpc_url, slot).await;
+    rpc.record(&rpc_url, started.elapsed(), skipped.is_ok());
+    match skipped {
+        // The slot made it into the finalized chain; the status lookup is lagging, check again next tick
+        Ok(Some(false)) | Ok(None) => return,
+        Ok(Some(true)) => {}
+        Err(e) => {
+            eprintln!("Pending tracker: reorg check for {} failed: {}", signature, e);
+            return;
+        }
+    }
+
+    let signed = tracker.mark_reorged(signature, slot);
+    let resubmit = match &signed {
+        Some(signed) => Some(resend(http, &rpc_url, signed).await),
+        None => None,
+    };
+    let (resubmitted, resubmit_error) = match (&resubmit, signed) {
+        (Some(Ok(())), Some(signed)) => {
+            tracker.resubmitted(signature, network, signed);
+            (true, None)
+        }
+        (Some(Err(e)), _) => {
+            tracker.set_detail(signature, format!("reorged; resubmit failed: {}", e));
+            (false, Some(e.clone()))
+        }
+        _ => (false, Some("signed transaction not held; submit it again".to_string())),
+    };
+    alerts.emit(
+        "transaction_reorged",
+        Severity::High,
+        format!("Transaction {} was confirmed in slot {} but dropped by a fork", signature, slot),
+        serde_json::json!({
+            "signature": signature,
+            "network": network,
+            "slot": slot,
+            "resubmitted": resubmitted,
+            "resubmit_error": resubmit_error
+        }),
+    );
+}
+
+pub async fn fetch_statuses(
+    http: &reqwest::Client,
+    rpc_url: &str,
+    signatures: &[String],
+    search_history: bool,
+) -> Result<Vec<serde_json::Value>, String> {
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "getSignatureStatuses",
+        "params": [signatures, { "searchTransactionHistory": search_history }]
+    });
+    let res = http.post(rpc_url).headers(crate::trace::outbound_headers()).json(&body).send().await.map_err(|e| e.to_string())?;
+    let json: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+    if let Some(err) = json.get("error") {
+        return Err(format!("RPC error: {}", err));
+    }
+    json["result"]["value"]
+        .as_array()
+        .cloned()
+        .ok_or_else(|| "Missing result.value in RPC response".to_string())
+}
+
+/// Where a signature stands against a confirmations threshold.
+#[derive(Clone, Serialize)]
+pub struct ConfirmationProgress {
+    /// Confirmations as reported by the RPC; null once the transaction is finalized (rooted)
+    pub confirmations: Option<u64>,
+    /// processed / confirmed / finalized, or null while the RPC doesn't know the signature yet
+    pub confirmation_status: Option<String>,
+    pub finalized: bool,
+    pub min_confirmations: u64,
+    pub threshold_met: bool,
+    /// On-chain error, if the transaction landed but failed
+    pub err: Option<serde_json::Value>,
+}
+
+impl ConfirmationProgress {
+    /// Finalized transactions meet any threshold: the RPC stops counting confirmations at that point.
+    pub fn from_status(status: &serde_json::Value, min_confirmations: u64) -> Self {
+        let confirmation_status = status["confirmationStatus"].as_str().map(str::to_string);
+        let finalized = confirmation_status.as_deref() == Some("finalized");
+        let confirmations = status["confirmations"].as_u64();
+        ConfirmationProgress {
+            confirmations,
+            confirmation_status,
+            finalized,
+            min_confirmations,
+            threshold_met: finalized || confirmations.map(|c| c >= min_confirmations).unwrap_or(false),
+            err: status.get("err").filter(|e| !e.is_null()).cloned(),
+        }
+    }
+}
+
+/// Current status of one signature, searching the ledger history so older transactions resolve.
+pub async fn signature_status(rpc_url: &str, signature: &str, min_confirmations: u64) -> Result<ConfirmationProgress, String> {
+    let statuses = fetch_statuses(&crate::rpc::http_client(rpc_url), rpc_url, &[signature.to_string()], true).await?;
+    let status = statuses.into_iter().next().unwrap_or(serde_json::Value::Null);
+    Ok(ConfirmationProgress::from_status(&status, min_confirmations))
+}
+
+pub enum ConfirmationOutcome {
+    Reached(ConfirmationProgress),
+    /// Landed with an error; waiting longer won't change that
+    Failed(ConfirmationProgress),
+    /// Gave up after the timeout, with the last progress seen
+    TimedOut { progress: ConfirmationProgress, waited_secs: u64 },
+}
+
+/// Poll a freshly submitted signature until it has `min_confirmations` (or is finalized), fails on
+/// chain, or `timeout` passes. Failed polls are retried until the timeout.
+pub async fn wait_for_confirmations(rpc_url: &str, signature: &str, min_confirmations: u64, timeout: Duration) -> ConfirmationOutcome {
+    let http = crate::rpc::http_client(rpc_url);
+    let started = Instant::now();
+    let mut progress = ConfirmationProgress::from_status(&serde_json::Value::Null, min_confirmations);
+    loop {
+        match fetch_statuses(&http, rpc_url, &[signature.to_string()], false).await {
+            Ok(statuses) => {
+                progress = ConfirmationProgress::from_status(statuses.first().unwrap_or(&serde_json::Value::Null), min_confirmations);
+                if progress.err.is_some() {
+                    return ConfirmationOutcome::Failed(progress);
+                }
+                if progress.threshold_met {
+                    return ConfirmationOutcome::Reached(progress);
+                }
+            }
+            Err(e) => eprintln!("Confirmation wait: status check for {} failed: {}", signature, e),
+        }
+        if started.elapsed() + CONFIRM_POLL > timeout {
+            return ConfirmationOutcome::TimedOut {
+                progress,
+                waited_secs: started.elapsed().as_secs(),
+            };
+        }
+        tokio::time::sleep(CONFIRM_POLL).await;
+    }
+}
@@ -0,0 +1,126 @@
+//! Named RPC provider profiles from the `providers` section of the server config, so requests
+//! can say `provider: "helius-mainnet"` instead of carrying a URL with an API key in it. Provider
+//! URLs are secrets: they are never returned, and `scrub` replaces them with the provider's name
+//! in response bodies and log lines.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use crate::config::ServerConfig;
+
+/// (secret text, replacement) pairs for every loaded provider URL.
+static SECRETS: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+#[derive(Clone)]
+pub struct Provider {
+    pub name: String,
+    url: String,
+    /// Cluster the provider serves: from the profile, or guessed from its name and URL
+    pub network: Option<String>,
+}
+
+impl Provider {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Providers {
+    by_name: Arc<HashMap<String, Provider>>,
+}
+
+/// A profile is either a bare URL or `{ "url": ..., "network": ... }`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Entry {
+    Url(String),
+    Profile {
+        url: String,
+        #[serde(default)]
+        network: Option<String>,
+    },
+}
+
+impl Entry {
+    pub fn url(&self) -> &str {
+        match self {
+            Entry::Url(url) | Entry::Profile { url, .. } => url,
+        }
+    }
+}
+
+/// "devnet", "testnet" or "mainnet-beta" when the name or URL says so.
+fn guess_network(name: &str, url: &str) -> Option<String> {
+    let text = format!("{} {}", name, url).to_lowercase();
+    ["devnet", "testnet", "mainnet"]
+        .iter()
+        .find(|cluster| text.contains(*cluster))
+        .map(|cluster| if *cluster == "mainnet" { "mainnet-beta" } else { cluster }.to_string())
+}
+
+impl Providers {
+    /// Profiles from the server config, whose URLs were checked when it loaded.
+    pub fn load(config: &ServerConfig) -> Self {
+        let mut by_name = HashMap::new();
+        for (name, entry) in &config.providers {
+            let network = match entry {
+                Entry::Url(_) => None,
+                Entry::Profile { network, .. } => network.clone().filter(|n| !n.is_empty()),
+            };
+            let url = match crate::rpc::validate_url(entry.url()) {
+                Ok(url) => url,
+                Err(e) => {
+                    eprintln!("Ignoring provider {}: {}", name, e);
+                    continue;
+                }
+            };
+            let network = network.or_else(|| guess_network(name, &url));
+            by_name.insert(name.clone(), Provider { name: name.clone(), url, network });
+        }
+
+        let mut secrets = Vec::new();
+        for provider in by_name.values() {
+            let replacement = format!("provider:{}", provider.name);
+            let mut forms = vec![provider.url.clone(), crate::rpc::redact_url(&provider.url)];
+            if let Ok(parsed) = reqwest::Url::parse(&provider.url) {
+                forms.push(parsed.to_string());
+            }
+            for form in forms {
+                secrets.push((form, replacement.clone()));
+            }
+        }
+        // Longest first, so a URL is replaced whole before a shorter form of it matches
+        secrets.sort_by_key(|(secret, _)| std::cmp::Reverse(secret.len()));
+        let _ = SECRETS.set(secrets);
+
+        Providers { by_name: Arc::new(by_name) }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Provider> {
+        self.by_name.get(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+
+    /// Names and networks only, sorted by name; never URLs.
+    pub fn describe(&self) -> Vec<serde_json::Value> {
+        let mut list: Vec<&Provider> = self.by_name.values().collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        list.iter().map(|p| serde_json::json!({ "name": p.name, "network": p.network })).collect()
+    }
+}
+
+/// `text` with every provider URL replaced by "provider:<name>".
+pub fn scrub(text: &str) -> String {
+    let mut out = text.to_string();
+    for (secret, replacement) in SECRETS.get().into_iter().flatten() {
+        if out.contains(secret.as_str()) {
+            out = out.replace(secret.as_str(), replacement);
+        }
+    }
+    out
+}
@@ -0,0 +1,225 @@
+//! Per-client token buckets, so one runaway script can't burn through the RPC provider's quota
+//! for everyone. Each client IP gets one bucket for writes (building, submitting, paying) and one
+//! for everything else; a bucket holds a minute's worth of requests and refills continuously.
+//! Behind a reverse proxy every request comes from the proxy's address, so with
+//! FUEGO_TRUST_PROXY=true (or `trust_proxy` in the server config) the client is taken from
+//! X-Forwarded-For instead.
+
+use axum::http::{HeaderMap, Method};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::ServerConfig;
+
+/// Buckets kept before idle ones are dropped; an idle bucket is full, so dropping it changes nothing.
+const MAX_BUCKETS: usize = 10_000;
+
+/// Never limited: liveness probes and the dashboard's static files.
+const EXEMPT_PATHS: &[&str] = &["/", "/health", "/ui", "/ui-config"];
+
+/// Refill is fractional, so rounding can leave a bucket a hair short of a whole token at exactly
+/// the moment Retry-After promised one.
+const TOKEN_EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Class {
+    Read,
+    Write,
+}
+
+impl Class {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Class::Read => "read",
+            Class::Write => "write",
+        }
+    }
+
+    /// Which bucket a request draws from, or None when it isn't limited.
+    pub fn of(method: &Method, path: &str) -> Option<Class> {
+        if EXEMPT_PATHS.contains(&path) || path.starts_with("/ui/") || method == Method::OPTIONS {
+            return None;
+        }
+        let write = path.starts_with("/build-")
+            || path.starts_with("/submit-transaction")
+            || path == "/submit-versioned-transaction"
+            || path == "/x402-purch"
+            || path == "/devnet/mint-tokens"
+            || (path.starts_with("/scheduled-transfers/") && method == Method::POST);
+        Some(if write { Class::Write } else { Class::Read })
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<HashMap<(IpAddr, Class), Bucket>>>,
+    /// Requests per minute per client; 0 turns the class off
+    read_per_minute: u32,
+    write_per_minute: u32,
+    trust_proxy: bool,
+}
+
+impl RateLimiter {
+    pub fn new(read_per_minute: u32, write_per_minute: u32, trust_proxy: bool) -> Self {
+        RateLimiter { buckets: Arc::new(Mutex::new(HashMap::new())), read_per_minute, write_per_minute, trust_proxy }
+    }
+
+    /// FUEGO_RATE_LIMIT_READ_PER_MIN (default 60), FUEGO_RATE_LIMIT_WRITE_PER_MIN (default 10),
+    /// FUEGO_TRUST_PROXY (default false), each falling back to the same key in the server config.
+    pub fn load(config: &ServerConfig) -> Self {
+        let per_minute = |name: &str, file: Option<u32>, default: u32| {
+            std::env::var(name).ok().and_then(|v| v.trim().parse().ok()).or(file).unwrap_or(default)
+        };
+        let trust_proxy = match std::env::var("FUEGO_TRUST_PROXY") {
+            Ok(value) => value == "true",
+            Err(_) => config.trust_proxy.unwrap_or(false),
+        };
+        RateLimiter::new(
+            per_minute("FUEGO_RATE_LIMIT_READ_PER_MIN", config.rate_limit_read_per_min, 60),
+            per_minute("FUEGO_RATE_LIMIT_WRITE_PER_MIN", config.rate_limit_write_per_min, 10),
+            trust_proxy,
+        )
+    }
+
+    /// Limits and proxy trust, for GET /config.
+    pub fn describe(&self) -> serde_json::Value {
+        serde_json::json!({
+            "read_per_minute": self.read_per_minute,
+            "write_per_minute": self.write_per_minute,
+            "trust_proxy": self.trust_proxy
+        })
+    }
+
+    pub fn per_minute(&self, class: Class) -> u32 {
+        match class {
+            Class::Read => self.read_per_minute,
+            Class::Write => self.write_per_minute,
+        }
+    }
+
+    /// The client a request is counted against: the last X-Forwarded-For entry (the address the
+    /// trusted proxy saw) when proxies are trusted, else the connection's peer.
+    pub fn client_ip(&self, headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<IpAddr> {
+        if self.trust_proxy {
+            let forwarded = headers
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.rsplit(',').next())
+                .and_then(|ip| ip.trim().parse().ok());
+            if forwarded.is_some() {
+                return forwarded;
+            }
+        }
+        peer.map(|addr| addr.ip())
+    }
+
+    /// Take one request from the client's bucket as of `now`. When it is empty, returns how long
+    /// until the next request would be allowed.
+    pub fn check(&self, ip: IpAddr, class: Class, now: Instant) -> Result<(), Duration> {
+        let per_minute = self.per_minute(class);
+        if per_minute == 0 {
+            return Ok(());
+        }
+        let capacity = per_minute as f64;
+        let refill_per_sec = capacity / 60.0;
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_BUCKETS {
+            buckets.retain(|_, b| now.saturating_duration_since(b.updated) < Duration::from_secs(60));
+        }
+        let bucket = buckets.entry((ip, class)).or_insert(Bucket { tokens: capacity, updated: now });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 - TOKEN_EPSILON {
+            bucket.tokens = (bucket.tokens - 1.0).max(0.0);
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / refill_per_sec))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(203, 0, 113, 7));
+
+    /// Take requests at `now` until the bucket refuses one; returns how many got through.
+    fn drain(limiter: &RateLimiter, class: Class, now: Instant) -> u32 {
+        let mut allowed = 0;
+        while limiter.check(CLIENT, class, now).is_ok() {
+            allowed += 1;
+        }
+        allowed
+    }
+
+    #[test]
+    fn a_new_client_gets_a_full_minute_of_requests() {
+        let limiter = RateLimiter::new(60, 10, false);
+        let start = Instant::now();
+        assert_eq!(drain(&limiter, Class::Read, start), 60);
+        assert_eq!(drain(&limiter, Class::Write, start), 10);
+    }
+
+    #[test]
+    fn an_empty_bucket_refills_at_the_per_minute_rate() {
+        let limiter = RateLimiter::new(60, 10, false);
+        let start = Instant::now();
+        drain(&limiter, Class::Write, start);
+
+        // 10 a minute is one every 6 seconds
+        assert!(limiter.check(CLIENT, Class::Write, start + Duration::from_secs(5)).is_err());
+        assert!(limiter.check(CLIENT, Class::Write, start + Duration::from_secs(6)).is_ok());
+        assert!(limiter.check(CLIENT, Class::Write, start + Duration::from_secs(6)).is_err());
+        assert_eq!(drain(&limiter, Class::Write, start + Duration::from_secs(36)), 5);
+    }
+
+    #[test]
+    fn refill_never_goes_past_capacity() {
+        let limiter = RateLimiter::new(60, 10, false);
+        let start = Instant::now();
+        drain(&limiter, Class::Write, start);
+        assert_eq!(drain(&limiter, Class::Write, start + Duration::from_secs(3600)), 10);
+    }
+
+    #[test]
+    fn retry_after_is_the_time_until_the_next_token() {
+        let limiter = RateLimiter::new(60, 10, false);
+        let start = Instant::now();
+        drain(&limiter, Class::Write, start);
+
+        let wait = limiter.check(CLIENT, Class::Write, start).unwrap_err();
+        assert!((wait.as_secs_f64() - 6.0).abs() < 1e-6, "{:?}", wait);
+        let wait = limiter.check(CLIENT, Class::Write, start + Duration::from_secs(4)).unwrap_err();
+        assert!((wait.as_secs_f64() - 2.0).abs() < 1e-6, "{:?}", wait);
+        assert!(limiter.check(CLIENT, Class::Write, start + Duration::from_secs(4) + wait).is_ok());
+    }
+
+    #[test]
+    fn classes_and_clients_have_separate_buckets() {
+        let limiter = RateLimiter::new(60, 10, false);
+        let start = Instant::now();
+        drain(&limiter, Class::Write, start);
+        assert!(limiter.check(CLIENT, Class::Read, start).is_ok());
+        let other: IpAddr = "198.51.100.1".parse().unwrap();
+        assert!(limiter.check(other, Class::Write, start).is_ok());
+    }
+
+    #[test]
+    fn zero_per_minute_turns_the_class_off() {
+        let limiter = RateLimiter::new(0, 10, false);
+        let start = Instant::now();
+        for _ in 0..1000 {
+            assert!(limiter.check(CLIENT, Class::Read, start).is_ok());
+        }
+    }
+}
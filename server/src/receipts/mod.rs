@@ -0,0 +1,326 @@
+//! x402 purchase receipts stored under ~/.fuego/x402-receipts, with optional
+//! content-addressed response bodies under ~/.fuego/x402-bodies, plus in-flight
+//! purchase records under ~/.fuego/x402-inflight.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::utils::fuego_home;
+
+/// Largest paid response body we will read (and hash) into memory.
+pub const MAX_X402_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct X402Receipt {
+    pub id: String,
+    pub url: String,
+    pub network: String,
+    pub status: u16,
+    /// SHA-256 of the response body, hex encoded
+    pub sha256: String,
+    pub content_type: Option<String>,
+    pub byte_length: usize,
+    /// True when the body was persisted and can be fetched from /x402-receipts/:id/body
+    pub body_stored: bool,
+    #[serde(default)]
+    pub yid: Option<String>,
+    pub created_at: i64,
+    /// Whether the payment moved funds on-chain; null when the chain couldn't be asked
+    #[serde(default)]
+    pub on_chain_verified: Option<bool>,
+    #[serde(default)]
+    pub settlement: Option<crate::settlement::Verification>,
+}
+
+fn receipts_dir() -> PathBuf {
+    fuego_home().join("x402-receipts")
+}
+
+fn bodies_dir() -> PathBuf {
+    fuego_home().join("x402-bodies")
+}
+
+fn inflight_dir() -> PathBuf {
+    fuego_home().join("x402-inflight")
+}
+
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub fn new_receipt_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+pub fn save_receipt(receipt: &X402Receipt) -> Result<(), String> {
+    let dir = receipts_dir();
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(receipt).map_err(|e| e.to_string())?;
+    fs::write(dir.join(format!("{}.json", receipt.id)), content).map_err(|e| e.to_string())
+}
+
+/// Load a receipt by id. Ids are validated so they can't escape the receipts directory.
+pub fn load_receipt(id: &str) -> Result<Option<X402Receipt>, String> {
+    if !is_hex(id, 32) {
+        return Err("Invalid receipt id".to_string());
+    }
+    let path = receipts_dir().join(format!("{}.json", id));
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map(Some).map_err(|e| e.to_string())
+}
+
+/// All receipts, newest first. Unreadable files are skipped.
+pub fn list_receipts() -> Result<Vec<X402Receipt>, String> {
+    let dir = receipts_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut receipts: Vec<X402Receipt> = fs::read_dir(&dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect();
+    receipts.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+    Ok(receipts)
+}
+
+/// Which receipts a yid lookup selects. `unattributed` picks the purchases made without a yid, so
+/// spend that was never attributed can still be found.
+#[derive(Default, Deserialize)]
+pub struct YidFilter {
+    #[serde(default)]
+    pub yid: Option<String>,
+    #[serde(default)]
+    pub yid_prefix: Option<String>,
+    #[serde(default)]
+    pub unattributed: bool,
+}
+
+impl YidFilter {
+    /// `unattributed` can't be combined with a yid; an empty filter selects everything.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.unattributed && (self.yid.is_some() || self.yid_prefix.is_some()) {
+            return Err("unattributed=true selects receipts without a yid; don't combine it with yid or yid_prefix".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, receipt: &X402Receipt) -> bool {
+        let yid = receipt.yid.as_deref();
+        if self.unattributed {
+            return yid.is_none();
+        }
+        if let Some(wanted) = self.yid.as_deref() {
+            if yid != Some(wanted) {
+                return false;
+            }
+        }
+        if let Some(prefix) = self.yid_prefix.as_deref() {
+            if !yid.is_some_and(|y| y.starts_with(prefix)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Persist a body under its own hash; identical payloads share one file.
+pub fn store_body(sha256: &str, body: &[u8]) -> Result<(), String> {
+    let dir = bodies_dir();
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(sha256);
+    if path.exists() {
+        return Ok(());
+    }
+    fs::write(path, body).map_err(|e| e.to_string())
+}
+
+pub fn load_body(sha256: &str) -> Result<Vec<u8>, String> {
+    if !is_hex(sha256, 64) {
+        return Err("Invalid content hash".to_string());
+    }
+    fs::read(bodies_dir().join(sha256)).map_err(|e| e.to_string())
+}
+
+/// A paid request that has been (or is being) sent. Written before the request goes out so the
+/// outcome can still be recovered if the caller disconnects while the payment settles.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InflightRecord {
+    pub id: String,
+    pub url: String,
+    pub network: String,
+    /// Maximum price authorized for the purchase, in cents
+    pub max_price: u64,
+    /// SHA-256 of the order payload sent with the paid request
+    pub payload_sha256: String,
+    #[serde(default)]
+    pub yid: Option<String>,
+    /// "in_flight", "completed" (a response was received), "failed" (no usable response) or
+    /// "interrupted" (the server stopped before the request finished)
+    pub status: String,
+    /// The response returned to the original caller, once known
+    #[serde(default)]
+    pub outcome: Option<serde_json::Value>,
+    pub created_at: i64,
+    #[serde(default)]
+    pub completed_at: Option<i64>,
+}
+
+impl InflightRecord {
+    pub fn new(url: &str, network: &str, max_price: u64, payload: &[u8], yid: Option<String>) -> Self {
+        use sha2::{Digest, Sha256};
+
+        InflightRecord {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            url: url.to_string(),
+            network: network.to_string(),
+            max_price,
+            payload_sha256: format!("{:x}", Sha256::digest(payload)),
+            yid,
+            status: "in_flight".to_string(),
+            outcome: None,
+            created_at: chrono::Utc::now().timestamp(),
+            completed_at: None,
+        }
+    }
+
+    /// Record the final response. Anything that got as far as an HTTP status counts as completed.
+    pub fn finish(&mut self, outcome: serde_json::Value) {
+        self.status = if outcome.get("status").is_some() { "completed" } else { "failed" }.to_string();
+        self.outcome = Some(outcome);
+        self.completed_at = Some(chrono::Utc::now().timestamp());
+    }
+}
+
+pub fn save_inflight(record: &InflightRecord) -> Result<(), String> {
+    let dir = inflight_dir();
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(record).map_err(|e| e.to_string())?;
+    fs::write(dir.join(format!("{}.json", record.id)), content).map_err(|e| e.to_string())
+}
+
+pub fn load_inflight(id: &str) -> Result<Option<InflightRecord>, String> {
+    if !is_hex(id, 32) {
+        return Err("Invalid in-flight id".to_string());
+    }
+    let path = inflight_dir().join(format!("{}.json", id));
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map(Some).map_err(|e| e.to_string())
+}
+
+/// All in-flight records, newest first. Unreadable files are skipped.
+pub fn list_inflight() -> Result<Vec<InflightRecord>, String> {
+    let dir = inflight_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut records: Vec<InflightRecord> = fs::read_dir(&dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect();
+    records.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+    Ok(records)
+}
+
+/// How long an unresolved paid request blocks an identical retry: the payment transaction it signed
+/// carries a recent blockhash, so until that expires the facilitator may still settle it.
+pub const PAYMENT_REUSE_WINDOW_SECS: i64 = 120;
+
+/// Newest record for the same URL and payload whose outcome is unknown (still in flight, interrupted,
+/// or failed before any HTTP response) and that is young enough for its payment to still settle.
+pub fn find_unresolved_attempt(url: &str, payload: &[u8]) -> Option<InflightRecord> {
+    use sha2::{Digest, Sha256};
+
+    let payload_sha256 = format!("{:x}", Sha256::digest(payload));
+    let now = chrono::Utc::now().timestamp();
+    list_inflight()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|r| now - r.created_at < PAYMENT_REUSE_WINDOW_SECS)
+        .find(|r| r.url == url && r.payload_sha256 == payload_sha256 && r.status != "completed")
+}
+
+/// Called at startup: anything still marked in_flight belonged to a previous process that never
+/// finished it. The payment may still have settled, so these are kept for the caller to reconcile.
+pub fn mark_interrupted_inflight() -> usize {
+    let records = list_inflight().unwrap_or_default();
+    let mut count = 0;
+    for mut record in records.into_iter().filter(|r| r.status == "in_flight") {
+        record.status = "interrupted".to_string();
+        if save_inflight(&record).is_ok() {
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt(yid: Option<&str>) -> X402Receipt {
+        X402Receipt {
+            id: new_receipt_id(),
+            url: "https://seller.example/api".to_string(),
+            network: "devnet".to_string(),
+            status: 200,
+            sha256: "00".repeat(32),
+            content_type: None,
+            byte_length: 0,
+            body_stored: false,
+            yid: yid.map(str::to_string),
+            created_at: 0,
+            on_chain_verified: None,
+            settlement: None,
+        }
+    }
+
+    fn selected(filter: &YidFilter) -> Vec<Option<String>> {
+        [Some("inv-1"), Some("inv-12"), Some("po-7"), None]
+            .into_iter()
+            .map(receipt)
+            .filter(|r| filter.matches(r))
+            .map(|r| r.yid)
+            .collect()
+    }
+
+    #[test]
+    fn exact_yid_selects_only_that_yid() {
+        let filter = YidFilter { yid: Some("inv-1".to_string()), ..Default::default() };
+        assert_eq!(selected(&filter), vec![Some("inv-1".to_string())]);
+    }
+
+    #[test]
+    fn prefix_skips_unattributed_receipts() {
+        let filter = YidFilter { yid_prefix: Some("inv-".to_string()), ..Default::default() };
+        assert_eq!(selected(&filter), vec![Some("inv-1".to_string()), Some("inv-12".to_string())]);
+    }
+
+    #[test]
+    fn unattributed_selects_receipts_without_a_yid() {
+        let filter = YidFilter { unattributed: true, ..Default::default() };
+        assert_eq!(selected(&filter), vec![None]);
+    }
+
+    #[test]
+    fn empty_filter_selects_everything() {
+        assert_eq!(selected(&YidFilter::default()).len(), 4);
+    }
+
+    #[test]
+    fn unattributed_cannot_be_combined_with_a_yid() {
+        let filter = YidFilter { yid: Some("inv-1".to_string()), unattributed: true, ..Default::default() };
+        assert!(filter.validate().is_err());
+        assert!(YidFilter { unattributed: true, ..Default::default() }.validate().is_ok());
+    }
+}
@@ -0,0 +1,239 @@
+//! Reconciliation of local state against the chain, for transactions that settled while fuego was
+//! down. Runs once at startup and on POST /admin/reconcile: wallet histories are scanned for fuego
+//! memos the index missed, and recent signatures fuego submitted are looked up so the pending
+//! tracker reports how they ended. Index entries whose signatures the chain has never heard of are
+//! flagged as orphaned (in memo-orphans.json), and unflagged if they turn up later.
+
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use crate::memo_index;
+use crate::pending::{fetch_statuses, PendingTracker, TrackState, EXPIRY_SECS, STATUS_BATCH};
+use crate::rpc::RpcPool;
+use crate::stats;
+use crate::submit_queue::{ItemStatus, SubmitQueue};
+
+/// Signatures scanned per wallet and network unless the caller asks for more.
+pub const DEFAULT_DEPTH: usize = 100;
+
+/// Submissions older than this aren't looked up; an RPC without full history would report them
+/// as missing when they landed long ago.
+const WINDOW_SECS: i64 = 7 * 24 * 3600;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+pub struct Options {
+    /// Report what would change without touching the index, the orphan flags or the tracker
+    pub dry_run: bool,
+    pub depth: usize,
+    /// Only this network; otherwise every network the index, queue or wallet has used
+    pub network: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Orphan {
+    pub signature: String,
+    pub network: String,
+    pub yid: Option<String>,
+}
+
+#[derive(Default, Serialize)]
+pub struct Report {
+    pub dry_run: bool,
+    pub networks: Vec<String>,
+    pub wallets: Vec<String>,
+    /// History signatures read while looking for missed memos
+    pub signatures_scanned: usize,
+    /// Submitted signatures looked up with getSignatureStatuses
+    pub signatures_checked: usize,
+    /// Transactions with fuego memos imported into the index
+    pub added: usize,
+    /// Submitted signatures whose final state was learned (confirmed, finalized or failed)
+    pub updated: usize,
+    /// Index entries the chain has no record of
+    pub orphaned: usize,
+    /// Previously orphaned entries that were found on-chain after all
+    pub recovered: usize,
+    pub orphans: Vec<Orphan>,
+    /// True when some history scan stopped at `depth` before reaching the end of the history
+    pub depth_limited: bool,
+    /// Per-network or per-wallet failures; the rest of the run still completes
+    pub errors: Vec<String>,
+    pub started_at: i64,
+    pub elapsed_ms: u64,
+}
+
+/// Reconcile every wallet in `wallets` on each network. `wallet_network` is the local wallet's
+/// network, reconciled even when nothing has been submitted on it yet. Returns Err only when a
+/// run is already in progress.
+pub async fn run(
+    rpc: &RpcPool,
+    tracker: &PendingTracker,
+    queue: &SubmitQueue,
+    wallets: Vec<String>,
+    wallet_network: Option<String>,
+    options: Options,
+) -> Result<Report, String> {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("A reconciliation is already running".to_string());
+    }
+    let report = reconcile(rpc, tracker, queue, wallets, wallet_network, options).await;
+    RUNNING.store(false, Ordering::SeqCst);
+    Ok(report)
+}
+
+async fn reconcile(
+    rpc: &RpcPool,
+    tracker: &PendingTracker,
+    queue: &SubmitQueue,
+    wallets: Vec<String>,
+    wallet_network: Option<String>,
+    options: Options,
+) -> Report {
+    let started = Instant::now();
+    let now = chrono::Utc::now().timestamp();
+    let indexed = memo_index::load();
+    let queued = queue.snapshot();
+
+    let networks: BTreeSet<String> = match &options.network {
+        Some(network) => BTreeSet::from([network.clone()]),
+        None => indexed
+            .iter()
+            .map(|m| m.network.clone())
+            .chain(queued.iter().map(|q| q.network.clone()))
+            .chain(wallet_network)
+            .collect(),
+    };
+    let mut report = Report {
+        dry_run: options.dry_run,
+        networks: networks.iter().cloned().collect(),
+        wallets: wallets.clone(),
+        started_at: now,
+        ..Report::default()
+    };
+
+    // Submitted signatures to look up, per network: (indexed or enqueued at, yid)
+    let mut submitted: HashMap<&str, HashMap<String, (i64, Option<String>)>> = HashMap::new();
+    for memo in indexed.iter().filter(|m| m.source != memo_index::BACKFILL_SOURCE && now - m.indexed_at < WINDOW_SECS) {
+        submitted
+            .entry(memo.network.as_str())
+            .or_default()
+            .entry(memo.signature.clone())
+            .or_insert((memo.indexed_at, memo.yid.clone()));
+    }
+    for item in queued.iter().filter(|q| q.status == ItemStatus::Submitted && now - q.enqueued_at < WINDOW_SECS) {
+        if let Some(signature) = &item.signature {
+            submitted
+                .entry(item.network.as_str())
+                .or_default()
+                .entry(signature.clone())
+                .or_insert((item.enqueued_at, None));
+        }
+    }
+
+    let mut orphans = memo_index::load_orphans();
+    let orphans_before = orphans.clone();
+    for network in &networks {
+        let rpc_url = rpc.primary(network);
+
+        // Transactions that landed while fuego was off, or were sent from elsewhere
+        let mut seen: HashSet<String> = HashSet::new();
+        for wallet in &wallets {
+            match stats::find_missing(&rpc_url, network, wallet, options.depth).await {
+                Ok((scan, missing)) => {
+                    report.signatures_scanned += scan.signatures_scanned;
+                    report.depth_limited |= scan.depth_limited;
+                    // A transfer between two reconciled wallets shows up in both histories
+                    for entry in missing.into_iter().filter(|m| seen.insert(m.signature.clone())) {
+                        report.added += 1;
+                        if !options.dry_run {
+                            memo_index::record_backfill(&entry.signature, network, entry.memos, entry.block_time);
+                        }
+                    }
+                }
+                Err(e) => report.errors.push(format!("{} history on {}: {}", wallet, network, e)),
+            }
+        }
+
+        // How the submissions the tracker lost track of ended
+        let Some(signatures) = submitted.get(network.as_str()) else {
+            continue;
+        };
+        let list: Vec<String> = signatures.keys().cloned().collect();
+        let http = crate::trace::http_client();
+        for batch in list.chunks(STATUS_BATCH) {
+            let statuses = match fetch_statuses(&http, &rpc_url, batch, true).await {
+                Ok(statuses) => statuses,
+                Err(e) => {
+                    report.errors.push(format!("Signature statuses on {}: {}", network, e));
+                    continue;
+                }
+            };
+            report.signatures_checked += batch.len();
+            for (signature, status) in batch.iter().zip(statuses) {
+                let (submitted_at, yid) = &signatures[signature];
+                if status.is_null() {
+                    // Still possibly in flight until its blockhash lapses
+                    if now - submitted_at >= EXPIRY_SECS {
+                        report.orphans.push(Orphan {
+                            signature: signature.clone(),
+                            network: network.clone(),
+                            yid: yid.clone(),
+                        });
+                        orphans.insert(signature.clone());
+                    }
+                    continue;
+                }
+                if orphans.remove(signature) {
+                    report.recovered += 1;
+                }
+                let slot = status["slot"].as_u64();
+                let (state, detail) = if !status["err"].is_null() {
+                    (TrackState::Failed, Some(status["err"].to_string()))
+                } else if status["confirmationStatus"].as_str() == Some("finalized") {
+                    (TrackState::Finalized, None)
+                } else if status["confirmationStatus"].as_str() == Some("confirmed") {
+                    (TrackState::Confirmed, None)
+                } else {
+                    continue;
+                };
+                let changed = if options.dry_run {
+                    tracker.status(signature).map(|s| s.state) != Some(state)
+                } else {
+                    tracker.record_reconciled(signature, network, state, slot, detail)
+                };
+                if changed {
+                    report.updated += 1;
+                }
+            }
+        }
+    }
+
+    report.orphaned = report.orphans.len();
+    if !options.dry_run && orphans != orphans_before {
+        if let Err(e) = memo_index::save_orphans(&orphans) {
+            report.errors.push(format!("Failed to save orphan flags: {}", e));
+        }
+    }
+    report.elapsed_ms = started.elapsed().as_millis() as u64;
+    report
+}
+
+impl Report {
+    /// One line for the server log.
+    pub fn summary(&self) -> String {
+        format!(
+            "Reconciliation{}: {} added, {} updated, {} orphaned, {} recovered across {} network(s) in {}ms{}",
+            if self.dry_run { " (dry run)" } else { "" },
+            self.added,
+            self.updated,
+            self.orphaned,
+            self.recovered,
+            self.networks.len(),
+            self.elapsed_ms,
+            if self.errors.is_empty() { String::new() } else { format!("; {} error(s): {}", self.errors.len(), self.errors.join("; ")) }
+        )
+    }
+}
@@ -0,0 +1,56 @@
+//! Tables compiled into the binary: the tokens fuego knows by name and the programs it can label.
+//! They need no config files, so a freshly copied binary works as-is.
+
+use serde::Serialize;
+
+use crate::mints::{TOKEN_2022_PROGRAM, TOKEN_PROGRAM};
+
+#[derive(Clone, Copy, Serialize)]
+pub struct KnownToken {
+    pub symbol: &'static str,
+    pub mint: &'static str,
+    pub decimals: u8,
+    pub token_program: &'static str,
+}
+
+/// Mainnet mints with a symbol in memos, balances and activity warnings.
+pub const KNOWN_TOKENS: &[KnownToken] = &[
+    KnownToken { symbol: "USDC", mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", decimals: 6, token_program: TOKEN_PROGRAM },
+    KnownToken { symbol: "USDT", mint: "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB", decimals: 6, token_program: TOKEN_PROGRAM },
+    KnownToken { symbol: "BONK", mint: "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263", decimals: 5, token_program: TOKEN_PROGRAM },
+    KnownToken { symbol: "JUP", mint: "JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN", decimals: 6, token_program: TOKEN_PROGRAM },
+    KnownToken { symbol: "PYUSD", mint: "2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo", decimals: 6, token_program: TOKEN_2022_PROGRAM },
+];
+
+#[derive(Clone, Copy, Serialize)]
+pub struct KnownProgram {
+    pub name: &'static str,
+    pub program_id: &'static str,
+    /// "system", "token", "memo", "compute_budget", "associated_token" or "dex"
+    pub kind: &'static str,
+}
+
+/// Programs recognised when describing instructions fuego did not build.
+pub const KNOWN_PROGRAMS: &[KnownProgram] = &[
+    KnownProgram { name: "System Program", program_id: "11111111111111111111111111111111", kind: "system" },
+    KnownProgram { name: "Compute Budget", program_id: "ComputeBudget111111111111111111111111111111", kind: "compute_budget" },
+    KnownProgram { name: "SPL Token", program_id: TOKEN_PROGRAM, kind: "token" },
+    KnownProgram { name: "Token-2022", program_id: TOKEN_2022_PROGRAM, kind: "token" },
+    KnownProgram { name: "Associated Token Account", program_id: "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL", kind: "associated_token" },
+    KnownProgram { name: "Memo", program_id: "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr", kind: "memo" },
+    KnownProgram { name: "Memo (v1)", program_id: "Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo", kind: "memo" },
+    KnownProgram { name: "Jupiter Aggregator v6", program_id: "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4", kind: "dex" },
+];
+
+pub fn token_symbol(mint: &str) -> Option<&'static str> {
+    KNOWN_TOKENS.iter().find(|t| t.mint == mint).map(|t| t.symbol)
+}
+
+/// The mint behind a registered symbol, for symbols written by older memos.
+pub fn token_mint(symbol: &str) -> Option<&'static str> {
+    KNOWN_TOKENS.iter().find(|t| t.symbol.eq_ignore_ascii_case(symbol)).map(|t| t.mint)
+}
+
+pub fn program(program_id: &str) -> Option<&'static KnownProgram> {
+    KNOWN_PROGRAMS.iter().find(|p| p.program_id == program_id)
+}
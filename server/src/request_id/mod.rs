@@ -0,0 +1,73 @@
+//! X-Request-Id, so an agent's logs and fuego's can be joined on one value. A caller's id is kept
+//! when it is short printable text, otherwise a UUID is made up. The id is on the request's log
+//! span, echoed in the response header, put in every JSON error body as `request_id`, and sent
+//! on the x402, webhook and raw JSON-RPC calls made while handling the request.
+
+use axum::http::HeaderMap;
+
+pub const HEADER: &str = "x-request-id";
+
+/// Longest caller-supplied id kept; longer ones are replaced.
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// The caller's id when usable, else a new UUID.
+pub fn from_headers(headers: &HeaderMap) -> String {
+    headers
+        .get(HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_LEN && id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Run a request's handling with `id` as the current request id.
+pub async fn scope<F: std::future::Future>(id: String, future: F) -> F::Output {
+    CURRENT.scope(id, future).await
+}
+
+pub fn current() -> Option<String> {
+    CURRENT.try_with(|id| id.clone()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn with_id(id: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER, HeaderValue::from_static(id));
+        headers
+    }
+
+    #[test]
+    fn a_usable_caller_id_is_kept() {
+        assert_eq!(from_headers(&with_id("agent-run-42")), "agent-run-42");
+        assert_eq!(from_headers(&with_id("  padded  ")), "padded");
+        let longest: &'static str = Box::leak("x".repeat(MAX_LEN).into_boxed_str());
+        assert_eq!(from_headers(&with_id(longest)), longest);
+    }
+
+    #[test]
+    fn a_missing_or_unusable_id_is_replaced_by_a_uuid() {
+        let too_long: &'static str = Box::leak("x".repeat(MAX_LEN + 1).into_boxed_str());
+        for headers in [HeaderMap::new(), with_id(""), with_id("two words"), with_id(too_long)] {
+            let id = from_headers(&headers);
+            assert!(uuid::Uuid::parse_str(&id).is_ok(), "{}", id);
+        }
+        assert_ne!(from_headers(&HeaderMap::new()), from_headers(&HeaderMap::new()));
+    }
+
+    #[tokio::test]
+    async fn the_id_is_current_only_inside_its_scope() {
+        assert_eq!(current(), None);
+        let inside = scope("req-1".to_string(), async { current() }).await;
+        assert_eq!(inside.as_deref(), Some("req-1"));
+        assert_eq!(current(), None);
+    }
+}
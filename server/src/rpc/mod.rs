@@ -0,0 +1,1032 @@
+//! RPC endpoint pool: configured endpoints per network, rolling latency/error stats per endpoint,
+//! the strategy deciding which endpoint serves reads, and failover to the network's next endpoint
+//! when one can't be reached. Each call to an endpoint is cut off after the RPC timeout:
+//! FUEGO_RPC_TIMEOUT_MS, or a request's own `timeout_ms`. Every endpoint has a circuit breaker:
+//! enough failures in a row open it, and calls skip the endpoint without waiting on it until the
+//! cool-down ends and one probe call is let through. The probe's outcome closes or reopens it.
+
+use async_trait::async_trait;
+use serde::Serialize;
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::nonblocking::rpc_client::RpcClient as AsyncRpcClient;
+use solana_client::rpc_client::{RpcClient, RpcClientConfig};
+use solana_client::rpc_config::CommitmentConfig;
+use solana_client::rpc_custom_error::{NodeUnhealthyErrorData, JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE};
+use solana_client::rpc_request::{RpcError, RpcRequest, RpcResponseErrorData};
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+
+use crate::network::Network;
+
+/// Calls slower than this are logged at warn level.
+const SLOW_CALL_MS: u64 = 1_000;
+
+/// Number of recent calls kept per endpoint for latency and error-rate stats.
+const STATS_WINDOW: usize = 50;
+
+/// An endpoint failing more than this share of recent calls is skipped for reads.
+const UNHEALTHY_ERROR_RATE: f64 = 0.5;
+
+/// Consecutive transport failures (connection errors, 429s, timeouts) that open a circuit.
+const DEFAULT_BREAKER_FAILURES: u32 = 3;
+
+/// How long an open circuit is skipped before a probe call is let through.
+const DEFAULT_BREAKER_COOLDOWN_SECS: u64 = 30;
+
+/// JSON-RPC "node is unhealthy" (behind the cluster); another node can serve the call.
+const NODE_UNHEALTHY: i64 = -32005;
+
+/// Transports kept for reuse, all for configured endpoints. Any other URL gets one built for the
+/// request and dropped after it, as does a configured endpoint past this many.
+const MAX_CACHED_TRANSPORTS: usize = 64;
+
+type StatsMap = Mutex<HashMap<String, EndpointStats>>;
+
+type TransportMap = Mutex<HashMap<String, Arc<dyn RpcSender + Send + Sync>>>;
+
+pub const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+
+/// Longest timeout a request may ask for; transports give up on their own after this.
+pub const MAX_TIMEOUT_MS: u64 = 120_000;
+
+static DEFAULT_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+tokio::task_local! {
+    static SCOPE: CallScope;
+}
+
+/// The RPC timeout of the request being handled, why its calls gave up, if they did, and the
+/// endpoints it brought itself.
+#[derive(Clone)]
+struct CallScope {
+    timeout: Duration,
+    gave_up: Arc<Mutex<Option<RpcGaveUp>>>,
+    /// The request's own `rpc_url`s, by URL, once the egress policy approved them
+    pinned: Arc<Mutex<HashMap<String, PinnedEndpoint>>>,
+}
+
+/// A per-request endpoint's client, which only connects to the addresses the egress check
+/// resolved, and the RPC transport sending over it.
+#[derive(Clone)]
+struct PinnedEndpoint {
+    http: reqwest::Client,
+    transport: Arc<dyn RpcSender + Send + Sync>,
+}
+
+/// Why a request's RPC calls failed without an answer from any endpoint.
+#[derive(Clone)]
+pub enum RpcGaveUp {
+    Timeout(RpcTimeout),
+    CircuitOpen(CircuitOpen),
+}
+
+/// An RPC call that got no answer in time, from any endpoint it tried.
+#[derive(Clone)]
+pub struct RpcTimeout {
+    /// The last endpoint tried, redacted
+    pub endpoint: String,
+    pub elapsed_ms: u64,
+}
+
+/// A call that wasn't sent because every endpoint it could use had its circuit open.
+#[derive(Clone)]
+pub struct CircuitOpen {
+    /// The first endpoint it would have tried, redacted
+    pub endpoint: String,
+    /// Until the soonest of those circuits lets a probe through
+    pub retry_after_secs: u64,
+}
+
+/// FUEGO_RPC_TIMEOUT_MS, or 10 seconds.
+pub fn default_timeout() -> Duration {
+    *DEFAULT_TIMEOUT.get_or_init(|| {
+        let ms = match std::env::var("FUEGO_RPC_TIMEOUT_MS") {
+            Ok(value) => match value.trim().parse::<u64>() {
+                Ok(ms) if (1..=MAX_TIMEOUT_MS).contains(&ms) => ms,
+                _ => {
+                    eprintln!("Ignoring FUEGO_RPC_TIMEOUT_MS '{}': expected 1-{} ms", value, MAX_TIMEOUT_MS);
+                    DEFAULT_TIMEOUT_MS
+                }
+            },
+            Err(_) => DEFAULT_TIMEOUT_MS,
+        };
+        Duration::from_millis(ms)
+    })
+}
+
+/// The timeout for RPC calls made now: the request's own, else the default.
+pub fn timeout() -> Duration {
+    SCOPE.try_with(|scope| scope.timeout).unwrap_or_else(|_| default_timeout())
+}
+
+/// Run a request with its RPC timeout, returning why its last failed call gave up, if one did.
+pub async fn scope<F: Future>(timeout: Duration, f: F) -> (F::Output, Option<RpcGaveUp>) {
+    let scope = CallScope { timeout, gave_up: Arc::new(Mutex::new(None)), pinned: Arc::new(Mutex::new(HashMap::new())) };
+    let gave_up = scope.gave_up.clone();
+    let output = SCOPE.scope(scope, f).await;
+    let gave_up = gave_up.lock().unwrap().take();
+    (output, gave_up)
+}
+
+/// Note a call that ran out of time, for the request's error response. Raw JSON-RPC calls made
+/// with reqwest report theirs here too.
+pub fn record_timeout(url: &str, elapsed: Duration) {
+    let _ = SCOPE.try_with(|scope| {
+        *scope.gave_up.lock().unwrap() =
+            Some(RpcGaveUp::Timeout(RpcTimeout { endpoint: redact_url(url), elapsed_ms: elapsed.as_millis() as u64 }));
+    });
+}
+
+/// Send every call to `url` for the rest of the request over `http`: a request's own endpoint,
+/// with the client `EgressPolicy::client_builder` pinned to the addresses its check approved.
+pub fn pin(url: &str, http: reqwest::Client) {
+    let transport: Arc<dyn RpcSender + Send + Sync> = Arc::from(pinned_transport(url.to_string(), http.clone()));
+    let _ = SCOPE.try_with(|scope| {
+        scope.pinned.lock().unwrap().insert(url.to_string(), PinnedEndpoint { http, transport });
+    });
+}
+
+fn pinned(url: &str) -> Option<PinnedEndpoint> {
+    SCOPE.try_with(|scope| scope.pinned.lock().unwrap().get(url).cloned()).ok().flatten()
+}
+
+/// The HTTP client for a raw JSON-RPC call to `url`: the pinned one when the request brought
+/// `url` itself, otherwise the shared `trace::http_client()`.
+pub fn http_client(url: &str) -> reqwest::Client {
+    pinned(url).map(|p| p.http).unwrap_or_else(crate::trace::http_client)
+}
+
+fn record_circuit_open(url: &str, retry_after_secs: u64) {
+    let _ = SCOPE.try_with(|scope| {
+        *scope.gave_up.lock().unwrap() = Some(RpcGaveUp::CircuitOpen(CircuitOpen { endpoint: redact_url(url), retry_after_secs }));
+    });
+}
+
+/// When an endpoint's circuit opens and for how long.
+#[derive(Clone, Copy)]
+pub struct BreakerSettings {
+    /// Transport failures in a row that open the circuit
+    pub failures: u32,
+    pub cooldown_secs: u64,
+}
+
+impl Default for BreakerSettings {
+    fn default() -> Self {
+        BreakerSettings { failures: DEFAULT_BREAKER_FAILURES, cooldown_secs: DEFAULT_BREAKER_COOLDOWN_SECS }
+    }
+}
+
+impl BreakerSettings {
+    /// FUEGO_RPC_BREAKER_FAILURES and FUEGO_RPC_BREAKER_COOLDOWN_SECS, else `rpc_breaker_failures`
+    /// and `rpc_breaker_cooldown_secs` from the server config, else 3 failures and 30 seconds.
+    fn load(config: &crate::config::ServerConfig) -> Self {
+        fn setting<T: std::str::FromStr + PartialOrd + From<u8>>(var: &str, configured: Option<T>, default: T) -> T {
+            match std::env::var(var) {
+                Ok(value) => match value.trim().parse::<T>() {
+                    Ok(n) if n >= T::from(1) => n,
+                    _ => {
+                        eprintln!("Ignoring {} '{}': expected a whole number of at least 1", var, value);
+                        configured.unwrap_or(default)
+                    }
+                },
+                Err(_) => configured.unwrap_or(default),
+            }
+        }
+        BreakerSettings {
+            failures: setting("FUEGO_RPC_BREAKER_FAILURES", config.rpc_breaker_failures, DEFAULT_BREAKER_FAILURES),
+            cooldown_secs: setting("FUEGO_RPC_BREAKER_COOLDOWN_SECS", config.rpc_breaker_cooldown_secs, DEFAULT_BREAKER_COOLDOWN_SECS),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+enum Circuit {
+    #[default]
+    Closed,
+    /// Skipped until then (unix seconds)
+    Open { until: i64 },
+    /// A probe call was let through then (unix seconds); other calls still skip the endpoint
+    HalfOpen { since: i64 },
+}
+
+impl Circuit {
+    fn name(&self) -> &'static str {
+        match self {
+            Circuit::Closed => "closed",
+            Circuit::Open { .. } => "open",
+            Circuit::HalfOpen { .. } => "half_open",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RpcStrategy {
+    Primary,
+    Fastest,
+    RoundRobin,
+}
+
+impl RpcStrategy {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "primary" => Some(RpcStrategy::Primary),
+            "fastest" => Some(RpcStrategy::Fastest),
+            "round-robin" => Some(RpcStrategy::RoundRobin),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct EndpointStats {
+    /// (latency in ms, succeeded) for the most recent calls
+    samples: VecDeque<(u64, bool)>,
+    last_success: Option<i64>,
+    /// Transport failures in a row, reset by any answer
+    consecutive_failures: u32,
+    circuit: Circuit,
+    /// Times the circuit changed state since startup, by the state it went to
+    transitions: BTreeMap<&'static str, u64>,
+    /// Failed calls since startup by `error_kind`, counted by the failover sender
+    errors: BTreeMap<&'static str, u64>,
+}
+
+impl EndpointStats {
+    fn error_rate(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let errors = self.samples.iter().filter(|(_, ok)| !ok).count();
+        errors as f64 / self.samples.len() as f64
+    }
+
+    fn avg_latency_ms(&self) -> Option<u64> {
+        let ok: Vec<u64> = self.samples.iter().filter(|(_, ok)| *ok).map(|(ms, _)| *ms).collect();
+        if ok.is_empty() {
+            return None;
+        }
+        Some(ok.iter().sum::<u64>() / ok.len() as u64)
+    }
+
+    /// Nearest-rank percentile (0-100) of recent successful calls' latency.
+    fn latency_percentile(&self, percentile: usize) -> Option<u64> {
+        let mut ok: Vec<u64> = self.samples.iter().filter(|(_, ok)| *ok).map(|(ms, _)| *ms).collect();
+        if ok.is_empty() {
+            return None;
+        }
+        ok.sort_unstable();
+        let rank = (percentile * ok.len()).div_ceil(100).max(1);
+        Some(ok[rank - 1])
+    }
+
+    fn is_closed(&self) -> bool {
+        self.circuit == Circuit::Closed
+    }
+
+    fn open_until(&self) -> Option<i64> {
+        match self.circuit {
+            Circuit::Open { until } => Some(until),
+            _ => None,
+        }
+    }
+
+    /// Whether a call may go to this endpoint now. The first call after an open circuit's
+    /// cool-down is the probe and half-opens it; a probe that never reported back (its request
+    /// was dropped) is replaced after another cool-down.
+    fn admits(&mut self, url: &str, now: i64, breaker: BreakerSettings) -> bool {
+        match self.circuit {
+            Circuit::Closed => true,
+            Circuit::Open { until } if now >= until => {
+                self.transition(url, Circuit::HalfOpen { since: now });
+                true
+            }
+            Circuit::HalfOpen { since } if now - since >= breaker.cooldown_secs as i64 => {
+                self.circuit = Circuit::HalfOpen { since: now };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Seconds until a call that `admits` refused could be let through.
+    fn retry_after_secs(&self, now: i64, breaker: BreakerSettings) -> u64 {
+        let at = match self.circuit {
+            Circuit::Closed => now,
+            Circuit::Open { until } => until,
+            Circuit::HalfOpen { since } => since + breaker.cooldown_secs as i64,
+        };
+        (at - now).max(1) as u64
+    }
+
+    fn transition(&mut self, url: &str, to: Circuit) {
+        let endpoint = redact_url(&crate::providers::scrub(url));
+        match to {
+            Circuit::Open { .. } => tracing::warn!(endpoint = %endpoint, from = self.circuit.name(), "rpc circuit opened"),
+            _ => tracing::info!(endpoint = %endpoint, from = self.circuit.name(), to = to.name(), "rpc circuit changed"),
+        }
+        self.circuit = to;
+        *self.transitions.entry(to.name()).or_default() += 1;
+    }
+}
+
+/// Where an unknown network name is sent. Handlers refuse such names before getting this far;
+/// this only guarantees a name is never turned into a hostname. `.invalid` never resolves.
+const UNKNOWN_NETWORK_ENDPOINT: &str = "https://unknown-network.invalid";
+
+/// An RPC URL from the environment or a request: http or https with a host, nothing else.
+pub fn validate_url(url: &str) -> Result<String, String> {
+    let url = url.trim();
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid RPC URL: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("RPC URL must be http or https, not {}", parsed.scheme()));
+    }
+    if parsed.host_str().map(|h| h.is_empty()).unwrap_or(true) {
+        return Err("RPC URL has no host".to_string());
+    }
+    Ok(url.to_string())
+}
+
+/// Every blocking RPC client is made here, so test builds can slip the fault-injection transport
+/// in front of the real one. Only for background jobs already on a blocking thread (the fee
+/// wallet check and the submit queue run it under `spawn_blocking`); request handlers use the
+/// pool. Calls time out after the default RPC timeout.
+pub fn client(url: impl ToString, commitment: CommitmentConfig) -> RpcClient {
+    #[cfg(feature = "fault-injection")]
+    {
+        RpcClient::new_sender(crate::faults::sender(url.to_string(), default_timeout()), RpcClientConfig::with_commitment(commitment))
+    }
+    #[cfg(not(feature = "fault-injection"))]
+    {
+        RpcClient::new_with_timeout_and_commitment(url.to_string(), default_timeout(), commitment)
+    }
+}
+
+/// The transport every RPC client sends through; fault injection wraps it in test builds. Its own
+/// timeout is the longest allowed; the sender cuts each call off at the request's timeout.
+fn transport(url: String) -> Box<dyn RpcSender + Send + Sync> {
+    let timeout = Duration::from_millis(MAX_TIMEOUT_MS);
+    #[cfg(feature = "fault-injection")]
+    {
+        Box::new(crate::faults::sender(url, timeout))
+    }
+    #[cfg(not(feature = "fault-injection"))]
+    {
+        Box::new(solana_rpc_client::http_sender::HttpSender::new_with_timeout(url, timeout))
+    }
+}
+
+/// The transport for a request's own endpoint, over its pinned client.
+fn pinned_transport(url: String, http: reqwest::Client) -> Box<dyn RpcSender + Send + Sync> {
+    let sender = PinnedSender { url, http, next_id: AtomicU64::new(1), stats: Mutex::new(RpcTransportStats::default()) };
+    #[cfg(feature = "fault-injection")]
+    {
+        Box::new(crate::faults::wrap(Box::new(sender)))
+    }
+    #[cfg(not(feature = "fault-injection"))]
+    {
+        Box::new(sender)
+    }
+}
+
+/// Sends JSON-RPC over a reqwest client of ours, reading answers and errors the way solana's
+/// HttpSender does. That transport builds its own client, so it can't be pinned.
+struct PinnedSender {
+    url: String,
+    http: reqwest::Client,
+    next_id: AtomicU64,
+    stats: Mutex<RpcTransportStats>,
+}
+
+impl PinnedSender {
+    async fn post(&self, body: serde_json::Value) -> ClientResult<serde_json::Value> {
+        // Shaped like the transport failures failover and the error counts know; the URL may carry a key
+        let unreachable = |e: reqwest::Error| -> ClientError {
+            let kind = if e.is_timeout() { std::io::ErrorKind::TimedOut } else { std::io::ErrorKind::Other };
+            ClientErrorKind::Io(std::io::Error::new(kind, e.without_url().to_string())).into()
+        };
+        let response = self
+            .http
+            .post(&self.url)
+            .timeout(Duration::from_millis(MAX_TIMEOUT_MS))
+            .json(&body)
+            .send()
+            .await
+            .map_err(unreachable)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ClientErrorKind::Io(std::io::Error::other(format!("RPC endpoint answered HTTP {}", status))).into());
+        }
+        let mut json: serde_json::Value = response.json().await.map_err(unreachable)?;
+        if !json["error"].is_object() {
+            return Ok(json["result"].take());
+        }
+        let error = json["error"].take();
+        let (Some(code), Some(message)) = (error["code"].as_i64(), error["message"].as_str()) else {
+            return Err(RpcError::RpcRequestError(format!("Failed to deserialize RPC error response: {}", error)).into());
+        };
+        let data = match code {
+            JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE => serde_json::from_value(error["data"].clone())
+                .map(RpcResponseErrorData::SendTransactionPreflightFailure)
+                .unwrap_or(RpcResponseErrorData::Empty),
+            NODE_UNHEALTHY => serde_json::from_value::<NodeUnhealthyErrorData>(error["data"].clone())
+                .map(|data| RpcResponseErrorData::NodeUnhealthy { num_slots_behind: data.num_slots_behind })
+                .unwrap_or(RpcResponseErrorData::Empty),
+            _ => RpcResponseErrorData::Empty,
+        };
+        Err(RpcError::RpcResponseError { code, message: message.to_string(), data }.into())
+    }
+}
+
+#[async_trait]
+impl RpcSender for PinnedSender {
+    async fn send(&self, request: RpcRequest, params: serde_json::Value) -> ClientResult<serde_json::Value> {
+        let started = Instant::now();
+        let body = request.build_request_json(self.next_id.fetch_add(1, Ordering::Relaxed), params);
+        let result = self.post(body).await;
+        let mut stats = self.stats.lock().unwrap();
+        stats.request_count += 1;
+        stats.elapsed_time += started.elapsed();
+        result
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+}
+
+/// What kind of failure a call ended in, for the per-endpoint error counts.
+fn error_kind(err: &ClientError) -> &'static str {
+    match err.kind() {
+        _ if is_timeout(err) => "timeout",
+        ClientErrorKind::Io(_) => "connect",
+        ClientErrorKind::Reqwest(e) if e.is_connect() => "connect",
+        ClientErrorKind::Reqwest(e) => match e.status().map(|s| s.as_u16()) {
+            Some(429) => "rate_limited",
+            Some(status) if status >= 500 => "server_error",
+            _ => "http_error",
+        },
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) if *code == NODE_UNHEALTHY => "node_unhealthy",
+        // Answered errors: preflight failures, bad params, missing accounts
+        ClientErrorKind::RpcError(_) | ClientErrorKind::TransactionError(_) => "rpc_error",
+        _ => "other",
+    }
+}
+
+/// No answer within the RPC timeout.
+fn is_timeout(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Io(e) => e.kind() == std::io::ErrorKind::TimedOut,
+        ClientErrorKind::Reqwest(e) => e.is_timeout(),
+        _ => false,
+    }
+}
+
+/// Failures another endpoint might not have: the endpoint couldn't be reached, timed out, rate
+/// limited us (after the transport's own 429 retries), returned a 5xx, or reports itself behind.
+/// Anything the node actually answered, errors included, is final.
+fn is_failover_error(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(e) => {
+            e.is_connect()
+                || e.is_timeout()
+                || e.status().map(|s| s.as_u16() == 429 || s.is_server_error()).unwrap_or(false)
+        }
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => *code == NODE_UNHEALTHY,
+        _ => false,
+    }
+}
+
+/// Sends each call to the first endpoint in order that answers, skipping those whose circuit is
+/// open, and fails at once when every circuit is. Resending a transaction after a timeout is safe: it is the same signed transaction, so it can
+/// land at most once.
+struct FailoverSender {
+    /// The requested endpoint first, then the network's other endpoints in configured order
+    endpoints: Vec<String>,
+    /// Index into `endpoints` of the one that answered the last call
+    served: AtomicUsize,
+    /// The pool's transports, one per endpoint and shared by every request so connections are reused
+    transports: Arc<TransportMap>,
+    stats: Arc<StatsMap>,
+    /// The pool's configured endpoints; only these get stats and a circuit breaker
+    tracked: Arc<HashSet<String>>,
+    breaker: BreakerSettings,
+}
+
+impl FailoverSender {
+    fn transport(&self, url: &str) -> Arc<dyn RpcSender + Send + Sync> {
+        if let Some(pinned) = pinned(url) {
+            return pinned.transport;
+        }
+        let mut transports = self.transports.lock().unwrap();
+        if let Some(transport) = transports.get(url) {
+            return transport.clone();
+        }
+        let made: Arc<dyn RpcSender + Send + Sync> = Arc::from(transport(url.to_string()));
+        if self.tracked.contains(url) && transports.len() < MAX_CACHED_TRANSPORTS {
+            transports.insert(url.to_string(), made.clone());
+        }
+        made
+    }
+}
+
+#[async_trait]
+impl RpcSender for FailoverSender {
+    async fn send(&self, request: RpcRequest, params: serde_json::Value) -> ClientResult<serde_json::Value> {
+        let (timeout, started) = (timeout(), Instant::now());
+        let mut last_error = None;
+        // Soonest an endpoint skipped for its open circuit takes calls again
+        let mut retry_after_secs = None;
+        for (i, url) in self.endpoints.iter().enumerate() {
+            // Checked just before each call, so only an endpoint actually called becomes the probe
+            {
+                let mut stats = self.stats.lock().unwrap();
+                let now = chrono::Utc::now().timestamp();
+                if let Some(s) = stats.get_mut(url) {
+                    if !s.admits(url, now, self.breaker) {
+                        let secs = s.retry_after_secs(now, self.breaker);
+                        retry_after_secs = Some(retry_after_secs.map_or(secs, |soonest: u64| soonest.min(secs)));
+                        continue;
+                    }
+                }
+            }
+            self.served.store(i, Ordering::Relaxed);
+            // A child of the request's span, so a slow answer is tied to the request that waited
+            let span = tracing::info_span!("rpc", method = %request, endpoint = %redact_url(&crate::providers::scrub(url)));
+            let call_started = Instant::now();
+            let transport = self.transport(url);
+            let call = tokio::time::timeout(timeout, transport.send(request, params.clone()));
+            let result = match call.instrument(span.clone()).await {
+                Ok(result) => result,
+                Err(_) => Err(ClientErrorKind::Io(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("{} got no answer within {} ms", request, timeout.as_millis()),
+                ))
+                .into()),
+            };
+            let elapsed_ms = call_started.elapsed().as_millis() as u64;
+            let tracked = self.tracked.contains(url);
+            if let (Err(e), true) = (&result, tracked) {
+                let mut stats = self.stats.lock().unwrap();
+                *stats.entry(url.clone()).or_default().errors.entry(error_kind(e)).or_default() += 1;
+            }
+            match result {
+                Err(e) if is_failover_error(&e) => {
+                    // The error text can carry the endpoint's full URL, so it isn't logged
+                    tracing::warn!(parent: &span, elapsed_ms, timed_out = is_timeout(&e), "rpc endpoint unreachable");
+                    if tracked {
+                        record_transport(&self.stats, url, false, self.breaker);
+                    }
+                    last_error = Some(e);
+                }
+                result => {
+                    if elapsed_ms >= SLOW_CALL_MS {
+                        tracing::warn!(parent: &span, elapsed_ms, ok = result.is_ok(), "slow rpc call");
+                    } else {
+                        tracing::debug!(parent: &span, elapsed_ms, ok = result.is_ok(), "rpc call");
+                    }
+                    if tracked {
+                        record_transport(&self.stats, url, true, self.breaker);
+                    }
+                    return result;
+                }
+            }
+        }
+        if last_error.as_ref().map(is_timeout).unwrap_or(false) {
+            record_timeout(&self.url(), started.elapsed());
+        }
+        if let (None, Some(secs)) = (&last_error, retry_after_secs) {
+            let url = &self.endpoints[0];
+            record_circuit_open(url, secs);
+            return Err(ClientErrorKind::Custom(format!(
+                "RPC endpoint {} is failing and was not called; retry in {} s",
+                redact_url(url),
+                secs
+            ))
+            .into());
+        }
+        Err(last_error.unwrap_or_else(|| ClientErrorKind::Custom("No RPC endpoint configured".to_string()).into()))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        let mut total = RpcTransportStats::default();
+        let transports = self.transports.lock().unwrap();
+        for transport in self.endpoints.iter().filter_map(|url| transports.get(url)) {
+            let stats = transport.get_transport_stats();
+            total.request_count += stats.request_count;
+            total.elapsed_time += stats.elapsed_time;
+            total.rate_limited_time += stats.rate_limited_time;
+        }
+        total
+    }
+
+    /// The endpoint that answered the last call; the requested one before any call.
+    fn url(&self) -> String {
+        self.endpoints[self.served.load(Ordering::Relaxed)].clone()
+    }
+}
+
+/// Track whether an endpoint could be reached. Any answer closes its circuit; enough failures in
+/// a row, or a failed probe, open it for the cool-down.
+fn record_transport(stats: &StatsMap, url: &str, reached: bool, breaker: BreakerSettings) {
+    let mut stats = stats.lock().unwrap();
+    let entry = stats.entry(url.to_string()).or_default();
+    if reached {
+        entry.consecutive_failures = 0;
+        if !entry.is_closed() {
+            entry.transition(url, Circuit::Closed);
+        }
+        return;
+    }
+    entry.consecutive_failures += 1;
+    let probe_failed = matches!(entry.circuit, Circuit::HalfOpen { .. });
+    if probe_failed || (entry.is_closed() && entry.consecutive_failures >= breaker.failures) {
+        entry.transition(url, Circuit::Open { until: chrono::Utc::now().timestamp() + breaker.cooldown_secs as i64 });
+        entry.consecutive_failures = 0;
+    }
+}
+
+/// A raw JSON-RPC call's reqwest error as text, noting it for the response when it was a timeout.
+pub fn http_error(url: &str, started: Instant, err: reqwest::Error) -> String {
+    if err.is_timeout() {
+        record_timeout(url, started.elapsed());
+    }
+    err.to_string()
+}
+
+/// The node's "could not find account" answer (invalid params, -32602) for an account that
+/// doesn't exist. Transport failures, timeouts and rate limits are never this.
+pub fn is_account_not_found(err: &ClientError) -> bool {
+    matches!(
+        err.kind(),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code: -32602, message, .. })
+            if message.contains("could not find account")
+    )
+}
+
+/// Strip credentials and query strings (where providers put API keys) before showing a URL.
+pub fn redact_url(url: &str) -> String {
+    let without_query = url.split(['?', '#']).next().unwrap_or(url);
+    match without_query.split_once("://") {
+        Some((scheme, rest)) => {
+            let host_and_path = rest.rsplit_once('@').map(|(_, h)| h).unwrap_or(rest);
+            format!("{}://{}", scheme, host_and_path)
+        }
+        None => without_query.to_string(),
+    }
+}
+
+#[derive(Clone)]
+pub struct RpcPool {
+    endpoints: Arc<HashMap<String, Vec<String>>>,
+    /// Every endpoint the operator configured: listed endpoints, the public clusters' endpoints
+    /// (or the fallback in their place) and provider profiles. Stats are only kept for these, so
+    /// per-request URLs can't grow them or show up in /rpc-status and /metrics.
+    tracked: Arc<HashSet<String>>,
+    stats: Arc<StatsMap>,
+    /// Shared transports, so requests reuse connections
+    transports: Arc<TransportMap>,
+    strategy: RpcStrategy,
+    next: Arc<AtomicUsize>,
+    /// FUEGO_RPC_URL or `rpc_url`: used instead of the public endpoint for unlisted networks
+    fallback: Option<String>,
+    breaker: BreakerSettings,
+}
+
+impl RpcPool {
+    /// Endpoints from FUEGO_RPC_ENDPOINTS ("mainnet-beta=https://a,https://b;devnet=https://c"),
+    /// strategy from FUEGO_RPC_STRATEGY ("primary" default, "fastest", "round-robin"), and the
+    /// endpoint for unlisted networks from FUEGO_RPC_URL ("{network}" is replaced by the network).
+    /// Without the variables, `rpc_endpoints` and `rpc_url` from the server config apply.
+    pub fn load(config: &crate::config::ServerConfig) -> Self {
+        let mut endpoints: HashMap<String, Vec<String>> = HashMap::new();
+        if let Ok(spec) = std::env::var("FUEGO_RPC_ENDPOINTS") {
+            for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+                match entry.split_once('=') {
+                    Some((network, urls)) => {
+                        let urls: Vec<String> = urls
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|u| !u.is_empty())
+                            .map(str::to_string)
+                            .collect();
+                        if !urls.is_empty() {
+                            endpoints.insert(network.trim().to_string(), urls);
+                        }
+                    }
+                    None => eprintln!("Ignoring malformed FUEGO_RPC_ENDPOINTS entry: {}", entry),
+                }
+            }
+        } else {
+            // Checked when the config loaded
+            for (network, urls) in &config.rpc_endpoints {
+                let urls: Vec<String> = urls.iter().map(|u| u.trim().to_string()).collect();
+                endpoints.insert(network.trim().to_string(), urls);
+            }
+        }
+
+        let strategy = match std::env::var("FUEGO_RPC_STRATEGY") {
+            Ok(value) => RpcStrategy::parse(&value).unwrap_or_else(|| {
+                eprintln!("Unknown FUEGO_RPC_STRATEGY '{}', using primary", value);
+                RpcStrategy::Primary
+            }),
+            Err(_) => RpcStrategy::Primary,
+        };
+
+        let fallback = match std::env::var("FUEGO_RPC_URL").ok().or_else(|| config.rpc_url.clone()) {
+            Some(url) if !url.trim().is_empty() => match validate_url(&url) {
+                Ok(url) => Some(url),
+                Err(e) => {
+                    eprintln!("Ignoring FUEGO_RPC_URL: {}", e);
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        let mut pool = RpcPool {
+            endpoints: Arc::new(endpoints),
+            tracked: Arc::new(HashSet::new()),
+            stats: Arc::new(Mutex::new(HashMap::new())),
+            transports: Arc::new(Mutex::new(HashMap::new())),
+            strategy,
+            next: Arc::new(AtomicUsize::new(0)),
+            fallback,
+            breaker: BreakerSettings::load(config),
+        };
+        let mut tracked: HashSet<String> = pool.endpoints.values().flatten().cloned().collect();
+        for network in ["mainnet-beta", "devnet", "testnet"] {
+            tracked.extend(pool.endpoints(network));
+        }
+        tracked.extend(config.providers.values().filter_map(|entry| validate_url(entry.url()).ok()));
+        pool.tracked = Arc::new(tracked);
+        pool
+    }
+
+    /// Configured endpoints for a network, falling back to FUEGO_RPC_URL, then the public endpoint.
+    pub fn endpoints(&self, network: &str) -> Vec<String> {
+        if let Some(urls) = self.endpoints.get(network) {
+            return urls.clone();
+        }
+        vec![match (network.parse::<Network>(), &self.fallback) {
+            (Ok(_), Some(url)) => url.replace("{network}", network),
+            (Ok(known), None) => known.public_endpoint().unwrap_or(UNKNOWN_NETWORK_ENDPOINT).to_string(),
+            (Err(_), _) => UNKNOWN_NETWORK_ENDPOINT.to_string(),
+        }]
+    }
+
+    /// The network a name stands for: a public cluster or one with configured endpoints.
+    pub fn network(&self, name: &str) -> Option<Network> {
+        name.parse()
+            .ok()
+            .or_else(|| self.endpoints.get(name).map(|urls| Network::Custom(urls[0].clone())))
+    }
+
+    /// Every name `network` accepts, for error messages.
+    pub fn network_names(&self) -> Vec<String> {
+        let mut custom: Vec<String> = self.endpoints.keys().filter(|n| n.parse::<Network>().is_err()).cloned().collect();
+        custom.sort();
+        let mut names: Vec<String> = ["mainnet-beta", "devnet", "testnet"].iter().map(|n| n.to_string()).collect();
+        names.extend(custom);
+        names
+    }
+
+    /// First configured endpoint; used for submissions and anything that must be consistent.
+    pub fn primary(&self, network: &str) -> String {
+        self.endpoints(network).remove(0)
+    }
+
+    /// Endpoint that should serve a read for this network under the configured strategy.
+    pub fn select_read(&self, network: &str) -> String {
+        let endpoints = self.endpoints(network);
+        if endpoints.len() == 1 || self.strategy == RpcStrategy::Primary {
+            return endpoints[0].clone();
+        }
+
+        let stats = self.stats.lock().unwrap();
+        let healthy: Vec<&String> = endpoints
+            .iter()
+            .filter(|url| stats.get(*url).map(|s| s.error_rate() <= UNHEALTHY_ERROR_RATE && s.is_closed()).unwrap_or(true))
+            .collect();
+        let candidates: Vec<&String> = if healthy.is_empty() { endpoints.iter().collect() } else { healthy };
+
+        match self.strategy {
+            RpcStrategy::RoundRobin => {
+                let i = self.next.fetch_add(1, Ordering::Relaxed);
+                candidates[i % candidates.len()].clone()
+            }
+            // Endpoints without samples sort first so every endpoint gets measured
+            _ => candidates
+                .into_iter()
+                .min_by_key(|url| stats.get(*url).and_then(|s| s.avg_latency_ms()).unwrap_or(0))
+                .cloned()
+                .unwrap_or_else(|| endpoints[0].clone()),
+        }
+    }
+
+    /// An async client for `network` that starts at `url`. Clients are cheap and made per request;
+    /// the connections underneath are the pool's and shared. When `url` is one of the network's
+    /// pooled endpoints, calls that fail to reach it move on to the others; a URL from the request
+    /// is used alone. `url()` on the client names the endpoint that answered its last call.
+    pub fn client(&self, network: &str, url: &str, commitment: CommitmentConfig) -> AsyncRpcClient {
+        let mut endpoints = vec![url.to_string()];
+        let pooled = self.endpoints(network);
+        if pooled.iter().any(|e| e == url) {
+            endpoints.extend(pooled.into_iter().filter(|e| e != url));
+        }
+        self.sender(endpoints, commitment)
+    }
+
+    /// An async client that only ever calls `url`, for checks about that one endpoint (health,
+    /// its cluster). It shares the pool's connections and circuit breaker like any other client.
+    pub fn pinned(&self, url: &str, commitment: CommitmentConfig) -> AsyncRpcClient {
+        self.sender(vec![url.to_string()], commitment)
+    }
+
+    fn sender(&self, endpoints: Vec<String>, commitment: CommitmentConfig) -> AsyncRpcClient {
+        let sender = FailoverSender {
+            endpoints,
+            served: AtomicUsize::new(0),
+            transports: self.transports.clone(),
+            stats: self.stats.clone(),
+            tracked: self.tracked.clone(),
+            breaker: self.breaker,
+        };
+        AsyncRpcClient::new_sender(sender, RpcClientConfig::with_commitment(commitment))
+    }
+
+    /// Record the outcome of one call against a configured endpoint; calls to others aren't kept.
+    pub fn record(&self, url: &str, elapsed: Duration, ok: bool) {
+        if !self.tracked.contains(url) {
+            return;
+        }
+        let mut stats = self.stats.lock().unwrap();
+        let entry = stats.entry(url.to_string()).or_default();
+        if entry.samples.len() == STATS_WINDOW {
+            entry.samples.pop_front();
+        }
+        entry.samples.push_back((elapsed.as_millis() as u64, ok));
+        if ok {
+            entry.last_success = Some(chrono::Utc::now().timestamp());
+        }
+    }
+
+    /// Strategy, configured endpoints, the fallback (redacted) and the breaker, for GET /config.
+    pub fn describe(&self) -> serde_json::Value {
+        let endpoints: std::collections::BTreeMap<&String, Vec<String>> = self
+            .endpoints
+            .iter()
+            .map(|(network, urls)| (network, urls.iter().map(|u| redact_url(u)).collect()))
+            .collect();
+        serde_json::json!({
+            "strategy": self.strategy,
+            "endpoints": endpoints,
+            "fallback": self.fallback.as_deref().map(redact_url),
+            "breaker": self.breaker_json()
+        })
+    }
+
+    fn breaker_json(&self) -> serde_json::Value {
+        serde_json::json!({ "failures": self.breaker.failures, "cooldown_secs": self.breaker.cooldown_secs })
+    }
+
+    /// The same per-endpoint stats as `snapshot`, as Prometheus text for GET /metrics.
+    pub fn render_prometheus(&self) -> String {
+        let stats = self.stats.lock().unwrap();
+        let mut urls: Vec<&String> = stats.keys().collect();
+        urls.sort();
+        let label = |url: &str| redact_url(&crate::providers::scrub(url)).replace('\\', "\\\\").replace('"', "\\\"");
+
+        let mut out = String::new();
+        out.push_str("# HELP fuego_rpc_latency_ms Latency of recent successful calls per endpoint.\n");
+        out.push_str("# TYPE fuego_rpc_latency_ms gauge\n");
+        for url in &urls {
+            for (quantile, percentile) in [("0.5", 50), ("0.95", 95)] {
+                if let Some(ms) = stats[*url].latency_percentile(percentile) {
+                    out.push_str(&format!("fuego_rpc_latency_ms{{endpoint=\"{}\",quantile=\"{}\"}} {}\n", label(url), quantile, ms));
+                }
+            }
+        }
+        out.push_str("# HELP fuego_rpc_errors_total Failed RPC calls per endpoint since startup, by kind.\n");
+        out.push_str("# TYPE fuego_rpc_errors_total counter\n");
+        for url in &urls {
+            for (kind, count) in &stats[*url].errors {
+                out.push_str(&format!("fuego_rpc_errors_total{{endpoint=\"{}\",kind=\"{}\"}} {}\n", label(url), kind, count));
+            }
+        }
+        out.push_str("# HELP fuego_rpc_circuit_open Whether the endpoint's circuit is open or half-open (1) or closed (0).\n");
+        out.push_str("# TYPE fuego_rpc_circuit_open gauge\n");
+        for url in &urls {
+            out.push_str(&format!("fuego_rpc_circuit_open{{endpoint=\"{}\"}} {}\n", label(url), !stats[*url].is_closed() as u8));
+        }
+        out.push_str("# HELP fuego_rpc_circuit_transitions_total Circuit state changes per endpoint since startup, by new state.\n");
+        out.push_str("# TYPE fuego_rpc_circuit_transitions_total counter\n");
+        for url in &urls {
+            for (state, count) in &stats[*url].transitions {
+                out.push_str(&format!(
+                    "fuego_rpc_circuit_transitions_total{{endpoint=\"{}\",state=\"{}\"}} {}\n",
+                    label(url),
+                    state,
+                    count
+                ));
+            }
+        }
+        out
+    }
+
+    /// Per-endpoint stats for every configured network (URLs redacted).
+    pub fn snapshot(&self) -> serde_json::Value {
+        let stats = self.stats.lock().unwrap();
+        let mut networks: Vec<&String> = self.endpoints.keys().collect();
+        networks.sort();
+
+        let describe = |url: &String, primary: bool| {
+            let s = stats.get(url);
+            serde_json::json!({
+                "endpoint": redact_url(url),
+                "primary": primary,
+                "calls": s.map(|s| s.samples.len()).unwrap_or(0),
+                "error_rate": s.map(|s| s.error_rate()).unwrap_or(0.0),
+                "avg_latency_ms": s.and_then(|s| s.avg_latency_ms()),
+                "p50_latency_ms": s.and_then(|s| s.latency_percentile(50)),
+                "p95_latency_ms": s.and_then(|s| s.latency_percentile(95)),
+                "errors": s.map(|s| s.errors.clone()).unwrap_or_default(),
+                "circuit": s.map(|s| s.circuit.name()).unwrap_or("closed"),
+                "circuit_open_until": s.and_then(|s| s.open_until()),
+                "circuit_transitions": s.map(|s| s.transitions.clone()).unwrap_or_default(),
+                "healthy": s.map(|s| s.error_rate() <= UNHEALTHY_ERROR_RATE && s.is_closed()).unwrap_or(true),
+                "consecutive_failures": s.map(|s| s.consecutive_failures).unwrap_or(0),
+                "last_success": s.and_then(|s| s.last_success)
+            })
+        };
+
+        let mut configured = serde_json::Map::new();
+        for network in networks {
+            let urls = &self.endpoints[network];
+            let list: Vec<serde_json::Value> = urls.iter().enumerate().map(|(i, url)| describe(url, i == 0)).collect();
+            configured.insert(network.clone(), serde_json::Value::Array(list));
+        }
+
+        // Public endpoints, the fallback and providers that have been used but aren't in a network's list
+        let mut fallback: Vec<serde_json::Value> = stats
+            .keys()
+            .filter(|url| !self.endpoints.values().any(|urls| urls.contains(url)))
+            .map(|url| describe(url, true))
+            .collect();
+        fallback.sort_by_key(|v| v["endpoint"].as_str().unwrap_or("").to_string());
+
+        serde_json::json!({
+            "strategy": self.strategy,
+            "window": STATS_WINDOW,
+            "breaker": self.breaker_json(),
+            "cached_transports": self.transports.lock().unwrap().len(),
+            "networks": configured,
+            "default_endpoints": fallback
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> RpcPool {
+        let mut config = crate::config::ServerConfig::default();
+        config.rpc_endpoints.insert("devnet".to_string(), vec!["https://a.example".to_string(), "https://b.example".to_string()]);
+        RpcPool::load(&config)
+    }
+
+    #[test]
+    fn only_configured_endpoints_keep_stats() {
+        let pool = pool();
+        for i in 0..100 {
+            pool.record(&format!("https://caller-{}.example/?api-key=secret", i), Duration::from_millis(5), false);
+        }
+        pool.record("https://a.example", Duration::from_millis(5), true);
+        pool.record("https://api.mainnet-beta.solana.com", Duration::from_millis(5), true);
+        assert_eq!(pool.stats.lock().unwrap().len(), 2);
+        assert!(!pool.snapshot().to_string().contains("caller-"));
+        assert!(!pool.render_prometheus().contains("caller-"));
+    }
+
+    #[tokio::test]
+    async fn failed_calls_to_a_request_url_leave_no_stats_or_transport() {
+        let pool = pool();
+        // Nothing listens on the discard port, so the call fails at once
+        let client = pool.client("devnet", "http://127.0.0.1:9", CommitmentConfig::confirmed());
+        assert!(client.get_slot().await.is_err());
+        assert!(pool.stats.lock().unwrap().is_empty());
+        assert!(pool.transports.lock().unwrap().is_empty());
+        assert_eq!(pool.snapshot()["default_endpoints"], serde_json::json!([]));
+    }
+}
@@ -0,0 +1,140 @@
+//! Failed sends and simulations translated into codes a client can act on. When preflight rejects
+//! a transaction the RPC answers with JSON-RPC error -32002 wrapping the simulation's
+//! TransactionError; that inner error, not the wrapper's text, says what went wrong.
+
+use serde::Serialize;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::rpc_request::{RpcError, RpcResponseErrorData};
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::transaction::TransactionError;
+
+#[derive(Serialize)]
+pub struct TransactionFailure {
+    pub code: &'static str,
+    pub message: String,
+    /// Which instruction failed, for program errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instruction_index: Option<u8>,
+    /// The program's own error number (InstructionError::Custom)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_error: Option<u32>,
+}
+
+pub fn transaction_failure(error: &TransactionError) -> TransactionFailure {
+    let (code, message) = match error {
+        TransactionError::BlockhashNotFound => (
+            "blockhash_not_found",
+            "Blockhash not found or expired; rebuild the transaction with a fresh one".to_string(),
+        ),
+        TransactionError::InsufficientFundsForFee => {
+            ("insufficient_funds_for_fee", "The fee payer has too little SOL for the fee".to_string())
+        }
+        TransactionError::InsufficientFundsForRent { account_index } => (
+            "insufficient_funds_for_rent",
+            format!("Account {} would be left below the rent-exempt minimum", account_index),
+        ),
+        TransactionError::AlreadyProcessed => ("already_processed", "This transaction has already been processed".to_string()),
+        TransactionError::AccountNotFound => (
+            "account_not_found",
+            "The fee payer account doesn't exist; fund it with SOL first".to_string(),
+        ),
+        TransactionError::InstructionError(index, e) => {
+            let custom_error = match e {
+                InstructionError::Custom(n) => Some(*n),
+                _ => None,
+            };
+            return TransactionFailure {
+                code: "program_error",
+                message: format!("Instruction {} failed: {}", index, e),
+                instruction_index: Some(*index),
+                custom_error,
+            };
+        }
+        other => ("transaction_rejected", other.to_string()),
+    };
+    TransactionFailure { code, message, instruction_index: None, custom_error: None }
+}
+
+/// The transaction's own error behind a failed send or simulate call, if the RPC reported one.
+pub fn classify(error: &ClientError) -> Option<TransactionFailure> {
+    error.get_transaction_error().map(|e| transaction_failure(&e))
+}
+
+/// Program logs from a preflight simulation that rejected the transaction.
+pub fn preflight_logs(error: &ClientError) -> Option<Vec<String>> {
+    match error.kind() {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            ..
+        }) => result.logs.clone(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_client::rpc_response::RpcSimulateTransactionResult;
+
+    fn code(error: TransactionError) -> &'static str {
+        transaction_failure(&error).code
+    }
+
+    /// What a send returns when preflight simulation rejects the transaction.
+    fn preflight_failure(simulation: serde_json::Value) -> ClientError {
+        let result: RpcSimulateTransactionResult = serde_json::from_value(simulation).unwrap();
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            code: -32002,
+            message: "Transaction simulation failed: Error processing Instruction 1".to_string(),
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+        })
+        .into()
+    }
+
+    #[test]
+    fn each_mapped_error_has_its_own_code() {
+        assert_eq!(code(TransactionError::BlockhashNotFound), "blockhash_not_found");
+        assert_eq!(code(TransactionError::InsufficientFundsForFee), "insufficient_funds_for_fee");
+        assert_eq!(code(TransactionError::AlreadyProcessed), "already_processed");
+        assert_eq!(code(TransactionError::AccountNotFound), "account_not_found");
+
+        let rent = transaction_failure(&TransactionError::InsufficientFundsForRent { account_index: 2 });
+        assert_eq!(rent.code, "insufficient_funds_for_rent");
+        assert!(rent.message.contains("Account 2"), "{}", rent.message);
+    }
+
+    #[test]
+    fn instruction_errors_name_the_instruction_and_custom_error() {
+        let custom = transaction_failure(&TransactionError::InstructionError(1, InstructionError::Custom(6001)));
+        assert_eq!((custom.code, custom.instruction_index, custom.custom_error), ("program_error", Some(1), Some(6001)));
+
+        let builtin = transaction_failure(&TransactionError::InstructionError(0, InstructionError::InsufficientFunds));
+        assert_eq!((builtin.code, builtin.instruction_index, builtin.custom_error), ("program_error", Some(0), None));
+    }
+
+    #[test]
+    fn anything_else_is_transaction_rejected() {
+        let other = transaction_failure(&TransactionError::SignatureFailure);
+        assert_eq!(other.code, "transaction_rejected");
+        assert_eq!(other.message, TransactionError::SignatureFailure.to_string());
+        assert!(other.instruction_index.is_none() && other.custom_error.is_none());
+    }
+
+    #[test]
+    fn a_preflight_failure_is_classified_by_its_inner_error() {
+        let error = preflight_failure(serde_json::json!({
+            "err": { "InstructionError": [1, { "Custom": 6001 }] },
+            "logs": ["Program log: Error: insufficient funds"],
+        }));
+        let failure = classify(&error).unwrap();
+        assert_eq!((failure.code, failure.custom_error), ("program_error", Some(6001)));
+        assert_eq!(preflight_logs(&error).unwrap(), vec!["Program log: Error: insufficient funds".to_string()]);
+    }
+
+    #[test]
+    fn errors_without_a_transaction_error_are_left_alone() {
+        let error: ClientError = ClientErrorKind::Custom("connection refused".to_string()).into();
+        assert!(classify(&error).is_none());
+        assert!(preflight_logs(&error).is_none());
+    }
+}
@@ -0,0 +1,220 @@
+//! Spending sessions: bounded, expiring allowances an agent presents via the `X-Fuego-Session` header.
+//! Sessions live in memory only, so a restart drops them all. Only keys in `session_admin_keys`
+//! can create or revoke one once auth is on.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::utils::base_units_to_ui;
+
+/// Header carrying the session token on money-moving requests.
+pub const SESSION_HEADER: &str = "x-fuego-session";
+
+/// Endpoints a session may be scoped to. Used as the default when a session doesn't list any.
+pub const SESSION_ENDPOINTS: &[&str] = &[
+    "x402-purch",
+    "build-transfer-sol",
+    "build-sweep-sol",
+    "build-transfer-usdc",
+    "build-transfer-usdt",
+    "build-transfer-pyusd",
+    "build-transfer-token",
+    "build-timelocked-transfer",
+];
+
+/// Decimals for the tokens a session cap can be expressed in.
+pub fn decimals_for_symbol(symbol: &str) -> Option<u8> {
+    match symbol {
+        "SOL" => Some(9),
+        "USDC" | "USDT" | "PYUSD" => Some(6),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct Session {
+    pub id: String,
+    #[serde(skip)]
+    pub token: String,
+    /// Cap per token symbol, in base units
+    pub caps: HashMap<String, u64>,
+    /// Amount consumed per token symbol, in base units
+    pub spent: HashMap<String, u64>,
+    pub allowed_endpoints: Vec<String>,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub revoked: bool,
+}
+
+impl Session {
+    fn remaining(&self, symbol: &str) -> u64 {
+        let cap = self.caps.get(symbol).copied().unwrap_or(0);
+        let spent = self.spent.get(symbol).copied().unwrap_or(0);
+        cap.saturating_sub(spent)
+    }
+
+    /// Remaining allowance per token as UI amount strings.
+    pub fn remaining_ui(&self) -> HashMap<String, String> {
+        self.caps
+            .keys()
+            .map(|symbol| {
+                let decimals = decimals_for_symbol(symbol).unwrap_or(0);
+                (symbol.clone(), base_units_to_ui(self.remaining(symbol), decimals))
+            })
+            .collect()
+    }
+
+    /// JSON view of the session (never includes the token).
+    pub fn summary(&self) -> serde_json::Value {
+        let ui = |amounts: &HashMap<String, u64>| -> HashMap<String, String> {
+            amounts
+                .iter()
+                .map(|(symbol, amount)| {
+                    let decimals = decimals_for_symbol(symbol).unwrap_or(0);
+                    (symbol.clone(), base_units_to_ui(*amount, decimals))
+                })
+                .collect()
+        };
+        let now = chrono::Utc::now().timestamp();
+        serde_json::json!({
+            "id": self.id,
+            "caps": ui(&self.caps),
+            "spent": ui(&self.spent),
+            "remaining": self.remaining_ui(),
+            "allowed_endpoints": self.allowed_endpoints,
+            "created_at": self.created_at,
+            "expires_at": self.expires_at,
+            "expired": now >= self.expires_at,
+            "revoked": self.revoked
+        })
+    }
+}
+
+pub enum SessionError {
+    Missing,
+    Unknown,
+    Expired,
+    Revoked,
+    EndpointNotAllowed(String),
+    TokenNotAllowed(String),
+    Exceeded {
+        symbol: String,
+        remaining: HashMap<String, String>,
+    },
+}
+
+impl SessionError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SessionError::Missing => "session_required",
+            SessionError::Unknown => "session_unknown",
+            SessionError::Expired => "session_expired",
+            SessionError::Revoked => "session_revoked",
+            SessionError::EndpointNotAllowed(_) => "session_endpoint_not_allowed",
+            SessionError::TokenNotAllowed(_) => "session_token_not_allowed",
+            SessionError::Exceeded { .. } => "session_allowance_exceeded",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            SessionError::Missing => format!("A spending session is required; pass its token in the {} header", SESSION_HEADER),
+            SessionError::Unknown => "Unknown session token".to_string(),
+            SessionError::Expired => "Session has expired".to_string(),
+            SessionError::Revoked => "Session has been revoked".to_string(),
+            SessionError::EndpointNotAllowed(endpoint) => format!("Session does not allow /{}", endpoint),
+            SessionError::TokenNotAllowed(symbol) => format!("Session has no allowance for {}", symbol),
+            SessionError::Exceeded { symbol, .. } => format!("Session allowance for {} exceeded", symbol),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct SessionStore {
+    inner: Arc<Mutex<HashMap<String, Session>>>,
+}
+
+impl SessionStore {
+    pub fn create(&self, caps: HashMap<String, u64>, allowed_endpoints: Vec<String>, ttl_seconds: i64) -> Session {
+        let now = chrono::Utc::now().timestamp();
+        let session = Session {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            token: format!("fst_{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple()),
+            caps,
+            spent: HashMap::new(),
+            allowed_endpoints,
+            created_at: now,
+            expires_at: now + ttl_seconds,
+            revoked: false,
+        };
+        self.inner.lock().unwrap().insert(session.id.clone(), session.clone());
+        session
+    }
+
+    pub fn get(&self, id: &str) -> Option<Session> {
+        self.inner.lock().unwrap().get(id).cloned()
+    }
+
+    pub fn revoke(&self, id: &str) -> bool {
+        match self.inner.lock().unwrap().get_mut(id) {
+            Some(session) => {
+                session.revoked = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// True while the session exists, is unexpired and has not been revoked.
+    pub fn is_active(&self, id: &str) -> bool {
+        let now = chrono::Utc::now().timestamp();
+        self.inner
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|s| !s.revoked && now < s.expires_at)
+            .unwrap_or(false)
+    }
+
+    /// Check and consume allowance in one step under the lock. Returns the charged session id.
+    pub fn charge(&self, token: &str, endpoint: &str, symbol: &str, amount: u64) -> Result<String, SessionError> {
+        let now = chrono::Utc::now().timestamp();
+        let mut sessions = self.inner.lock().unwrap();
+        let session = sessions
+            .values_mut()
+            .find(|s| s.token == token)
+            .ok_or(SessionError::Unknown)?;
+
+        if session.revoked {
+            return Err(SessionError::Revoked);
+        }
+        if now >= session.expires_at {
+            return Err(SessionError::Expired);
+        }
+        if !session.allowed_endpoints.iter().any(|e| e == endpoint) {
+            return Err(SessionError::EndpointNotAllowed(endpoint.to_string()));
+        }
+        if !session.caps.contains_key(symbol) {
+            return Err(SessionError::TokenNotAllowed(symbol.to_string()));
+        }
+        if amount > session.remaining(symbol) {
+            return Err(SessionError::Exceeded {
+                symbol: symbol.to_string(),
+                remaining: session.remaining_ui(),
+            });
+        }
+
+        *session.spent.entry(symbol.to_string()).or_insert(0) += amount;
+        Ok(session.id.clone())
+    }
+
+    /// Give back allowance for an action that was charged but never went through.
+    pub fn refund(&self, id: &str, symbol: &str, amount: u64) {
+        if let Some(session) = self.inner.lock().unwrap().get_mut(id) {
+            if let Some(spent) = session.spent.get_mut(symbol) {
+                *spent = spent.saturating_sub(amount);
+            }
+        }
+    }
+}
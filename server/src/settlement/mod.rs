@@ -0,0 +1,214 @@
+//! On-chain check of x402 settlements. The paid endpoint's word on whether our payment settled
+//! isn't trusted: after each paid request the payment transaction is looked up on-chain, by the
+//! signature in the settlement header or, when there is none, by scanning the payer's history
+//! since the request started. Disagreements between the response and the chain are recorded on
+//! the receipt so they can be produced as dispute evidence.
+
+use base64::engine::general_purpose;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::history::rpc_call;
+use crate::pending::fetch_statuses;
+
+/// Settlement response headers: x402 v2, then v1.
+const HEADERS: &[&str] = &["payment-response", "x-payment-response"];
+
+/// The facilitator settles before the paid endpoint responds, but the RPC may not report the
+/// transaction at `confirmed` for a moment; this many lookups, POLL_INTERVAL apart.
+const ATTEMPTS: usize = 6;
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Payer history entries this much older than the request start still count (clock skew).
+const CLOCK_SLACK_SECS: i64 = 5;
+
+/// Recent payer signatures scanned when the response names no settlement transaction.
+const HISTORY_LIMIT: usize = 20;
+
+/// What the facilitator said about settlement, decoded from the response header.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Claim {
+    pub header: String,
+    pub success: Option<bool>,
+    pub signature: Option<String>,
+    /// Set when the header carried a failure reason
+    pub error_reason: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// The payment transaction landed without error: funds moved
+    Settled,
+    /// The payment transaction landed but failed: no funds moved
+    Failed,
+    /// The claimed signature isn't known to the chain
+    NotFound,
+    /// No settlement header, and nothing from the payer landed since the request started
+    NoPaymentObserved,
+    /// The RPC couldn't be asked; nothing is known either way
+    Unavailable,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mismatch {
+    /// Our payment settled, but the endpoint refused the request
+    PaidButDenied,
+    /// The endpoint granted access and claimed settlement, but the chain shows none
+    AccessWithoutSettlement,
+}
+
+impl Mismatch {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mismatch::PaidButDenied => "paid_but_denied",
+            Mismatch::AccessWithoutSettlement => "access_without_settlement",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Verification {
+    pub outcome: Outcome,
+    /// The payment transaction, from the settlement header or the payer's history
+    pub signature: Option<String>,
+    /// "settlement_header" or "payer_history"
+    pub source: String,
+    pub claim: Option<Claim>,
+    pub slot: Option<u64>,
+    /// On-chain error of a failed payment transaction
+    pub err: Option<Value>,
+    pub mismatch: Option<Mismatch>,
+    /// Why the lookup couldn't complete, when outcome is unavailable
+    pub error: Option<String>,
+    pub checked_at: i64,
+}
+
+impl Verification {
+    /// Whether funds moved: null when the chain couldn't be asked.
+    pub fn on_chain_verified(&self) -> Option<bool> {
+        match self.outcome {
+            Outcome::Settled => Some(true),
+            Outcome::Unavailable => None,
+            _ => Some(false),
+        }
+    }
+}
+
+/// The settlement header of a paid response, if it sent one. Undecodable headers still count as a
+/// claim, with nothing in it.
+pub fn claim(headers: &reqwest::header::HeaderMap) -> Option<Claim> {
+    let (name, value) = HEADERS.iter().find_map(|name| headers.get(*name).map(|v| (*name, v)))?;
+    let decoded: Value = value
+        .to_str()
+        .ok()
+        .and_then(|v| general_purpose::STANDARD.decode(v.trim()).or_else(|_| general_purpose::URL_SAFE.decode(v.trim())).ok())
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or(Value::Null);
+    Some(Claim {
+        header: name.to_string(),
+        success: decoded["success"].as_bool(),
+        signature: decoded["transaction"].as_str().filter(|s| !s.is_empty()).map(str::to_string),
+        error_reason: decoded["errorReason"].as_str().map(str::to_string),
+    })
+}
+
+/// Look the payment up on-chain and compare it with the endpoint's `http_status` and `claim`.
+/// `payer` is the wallet that signed the payment; `started_at` is when the paid request went out.
+pub async fn verify(rpc_url: &str, payer: &str, claim: Option<Claim>, http_status: u16, started_at: i64) -> Verification {
+    let http = crate::rpc::http_client(rpc_url);
+    let claimed_signature = claim.as_ref().and_then(|c| c.signature.clone());
+    let mut verification = Verification {
+        outcome: Outcome::Unavailable,
+        signature: claimed_signature.clone(),
+        source: if claimed_signature.is_some() { "settlement_header" } else { "payer_history" }.to_string(),
+        claim,
+        slot: None,
+        err: None,
+        mismatch: None,
+        error: None,
+        checked_at: 0,
+    };
+
+    for attempt in 0..ATTEMPTS {
+        if attempt > 0 {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        let found = match &claimed_signature {
+            Some(signature) => lookup_signature(&http, rpc_url, signature).await,
+            None => scan_payer(&http, rpc_url, payer, started_at).await,
+        };
+        match found {
+            Ok(Some((signature, slot, err))) => {
+                verification.outcome = if err.is_some() { Outcome::Failed } else { Outcome::Settled };
+                verification.signature = Some(signature);
+                verification.slot = slot;
+                verification.err = err;
+                verification.error = None;
+                break;
+            }
+            Ok(None) => {
+                verification.outcome = if claimed_signature.is_some() { Outcome::NotFound } else { Outcome::NoPaymentObserved };
+                verification.error = None;
+            }
+            Err(e) => {
+                // A later attempt that reaches the RPC overrides this
+                if verification.outcome == Outcome::Unavailable {
+                    verification.error = Some(e);
+                }
+            }
+        }
+    }
+
+    let granted = (200..300).contains(&http_status);
+    let claimed_settled = verification.claim.as_ref().map(|c| c.signature.is_some() || c.success == Some(true)).unwrap_or(false);
+    verification.mismatch = match verification.outcome {
+        Outcome::Settled if !granted => Some(Mismatch::PaidButDenied),
+        Outcome::Failed | Outcome::NotFound | Outcome::NoPaymentObserved if granted && claimed_settled => {
+            Some(Mismatch::AccessWithoutSettlement)
+        }
+        _ => None,
+    };
+    verification.checked_at = chrono::Utc::now().timestamp();
+    verification
+}
+
+async fn lookup_signature(
+    http: &reqwest::Client,
+    rpc_url: &str,
+    signature: &str,
+) -> Result<Option<(String, Option<u64>, Option<Value>)>, String> {
+    let statuses = fetch_statuses(http, rpc_url, &[signature.to_string()], true).await?;
+    let Some(status) = statuses.into_iter().next().filter(|s| !s.is_null()) else {
+        return Ok(None);
+    };
+    let err = status.get("err").filter(|e| !e.is_null()).cloned();
+    Ok(Some((signature.to_string(), status["slot"].as_u64(), err)))
+}
+
+/// The oldest payer transaction since the request started. Another transaction the wallet sent
+/// at the same moment would be indistinguishable; the source says this was inferred.
+async fn scan_payer(
+    http: &reqwest::Client,
+    rpc_url: &str,
+    payer: &str,
+    started_at: i64,
+) -> Result<Option<(String, Option<u64>, Option<Value>)>, String> {
+    let config = json!({ "limit": HISTORY_LIMIT, "commitment": "confirmed" });
+    let page = rpc_call(http, rpc_url, "getSignaturesForAddress", json!([payer, config])).await?;
+    let entries = page.as_array().cloned().unwrap_or_default();
+    let recent: Vec<&Value> = entries
+        .iter()
+        .filter(|e| e["blockTime"].as_i64().map(|t| t >= started_at - CLOCK_SLACK_SECS).unwrap_or(false))
+        .collect();
+    // The list is newest first: take the oldest successful transaction, else the oldest failed one
+    let chosen = recent.iter().rev().find(|e| e["err"].is_null()).or_else(|| recent.last());
+    Ok(chosen.and_then(|entry| {
+        let signature = entry["signature"].as_str()?.to_string();
+        let err = entry.get("err").filter(|e| !e.is_null()).cloned();
+        Some((signature, entry["slot"].as_u64(), err))
+    }))
+}
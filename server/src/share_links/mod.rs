@@ -0,0 +1,358 @@
+//! Read-only share links: a signed, expiring token that lets someone without an API key see one
+//! wallet's history, balances or transactions on one network, optionally limited to a time range.
+//! Links are stored in ~/.fuego/share-links.json so they can be listed and revoked; the token is
+//! `<id>.<hmac>`, where the HMAC covers the whole scope, so editing the store can't widen a link.
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::utils::{constant_time_eq, fuego_home};
+
+/// What a link can show. Nothing here signs, submits or touches the wallet.
+pub const SHARE_VIEWS: &[&str] = &["history", "balances", "transaction"];
+
+pub const DEFAULT_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+pub const MAX_TTL_SECS: i64 = 90 * 24 * 60 * 60;
+
+fn store_path() -> PathBuf {
+    fuego_home().join("share-links.json")
+}
+
+fn secret_path() -> PathBuf {
+    fuego_home().join("share-links.key")
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Scope {
+    pub address: String,
+    pub network: String,
+    pub views: Vec<String>,
+    /// Unix seconds; transactions before this are out of scope
+    #[serde(default)]
+    pub from: Option<i64>,
+    /// Unix seconds; transactions after this are out of scope
+    #[serde(default)]
+    pub until: Option<i64>,
+}
+
+impl Scope {
+    pub fn allows_view(&self, view: &str) -> bool {
+        self.views.iter().any(|v| v == view)
+    }
+
+    /// Whether a transaction's block time falls in the range. Without a block time it can only be
+    /// shown when the range is open on both ends.
+    pub fn in_range(&self, block_time: Option<i64>) -> bool {
+        match block_time {
+            Some(t) => self.from.is_none_or(|from| t >= from) && self.until.is_none_or(|until| t <= until),
+            None => self.from.is_none() && self.until.is_none(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ShareLink {
+    pub id: String,
+    pub scope: Scope,
+    #[serde(default)]
+    pub label: Option<String>,
+    pub created_at: i64,
+    pub expires_at: i64,
+    #[serde(default)]
+    pub revoked_at: Option<i64>,
+    signature: String,
+}
+
+impl ShareLink {
+    pub fn token(&self) -> String {
+        format!("{}.{}", self.id, self.signature)
+    }
+
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "label": self.label,
+            "address": self.scope.address,
+            "network": self.scope.network,
+            "views": self.scope.views,
+            "from": self.scope.from,
+            "until": self.scope.until,
+            "created_at": self.created_at,
+            "expires_at": self.expires_at,
+            "revoked": self.revoked_at.is_some(),
+            "revoked_at": self.revoked_at
+        })
+    }
+}
+
+/// Why a shared request was refused; `code()` is the stable error code.
+#[derive(Debug)]
+pub enum ShareError {
+    /// Malformed, unknown or wrongly signed: never says which, so tokens can't be probed
+    Invalid,
+    Expired,
+    Revoked,
+    ViewNotShared(String),
+    ParamNotAllowed(String),
+    OutOfScope(String),
+}
+
+impl ShareError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ShareError::Invalid => "share_link_invalid",
+            ShareError::Expired => "share_link_expired",
+            ShareError::Revoked => "share_link_revoked",
+            ShareError::ViewNotShared(_) => "share_view_not_allowed",
+            ShareError::ParamNotAllowed(_) => "share_param_not_allowed",
+            ShareError::OutOfScope(_) => "share_out_of_scope",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ShareError::Invalid => "Share link is not valid".to_string(),
+            ShareError::Expired => "Share link has expired".to_string(),
+            ShareError::Revoked => "Share link was revoked".to_string(),
+            ShareError::ViewNotShared(view) => format!("This link does not share {}", view),
+            ShareError::ParamNotAllowed(name) => format!("Parameter {} can't be set on a shared view; the link fixes it", name),
+            ShareError::OutOfScope(what) => format!("{} is outside what this link shares", what),
+        }
+    }
+
+    /// Token problems are 401, scope problems 403.
+    pub fn is_token_error(&self) -> bool {
+        matches!(self, ShareError::Invalid | ShareError::Expired | ShareError::Revoked)
+    }
+}
+
+/// Refuse any query parameter a view doesn't take. Address, network and range come from the link.
+pub fn check_params(params: &HashMap<String, String>, allowed: &[&str]) -> Result<(), ShareError> {
+    let mut names: Vec<&String> = params.keys().collect();
+    names.sort();
+    match names.into_iter().find(|name| !allowed.contains(&name.as_str())) {
+        Some(name) => Err(ShareError::ParamNotAllowed(name.clone())),
+        None => Ok(()),
+    }
+}
+
+#[derive(Clone)]
+pub struct ShareLinks {
+    secret: Arc<Vec<u8>>,
+    inner: Arc<Mutex<HashMap<String, ShareLink>>>,
+    /// Write changes to ~/.fuego/share-links.json
+    persist: bool,
+}
+
+#[derive(Default, Deserialize)]
+struct Persisted {
+    #[serde(default)]
+    links: Vec<ShareLink>,
+}
+
+impl ShareLinks {
+    /// Links from the store, signed with the key in ~/.fuego/share-links.key (created on first
+    /// use). Deleting the key invalidates every link issued so far.
+    pub fn load() -> Self {
+        let secret = match fs::read(secret_path()) {
+            Ok(bytes) if bytes.len() >= 32 => bytes,
+            _ => {
+                let mut bytes = vec![0u8; 32];
+                OsRng.fill_bytes(&mut bytes);
+                let written = fs::create_dir_all(fuego_home()).and_then(|_| fs::write(secret_path(), &bytes));
+                match written {
+                    Ok(()) => {
+                        #[cfg(unix)]
+                        {
+                            use std::os::unix::fs::PermissionsExt;
+                            let _ = fs::set_permissions(secret_path(), fs::Permissions::from_mode(0o600));
+                        }
+                    }
+                    // Links still work until restart; they just won't survive it
+                    Err(e) => eprintln!("Failed to save share link key: {}", e),
+                }
+                bytes
+            }
+        };
+        let persisted: Persisted = fs::read_to_string(store_path())
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default();
+        ShareLinks {
+            secret: Arc::new(secret),
+            inner: Arc::new(Mutex::new(persisted.links.into_iter().map(|l| (l.id.clone(), l)).collect())),
+            persist: true,
+        }
+    }
+
+    /// No links, signed with `secret`, and nothing read from or written to disk.
+    #[cfg(test)]
+    pub fn in_memory(secret: &[u8]) -> Self {
+        ShareLinks { secret: Arc::new(secret.to_vec()), inner: Arc::default(), persist: false }
+    }
+
+    fn sign(&self, id: &str, scope: &Scope, expires_at: i64) -> String {
+        let canonical = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}\n{}",
+            id,
+            scope.address,
+            scope.network,
+            scope.views.join(","),
+            scope.from.map(|t| t.to_string()).unwrap_or_default(),
+            scope.until.map(|t| t.to_string()).unwrap_or_default(),
+            expires_at
+        );
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts any key length");
+        mac.update(canonical.as_bytes());
+        format!("{:x}", mac.finalize().into_bytes())
+    }
+
+    fn persist_locked(&self, map: &HashMap<String, ShareLink>) {
+        if !self.persist {
+            return;
+        }
+        let mut links: Vec<&ShareLink> = map.values().collect();
+        links.sort_by_key(|l| l.created_at);
+        let persisted = serde_json::json!({ "links": links });
+        let result = fs::create_dir_all(fuego_home()).and_then(|_| {
+            fs::write(store_path(), serde_json::to_string_pretty(&persisted).unwrap_or_default())
+        });
+        if let Err(e) = result {
+            eprintln!("Failed to persist share links: {}", e);
+        }
+    }
+
+    pub fn create(&self, scope: Scope, ttl_secs: i64, label: Option<String>) -> ShareLink {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let created_at = chrono::Utc::now().timestamp();
+        let expires_at = created_at + ttl_secs;
+        let signature = self.sign(&id, &scope, expires_at);
+        let link = ShareLink { id, scope, label, created_at, expires_at, revoked_at: None, signature };
+        let mut map = self.inner.lock().unwrap();
+        map.insert(link.id.clone(), link.clone());
+        self.persist_locked(&map);
+        link
+    }
+
+    /// Every link, newest first, revoked and expired ones included.
+    pub fn list(&self) -> Vec<ShareLink> {
+        let mut links: Vec<ShareLink> = self.inner.lock().unwrap().values().cloned().collect();
+        links.sort_by_key(|l| std::cmp::Reverse(l.created_at));
+        links
+    }
+
+    /// Revoke a link; false when there's no such link. Revoking twice keeps the first time.
+    pub fn revoke(&self, id: &str) -> bool {
+        let mut map = self.inner.lock().unwrap();
+        let Some(link) = map.get_mut(id) else {
+            return false;
+        };
+        link.revoked_at.get_or_insert(chrono::Utc::now().timestamp());
+        self.persist_locked(&map);
+        true
+    }
+
+    /// The link a token stands for, if it is genuine, unexpired and not revoked.
+    pub fn verify(&self, token: &str) -> Result<ShareLink, ShareError> {
+        let (id, signature) = token.split_once('.').ok_or(ShareError::Invalid)?;
+        let link = self.inner.lock().unwrap().get(id).cloned().ok_or(ShareError::Invalid)?;
+        let expected = self.sign(&link.id, &link.scope, link.expires_at);
+        if !constant_time_eq(expected.as_bytes(), signature.as_bytes()) || !constant_time_eq(expected.as_bytes(), link.signature.as_bytes()) {
+            return Err(ShareError::Invalid);
+        }
+        if link.revoked_at.is_some() {
+            return Err(ShareError::Revoked);
+        }
+        if chrono::Utc::now().timestamp() >= link.expires_at {
+            return Err(ShareError::Expired);
+        }
+        Ok(link)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope() -> Scope {
+        Scope {
+            address: "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM".to_string(),
+            network: "mainnet-beta".to_string(),
+            views: vec!["history".to_string()],
+            from: Some(1_700_000_000),
+            until: None,
+        }
+    }
+
+    /// Edit a stored link the way someone with write access to share-links.json could.
+    fn tamper(links: &ShareLinks, id: &str, edit: impl FnOnce(&mut ShareLink)) {
+        edit(links.inner.lock().unwrap().get_mut(id).unwrap());
+    }
+
+    #[test]
+    fn a_genuine_link_verifies() {
+        let links = ShareLinks::in_memory(&[7; 32]);
+        let link = links.create(scope(), DEFAULT_TTL_SECS, Some("accountant".to_string()));
+        let verified = links.verify(&link.token()).unwrap();
+        assert_eq!(verified.id, link.id);
+        assert!(verified.scope.allows_view("history") && !verified.scope.allows_view("balances"));
+    }
+
+    #[test]
+    fn a_tampered_scope_is_invalid() {
+        let links = ShareLinks::in_memory(&[7; 32]);
+        let edits: [fn(&mut ShareLink); 5] = [
+            |l| l.scope.address = "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T".to_string(),
+            |l| l.scope.network = "devnet".to_string(),
+            |l| l.scope.views.push("balances".to_string()),
+            |l| l.scope.from = None,
+            |l| l.scope.until = Some(i64::MAX),
+        ];
+        for edit in edits {
+            let link = links.create(scope(), DEFAULT_TTL_SECS, None);
+            tamper(&links, &link.id, edit);
+            assert!(matches!(links.verify(&link.token()), Err(ShareError::Invalid)));
+        }
+    }
+
+    #[test]
+    fn a_tampered_expiry_is_invalid() {
+        let links = ShareLinks::in_memory(&[7; 32]);
+        let link = links.create(scope(), 60, None);
+        tamper(&links, &link.id, |l| l.expires_at += MAX_TTL_SECS);
+        assert!(matches!(links.verify(&link.token()), Err(ShareError::Invalid)));
+    }
+
+    #[test]
+    fn forged_or_malformed_tokens_are_invalid() {
+        let links = ShareLinks::in_memory(&[7; 32]);
+        let link = links.create(scope(), DEFAULT_TTL_SECS, None);
+        let other_key = ShareLinks::in_memory(&[8; 32]);
+        let forged = other_key.sign(&link.id, &link.scope, link.expires_at);
+        for token in [format!("{}.{}", link.id, forged), format!("{}.", link.id), link.id.clone(), format!("nope.{}", link.signature), String::new()] {
+            assert!(matches!(links.verify(&token), Err(ShareError::Invalid)), "{}", token);
+        }
+    }
+
+    #[test]
+    fn revoked_and_expired_links_are_refused() {
+        let links = ShareLinks::in_memory(&[7; 32]);
+        let revoked = links.create(scope(), DEFAULT_TTL_SECS, None);
+        assert!(links.revoke(&revoked.id));
+        assert!(matches!(links.verify(&revoked.token()), Err(ShareError::Revoked)));
+        assert!(!links.revoke("no-such-link"));
+
+        let expired = links.create(scope(), 0, None);
+        assert!(matches!(links.verify(&expired.token()), Err(ShareError::Expired)));
+        for error in [ShareError::Revoked, ShareError::Expired, ShareError::Invalid] {
+            assert!(error.is_token_error());
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Transfer statistics over the local memo index: counts and summed base-unit amounts grouped by
+//! counterparty, token and time bucket. Only confirmed, successful transactions are counted, and
+//! the index can be backfilled from chain history first for wallets used outside this server.
+
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::history::{rpc_call, SIGNATURE_PAGE};
+use crate::memo_index::{self, IndexedMemo};
+use crate::pending::{fetch_statuses, STATUS_BATCH};
+
+/// Deepest a refresh may walk back through an address's history.
+pub const MAX_BACKFILL_DEPTH: usize = 5000;
+pub const DEFAULT_BACKFILL_DEPTH: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    Counterparty,
+    Token,
+}
+
+#[derive(Clone, Copy)]
+pub enum Bucket {
+    Day,
+    Week,
+    Month,
+}
+
+impl GroupBy {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "counterparty" => Ok(GroupBy::Counterparty),
+            "token" => Ok(GroupBy::Token),
+            other => Err(format!("Unknown group_by '{}'; use counterparty or token", other)),
+        }
+    }
+}
+
+impl Bucket {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "day" => Ok(Bucket::Day),
+            "week" => Ok(Bucket::Week),
+            "month" => Ok(Bucket::Month),
+            other => Err(format!("Unknown bucket '{}'; use day, week or month", other)),
+        }
+    }
+
+    fn label(&self, timestamp: i64) -> String {
+        let format = match self {
+            Bucket::Day => "%Y-%m-%d",
+            Bucket::Week => "%G-W%V",
+            Bucket::Month => "%Y-%m",
+        };
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .map(|t| t.format(format).to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+}
+
+pub struct Query {
+    pub network: String,
+    /// Wallet whose outgoing transfers are counted
+    pub address: String,
+    pub group_by: Vec<GroupBy>,
+    pub bucket: Option<Bucket>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub token: Option<String>,
+    pub counterparty: Option<String>,
+}
+
+/// (counterparty, token, bucket); a part is None when the query doesn't group by it.
+type GroupKey = (Option<String>, Option<String>, Option<String>);
+
+#[derive(Serialize)]
+pub struct Group {
+    pub counterparty: Option<String>,
+    pub token: Option<String>,
+    pub bucket: Option<String>,
+    pub count: u64,
+    /// Summed base units per token; tokens are never added together
+    pub amounts: BTreeMap<String, String>,
+}
+
+#[derive(Default, Serialize)]
+pub struct BackfillReport {
+    pub signatures_scanned: usize,
+    pub memos_added: usize,
+    /// True when the walk stopped at the depth limit rather than the start of the history
+    pub depth_limited: bool,
+}
+
+/// When a memo's transfer happened: block time for backfilled memos, submission time otherwise.
+fn timestamp(memo: &IndexedMemo) -> i64 {
+    memo.block_time.unwrap_or(memo.indexed_at)
+}
+
+/// Memo texts from getSignaturesForAddress's `memo` field: "[len] text" entries joined by "; ".
+fn parse_signature_memos(field: &str) -> Vec<String> {
+    field
+        .split("; ")
+        .map(|part| match part.strip_prefix('[').and_then(|p| p.split_once("] ")) {
+            Some((_, text)) => text.to_string(),
+            None => part.to_string(),
+        })
+        .filter(|m| !m.is_empty())
+        .collect()
+}
+
+/// A confirmed transaction with fuego memos that the index doesn't have yet.
+pub struct MissingMemos {
+    pub signature: String,
+    pub memos: Vec<String>,
+    pub block_time: Option<i64>,
+}
+
+/// Index fuego memos from `address`'s confirmed history that aren't indexed yet, newest first,
+/// scanning at most `depth` signatures.
+pub async fn backfill(rpc_url: &str, network: &str, address: &str, depth: usize) -> Result<BackfillReport, String> {
+    let (report, missing) = find_missing(rpc_url, network, address, depth).await?;
+    for entry in missing {
+        memo_index::record_backfill(&entry.signature, network, entry.memos, entry.block_time);
+    }
+    Ok(report)
+}
+
+/// What `backfill` would add, without writing anything.
+pub async fn find_missing(
+    rpc_url: &str,
+    network: &str,
+    address: &str,
+    depth: usize,
+) -> Result<(BackfillReport, Vec<MissingMemos>), String> {
+    let depth = depth.min(MAX_BACKFILL_DEPTH);
+    let known: HashSet<String> = memo_index::load()
+        .into_iter()
+        .filter(|m| m.network == network)
+        .map(|m| m.signature)
+        .collect();
+    let http = crate::rpc::http_client(rpc_url);
+    let mut report = BackfillReport::default();
+    let mut missing = Vec::new();
+    let mut before: Option<String> = None;
+
+    while report.signatures_scanned < depth {
+        let limit = SIGNATURE_PAGE.min(depth - report.signatures_scanned);
+        let mut config = json!({ "commitment": "confirmed", "limit": limit });
+        if let Some(ref sig) = before {
+            config["before"] = json!(sig);
+        }
+        let page = rpc_call(&http, rpc_url, "getSignaturesForAddress", json!([address, config])).await?;
+        let entries = page.as_array().cloned().unwrap_or_default();
+        if entries.is_empty() {
+            return Ok((report, missing));
+        }
+        for entry in &entries {
+            report.signatures_scanned += 1;
+            let signature = entry["signature"].as_str().unwrap_or_default().to_string();
+            before = Some(signature.clone());
+            // Failed transactions moved nothing
+            if !entry["err"].is_null() || known.contains(&signature) {
+                continue;
+            }
+            let memos: Vec<String> = entry["memo"]
+                .as_str()
+                .map(parse_signature_memos)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|m| m.starts_with("fuego|"))
+                .collect();
+            if memos.is_empty() {
+                continue;
+            }
+            report.memos_added += memos.len();
+            missing.push(MissingMemos {
+                signature,
+                memos,
+                block_time: entry["blockTime"].as_i64(),
+            });
+        }
+        if entries.len() < limit {
+            return Ok((report, missing));
+        }
+    }
+    report.depth_limited = true;
+    Ok((report, missing))
+}
+
+/// Which signatures are confirmed (or finalized) without an on-chain error.
+async fn confirmed_signatures(rpc_url: &str, signatures: Vec<String>) -> Result<HashSet<String>, String> {
+    let http = crate::rpc::http_client(rpc_url);
+    let mut confirmed = HashSet::new();
+    for batch in signatures.chunks(STATUS_BATCH) {
+        let statuses = fetch_statuses(&http, rpc_url, batch, true).await?;
+        for (signature, status) in batch.iter().zip(statuses) {
+            let landed = matches!(status["confirmationStatus"].as_str(), Some("confirmed") | Some("finalized"));
+            if landed && status["err"].is_null() {
+                confirmed.insert(signature.clone());
+            }
+        }
+    }
+    Ok(confirmed)
+}
+
+/// Aggregate the index for `query`. Backfilled memos come from confirmed history already; memos
+/// recorded at submission are checked against the chain before they count.
+pub async fn aggregate(rpc_url: &str, query: &Query) -> Result<serde_json::Value, String> {
+    let indexed = memo_index::load();
+    let network_memos: Vec<&IndexedMemo> = indexed.iter().filter(|m| m.network == query.network).collect();
+
+    let candidates: Vec<&IndexedMemo> = network_memos
+        .iter()
+        .copied()
+        .filter(|m| m.token.is_some() && m.amount.is_some())
+        // Memos recorded at submission without a sender (minimal privacy) were still sent by this server
+        .filter(|m| match m.from.as_deref() {
+            Some(from) => from == query.address,
+            None => m.source != memo_index::BACKFILL_SOURCE,
+        })
+        .filter(|m| query.from.map(|from| timestamp(m) >= from).unwrap_or(true))
+        .filter(|m| query.to.map(|to| timestamp(m) < to).unwrap_or(true))
+        .filter(|m| query.token.as_deref().map(|token| m.matches_token(token)).unwrap_or(true))
+        .filter(|m| match &query.counterparty {
+            Some(c) => m.to.as_deref() == Some(c.as_str()),
+            None => true,
+        })
+        .collect();
+
+    let to_check: Vec<String> = candidates
+        .iter()
+        .filter(|m| m.source != memo_index::BACKFILL_SOURCE)
+        .map(|m| m.signature.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let checked = to_check.len();
+    let confirmed = confirmed_signatures(rpc_url, to_check).await?;
+
+    let mut groups: HashMap<GroupKey, (u64, BTreeMap<String, u128>)> = HashMap::new();
+    let mut counted = 0u64;
+    let mut excluded = 0u64;
+    let (mut earliest, mut latest): (Option<i64>, Option<i64>) = (None, None);
+    for memo in &candidates {
+        if memo.source != memo_index::BACKFILL_SOURCE && !confirmed.contains(&memo.signature) {
+            excluded += 1;
+            continue;
+        }
+        let amount = match memo.amount.as_deref().and_then(|a| a.parse::<u128>().ok()) {
+            Some(amount) => amount,
+            None => continue,
+        };
+        let token = memo.symbol.clone().unwrap_or_default();
+        let key = (
+            query
+                .group_by
+                .contains(&GroupBy::Counterparty)
+                .then(|| memo.to.clone().unwrap_or_else(|| "unknown".to_string())),
+            query.group_by.contains(&GroupBy::Token).then(|| token.clone()),
+            query.bucket.map(|b| b.label(timestamp(memo))),
+        );
+        let group = groups.entry(key).or_default();
+        group.0 += 1;
+        *group.1.entry(token).or_default() += amount;
+        counted += 1;
+        let ts = timestamp(memo);
+        earliest = Some(earliest.map_or(ts, |e| e.min(ts)));
+        latest = Some(latest.map_or(ts, |l| l.max(ts)));
+    }
+
+    let mut groups: Vec<Group> = groups
+        .into_iter()
+        .map(|((counterparty, token, bucket), (count, amounts))| Group {
+            counterparty,
+            token,
+            bucket,
+            count,
+            amounts: amounts.into_iter().map(|(t, a)| (t, a.to_string())).collect(),
+        })
+        .collect();
+    groups.sort_by(|a, b| (&a.bucket, &a.counterparty, &a.token).cmp(&(&b.bucket, &b.counterparty, &b.token)));
+
+    let index_from = network_memos.iter().map(|m| timestamp(m)).min();
+    let index_to = network_memos.iter().map(|m| timestamp(m)).max();
+    Ok(json!({
+        "groups": groups,
+        "transfers_counted": counted,
+        "window": {
+            "requested_from": query.from,
+            "requested_to": query.to,
+            "earliest_counted": earliest,
+            "latest_counted": latest
+        },
+        "coverage": {
+            "indexed_memos": network_memos.len(),
+            "index_from": index_from,
+            "index_to": index_to,
+            "window_before_index": match (query.from, index_from) {
+                (Some(from), Some(start)) => from < start,
+                (None, _) => true,
+                _ => false,
+            },
+            "statuses_checked": checked,
+            "unconfirmed_excluded": excluded,
+            "note": "Counts transfers in the local memo index only: those submitted through this server, plus any backfilled with refresh. Transfers without a fuego memo are never counted."
+        }
+    }))
+}
@@ -0,0 +1,208 @@
+//! Strict request parsing: report JSON fields the request struct doesn't define (typos like "ammount")
+//! instead of silently ignoring them. Off by default; enabled for every request with
+//! FUEGO_STRICT_JSON=true, or per request with the `X-Fuego-Strict: true` header.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_ignored::Path;
+use serde_json::Value;
+
+pub const STRICT_HEADER: &str = "x-fuego-strict";
+
+/// Top-level fields any request may carry, read by middleware rather than the handler.
+const COMMON_FIELDS: &[&str] = &["timeout_ms"];
+
+/// True when the request opts into strict parsing.
+pub fn requested(headers: &axum::http::HeaderMap) -> bool {
+    headers
+        .get(STRICT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| matches!(v.trim(), "true" | "1"))
+        .unwrap_or(false)
+}
+
+/// `input` parsed as `T`, with the path of every field `T` doesn't define, e.g. "ammount" or
+/// "extra_instructions[0].acounts". serde reports the fields it skips, except inside a
+/// `#[serde(flatten)]` member (RpcTarget, TransferOptions) and at the top level of the struct
+/// holding one: there unknown fields are dropped silently. So every field the parsed payload
+/// doesn't serialize back is also checked, one by one, with `reads_field`.
+pub fn parse<T: DeserializeOwned + Serialize>(input: &Value) -> Result<(T, Vec<String>), serde_json::Error> {
+    let mut unknown = Vec::new();
+    let payload: T = serde_ignored::deserialize(input, |path| {
+        let mut name = String::new();
+        write_path(&path, &mut name);
+        unknown.push(name);
+    })?;
+    unknown.retain(|path| !COMMON_FIELDS.contains(&path.as_str()));
+
+    let known = serde_json::to_value(&payload).unwrap_or_default();
+    let mut unread = Vec::new();
+    walk(input, &known, "", "", &mut unread);
+    for (name, pointer) in unread {
+        if !unknown.contains(&name) && !reads_field::<T>(input, &pointer) {
+            unknown.push(name);
+        }
+    }
+    unknown.sort();
+    Ok((payload, unknown))
+}
+
+/// Fields in `input` missing from `known`, the re-serialization of what was parsed, as display
+/// path and JSON pointer. Nested objects and arrays are walked where both sides have them.
+fn walk(input: &Value, known: &Value, prefix: &str, pointer: &str, unread: &mut Vec<(String, String)>) {
+    match (input, known) {
+        (Value::Object(fields), Value::Object(known_fields)) => {
+            for (name, value) in fields {
+                if prefix.is_empty() && COMMON_FIELDS.contains(&name.as_str()) {
+                    continue;
+                }
+                let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+                let pointer = format!("{}/{}", pointer, name.replace('~', "~0").replace('/', "~1"));
+                match known_fields.get(name) {
+                    Some(known_value) => walk(value, known_value, &path, &pointer, unread),
+                    None => unread.push((path, pointer)),
+                }
+            }
+        }
+        (Value::Array(items), Value::Array(known_items)) => {
+            for (i, (item, known_item)) in items.iter().zip(known_items).enumerate() {
+                walk(item, known_item, &format!("{}[{}]", prefix, i), &format!("{}/{}", pointer, i), unread);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether `T` reads the field at `pointer` even though it wasn't serialized back, like a null
+/// `Option` behind `skip_serializing_if`. A real field given a value of the wrong shape fails to
+/// parse or comes back out; an unknown one is dropped either way.
+fn reads_field<T: DeserializeOwned + Serialize>(input: &Value, pointer: &str) -> bool {
+    let mut probe = input.clone();
+    if let Some(value) = probe.pointer_mut(pointer) {
+        *value = serde_json::json!({ "\u{0}": [] });
+    }
+    match serde_json::from_value::<T>(probe) {
+        Err(_) => true,
+        Ok(payload) => serde_json::to_value(&payload).map(|v| v.pointer(pointer).is_some()).unwrap_or(true),
+    }
+}
+
+/// "a.b[0].c" rather than serde_ignored's "a.b.0.c", and without its "?" for Option layers.
+fn write_path(path: &Path, out: &mut String) {
+    match path {
+        Path::Root => {}
+        Path::Seq { parent, index } => {
+            write_path(parent, out);
+            out.push_str(&format!("[{}]", index));
+        }
+        Path::Map { parent, key } => {
+            write_path(parent, out);
+            if !out.is_empty() {
+                out.push('.');
+            }
+            out.push_str(key);
+        }
+        Path::Some { parent } | Path::NewtypeStruct { parent } | Path::NewtypeVariant { parent } => write_path(parent, out),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Serialize, Deserialize)]
+    struct Target {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rpc_url: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        provider: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Account {
+        pubkey: String,
+        #[serde(default)]
+        is_signer: bool,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Extra {
+        program_id: String,
+        #[serde(default)]
+        accounts: Vec<Account>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Options {
+        #[serde(default)]
+        fresh_blockhash: bool,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Common {
+        #[serde(default)]
+        extra_instructions: Vec<Extra>,
+    }
+
+    /// Shaped like the transfer requests: flattened members, skipped options and nested lists.
+    #[derive(Serialize, Deserialize)]
+    struct Transfer {
+        #[serde(flatten)]
+        target: Target,
+        #[serde(flatten)]
+        common: Common,
+        #[serde(default)]
+        amount: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        notes: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        options: Option<Options>,
+    }
+
+    fn unknown(input: serde_json::Value) -> Vec<String> {
+        parse::<Transfer>(&input).unwrap().1
+    }
+
+    #[test]
+    fn a_top_level_typo_is_reported_despite_the_flattened_target() {
+        assert_eq!(unknown(json!({ "ammount": "1.5" })), vec!["ammount"]);
+        assert_eq!(unknown(json!({ "amount": "1.5", "rpc_ulr": "https://rpc.example.com" })), vec!["rpc_ulr"]);
+    }
+
+    #[test]
+    fn nested_typos_are_reported_with_their_path_even_under_a_flattened_member() {
+        let input = json!({
+            "amount": "1",
+            "options": { "fresh_blockhsh": true },
+            "extra_instructions": [
+                { "program_id": "p", "accounts": [] },
+                { "program_id": "p", "acounts": [], "accounts": [{ "pubkey": "k", "is_singer": true }] }
+            ]
+        });
+        assert_eq!(
+            unknown(input),
+            vec!["extra_instructions[1].accounts[0].is_singer", "extra_instructions[1].acounts", "options.fresh_blockhsh"]
+        );
+    }
+
+    #[test]
+    fn skipped_and_flattened_fields_are_known() {
+        let input = json!({
+            "amount": "1",
+            "notes": null,
+            "options": null,
+            "rpc_url": null,
+            "provider": "helius",
+            "timeout_ms": 5000,
+            "extra_instructions": []
+        });
+        assert!(unknown(input).is_empty());
+    }
+
+    #[test]
+    fn a_value_of_the_wrong_type_is_still_a_parse_error() {
+        assert!(parse::<Transfer>(&json!({ "amount": 1.5 })).is_err());
+    }
+}
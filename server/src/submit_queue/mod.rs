@@ -0,0 +1,331 @@
+//! Bulk submission queue: signed transactions are enqueued, then drained by a small worker pool
+//! with bounded concurrency and a minimum gap between submissions. The queue is written through to
+//! ~/.fuego/submit-queue.json so a restart never drops a signed-but-unsubmitted transaction.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+use crate::pending::PendingTracker;
+use crate::rpc::RpcPool;
+use crate::utils::fuego_home;
+
+/// Finished items kept for status queries; older ones are dropped from the persisted queue.
+const MAX_FINISHED_ITEMS: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    Queued,
+    Submitting,
+    Submitted,
+    Simulated,
+    Failed,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct QueueItem {
+    pub id: String,
+    pub network: String,
+    /// Base64-encoded signed transaction
+    pub transaction: String,
+    #[serde(default)]
+    pub versioned: bool,
+    /// First signature of the transaction, known as soon as it is enqueued
+    pub signature: Option<String>,
+    pub status: ItemStatus,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub enqueued_at: i64,
+    #[serde(default)]
+    pub finished_at: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct PersistedQueue {
+    items: Vec<QueueItem>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    items: HashMap<String, QueueItem>,
+    pending: VecDeque<String>,
+    /// Completion times of recent submissions, for the drain rate
+    recent: VecDeque<Instant>,
+    submitted_total: u64,
+    failed_total: u64,
+}
+
+#[derive(Clone)]
+pub struct SubmitQueue {
+    inner: Arc<Mutex<QueueState>>,
+    notify: Arc<Notify>,
+    /// Earliest moment the next submission may start, shared by all workers
+    next_slot: Arc<tokio::sync::Mutex<Instant>>,
+    concurrency: usize,
+    delay: Duration,
+}
+
+fn queue_path() -> PathBuf {
+    fuego_home().join("submit-queue.json")
+}
+
+impl SubmitQueue {
+    /// Concurrency from FUEGO_SUBMIT_CONCURRENCY (default 4), gap from FUEGO_SUBMIT_DELAY_MS (default 250).
+    /// Items left queued or mid-submission by a previous run are requeued; resending a signed
+    /// transaction that already landed is rejected by the cluster rather than paid twice.
+    pub fn from_env() -> Self {
+        let concurrency = std::env::var("FUEGO_SUBMIT_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(4);
+        let delay_ms = std::env::var("FUEGO_SUBMIT_DELAY_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(250);
+
+        let mut state = QueueState::default();
+        let persisted: PersistedQueue = fs::read_to_string(queue_path())
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default();
+        let mut restored: Vec<QueueItem> = persisted.items;
+        restored.sort_by_key(|i| i.enqueued_at);
+        for mut item in restored {
+            if matches!(item.status, ItemStatus::Queued | ItemStatus::Submitting) {
+                item.status = ItemStatus::Queued;
+                state.pending.push_back(item.id.clone());
+            }
+            state.items.insert(item.id.clone(), item);
+        }
+
+        SubmitQueue {
+            inner: Arc::new(Mutex::new(state)),
+            notify: Arc::new(Notify::new()),
+            next_slot: Arc::new(tokio::sync::Mutex::new(Instant::now())),
+            concurrency,
+            delay: Duration::from_millis(delay_ms),
+        }
+    }
+
+    pub fn enqueue(&self, network: &str, transaction: String, versioned: bool, signature: Option<String>) -> QueueItem {
+        let item = QueueItem {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            network: network.to_string(),
+            transaction,
+            versioned,
+            signature,
+            status: ItemStatus::Queued,
+            error: None,
+            enqueued_at: chrono::Utc::now().timestamp(),
+            finished_at: None,
+        };
+        {
+            let mut state = self.inner.lock().unwrap();
+            state.items.insert(item.id.clone(), item.clone());
+            state.pending.push_back(item.id.clone());
+            Self::persist_locked(&state);
+        }
+        self.notify.notify_one();
+        item
+    }
+
+    pub fn get(&self, id: &str) -> Option<QueueItem> {
+        self.inner.lock().unwrap().items.get(id).cloned()
+    }
+
+    /// All tracked items, oldest first.
+    pub fn snapshot(&self) -> Vec<QueueItem> {
+        let mut items: Vec<QueueItem> = self.inner.lock().unwrap().items.values().cloned().collect();
+        items.sort_by_key(|i| i.enqueued_at);
+        items
+    }
+
+    /// Transactions waiting to be submitted.
+    pub fn depth(&self) -> usize {
+        self.inner.lock().unwrap().pending.len()
+    }
+
+    /// Depth, throughput and totals for /submit-queue.
+    pub fn stats(&self) -> serde_json::Value {
+        let mut state = self.inner.lock().unwrap();
+        let minute_ago = Instant::now() - Duration::from_secs(60);
+        while state.recent.front().map(|t| *t < minute_ago).unwrap_or(false) {
+            state.recent.pop_front();
+        }
+        let in_flight = state.items.values().filter(|i| i.status == ItemStatus::Submitting).count();
+        serde_json::json!({
+            "depth": state.pending.len(),
+            "in_flight": in_flight,
+            "drain_rate_per_minute": state.recent.len(),
+            "submitted_total": state.submitted_total,
+            "failed_total": state.failed_total,
+            "concurrency": self.concurrency,
+            "delay_ms": self.delay.as_millis() as u64
+        })
+    }
+
+    /// Write the whole queue to disk. Called with the lock held so snapshots never interleave.
+    fn persist_locked(state: &QueueState) {
+        let mut items: Vec<&QueueItem> = state.items.values().collect();
+        items.sort_by_key(|i| i.enqueued_at);
+        let persisted = serde_json::json!({ "items": items });
+        let result = fs::create_dir_all(fuego_home()).and_then(|_| {
+            fs::write(queue_path(), serde_json::to_string_pretty(&persisted).unwrap_or_default())
+        });
+        if let Err(e) = result {
+            eprintln!("Failed to persist submit queue: {}", e);
+        }
+    }
+
+    pub fn persist(&self) {
+        Self::persist_locked(&self.inner.lock().unwrap());
+    }
+
+    fn next_pending(&self) -> Option<QueueItem> {
+        let mut state = self.inner.lock().unwrap();
+        let id = state.pending.pop_front()?;
+        let item = state.items.get_mut(&id)?;
+        item.status = ItemStatus::Submitting;
+        let item = item.clone();
+        Self::persist_locked(&state);
+        Some(item)
+    }
+
+    fn finish(&self, id: &str, status: ItemStatus, signature: Option<String>, error: Option<String>) {
+        let mut state = self.inner.lock().unwrap();
+        if let Some(item) = state.items.get_mut(id) {
+            item.status = status;
+            if signature.is_some() {
+                item.signature = signature;
+            }
+            item.error = error;
+            item.finished_at = Some(chrono::Utc::now().timestamp());
+        }
+        match status {
+            ItemStatus::Failed => state.failed_total += 1,
+            _ => state.submitted_total += 1,
+        }
+        state.recent.push_back(Instant::now());
+
+        // Keep the persisted file bounded: drop the oldest finished items beyond the cap
+        let mut finished: Vec<(i64, String)> = state
+            .items
+            .values()
+            .filter(|i| i.finished_at.is_some())
+            .map(|i| (i.finished_at.unwrap_or(0), i.id.clone()))
+            .collect();
+        if finished.len() > MAX_FINISHED_ITEMS {
+            finished.sort();
+            let excess = finished.len() - MAX_FINISHED_ITEMS;
+            for (_, old) in finished.into_iter().take(excess) {
+                state.items.remove(&old);
+            }
+        }
+        Self::persist_locked(&state);
+    }
+
+    /// Start the worker pool. Workers drain anything restored from disk before waiting for new work.
+    /// In dry-run, items are simulated instead of sent.
+    pub fn spawn_workers(&self, rpc: RpcPool, tracker: PendingTracker, dry_run: bool) {
+        for _ in 0..self.concurrency {
+            let queue = self.clone();
+            let rpc = rpc.clone();
+            let tracker = tracker.clone();
+            tokio::spawn(async move {
+                loop {
+                    let item = match queue.next_pending() {
+                        Some(item) => item,
+                        None => {
+                            queue.notify.notified().await;
+                            continue;
+                        }
+                    };
+
+                    // Space submissions out across all workers
+                    {
+                        let mut next_slot = queue.next_slot.lock().await;
+                        let now = Instant::now();
+                        if *next_slot > now {
+                            tokio::time::sleep(*next_slot - now).await;
+                        }
+                        *next_slot = Instant::now() + queue.delay;
+                    }
+
+                    let rpc_url = rpc.primary(&item.network);
+                    let started = Instant::now();
+                    let (url, network) = (rpc_url.clone(), item.network.clone());
+                    let (transaction, versioned) = (item.transaction.clone(), item.versioned);
+                    let outcome = tokio::task::spawn_blocking(move || submit_one(&url, &network, &transaction, versioned, dry_run))
+                        .await
+                        .unwrap_or_else(|e| Err(format!("Submission task failed: {}", e)));
+                    rpc.record(&rpc_url, started.elapsed(), outcome.is_ok());
+
+                    match outcome {
+                        Ok(signature) => {
+                            let status = if dry_run { ItemStatus::Simulated } else { ItemStatus::Submitted };
+                            if !dry_run {
+                                let signed = crate::pending::SignedTransaction {
+                                    transaction: item.transaction.clone(),
+                                    versioned: item.versioned,
+                                };
+                                tracker.track(&signature, &item.network, Some(signed));
+                            }
+                            queue.finish(&item.id, status, Some(signature), None);
+                        }
+                        Err(e) => queue.finish(&item.id, ItemStatus::Failed, None, Some(e)),
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Decode and send (or simulate) one signed transaction, returning its signature.
+fn submit_one(rpc_url: &str, network: &str, transaction: &str, versioned: bool, dry_run: bool) -> Result<String, String> {
+    use base64::engine::general_purpose;
+    use base64::Engine;
+    use solana_client::rpc_config::CommitmentConfig;
+    use solana_transaction::versioned::VersionedTransaction as ClientVersionedTransaction;
+    use solana_transaction::Transaction as ClientTransaction;
+
+    let bytes = general_purpose::STANDARD
+        .decode(transaction)
+        .map_err(|_| "Invalid base64 transaction".to_string())?;
+    let rpc = crate::rpc::client(rpc_url.to_string(), CommitmentConfig::default());
+
+    if versioned {
+        let tx: ClientVersionedTransaction =
+            bincode::deserialize(&bytes).map_err(|_| "Failed to deserialize transaction".to_string())?;
+        let signature = tx.signatures.first().map(|s| s.to_string()).unwrap_or_default();
+        if dry_run {
+            let result = rpc.simulate_transaction(&tx).map_err(|e| e.to_string())?;
+            return match result.value.err {
+                Some(err) => Err(format!("Simulation failed: {}", err)),
+                None => Ok(signature),
+            };
+        }
+        let signature = rpc.send_transaction(&tx).map(|s| s.to_string()).map_err(|e| e.to_string())?;
+        crate::memo_index::record(&signature, network, crate::memo_index::memos_from_versioned(&tx), "submit-queue");
+        Ok(signature)
+    } else {
+        let tx: ClientTransaction =
+            bincode::deserialize(&bytes).map_err(|_| "Failed to deserialize transaction".to_string())?;
+        let signature = tx.signatures.first().map(|s| s.to_string()).unwrap_or_default();
+        if dry_run {
+            let result = rpc.simulate_transaction(&tx).map_err(|e| e.to_string())?;
+            return match result.value.err {
+                Some(err) => Err(format!("Simulation failed: {}", err)),
+                None => Ok(signature),
+            };
+        }
+        let signature = rpc.send_transaction(&tx).map(|s| s.to_string()).map_err(|e| e.to_string())?;
+        crate::memo_index::record(&signature, network, crate::memo_index::memos_from_legacy(&tx), "submit-queue");
+        Ok(signature)
+    }
+}
@@ -0,0 +1,478 @@
+//! Time-locked transfers: a transfer built against a durable nonce account that fuego creates and
+//! controls, so the signed transaction never expires. Intents are written through to
+//! ~/.fuego/timelocked-transfers.json and a scheduler submits each one only once the cluster's clock
+//! has passed its unlock time, after checking the nonce is still the one the transaction was built on.
+
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::keypair::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::Transaction;
+use solana_system_interface::instruction::{create_nonce_account, withdraw_nonce_account};
+use solana_transaction::Transaction as ClientTransaction;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::alerts::{AlertLog, Severity};
+use crate::rpc::RpcPool;
+use crate::utils::fuego_home;
+
+const TICK: Duration = Duration::from_secs(15);
+
+/// Size of an initialized nonce account: version, state, authority, durable nonce and fee calculator.
+pub const NONCE_ACCOUNT_LEN: usize = 80;
+
+/// A submitted transaction not yet seen by the RPC after this long is sent again. Durable-nonce
+/// transactions don't expire, so resending is safe until the nonce moves.
+const RESEND_AFTER_SECS: i64 = 60;
+
+/// Failed sends at unlock time before the intent is marked failed.
+const MAX_SEND_ATTEMPTS: u32 = 5;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntentStatus {
+    /// Built and partially signed; the sender's signature is still missing
+    AwaitingSignature,
+    AwaitingUnlock,
+    Submitted,
+    Confirmed,
+    Failed,
+    Cancelled,
+}
+
+impl IntentStatus {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "awaiting_signature" => Ok(IntentStatus::AwaitingSignature),
+            "awaiting_unlock" => Ok(IntentStatus::AwaitingUnlock),
+            "submitted" => Ok(IntentStatus::Submitted),
+            "confirmed" => Ok(IntentStatus::Confirmed),
+            "failed" => Ok(IntentStatus::Failed),
+            "cancelled" => Ok(IntentStatus::Cancelled),
+            other => Err(format!(
+                "Unknown status '{}' (use awaiting_signature, awaiting_unlock, submitted, confirmed, failed or cancelled)",
+                other
+            )),
+        }
+    }
+
+    /// Not yet handed to the cluster; the nonce account still guards the transaction.
+    pub fn is_locked(&self) -> bool {
+        matches!(self, IntentStatus::AwaitingSignature | IntentStatus::AwaitingUnlock)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TimelockedTransfer {
+    pub id: String,
+    pub network: String,
+    pub token: String,
+    pub from: String,
+    pub to: String,
+    /// UI amount as requested
+    pub amount: String,
+    pub amount_base_units: String,
+    /// Unix time before which the transfer is never submitted
+    pub unlock_at: i64,
+    pub nonce_account: String,
+    /// The local wallet; it must sign the transaction's AdvanceNonceAccount instruction
+    pub nonce_authority: String,
+    /// Durable nonce the transaction was built on, used as its blockhash
+    pub nonce: String,
+    pub nonce_rent_lamports: u64,
+    /// Base64-encoded transaction; fully signed once the intent leaves awaiting_signature
+    pub transaction: String,
+    pub signature: Option<String>,
+    pub status: IntentStatus,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub send_attempts: u32,
+    pub created_at: i64,
+    #[serde(default)]
+    pub submitted_at: Option<i64>,
+    #[serde(default)]
+    pub confirmed_at: Option<i64>,
+    #[serde(default)]
+    pub cancelled_at: Option<i64>,
+    /// The nonce account was withdrawn to its authority after confirmation or cancellation
+    #[serde(default)]
+    pub nonce_closed: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct PersistedIntents {
+    transfers: Vec<TimelockedTransfer>,
+}
+
+#[derive(Clone, Default)]
+pub struct TimelockStore {
+    inner: Arc<Mutex<HashMap<String, TimelockedTransfer>>>,
+}
+
+fn store_path() -> PathBuf {
+    fuego_home().join("timelocked-transfers.json")
+}
+
+impl TimelockStore {
+    pub fn load() -> Self {
+        let persisted: PersistedIntents = fs::read_to_string(store_path())
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default();
+        let map = persisted.transfers.into_iter().map(|t| (t.id.clone(), t)).collect();
+        TimelockStore {
+            inner: Arc::new(Mutex::new(map)),
+        }
+    }
+
+    /// Write every intent to disk. Called with the lock held so snapshots never interleave.
+    fn persist_locked(map: &HashMap<String, TimelockedTransfer>) {
+        let mut transfers: Vec<&TimelockedTransfer> = map.values().collect();
+        transfers.sort_by_key(|t| t.created_at);
+        let persisted = serde_json::json!({ "transfers": transfers });
+        let result = fs::create_dir_all(fuego_home()).and_then(|_| {
+            fs::write(store_path(), serde_json::to_string_pretty(&persisted).unwrap_or_default())
+        });
+        if let Err(e) = result {
+            eprintln!("Failed to persist time-locked transfers: {}", e);
+        }
+    }
+
+    pub fn insert(&self, transfer: TimelockedTransfer) {
+        let mut map = self.inner.lock().unwrap();
+        map.insert(transfer.id.clone(), transfer);
+        Self::persist_locked(&map);
+    }
+
+    pub fn get(&self, id: &str) -> Option<TimelockedTransfer> {
+        self.inner.lock().unwrap().get(id).cloned()
+    }
+
+    /// Intents ordered by unlock time, optionally only those in one status.
+    pub fn list(&self, status: Option<IntentStatus>) -> Vec<TimelockedTransfer> {
+        let mut transfers: Vec<TimelockedTransfer> = self
+            .inner
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|t| status.is_none_or(|s| t.status == s))
+            .cloned()
+            .collect();
+        transfers.sort_by_key(|t| t.unlock_at);
+        transfers
+    }
+
+    /// Apply `change` to one intent and persist. Returns the updated intent.
+    pub fn update(&self, id: &str, change: impl FnOnce(&mut TimelockedTransfer)) -> Option<TimelockedTransfer> {
+        let mut map = self.inner.lock().unwrap();
+        let transfer = map.get_mut(id)?;
+        change(transfer);
+        let updated = transfer.clone();
+        Self::persist_locked(&map);
+        Some(updated)
+    }
+
+    /// The still-locked intent whose nonce account appears among `account_keys`, if its unlock time
+    /// hasn't passed. Submit endpoints use this to refuse early manual submission.
+    pub fn locked_by(&self, account_keys: &[String], now: i64) -> Option<TimelockedTransfer> {
+        self.inner
+            .lock()
+            .unwrap()
+            .values()
+            .find(|t| t.status.is_locked() && now < t.unlock_at && account_keys.contains(&t.nonce_account))
+            .cloned()
+    }
+}
+
+/// Current durable nonce from nonce account data (bincode `nonce::state::Versions`). None unless
+/// the account is initialized.
+pub fn parse_nonce_account(data: &[u8]) -> Option<Hash> {
+    if data.len() < NONCE_ACCOUNT_LEN {
+        return None;
+    }
+    // Bytes 0..4 are the version (legacy or current, same layout); 4..8 the state, 1 = initialized
+    if u32::from_le_bytes(data[4..8].try_into().ok()?) != 1 {
+        return None;
+    }
+    // 8..40 is the authority
+    let nonce: [u8; 32] = data[40..72].try_into().ok()?;
+    Some(Hash::new_from_array(nonce))
+}
+
+/// Current durable nonce of a nonce account, or None once it has been closed.
+pub async fn fetch_nonce(rpc: &RpcClient, nonce_account: &Pubkey) -> Result<Option<Hash>, String> {
+    let account = rpc
+        .get_account_with_commitment(nonce_account, CommitmentConfig::confirmed())
+        .await
+        .map_err(|e| format!("Failed to fetch nonce account: {}", e))?
+        .value;
+    Ok(account.and_then(|a| parse_nonce_account(&a.data)))
+}
+
+fn to_client(transaction: &Transaction) -> Result<ClientTransaction, String> {
+    bincode::serialize(transaction)
+        .ok()
+        .and_then(|bytes| bincode::deserialize(&bytes).ok())
+        .ok_or_else(|| "Failed to serialize transaction".to_string())
+}
+
+/// Create and fund a fresh nonce account with `authority` as payer and authority, wait for it to
+/// confirm, and return its address, first durable nonce and the rent it holds.
+pub async fn create_managed_nonce(rpc: &RpcClient, authority: &Keypair) -> Result<(Pubkey, Hash, u64), String> {
+    let nonce_keypair = Keypair::new();
+    let rent = rpc
+        .get_minimum_balance_for_rent_exemption(NONCE_ACCOUNT_LEN)
+        .await
+        .map_err(|e| format!("Failed to fetch nonce account rent: {}", e))?;
+    let blockhash = rpc
+        .get_latest_blockhash()
+        .await
+        .map_err(|e| format!("Failed to fetch blockhash: {}", e))?;
+    let instructions = create_nonce_account(&authority.pubkey(), &nonce_keypair.pubkey(), &authority.pubkey(), rent);
+    let message = Message::new_with_blockhash(&instructions, Some(&authority.pubkey()), &blockhash);
+    let transaction = Transaction::new(&[authority, &nonce_keypair], message, blockhash);
+    rpc.send_and_confirm_transaction(&to_client(&transaction)?)
+        .await
+        .map_err(|e| format!("Failed to create nonce account: {}", e))?;
+
+    let nonce = fetch_nonce(rpc, &nonce_keypair.pubkey())
+        .await?
+        .ok_or_else(|| "Nonce account was created but is not initialized yet; try again".to_string())?;
+    Ok((nonce_keypair.pubkey(), nonce, rent))
+}
+
+/// Withdraw a nonce account's whole balance back to its authority, closing it. Any transaction
+/// built on its nonce can then never land. Returns the withdrawal signature, or None if the account
+/// was already closed.
+pub async fn close_nonce(rpc: &RpcClient, authority: &Keypair, nonce_account: &Pubkey) -> Result<Option<String>, String> {
+    let lamports = rpc
+        .get_balance(nonce_account)
+        .await
+        .map_err(|e| format!("Failed to fetch nonce account balance: {}", e))?;
+    if lamports == 0 {
+        return Ok(None);
+    }
+    let blockhash = rpc
+        .get_latest_blockhash()
+        .await
+        .map_err(|e| format!("Failed to fetch blockhash: {}", e))?;
+    let instruction = withdraw_nonce_account(nonce_account, &authority.pubkey(), &authority.pubkey(), lamports);
+    let message = Message::new_with_blockhash(&[instruction], Some(&authority.pubkey()), &blockhash);
+    let transaction = Transaction::new(&[authority], message, blockhash);
+    rpc.send_and_confirm_transaction(&to_client(&transaction)?)
+        .await
+        .map(|s| Some(s.to_string()))
+        .map_err(|e| format!("Failed to close nonce account: {}", e))
+}
+
+/// What one scheduler pass did to an intent.
+enum Step {
+    Idle,
+    Submitted(String),
+    Confirmed,
+    /// Send failed but may succeed on a later tick
+    Retry(String),
+    Failed(String),
+}
+
+/// Unix time of the cluster's latest finalized block. Unlocks follow the chain, not the local clock.
+async fn cluster_time(rpc: &RpcClient) -> Result<i64, String> {
+    let slot = rpc.get_slot().await.map_err(|e| format!("Failed to fetch slot: {}", e))?;
+    rpc.get_block_time(slot).await.map_err(|e| format!("Failed to fetch block time: {}", e))
+}
+
+/// Whether `signature` has landed: Some(Ok) confirmed, Some(Err) failed on chain, None not seen.
+async fn landed(rpc: &RpcClient, transaction: &ClientTransaction) -> Result<Option<Result<(), String>>, String> {
+    let signature = transaction.signatures.first().ok_or_else(|| "Transaction has no signature".to_string())?;
+    let statuses = rpc
+        .get_signature_statuses(&[*signature])
+        .await
+        .map_err(|e| format!("Failed to fetch signature status: {}", e))?;
+    Ok(match statuses.value.into_iter().next().flatten() {
+        Some(status) if status.err.is_some() => Some(Err(format!("Transaction failed on chain: {:?}", status.err))),
+        Some(status) if status.satisfies_commitment(CommitmentConfig::confirmed()) => Some(Ok(())),
+        _ => None,
+    })
+}
+
+async fn step(rpc: &RpcPool, rpc_url: &str, transfer: &TimelockedTransfer, now: i64) -> Step {
+    use base64::Engine;
+
+    let rpc = rpc.client(&transfer.network, rpc_url, CommitmentConfig::finalized());
+    let transaction: ClientTransaction = match base64::engine::general_purpose::STANDARD
+        .decode(&transfer.transaction)
+        .ok()
+        .and_then(|bytes| bincode::deserialize(&bytes).ok())
+    {
+        Some(tx) => tx,
+        None => return Step::Failed("Stored transaction is unreadable".to_string()),
+    };
+    let nonce_account = match crate::utils::string_to_pub_key(&transfer.nonce_account) {
+        Ok(pk) => pk,
+        Err(_) => return Step::Failed("Stored nonce account is invalid".to_string()),
+    };
+
+    match transfer.status {
+        IntentStatus::AwaitingUnlock => {
+            // Lookups that fail are retried next tick without counting as a send attempt
+            match cluster_time(&rpc).await {
+                Ok(time) if time >= transfer.unlock_at => {}
+                _ => return Step::Idle,
+            }
+            match fetch_nonce(&rpc, &nonce_account).await {
+                Ok(Some(nonce)) if nonce.to_string() == transfer.nonce => {}
+                Ok(_) => {
+                    // The nonce moved: fine if it was this transaction landing, otherwise it can never land
+                    return match landed(&rpc, &transaction).await {
+                        Ok(Some(Ok(()))) => Step::Confirmed,
+                        Ok(Some(Err(e))) => Step::Failed(e),
+                        _ => Step::Failed(
+                            "Nonce account was advanced or closed before unlock; this transaction can no longer land".to_string(),
+                        ),
+                    };
+                }
+                Err(_) => return Step::Idle,
+            }
+            match rpc.send_transaction(&transaction).await {
+                Ok(signature) => Step::Submitted(signature.to_string()),
+                Err(e) => Step::Retry(format!("Failed to submit transaction: {}", e)),
+            }
+        }
+        IntentStatus::Submitted => match landed(&rpc, &transaction).await {
+            Ok(Some(Ok(()))) => Step::Confirmed,
+            Ok(Some(Err(e))) => Step::Failed(e),
+            Ok(None) if now - transfer.submitted_at.unwrap_or(now) >= RESEND_AFTER_SECS => {
+                match fetch_nonce(&rpc, &nonce_account).await {
+                    Ok(Some(nonce)) if nonce.to_string() == transfer.nonce => {
+                        let _ = rpc.send_transaction(&transaction).await;
+                        Step::Idle
+                    }
+                    // Moved but not by us (a status lookup can lag; re-check before giving up)
+                    Ok(_) => match landed(&rpc, &transaction).await {
+                        Ok(Some(Ok(()))) => Step::Confirmed,
+                        Ok(Some(Err(e))) => Step::Failed(e),
+                        _ => Step::Failed("Nonce account was advanced by another transaction".to_string()),
+                    },
+                    Err(_) => Step::Idle,
+                }
+            }
+            Ok(None) => Step::Idle,
+            Err(_) => Step::Idle,
+        },
+        _ => Step::Idle,
+    }
+}
+
+/// Submit intents once unlocked and follow them to confirmation. A confirmed intent's nonce account
+/// is closed so its rent returns to the local wallet. `load_authority` yields the local wallet key.
+pub fn spawn_scheduler(store: TimelockStore, rpc: RpcPool, alerts: AlertLog, load_authority: fn() -> Result<Keypair, String>) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(TICK).await;
+            let now = chrono::Utc::now().timestamp();
+            let due: Vec<TimelockedTransfer> = store
+                .list(None)
+                .into_iter()
+                .filter(|t| {
+                    (t.status == IntentStatus::AwaitingUnlock && t.unlock_at <= now)
+                        || t.status == IntentStatus::Submitted
+                        || (t.status == IntentStatus::Confirmed && !t.nonce_closed)
+                })
+                .collect();
+
+            for transfer in due {
+                let rpc_url = rpc.primary(&transfer.network);
+
+                if transfer.status == IntentStatus::Confirmed {
+                    let closed = async {
+                        let authority = load_authority()?;
+                        let nonce_account = crate::utils::string_to_pub_key(&transfer.nonce_account).map_err(|e| e.to_string())?;
+                        let client = rpc.client(&transfer.network, &rpc_url, CommitmentConfig::confirmed());
+                        close_nonce(&client, &authority, &nonce_account).await
+                    }
+                    .await;
+                    match closed {
+                        Ok(_) => {
+                            store.update(&transfer.id, |t| t.nonce_closed = true);
+                        }
+                        Err(e) => eprintln!("Could not close nonce account {}: {}", transfer.nonce_account, e),
+                    }
+                    continue;
+                }
+
+                let outcome = step(&rpc, &rpc_url, &transfer, now).await;
+
+                match outcome {
+                    Step::Idle => {}
+                    Step::Submitted(signature) => {
+                        store.update(&transfer.id, |t| {
+                            t.status = IntentStatus::Submitted;
+                            t.signature = Some(signature);
+                            t.submitted_at = Some(now);
+                            t.error = None;
+                        });
+                    }
+                    Step::Confirmed => {
+                        store.update(&transfer.id, |t| {
+                            t.status = IntentStatus::Confirmed;
+                            t.confirmed_at = Some(now);
+                            t.error = None;
+                        });
+                    }
+                    // A cancel that raced this pass wins over failures, but not over a send
+                    Step::Retry(e) => {
+                        let updated = store.update(&transfer.id, |t| {
+                            if t.status != transfer.status {
+                                return;
+                            }
+                            t.send_attempts += 1;
+                            t.error = Some(e.clone());
+                            if t.send_attempts >= MAX_SEND_ATTEMPTS {
+                                t.status = IntentStatus::Failed;
+                            }
+                        });
+                        if updated.is_some_and(|t| t.status == IntentStatus::Failed) {
+                            emit_failure(&alerts, &transfer, &e);
+                        }
+                    }
+                    Step::Failed(e) => {
+                        let updated = store.update(&transfer.id, |t| {
+                            if t.status == transfer.status {
+                                t.status = IntentStatus::Failed;
+                                t.error = Some(e.clone());
+                            }
+                        });
+                        if updated.is_some_and(|t| t.status == IntentStatus::Failed) {
+                            emit_failure(&alerts, &transfer, &e);
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
+
+fn emit_failure(alerts: &AlertLog, transfer: &TimelockedTransfer, error: &str) {
+    alerts.emit(
+        "timelocked_transfer_failed",
+        Severity::High,
+        format!("Time-locked transfer {} failed: {}", transfer.id, error),
+        serde_json::json!({
+            "id": transfer.id,
+            "network": transfer.network,
+            "token": transfer.token,
+            "to": transfer.to,
+            "amount": transfer.amount,
+            "nonce_account": transfer.nonce_account
+        }),
+    );
+}
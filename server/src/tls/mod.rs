@@ -0,0 +1,76 @@
+//! Optional HTTPS without a reverse proxy in front. With `tls.cert_path` and `tls.key_path` in the
+//! server config, fuego serves HTTPS through rustls on its usual address; without them it serves
+//! plain HTTP as before. Both files are read and checked at startup, and a file that isn't usable
+//! PEM stops the server with a message naming it. On Unix, SIGHUP re-reads them, so a renewed
+//! certificate is picked up without a restart; a bad renewal is logged and the old one kept.
+
+use axum_server::tls_rustls::RustlsConfig;
+use std::path::Path;
+
+use crate::config::TlsConfig;
+
+/// The certificate chain and key, read and checked for use.
+pub async fn load(config: &TlsConfig) -> Result<RustlsConfig, String> {
+    let (cert, key) = read_pem(config)?;
+    RustlsConfig::from_pem(cert, key)
+        .await
+        .map_err(|e| format!("tls: {} and {} don't make a usable certificate: {}", config.cert_path.display(), config.key_path.display(), e))
+}
+
+/// Both files, checked to hold at least one certificate and a private key. The key's contents
+/// never appear in a message.
+fn read_pem(config: &TlsConfig) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let cert = read(&config.cert_path, "tls.cert_path")?;
+    let certs = rustls_pemfile::certs(&mut cert.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("tls.cert_path {} is not valid PEM: {}", config.cert_path.display(), e))?;
+    if certs.is_empty() {
+        return Err(format!(
+            "tls.cert_path {} holds no certificate; expected a PEM file with -----BEGIN CERTIFICATE-----",
+            config.cert_path.display()
+        ));
+    }
+
+    let key = read(&config.key_path, "tls.key_path")?;
+    match rustls_pemfile::private_key(&mut key.as_slice()) {
+        Ok(Some(_)) => Ok((cert, key)),
+        Ok(None) => Err(format!(
+            "tls.key_path {} holds no private key; expected a PEM file with a PKCS#8, PKCS#1 or SEC1 key",
+            config.key_path.display()
+        )),
+        Err(_) => Err(format!("tls.key_path {} is not valid PEM", config.key_path.display())),
+    }
+}
+
+fn read(path: &Path, key: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("Can't read {} {}: {}", key, path.display(), e))
+}
+
+/// Re-read both files on every SIGHUP for as long as the server runs.
+#[cfg(unix)]
+pub fn reload_on_sighup(rustls: RustlsConfig, config: TlsConfig) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            tracing::warn!(error = %e, "can't listen for SIGHUP; TLS certificate reload is off");
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            let reloaded = match read_pem(&config) {
+                Ok((cert, key)) => rustls.reload_from_pem(cert, key).await.map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            match reloaded {
+                Ok(()) => tracing::info!(cert_path = %config.cert_path.display(), "tls certificate reloaded"),
+                Err(e) => tracing::error!(error = %e, "tls certificate reload failed; still serving the previous one"),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn reload_on_sighup(_rustls: RustlsConfig, _config: TlsConfig) {}
@@ -0,0 +1,313 @@
+//! W3C trace context (`traceparent` / `tracestate`) for every request, carried onto outbound
+//! x402, webhook and raw JSON-RPC calls, plus optional span export over OTLP/HTTP JSON when
+//! FUEGO_OTLP_ENDPOINT (or OTEL_EXPORTER_OTLP_ENDPOINT) is set.
+//!
+//! Spans carry the network, endpoint and outcome code. Amounts are only ever recorded as an
+//! order-of-magnitude bucket, and not at all with FUEGO_TRACE_AMOUNTS=false.
+
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+pub const TRACEPARENT: &str = "traceparent";
+pub const TRACESTATE: &str = "tracestate";
+
+/// Spans waiting for export; past this, new spans are dropped rather than queued.
+const EXPORT_QUEUE: usize = 2048;
+const EXPORT_BATCH: usize = 256;
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Limits for raw JSON-RPC calls, so a stalled endpoint can't hold a request forever.
+const RPC_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+static HTTP: OnceLock<reqwest::Client> = OnceLock::new();
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+/// The server span of the request being handled.
+#[derive(Clone)]
+pub struct TraceContext {
+    /// 32 lowercase hex digits
+    pub trace_id: String,
+    /// 16 lowercase hex digits; outbound calls name this span as their parent
+    pub span_id: String,
+    /// The caller's span, when the request arrived with a traceparent
+    pub parent_span_id: Option<String>,
+    pub sampled: bool,
+    /// Vendor state, passed through untouched
+    pub tracestate: Option<String>,
+}
+
+impl TraceContext {
+    /// A child of the caller's traceparent, or the root of a new trace if there is none (or it is
+    /// malformed, which the spec says to treat the same way).
+    pub fn from_headers(headers: &axum::http::HeaderMap) -> Self {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(|v| v.trim().to_string());
+        let span_id = random_hex(8);
+        match header(TRACEPARENT).as_deref().and_then(parse_traceparent) {
+            Some((trace_id, parent, sampled)) => TraceContext {
+                trace_id,
+                span_id,
+                parent_span_id: Some(parent),
+                sampled,
+                tracestate: header(TRACESTATE).filter(|s| !s.is_empty()),
+            },
+            None => TraceContext {
+                trace_id: random_hex(16),
+                span_id,
+                parent_span_id: None,
+                sampled: true,
+                tracestate: None,
+            },
+        }
+    }
+
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-{}", self.trace_id, self.span_id, if self.sampled { "01" } else { "00" })
+    }
+}
+
+/// (trace id, parent span id, sampled) from a traceparent. Versions after 00 may append fields,
+/// which are ignored; version ff and all-zero ids are invalid.
+fn parse_traceparent(value: &str) -> Option<(String, String, bool)> {
+    let parts: Vec<&str> = value.split('-').collect();
+    if parts.len() < 4 {
+        return None;
+    }
+    let (version, trace_id, parent, flags) = (parts[0], parts[1], parts[2], parts[3]);
+    let is_hex = |s: &str, len: usize| s.len() == len && s.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+    if !is_hex(version, 2) || version == "ff" || (version == "00" && parts.len() != 4) {
+        return None;
+    }
+    if !is_hex(trace_id, 32) || !is_hex(parent, 16) || !is_hex(flags, 2) {
+        return None;
+    }
+    if trace_id.bytes().all(|b| b == b'0') || parent.bytes().all(|b| b == b'0') {
+        return None;
+    }
+    let sampled = u8::from_str_radix(flags, 16).map(|f| f & 1 == 1).unwrap_or(false);
+    Some((trace_id.to_string(), parent.to_string(), sampled))
+}
+
+fn random_hex(bytes: usize) -> String {
+    let id = uuid::Uuid::new_v4();
+    id.as_bytes()[..bytes].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Run a request's handling with `context` as the current span.
+pub async fn scope<F: std::future::Future>(context: TraceContext, future: F) -> F::Output {
+    CURRENT.scope(context, future).await
+}
+
+pub fn current() -> Option<TraceContext> {
+    CURRENT.try_with(|c| c.clone()).ok()
+}
+
+/// Spawn a task that keeps the current request's trace context and request id, e.g. a webhook
+/// delivery.
+pub fn spawn<F>(future: F)
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    let request_id = crate::request_id::current();
+    let future = async move {
+        match request_id {
+            Some(id) => crate::request_id::scope(id, future).await,
+            None => future.await,
+        }
+    };
+    match current() {
+        Some(context) => tokio::spawn(CURRENT.scope(context, future)),
+        None => tokio::spawn(future),
+    };
+}
+
+/// traceparent/tracestate and X-Request-Id for an outbound call made while handling a request;
+/// empty outside one.
+pub fn outbound_headers() -> reqwest::header::HeaderMap {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(Ok(value)) = crate::request_id::current().as_deref().map(str::parse) {
+        headers.insert(crate::request_id::HEADER, value);
+    }
+    if let Some(context) = current() {
+        if let Ok(value) = context.traceparent().parse() {
+            headers.insert(TRACEPARENT, value);
+        }
+        if let Some(Ok(value)) = context.tracestate.as_deref().map(str::parse) {
+            headers.insert(TRACESTATE, value);
+        }
+    }
+    headers
+}
+
+/// The process-wide HTTP client for raw JSON-RPC calls. Clones share one connection pool, so
+/// calls reuse connections instead of paying for TCP and TLS setup each time. Add
+/// `outbound_headers()` to each request to carry the trace context.
+pub fn http_client() -> reqwest::Client {
+    HTTP.get_or_init(|| {
+        reqwest::Client::builder()
+            .connect_timeout(RPC_CONNECT_TIMEOUT)
+            .timeout(RPC_TIMEOUT)
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new())
+    })
+    .clone()
+}
+
+/// Order-of-magnitude bucket for a UI amount, so traces never carry exact figures.
+pub fn amount_bucket(amount: f64) -> &'static str {
+    const BUCKETS: [(f64, &str); 8] = [
+        (0.01, "<0.01"),
+        (1.0, "0.01-1"),
+        (10.0, "1-10"),
+        (100.0, "10-100"),
+        (1_000.0, "100-1k"),
+        (10_000.0, "1k-10k"),
+        (100_000.0, "10k-100k"),
+        (f64::INFINITY, ">=100k"),
+    ];
+    if !amount.is_finite() || amount < 0.0 {
+        return "invalid";
+    }
+    if amount == 0.0 {
+        return "0";
+    }
+    BUCKETS.iter().find(|(below, _)| amount < *below).map(|(_, bucket)| *bucket).unwrap_or(">=100k")
+}
+
+/// A finished server span.
+pub struct Span {
+    pub context: TraceContext,
+    pub name: String,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    /// (key, value) with value a string, bool or integer
+    pub attributes: Vec<(&'static str, Value)>,
+    pub failed: bool,
+}
+
+/// Span exporter; a no-op unless an OTLP endpoint is configured.
+#[derive(Clone)]
+pub struct Tracer {
+    sender: Option<mpsc::Sender<Span>>,
+    /// FUEGO_TRACE_AMOUNTS=false leaves amount buckets off spans entirely
+    pub record_amounts: bool,
+}
+
+impl Tracer {
+    /// FUEGO_OTLP_ENDPOINT, else OTEL_EXPORTER_OTLP_ENDPOINT: the collector's base URL
+    /// (/v1/traces is appended) or the full traces URL. Service name from OTEL_SERVICE_NAME.
+    pub fn from_env() -> Self {
+        let record_amounts = std::env::var("FUEGO_TRACE_AMOUNTS").map(|v| v != "false").unwrap_or(true);
+        let endpoint = std::env::var("FUEGO_OTLP_ENDPOINT")
+            .or_else(|_| std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT"))
+            .ok()
+            .map(|e| e.trim().trim_end_matches('/').to_string())
+            .filter(|e| !e.is_empty());
+        let Some(endpoint) = endpoint else {
+            return Tracer { sender: None, record_amounts };
+        };
+        let url = if endpoint.ends_with("/v1/traces") { endpoint } else { format!("{}/v1/traces", endpoint) };
+        let service = std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "fuego-server".to_string());
+        let (sender, receiver) = mpsc::channel(EXPORT_QUEUE);
+        tokio::spawn(export_loop(receiver, url, service));
+        Tracer { sender: Some(sender), record_amounts }
+    }
+
+    /// True when this request's span would be exported: an endpoint is set and the caller didn't
+    /// mark the trace unsampled.
+    pub fn exports(&self, context: &TraceContext) -> bool {
+        self.sender.is_some() && context.sampled
+    }
+
+    pub fn record(&self, span: Span) {
+        if let Some(sender) = &self.sender {
+            // A full queue means the collector is down or slow; losing spans beats slowing requests
+            let _ = sender.try_send(span);
+        }
+    }
+}
+
+async fn export_loop(mut receiver: mpsc::Receiver<Span>, url: String, service: String) {
+    let http = reqwest::Client::new();
+    let mut batch = Vec::with_capacity(EXPORT_BATCH);
+    let mut interval = tokio::time::interval(EXPORT_INTERVAL);
+    loop {
+        // (channel closed, interval elapsed)
+        let (closed, due) = tokio::select! {
+            span = receiver.recv() => match span {
+                Some(span) => {
+                    batch.push(span);
+                    (false, false)
+                }
+                None => (true, false),
+            },
+            _ = interval.tick() => (false, true),
+        };
+        if !batch.is_empty() && (closed || due || batch.len() >= EXPORT_BATCH) {
+            let body = otlp_body(&service, &batch);
+            batch.clear();
+            let result = http.post(&url).json(&body).send().await.and_then(|r| r.error_for_status());
+            if let Err(e) = result {
+                eprintln!("Failed to export spans to {}: {}", url, e);
+            }
+        }
+        if closed {
+            return;
+        }
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0).to_string()
+}
+
+/// ExportTraceServiceRequest in the OTLP JSON encoding.
+fn otlp_body(service: &str, spans: &[Span]) -> Value {
+    let attribute = |key: &str, value: &Value| {
+        let value = match value {
+            Value::Bool(b) => json!({ "boolValue": b }),
+            Value::Number(n) => json!({ "intValue": n.to_string() }),
+            Value::String(s) => json!({ "stringValue": s }),
+            other => json!({ "stringValue": other.to_string() }),
+        };
+        json!({ "key": key, "value": value })
+    };
+    let spans: Vec<Value> = spans
+        .iter()
+        .map(|span| {
+            let mut encoded = json!({
+                "traceId": span.context.trace_id,
+                "spanId": span.context.span_id,
+                "name": span.name,
+                // SPAN_KIND_SERVER
+                "kind": 2,
+                "startTimeUnixNano": unix_nanos(span.start),
+                "endTimeUnixNano": unix_nanos(span.end),
+                "attributes": span.attributes.iter().map(|(k, v)| attribute(k, v)).collect::<Vec<_>>(),
+                // STATUS_CODE_ERROR for failed requests, otherwise unset
+                "status": { "code": if span.failed { 2 } else { 0 } }
+            });
+            if let Some(parent) = &span.context.parent_span_id {
+                encoded["parentSpanId"] = json!(parent);
+            }
+            if let Some(state) = &span.context.tracestate {
+                encoded["traceState"] = json!(state);
+            }
+            encoded
+        })
+        .collect();
+    json!({
+        "resourceSpans": [{
+            "resource": { "attributes": [attribute("service.name", &json!(service))] },
+            "scopeSpans": [{
+                "scope": { "name": "fuego-server", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans
+            }]
+        }]
+    })
+}
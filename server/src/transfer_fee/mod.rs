@@ -0,0 +1,285 @@
+//! Token-2022 transfer fees: read a mint's TransferFeeConfig extension and compute the fee withheld
+//! from a transfer, what the recipient receives, and the gross amount that delivers an exact net.
+//! Quotes and builders share these functions so they can never disagree.
+
+use serde::Serialize;
+use solana_client::rpc_config::CommitmentConfig;
+
+use crate::mints::{TOKEN_2022_PROGRAM, TOKEN_PROGRAM};
+use crate::rpc::RpcPool;
+use crate::utils::string_to_pub_key;
+
+const MAX_FEE_BASIS_POINTS: u16 = 10_000;
+
+/// Token-2022 pads the 82-byte base mint to the token-account length, then stores an account-type
+/// byte, then the extension TLV entries.
+const EXTENSIONS_OFFSET: usize = 165 + 1;
+const EXTENSION_TRANSFER_FEE_CONFIG: u16 = 1;
+const TRANSFER_FEE_CONFIG_LEN: usize = 108;
+
+const DECIMALS_OFFSET: usize = 44;
+const MINT_LEN: usize = 82;
+
+#[derive(Clone, Copy, Serialize)]
+pub struct TransferFee {
+    /// First epoch this schedule applies to
+    pub epoch: u64,
+    pub maximum_fee: u64,
+    pub basis_points: u16,
+}
+
+impl TransferFee {
+    /// Fee withheld from a transfer of `amount` base units: bps of the amount rounded up, capped at
+    /// the maximum fee.
+    pub fn fee(&self, amount: u64) -> u64 {
+        if self.basis_points == 0 || amount == 0 {
+            return 0;
+        }
+        let fee = (amount as u128 * self.basis_points as u128).div_ceil(MAX_FEE_BASIS_POINTS as u128);
+        fee.min(self.maximum_fee as u128) as u64
+    }
+
+    /// Smallest amount whose transfer delivers exactly `net` to the recipient; None if it overflows.
+    pub fn gross_for_net(&self, net: u64) -> Option<u64> {
+        if self.basis_points == 0 || net == 0 {
+            return Some(net);
+        }
+        if self.basis_points == MAX_FEE_BASIS_POINTS {
+            return net.checked_add(self.maximum_fee);
+        }
+        let raw = (net as u128 * MAX_FEE_BASIS_POINTS as u128)
+            .div_ceil((MAX_FEE_BASIS_POINTS - self.basis_points) as u128);
+        let gross = if raw - net as u128 >= self.maximum_fee as u128 {
+            net as u128 + self.maximum_fee as u128
+        } else {
+            raw
+        };
+        u64::try_from(gross).ok()
+    }
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct TransferFeeConfig {
+    pub older: TransferFee,
+    pub newer: TransferFee,
+}
+
+impl TransferFeeConfig {
+    /// The schedule in force during `epoch`.
+    pub fn for_epoch(&self, epoch: u64) -> TransferFee {
+        if epoch >= self.newer.epoch {
+            self.newer
+        } else {
+            self.older
+        }
+    }
+}
+
+fn read_fee(data: &[u8]) -> TransferFee {
+    TransferFee {
+        epoch: u64::from_le_bytes(data[0..8].try_into().unwrap()),
+        maximum_fee: u64::from_le_bytes(data[8..16].try_into().unwrap()),
+        basis_points: u16::from_le_bytes(data[16..18].try_into().unwrap()),
+    }
+}
+
+/// The TransferFeeConfig extension of a Token-2022 mint account, if it has one.
+pub fn parse_transfer_fee_config(data: &[u8]) -> Option<TransferFeeConfig> {
+    let mut offset = EXTENSIONS_OFFSET;
+    while offset + 4 <= data.len() {
+        let kind = u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let len = u16::from_le_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        let start = offset + 4;
+        if kind == 0 || start + len > data.len() {
+            return None;
+        }
+        if kind == EXTENSION_TRANSFER_FEE_CONFIG && len >= TRANSFER_FEE_CONFIG_LEN {
+            // Two authorities (32 bytes each) and the withheld amount precede the two fee schedules
+            let fees = &data[start + 72..start + TRANSFER_FEE_CONFIG_LEN];
+            return Some(TransferFeeConfig {
+                older: read_fee(&fees[0..18]),
+                newer: read_fee(&fees[18..36]),
+            });
+        }
+        offset = start + len;
+    }
+    None
+}
+
+/// A mint's decimals, owning program and transfer fee schedule, plus the current epoch.
+pub struct MintFees {
+    pub decimals: u8,
+    pub program_id: String,
+    pub config: Option<TransferFeeConfig>,
+    pub epoch: u64,
+}
+
+impl MintFees {
+    /// Fee schedule in force now; None for mints without transfer fees.
+    pub fn current(&self) -> Option<TransferFee> {
+        self.config.map(|c| c.for_epoch(self.epoch))
+    }
+
+    pub fn fee(&self, amount: u64) -> u64 {
+        self.current().map(|f| f.fee(amount)).unwrap_or(0)
+    }
+
+    pub fn gross_for_net(&self, net: u64) -> Option<u64> {
+        match self.current() {
+            Some(f) => f.gross_for_net(net),
+            None => Some(net),
+        }
+    }
+}
+
+/// How a builder moves a transfer of a fee-charging mint.
+#[derive(Clone, Copy, Serialize)]
+pub struct FeePlan {
+    /// Base units leaving the sender's account
+    pub send: u64,
+    /// Fee withheld, as passed to TransferCheckedWithFee
+    pub expected_fee: u64,
+    /// Base units the recipient receives
+    pub net_amount: u64,
+    pub gross_up: bool,
+    pub epoch: u64,
+    pub transfer_fee: TransferFee,
+    /// A schedule starting in a later epoch. A transfer that lands after the switch fails instead of
+    /// withholding a different fee, so it has to be rebuilt.
+    pub scheduled_transfer_fee: Option<TransferFee>,
+}
+
+impl MintFees {
+    /// Plan a transfer of `requested` base units; with `gross_up` the recipient nets `requested`.
+    /// Ok(None) for mints without a TransferFeeConfig.
+    pub fn plan(&self, requested: u64, gross_up: bool) -> Result<Option<FeePlan>, String> {
+        let (config, current) = match (self.config, self.current()) {
+            (Some(config), Some(current)) => (config, current),
+            _ => return Ok(None),
+        };
+        let send = if gross_up {
+            current
+                .gross_for_net(requested)
+                .ok_or_else(|| "No amount of this token can deliver that net amount".to_string())?
+        } else {
+            requested
+        };
+        let expected_fee = current.fee(send);
+        Ok(Some(FeePlan {
+            send,
+            expected_fee,
+            net_amount: send - expected_fee,
+            gross_up,
+            epoch: self.epoch,
+            transfer_fee: current,
+            scheduled_transfer_fee: Some(config.newer).filter(|f| f.epoch > self.epoch),
+        }))
+    }
+}
+
+/// Read the mint fresh (fee schedules can change, so this is never cached) along with the epoch.
+pub async fn fetch_mint_fees(rpc: &RpcPool, network: &str, rpc_url: &str, mint: &str) -> Result<MintFees, String> {
+    let pubkey = string_to_pub_key(mint).map_err(|_| "Invalid mint address".to_string())?;
+    let rpc = rpc.client(network, rpc_url, CommitmentConfig::confirmed());
+    let account = rpc
+        .get_account_with_commitment(&pubkey, CommitmentConfig::confirmed())
+        .await
+        .map_err(|e| format!("Failed to fetch mint: {}", e))?
+        .value
+        .ok_or_else(|| "Mint not found on this network".to_string())?;
+
+    let program_id = account.owner.to_string();
+    if (program_id != TOKEN_PROGRAM && program_id != TOKEN_2022_PROGRAM) || account.data.len() < MINT_LEN {
+        return Err("Account is not a token mint".to_string());
+    }
+    let config = if program_id == TOKEN_2022_PROGRAM {
+        parse_transfer_fee_config(&account.data)
+    } else {
+        None
+    };
+    let epoch = rpc
+        .get_epoch_info()
+        .await
+        .map_err(|e| format!("Failed to fetch epoch: {}", e))?
+        .epoch;
+
+    Ok(MintFees {
+        decimals: account.data[DECIMALS_OFFSET],
+        program_id,
+        config,
+        epoch,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fee(basis_points: u16, maximum_fee: u64) -> TransferFee {
+        TransferFee { epoch: 0, maximum_fee, basis_points }
+    }
+
+    #[test]
+    fn basis_points_round_the_fee_up() {
+        let one_percent = fee(100, u64::MAX);
+        assert_eq!(one_percent.fee(10_000), 100);
+        assert_eq!(one_percent.fee(10_001), 101);
+        assert_eq!(one_percent.fee(1), 1);
+        assert_eq!(one_percent.fee(0), 0);
+        assert_eq!(fee(1, u64::MAX).fee(9_999), 1);
+        assert_eq!(fee(0, u64::MAX).fee(1_000_000), 0);
+        // No overflow at the top of the range
+        assert_eq!(fee(MAX_FEE_BASIS_POINTS, u64::MAX).fee(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn the_maximum_fee_caps_it() {
+        let capped = fee(500, 3_000);
+        assert_eq!(capped.fee(50_000), 2_500);
+        assert_eq!(capped.fee(60_000), 3_000);
+        assert_eq!(capped.fee(1_000_000_000), 3_000);
+        assert_eq!(fee(500, 0).fee(1_000_000), 0);
+    }
+
+    #[test]
+    fn gross_up_is_the_smallest_amount_that_nets_exactly() {
+        for schedule in [fee(100, u64::MAX), fee(250, 40), fee(9_999, 1_000), fee(MAX_FEE_BASIS_POINTS, 7), fee(33, 5)] {
+            for net in [1, 2, 99, 100, 101, 10_000, 123_457, 1_000_000_007] {
+                let gross = schedule.gross_for_net(net).unwrap();
+                assert_eq!(gross - schedule.fee(gross), net, "{} bps, max {}: net {}", schedule.basis_points, schedule.maximum_fee, net);
+                assert!(gross - 1 - schedule.fee(gross - 1) < net, "{} bps: {} isn't the smallest gross for {}", schedule.basis_points, gross, net);
+            }
+        }
+        assert_eq!(fee(100, u64::MAX).gross_for_net(0), Some(0));
+        assert_eq!(fee(100, 10).gross_for_net(u64::MAX), None);
+    }
+
+    #[test]
+    fn the_schedule_switches_at_its_epoch() {
+        let config = TransferFeeConfig { older: TransferFee { epoch: 0, ..fee(50, 100) }, newer: TransferFee { epoch: 500, ..fee(75, 100) } };
+        assert_eq!(config.for_epoch(499).basis_points, 50);
+        assert_eq!(config.for_epoch(500).basis_points, 75);
+        let mint = MintFees { decimals: 6, program_id: TOKEN_2022_PROGRAM.to_string(), config: Some(config), epoch: 499 };
+        let plan = mint.plan(10_000, false).unwrap().unwrap();
+        assert_eq!((plan.send, plan.expected_fee, plan.net_amount), (10_000, 50, 9_950));
+        assert_eq!(plan.scheduled_transfer_fee.map(|f| f.basis_points), Some(75));
+    }
+
+    #[test]
+    fn reads_the_fee_schedules_out_of_the_extension() {
+        let mut data = vec![0u8; EXTENSIONS_OFFSET];
+        data.extend_from_slice(&EXTENSION_TRANSFER_FEE_CONFIG.to_le_bytes());
+        data.extend_from_slice(&(TRANSFER_FEE_CONFIG_LEN as u16).to_le_bytes());
+        data.extend_from_slice(&[0; 72]);
+        for (epoch, maximum_fee, basis_points) in [(3u64, 1_000u64, 25u16), (9, 2_000, 30)] {
+            data.extend_from_slice(&epoch.to_le_bytes());
+            data.extend_from_slice(&maximum_fee.to_le_bytes());
+            data.extend_from_slice(&basis_points.to_le_bytes());
+        }
+        let config = parse_transfer_fee_config(&data).unwrap();
+        assert_eq!((config.older.epoch, config.older.maximum_fee, config.older.basis_points), (3, 1_000, 25));
+        assert_eq!((config.newer.epoch, config.newer.maximum_fee, config.newer.basis_points), (9, 2_000, 30));
+        assert!(parse_transfer_fee_config(&data[..data.len() - 1]).is_none());
+        assert!(parse_transfer_fee_config(&[0; MINT_LEN]).is_none());
+    }
+}
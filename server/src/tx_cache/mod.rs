@@ -0,0 +1,154 @@
+//! Bounded LRU of getTransaction results keyed by (network, signature, encoding), shared by the
+//! history endpoints. Finalized transactions never change, so they live for the long TTL; entries
+//! fetched at a lower commitment are short-lived and never served to a finalized request.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a transaction fetched below finalized is trusted.
+const UNFINALIZED_TTL: Duration = Duration::from_secs(30);
+
+type Key = (String, String, &'static str);
+
+struct Entry {
+    value: Value,
+    /// Commitment the transaction was fetched at
+    commitment: &'static str,
+    fetched: Instant,
+    /// Recency tick; the smallest one is evicted first
+    used: u64,
+}
+
+#[derive(Default, Serialize, Clone)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Misses caused by an unfinalized entry when a finalized view was requested
+    pub commitment_refreshes: u64,
+    pub evictions: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<Key, Entry>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+#[derive(Clone)]
+pub struct TxCache {
+    inner: Arc<Mutex<Inner>>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl TxCache {
+    /// Capacity from FUEGO_TX_CACHE_CAPACITY (default 2000, 0 disables caching), finalized TTL from
+    /// FUEGO_TX_CACHE_TTL_SECS (default 86400).
+    pub fn from_env() -> Self {
+        let capacity = std::env::var("FUEGO_TX_CACHE_CAPACITY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(2000);
+        let ttl_secs = std::env::var("FUEGO_TX_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(86_400);
+        TxCache {
+            inner: Arc::new(Mutex::new(Inner::default())),
+            capacity,
+            ttl: Duration::from_secs(ttl_secs),
+        }
+    }
+
+    /// Cached transaction, if one fetched at a sufficient commitment is still fresh.
+    pub fn get(&self, network: &str, signature: &str, encoding: &'static str, commitment: &str) -> Option<Value> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+        let key = (network.to_string(), signature.to_string(), encoding);
+
+        let lookup = match inner.entries.get_mut(&key) {
+            None => None,
+            Some(entry) if commitment == "finalized" && entry.commitment != "finalized" => Some(Err(true)),
+            Some(entry) => {
+                let ttl = if entry.commitment == "finalized" { self.ttl } else { UNFINALIZED_TTL };
+                if entry.fetched.elapsed() < ttl {
+                    entry.used = tick;
+                    Some(Ok(entry.value.clone()))
+                } else {
+                    Some(Err(false))
+                }
+            }
+        };
+        match lookup {
+            Some(Ok(value)) => {
+                inner.stats.hits += 1;
+                Some(value)
+            }
+            Some(Err(stale_commitment)) => {
+                inner.entries.remove(&key);
+                inner.stats.misses += 1;
+                if stale_commitment {
+                    inner.stats.commitment_refreshes += 1;
+                }
+                None
+            }
+            None => {
+                inner.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Store a fetched transaction, evicting the least recently used entry when full.
+    pub fn insert(&self, network: &str, signature: &str, encoding: &'static str, commitment: &'static str, value: Value) {
+        if self.capacity == 0 || value.is_null() {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let used = inner.tick;
+        let key = (network.to_string(), signature.to_string(), encoding);
+        if !inner.entries.contains_key(&key) && inner.entries.len() >= self.capacity {
+            if let Some(oldest) = inner.entries.iter().min_by_key(|(_, e)| e.used).map(|(k, _)| k.clone()) {
+                inner.entries.remove(&oldest);
+                inner.stats.evictions += 1;
+            }
+        }
+        inner.entries.insert(
+            key,
+            Entry {
+                value,
+                commitment,
+                fetched: Instant::now(),
+                used,
+            },
+        );
+    }
+
+    /// Drop every entry; returns how many there were.
+    pub fn invalidate_all(&self) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        let count = inner.entries.len();
+        inner.entries.clear();
+        count
+    }
+
+    /// Size, configuration and hit-rate counters.
+    pub fn snapshot(&self) -> Value {
+        let inner = self.inner.lock().unwrap();
+        let lookups = inner.stats.hits + inner.stats.misses;
+        serde_json::json!({
+            "entries": inner.entries.len(),
+            "capacity": self.capacity,
+            "finalized_ttl_secs": self.ttl.as_secs(),
+            "unfinalized_ttl_secs": UNFINALIZED_TTL.as_secs(),
+            "stats": inner.stats,
+            "hit_rate": if lookups == 0 { 0.0 } else { inner.stats.hits as f64 / lookups as f64 }
+        })
+    }
+}
@@ -0,0 +1,65 @@
+//! Minimal dashboard at GET /ui, for quick local use without running the separate frontend. The
+//! page and its assets (server/ui/) are embedded in the binary and only call the ordinary JSON
+//! endpoints. FUEGO_UI=false leaves the routes out entirely for headless deployments.
+
+use axum::extract::Path;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use rust_embed::RustEmbed;
+
+#[derive(RustEmbed)]
+#[folder = "ui/"]
+struct Assets;
+
+/// Same-origin everything: the page only ever talks to this server.
+const CONTENT_SECURITY_POLICY: &str =
+    "default-src 'self'; img-src 'self' data:; object-src 'none'; base-uri 'none'; frame-ancestors 'none'";
+
+pub fn enabled() -> bool {
+    std::env::var("FUEGO_UI").map(|v| v != "false").unwrap_or(true)
+}
+
+pub async fn index(headers: HeaderMap) -> Response {
+    serve("index.html", &headers)
+}
+
+pub async fn asset(Path(path): Path<String>, headers: HeaderMap) -> Response {
+    if path.is_empty() {
+        return serve("index.html", &headers);
+    }
+    serve(&path, &headers)
+}
+
+fn content_type(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+fn serve(path: &str, headers: &HeaderMap) -> Response {
+    let Some(file) = Assets::get(path) else {
+        return (StatusCode::NOT_FOUND, "Not found").into_response();
+    };
+    let hash: String = file.metadata.sha256_hash().iter().take(16).map(|b| format!("{:02x}", b)).collect();
+    let etag = format!("\"{}\"", hash);
+    let mut response = if headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()) == Some(etag.as_str()) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        ([(header::CONTENT_TYPE, content_type(path))], file.data.into_owned()).into_response()
+    };
+    let response_headers = response.headers_mut();
+    // Asset names aren't fingerprinted, so browsers revalidate on every load; the ETag keeps that cheap
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        response_headers.insert(header::ETAG, value);
+    }
+    response_headers.insert(header::CONTENT_SECURITY_POLICY, HeaderValue::from_static(CONTENT_SECURITY_POLICY));
+    response_headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    response
+}
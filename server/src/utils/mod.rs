@@ -1,5 +1,6 @@
 use solana_sdk::{signature::{Signature, ParseSignatureError}, pubkey::{Pubkey, ParsePubkeyError}};
 use std::str::FromStr;
+use subtle::ConstantTimeEq;
 
 /// Pubkey type used by spl_associated_token_account and solana_client RpcClient.
 pub type SplPubkey = spl_associated_token_account::solana_program::pubkey::Pubkey;
@@ -37,7 +38,315 @@ pub fn instruction_from_spl(spl_instr: &SplInstruction) -> solana_sdk::instructi
     }
 }
 
-#[allow(dead_code)]
+/// Compare secrets (MACs, API keys, derived keys) in time that doesn't depend on where they first
+/// differ. Only the length may leak.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
 pub fn string_to_signature(transaction: &str) -> Result<Signature, ParseSignatureError> {
     Signature::from_str(transaction)
-}
\ No newline at end of file
+}
+/// What to do with fractional digits beyond what the token's decimals can represent.
+#[derive(Clone, Copy, PartialEq)]
+pub enum RoundingPolicy {
+    /// Refuse the amount (default)
+    Reject,
+    /// Drop the extra digits; never increases the amount
+    Truncate,
+}
+
+impl RoundingPolicy {
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value.map(str::trim) {
+            None | Some("") | Some("reject") => Ok(RoundingPolicy::Reject),
+            Some("truncate") => Ok(RoundingPolicy::Truncate),
+            // Rounding to nearest can send more than was asked for, so it isn't offered
+            Some("half_even") => Err("Rounding policy 'half_even' is not supported: it can round an amount up; use truncate".to_string()),
+            Some(other) => Err(format!("Unknown rounding policy '{}' (use reject or truncate)", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoundingPolicy::Reject => "reject",
+            RoundingPolicy::Truncate => "truncate",
+        }
+    }
+}
+
+/// A UI amount converted to base units, with whether the rounding policy changed it.
+pub struct BaseUnits {
+    pub amount: u64,
+    pub rounded: bool,
+}
+
+/// Most decimals a UI amount can be parsed at: 10^38 is the largest power of ten in a u128.
+pub const MAX_DECIMALS: u8 = 38;
+
+/// Why a UI amount string could not be converted to base units.
+#[derive(Debug)]
+pub enum AmountError {
+    NotNumeric(String),
+    Negative(String),
+    TooPrecise { amount: String, decimals: u8 },
+    ExceedsMax { amount: String, decimals: u8 },
+    /// More decimals than a u128 scale can hold; no real mint has them
+    InvalidDecimals(u8),
+}
+
+impl AmountError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AmountError::NotNumeric(_) => "amount_not_numeric",
+            AmountError::Negative(_) => "amount_negative",
+            AmountError::TooPrecise { .. } => "amount_too_precise",
+            AmountError::ExceedsMax { .. } => "amount_exceeds_max",
+            AmountError::InvalidDecimals(_) => "invalid_mint_decimals",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            AmountError::NotNumeric(amount) if amount.is_empty() => "Amount is empty".to_string(),
+            AmountError::NotNumeric(amount) => format!("Invalid amount: {}", amount),
+            AmountError::Negative(amount) => format!("Amount {} is negative", amount),
+            AmountError::TooPrecise { amount, decimals } => {
+                format!("Amount {} has more than {} decimal places", amount, decimals)
+            }
+            AmountError::ExceedsMax { amount, decimals } => format!(
+                "Amount {} is too large; the most this token can represent is {}",
+                amount,
+                base_units_to_ui(u64::MAX, *decimals)
+            ),
+            AmountError::InvalidDecimals(decimals) => format!(
+                "The token has {} decimals; amounts are only supported up to {}",
+                decimals, MAX_DECIMALS
+            ),
+        }
+    }
+}
+
+/// Parse a UI amount string ("1.5") into base units using integer math (no f64 rounding).
+/// Rejects amounts with more fractional digits than `decimals` supports.
+pub fn ui_amount_to_base_units(amount: &str, decimals: u8) -> Result<u64, String> {
+    ui_amount_to_base_units_with(amount, decimals, RoundingPolicy::Reject)
+        .map(|units| units.amount)
+        .map_err(|e| e.message())
+}
+
+/// Like `ui_amount_to_base_units`, resolving excess fractional digits with `policy`.
+pub fn ui_amount_to_base_units_with(amount: &str, decimals: u8, policy: RoundingPolicy) -> Result<BaseUnits, AmountError> {
+    let amount = amount.trim();
+    let is_decimal = |s: &str| {
+        let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+        !(whole.is_empty() && frac.is_empty())
+            && whole.chars().all(|c| c.is_ascii_digit())
+            && frac.chars().all(|c| c.is_ascii_digit())
+    };
+    if !is_decimal(amount) {
+        return Err(match amount.strip_prefix('-') {
+            Some(rest) if is_decimal(rest) => AmountError::Negative(amount.to_string()),
+            _ => AmountError::NotNumeric(amount.to_string()),
+        });
+    }
+    let (whole, frac) = amount.split_once('.').unwrap_or((amount, ""));
+    let too_large = || AmountError::ExceedsMax { amount: amount.to_string(), decimals };
+
+    let (kept, excess) = frac.split_at(frac.len().min(decimals as usize));
+    let has_excess = excess.bytes().any(|b| b != b'0');
+    if has_excess && policy == RoundingPolicy::Reject {
+        return Err(AmountError::TooPrecise { amount: amount.to_string(), decimals });
+    }
+
+    let scale = 10u128.checked_pow(decimals as u32).ok_or(AmountError::InvalidDecimals(decimals))?;
+    let whole_units: u128 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse::<u128>().map_err(|_| too_large())?
+    };
+    let frac_units: u128 = if kept.is_empty() {
+        0
+    } else {
+        format!("{:0<width$}", kept, width = decimals as usize)
+            .parse::<u128>()
+            .map_err(|_| AmountError::NotNumeric(amount.to_string()))?
+    };
+    // Excess digits are dropped, so the result never exceeds the amount given
+    let truncated = whole_units
+        .checked_mul(scale)
+        .and_then(|v| v.checked_add(frac_units))
+        .ok_or_else(too_large)?;
+
+    let amount = u64::try_from(truncated).map_err(|_| too_large())?;
+    Ok(BaseUnits {
+        amount,
+        rounded: has_excess,
+    })
+}
+
+/// Parse an integer base-unit amount ("1500000"), as sent in `amount_raw`.
+pub fn parse_base_units(raw: &str) -> Result<u64, AmountError> {
+    let raw = raw.trim();
+    let is_integer = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if !is_integer(raw) {
+        return Err(match raw.strip_prefix('-') {
+            Some(rest) if is_integer(rest) => AmountError::Negative(raw.to_string()),
+            _ => AmountError::NotNumeric(raw.to_string()),
+        });
+    }
+    // Zero decimals: the message then quotes the limit in base units too
+    raw.parse::<u64>().map_err(|_| AmountError::ExceedsMax {
+        amount: raw.to_string(),
+        decimals: 0,
+    })
+}
+
+/// Format base units as a UI amount string without trailing zeros ("1500000", 6 -> "1.5").
+pub fn base_units_to_ui(amount: u64, decimals: u8) -> String {
+    if decimals == 0 {
+        return amount.to_string();
+    }
+    // Split the digits rather than divide, so any u8 of decimals works
+    let digits = format!("{:0>width$}", amount, width = decimals as usize + 1);
+    let (whole, frac) = digits.split_at(digits.len() - decimals as usize);
+    let formatted = format!("{}.{}", whole, frac);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// `base_units_to_ui` for the response fields that have always been JSON numbers. Built from the
+/// exact decimal string, and only ever serialized.
+pub fn base_units_to_ui_number(amount: u64, decimals: u8) -> serde_json::Number {
+    base_units_to_ui(amount, decimals).parse().unwrap_or_else(|_| 0.into())
+}
+
+/// Fuego home directory (~/.fuego) holding the wallet, config and local records.
+pub fn fuego_home() -> std::path::PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("/"))
+        .join(".fuego")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering;
+
+    const POLICIES: [RoundingPolicy; 2] = [RoundingPolicy::Reject, RoundingPolicy::Truncate];
+
+    /// Exact comparison of two plain decimal strings.
+    fn cmp_decimal(a: &str, b: &str) -> Ordering {
+        let split = |s: &str| {
+            let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+            (whole.trim_start_matches('0').to_string(), frac.trim_end_matches('0').to_string())
+        };
+        let ((aw, af), (bw, bf)) = (split(a), split(b));
+        aw.len().cmp(&bw.len()).then_with(|| aw.cmp(&bw)).then_with(|| af.cmp(&bf))
+    }
+
+    /// xorshift64, so the property runs are reproducible without a dependency
+    fn next(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    fn random_amount(state: &mut u64) -> String {
+        let digits = |state: &mut u64, n: u64| (0..n).map(|_| char::from(b'0' + (next(state) % 10) as u8)).collect::<String>();
+        let whole_len = next(state) % 13;
+        let frac_len = next(state) % 16;
+        let whole = digits(state, whole_len);
+        let frac = digits(state, frac_len);
+        match (whole.is_empty(), frac.is_empty()) {
+            (true, true) => "0".to_string(),
+            (_, true) => whole,
+            _ => format!("{}.{}", whole, frac),
+        }
+    }
+
+    #[test]
+    fn only_the_listed_policies_parse() {
+        for policy in POLICIES {
+            assert!(RoundingPolicy::parse(Some(policy.as_str())) == Ok(policy));
+        }
+        assert!(RoundingPolicy::parse(None) == Ok(RoundingPolicy::Reject));
+        assert!(RoundingPolicy::parse(Some("half_even")).is_err());
+        assert!(RoundingPolicy::parse(Some("up")).is_err());
+    }
+
+    #[test]
+    fn no_policy_ever_increases_the_amount() {
+        let mut state = 0x9e37_79b9_7f4a_7c15;
+        for _ in 0..20_000 {
+            let amount = random_amount(&mut state);
+            let decimals = (next(&mut state) % 13) as u8;
+            for policy in POLICIES {
+                let Ok(parsed) = ui_amount_to_base_units_with(&amount, decimals, policy) else { continue };
+                let sent = base_units_to_ui(parsed.amount, decimals);
+                let order = cmp_decimal(&sent, &amount);
+                assert!(order != Ordering::Greater, "{} at {} decimals became {} under {}", amount, decimals, sent, policy.as_str());
+                assert_eq!(order == Ordering::Less, parsed.rounded, "{} at {} decimals under {}", amount, decimals, policy.as_str());
+            }
+        }
+    }
+
+    #[test]
+    fn reject_refuses_exactly_what_truncate_rounds() {
+        let mut state = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..20_000 {
+            let amount = random_amount(&mut state);
+            let decimals = (next(&mut state) % 13) as u8;
+            let truncated = ui_amount_to_base_units_with(&amount, decimals, RoundingPolicy::Truncate);
+            let rejected = ui_amount_to_base_units_with(&amount, decimals, RoundingPolicy::Reject);
+            match (truncated, rejected) {
+                (Ok(t), Ok(r)) => assert!(!t.rounded && t.amount == r.amount, "{}", amount),
+                (Ok(t), Err(e)) => assert!(t.rounded && e.code() == "amount_too_precise", "{}", amount),
+                (Err(e), _) => assert_eq!(e.code(), "amount_exceeds_max", "{}", amount),
+            }
+        }
+    }
+
+    #[test]
+    fn formats_any_decimals_without_overflow() {
+        assert_eq!(base_units_to_ui(1_500_000, 6), "1.5");
+        assert_eq!(base_units_to_ui(7, 0), "7");
+        assert_eq!(base_units_to_ui(0, 9), "0");
+        assert_eq!(base_units_to_ui(1, 39), format!("0.{}1", "0".repeat(38)));
+        assert_eq!(base_units_to_ui(u64::MAX, 255).len(), 2 + 255);
+    }
+
+    #[test]
+    fn parses_up_to_38_decimals_and_refuses_more() {
+        let one = format!("0.{}1", "0".repeat(37));
+        assert_eq!(ui_amount_to_base_units_with(&one, 38, RoundingPolicy::Reject).map(|u| u.amount).ok(), Some(1));
+        for decimals in [39, 100, 255] {
+            let err = ui_amount_to_base_units_with("1", decimals, RoundingPolicy::Reject).err().unwrap();
+            assert_eq!(err.code(), "invalid_mint_decimals");
+        }
+    }
+
+    fn units(amount: &str, decimals: u8) -> Option<u64> {
+        ui_amount_to_base_units_with(amount, decimals, RoundingPolicy::Reject).ok().map(|u| u.amount)
+    }
+
+    #[test]
+    fn amounts_f64_cannot_represent_convert_exactly() {
+        // 0.07 * 1e6 is 70000.00000000001 in f64, and 0.1 is just as inexact
+        assert_eq!(units("0.07", 6), Some(70_000));
+        assert_eq!(units("0.1", 6), Some(100_000));
+        assert_eq!(units("123456.789123", 6), Some(123_456_789_123));
+        assert_eq!(units(".5", 6), Some(500_000));
+        assert_eq!(units("0.1000000", 6), Some(100_000));
+    }
+
+    #[test]
+    fn the_largest_u64_converts_and_one_more_unit_overflows() {
+        assert_eq!(units("18446744073709.551615", 6), Some(u64::MAX));
+        assert_eq!(units(&u64::MAX.to_string(), 0), Some(u64::MAX));
+        for (amount, decimals) in [("18446744073709.551616", 6), ("18446744073709551616", 0), ("340282366920938463463374607431768211456", 6)] {
+            let err = ui_amount_to_base_units_with(amount, decimals, RoundingPolicy::Truncate).err().unwrap();
+            assert_eq!(err.code(), "amount_exceeds_max", "{} at {} decimals", amount, decimals);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Watch-only wallet profiles (address + label, no key material) stored in ~/.fuego/watch-wallets.json,
+//! plus export and consistency checks for the local signing wallet.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::utils::fuego_home;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WatchWallet {
+    pub address: String,
+    pub label: String,
+    pub added_at: i64,
+}
+
+fn watch_wallets_path() -> PathBuf {
+    fuego_home().join("watch-wallets.json")
+}
+
+pub fn load_watch_wallets() -> Result<Vec<WatchWallet>, String> {
+    let path = watch_wallets_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid watch-wallets.json: {}", e))
+}
+
+pub fn save_watch_wallets(wallets: &[WatchWallet]) -> Result<(), String> {
+    fs::create_dir_all(fuego_home()).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(wallets).map_err(|e| e.to_string())?;
+    fs::write(watch_wallets_path(), content).map_err(|e| e.to_string())
+}
+
+/// True when the address is registered as watch-only, i.e. fuego holds no key that can sign for it.
+pub fn is_watch_only(address: &str) -> bool {
+    load_watch_wallets()
+        .map(|wallets| wallets.iter().any(|w| w.address == address))
+        .unwrap_or(false)
+}
+
+/// Key export formats for moving the local wallet to other tools.
+pub enum ExportFormat {
+    /// 64-byte JSON array, as written by `solana-keygen`
+    SolanaCli,
+    /// base58 of the 64-byte keypair, as imported by Phantom/Solflare
+    Base58,
+}
+
+impl ExportFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "solana_cli" => Ok(ExportFormat::SolanaCli),
+            "base58" => Ok(ExportFormat::Base58),
+            "mnemonic" => Err("This wallet was not derived from a mnemonic; export as solana_cli or base58".to_string()),
+            other => Err(format!("Unknown export format '{}' (use solana_cli or base58)", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::SolanaCli => "solana_cli",
+            ExportFormat::Base58 => "base58",
+        }
+    }
+}
+
+/// Encode the wallet key as a 64-byte keypair (secret followed by public key) in the given format.
+pub fn export_keypair(private_key: &[u8], address: &str, format: &ExportFormat) -> Result<serde_json::Value, String> {
+    let keypair: Vec<u8> = match private_key.len() {
+        64 => private_key.to_vec(),
+        32 => {
+            let public = bs58::decode(address).into_vec().map_err(|_| "Wallet address is not valid base58".to_string())?;
+            if public.len() != 32 {
+                return Err("Wallet address is not a 32-byte public key".to_string());
+            }
+            [private_key, public.as_slice()].concat()
+        }
+        _ => return Err("Wallet private key must be 32 or 64 bytes".to_string()),
+    };
+    Ok(match format {
+        ExportFormat::SolanaCli => serde_json::json!(keypair),
+        ExportFormat::Base58 => serde_json::json!(bs58::encode(&keypair).into_string()),
+    })
+}
+
+/// Append an export attempt to ~/.fuego/key-export-audit.jsonl. Never includes key material.
+pub fn audit_export(format: &str, outcome: &str) {
+    use std::io::Write;
+
+    let line = serde_json::json!({
+        "timestamp": chrono::Utc::now().timestamp(),
+        "format": format,
+        "outcome": outcome
+    })
+    .to_string();
+    eprintln!("AUDIT key-export: {}", line);
+    let result = fs::create_dir_all(fuego_home()).and_then(|_| {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(fuego_home().join("key-export-audit.jsonl"))
+            .and_then(|mut f| writeln!(f, "{}", line))
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to write key export audit log: {}", e);
+    }
+}
+
+/// One way the local wallet files disagree with the key they hold.
+#[derive(Serialize)]
+pub struct WalletMismatch {
+    /// File that is wrong (or most likely wrong)
+    pub file: &'static str,
+    pub field: &'static str,
+    pub found: String,
+    /// Address the stored private key actually signs as
+    pub expected: String,
+    /// How to fix it
+    pub fix: String,
+}
+
+#[derive(Serialize)]
+pub struct WalletVerification {
+    pub ok: bool,
+    /// Public key derived from the stored secret
+    pub derived_address: String,
+    pub wallet_address: String,
+    pub config_address: Option<String>,
+    pub mismatches: Vec<WalletMismatch>,
+}
+
+impl WalletVerification {
+    /// One-line explanation of every mismatch, for error responses and startup logs.
+    pub fn explain(&self) -> String {
+        self.mismatches.iter().map(|m| m.fix.clone()).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Derive the public key from wallet.json's secret and compare it with wallet.json's `address`
+/// and wallet-config.json's `walletAddress`.
+pub fn verify_keys(private_key: &[u8], address: &str, config_address: Option<&str>) -> Result<WalletVerification, String> {
+    use solana_sdk::signer::Signer;
+
+    if private_key.len() != 32 && private_key.len() != 64 {
+        return Err("wallet.json privateKey must be 32 or 64 bytes; restore wallet.json from your backup".to_string());
+    }
+    let mut secret = [0u8; 32];
+    secret.copy_from_slice(&private_key[..32]);
+    let derived = solana_sdk::signer::keypair::Keypair::new_from_array(secret).pubkey().to_string();
+
+    let mut mismatches = Vec::new();
+    if private_key.len() == 64 {
+        let embedded = bs58::encode(&private_key[32..]).into_string();
+        if embedded != derived {
+            mismatches.push(WalletMismatch {
+                file: "wallet.json",
+                field: "privateKey",
+                found: embedded,
+                expected: derived.clone(),
+                fix: "wallet.json privateKey is corrupt: its public half does not match its secret half. Restore wallet.json from your backup.".to_string(),
+            });
+        }
+    }
+    if address != derived {
+        mismatches.push(WalletMismatch {
+            file: "wallet.json",
+            field: "address",
+            found: address.to_string(),
+            expected: derived.clone(),
+            fix: format!(
+                "wallet.json address is {} but its privateKey signs as {}. If {} is your wallet, set address to it; otherwise restore the wallet.json that holds the key for {}.",
+                address, derived, derived, address
+            ),
+        });
+    }
+    if let Some(config_address) = config_address {
+        if config_address != derived {
+            mismatches.push(WalletMismatch {
+                file: "wallet-config.json",
+                field: "walletAddress",
+                found: config_address.to_string(),
+                expected: derived.clone(),
+                fix: format!(
+                    "wallet-config.json walletAddress is {} but wallet.json signs as {}. Set walletAddress to {}, or restore the wallet.json for {}.",
+                    config_address, derived, derived, config_address
+                ),
+            });
+        }
+    }
+
+    Ok(WalletVerification {
+        ok: mismatches.is_empty(),
+        derived_address: derived,
+        wallet_address: address.to_string(),
+        config_address: config_address.map(str::to_string),
+        mismatches,
+    })
+}
@@ -0,0 +1,98 @@
+//! Soft validation warnings for build, submit and x402 responses: conditions worth showing the
+//! user that never block a request on their own (unless the caller sets treat_warnings_as_errors).
+//! Codes are stable so dashboards can map them to banners.
+
+use serde::Serialize;
+
+use crate::anomaly::Flag;
+
+pub const ROUNDING_APPLIED: &str = "rounding_applied";
+pub const FEE_WALLET_LOW: &str = "fee_wallet_low";
+pub const EXCHANGE_UNCREDITED_TOKEN: &str = "exchange_uncredited_token";
+pub const TRANSFER_FEE_SCHEDULE_CHANGE: &str = "transfer_fee_schedule_change";
+// Activity heuristics keep their own names as codes: new_recipient, burst, new_x402_host, priority_fee
+
+#[derive(Clone, Serialize)]
+pub struct Warning {
+    pub code: String,
+    pub message: String,
+    pub details: serde_json::Value,
+}
+
+/// Collector threaded through a request; serializes as the plain `warnings` array.
+#[derive(Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct Warnings(Vec<Warning>);
+
+impl Warnings {
+    pub fn push(&mut self, code: &str, message: impl Into<String>, details: serde_json::Value) {
+        self.0.push(Warning {
+            code: code.to_string(),
+            message: message.into(),
+            details,
+        });
+    }
+
+    pub fn extend_flags(&mut self, flags: Vec<Flag>) {
+        for flag in flags {
+            self.push(flag.heuristic, flag.message, flag.details);
+        }
+    }
+
+    /// From the `rounding` disclosure of a builder response.
+    pub fn rounding(&mut self, rounding: &serde_json::Value) {
+        if rounding["rounding_applied"].as_bool() == Some(true) {
+            self.push(
+                ROUNDING_APPLIED,
+                format!(
+                    "Amount {} was rounded {} to {}",
+                    rounding["requested"].as_str().unwrap_or_default(),
+                    rounding["direction"].as_str().unwrap_or_default(),
+                    rounding["amount_ui"].as_str().unwrap_or_default()
+                ),
+                rounding.clone(),
+            );
+        }
+    }
+
+    /// From the `exchange` block of a builder response.
+    pub fn exchange(&mut self, exchange: &serde_json::Value) {
+        for message in exchange["warnings"].as_array().into_iter().flatten().filter_map(|w| w.as_str()) {
+            self.push(
+                EXCHANGE_UNCREDITED_TOKEN,
+                message,
+                serde_json::json!({ "exchange": exchange["matched"] }),
+            );
+        }
+    }
+
+    /// A Token-2022 fee schedule that changes before the transaction might land.
+    pub fn transfer_fee(&mut self, plan: &Option<crate::transfer_fee::FeePlan>) {
+        if let Some(scheduled) = plan.as_ref().and_then(|p| p.scheduled_transfer_fee) {
+            self.push(
+                TRANSFER_FEE_SCHEDULE_CHANGE,
+                format!(
+                    "The mint's transfer fee changes to {} bps at epoch {}; a transaction landing after that fails and must be rebuilt",
+                    scheduled.basis_points, scheduled.epoch
+                ),
+                serde_json::json!({ "scheduled_transfer_fee": scheduled }),
+            );
+        }
+    }
+
+    pub fn fee_wallet_low(&mut self, address: &str) {
+        self.push(
+            FEE_WALLET_LOW,
+            format!("Fee wallet {} is below its balance floor", address),
+            serde_json::json!({ "address": address }),
+        );
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn codes(&self) -> Vec<&str> {
+        self.0.iter().map(|w| w.code.as_str()).collect()
+    }
+}
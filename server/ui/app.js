@@ -0,0 +1,256 @@
+// Embedded fuego dashboard. Talks to the ordinary JSON endpoints; with auth enabled, the bearer
+// key lives in sessionStorage for this tab only.
+'use strict';
+
+const KEY_STORAGE = 'fuego_api_key';
+const SESSION_STORAGE = 'fuego_session';
+const AUTH_CODES = ['auth_required', 'unknown_key', 'wrong_auth_scheme'];
+
+let config = null;
+let network = null;
+let walletAddress = null;
+
+const $ = (id) => document.getElementById(id);
+
+function show(id, visible) {
+    $(id).hidden = !visible;
+}
+
+function showError(message) {
+    $('error').textContent = message;
+    show('error', Boolean(message));
+}
+
+function cell(row, text) {
+    const td = document.createElement('td');
+    td.textContent = text === null || text === undefined ? '—' : String(text);
+    row.appendChild(td);
+    return td;
+}
+
+function short(value) {
+    return value && value.length > 16 ? value.slice(0, 8) + '…' + value.slice(-6) : value;
+}
+
+async function api(method, path, body) {
+    const headers = {};
+    const key = sessionStorage.getItem(KEY_STORAGE);
+    if (key) {
+        headers['Authorization'] = 'Bearer ' + key;
+    }
+    const session = sessionStorage.getItem(SESSION_STORAGE);
+    if (session) {
+        headers['X-Fuego-Session'] = session;
+    }
+    if (body !== undefined) {
+        headers['Content-Type'] = 'application/json';
+    }
+    const response = await fetch(path, {
+        method,
+        headers,
+        body: body === undefined ? undefined : JSON.stringify(body),
+    });
+    const json = await response.json().catch(() => ({ success: false, error: 'HTTP ' + response.status }));
+    if (json.success === false && AUTH_CODES.includes(json.code)) {
+        sessionStorage.removeItem(KEY_STORAGE);
+        askForKey();
+        throw new Error(json.error);
+    }
+    return json;
+}
+
+function askForKey() {
+    show('key-panel', true);
+    show('main', false);
+    show('forget-key', false);
+}
+
+async function loadDashboard() {
+    showError('');
+    const dashboard = await api('GET', '/dashboard');
+    if (!dashboard.success) {
+        throw new Error(dashboard.error);
+    }
+    const data = dashboard.data;
+    const sections = data.sections;
+    network = data.network;
+    $('network').textContent = network;
+
+    const wallet = sections.wallet;
+    walletAddress = wallet.ok ? wallet.data.address : null;
+    $('wallet-address').textContent = wallet.ok ? wallet.data.address : wallet.error;
+    show('wallet-low', Boolean(wallet.ok && wallet.data.low_balance));
+
+    const balances = sections.balances;
+    const tokens = $('tokens');
+    tokens.replaceChildren();
+    if (balances.ok) {
+        $('sol-balance').textContent = balances.data.sol_balance;
+        for (const token of balances.data.tokens) {
+            const row = tokens.insertRow();
+            cell(row, token.symbol || short(token.mint));
+            cell(row, token.ui_amount);
+            cell(row, short(token.mint)).title = token.mint;
+        }
+    } else {
+        $('sol-balance').textContent = balances.error;
+    }
+
+    const transactions = $('transactions');
+    transactions.replaceChildren();
+    const history = sections.transactions;
+    if (history.ok) {
+        for (const tx of history.data.transactions) {
+            const row = transactions.insertRow();
+            cell(row, short(tx.signature)).title = tx.signature;
+            cell(row, tx.token);
+            cell(row, tx.amount);
+            cell(row, short(tx.to));
+            cell(row, tx.yid);
+        }
+    } else {
+        cell(transactions.insertRow(), history.error);
+    }
+
+    if (sections.pending.ok) {
+        const gauges = sections.pending.data.gauges;
+        $('pending-summary').textContent =
+            gauges.pending_total + ' pending, ' + gauges.awaiting_finalization + ' awaiting finalization, ' +
+            sections.pending.data.submit_queue_depth + ' queued';
+    }
+    await loadPending();
+}
+
+async function loadPending() {
+    const pending = await api('GET', '/pending-transactions?limit=20');
+    const rows = $('pending');
+    rows.replaceChildren();
+    if (!pending.success) {
+        cell(rows.insertRow(), pending.error);
+        return;
+    }
+    for (const status of pending.data.transactions) {
+        const row = rows.insertRow();
+        cell(row, short(status.signature)).title = status.signature;
+        cell(row, status.state);
+        cell(row, new Date(status.updated_at * 1000).toLocaleString());
+        cell(row, status.detail);
+    }
+}
+
+async function refresh() {
+    try {
+        await loadDashboard();
+        show('main', true);
+        show('key-panel', false);
+        show('forget-key', Boolean(sessionStorage.getItem(KEY_STORAGE)));
+    } catch (e) {
+        showError(e.message);
+    }
+}
+
+async function build(event) {
+    event.preventDefault();
+    showError('');
+    show('build-result', false);
+    if (!walletAddress) {
+        showError('No local wallet to send from');
+        return;
+    }
+    const token = $('token').value;
+    const session = $('session').value.trim();
+    if (session) {
+        sessionStorage.setItem(SESSION_STORAGE, session);
+    }
+    const body = {
+        network,
+        from_address: walletAddress,
+        to_address: $('to').value.trim(),
+        amount: $('amount').value.trim(),
+        yid: 'ui-' + Date.now(),
+    };
+    const notes = $('notes').value.trim();
+    if (notes) {
+        body.notes = notes;
+    }
+    try {
+        const result = await api('POST', '/build-transfer-' + token.toLowerCase(), body);
+        if (!result.success) {
+            showError(result.error + (result.code ? ' (' + result.code + ')' : ''));
+            return;
+        }
+        const data = result.data;
+        let summary = 'Sending ' + data.amount_ui + ' ' + token + ' to ' + data.to;
+        if (data.network_cost) {
+            summary += '. Network cost: ' + data.network_cost.total_sol + ' SOL';
+        }
+        $('build-summary').textContent = summary;
+        const warnings = $('build-warnings');
+        warnings.replaceChildren();
+        for (const warning of data.warnings || []) {
+            const item = document.createElement('li');
+            item.textContent = warning.message;
+            warnings.appendChild(item);
+        }
+        $('unsigned').value = data.transaction;
+        $('signed').value = '';
+        $('submit-result').textContent = '';
+        show('build-result', true);
+    } catch (e) {
+        showError(e.message);
+    }
+}
+
+async function submit(event) {
+    event.preventDefault();
+    showError('');
+    try {
+        const result = await api('POST', '/submit-transaction', {
+            network,
+            transaction: $('signed').value.trim(),
+        });
+        if (!result.success) {
+            showError(result.error + (result.code ? ' (' + result.code + ')' : ''));
+            return;
+        }
+        $('submit-result').textContent = 'Submitted ' + result.data.signature + ' (' + result.data.status + ')';
+        await loadPending();
+    } catch (e) {
+        showError(e.message);
+    }
+}
+
+async function start() {
+    $('refresh').addEventListener('click', refresh);
+    $('build-form').addEventListener('submit', build);
+    $('submit-form').addEventListener('submit', submit);
+    $('copy-unsigned').addEventListener('click', () => navigator.clipboard.writeText($('unsigned').value));
+    $('forget-key').addEventListener('click', () => {
+        sessionStorage.removeItem(KEY_STORAGE);
+        askForKey();
+    });
+    $('key-form').addEventListener('submit', (event) => {
+        event.preventDefault();
+        sessionStorage.setItem(KEY_STORAGE, $('key-input').value.trim());
+        $('key-input').value = '';
+        refresh();
+    });
+
+    try {
+        const response = await api('GET', '/ui-config');
+        config = response.data;
+    } catch (e) {
+        showError('Could not load /ui-config: ' + e.message);
+        return;
+    }
+    show('dry-run', config.dry_run);
+    show('session-label', config.require_sessions);
+    $('session').value = sessionStorage.getItem(SESSION_STORAGE) || '';
+    if (config.auth_required && !sessionStorage.getItem(KEY_STORAGE)) {
+        askForKey();
+        return;
+    }
+    refresh();
+}
+
+start();
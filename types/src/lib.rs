@@ -0,0 +1,320 @@
+//! Request bodies of the fuego server's HTTP API. The server parses these and fuego-client sends
+//! them, so a field renamed on one side is renamed on both.
+
+use serde::{Deserialize, Serialize};
+
+/// Where a request's RPC calls go, when not to the network's configured endpoints. Flattened
+/// into every request that may pick its endpoint.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RpcTarget {
+    /// RPC endpoint for this request only (http or https); `network` still names the cluster
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+    /// Named provider profile from ~/.fuego/server.json, in place of rpc_url (and of network, when the profile names one)
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+impl RpcTarget {
+    pub fn is_set(&self) -> bool {
+        self.rpc_url.is_some() || self.provider.is_some()
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct GetBalanceRequest {
+    #[serde(default)]
+    pub network: String,
+    #[serde(flatten)]
+    pub target: RpcTarget,
+    pub address: String,
+    #[serde(default)]
+    pub commitment: Option<String>,
+    /// Comma-separated dotted paths of `data` to keep, e.g. "sol.lamports,usdc"
+    #[serde(default)]
+    pub fields: Option<String>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct GetTokenBalanceRequest {
+    #[serde(default)]
+    pub network: String,
+    #[serde(flatten)]
+    pub target: RpcTarget,
+    pub address: String,
+    #[serde(default)]
+    pub commitment: Option<String>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct MintBalanceRequest {
+    #[serde(default)]
+    pub network: String,
+    #[serde(flatten)]
+    pub target: RpcTarget,
+    pub address: String,
+    /// Any SPL or Token-2022 mint address
+    pub mint: String,
+    #[serde(default)]
+    pub commitment: Option<String>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct GetTokensRequest {
+    pub address: String,
+    #[serde(default)]
+    pub network: String,
+    #[serde(flatten)]
+    pub target: RpcTarget,
+    #[serde(default)]
+    pub commitment: Option<String>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TokenBalancesRequest {
+    #[serde(default)]
+    pub network: String,
+    #[serde(flatten)]
+    pub target: RpcTarget,
+    pub address: String,
+    #[serde(default)]
+    pub commitment: Option<String>,
+    /// Also list token accounts holding zero
+    #[serde(default)]
+    pub include_empty: bool,
+    /// Comma-separated dotted paths of `data` to keep, e.g. "balances.mint,balances.ui_amount"
+    #[serde(default)]
+    pub fields: Option<String>,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtraPosition {
+    BeforeMemo,
+    #[default]
+    AfterMemo,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExtraAccount {
+    pub pubkey: String,
+    #[serde(default)]
+    pub signer: bool,
+    #[serde(default)]
+    pub writable: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExtraInstruction {
+    pub program_id: String,
+    #[serde(default)]
+    pub accounts: Vec<ExtraAccount>,
+    /// Instruction data, base64 encoded
+    #[serde(default)]
+    pub data: String,
+    /// Where the instruction goes relative to the memo; always after the main transfer
+    #[serde(default)]
+    pub position: ExtraPosition,
+}
+
+/// What every transfer builder accepts besides the transfer itself, flattened into the request
+/// like `RpcTarget`.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TransferOptions {
+    #[serde(default)]
+    pub notes: Option<String>, // Optional memo notes (max 16 chars)
+    #[serde(default)]
+    pub fee_amount: Option<String>,
+    /// "full" (default) embeds from/to addresses in the memo; "minimal" omits them
+    #[serde(default)]
+    pub memo_privacy: Option<String>,
+    /// build_id from an earlier build of this transfer; the response then diffs against it
+    #[serde(default)]
+    pub previous_build_id: Option<String>,
+    /// Caller-supplied instructions appended after the transfer (before or after the memo)
+    #[serde(default)]
+    pub extra_instructions: Vec<ExtraInstruction>,
+    /// Deposit memo/tag for exchange destinations, sent as its own plain memo instruction
+    #[serde(default)]
+    pub exchange_memo: Option<String>,
+    /// Excess decimal places: "reject" (default) or "truncate"
+    #[serde(default)]
+    pub rounding: Option<String>,
+    /// Build even when the pre-flight check finds the source balance too low
+    #[serde(default)]
+    pub skip_balance_check: bool,
+    /// Refuse instead of returning warnings (nothing is charged or recorded)
+    #[serde(default)]
+    pub treat_warnings_as_errors: bool,
+    /// Fetch a new blockhash instead of reusing one cached in the last few seconds
+    #[serde(default)]
+    pub fresh_blockhash: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TransferSolRequest {
+    #[serde(default)]
+    pub network: String,
+    #[serde(flatten)]
+    pub target: RpcTarget,
+    pub from_address: String,
+    pub to_address: String,
+    /// UI amount, or "max" for the whole balance; may be omitted when amount_raw is given
+    #[serde(default)]
+    pub amount: String, // String to preserve decimals (in SOL)
+    /// Base units (lamports, or 10^-decimals of the token) as an integer string; wins over amount
+    #[serde(default)]
+    pub amount_raw: Option<String>,
+    pub yid: String, // Yield ID for tracking
+    #[serde(flatten)]
+    pub options: TransferOptions,
+    /// With amount "max": leave the rent-exempt minimum behind instead of draining to zero
+    #[serde(default)]
+    pub keep_rent_exempt: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TransferUsdcRequest {
+    #[serde(default)]
+    pub network: String,
+    #[serde(flatten)]
+    pub target: RpcTarget,
+    pub from_address: String,
+    pub to_address: String,
+    /// UI amount, or "max" for the whole balance; may be omitted when amount_raw is given
+    #[serde(default)]
+    pub amount: String, // String to preserve decimals
+    /// Base units (lamports, or 10^-decimals of the token) as an integer string; wins over amount
+    #[serde(default)]
+    pub amount_raw: Option<String>,
+    pub yid: String, // Yield ID for tracking
+    #[serde(flatten)]
+    pub options: TransferOptions,
+    /// Recipient token account: "auto" (default) creates it when missing, "never" or "always"
+    #[serde(default)]
+    pub create_ata: Option<String>,
+    /// With amount "max": also close the emptied source token account, returning its rent to the sender
+    #[serde(default)]
+    pub close_account: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TransferUsdtRequest {
+    #[serde(default)]
+    pub network: String,
+    #[serde(flatten)]
+    pub target: RpcTarget,
+    pub from_address: String,
+    pub to_address: String,
+    /// UI amount, or "max" for the whole balance; may be omitted when amount_raw is given
+    #[serde(default)]
+    pub amount: String, // String to preserve decimals
+    /// Base units (lamports, or 10^-decimals of the token) as an integer string; wins over amount
+    #[serde(default)]
+    pub amount_raw: Option<String>,
+    pub yid: String, // Yield ID for tracking
+    #[serde(flatten)]
+    pub options: TransferOptions,
+    /// Recipient token account: "auto" (default) creates it when missing, "never" or "always"
+    #[serde(default)]
+    pub create_ata: Option<String>,
+    /// With amount "max": also close the emptied source token account, returning its rent to the sender
+    #[serde(default)]
+    pub close_account: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TransferPyusdRequest {
+    #[serde(default)]
+    pub network: String,
+    #[serde(flatten)]
+    pub target: RpcTarget,
+    pub from_address: String,
+    pub to_address: String,
+    /// UI amount, or "max" for the whole balance; may be omitted when amount_raw is given
+    #[serde(default)]
+    pub amount: String, // String to preserve decimals
+    /// Base units (lamports, or 10^-decimals of the token) as an integer string; wins over amount
+    #[serde(default)]
+    pub amount_raw: Option<String>,
+    pub yid: String, // Yield ID for tracking
+    #[serde(flatten)]
+    pub options: TransferOptions,
+    /// Token-2022 transfer fees: send enough that the recipient nets `amount`
+    #[serde(default)]
+    pub gross_up: bool,
+    /// Recipient token account: "auto" (default) creates it when missing, "never" or "always"
+    #[serde(default)]
+    pub create_ata: Option<String>,
+    /// With amount "max": also close the emptied source token account, returning its rent to the sender
+    #[serde(default)]
+    pub close_account: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TransferTokenRequest {
+    #[serde(default)]
+    pub network: String,
+    #[serde(flatten)]
+    pub target: RpcTarget,
+    pub from_address: String,
+    pub to_address: String,
+    /// Any SPL or Token-2022 mint; decimals are read from the mint account
+    pub mint: String,
+    /// UI amount, or "max" for the whole balance; may be omitted when amount_raw is given
+    #[serde(default)]
+    pub amount: String, // String to preserve decimals
+    /// Base units (lamports, or 10^-decimals of the token) as an integer string; wins over amount
+    #[serde(default)]
+    pub amount_raw: Option<String>,
+    pub yid: String, // Yield ID for tracking
+    #[serde(flatten)]
+    pub options: TransferOptions,
+    /// Token-2022 transfer fees: send enough that the recipient nets `amount`
+    #[serde(default)]
+    pub gross_up: bool,
+    /// Recipient token account: "auto" (default) creates it when missing, "never" or "always"
+    #[serde(default)]
+    pub create_ata: Option<String>,
+    /// With amount "max": also close the emptied source token account, returning its rent to the sender
+    #[serde(default)]
+    pub close_account: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SubmitTransactionRequest {
+    #[serde(default)]
+    pub network: String,
+    #[serde(flatten)]
+    pub target: RpcTarget,
+    pub transaction: String, // Base64-encoded signed transaction
+    #[serde(default)]
+    pub commitment: Option<String>,
+    /// Add the local wallet's signature when it is the only one missing
+    #[serde(default)]
+    pub sign_and_submit: bool,
+    /// Wait until the transaction has this many confirmations (or is finalized) before responding
+    #[serde(default)]
+    pub min_confirmations: Option<u64>,
+    /// How long to wait for min_confirmations, in seconds (default 60, max 300)
+    #[serde(default)]
+    pub confirm_timeout_secs: Option<u64>,
+    /// Refuse instead of returning warnings (nothing is charged or recorded)
+    #[serde(default)]
+    pub treat_warnings_as_errors: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TransferQuoteRequest {
+    #[serde(default)]
+    pub network: String,
+    #[serde(flatten)]
+    pub target: RpcTarget,
+    pub mint: String,
+    /// UI amount being sent; the quote reports the fee and what arrives
+    #[serde(default)]
+    pub amount: Option<String>,
+    /// UI amount that must arrive; the quote reports what to send
+    #[serde(default)]
+    pub net_amount: Option<String>,
+}